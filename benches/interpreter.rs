@@ -0,0 +1,72 @@
+//! Interpreter benchmarks: numeric programs that stay in one class, and object-heavy ones that allocate,
+//! dispatch virtual calls and use the collections of `java.base`.
+//!
+//! The programs in `benches/programs` are compiled with `javac` from the `PATH` and run on a booted VM.
+//! Like the tests they need a JDK 17, see `tests/common`, and fail without one.
+//!
+//! Compare against a baseline with `cargo bench -- --save-baseline before` and `cargo bench -- --baseline before`.
+
+use std::time::Duration;
+
+use criterion::{BenchmarkId, Criterion};
+use java_vm::{JValue, VirtualMachine};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const PROGRAMS: [&str; 2] = ["benches/programs/Numeric.java", "benches/programs/Objects.java"];
+
+/// A static method of a benchmark program and the argument it's called with
+struct Workload {
+    method: &'static str,
+    descriptor: &'static str,
+    argument: i32
+}
+
+const NUMERIC: [Workload; 4] = [
+    Workload { method: "fib", descriptor: "(I)I", argument: 20 },
+    Workload { method: "sieve", descriptor: "(I)I", argument: 50_000 },
+    Workload { method: "matrix", descriptor: "(I)D", argument: 40 },
+    Workload { method: "hash", descriptor: "(I)J", argument: 50_000 }
+];
+
+const OBJECTS: [Workload; 7] = [
+    Workload { method: "linkedList", descriptor: "(I)I", argument: 20_000 },
+    Workload { method: "shapes", descriptor: "(I)D", argument: 2_000 },
+    Workload { method: "deepHierarchy", descriptor: "(I)I", argument: 20_000 },
+    Workload { method: "megamorphic", descriptor: "(I)I", argument: 20_000 },
+    Workload { method: "sortList", descriptor: "(I)I", argument: 5_000 },
+    Workload { method: "wordCount", descriptor: "(I)I", argument: 5_000 },
+    Workload { method: "buildString", descriptor: "(I)I", argument: 20_000 }
+];
+
+fn bench_program(criterion: &mut Criterion, vm: &mut VirtualMachine, program: &str, workloads: &[Workload]) {
+    let class = vm.load_class(program).expect("Benchmark class can't be loaded");
+
+    let mut group = criterion.benchmark_group(program.to_lowercase());
+    group.sample_size(20).measurement_time(Duration::from_secs(5));
+
+    for workload in workloads {
+        // Runs once first, so the classes it uses are loaded and initialized before measuring
+        let args = [JValue::Int(workload.argument)];
+        vm.invoke_static(class, workload.method, workload.descriptor, &args).expect("Benchmark failed");
+
+        group.bench_with_input(BenchmarkId::new(workload.method, workload.argument), &args, |bencher, args| {
+            bencher.iter(|| vm.invoke_static(class, workload.method, workload.descriptor, args).expect("Benchmark failed"))
+        });
+    }
+
+    group.finish();
+}
+
+fn main() {
+    let classes = common::compile_programs("bench-classes", &PROGRAMS);
+
+    let mut criterion = Criterion::default().configure_from_args();
+    let mut vm = common::booted_vm(&classes);
+
+    bench_program(&mut criterion, &mut vm, "Numeric", &NUMERIC);
+    bench_program(&mut criterion, &mut vm, "Objects", &OBJECTS);
+
+    criterion.final_summary();
+}
\ No newline at end of file
@@ -0,0 +1,61 @@
+/** Arithmetic, loops, arrays and static calls, without allocating objects in the hot loops */
+public class Numeric {
+
+    public static int fib(int n) {
+        return n < 2 ? n : fib(n - 1) + fib(n - 2);
+    }
+
+    public static int sieve(int limit) {
+        boolean[] composite = new boolean[limit + 1];
+        int count = 0;
+
+        for (int i = 2; i <= limit; i++) {
+            if (!composite[i]) {
+                count++;
+                for (int j = i * 2; j <= limit; j += i) {
+                    composite[j] = true;
+                }
+            }
+        }
+
+        return count;
+    }
+
+    public static double matrix(int size) {
+        double[][] a = new double[size][size];
+        double[][] b = new double[size][size];
+        double[][] c = new double[size][size];
+
+        for (int i = 0; i < size; i++) {
+            for (int j = 0; j < size; j++) {
+                a[i][j] = i + j;
+                b[i][j] = i - j;
+            }
+        }
+
+        for (int i = 0; i < size; i++) {
+            for (int j = 0; j < size; j++) {
+                double sum = 0;
+                for (int k = 0; k < size; k++) {
+                    sum += a[i][k] * b[k][j];
+                }
+                c[i][j] = sum;
+            }
+        }
+
+        return c[size - 1][size - 1];
+    }
+
+    public static long hash(int rounds) {
+        long hash = 0xcbf29ce484222325L;
+
+        for (int i = 0; i < rounds; i++) {
+            hash ^= i & 0xff;
+            hash *= 0x100000001b3L;
+            hash = (hash << 7) | (hash >>> 57);
+        }
+
+        return hash;
+    }
+
+}
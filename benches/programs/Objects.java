@@ -0,0 +1,166 @@
+import java.util.ArrayList;
+import java.util.Collections;
+import java.util.HashMap;
+import java.util.List;
+import java.util.Map;
+
+/** Allocation, field access, virtual and interface calls, and the collections of java.base */
+public class Objects {
+
+    interface Shape {
+        double area();
+    }
+
+    static final class Square implements Shape {
+        private final double side;
+
+        Square(double side) { this.side = side; }
+
+        public double area() { return side * side; }
+    }
+
+    static final class Circle implements Shape {
+        private final double radius;
+
+        Circle(double radius) { this.radius = radius; }
+
+        public double area() { return 3.14159 * radius * radius; }
+    }
+
+    static class Rectangle implements Shape {
+        protected final double width;
+        protected final double height;
+
+        Rectangle(double width, double height) { this.width = width; this.height = height; }
+
+        public double area() { return width * height; }
+    }
+
+    static final class Triangle extends Rectangle {
+        Triangle(double width, double height) { super(width, height); }
+
+        @Override
+        public double area() { return super.area() / 2; }
+    }
+
+    /** A method inherited down a deep hierarchy, and one overridden at every level */
+    static class Level0 {
+        int weight() { return 1; }
+        int level() { return 0; }
+    }
+    static class Level1 extends Level0 { int level() { return 1; } }
+    static class Level2 extends Level1 { int level() { return 2; } }
+    static class Level3 extends Level2 { int level() { return 3; } }
+    static class Level4 extends Level3 { int level() { return 4; } }
+    static class Level5 extends Level4 { int level() { return 5; } }
+    static class Level6 extends Level5 { int level() { return 6; } }
+    static class Level7 extends Level6 { int level() { return 7; } }
+
+    interface Visitor {
+        int visit(int value);
+    }
+
+    static final class Increment implements Visitor { public int visit(int value) { return value + 1; } }
+    static final class Decrement implements Visitor { public int visit(int value) { return value - 1; } }
+    static final class Twice implements Visitor { public int visit(int value) { return value * 2; } }
+    static final class Halve implements Visitor { public int visit(int value) { return value / 2; } }
+    static final class Negate implements Visitor { public int visit(int value) { return -value; } }
+    static final class SquareMod implements Visitor { public int visit(int value) { return value * value % 1000; } }
+    static final class Keep implements Visitor { public int visit(int value) { return value; } }
+    static final class Zero implements Visitor { public int visit(int value) { return 0; } }
+
+    static final class Node {
+        final int value;
+        final Node next;
+
+        Node(int value, Node next) { this.value = value; this.next = next; }
+    }
+
+    public static int linkedList(int length) {
+        Node head = null;
+        for (int i = 0; i < length; i++) {
+            head = new Node(i, head);
+        }
+
+        int sum = 0;
+        for (Node node = head; node != null; node = node.next) {
+            sum += node.value;
+        }
+
+        return sum;
+    }
+
+    public static double shapes(int count) {
+        Shape[] shapes = new Shape[count];
+        for (int i = 0; i < count; i++) {
+            switch (i % 4) {
+                case 0: shapes[i] = new Square(i); break;
+                case 1: shapes[i] = new Circle(i); break;
+                case 2: shapes[i] = new Rectangle(i, 2); break;
+                default: shapes[i] = new Triangle(i, 3); break;
+            }
+        }
+
+        double total = 0;
+        for (int round = 0; round < 10; round++) {
+            for (Shape shape : shapes) {
+                total += shape.area();
+            }
+        }
+
+        return total;
+    }
+
+    public static int deepHierarchy(int calls) {
+        Level0 object = new Level7();
+
+        int sum = 0;
+        for (int i = 0; i < calls; i++) {
+            sum += object.weight() + object.level();
+        }
+
+        return sum;
+    }
+
+    public static int megamorphic(int calls) {
+        Visitor[] visitors = { new Increment(), new Decrement(), new Twice(), new Halve(), new Negate(), new SquareMod(), new Keep(), new Zero() };
+
+        int value = 1;
+        for (int i = 0; i < calls; i++) {
+            value = visitors[i % visitors.length].visit(value);
+        }
+
+        return value;
+    }
+
+    public static int sortList(int size) {
+        List<Integer> list = new ArrayList<>();
+        for (int i = 0; i < size; i++) {
+            list.add((i * 7919) % size);
+        }
+
+        Collections.sort(list);
+
+        return list.get(size / 2);
+    }
+
+    public static int wordCount(int words) {
+        Map<String, Integer> counts = new HashMap<>();
+        for (int i = 0; i < words; i++) {
+            String word = "word" + (i % 100);
+            counts.merge(word, 1, Integer::sum);
+        }
+
+        return counts.get("word42");
+    }
+
+    public static int buildString(int length) {
+        StringBuilder builder = new StringBuilder();
+        for (int i = 0; i < length; i++) {
+            builder.append((char) ('a' + i % 26));
+        }
+
+        return builder.toString().indexOf("xyz");
+    }
+
+}
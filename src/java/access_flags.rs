@@ -0,0 +1,87 @@
+#![allow(dead_code)]
+
+pub const ACC_PUBLIC: u16       = 0x0001;
+pub const ACC_PRIVATE: u16      = 0x0002;
+pub const ACC_PROTECTED: u16    = 0x0004;
+pub const ACC_STATIC: u16       = 0x0008;
+pub const ACC_FINAL: u16        = 0x0010;
+pub const ACC_SUPER: u16        = 0x0020;
+pub const ACC_SYNCHRONIZED: u16 = 0x0020;
+pub const ACC_VOLATILE: u16     = 0x0040;
+pub const ACC_BRIDGE: u16       = 0x0040;
+pub const ACC_TRANSIENT: u16    = 0x0080;
+pub const ACC_VARARGS: u16      = 0x0080;
+pub const ACC_NATIVE: u16       = 0x0100;
+pub const ACC_INTERFACE: u16    = 0x0200;
+pub const ACC_ABSTRACT: u16     = 0x0400;
+pub const ACC_STRICT: u16       = 0x0800;
+pub const ACC_SYNTHETIC: u16    = 0x1000;
+pub const ACC_ANNOTATION: u16   = 0x2000;
+pub const ACC_ENUM: u16         = 0x4000;
+pub const ACC_MODULE: u16       = 0x8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagTarget {
+    Class,
+    Field,
+    Method
+}
+
+const CLASS_FLAGS: [(u16, &str); 9] = [
+    (ACC_PUBLIC, "ACC_PUBLIC"), (ACC_FINAL, "ACC_FINAL"), (ACC_SUPER, "ACC_SUPER"),
+    (ACC_INTERFACE, "ACC_INTERFACE"), (ACC_ABSTRACT, "ACC_ABSTRACT"), (ACC_SYNTHETIC, "ACC_SYNTHETIC"),
+    (ACC_ANNOTATION, "ACC_ANNOTATION"), (ACC_ENUM, "ACC_ENUM"), (ACC_MODULE, "ACC_MODULE")
+];
+
+const FIELD_FLAGS: [(u16, &str); 9] = [
+    (ACC_PUBLIC, "ACC_PUBLIC"), (ACC_PRIVATE, "ACC_PRIVATE"), (ACC_PROTECTED, "ACC_PROTECTED"),
+    (ACC_STATIC, "ACC_STATIC"), (ACC_FINAL, "ACC_FINAL"), (ACC_VOLATILE, "ACC_VOLATILE"),
+    (ACC_TRANSIENT, "ACC_TRANSIENT"), (ACC_SYNTHETIC, "ACC_SYNTHETIC"), (ACC_ENUM, "ACC_ENUM")
+];
+
+const METHOD_FLAGS: [(u16, &str); 12] = [
+    (ACC_PUBLIC, "ACC_PUBLIC"), (ACC_PRIVATE, "ACC_PRIVATE"), (ACC_PROTECTED, "ACC_PROTECTED"),
+    (ACC_STATIC, "ACC_STATIC"), (ACC_FINAL, "ACC_FINAL"), (ACC_SYNCHRONIZED, "ACC_SYNCHRONIZED"),
+    (ACC_BRIDGE, "ACC_BRIDGE"), (ACC_VARARGS, "ACC_VARARGS"), (ACC_NATIVE, "ACC_NATIVE"),
+    (ACC_ABSTRACT, "ACC_ABSTRACT"), (ACC_STRICT, "ACC_STRICT"), (ACC_SYNTHETIC, "ACC_SYNTHETIC")
+];
+
+/// Names of all flags set in `access_flags`, e.g. `["ACC_PUBLIC", "ACC_STATIC"]`
+pub fn flag_names(access_flags: u16, target: FlagTarget) -> Vec<&'static str> {
+    let flags: &[(u16, &str)] = match target {
+        FlagTarget::Class   => &CLASS_FLAGS,
+        FlagTarget::Field   => &FIELD_FLAGS,
+        FlagTarget::Method  => &METHOD_FLAGS
+    };
+
+    flags.iter().filter(|(flag, _)| access_flags & flag != 0).map(|(_, name)| *name).collect()
+}
+
+/// Java source modifiers for `access_flags`, e.g. `"public static"`
+pub fn modifiers(access_flags: u16, target: FlagTarget) -> String {
+    let mut result = vec![];
+
+    if access_flags & ACC_PUBLIC != 0 { result.push("public"); }
+    if access_flags & ACC_PRIVATE != 0 && target != FlagTarget::Class { result.push("private"); }
+    if access_flags & ACC_PROTECTED != 0 && target != FlagTarget::Class { result.push("protected"); }
+    if access_flags & ACC_STATIC != 0 && target != FlagTarget::Class { result.push("static"); }
+    if access_flags & ACC_FINAL != 0 { result.push("final"); }
+
+    match target {
+        FlagTarget::Class   => {
+            if access_flags & ACC_ABSTRACT != 0 && access_flags & ACC_INTERFACE == 0 { result.push("abstract"); }
+        },
+        FlagTarget::Field   => {
+            if access_flags & ACC_VOLATILE != 0 { result.push("volatile"); }
+            if access_flags & ACC_TRANSIENT != 0 { result.push("transient"); }
+        },
+        FlagTarget::Method  => {
+            if access_flags & ACC_SYNCHRONIZED != 0 { result.push("synchronized"); }
+            if access_flags & ACC_NATIVE != 0 { result.push("native"); }
+            if access_flags & ACC_ABSTRACT != 0 { result.push("abstract"); }
+            if access_flags & ACC_STRICT != 0 { result.push("strictfp"); }
+        }
+    }
+
+    result.join(" ")
+}
\ No newline at end of file
@@ -0,0 +1,310 @@
+#![allow(dead_code)]
+
+use crate::java::attribute::{Annotation, ElementValue};
+use crate::java::class::ConstantPoolEntry;
+use crate::java::descriptor::FieldType;
+use crate::java::native::NativeRegistry;
+use crate::java::reflection::declared_methods;
+use crate::java::string::decode_modified_utf8;
+use crate::java::vm::{Throwable, Value, VmContext};
+use crate::java::{Attribute, AttributeInfo, ClassFile};
+
+const CONSTANT_POOL: &str = "jdk/internal/reflect/ConstantPool";
+
+/// The value of an annotation element with its constant pool references resolved
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationValue {
+    Byte(i8),
+    Char(u16),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Boolean(bool),
+    String(String),
+    /// An enum constant, the enum class is an internal name, e.g. `java/lang/annotation/RetentionPolicy`
+    Enum { class_name: String, constant: String },
+    /// A class literal by its return descriptor, e.g. `Ljava/lang/String;`, `I` or `V` for `void.class`
+    Class(String),
+    Annotation(ResolvedAnnotation),
+    Array(Vec<AnnotationValue>)
+}
+
+/// An annotation with its constant pool references resolved
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedAnnotation {
+    /// Internal name of the annotation interface, e.g. `java/lang/Deprecated`
+    pub type_name: String,
+    /// Elements in the order they were written, without the default values of the left out ones,
+    /// see `VmContext::apply_annotation_defaults`
+    pub elements: Vec<(String, AnnotationValue)>
+}
+
+/// A CONSTANT_Utf8 entry decoded from modified UTF-8
+fn utf8(class_file: &ClassFile, index: u16) -> Option<String> {
+    match class_file.constant_pool.get((index as usize).checked_sub(1)?)? {
+        ConstantPoolEntry::String { length: _, string } => Some(String::from_utf16_lossy(&decode_modified_utf8(string))),
+        _ => None
+    }
+}
+
+/// Internal class name of a field descriptor naming a class, e.g. `java/lang/Deprecated` for `Ljava/lang/Deprecated;`
+fn descriptor_class_name(descriptor: &str) -> String {
+    match FieldType::parse(descriptor) {
+        Some(FieldType::Object(class_name)) => class_name,
+        _ => descriptor.to_string()
+    }
+}
+
+impl AnnotationValue {
+
+    pub fn new(class_file: &ClassFile, element_value: &ElementValue) -> Option<Self> {
+        let constant = |index: u16| class_file.constant_pool.get((index as usize).checked_sub(1)?);
+        let int = |index: u16| match constant(index)? {
+            ConstantPoolEntry::Integer(value) => Some(*value as i32),
+            _ => None
+        };
+
+        Some(match element_value {
+            ElementValue::Byte { const_value_index }    => AnnotationValue::Byte(int(*const_value_index)? as i8),
+            ElementValue::Char { const_value_index }    => AnnotationValue::Char(int(*const_value_index)? as u16),
+            ElementValue::Short { const_value_index }   => AnnotationValue::Short(int(*const_value_index)? as i16),
+            ElementValue::Int { const_value_index }     => AnnotationValue::Int(int(*const_value_index)?),
+            ElementValue::Boolean { const_value_index } => AnnotationValue::Boolean(int(*const_value_index)? != 0),
+            ElementValue::Long { const_value_index }    => match constant(*const_value_index)? {
+                ConstantPoolEntry::Long(high, low) => AnnotationValue::Long((((*high as u64) << 32) | *low as u64) as i64),
+                _ => return None
+            },
+            ElementValue::Float { const_value_index }   => match constant(*const_value_index)? {
+                ConstantPoolEntry::Float(bits) => AnnotationValue::Float(f32::from_bits(*bits)),
+                _ => return None
+            },
+            ElementValue::Double { const_value_index }  => match constant(*const_value_index)? {
+                ConstantPoolEntry::Double(high, low) => AnnotationValue::Double(f64::from_bits(((*high as u64) << 32) | *low as u64)),
+                _ => return None
+            },
+            ElementValue::String { const_value_index }  => AnnotationValue::String(utf8(class_file, *const_value_index)?),
+            ElementValue::Enum { type_name_index, const_name_index } => AnnotationValue::Enum {
+                class_name: descriptor_class_name(&utf8(class_file, *type_name_index)?),
+                constant: utf8(class_file, *const_name_index)?
+            },
+            ElementValue::Class { class_info_index }    => AnnotationValue::Class(utf8(class_file, *class_info_index)?),
+            ElementValue::AnnotationType { annotation_value } => AnnotationValue::Annotation(ResolvedAnnotation::new(class_file, annotation_value)?),
+            ElementValue::Array { element_value, .. }   => AnnotationValue::Array(element_value.iter()
+                .map(|value| AnnotationValue::new(class_file, value))
+                .collect::<Option<Vec<_>>>()?)
+        })
+    }
+
+}
+
+impl ResolvedAnnotation {
+
+    pub fn new(class_file: &ClassFile, annotation: &Annotation) -> Option<Self> {
+        let elements = annotation.element_value_pairs.iter()
+            .map(|pair| Some((utf8(class_file, pair.element_name_index)?, AnnotationValue::new(class_file, &pair.value)?)))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(ResolvedAnnotation {
+            type_name: descriptor_class_name(&utf8(class_file, annotation.type_index)?),
+            elements
+        })
+    }
+
+    pub fn element(&self, name: &str) -> Option<&AnnotationValue> {
+        self.elements.iter().find(|(element_name, _)| element_name == name).map(|(_, value)| value)
+    }
+
+    /// Name of the annotation interface as written in Java source, e.g. `java.lang.Deprecated`
+    pub fn java_name(&self) -> String {
+        self.type_name.replace('/', ".")
+    }
+
+}
+
+/// The runtime visible annotations of a class, field or method, annotations that can't be resolved are left out
+pub fn runtime_visible_annotations(class_file: &ClassFile, attributes: &[Attribute]) -> Vec<ResolvedAnnotation> {
+    attributes.iter()
+        .filter_map(|attribute| match attribute {
+            Attribute::RuntimeVisibleAnnotations(attribute) => Some(&attribute.annotations),
+            _ => None
+        })
+        .flatten()
+        .filter_map(|annotation| ResolvedAnnotation::new(class_file, annotation))
+        .collect()
+}
+
+/// The default value of an element of an annotation interface
+pub fn annotation_default(class_file: &ClassFile, attributes: &[Attribute]) -> Option<AnnotationValue> {
+    attributes.iter().find_map(|attribute| match attribute {
+        Attribute::AnnotationDefault(attribute) => AnnotationValue::new(class_file, &attribute.default_value),
+        _ => None
+    })
+}
+
+/// Bytes of an attribute by its name, which is how `java.lang.reflect` receives annotations to parse them itself
+pub fn raw_attribute<'a>(class_file: &ClassFile, attributes: &'a [AttributeInfo], name: &str) -> Option<&'a [u8]> {
+    attributes.iter()
+        .find(|attribute_info| class_file.get_constant_pool_string(attribute_info.attribute_name_index as usize).as_deref() == Some(name))
+        .map(|attribute_info| attribute_info.info.as_slice())
+}
+
+impl VmContext {
+
+    /// Adds the default values of the elements an annotation leaves out, as declared by the AnnotationDefault attributes
+    /// of its interface, also to nested annotations. Annotations whose interface can't be loaded are returned as they are.
+    pub fn apply_annotation_defaults(&mut self, annotation: &ResolvedAnnotation) -> ResolvedAnnotation {
+        let mut elements: Vec<(String, AnnotationValue)> = annotation.elements.iter()
+            .map(|(name, value)| (name.clone(), self.apply_element_defaults(value)))
+            .collect();
+
+        let annotation_type = self.load_class(&annotation.type_name).ok().and_then(|class_id| self.class(class_id).class.clone());
+        if let Some(annotation_type) = annotation_type {
+            for (_, method) in declared_methods(&annotation_type) {
+                if let Some(default) = method.annotation_default().filter(|_| annotation.element(&method.name).is_none()) {
+                    let default = self.apply_element_defaults(default);
+                    elements.push((method.name.clone(), default));
+                }
+            }
+        }
+
+        ResolvedAnnotation {
+            type_name: annotation.type_name.clone(),
+            elements
+        }
+    }
+
+    fn apply_element_defaults(&mut self, value: &AnnotationValue) -> AnnotationValue {
+        match value {
+            AnnotationValue::Annotation(annotation) => AnnotationValue::Annotation(self.apply_annotation_defaults(annotation)),
+            AnnotationValue::Array(values) => AnnotationValue::Array(values.iter().map(|value| self.apply_element_defaults(value)).collect()),
+            value => value.clone()
+        }
+    }
+
+    /// A `byte[]` with the contents of an attribute, null without one
+    pub fn raw_attribute_array(&mut self, attribute: Option<&[u8]>) -> Result<Value, Throwable> {
+        match attribute {
+            Some(bytes) => self.new_byte_array(bytes),
+            None => Ok(Value::null())
+        }
+    }
+
+    /// Entry of the constant pool a `jdk.internal.reflect.ConstantPool` stands for, which is the one of the class of its mirror
+    fn reflected_constant(&self, mirror: Value, index: i32) -> Result<(std::sync::Arc<crate::java::Class>, usize), Throwable> {
+        let class = self.mirror_class(mirror)
+            .and_then(|class_id| self.class(class_id).class.clone())
+            .ok_or_else(|| Throwable::new("java/lang/IllegalArgumentException", "Constant pool of a class without a class file"))?;
+
+        match usize::try_from(index).ok().filter(|index| *index >= 1 && *index <= class.class_file.constant_pool.len()) {
+            Some(index) => Ok((class, index)),
+            None => Err(Throwable::new("java/lang/IllegalArgumentException", "Constant pool index out of bounds"))
+        }
+    }
+
+}
+
+fn wrong_constant_type() -> Throwable {
+    Throwable::new("java/lang/IllegalArgumentException", "Wrong type at constant pool index")
+}
+
+fn class_get_raw_annotations(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class = context.mirror_class(args[0]).and_then(|class_id| context.class(class_id).class.clone());
+
+    let attribute = class.as_ref().and_then(|class| raw_attribute(&class.class_file, &class.class_file.attribute_table, "RuntimeVisibleAnnotations"));
+
+    Ok(Some(context.raw_attribute_array(attribute)?))
+}
+
+/// The constant pool annotations of a class refer to, backed by the class file of the mirror
+fn class_get_constant_pool(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let constant_pool = context.construct(CONSTANT_POOL, "()V", &[])?;
+    context.set_field(constant_pool, CONSTANT_POOL, "constantPoolOop", args[0])?;
+
+    Ok(Some(constant_pool))
+}
+
+fn constant_pool_get_size(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let size = context.mirror_class(args[1])
+        .and_then(|class_id| context.class(class_id).class.as_ref())
+        .map(|class| class.class_file.constant_pool.len() + 1)
+        .unwrap_or(0);
+
+    Ok(Some(Value::from_int(size as i32)))
+}
+
+fn constant_pool_get_tag(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (class, index) = context.reflected_constant(args[1], args[2].as_int())?;
+
+    let tag = match &class.class_file.constant_pool[index - 1] {
+        ConstantPoolEntry::None()                           => 0,
+        ConstantPoolEntry::String { .. }                    => 1,
+        ConstantPoolEntry::Integer(_)                       => 3,
+        ConstantPoolEntry::Float(_)                         => 4,
+        ConstantPoolEntry::Long(_, _)                       => 5,
+        ConstantPoolEntry::Double(_, _)                     => 6,
+        ConstantPoolEntry::ClassReference(_)                => 7,
+        ConstantPoolEntry::StringReference(_)               => 8,
+        ConstantPoolEntry::FieldReference(_, _)             => 9,
+        ConstantPoolEntry::MethodReference(_, _)            => 10,
+        ConstantPoolEntry::InterfaceMethodReference(_, _)   => 11,
+        ConstantPoolEntry::NameAndTypeDescriptor(_, _)      => 12,
+        ConstantPoolEntry::MethodHandle(_, _)               => 15,
+        ConstantPoolEntry::MethodType(_)                    => 16,
+        ConstantPoolEntry::Dynamic(_, _)                    => 17,
+        ConstantPoolEntry::InvokeDynamic(_, _)              => 18,
+        ConstantPoolEntry::Module(_)                        => 19,
+        ConstantPoolEntry::Package(_)                       => 20
+    };
+
+    Ok(Some(Value::from_int(tag)))
+}
+
+/// `int`, `long`, `float` and `double` constants
+fn constant_pool_get_number(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (class, index) = context.reflected_constant(args[1], args[2].as_int())?;
+
+    Ok(Some(context.constant_value(&class, index as u16).ok_or_else(wrong_constant_type)?))
+}
+
+fn constant_pool_get_utf8(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (class, index) = context.reflected_constant(args[1], args[2].as_int())?;
+
+    match &class.class_file.constant_pool[index - 1] {
+        ConstantPoolEntry::String { length: _, string } => Ok(Some(context.constant_string(string)?)),
+        _ => Err(wrong_constant_type())
+    }
+}
+
+fn constant_pool_get_string(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (class, index) = context.reflected_constant(args[1], args[2].as_int())?;
+
+    Ok(Some(context.constant_pool_string(&class, index as u16)?.ok_or_else(wrong_constant_type)?))
+}
+
+fn constant_pool_get_class(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (class, index) = context.reflected_constant(args[1], args[2].as_int())?;
+    let class_name = class.class_file.get_class_name(index).ok_or_else(wrong_constant_type)?;
+
+    let class_id = context.load_class(&class_name)?;
+
+    Ok(Some(context.class_mirror(class_id)?))
+}
+
+/// Raw annotations for `java.lang.reflect` to parse and the constant pool they refer to.
+/// The annotation objects are proxies created by the Java side, see `ClassLoader.defineClass1`.
+pub fn register_annotation_natives(registry: &mut NativeRegistry) {
+    registry.register("java/lang/Class", "getRawAnnotations", "()[B", class_get_raw_annotations);
+    registry.register("java/lang/Class", "getConstantPool", "()Ljdk/internal/reflect/ConstantPool;", class_get_constant_pool);
+
+    registry.register(CONSTANT_POOL, "getSize0", "(Ljava/lang/Object;)I", constant_pool_get_size);
+    registry.register(CONSTANT_POOL, "getTagAt0", "(Ljava/lang/Object;I)B", constant_pool_get_tag);
+    registry.register(CONSTANT_POOL, "getIntAt0", "(Ljava/lang/Object;I)I", constant_pool_get_number);
+    registry.register(CONSTANT_POOL, "getLongAt0", "(Ljava/lang/Object;I)J", constant_pool_get_number);
+    registry.register(CONSTANT_POOL, "getFloatAt0", "(Ljava/lang/Object;I)F", constant_pool_get_number);
+    registry.register(CONSTANT_POOL, "getDoubleAt0", "(Ljava/lang/Object;I)D", constant_pool_get_number);
+    registry.register(CONSTANT_POOL, "getUTF8At0", "(Ljava/lang/Object;I)Ljava/lang/String;", constant_pool_get_utf8);
+    registry.register(CONSTANT_POOL, "getStringAt0", "(Ljava/lang/Object;I)Ljava/lang/String;", constant_pool_get_string);
+    registry.register(CONSTANT_POOL, "getClassAt0", "(Ljava/lang/Object;I)Ljava/lang/Class;", constant_pool_get_class);
+}
\ No newline at end of file
@@ -2,19 +2,19 @@
 
 use std::io::Cursor;
 use binrw::binrw;
-use binrw::BinRead;
+use binrw::{BinRead, BinResult, BinWrite};
 use crate::java::ClassFile;
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct AttributeConstantValue {
     pub constantvalue_index: u16
 }
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct ExceptionTable {
     pub start_pc: u16,
     pub end_pc: u16,
@@ -24,7 +24,7 @@ pub struct ExceptionTable {
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct AttributeCode {
     pub max_stack: u16,
     pub max_locals: u16,
@@ -44,7 +44,7 @@ pub struct AttributeCode {
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct AttributeException {
     pub number_of_exceptions: u16,
 
@@ -53,59 +53,59 @@ pub struct AttributeException {
 }
 
 #[binrw]
-#[br(big)]
+#[brw(big)]
 #[derive(Debug)]
 pub enum ElementValue {
-    #[br(magic('B'))]
+    #[brw(magic(b'B'))]
     Byte {
         const_value_index: u16
     },
-    #[br(magic('C'))]
+    #[brw(magic(b'C'))]
     Char {
         const_value_index: u16
     },
-    #[br(magic('D'))]
+    #[brw(magic(b'D'))]
     Double {
         const_value_index: u16
     },
-    #[br(magic('F'))]
+    #[brw(magic(b'F'))]
     Float {
         const_value_index: u16
     },
-    #[br(magic('I'))]
+    #[brw(magic(b'I'))]
     Int {
         const_value_index: u16
     },
-    #[br(magic('J'))]
+    #[brw(magic(b'J'))]
     Long {
         const_value_index: u16
     },
-    #[br(magic('S'))]
+    #[brw(magic(b'S'))]
     Short {
         const_value_index: u16
     },
-    #[br(magic('Z'))]
+    #[brw(magic(b'Z'))]
     Boolean {
         const_value_index: u16
     },
-    #[br(magic('s'))]
+    #[brw(magic(b's'))]
     String {
         const_value_index: u16
     },
-    #[br(magic('e'))]
+    #[brw(magic(b'e'))]
     Enum {
         type_name_index: u16,
         const_name_index: u16
     },
-    #[br(magic('c'))]
+    #[brw(magic(b'c'))]
     Class {
         class_info_index: u16
     },
-    #[br(magic('@'))]
+    #[brw(magic(b'@'))]
     AnnotationType {
         annotation_value: Annotation
     },
-    #[br(magic('['))]
+    #[brw(magic(b'['))]
     Array {
         num_values: u16,
 
@@ -115,7 +115,7 @@ pub enum ElementValue {
 }
 
 #[binrw]
-#[br(big)]
+#[brw(big)]
 #[derive(Debug)]
 pub struct ElementValuePair {
     pub element_name_index: u16,
@@ -124,7 +124,7 @@ pub struct ElementValuePair {
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct Annotation {
     pub type_index: u16,
     pub num_element_value_pairs: u16,
@@ -135,7 +135,7 @@ pub struct Annotation {
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct AttributeRuntimeVisibleAnnotations {
     pub num_annotations: u16,
 
@@ -145,7 +145,7 @@ pub struct AttributeRuntimeVisibleAnnotations {
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct AttributeRuntimeInvisibleAnnotations {
     pub num_annotations: u16,
 
@@ -155,28 +155,28 @@ pub struct AttributeRuntimeInvisibleAnnotations {
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct AttributeSignature {
     pub signature_index: u16
 }
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct AttributeDeprecated {
 
 }
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct AttributeAnnotationDefault {
     pub default_value: ElementValue
 }
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct MethodParameter {
     pub name_index: u16,
     pub access_flags: u16
@@ -184,7 +184,7 @@ pub struct MethodParameter {
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct AttributeMethodParameters {
     pub parameters_count: u8,
 
@@ -194,7 +194,7 @@ pub struct AttributeMethodParameters {
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct LineNumber {
     pub start_pc: u16,
     pub line_number: u16
@@ -202,12 +202,177 @@ pub struct LineNumber {
 
 #[binrw]
 #[derive(Debug)]
-#[br(big)]
+#[brw(big)]
 pub struct AttributeLineNumberTable {
     pub line_number_table_length: u16,
 
     #[br(count = line_number_table_length)]
-    pub parameters: Vec<LineNumber>
+    pub line_number_table: Vec<LineNumber>
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big)]
+pub struct BootstrapMethod {
+    pub bootstrap_method_ref: u16,
+    pub num_bootstrap_arguments: u16,
+
+    #[br(count = num_bootstrap_arguments)]
+    pub bootstrap_arguments: Vec<u16>
+}
+
+#[binrw]
+#[derive(Debug)]
+#[brw(big)]
+pub struct AttributeBootstrapMethods {
+    pub num_bootstrap_methods: u16,
+
+    #[br(count = num_bootstrap_methods)]
+    pub bootstrap_methods: Vec<BootstrapMethod>
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+#[brw(big)]
+pub struct InnerClass {
+    pub inner_class_info_index: u16,
+    /// `0` for top-level classes and for local and anonymous classes
+    pub outer_class_info_index: u16,
+    /// `0` for anonymous classes
+    pub inner_name_index: u16,
+    pub inner_class_access_flags: u16
+}
+
+#[binrw]
+#[derive(Debug)]
+#[brw(big)]
+pub struct AttributeInnerClasses {
+    pub number_of_classes: u16,
+
+    #[br(count = number_of_classes)]
+    pub classes: Vec<InnerClass>
+}
+
+#[binrw]
+#[derive(Debug)]
+#[brw(big)]
+pub struct AttributeEnclosingMethod {
+    pub class_index: u16,
+    /// `0` if the class isn't enclosed by a method, e.g. in an initializer
+    pub method_index: u16
+}
+
+#[binrw]
+#[derive(Debug)]
+#[brw(big)]
+pub struct AttributeSourceFile {
+    pub sourcefile_index: u16
+}
+
+#[binrw]
+#[derive(Debug)]
+#[brw(big)]
+pub struct AttributeNestHost {
+    pub host_class_index: u16
+}
+
+#[binrw]
+#[derive(Debug)]
+#[brw(big)]
+pub struct AttributeNestMembers {
+    pub number_of_classes: u16,
+
+    #[br(count = number_of_classes)]
+    pub classes: Vec<u16>
+}
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[brw(big)]
+pub enum VerificationTypeInfo {
+    #[brw(magic(0u8))]
+    Top(),
+    #[brw(magic(1u8))]
+    Integer(),
+    #[brw(magic(2u8))]
+    Float(),
+    #[brw(magic(3u8))]
+    Double(),
+    #[brw(magic(4u8))]
+    Long(),
+    #[brw(magic(5u8))]
+    Null(),
+    #[brw(magic(6u8))]
+    UninitializedThis(),
+    #[brw(magic(7u8))]
+    Object(u16),
+    #[brw(magic(8u8))]
+    Uninitialized(u16)
+}
+
+#[binrw]
+#[derive(Debug)]
+#[brw(big)]
+#[br(import(frame_type: u8))]
+pub enum StackMapFrameBody {
+    #[br(pre_assert(frame_type <= 63))]
+    Same(),
+    #[br(pre_assert((64..=127).contains(&frame_type)))]
+    SameLocals1StackItem {
+        stack: VerificationTypeInfo
+    },
+    #[br(pre_assert(frame_type == 247))]
+    SameLocals1StackItemExtended {
+        offset_delta: u16,
+        stack: VerificationTypeInfo
+    },
+    #[br(pre_assert((248..=250).contains(&frame_type)))]
+    Chop {
+        offset_delta: u16
+    },
+    #[br(pre_assert(frame_type == 251))]
+    SameExtended {
+        offset_delta: u16
+    },
+    #[br(pre_assert((252..=254).contains(&frame_type)))]
+    Append {
+        offset_delta: u16,
+
+        #[br(count = frame_type - 251)]
+        locals: Vec<VerificationTypeInfo>
+    },
+    #[br(pre_assert(frame_type == 255))]
+    Full {
+        offset_delta: u16,
+
+        number_of_locals: u16,
+        #[br(count = number_of_locals)]
+        locals: Vec<VerificationTypeInfo>,
+
+        number_of_stack_items: u16,
+        #[br(count = number_of_stack_items)]
+        stack: Vec<VerificationTypeInfo>
+    }
+}
+
+#[binrw]
+#[derive(Debug)]
+#[brw(big)]
+pub struct StackMapFrame {
+    pub frame_type: u8,
+
+    #[br(args(frame_type))]
+    pub body: StackMapFrameBody
+}
+
+#[binrw]
+#[derive(Debug)]
+#[brw(big)]
+pub struct AttributeStackMapTable {
+    pub number_of_entries: u16,
+
+    #[br(count = number_of_entries)]
+    pub entries: Vec<StackMapFrame>
 }
 
 #[derive(Debug)]
@@ -221,11 +386,18 @@ pub enum Attribute {
     Deprecated(AttributeDeprecated),
     AnnotationDefault(AttributeAnnotationDefault),
     MethodParameters(AttributeMethodParameters),
-    LineNumberTable(AttributeLineNumberTable)
+    LineNumberTable(AttributeLineNumberTable),
+    StackMapTable(AttributeStackMapTable),
+    BootstrapMethods(AttributeBootstrapMethods),
+    InnerClasses(AttributeInnerClasses),
+    EnclosingMethod(AttributeEnclosingMethod),
+    SourceFile(AttributeSourceFile),
+    NestHost(AttributeNestHost),
+    NestMembers(AttributeNestMembers)
 }
 
 #[binrw]
-#[br(big)]
+#[brw(big)]
 #[derive(Debug)]
 pub struct AttributeInfo {
     pub attribute_name_index: u16,
@@ -237,6 +409,7 @@ pub struct AttributeInfo {
 
 impl Attribute {
 
+    /// The typed attribute, `None` for attributes the VM doesn't know or that can't be parsed
     pub fn new(class_file: &ClassFile, attribute_info: &AttributeInfo) -> Option<Self> {
         if let Some(type_string) = class_file.get_constant_pool_string(attribute_info.attribute_name_index as usize) {
             match type_string.trim() {
@@ -290,13 +463,117 @@ impl Attribute {
                         return Some(Attribute::LineNumberTable(attribute));
                     }
                 },
-                _ => println!("Unimplemented attribute '{}'!", type_string)
+                "StackMapTable" => {
+                    if let Ok(attribute) = AttributeStackMapTable::read(&mut Cursor::new(&attribute_info.info)) {
+                        return Some(Attribute::StackMapTable(attribute));
+                    }
+                },
+                "BootstrapMethods" => {
+                    if let Ok(attribute) = AttributeBootstrapMethods::read(&mut Cursor::new(&attribute_info.info)) {
+                        return Some(Attribute::BootstrapMethods(attribute));
+                    }
+                },
+                "InnerClasses" => {
+                    if let Ok(attribute) = AttributeInnerClasses::read(&mut Cursor::new(&attribute_info.info)) {
+                        return Some(Attribute::InnerClasses(attribute));
+                    }
+                },
+                "EnclosingMethod" => {
+                    if let Ok(attribute) = AttributeEnclosingMethod::read(&mut Cursor::new(&attribute_info.info)) {
+                        return Some(Attribute::EnclosingMethod(attribute));
+                    }
+                },
+                "SourceFile" => {
+                    if let Ok(attribute) = AttributeSourceFile::read(&mut Cursor::new(&attribute_info.info)) {
+                        return Some(Attribute::SourceFile(attribute));
+                    }
+                },
+                "NestHost" => {
+                    if let Ok(attribute) = AttributeNestHost::read(&mut Cursor::new(&attribute_info.info)) {
+                        return Some(Attribute::NestHost(attribute));
+                    }
+                },
+                "NestMembers" => {
+                    if let Ok(attribute) = AttributeNestMembers::read(&mut Cursor::new(&attribute_info.info)) {
+                        return Some(Attribute::NestMembers(attribute));
+                    }
+                },
+                // Attributes the VM doesn't know are skipped, like JVMS 4.7.1 requires
+                _ => { }
             };
-
-            println!("Failed to parse '{}' attribute", type_string);
         }
 
         None
     }
 
+    pub fn is_supported(name: &str) -> bool {
+        matches!(name, "ConstantValue" | "Code" | "Exceptions" | "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" |
+                       "Signature" | "Deprecated" | "AnnotationDefault" | "MethodParameters" | "LineNumberTable" |
+                       "StackMapTable" | "BootstrapMethods" | "InnerClasses" | "EnclosingMethod" | "SourceFile" |
+                       "NestHost" | "NestMembers")
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Attribute::ConstantValue(_)                 => "ConstantValue",
+            Attribute::Code(_)                          => "Code",
+            Attribute::Exceptions(_)                    => "Exceptions",
+            Attribute::RuntimeVisibleAnnotations(_)     => "RuntimeVisibleAnnotations",
+            Attribute::RuntimeInvisibleAnnotations(_)   => "RuntimeInvisibleAnnotations",
+            Attribute::Signature(_)                     => "Signature",
+            Attribute::Deprecated(_)                    => "Deprecated",
+            Attribute::AnnotationDefault(_)             => "AnnotationDefault",
+            Attribute::MethodParameters(_)              => "MethodParameters",
+            Attribute::LineNumberTable(_)               => "LineNumberTable",
+            Attribute::StackMapTable(_)                 => "StackMapTable",
+            Attribute::BootstrapMethods(_)              => "BootstrapMethods",
+            Attribute::InnerClasses(_)                  => "InnerClasses",
+            Attribute::EnclosingMethod(_)               => "EnclosingMethod",
+            Attribute::SourceFile(_)                    => "SourceFile",
+            Attribute::NestHost(_)                      => "NestHost",
+            Attribute::NestMembers(_)                   => "NestMembers"
+        }
+    }
+
+    pub fn write(&self) -> BinResult<Vec<u8>> {
+        let mut writer = Cursor::new(Vec::new());
+
+        match self {
+            Attribute::ConstantValue(attribute)                 => attribute.write_to(&mut writer)?,
+            Attribute::Code(attribute)                          => attribute.write_to(&mut writer)?,
+            Attribute::Exceptions(attribute)                    => attribute.write_to(&mut writer)?,
+            Attribute::RuntimeVisibleAnnotations(attribute)     => attribute.write_to(&mut writer)?,
+            Attribute::RuntimeInvisibleAnnotations(attribute)   => attribute.write_to(&mut writer)?,
+            Attribute::Signature(attribute)                     => attribute.write_to(&mut writer)?,
+            Attribute::Deprecated(attribute)                    => attribute.write_to(&mut writer)?,
+            Attribute::AnnotationDefault(attribute)             => attribute.write_to(&mut writer)?,
+            Attribute::MethodParameters(attribute)              => attribute.write_to(&mut writer)?,
+            Attribute::LineNumberTable(attribute)               => attribute.write_to(&mut writer)?,
+            Attribute::StackMapTable(attribute)                 => attribute.write_to(&mut writer)?,
+            Attribute::BootstrapMethods(attribute)              => attribute.write_to(&mut writer)?,
+            Attribute::InnerClasses(attribute)                  => attribute.write_to(&mut writer)?,
+            Attribute::EnclosingMethod(attribute)               => attribute.write_to(&mut writer)?,
+            Attribute::SourceFile(attribute)                    => attribute.write_to(&mut writer)?,
+            Attribute::NestHost(attribute)                      => attribute.write_to(&mut writer)?,
+            Attribute::NestMembers(attribute)                   => attribute.write_to(&mut writer)?
+        };
+
+        Ok(writer.into_inner())
+    }
+
+    /// The attribute as it's stored in a class file, whose constant pool has to contain its name already
+    pub fn to_attribute_info(&self, class_file: &ClassFile) -> BinResult<AttributeInfo> {
+        let attribute_name_index = class_file.find_constant_pool_string(self.name()).ok_or_else(|| binrw::Error::AssertFail {
+            pos: 0,
+            message: format!("The constant pool has no entry for the attribute name {}", self.name())
+        })?;
+        let info = self.write()?;
+
+        Ok(AttributeInfo {
+            attribute_name_index,
+            attribute_length: info.len() as u32,
+            info
+        })
+    }
+
 }
\ No newline at end of file
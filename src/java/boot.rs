@@ -0,0 +1,193 @@
+#![allow(dead_code)]
+
+use std::fmt;
+use std::fmt::Formatter;
+
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+/// Classes initialized before `System.initPhase1`, in the order HotSpot initializes them
+const EARLY_CLASSES: [&str; 7] = [
+    "java/lang/Object",
+    "java/lang/String",
+    "java/lang/System",
+    "java/lang/Class",
+    "java/lang/ThreadGroup",
+    "java/lang/Thread",
+    // Sets up `SharedSecrets.getJavaLangReflectAccess()` before a `ReflectionFactory` can capture it
+    "java/lang/reflect/Method"
+];
+
+/// Classes initialized between the phases, the VM raises them without running Java code first
+const PREINITIALIZED_EXCEPTIONS: [&str; 8] = [
+    "java/lang/OutOfMemoryError",
+    "java/lang/NullPointerException",
+    "java/lang/ClassCastException",
+    "java/lang/ArrayStoreException",
+    "java/lang/ArithmeticException",
+    "java/lang/StackOverflowError",
+    "java/lang/IllegalMonitorStateException",
+    "java/lang/IllegalArgumentException"
+];
+
+/// How far `java.base` has been initialized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BootState {
+    /// `java.base` wasn't initialized, classes are initialized on first use and `System` sets up its properties
+    /// and streams itself, see `VmContext::initialize_system`
+    #[default]
+    NotBooted,
+    Booting,
+    Booted,
+    /// Booting failed, the VM can't run Java code
+    Failed
+}
+
+/// Why `java.base` couldn't be initialized
+#[derive(Debug, Clone)]
+pub struct BootError {
+    /// The step of the startup sequence that failed, e.g. `System.initPhase1`
+    pub phase: String,
+    /// The exception the step raised, as `Throwable.toString` would describe it
+    pub exception: String,
+    /// Native methods that were called during boot but aren't implemented, in the order they were called
+    pub missing_natives: Vec<String>
+}
+
+impl VmContext {
+
+    /// Runs the startup sequence of `java.base` like the launcher does before it invokes `main`:
+    /// initializes the core classes and the main thread, then calls `System.initPhase1`, `initPhase2` and `initPhase3`,
+    /// which set up the system properties, the standard streams, the module system and the system class loader
+    pub fn boot(&mut self) -> Result<(), BootError> {
+        if matches!(self.boot_state, BootState::Booting | BootState::Booted) {
+            return Ok(());
+        }
+
+        self.boot_state = BootState::Booting;
+        self.missing_natives.clear();
+
+        let result = self.run_boot_sequence();
+
+        self.boot_state = if result.is_ok() { BootState::Booted } else { BootState::Failed };
+
+        // Booting isn't limited, the program gets all of its limits
+        self.restart_limits();
+
+        result
+    }
+
+    fn run_boot_sequence(&mut self) -> Result<(), BootError> {
+        for class_name in EARLY_CLASSES {
+            self.boot_step(class_name, |context| context.initialize_class_named(class_name))?;
+        }
+
+        // The main thread has to exist before Java code asks for it, initPhase1 adds it to its thread group
+        self.boot_step("main thread", |context| context.main_thread_object().map(|_| ()))?;
+
+        let system_class = self.boot_step("java/lang/System", |context| context.load_class("java/lang/System"))?;
+        self.boot_step("System.initPhase1", |context| context.invoke_static(system_class, "initPhase1", "()V", &[]).map(|_| ()))?;
+
+        for class_name in PREINITIALIZED_EXCEPTIONS {
+            self.boot_step(class_name, |context| context.initialize_class_named(class_name))?;
+        }
+
+        // The module system reads the module graph from a run-time image, without one there's no boot layer.
+        // Classes aren't bound to modules by the VM anyway, so java.base.jar works without it.
+        if self.has_runtime_image() {
+            // initPhase2 reports its own failures to System.err and returns a status instead of throwing
+            let status = self.boot_step("System.initPhase2", |context| {
+                context.invoke_static(system_class, "initPhase2", "(ZZ)I", &[Value::from_bool(true), Value::from_bool(true)])
+            })?;
+
+            if status.map(|status| status.as_int()) != Some(0) {
+                return Err(self.boot_error("System.initPhase2", "the boot layer couldn't be initialized".to_string()));
+            }
+        }
+
+        self.boot_step("System.initPhase3", |context| context.invoke_static(system_class, "initPhase3", "()V", &[]).map(|_| ()))?;
+
+        if !self.has_runtime_image() {
+            self.boot_step("ModuleLayer.empty", |context| context.define_empty_boot_layer(system_class))?;
+        }
+
+        Ok(())
+    }
+
+    /// Makes the empty layer the boot layer, which leaves all classes in the unnamed module of the boot loader.
+    /// Code like `StackTraceElement` expects the module system to be set up once `initPhase3` is done.
+    fn define_empty_boot_layer(&mut self, system_class: ClassId) -> Result<(), Throwable> {
+        let module_layer_class = self.load_class("java/lang/ModuleLayer")?;
+        let empty_layer = self.invoke_static(module_layer_class, "empty", "()Ljava/lang/ModuleLayer;", &[])?.unwrap_or(Value::null());
+        self.classes[system_class as usize].static_values.insert("bootLayer".to_string(), empty_layer);
+
+        self.initialize_class_named("jdk/internal/loader/BootLoader")
+    }
+
+    /// Whether `java.home` is a JDK run-time image with the module graph, a jimage or exploded modules
+    fn has_runtime_image(&self) -> bool {
+        let java_home = self.vm_properties().into_iter()
+            .find(|(name, _)| name == "java.home")
+            .map(|(_, value)| std::path::PathBuf::from(value));
+
+        java_home.is_some_and(|java_home| java_home.join("lib").join("modules").is_file() || java_home.join("modules").is_dir())
+    }
+
+    fn initialize_class_named(&mut self, class_name: &str) -> Result<(), Throwable> {
+        let class_id = self.load_class(class_name)?;
+        self.initialize_class(class_id)
+    }
+
+    /// Runs a step of the startup sequence, turning an exception into a `BootError`
+    fn boot_step<T>(&mut self, phase: &str, step: impl FnOnce(&mut Self) -> Result<T, Throwable>) -> Result<T, BootError> {
+        step(self).map_err(|throwable| {
+            let exception = self.describe_throwable(&throwable);
+            self.boot_error(phase, exception)
+        })
+    }
+
+    fn boot_error(&self, phase: &str, exception: String) -> BootError {
+        BootError {
+            phase: phase.to_string(),
+            exception,
+            missing_natives: self.missing_natives.clone()
+        }
+    }
+
+    /// Sets the constants HotSpot injects into `jdk.internal.misc.UnsafeConstants` once its initializer ran
+    pub fn inject_unsafe_constants(&mut self, class_id: ClassId) {
+        let constants = [
+            ("ADDRESS_SIZE0", Value::from_int(std::mem::size_of::<usize>() as i32)),
+            ("PAGE_SIZE", Value::from_int(4096)),
+            // Primitive arrays read with a different width are viewed as little endian bytes, see `ArrayData::get_bits`
+            ("BIG_ENDIAN", Value::from_bool(false)),
+            // Unsafe accesses aren't backed by memory, so there's no alignment to respect
+            ("UNALIGNED_ACCESS", Value::from_bool(true)),
+            ("DATA_CACHE_LINE_FLUSH_SIZE", Value::from_int(0))
+        ];
+
+        for (name, value) in constants {
+            self.classes[class_id as usize].static_values.insert(name.to_string(), value);
+        }
+    }
+
+}
+
+impl fmt::Display for BootError {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Error occurred during initialization of VM")?;
+        writeln!(f, "{} failed: {}", self.phase, self.exception)?;
+
+        if !self.missing_natives.is_empty() {
+            writeln!(f, "Native methods that aren't implemented:")?;
+
+            for native in &self.missing_natives {
+                writeln!(f, "    {}", native)?;
+            }
+        }
+
+        Ok(())
+    }
+
+}
\ No newline at end of file
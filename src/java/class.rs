@@ -1,21 +1,43 @@
 #![allow(dead_code)]
 
 use std::collections::HashMap;
-use binrw::{BinRead, BinReaderExt, BinResult, Endian, ReadOptions};
-use std::io::{Cursor, Read, Seek};
+use std::sync::Arc;
+use binrw::{BinRead, BinReaderExt, BinResult, BinWrite, BinWriterExt, Endian, ReadOptions, WriteOptions};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use binrw::binrw;
 
 use crate::java;
-use crate::java::{Field, Method};
+use crate::java::{Attribute, Field, Method};
+use crate::java::annotation::{self, ResolvedAnnotation};
+use crate::java::attribute::BootstrapMethod;
+
+/// Tags of the constant pool entries JVMS 4.4 defines
+fn is_constant_tag(tag: u8) -> bool {
+    matches!(tag, 1 | 3..=12 | 15..=20)
+}
+
+fn format_error(pos: u64, message: String) -> binrw::Error {
+    binrw::Error::AssertFail { pos, message }
+}
 
 fn constant_pool_entry_parser<R: Read + Seek>(reader: &mut R, _: &ReadOptions, _: ()) -> BinResult<Vec<ConstantPoolEntry>>{
-    let constant_pool_size = reader.read_be::<u16>().unwrap();
+    let constant_pool_size = reader.read_be::<u16>()? as usize;
+    if constant_pool_size == 0 {
+        return Err(format_error(reader.stream_position()?, "Illegal constant pool size 0".to_string()));
+    }
 
     let mut constant_pool: Vec<ConstantPoolEntry> = Vec::new();
 
-    let mut i = 0;
-    while i < constant_pool_size - 1{
-        let entry = reader.read_type::<ConstantPoolEntry>(Endian::Big).unwrap();
+    let mut i = 1;
+    while i < constant_pool_size {
+        let pos = reader.stream_position()?;
+        let tag = reader.read_be::<u8>()?;
+        if !is_constant_tag(tag) {
+            return Err(format_error(pos, format!("Unknown constant tag {} at constant pool index {}", tag, i)));
+        }
+
+        reader.seek(SeekFrom::Start(pos))?;
+        let entry = reader.read_type::<ConstantPoolEntry>(Endian::Big)?;
 
         let mut push_none = false;
 
@@ -25,6 +47,10 @@ fn constant_pool_entry_parser<R: Read + Seek>(reader: &mut R, _: &ReadOptions, _
             _ => { i += 1; }
         };
 
+        if i > constant_pool_size {
+            return Err(format_error(pos, format!("Long or Double at the last constant pool index {}", constant_pool_size - 1)));
+        }
+
         constant_pool.push(entry);
 
         if push_none { constant_pool.push(ConstantPoolEntry::None()); }
@@ -33,55 +59,68 @@ fn constant_pool_entry_parser<R: Read + Seek>(reader: &mut R, _: &ReadOptions, _
     Ok(constant_pool)
 }
 
+fn constant_pool_entry_writer<W: Write + Seek>(constant_pool: &Vec<ConstantPoolEntry>, writer: &mut W, _: &WriteOptions, _: ()) -> BinResult<()> {
+    writer.write_be(&((constant_pool.len() + 1) as u16))?;
+
+    for entry in constant_pool {
+        // Second slot of a Long or Double, it only exists to keep the indices intact
+        if let ConstantPoolEntry::None() = entry { continue; }
+
+        writer.write_type(entry, Endian::Big)?;
+    }
+
+    Ok(())
+}
+
 #[binrw]
-#[br(big)]
+#[brw(big)]
 #[derive(Debug)]
 pub enum ConstantPoolEntry {
-    #[br(magic(0u8))]
+    #[brw(magic(0u8))]
     None(),
-    #[br(magic(1u8))]
+    #[brw(magic(1u8))]
     String {
         length: u16,
 
         #[br(count = length)]
         string: Vec<u8>
     },
-    #[br(big, magic(3u8))]
+    #[brw(big, magic(3u8))]
     Integer(u32),
-    #[br(big, magic(4u8))]
+    #[brw(big, magic(4u8))]
     Float(u32),
-    #[br(big, magic(5u8))]
+    #[brw(big, magic(5u8))]
     Long(u32, u32),
-    #[br(big, magic(6u8))]
+    #[brw(big, magic(6u8))]
     Double(u32, u32),
-    #[br(big, magic(7u8))]
+    #[brw(big, magic(7u8))]
     ClassReference(u16),
-    #[br(big, magic(8u8))]
+    #[brw(big, magic(8u8))]
     StringReference(u16),
-    #[br(big, magic(9u8))]
+    #[brw(big, magic(9u8))]
     FieldReference(u16, u16),
-    #[br(big, magic(10u8))]
+    #[brw(big, magic(10u8))]
     MethodReference(u16, u16),
-    #[br(big, magic(11u8))]
+    #[brw(big, magic(11u8))]
     InterfaceMethodReference(u16, u16),
-    #[br(big, magic(12u8))]
+    #[brw(big, magic(12u8))]
     NameAndTypeDescriptor(u16, u16),
-    #[br(big, magic(15u8))]
+    #[brw(big, magic(15u8))]
     MethodHandle(u8, u16),
-    #[br(big, magic(16u8))]
+    #[brw(big, magic(16u8))]
     MethodType(u16),
-    #[br(big, magic(17u8))]
+    #[brw(big, magic(17u8))]
     Dynamic(u16, u16),
-    #[br(big, magic(18u8))]
+    #[brw(big, magic(18u8))]
     InvokeDynamic(u16, u16),
-    #[br(big, magic(19u8))]
+    #[brw(big, magic(19u8))]
     Module(u16),
-    #[br(big, magic(20u8))]
+    #[brw(big, magic(20u8))]
     Package(u16),
 }
 
 #[binrw]
-#[br(big)]
+#[brw(big)]
 #[derive(Debug)]
 pub struct FieldInfo {
     pub access_flags: u16,
@@ -94,7 +133,7 @@ pub struct FieldInfo {
 }
 
 #[binrw]
-#[br(big)]
+#[brw(big)]
 #[derive(Debug)]
 pub struct MethodInfo {
     pub access_flags: u16,
@@ -108,12 +147,13 @@ pub struct MethodInfo {
 
 #[binrw]
 #[derive(Debug)]
-#[br(big, magic = b"\xCA\xFE\xBA\xBE")]
+#[brw(big, magic = b"\xCA\xFE\xBA\xBE")]
 pub struct ClassFile {
     pub minor_version: u16,
     pub major_version: u16,
 
     #[br(parse_with = constant_pool_entry_parser)]
+    #[bw(write_with = constant_pool_entry_writer)]
     pub constant_pool: Vec<ConstantPoolEntry>,
 
     pub access_flags: u16,
@@ -142,20 +182,24 @@ pub struct Class {
     pub class_file: ClassFile,
 
     pub fields: HashMap<String, java::Field>,
-    pub methods: HashMap<String, java::Method>,
+    /// Keyed by name and descriptor, e.g. `main([Ljava/lang/String;)V`
+    pub methods: HashMap<String, Arc<java::Method>>,
+
+    pub bootstrap_methods: Vec<BootstrapMethod>,
 
-    pub initialized: bool
+    annotations: Vec<ResolvedAnnotation>,
+    source_file: Option<String>
 }
 
 impl Class {
 
-    fn parse_methods(class_file: &ClassFile) -> HashMap<String, java::Method> {
+    fn parse_methods(class_file: &ClassFile) -> HashMap<String, Arc<java::Method>> {
         let mut result = HashMap::new();
 
         for method_info in &class_file.method_table {
-            let method = Method::new(&class_file, method_info);
+            let method = Method::new(class_file, method_info);
             if let Some(method) = method {
-                result.insert(method.name.clone(), method);
+                result.insert(format!("{}{}", method.name, method.descriptor), Arc::new(method));
             }
 
         }
@@ -167,7 +211,7 @@ impl Class {
         let mut result = HashMap::new();
 
         for field_info in &class_file.field_table {
-            let field = Field::new(&class_file, field_info);
+            let field = Field::new(class_file, field_info);
             if let Some(field) = field {
                 result.insert(field.name.clone(), field);
             }
@@ -177,23 +221,89 @@ impl Class {
         result
     }
 
-    pub fn new(data: &Vec<u8>) -> Option<Self> {
-        let class_file = ClassFile::read(&mut Cursor::new(&data));
-        if let Ok(class_file) = class_file {
-            let fields = Self::parse_fields(&class_file);
-            let methods = Self::parse_methods(&class_file);
-
-            Some(Class {
-                class_file,
-                fields,
-                methods,
-                initialized: false
-            })
-        } else {
-            println!("Class parse error!");
-            println!("{}", class_file.unwrap_err());
-            None
+    fn parse_attribute(class_file: &ClassFile, name: &str) -> Option<Attribute> {
+        class_file.attribute_table.iter()
+            .filter(|attribute_info| class_file.get_constant_pool_string(attribute_info.attribute_name_index as usize).as_deref() == Some(name))
+            .find_map(|attribute_info| Attribute::new(class_file, attribute_info))
+    }
+
+    fn parse_annotations(class_file: &ClassFile) -> Vec<ResolvedAnnotation> {
+        match Self::parse_attribute(class_file, "RuntimeVisibleAnnotations") {
+            Some(attribute) => annotation::runtime_visible_annotations(class_file, &[attribute]),
+            None => vec![]
+        }
+    }
+
+    fn parse_source_file(class_file: &ClassFile) -> Option<String> {
+        match Self::parse_attribute(class_file, "SourceFile") {
+            Some(Attribute::SourceFile(attribute)) => class_file.get_constant_pool_string(attribute.sourcefile_index as usize),
+            _ => None
+        }
+    }
+
+    fn parse_bootstrap_methods(class_file: &ClassFile) -> Vec<BootstrapMethod> {
+        match Self::parse_attribute(class_file, "BootstrapMethods") {
+            Some(Attribute::BootstrapMethods(attribute)) => attribute.bootstrap_methods,
+            _ => vec![]
+        }
+    }
+
+    /// Parses an attribute of the class itself by its name, e.g. `InnerClasses`
+    pub fn find_attribute(&self, name: &str) -> Option<Attribute> {
+        Self::parse_attribute(&self.class_file, name)
+    }
+
+    /// Runtime visible annotations of the class, without the default values of elements they leave out
+    pub fn annotations(&self) -> &[ResolvedAnnotation] {
+        &self.annotations
+    }
+
+    /// Name of the source file the class was compiled from, e.g. `Foo.java`
+    pub fn source_file(&self) -> Option<&str> {
+        self.source_file.as_deref()
+    }
+
+    pub fn name(&self) -> String {
+        self.class_file.get_class_name(self.class_file.this_class as usize).unwrap_or_default()
+    }
+
+    pub fn super_class_name(&self) -> Option<String> {
+        self.class_file.get_class_name(self.class_file.super_class as usize)
+    }
+
+    pub fn interface_names(&self) -> Vec<String> {
+        self.class_file.interface_table.iter()
+            .filter_map(|index| self.class_file.get_class_name(*index as usize))
+            .collect()
+    }
+
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&Arc<java::Method>> {
+        self.methods.get(&format!("{}{}", name, descriptor))
+    }
+
+    /// Parses a class file, failing if it's truncated, has bytes left over or can't be a class file at all.
+    /// Whether its contents are consistent is up to `format_checker::check_class_format`.
+    pub fn new(data: &Vec<u8>) -> BinResult<Self> {
+        let mut reader = Cursor::new(&data);
+        let class_file = ClassFile::read(&mut reader)?;
+        if reader.position() != data.len() as u64 {
+            return Err(format_error(reader.position(), "Extra bytes at the end of class file".to_string()));
         }
+
+        let fields = Self::parse_fields(&class_file);
+        let methods = Self::parse_methods(&class_file);
+        let bootstrap_methods = Self::parse_bootstrap_methods(&class_file);
+        let annotations = Self::parse_annotations(&class_file);
+        let source_file = Self::parse_source_file(&class_file);
+
+        Ok(Class {
+            class_file,
+            fields,
+            methods,
+            bootstrap_methods,
+            annotations,
+            source_file
+        })
     }
 
 }
@@ -201,10 +311,61 @@ impl Class {
 impl ClassFile {
 
     pub fn get_constant_pool_string(&self, index: usize) -> Option<String> {
-        if let Some(attribute_type) = self.constant_pool.get((index - 1) as usize) {
-            if let ConstantPoolEntry::String { length: _, string } = attribute_type {
-                if let Ok(string) = String::from_utf8(string.to_vec()) {
-                    return Some(string);
+        if let Some(ConstantPoolEntry::String { length: _, string }) = self.constant_pool.get(index.checked_sub(1)?) {
+            if let Ok(string) = String::from_utf8(string.to_vec()) {
+                return Some(string);
+            }
+        }
+
+        None
+    }
+
+    pub fn get_class_name(&self, index: usize) -> Option<String> {
+        if let Some(ConstantPoolEntry::ClassReference(name_index)) = self.constant_pool.get(index.checked_sub(1)?) {
+            return self.get_constant_pool_string(*name_index as usize);
+        }
+
+        None
+    }
+
+    pub fn get_name_and_type(&self, index: usize) -> Option<(String, String)> {
+        if let Some(ConstantPoolEntry::NameAndTypeDescriptor(name_index, descriptor_index)) = self.constant_pool.get(index.checked_sub(1)?) {
+            return Some((self.get_constant_pool_string(*name_index as usize)?, self.get_constant_pool_string(*descriptor_index as usize)?));
+        }
+
+        None
+    }
+
+    /// Class name, member name and descriptor of a field, method or interface method reference
+    pub fn get_member_reference(&self, index: usize) -> Option<(String, String, String)> {
+        match self.constant_pool.get(index.checked_sub(1)?)? {
+            ConstantPoolEntry::FieldReference(class_index, name_and_type_index) |
+            ConstantPoolEntry::MethodReference(class_index, name_and_type_index) |
+            ConstantPoolEntry::InterfaceMethodReference(class_index, name_and_type_index) => {
+                let (name, descriptor) = self.get_name_and_type(*name_and_type_index as usize)?;
+
+                Some((self.get_class_name(*class_index as usize)?, name, descriptor))
+            },
+            _ => None
+        }
+    }
+
+    /// Reference kind, class name, member name and descriptor of a method handle
+    pub fn get_method_handle(&self, index: usize) -> Option<(u8, String, String, String)> {
+        if let Some(ConstantPoolEntry::MethodHandle(kind, reference_index)) = self.constant_pool.get(index.checked_sub(1)?) {
+            let (class_name, name, descriptor) = self.get_member_reference(*reference_index as usize)?;
+
+            return Some((*kind, class_name, name, descriptor));
+        }
+
+        None
+    }
+
+    pub fn find_constant_pool_string(&self, string: &str) -> Option<u16> {
+        for (index, entry) in self.constant_pool.iter().enumerate() {
+            if let ConstantPoolEntry::String { length: _, string: bytes } = entry {
+                if bytes == string.as_bytes() {
+                    return Some((index + 1) as u16);
                 }
             }
         }
@@ -212,4 +373,11 @@ impl ClassFile {
         None
     }
 
+    pub fn write(&self) -> BinResult<Vec<u8>> {
+        let mut writer = Cursor::new(Vec::new());
+        self.write_to(&mut writer)?;
+
+        Ok(writer.into_inner())
+    }
+
 }
\ No newline at end of file
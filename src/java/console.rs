@@ -0,0 +1,176 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::java::descriptor::FieldType;
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+/// Where the console streams write to
+#[derive(Debug, Clone)]
+pub enum ConsoleOutput {
+    Stdout,
+    Stderr,
+    /// In-memory buffer, e.g. to inspect the output of a program in tests
+    Buffer(Arc<Mutex<Vec<u8>>>)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleStream {
+    Out,
+    Err
+}
+
+/// Intrinsic `java.io.PrintStream` objects used for `System.out` and `System.err`
+/// as long as `java.base` hasn't installed its own streams
+#[derive(Debug)]
+pub struct Console {
+    pub out: ConsoleOutput,
+    pub err: ConsoleOutput,
+
+    streams: HashMap<u32, ConsoleStream>
+}
+
+impl ConsoleOutput {
+
+    pub fn write(&self, bytes: &[u8]) {
+        // Like PrintStream, failures to write are not reported to the program
+        let _ = match self {
+            ConsoleOutput::Stdout => std::io::stdout().write_all(bytes),
+            ConsoleOutput::Stderr => std::io::stderr().write_all(bytes),
+            ConsoleOutput::Buffer(buffer) => {
+                if let Ok(mut buffer) = buffer.lock() {
+                    buffer.extend_from_slice(bytes);
+                }
+
+                Ok(())
+            }
+        };
+    }
+
+    pub fn flush(&self) {
+        let _ = match self {
+            ConsoleOutput::Stdout => std::io::stdout().flush(),
+            ConsoleOutput::Stderr => std::io::stderr().flush(),
+            ConsoleOutput::Buffer(_) => Ok(())
+        };
+    }
+
+}
+
+impl Console {
+
+    pub fn new() -> Self {
+        Console {
+            out: ConsoleOutput::Stdout,
+            err: ConsoleOutput::Stderr,
+
+            streams: HashMap::new()
+        }
+    }
+
+    pub fn output(&self, stream: ConsoleStream) -> &ConsoleOutput {
+        match stream {
+            ConsoleStream::Out => &self.out,
+            ConsoleStream::Err => &self.err
+        }
+    }
+
+    pub fn write(&self, stream: ConsoleStream, text: &str) {
+        self.output(stream).write(text.as_bytes());
+    }
+
+    /// The intrinsic `PrintStream` objects
+    pub fn references(&self) -> impl Iterator<Item = u32> + '_ {
+        self.streams.keys().copied()
+    }
+
+    /// The console stream a `PrintStream` object stands for, if it's one of the intrinsic ones
+    pub fn stream(&self, reference: u32) -> Option<ConsoleStream> {
+        self.streams.get(&reference).copied()
+    }
+
+}
+
+impl Default for Console {
+
+    fn default() -> Self {
+        Self::new()
+    }
+
+}
+
+impl VmContext {
+
+    /// Points `System.out` and `System.err` at console streams unless they were already set
+    pub fn install_console_streams(&mut self, system_class: ClassId) -> Result<(), Throwable> {
+        for (field, stream) in [("out", ConsoleStream::Out), ("err", ConsoleStream::Err)] {
+            let current = self.class(system_class).static_values.get(field).copied().unwrap_or(Value::null());
+            if !current.is_null() {
+                continue;
+            }
+
+            let print_stream_class = self.load_class("java/io/PrintStream")?;
+            let print_stream = self.new_object(print_stream_class);
+
+            self.console.streams.insert(print_stream.as_reference(), stream);
+            self.classes[system_class as usize].static_values.insert(field.to_string(), print_stream);
+        }
+
+        Ok(())
+    }
+
+    /// Text `PrintStream.print` writes for an argument of the given descriptor
+    fn print_text(&mut self, descriptor: &str, value: Value) -> Result<String, Throwable> {
+        match descriptor {
+            // Unlike String.valueOf, print(char[]) writes the characters
+            "[C" => self.rust_string_from_chars(value),
+            _ => {
+                let field_type = FieldType::parse(descriptor).unwrap_or(FieldType::Object("java/lang/Object".to_string()));
+                Ok(String::from_utf16_lossy(&self.java_string_of(&field_type, value)?))
+            }
+        }
+    }
+
+    /// Runs a `PrintStream` method on a console stream, returns false for methods without an intrinsic
+    pub fn invoke_console_intrinsic(&mut self, stream: ConsoleStream, name: &str, descriptor: &str, args: &[Value]) -> Result<bool, Throwable> {
+        let parameter = descriptor.strip_prefix('(').and_then(|descriptor| descriptor.strip_suffix(")V"));
+
+        let text = match (name, parameter) {
+            ("print", Some(parameter)) if !parameter.is_empty() => self.print_text(parameter, args[1])?,
+            ("println", Some("")) => "\n".to_string(),
+            ("println", Some(parameter)) => self.print_text(parameter, args[1])? + "\n",
+            ("write", Some("I")) => {
+                self.console.output(stream).write(&[args[1].as_int() as u8]);
+                return Ok(true);
+            },
+            ("write", Some("[BII")) => {
+                let (offset, length) = (args[2].as_int(), args[3].as_int());
+                let bytes = match self.object(args[1])?.array() {
+                    Some(crate::java::heap::ArrayData::Byte(bytes)) => bytes,
+                    _ => return Err(Throwable::null_pointer())
+                };
+
+                if offset < 0 || length < 0 || offset as usize + length as usize > bytes.len() {
+                    return Err(Throwable::without_message("java/lang/IndexOutOfBoundsException"));
+                }
+
+                let bytes: Vec<u8> = bytes[offset as usize..(offset + length) as usize].iter().map(|byte| *byte as u8).collect();
+                self.console.output(stream).write(&bytes);
+                return Ok(true);
+            },
+            ("flush", Some("")) => {
+                self.console.output(stream).flush();
+                return Ok(true);
+            },
+            _ => return Ok(false)
+        };
+
+        self.console.write(stream, &text);
+
+        Ok(true)
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,245 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::java;
+use crate::java::descriptor::FieldType;
+use crate::java::dispatch::InlineCache;
+use crate::java::instruction::Instruction;
+#[cfg(feature = "jit")]
+use crate::java::jit::JitState;
+use crate::java::method_handle::Intrinsic;
+use crate::java::opcodes::Opcode;
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+/// Marks the bytes of `DecodedCode::index` that don't start an instruction
+const NO_INSTRUCTION: u32 = u32::MAX;
+
+/// The byte code of a method, decoded once when its class is linked. Program counters stay byte offsets
+/// into the original code, so exception tables, line numbers and stack traces apply unchanged.
+#[derive(Debug)]
+pub struct DecodedCode {
+    pub instructions: Vec<DecodedInstruction>,
+    /// Index into `instructions` by the byte offset an instruction starts at
+    index: Vec<u32>,
+    /// Counts calls and loop iterations, and holds the machine code once the method got hot
+    #[cfg(feature = "jit")]
+    pub jit: JitState
+}
+
+#[derive(Debug)]
+pub struct DecodedInstruction {
+    pub op: Op,
+    /// Byte offset of the following instruction
+    pub next: usize
+}
+
+/// An instruction in the form the interpreter executes. Local variable indices and constants are taken out
+/// of the opcodes, branch targets are absolute byte offsets.
+/// Constant pool references are resolved the first time they run and kept in the instruction, this quickening
+/// doesn't keep failures, an instruction that couldn't be resolved tries again when it runs again.
+#[derive(Debug)]
+pub enum Op {
+    /// Instructions without operands that aren't turned into one of the others
+    Simple(Opcode),
+    /// `aconst_null`, `iconst_<i>`, `lconst_<l>`, `fconst_<f>`, `dconst_<d>`, `bipush`, `sipush` and `ldc` of numbers
+    Const(Value),
+    /// Loads and stores of all types, including the ones with the index in the opcode
+    Load(u16),
+    Store(u16),
+    Increment(u16, i32),
+    Ret(u16),
+    /// Conditional branches, `goto` and `jsr`
+    Branch(Opcode, usize),
+    TableSwitch { default: usize, low: i32, targets: Box<[usize]> },
+    LookupSwitch { default: usize, pairs: Box<[(i32, usize)]> },
+    NewArray(FieldType),
+    MultiANewArray(u16, u8),
+    /// `ldc` of strings, classes and dynamic constants, which stay alive as long as the VM once they're loaded
+    Ldc(u16, OnceLock<Value>),
+    Field(Opcode, u16, OnceLock<ResolvedField>),
+    /// `invokevirtual`, `invokespecial`, `invokestatic` and `invokeinterface`
+    Invoke(Opcode, u16, OnceLock<ResolvedMethod>),
+    InvokeDynamic(u16),
+    New(u16, OnceLock<ClassId>),
+    ANewArray(u16, OnceLock<FieldType>),
+    /// `checkcast` and `instanceof`
+    TypeCheck(Opcode, u16, OnceLock<ClassId>),
+    /// Code that couldn't be decoded, a `VerifyError` with the message is raised once it's reached
+    Invalid(String)
+}
+
+/// The field a field instruction accesses
+#[derive(Debug)]
+pub enum ResolvedField {
+    /// Held by the static values of the declaring class, which is initialized before the access
+    Static { class: ClassId, name: String, default: Value },
+    /// Index into the field values of objects
+    Instance { slot: usize, name: String }
+}
+
+/// The method an invoke instruction calls
+#[derive(Debug)]
+pub struct ResolvedMethod {
+    pub class: ClassId,
+    pub method: Arc<java::Method>,
+    /// Values popped off the operand stack, including the receiver
+    pub argument_count: usize,
+    pub dispatch: Dispatch,
+    /// For `Dispatch::Virtual`, the slot of the method in the vtable of `class`, which indexes the itables of
+    /// receivers if `class` is an interface, `NO_SLOT` if it has none
+    pub slot: u32,
+    /// Receiver classes of `Dispatch::Virtual` calls with the vtable slot of the method they selected
+    pub cache: InlineCache
+}
+
+/// How an invoke instruction selects the method it calls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dispatch {
+    /// `invokestatic`, initializes the class of the method first
+    Static,
+    /// Constructors, private methods and super calls, which call `ResolvedMethod::method` on any receiver
+    Direct,
+    /// Selected by the class of the receiver
+    Virtual,
+    /// `invokeBasic` and `linkTo*` of `MethodHandle`, which select the method from their arguments
+    Intrinsic(Intrinsic),
+    /// Other signature polymorphic methods, which call the invoker `ResolvedMethod::method` with the appendix appended
+    Linked(Option<u32>)
+}
+
+impl DecodedCode {
+
+    /// The instruction starting at a byte offset
+    pub fn at(&self, program_counter: usize) -> Option<&DecodedInstruction> {
+        match self.index.get(program_counter) {
+            Some(&index) if index != NO_INSTRUCTION => self.instructions.get(index as usize),
+            _ => None
+        }
+    }
+
+}
+
+/// The value of a quickened instruction, resolving it the first time
+pub fn quickened<T>(cell: &OnceLock<T>, resolve: impl FnOnce() -> Result<T, Throwable>) -> Result<&T, Throwable> {
+    match cell.get() {
+        Some(value) => Ok(value),
+        None => {
+            let value = resolve()?;
+            Ok(cell.get_or_init(|| value))
+        }
+    }
+}
+
+impl VmContext {
+
+    /// Decodes the code of a method of a class file. Decoding stops at code that can't be decoded,
+    /// which raises the error only if it's reached.
+    pub fn decode_code(&self, class: &java::Class, code: &[u8]) -> DecodedCode {
+        let mut instructions = vec![];
+        let mut index = vec![NO_INSTRUCTION; code.len()];
+
+        let mut pc = 0;
+        while pc < code.len() {
+            index[pc] = instructions.len() as u32;
+
+            match Instruction::decode_at(code, pc) {
+                Ok(instruction) => {
+                    let next = pc + instruction.length(pc);
+                    instructions.push(DecodedInstruction { op: self.decode_op(class, instruction), next });
+                    pc = next;
+                },
+                Err(error) => {
+                    instructions.push(DecodedInstruction { op: Op::Invalid(error.to_string()), next: pc });
+                    break;
+                }
+            }
+        }
+
+        DecodedCode {
+            instructions,
+            index,
+            #[cfg(feature = "jit")]
+            jit: JitState::default()
+        }
+    }
+
+    fn decode_op(&self, class: &java::Class, instruction: Instruction) -> Op {
+        match instruction {
+            Instruction::Simple(opcode) => {
+                let code = opcode as u8;
+
+                match opcode {
+                    Opcode::aconst_null => Op::Const(Value::null()),
+                    Opcode::iconst_m1 | Opcode::iconst_0 | Opcode::iconst_1 | Opcode::iconst_2 |
+                    Opcode::iconst_3 | Opcode::iconst_4 | Opcode::iconst_5 => Op::Const(Value::from_int(code as i32 - Opcode::iconst_0 as i32)),
+                    Opcode::lconst_0 | Opcode::lconst_1 => Op::Const(Value::from_long((code - Opcode::lconst_0 as u8) as i64)),
+                    Opcode::fconst_0 | Opcode::fconst_1 | Opcode::fconst_2 => Op::Const(Value::from_float((code - Opcode::fconst_0 as u8) as f32)),
+                    Opcode::dconst_0 | Opcode::dconst_1 => Op::Const(Value::from_double((code - Opcode::dconst_0 as u8) as f64)),
+
+                    _ if (Opcode::iload_0 as u8..=Opcode::aload_3 as u8).contains(&code) => Op::Load(((code - Opcode::iload_0 as u8) % 4) as u16),
+                    _ if (Opcode::istore_0 as u8..=Opcode::astore_3 as u8).contains(&code) => Op::Store(((code - Opcode::istore_0 as u8) % 4) as u16),
+
+                    _ => Op::Simple(opcode)
+                }
+            },
+            Instruction::Push(_, value) => Op::Const(Value::from_int(value as i32)),
+            Instruction::Local { opcode, index, .. } => match opcode {
+                Opcode::iload | Opcode::lload | Opcode::fload | Opcode::dload | Opcode::aload => Op::Load(index),
+                Opcode::ret => Op::Ret(index),
+                _ => Op::Store(index)
+            },
+            Instruction::Increment { index, value, .. } => Op::Increment(index, value as i32),
+            Instruction::ConstantPool(opcode, index) => match opcode {
+                Opcode::ldc | Opcode::ldc_w | Opcode::ldc2_w => match self.constant_value(class, index) {
+                    Some(value) => Op::Const(value),
+                    None => Op::Ldc(index, OnceLock::new())
+                },
+                Opcode::getstatic | Opcode::putstatic | Opcode::getfield | Opcode::putfield => Op::Field(opcode, index, OnceLock::new()),
+                Opcode::invokevirtual | Opcode::invokespecial | Opcode::invokestatic => Op::Invoke(opcode, index, OnceLock::new()),
+                Opcode::new => Op::New(index, OnceLock::new()),
+                Opcode::anewarray => Op::ANewArray(index, OnceLock::new()),
+                Opcode::checkcast | Opcode::instanceof => Op::TypeCheck(opcode, index, OnceLock::new()),
+                _ => Op::Invalid(format!("Unexpected opcode {}", opcode))
+            },
+            Instruction::InvokeInterface { index, .. } => Op::Invoke(Opcode::invokeinterface, index, OnceLock::new()),
+            Instruction::InvokeDynamic { index } => Op::InvokeDynamic(index),
+            Instruction::Branch(opcode, target) => Op::Branch(opcode, target as usize),
+            Instruction::TableSwitch { default, low, targets, .. } => Op::TableSwitch {
+                default: default as usize,
+                low,
+                targets: targets.into_iter().map(|target| target as usize).collect()
+            },
+            Instruction::LookupSwitch { default, pairs } => Op::LookupSwitch {
+                default: default as usize,
+                pairs: pairs.into_iter().map(|(key, target)| (key, target as usize)).collect()
+            },
+            Instruction::NewArray(array_type) => Op::NewArray(FieldType::parse(&array_type.descriptor().to_string()).expect("Invalid array type")),
+            Instruction::MultiANewArray { index, dimensions } => Op::MultiANewArray(index, dimensions)
+        }
+    }
+
+    /// Decodes the methods of a class file that have code, by the address of the method
+    pub fn decode_methods(&self, class: &java::Class) -> HashMap<usize, Arc<DecodedCode>> {
+        class.methods.values()
+            .filter_map(|method| method.code().map(|code| (Arc::as_ptr(method) as usize, Arc::new(self.decode_code(class, &code.code)))))
+            .collect()
+    }
+
+    /// The decoded code of a method of a class, decoded now if the class didn't decode it when it was linked
+    pub fn decoded_code(&mut self, class_id: ClassId, method: &Arc<java::Method>) -> Option<Arc<DecodedCode>> {
+        let key = Arc::as_ptr(method) as usize;
+        if let Some(code) = self.class(class_id).decoded_code.get(&key) {
+            return Some(code.clone());
+        }
+
+        let class = self.class(class_id).class.clone()?;
+        let code = Arc::new(self.decode_code(&class, &method.code()?.code));
+        self.classes[class_id as usize].decoded_code.insert(key, code.clone());
+
+        Some(code)
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,147 @@
+#![allow(dead_code)]
+
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    Object(String),
+    Array(Box<FieldType>)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<FieldType>,
+    pub return_type: Option<FieldType>
+}
+
+impl FieldType {
+
+    fn parse_from(descriptor: &str) -> Option<(Self, &str)> {
+        let mut chars = descriptor.chars();
+        let field_type = match chars.next()? {
+            'B' => FieldType::Byte,
+            'C' => FieldType::Char,
+            'D' => FieldType::Double,
+            'F' => FieldType::Float,
+            'I' => FieldType::Int,
+            'J' => FieldType::Long,
+            'S' => FieldType::Short,
+            'Z' => FieldType::Boolean,
+            'L' => {
+                let (name, rest) = descriptor[1..].split_once(';')?;
+                if name.is_empty() { return None; }
+
+                return Some((FieldType::Object(name.to_string()), rest));
+            },
+            '[' => {
+                let (component, rest) = Self::parse_from(&descriptor[1..])?;
+
+                return Some((FieldType::Array(Box::new(component)), rest));
+            },
+            _ => return None
+        };
+
+        Some((field_type, chars.as_str()))
+    }
+
+    pub fn parse(descriptor: &str) -> Option<Self> {
+        match Self::parse_from(descriptor)? {
+            (field_type, "") => Some(field_type),
+            _ => None
+        }
+    }
+
+    /// Number of local variable or operand stack slots a value of this type occupies
+    pub fn slots(&self) -> usize {
+        match self {
+            FieldType::Long | FieldType::Double => 2,
+            _ => 1
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, FieldType::Object(_) | FieldType::Array(_))
+    }
+
+    pub fn descriptor(&self) -> String {
+        match self {
+            FieldType::Byte         => "B".to_string(),
+            FieldType::Char         => "C".to_string(),
+            FieldType::Double       => "D".to_string(),
+            FieldType::Float        => "F".to_string(),
+            FieldType::Int          => "I".to_string(),
+            FieldType::Long         => "J".to_string(),
+            FieldType::Short        => "S".to_string(),
+            FieldType::Boolean      => "Z".to_string(),
+            FieldType::Object(name) => format!("L{};", name),
+            FieldType::Array(component) => format!("[{}", component.descriptor())
+        }
+    }
+
+    /// Name of the class of values of this reference type, e.g. `java/lang/String` or `[I`
+    pub fn class_name(&self) -> String {
+        match self {
+            FieldType::Object(name) => name.clone(),
+            _                       => self.descriptor()
+        }
+    }
+
+}
+
+impl MethodDescriptor {
+
+    pub fn parse(descriptor: &str) -> Option<Self> {
+        let mut rest = descriptor.strip_prefix('(')?;
+
+        let mut parameters = vec![];
+        while !rest.starts_with(')') {
+            let (parameter, remaining) = FieldType::parse_from(rest)?;
+            parameters.push(parameter);
+            rest = remaining;
+        }
+
+        let return_type = match &rest[1..] {
+            "V" => None,
+            return_type => Some(FieldType::parse(return_type)?)
+        };
+
+        Some(MethodDescriptor {
+            parameters,
+            return_type
+        })
+    }
+
+    /// Number of local variable slots taken up by the parameters, not including `this`
+    pub fn parameter_slots(&self) -> usize {
+        self.parameters.iter().map(|parameter| parameter.slots()).sum()
+    }
+
+}
+
+impl fmt::Display for FieldType {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Byte         => write!(f, "byte"),
+            FieldType::Char         => write!(f, "char"),
+            FieldType::Double       => write!(f, "double"),
+            FieldType::Float        => write!(f, "float"),
+            FieldType::Int          => write!(f, "int"),
+            FieldType::Long         => write!(f, "long"),
+            FieldType::Short        => write!(f, "short"),
+            FieldType::Boolean      => write!(f, "boolean"),
+            FieldType::Object(name) => write!(f, "{}", name.replace('/', ".")),
+            FieldType::Array(component) => write!(f, "{}[]", component)
+        }
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,682 @@
+#![allow(dead_code)]
+
+use std::fmt;
+use std::fmt::Write;
+
+use crate::java::access_flags;
+use crate::java::access_flags::FlagTarget;
+use crate::java::attribute::{Annotation, ElementValue, StackMapFrame, StackMapFrameBody, VerificationTypeInfo};
+use crate::java::class::{ConstantPoolEntry, FieldInfo, MethodInfo};
+use crate::java::descriptor::{FieldType, MethodDescriptor};
+use crate::java::instruction;
+use crate::java::instruction::Instruction;
+use crate::java::{Attribute, AttributeInfo, ClassFile};
+
+/// Produces a `javap -c -v` style listing of a class file
+pub struct Disassembler<'a> {
+    class_file: &'a ClassFile,
+    output: String
+}
+
+pub fn disassemble(class_file: &ClassFile) -> String {
+    let mut disassembler = Disassembler { class_file, output: String::new() };
+
+    // Writing into a String never fails
+    disassembler.write_class().unwrap();
+
+    disassembler.output
+}
+
+/// An instruction on a single line, e.g. `invokevirtual #7 // Method java/io/PrintStream.println:(I)V`
+pub fn describe_instruction(class_file: &ClassFile, instruction: &Instruction) -> String {
+    let disassembler = Disassembler { class_file, output: String::new() };
+    let mnemonic = instruction.opcode().to_string();
+
+    match instruction {
+        Instruction::Simple(_) => mnemonic,
+        Instruction::Push(_, value) => format!("{} {}", mnemonic, value),
+        Instruction::Local { index, .. } => format!("{} {}", mnemonic, index),
+        Instruction::Increment { index, value, .. } => format!("{} {}, {}", mnemonic, index, value),
+        Instruction::ConstantPool(_, index) |
+        Instruction::InvokeInterface { index, .. } |
+        Instruction::InvokeDynamic { index } |
+        Instruction::MultiANewArray { index, .. } => format!("{} #{} // {}", mnemonic, index, disassembler.operand_comment(*index)),
+        Instruction::Branch(_, target) => format!("{} {}", mnemonic, target),
+        Instruction::TableSwitch { low, high, .. } => format!("{} {} to {}", mnemonic, low, high),
+        Instruction::LookupSwitch { pairs, .. } => format!("{} {}", mnemonic, pairs.len()),
+        Instruction::NewArray(array_type) => {
+            let element_type = FieldType::parse(&array_type.descriptor().to_string()).map(|field_type| field_type.to_string()).unwrap_or_default();
+            format!("{} {}", mnemonic, element_type)
+        }
+    }
+}
+
+fn reference_kind_name(kind: u8) -> &'static str {
+    match kind {
+        1 => "REF_getField",
+        2 => "REF_getStatic",
+        3 => "REF_putField",
+        4 => "REF_putStatic",
+        5 => "REF_invokeVirtual",
+        6 => "REF_invokeStatic",
+        7 => "REF_invokeSpecial",
+        8 => "REF_newInvokeSpecial",
+        9 => "REF_invokeInterface",
+        _ => "REF_unknown"
+    }
+}
+
+/// Escapes control characters the way javap does, e.g. `\u0001`
+fn escape(string: &str) -> String {
+    let mut result = String::new();
+
+    for character in string.chars() {
+        match character {
+            '\t' => result.push_str("\\t"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '"'  => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            character if character.is_control() => result.push_str(&format!("\\u{:04x}", character as u32)),
+            character => result.push(character)
+        }
+    }
+
+    result
+}
+
+fn quote_special_name(name: String) -> String {
+    if name.starts_with('<') || name.starts_with('[') {
+        format!("\"{}\"", name)
+    } else {
+        name
+    }
+}
+
+impl<'a> Disassembler<'a> {
+
+    fn utf8(&self, index: u16) -> String {
+        self.class_file.get_constant_pool_string(index as usize).unwrap_or_else(|| format!("<invalid #{}>", index))
+    }
+
+    fn entry(&self, index: u16) -> Option<&'a ConstantPoolEntry> {
+        if index == 0 { return None; }
+
+        self.class_file.constant_pool.get(index as usize - 1)
+    }
+
+    fn class_name(&self, index: u16) -> String {
+        match self.entry(index) {
+            Some(ConstantPoolEntry::ClassReference(name_index)) => self.utf8(*name_index),
+            _ => format!("<invalid #{}>", index)
+        }
+    }
+
+    fn this_class_name(&self) -> String {
+        self.class_name(self.class_file.this_class)
+    }
+
+    fn name_and_type(&self, index: u16) -> String {
+        match self.entry(index) {
+            Some(entry @ ConstantPoolEntry::NameAndTypeDescriptor(_, _)) => self.resolve(entry).unwrap_or_default(),
+            _ => format!("<invalid #{}>", index)
+        }
+    }
+
+    /// Member reference, leaving out the owner if it's the class being disassembled
+    fn member_reference(&self, class_index: u16, name_and_type_index: u16) -> String {
+        let class_name = self.class_name(class_index);
+        if class_name == self.this_class_name() {
+            self.name_and_type(name_and_type_index)
+        } else {
+            format!("{}.{}", quote_special_name(class_name), self.name_and_type(name_and_type_index))
+        }
+    }
+
+    fn entry_kind(entry: &ConstantPoolEntry) -> &'static str {
+        match entry {
+            ConstantPoolEntry::None()                           => "",
+            ConstantPoolEntry::String { .. }                    => "Utf8",
+            ConstantPoolEntry::Integer(_)                       => "Integer",
+            ConstantPoolEntry::Float(_)                         => "Float",
+            ConstantPoolEntry::Long(_, _)                       => "Long",
+            ConstantPoolEntry::Double(_, _)                     => "Double",
+            ConstantPoolEntry::ClassReference(_)                => "Class",
+            ConstantPoolEntry::StringReference(_)               => "String",
+            ConstantPoolEntry::FieldReference(_, _)             => "Fieldref",
+            ConstantPoolEntry::MethodReference(_, _)            => "Methodref",
+            ConstantPoolEntry::InterfaceMethodReference(_, _)   => "InterfaceMethodref",
+            ConstantPoolEntry::NameAndTypeDescriptor(_, _)      => "NameAndType",
+            ConstantPoolEntry::MethodHandle(_, _)               => "MethodHandle",
+            ConstantPoolEntry::MethodType(_)                    => "MethodType",
+            ConstantPoolEntry::Dynamic(_, _)                    => "Dynamic",
+            ConstantPoolEntry::InvokeDynamic(_, _)              => "InvokeDynamic",
+            ConstantPoolEntry::Module(_)                        => "Module",
+            ConstantPoolEntry::Package(_)                       => "Package"
+        }
+    }
+
+    fn literal(entry: &ConstantPoolEntry) -> Option<String> {
+        match entry {
+            ConstantPoolEntry::Integer(value)       => Some(format!("{}", *value as i32)),
+            ConstantPoolEntry::Float(value)         => Some(format!("{:?}f", f32::from_bits(*value))),
+            ConstantPoolEntry::Long(high, low)      => Some(format!("{}l", ((*high as u64) << 32 | *low as u64) as i64)),
+            ConstantPoolEntry::Double(high, low)    => Some(format!("{:?}d", f64::from_bits((*high as u64) << 32 | *low as u64))),
+            _ => None
+        }
+    }
+
+    /// Raw operands of a constant pool entry as they appear in the pool, e.g. `#2.#3`
+    fn entry_operands(&self, entry: &ConstantPoolEntry) -> String {
+        match entry {
+            ConstantPoolEntry::String { string, .. }            => escape(&String::from_utf8_lossy(string)),
+            ConstantPoolEntry::ClassReference(index) |
+            ConstantPoolEntry::StringReference(index) |
+            ConstantPoolEntry::MethodType(index) |
+            ConstantPoolEntry::Module(index) |
+            ConstantPoolEntry::Package(index)                   => format!("#{}", index),
+            ConstantPoolEntry::FieldReference(class, name_and_type) |
+            ConstantPoolEntry::MethodReference(class, name_and_type) |
+            ConstantPoolEntry::InterfaceMethodReference(class, name_and_type) => format!("#{}.#{}", class, name_and_type),
+            ConstantPoolEntry::NameAndTypeDescriptor(name, descriptor) => format!("#{}:#{}", name, descriptor),
+            ConstantPoolEntry::MethodHandle(kind, index)        => format!("{}:#{}", kind, index),
+            ConstantPoolEntry::Dynamic(bootstrap, name_and_type) |
+            ConstantPoolEntry::InvokeDynamic(bootstrap, name_and_type) => format!("#{}:#{}", bootstrap, name_and_type),
+            _ => Self::literal(entry).unwrap_or_default()
+        }
+    }
+
+    /// Human readable value of a constant pool entry with all references followed
+    fn resolve(&self, entry: &ConstantPoolEntry) -> Option<String> {
+        let result = match entry {
+            ConstantPoolEntry::ClassReference(index)            => quote_special_name(self.utf8(*index)),
+            ConstantPoolEntry::StringReference(index)           => escape(&self.utf8(*index)),
+            ConstantPoolEntry::MethodType(index)                => self.utf8(*index),
+            ConstantPoolEntry::Module(index) |
+            ConstantPoolEntry::Package(index)                   => self.utf8(*index),
+            ConstantPoolEntry::FieldReference(class, name_and_type) |
+            ConstantPoolEntry::MethodReference(class, name_and_type) |
+            ConstantPoolEntry::InterfaceMethodReference(class, name_and_type) =>
+                format!("{}.{}", quote_special_name(self.class_name(*class)), self.name_and_type(*name_and_type)),
+            ConstantPoolEntry::NameAndTypeDescriptor(name_index, type_index) =>
+                format!("{}:{}", quote_special_name(self.utf8(*name_index)), self.utf8(*type_index)),
+            ConstantPoolEntry::MethodHandle(kind, index)        => {
+                let reference = self.entry(*index).and_then(|entry| self.resolve(entry)).unwrap_or_default();
+                format!("{} {}", reference_kind_name(*kind), reference)
+            },
+            ConstantPoolEntry::Dynamic(bootstrap, name_and_type) |
+            ConstantPoolEntry::InvokeDynamic(bootstrap, name_and_type) =>
+                format!("#{}:{}", bootstrap, self.name_and_type(*name_and_type)),
+            _ => return None
+        };
+
+        Some(result)
+    }
+
+    /// Comment printed next to an instruction referencing the constant pool
+    fn operand_comment(&self, index: u16) -> String {
+        let entry = match self.entry(index) {
+            Some(entry) => entry,
+            None => return format!("<invalid #{}>", index)
+        };
+
+        match entry {
+            ConstantPoolEntry::FieldReference(class, name_and_type)     => format!("Field {}", self.member_reference(*class, *name_and_type)),
+            ConstantPoolEntry::MethodReference(class, name_and_type)    => format!("Method {}", self.member_reference(*class, *name_and_type)),
+            ConstantPoolEntry::InterfaceMethodReference(class, name_and_type) => format!("InterfaceMethod {}", self.member_reference(*class, *name_and_type)),
+            ConstantPoolEntry::ClassReference(_)                        => format!("class {}", self.resolve(entry).unwrap_or_default()),
+            ConstantPoolEntry::StringReference(_)                       => format!("String {}", self.resolve(entry).unwrap_or_default()),
+            ConstantPoolEntry::Integer(_)                               => format!("int {}", Self::literal(entry).unwrap_or_default()),
+            ConstantPoolEntry::Float(_)                                 => format!("float {}", Self::literal(entry).unwrap_or_default()),
+            ConstantPoolEntry::Long(_, _)                               => format!("long {}", Self::literal(entry).unwrap_or_default()),
+            ConstantPoolEntry::Double(_, _)                             => format!("double {}", Self::literal(entry).unwrap_or_default()),
+            ConstantPoolEntry::MethodType(_)                            => format!("MethodType {}", self.resolve(entry).unwrap_or_default()),
+            ConstantPoolEntry::MethodHandle(_, _)                       => format!("MethodHandle {}", self.resolve(entry).unwrap_or_default()),
+            ConstantPoolEntry::Dynamic(_, _)                            => format!("Dynamic {}", self.resolve(entry).unwrap_or_default()),
+            ConstantPoolEntry::InvokeDynamic(_, _)                      => format!("InvokeDynamic {}", self.resolve(entry).unwrap_or_default()),
+            _ => self.resolve(entry).unwrap_or_default()
+        }
+    }
+
+    fn flags(access_flags: u16, target: FlagTarget) -> String {
+        format!("(0x{:04x}) {}", access_flags, access_flags::flag_names(access_flags, target).join(", "))
+    }
+
+    fn attribute_name(&self, attribute_info: &AttributeInfo) -> String {
+        self.utf8(attribute_info.attribute_name_index)
+    }
+
+    fn write_class(&mut self) -> fmt::Result {
+        let class_file = self.class_file;
+
+        let source_file = class_file.attribute_table.iter()
+            .find(|attribute_info| self.attribute_name(attribute_info) == "SourceFile")
+            .and_then(|attribute_info| attribute_info.info.get(0..2))
+            .map(|index| self.utf8((index[0] as u16) << 8 | index[1] as u16));
+        if let Some(source_file) = source_file {
+            writeln!(self.output, "  Compiled from \"{}\"", source_file)?;
+        }
+
+        let is_interface = class_file.access_flags & access_flags::ACC_INTERFACE != 0;
+        let mut declaration = access_flags::modifiers(class_file.access_flags, FlagTarget::Class);
+        if !declaration.is_empty() { declaration.push(' '); }
+        declaration.push_str(if is_interface { "interface " } else { "class " });
+        declaration.push_str(&self.this_class_name().replace('/', "."));
+
+        if class_file.super_class != 0 && !is_interface {
+            let super_class = self.class_name(class_file.super_class);
+            if super_class != "java/lang/Object" {
+                write!(declaration, " extends {}", super_class.replace('/', "."))?;
+            }
+        }
+
+        if !class_file.interface_table.is_empty() {
+            let interfaces: Vec<String> = class_file.interface_table.iter().map(|index| self.class_name(*index).replace('/', ".")).collect();
+            write!(declaration, " {} {}", if is_interface { "extends" } else { "implements" }, interfaces.join(","))?;
+        }
+
+        writeln!(self.output, "{}", declaration)?;
+        writeln!(self.output, "  minor version: {}", class_file.minor_version)?;
+        writeln!(self.output, "  major version: {}", class_file.major_version)?;
+        writeln!(self.output, "  flags: {}", Self::flags(class_file.access_flags, FlagTarget::Class))?;
+        writeln!(self.output, "  {:<40}// {}", format!("this_class: #{}", class_file.this_class), self.this_class_name())?;
+        if class_file.super_class != 0 {
+            writeln!(self.output, "  {:<40}// {}", format!("super_class: #{}", class_file.super_class), self.class_name(class_file.super_class))?;
+        } else {
+            writeln!(self.output, "  super_class: #0")?;
+        }
+        writeln!(self.output, "  interfaces: {}, fields: {}, methods: {}, attributes: {}",
+            class_file.interface_count, class_file.field_count, class_file.method_count, class_file.attribute_count)?;
+
+        self.write_constant_pool()?;
+
+        writeln!(self.output, "{{")?;
+        for (index, field_info) in class_file.field_table.iter().enumerate() {
+            if index != 0 { writeln!(self.output)?; }
+            self.write_field(field_info)?;
+        }
+        for (index, method_info) in class_file.method_table.iter().enumerate() {
+            if index != 0 || !class_file.field_table.is_empty() { writeln!(self.output)?; }
+            self.write_method(method_info)?;
+        }
+        writeln!(self.output, "}}")?;
+
+        for attribute_info in &class_file.attribute_table {
+            self.write_attribute(attribute_info, "")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_constant_pool(&mut self) -> fmt::Result {
+        writeln!(self.output, "Constant pool:")?;
+
+        let width = format!("#{}", self.class_file.constant_pool.len()).len();
+        for (index, entry) in self.class_file.constant_pool.iter().enumerate() {
+            if let ConstantPoolEntry::None() = entry { continue; }
+
+            let line = format!("{:>width$} = {:<18} {}", format!("#{}", index + 1), Self::entry_kind(entry), self.entry_operands(entry), width = width + 2);
+            match self.resolve(entry) {
+                Some(comment) => writeln!(self.output, "{:<width$} // {}", line, comment, width = width + 37)?,
+                None => writeln!(self.output, "{}", line)?
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_field(&mut self, field_info: &FieldInfo) -> fmt::Result {
+        let descriptor = self.utf8(field_info.descriptor_index);
+        let field_type = FieldType::parse(&descriptor).map(|field_type| field_type.to_string()).unwrap_or_else(|| descriptor.clone());
+
+        let mut modifiers = access_flags::modifiers(field_info.access_flags, FlagTarget::Field);
+        if !modifiers.is_empty() { modifiers.push(' '); }
+
+        writeln!(self.output, "  {}{} {};", modifiers, field_type, self.utf8(field_info.name_index))?;
+        writeln!(self.output, "    descriptor: {}", descriptor)?;
+        writeln!(self.output, "    flags: {}", Self::flags(field_info.access_flags, FlagTarget::Field))?;
+
+        for attribute_info in &field_info.attributes {
+            self.write_attribute(attribute_info, "    ")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_method(&mut self, method_info: &MethodInfo) -> fmt::Result {
+        let name = self.utf8(method_info.name_index);
+        let descriptor = self.utf8(method_info.descriptor_index);
+
+        let mut modifiers = access_flags::modifiers(method_info.access_flags, FlagTarget::Method);
+        if !modifiers.is_empty() { modifiers.push(' '); }
+
+        let mut throws = String::new();
+        for attribute_info in method_info.attributes.iter().filter(|attribute_info| self.attribute_name(attribute_info) == "Exceptions") {
+            if let Some(Attribute::Exceptions(attribute)) = Attribute::new(self.class_file, attribute_info) {
+                let exceptions: Vec<String> = attribute.exception_index_table.iter().map(|index| self.class_name(*index).replace('/', ".")).collect();
+                throws = format!(" throws {}", exceptions.join(", "));
+            }
+        }
+
+        match MethodDescriptor::parse(&descriptor) {
+            Some(method_descriptor) => {
+                let parameters: Vec<String> = method_descriptor.parameters.iter().map(|parameter| parameter.to_string()).collect();
+                let return_type = method_descriptor.return_type.map(|return_type| return_type.to_string()).unwrap_or_else(|| "void".to_string());
+
+                match name.as_str() {
+                    "<clinit>"  => writeln!(self.output, "  static {{}};")?,
+                    "<init>"    => writeln!(self.output, "  {}{}({}){};", modifiers, self.this_class_name().replace('/', "."), parameters.join(", "), throws)?,
+                    _           => writeln!(self.output, "  {}{} {}({}){};", modifiers, return_type, name, parameters.join(", "), throws)?
+                }
+            },
+            None => writeln!(self.output, "  {}{};", modifiers, name)?
+        }
+
+        writeln!(self.output, "    descriptor: {}", descriptor)?;
+        writeln!(self.output, "    flags: {}", Self::flags(method_info.access_flags, FlagTarget::Method))?;
+
+        let args_size = MethodDescriptor::parse(&descriptor).map(|method_descriptor| method_descriptor.parameter_slots()).unwrap_or(0)
+            + if method_info.access_flags & access_flags::ACC_STATIC == 0 { 1 } else { 0 };
+
+        for attribute_info in &method_info.attributes {
+            if self.attribute_name(attribute_info) == "Code" {
+                if let Some(Attribute::Code(code)) = Attribute::new(self.class_file, attribute_info) {
+                    self.write_code(&code, args_size)?;
+                    continue;
+                }
+            }
+
+            self.write_attribute(attribute_info, "    ")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_code(&mut self, code: &crate::java::attribute::AttributeCode, args_size: usize) -> fmt::Result {
+        writeln!(self.output, "    Code:")?;
+        writeln!(self.output, "      stack={}, locals={}, args_size={}", code.max_stack, code.max_locals, args_size)?;
+
+        match instruction::decode_with_offsets(&code.code) {
+            Ok(instructions) => {
+                for (pc, instruction) in &instructions {
+                    self.write_instruction(*pc, instruction)?;
+                }
+            },
+            Err(error) => writeln!(self.output, "        <{}>", error)?
+        }
+
+        if !code.exception_table.is_empty() {
+            writeln!(self.output, "      Exception table:")?;
+            writeln!(self.output, "         from    to  target type")?;
+            for entry in &code.exception_table {
+                let catch_type = if entry.catch_type == 0 {
+                    "any".to_string()
+                } else {
+                    format!("Class {}", self.class_name(entry.catch_type))
+                };
+
+                writeln!(self.output, "         {:>5} {:>5} {:>5}   {}", entry.start_pc, entry.end_pc, entry.handler_pc, catch_type)?;
+            }
+        }
+
+        for attribute_info in &code.attributes {
+            self.write_attribute(attribute_info, "      ")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_instruction(&mut self, pc: usize, instruction: &Instruction) -> fmt::Result {
+        let mnemonic = instruction.opcode().to_string();
+
+        match instruction {
+            Instruction::Simple(_) => writeln!(self.output, "{:>10}: {}", pc, mnemonic),
+            Instruction::Push(_, value) => writeln!(self.output, "{:>10}: {:<13} {}", pc, mnemonic, value),
+            Instruction::Local { index, .. } => writeln!(self.output, "{:>10}: {:<13} {}", pc, mnemonic, index),
+            Instruction::Increment { index, value, .. } => writeln!(self.output, "{:>10}: {:<13} {}, {}", pc, mnemonic, index, value),
+            Instruction::ConstantPool(_, index) =>
+                writeln!(self.output, "{:>10}: {:<13} {:<19} // {}", pc, mnemonic, format!("#{}", index), self.operand_comment(*index)),
+            Instruction::InvokeInterface { index, count } =>
+                writeln!(self.output, "{:>10}: {:<13} {:<19} // {}", pc, mnemonic, format!("#{},  {}", index, count), self.operand_comment(*index)),
+            Instruction::InvokeDynamic { index } =>
+                writeln!(self.output, "{:>10}: {:<13} {:<19} // {}", pc, mnemonic, format!("#{},  0", index), self.operand_comment(*index)),
+            Instruction::Branch(_, target) => writeln!(self.output, "{:>10}: {:<13} {}", pc, mnemonic, target),
+            Instruction::TableSwitch { default, low, high, targets } => {
+                writeln!(self.output, "{:>10}: {:<13} {{ // {} to {}", pc, mnemonic, low, high)?;
+                for (key, target) in (*low..=*high).zip(targets) {
+                    writeln!(self.output, "{:>24}: {}", key, target)?;
+                }
+                writeln!(self.output, "{:>24}: {}", "default", default)?;
+                writeln!(self.output, "            }}")
+            },
+            Instruction::LookupSwitch { default, pairs } => {
+                writeln!(self.output, "{:>10}: {:<13} {{ // {}", pc, mnemonic, pairs.len())?;
+                for (key, target) in pairs {
+                    writeln!(self.output, "{:>24}: {}", key, target)?;
+                }
+                writeln!(self.output, "{:>24}: {}", "default", default)?;
+                writeln!(self.output, "            }}")
+            },
+            Instruction::NewArray(array_type) => {
+                let element_type = FieldType::parse(&array_type.descriptor().to_string()).map(|field_type| field_type.to_string()).unwrap_or_default();
+                writeln!(self.output, "{:>10}: {:<14} {}", pc, mnemonic, element_type)
+            },
+            Instruction::MultiANewArray { index, dimensions } =>
+                writeln!(self.output, "{:>10}: {:<13} {:<19} // {}", pc, mnemonic, format!("#{},  {}", index, dimensions), self.operand_comment(*index))
+        }
+    }
+
+    fn element_value(&self, value: &ElementValue) -> String {
+        match value {
+            ElementValue::Byte { const_value_index } |
+            ElementValue::Char { const_value_index } |
+            ElementValue::Double { const_value_index } |
+            ElementValue::Float { const_value_index } |
+            ElementValue::Int { const_value_index } |
+            ElementValue::Long { const_value_index } |
+            ElementValue::Short { const_value_index } |
+            ElementValue::Boolean { const_value_index } |
+            ElementValue::String { const_value_index }    => format!("#{}", const_value_index),
+            ElementValue::Enum { type_name_index, const_name_index } => format!("#{}.#{}", type_name_index, const_name_index),
+            ElementValue::Class { class_info_index }      => format!("#{}", class_info_index),
+            ElementValue::AnnotationType { annotation_value } => format!("@{}", self.annotation(annotation_value)),
+            ElementValue::Array { element_value, .. }     => {
+                let values: Vec<String> = element_value.iter().map(|value| self.element_value(value)).collect();
+                format!("[{}]", values.join(","))
+            }
+        }
+    }
+
+    fn annotation(&self, annotation: &Annotation) -> String {
+        let pairs: Vec<String> = annotation.element_value_pairs.iter()
+            .map(|pair| format!("#{}={}", pair.element_name_index, self.element_value(&pair.value)))
+            .collect();
+
+        format!("#{}({})", annotation.type_index, pairs.join(","))
+    }
+
+    fn write_annotations(&mut self, annotations: &[Annotation], indent: &str) -> fmt::Result {
+        for (index, annotation) in annotations.iter().enumerate() {
+            writeln!(self.output, "{}  {}: {}", indent, index, self.annotation(annotation))?;
+            writeln!(self.output, "{}    {}", indent, FieldType::parse(&self.utf8(annotation.type_index)).map(|field_type| field_type.to_string()).unwrap_or_default())?;
+        }
+
+        Ok(())
+    }
+
+    fn write_attribute(&mut self, attribute_info: &AttributeInfo, indent: &str) -> fmt::Result {
+        let name = self.attribute_name(attribute_info);
+
+        let attribute = if Attribute::is_supported(&name) { Attribute::new(self.class_file, attribute_info) } else { None };
+        match attribute {
+            Some(Attribute::ConstantValue(attribute)) => {
+                writeln!(self.output, "{}ConstantValue: {}", indent, self.operand_comment(attribute.constantvalue_index))
+            },
+            Some(Attribute::Code(code)) => self.write_code(&code, 0),
+            Some(Attribute::Exceptions(attribute)) => {
+                writeln!(self.output, "{}Exceptions:", indent)?;
+                let exceptions: Vec<String> = attribute.exception_index_table.iter().map(|index| self.class_name(*index).replace('/', ".")).collect();
+                writeln!(self.output, "{}  throws {}", indent, exceptions.join(", "))
+            },
+            Some(Attribute::RuntimeVisibleAnnotations(attribute)) => {
+                writeln!(self.output, "{}RuntimeVisibleAnnotations:", indent)?;
+                self.write_annotations(&attribute.annotations, indent)
+            },
+            Some(Attribute::RuntimeInvisibleAnnotations(attribute)) => {
+                writeln!(self.output, "{}RuntimeInvisibleAnnotations:", indent)?;
+                self.write_annotations(&attribute.annotations, indent)
+            },
+            Some(Attribute::Signature(attribute)) =>
+                writeln!(self.output, "{}{:<40}// {}", indent, format!("Signature: #{}", attribute.signature_index), self.utf8(attribute.signature_index)),
+            Some(Attribute::Deprecated(_)) => writeln!(self.output, "{}Deprecated: true", indent),
+            Some(Attribute::AnnotationDefault(attribute)) => {
+                writeln!(self.output, "{}AnnotationDefault:", indent)?;
+                writeln!(self.output, "{}  default_value: {}", indent, self.element_value(&attribute.default_value))
+            },
+            Some(Attribute::MethodParameters(attribute)) => {
+                writeln!(self.output, "{}MethodParameters:", indent)?;
+                writeln!(self.output, "{}  Name                           Flags", indent)?;
+                for parameter in &attribute.parameters {
+                    let name = if parameter.name_index == 0 { "<no name>".to_string() } else { self.utf8(parameter.name_index) };
+                    writeln!(self.output, "{}  {:<30} {}", indent, name, access_flags::modifiers(parameter.access_flags, FlagTarget::Field))?;
+                }
+                Ok(())
+            },
+            Some(Attribute::LineNumberTable(attribute)) => {
+                writeln!(self.output, "{}LineNumberTable:", indent)?;
+                for line_number in &attribute.line_number_table {
+                    writeln!(self.output, "{}  line {}: {}", indent, line_number.line_number, line_number.start_pc)?;
+                }
+                Ok(())
+            },
+            Some(Attribute::StackMapTable(attribute)) => {
+                writeln!(self.output, "{}StackMapTable: number_of_entries = {}", indent, attribute.number_of_entries)?;
+                for frame in &attribute.entries {
+                    self.write_stack_map_frame(frame, indent)?;
+                }
+                Ok(())
+            },
+            Some(Attribute::BootstrapMethods(attribute)) => {
+                writeln!(self.output, "{}BootstrapMethods:", indent)?;
+                for (index, bootstrap_method) in attribute.bootstrap_methods.iter().enumerate() {
+                    let method_handle = self.entry(bootstrap_method.bootstrap_method_ref).and_then(|entry| self.resolve(entry)).unwrap_or_default();
+                    writeln!(self.output, "{}  {}: #{} {}", indent, index, bootstrap_method.bootstrap_method_ref, method_handle)?;
+                    writeln!(self.output, "{}    Method arguments:", indent)?;
+                    for argument in &bootstrap_method.bootstrap_arguments {
+                        let value = self.entry(*argument).and_then(|entry| self.resolve(entry).or_else(|| Self::literal(entry))).unwrap_or_default();
+                        writeln!(self.output, "{}      #{} {}", indent, argument, value)?;
+                    }
+                }
+                Ok(())
+            },
+            Some(Attribute::InnerClasses(attribute)) => {
+                writeln!(self.output, "{}InnerClasses:", indent)?;
+                for inner_class in &attribute.classes {
+                    let mut declaration = access_flags::modifiers(inner_class.inner_class_access_flags, FlagTarget::Field);
+                    if !declaration.is_empty() { declaration.push(' '); }
+                    let mut comment = String::new();
+
+                    if inner_class.inner_name_index != 0 {
+                        write!(declaration, "#{}= ", inner_class.inner_name_index)?;
+                        write!(comment, "{}=", self.utf8(inner_class.inner_name_index))?;
+                    }
+                    write!(declaration, "#{}", inner_class.inner_class_info_index)?;
+                    write!(comment, "class {}", self.class_name(inner_class.inner_class_info_index))?;
+
+                    if inner_class.outer_class_info_index != 0 {
+                        write!(declaration, " of #{}", inner_class.outer_class_info_index)?;
+                        write!(comment, " of class {}", self.class_name(inner_class.outer_class_info_index))?;
+                    }
+
+                    writeln!(self.output, "{}  {:<40}// {}", indent, format!("{};", declaration), comment)?;
+                }
+                Ok(())
+            },
+            Some(Attribute::EnclosingMethod(attribute)) => {
+                let mut comment = self.class_name(attribute.class_index);
+                if attribute.method_index != 0 {
+                    let name = self.entry(attribute.method_index).and_then(|entry| self.resolve(entry)).unwrap_or_default();
+                    write!(comment, ".{}", name.split(':').next().unwrap_or_default())?;
+                }
+
+                writeln!(self.output, "{}{:<40}// {}", indent, format!("EnclosingMethod: #{}.#{}", attribute.class_index, attribute.method_index), comment)
+            },
+            Some(Attribute::SourceFile(attribute)) => {
+                writeln!(self.output, "{}SourceFile: \"{}\"", indent, self.utf8(attribute.sourcefile_index))
+            },
+            Some(Attribute::NestHost(attribute)) => {
+                writeln!(self.output, "{}NestHost: class {}", indent, self.class_name(attribute.host_class_index))
+            },
+            Some(Attribute::NestMembers(attribute)) => {
+                writeln!(self.output, "{}NestMembers:", indent)?;
+                for class_index in &attribute.classes {
+                    writeln!(self.output, "{}  {}", indent, self.class_name(*class_index))?;
+                }
+                Ok(())
+            },
+            None => {
+                write!(self.output, "{}{}: length = 0x{:X}", indent, name, attribute_info.attribute_length)?;
+                for (index, byte) in attribute_info.info.iter().enumerate() {
+                    if index % 16 == 0 { write!(self.output, "\n{}  ", indent)?; }
+                    write!(self.output, " {:02X}", byte)?;
+                }
+                writeln!(self.output)
+            }
+        }
+    }
+
+    fn verification_type(&self, verification_type: &VerificationTypeInfo) -> String {
+        match verification_type {
+            VerificationTypeInfo::Top()                 => "top".to_string(),
+            VerificationTypeInfo::Integer()             => "int".to_string(),
+            VerificationTypeInfo::Float()               => "float".to_string(),
+            VerificationTypeInfo::Double()              => "double".to_string(),
+            VerificationTypeInfo::Long()                => "long".to_string(),
+            VerificationTypeInfo::Null()                => "null".to_string(),
+            VerificationTypeInfo::UninitializedThis()   => "this".to_string(),
+            VerificationTypeInfo::Object(index)         => format!("class {}", self.class_name(*index)),
+            VerificationTypeInfo::Uninitialized(offset) => format!("uninitialized {}", offset)
+        }
+    }
+
+    fn verification_types(&self, verification_types: &[VerificationTypeInfo]) -> String {
+        let types: Vec<String> = verification_types.iter().map(|verification_type| self.verification_type(verification_type)).collect();
+
+        format!("[ {} ]", types.join(", "))
+    }
+
+    fn write_stack_map_frame(&mut self, frame: &StackMapFrame, indent: &str) -> fmt::Result {
+        match &frame.body {
+            StackMapFrameBody::Same() => writeln!(self.output, "{}  frame_type = {} /* same */", indent, frame.frame_type),
+            StackMapFrameBody::SameLocals1StackItem { stack } => {
+                writeln!(self.output, "{}  frame_type = {} /* same_locals_1_stack_item */", indent, frame.frame_type)?;
+                writeln!(self.output, "{}    stack = {}", indent, self.verification_types(std::slice::from_ref(stack)))
+            },
+            StackMapFrameBody::SameLocals1StackItemExtended { offset_delta, stack } => {
+                writeln!(self.output, "{}  frame_type = {} /* same_locals_1_stack_item_frame_extended */", indent, frame.frame_type)?;
+                writeln!(self.output, "{}    offset_delta = {}", indent, offset_delta)?;
+                writeln!(self.output, "{}    stack = {}", indent, self.verification_types(std::slice::from_ref(stack)))
+            },
+            StackMapFrameBody::Chop { offset_delta } => {
+                writeln!(self.output, "{}  frame_type = {} /* chop */", indent, frame.frame_type)?;
+                writeln!(self.output, "{}    offset_delta = {}", indent, offset_delta)
+            },
+            StackMapFrameBody::SameExtended { offset_delta } => {
+                writeln!(self.output, "{}  frame_type = {} /* same_frame_extended */", indent, frame.frame_type)?;
+                writeln!(self.output, "{}    offset_delta = {}", indent, offset_delta)
+            },
+            StackMapFrameBody::Append { offset_delta, locals } => {
+                writeln!(self.output, "{}  frame_type = {} /* append */", indent, frame.frame_type)?;
+                writeln!(self.output, "{}    offset_delta = {}", indent, offset_delta)?;
+                writeln!(self.output, "{}    locals = {}", indent, self.verification_types(locals))
+            },
+            StackMapFrameBody::Full { offset_delta, locals, stack, .. } => {
+                writeln!(self.output, "{}  frame_type = {} /* full_frame */", indent, frame.frame_type)?;
+                writeln!(self.output, "{}    offset_delta = {}", indent, offset_delta)?;
+                writeln!(self.output, "{}    locals = {}", indent, self.verification_types(locals))?;
+                writeln!(self.output, "{}    stack = {}", indent, self.verification_types(stack))
+            }
+        }
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,243 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::java;
+use crate::java::decoded_code::ResolvedMethod;
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::VmContext;
+
+/// Marks methods without a vtable slot, in `ResolvedMethod::slot` and in itables
+pub const NO_SLOT: u32 = u32::MAX;
+
+/// Receiver classes a call site caches before it turns megamorphic
+const INLINE_CACHE_SIZE: usize = 4;
+
+/// An unused entry of an inline cache, no class has all bits of its id set
+const EMPTY_ENTRY: u64 = u64::MAX;
+
+/// The methods objects of a class respond to, built when the class is linked.
+/// Each entry is what `VmContext::select_method` selects for its name and descriptor.
+#[derive(Debug, Clone, Default)]
+pub struct DispatchTable {
+    /// Classes start with the slots of their super class, so a slot found in a class selects the method in any subclass.
+    /// Interfaces only have slots for their own methods and those of their super interfaces.
+    pub vtable: Vec<(ClassId, Arc<java::Method>)>,
+    /// Slots by method name and descriptor, e.g. `toString()Ljava/lang/String;`
+    pub slots: HashMap<String, u32>,
+    /// For every interface a class implements, its vtable slot of each slot of the interface's vtable, `NO_SLOT` if it has none
+    pub itables: Vec<(ClassId, Box<[u32]>)>
+}
+
+/// Counters of how virtual and interface calls found the method they invoke, see `VirtualMachine::dispatch_stats`
+#[derive(Debug, Clone, Default)]
+pub struct DispatchStats {
+    /// Calls whose receiver class was cached at a call site that has seen only that class
+    pub monomorphic_hits: u64,
+    /// Calls whose receiver class was cached at a call site that has seen several
+    pub polymorphic_hits: u64,
+    /// Calls with a receiver class the call site hadn't seen before, which it caches from then on
+    pub misses: u64,
+    /// Calls at call sites that have seen more receiver classes than they cache, which look up the method every time
+    pub megamorphic_calls: u64
+}
+
+/// Receiver classes seen at a call site together with the vtable slot of the method they selected.
+/// Entries pack the class id and the slot into one atomic, so that threads share the cache without locking.
+#[derive(Debug)]
+pub struct InlineCache {
+    entries: [AtomicU64; INLINE_CACHE_SIZE]
+}
+
+/// What an inline cache knows about a receiver class
+pub enum CacheLookup {
+    Monomorphic(u32),
+    Polymorphic(u32),
+    Miss,
+    Megamorphic
+}
+
+impl DispatchTable {
+
+    pub fn slot(&self, name: &str, descriptor: &str) -> Option<u32> {
+        self.slots.get(&format!("{}{}", name, descriptor)).copied()
+    }
+
+    /// The vtable slot of a slot of an interface's vtable
+    pub fn interface_slot(&self, interface: ClassId, slot: u32) -> Option<u32> {
+        let (_, slots) = self.itables.iter().find(|(implemented, _)| *implemented == interface)?;
+        slots.get(slot as usize).copied().filter(|slot| *slot != NO_SLOT)
+    }
+
+    fn insert(&mut self, entry: (ClassId, Arc<java::Method>)) {
+        let key = format!("{}{}", entry.1.name, entry.1.descriptor);
+
+        match self.slots.get(&key) {
+            Some(&slot) => self.vtable[slot as usize] = entry,
+            None => {
+                self.slots.insert(key, self.vtable.len() as u32);
+                self.vtable.push(entry);
+            }
+        }
+    }
+
+}
+
+impl InlineCache {
+
+    pub fn new() -> Self {
+        InlineCache { entries: std::array::from_fn(|_| AtomicU64::new(EMPTY_ENTRY)) }
+    }
+
+    pub fn lookup(&self, class_id: ClassId) -> CacheLookup {
+        for (index, entry) in self.entries.iter().enumerate() {
+            let entry = entry.load(Ordering::Relaxed);
+            if entry == EMPTY_ENTRY {
+                return CacheLookup::Miss;
+            }
+
+            if (entry >> 32) as ClassId == class_id {
+                let slot = entry as u32;
+
+                return match index == 0 && self.entries[1].load(Ordering::Relaxed) == EMPTY_ENTRY {
+                    true => CacheLookup::Monomorphic(slot),
+                    false => CacheLookup::Polymorphic(slot)
+                };
+            }
+        }
+
+        CacheLookup::Megamorphic
+    }
+
+    /// Caches the slot a receiver class selected, unless the cache is full
+    pub fn insert(&self, class_id: ClassId, slot: u32) {
+        let packed = (class_id as u64) << 32 | slot as u64;
+
+        for entry in &self.entries {
+            match entry.compare_exchange(EMPTY_ENTRY, packed, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => return,
+                // Another thread may have cached the same class meanwhile
+                Err(existing) if existing == packed => return,
+                Err(_) => { }
+            }
+        }
+    }
+
+}
+
+impl Default for InlineCache {
+
+    fn default() -> Self {
+        Self::new()
+    }
+
+}
+
+impl VmContext {
+
+    /// Builds the vtable and itables of a linked class, whose super class and interfaces have theirs already
+    pub fn link_dispatch_table(&mut self, class_id: ClassId) {
+        let class = self.class(class_id);
+        let is_interface = class.is_interface();
+
+        let mut table = match class.super_class {
+            Some(super_class) if !is_interface => {
+                let super_table = &self.class(super_class).dispatch;
+                DispatchTable { vtable: super_table.vtable.clone(), slots: super_table.slots.clone(), itables: vec![] }
+            },
+            _ => DispatchTable::default()
+        };
+
+        // Methods the class declares override inherited ones, methods inherited from interfaces are selected again
+        // as the interfaces of the class may provide a more specific default method
+        let mut methods: Vec<Arc<java::Method>> = class.class.iter()
+            .flat_map(|class| class.methods.values())
+            .filter(|method| !method.is_static() && !method.name.starts_with('<'))
+            .cloned()
+            .collect();
+
+        methods.extend(table.vtable.iter()
+            .filter(|(declaring_class, _)| self.class(*declaring_class).is_interface())
+            .map(|(_, method)| method.clone()));
+
+        for interface in &class.interfaces {
+            methods.extend(self.class(*interface).dispatch.vtable.iter().map(|(_, method)| method.clone()));
+        }
+
+        for method in methods {
+            if let Some(entry) = self.select_method(class_id, &method.name, &method.descriptor) {
+                table.insert(entry);
+            }
+        }
+
+        if !is_interface {
+            table.itables = self.all_interfaces(class_id).into_iter()
+                .map(|interface| {
+                    let slots = self.class(interface).dispatch.vtable.iter()
+                        .map(|(_, method)| table.slot(&method.name, &method.descriptor).unwrap_or(NO_SLOT))
+                        .collect();
+
+                    (interface, slots)
+                })
+                .collect();
+        }
+
+        self.classes[class_id as usize].dispatch = table;
+    }
+
+    /// The interfaces a class implements directly or through its super classes and super interfaces
+    fn all_interfaces(&self, class_id: ClassId) -> Vec<ClassId> {
+        let mut interfaces = vec![];
+        let mut seen = HashSet::new();
+        let mut pending = vec![];
+
+        let mut current = Some(class_id);
+        while let Some(class_id) = current {
+            pending.extend(self.class(class_id).interfaces.iter().rev());
+            current = self.class(class_id).super_class;
+        }
+
+        while let Some(interface) = pending.pop() {
+            if seen.insert(interface) {
+                interfaces.push(interface);
+                pending.extend(self.class(interface).interfaces.iter().rev());
+            }
+        }
+
+        interfaces
+    }
+
+    /// Looks up the receiver class of a call in the inline cache of the call site and counts the outcome in `dispatch_stats`
+    pub fn lookup_inline_cache(&mut self, cache: &InlineCache, receiver_class: ClassId) -> CacheLookup {
+        let lookup = cache.lookup(receiver_class);
+
+        let counter = match lookup {
+            CacheLookup::Monomorphic(_) => &mut self.dispatch_stats.monomorphic_hits,
+            CacheLookup::Polymorphic(_) => &mut self.dispatch_stats.polymorphic_hits,
+            CacheLookup::Miss => &mut self.dispatch_stats.misses,
+            CacheLookup::Megamorphic => &mut self.dispatch_stats.megamorphic_calls
+        };
+        *counter += 1;
+
+        lookup
+    }
+
+    /// The vtable slot of the method a virtual or interface call selects on a receiver of a class
+    pub fn select_slot(&self, receiver_class: ClassId, resolved: &ResolvedMethod) -> Option<u32> {
+        let table = &self.class(receiver_class).dispatch;
+
+        let slot = match resolved.slot {
+            NO_SLOT => None,
+            slot if self.class(resolved.class).is_interface() => table.interface_slot(resolved.class, slot),
+            slot => Some(slot)
+        };
+
+        // Receivers that aren't of the class the call site resolved, which unverified code may pass, look the method up by name
+        let (name, descriptor) = (&resolved.method.name, &resolved.method.descriptor);
+        slot.filter(|slot| table.vtable.get(*slot as usize).is_some_and(|(_, method)| method.name == *name && method.descriptor == *descriptor))
+            .or_else(|| table.slot(name, descriptor))
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,575 @@
+#![allow(dead_code)]
+
+use std::fmt;
+use std::fmt::Formatter;
+
+use crate::java::annotation::ResolvedAnnotation;
+use crate::java::descriptor::{FieldType, MethodDescriptor};
+use crate::java::reflection::declared_methods;
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::{Halt, Throwable, TraceFrame, Value, VirtualMachine};
+
+/// A loaded class, as returned by `VirtualMachine::load_class`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JClass(ClassId);
+
+/// A Java object handed out to Rust. The VM keeps it alive until it was passed to `VirtualMachine::release`
+/// as often as it was handed out, like a JNI global reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JObject(u32);
+
+/// A Java value passed to or returned from the VM
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JValue {
+    /// Returned by `void` methods
+    Void,
+    Boolean(bool),
+    Byte(i8),
+    Char(u16),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Object(JObject)
+}
+
+/// A method declared by a class, as returned by `VirtualMachine::declared_methods`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JMethod {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u16,
+    /// Runtime visible annotations, including the default values of the elements they leave out
+    pub annotations: Vec<ResolvedAnnotation>
+}
+
+/// A frame of the stack trace of a Java exception, innermost first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackTraceElement {
+    pub class_name: String,
+    pub method_name: String,
+    pub program_counter: usize,
+    /// Name of the source file, e.g. `Foo.java`, if the class file has a SourceFile attribute
+    pub file_name: Option<String>,
+    /// `None` if the class file has no line numbers
+    pub line_number: Option<u32>
+}
+
+/// An exception thrown by Java code and not caught before it got back to Rust
+#[derive(Debug, Clone)]
+pub struct JavaException {
+    /// The throwable object, null if the VM couldn't even create it
+    pub throwable: JObject,
+    /// Class name as written in Java source, e.g. `java.lang.IllegalStateException`
+    pub class_name: String,
+    pub message: Option<String>,
+    pub stack_trace: Vec<StackTraceElement>
+}
+
+#[derive(Debug, Clone)]
+pub enum JavaError {
+    Exception(JavaException),
+    /// A value had another type than the Java type it was used as, e.g. an argument not matching the method descriptor
+    TypeMismatch { expected: String, found: String },
+    ArgumentCount { expected: usize, found: usize },
+    /// The VM stopped running Java code, e.g. because of a deadlock
+    Halted(Halt)
+}
+
+impl JClass {
+
+    pub fn id(&self) -> ClassId {
+        self.0
+    }
+
+}
+
+impl JObject {
+
+    pub fn null() -> Self {
+        JObject(0)
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn reference(&self) -> u32 {
+        self.0
+    }
+
+}
+
+impl JValue {
+
+    /// Name of the Java type of the value, e.g. `int`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            JValue::Void        => "void",
+            JValue::Boolean(_)  => "boolean",
+            JValue::Byte(_)     => "byte",
+            JValue::Char(_)     => "char",
+            JValue::Short(_)    => "short",
+            JValue::Int(_)      => "int",
+            JValue::Long(_)     => "long",
+            JValue::Float(_)    => "float",
+            JValue::Double(_)   => "double",
+            JValue::Object(_)   => "object"
+        }
+    }
+
+}
+
+macro_rules! jvalue_conversions {
+    ($($rust_type:ty => $variant:ident ($java_type:literal)),*) => {
+        $(
+            impl From<$rust_type> for JValue {
+
+                fn from(value: $rust_type) -> Self {
+                    JValue::$variant(value)
+                }
+
+            }
+
+            impl TryFrom<JValue> for $rust_type {
+                type Error = JavaError;
+
+                fn try_from(value: JValue) -> Result<Self, Self::Error> {
+                    match value {
+                        JValue::$variant(value) => Ok(value),
+                        _ => Err(JavaError::TypeMismatch { expected: $java_type.to_string(), found: value.type_name().to_string() })
+                    }
+                }
+
+            }
+        )*
+    };
+}
+
+jvalue_conversions!(
+    bool => Boolean("boolean"),
+    i8 => Byte("byte"),
+    u16 => Char("char"),
+    i16 => Short("short"),
+    i32 => Int("int"),
+    i64 => Long("long"),
+    f32 => Float("float"),
+    f64 => Double("double")
+);
+
+impl From<JObject> for JValue {
+
+    fn from(object: JObject) -> Self {
+        JValue::Object(object)
+    }
+
+}
+
+impl TryFrom<JValue> for JObject {
+    type Error = JavaError;
+
+    fn try_from(value: JValue) -> Result<Self, Self::Error> {
+        match value {
+            JValue::Object(object) => Ok(object),
+            _ => Err(JavaError::TypeMismatch { expected: "object".to_string(), found: value.type_name().to_string() })
+        }
+    }
+
+}
+
+impl TryFrom<JValue> for () {
+    type Error = JavaError;
+
+    fn try_from(value: JValue) -> Result<Self, Self::Error> {
+        match value {
+            JValue::Void => Ok(()),
+            _ => Err(JavaError::TypeMismatch { expected: "void".to_string(), found: value.type_name().to_string() })
+        }
+    }
+
+}
+
+impl fmt::Display for StackTraceElement {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}(", self.class_name, self.method_name)?;
+
+        match (&self.file_name, self.line_number) {
+            (Some(file_name), Some(line_number)) => write!(f, "{}:{})", file_name, line_number),
+            (Some(file_name), None) => write!(f, "{})", file_name),
+            (None, _) => write!(f, "Unknown Source, pc {})", self.program_counter)
+        }
+    }
+
+}
+
+/// Formatted like `Throwable.printStackTrace` does
+impl fmt::Display for JavaException {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {}", self.class_name, message)?,
+            None => write!(f, "{}", self.class_name)?
+        }
+
+        for element in &self.stack_trace {
+            write!(f, "\n\tat {}", element)?;
+        }
+
+        Ok(())
+    }
+
+}
+
+impl fmt::Display for JavaError {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            JavaError::Exception(exception) => write!(f, "{}", exception),
+            JavaError::TypeMismatch { expected, found } => write!(f, "Expected a value of type {}, found {}", expected, found),
+            JavaError::ArgumentCount { expected, found } => write!(f, "Expected {} arguments, found {}", expected, found),
+            JavaError::Halted(halt) => write!(f, "The VM stopped: {}", Throwable::Halt(*halt))
+        }
+    }
+
+}
+
+impl std::error::Error for JavaError { }
+
+impl From<JavaException> for JavaError {
+
+    fn from(exception: JavaException) -> Self {
+        JavaError::Exception(exception)
+    }
+
+}
+
+fn type_mismatch(expected: &FieldType, found: JValue) -> JavaError {
+    JavaError::TypeMismatch { expected: expected.to_string(), found: found.type_name().to_string() }
+}
+
+/// Calling into Java from Rust. Classes are loaded from the jars added to the VM, exceptions thrown by Java code
+/// come back as `JavaError::Exception`, and objects returned to Rust stay alive until they are released.
+impl VirtualMachine {
+
+    pub fn load_class(&mut self, class_name: &str) -> Result<JClass, JavaError> {
+        match self.context.load_class(class_name) {
+            Ok(class_id) => Ok(JClass(class_id)),
+            Err(throwable) => Err(self.java_error(throwable))
+        }
+    }
+
+    /// Invokes a static method of a class or its super classes, initializing the declaring class first
+    pub fn invoke_static(&mut self, class: JClass, name: &str, descriptor: &str, args: &[JValue]) -> Result<JValue, JavaError> {
+        let method_descriptor = self.method_descriptor(class, name, descriptor)?;
+        let args = self.arguments(&method_descriptor.parameters, args)?;
+
+        let result = self.context.invoke_static(class.0, name, descriptor, &args);
+
+        self.returned(result, method_descriptor.return_type.as_ref())
+    }
+
+    /// Invokes an instance method, selecting the implementation by the class of the object like `invokevirtual` does
+    pub fn invoke_virtual(&mut self, object: JObject, name: &str, descriptor: &str, args: &[JValue]) -> Result<JValue, JavaError> {
+        let method_descriptor = match MethodDescriptor::parse(descriptor) {
+            Some(method_descriptor) => method_descriptor,
+            None => return Err(self.java_error(Throwable::new("java/lang/NoSuchMethodError", &format!("{}{}", name, descriptor))))
+        };
+
+        let args: Vec<Value> = std::iter::once(Value::Reference(object.0))
+            .chain(self.arguments(&method_descriptor.parameters, args)?)
+            .collect();
+
+        let result = self.context.with_roots(&args, |context| context.invoke_virtual(name, descriptor, &args));
+
+        self.returned(result, method_descriptor.return_type.as_ref())
+    }
+
+    /// Allocates an object and runs the constructor with the given descriptor on it
+    pub fn new_object(&mut self, class: JClass, descriptor: &str, args: &[JValue]) -> Result<JObject, JavaError> {
+        let method_descriptor = self.method_descriptor(class, "<init>", descriptor)?;
+        let args = self.arguments(&method_descriptor.parameters, args)?;
+
+        let result = self.context.with_roots(&args, |context| context.instantiate(class.0, descriptor, &args));
+        match result {
+            Ok(object) => Ok(self.pin(object)),
+            Err(throwable) => Err(self.java_error(throwable))
+        }
+    }
+
+    pub fn get_field(&mut self, object: JObject, name: &str, descriptor: &str) -> Result<JValue, JavaError> {
+        let field_type = self.field_type(descriptor)?;
+
+        let result = self.field_slot(object, name, descriptor).and_then(|slot| {
+            self.context.object(Value::Reference(object.0))?.field(slot)
+                .ok_or_else(|| Throwable::new("java/lang/IncompatibleClassChangeError", name))
+        });
+
+        self.returned(result.map(Some), Some(&field_type))
+    }
+
+    pub fn set_field(&mut self, object: JObject, name: &str, descriptor: &str, value: JValue) -> Result<(), JavaError> {
+        let field_type = self.field_type(descriptor)?;
+        let value = self.argument(&field_type, value)?;
+
+        let result = self.field_slot(object, name, descriptor).and_then(|slot| {
+            match self.context.object(Value::Reference(object.0))?.set_field(slot, value) {
+                true => Ok(()),
+                false => Err(Throwable::new("java/lang/IncompatibleClassChangeError", name))
+            }
+        });
+
+        result.map_err(|throwable| self.java_error(throwable))
+    }
+
+    /// Reads a static field of a class or its super types, initializing the declaring class first
+    pub fn get_static_field(&mut self, class: JClass, name: &str, descriptor: &str) -> Result<JValue, JavaError> {
+        let field_type = self.field_type(descriptor)?;
+
+        let context = &mut self.context;
+        let result = context.resolve_field(class.0, name, descriptor)
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchFieldError", name))
+            .and_then(|declaring_class| {
+                context.initialize_class(declaring_class)?;
+                Ok(context.class(declaring_class).static_values.get(name).copied().unwrap_or_else(|| Value::default_for(descriptor)))
+            });
+
+        self.returned(result.map(Some), Some(&field_type))
+    }
+
+    pub fn set_static_field(&mut self, class: JClass, name: &str, descriptor: &str, value: JValue) -> Result<(), JavaError> {
+        let field_type = self.field_type(descriptor)?;
+        let value = self.argument(&field_type, value)?;
+
+        let context = &mut self.context;
+        let result = context.resolve_field(class.0, name, descriptor)
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchFieldError", name))
+            .and_then(|declaring_class| {
+                context.initialize_class(declaring_class)?;
+                context.classes[declaring_class as usize].static_values.insert(name.to_string(), value);
+                Ok(())
+            });
+
+        result.map_err(|throwable| self.java_error(throwable))
+    }
+
+    /// Creates a `java.lang.String`
+    pub fn new_string(&mut self, string: &str) -> Result<JObject, JavaError> {
+        match self.context.new_string(string) {
+            Ok(object) => Ok(self.pin(object)),
+            Err(throwable) => Err(self.java_error(throwable))
+        }
+    }
+
+    /// Contents of a `java.lang.String`
+    pub fn get_string(&mut self, string: JObject) -> Result<String, JavaError> {
+        self.context.rust_string(Value::Reference(string.0)).map_err(|throwable| self.java_error(throwable))
+    }
+
+    /// Runtime visible annotations of a class, including the default values of the elements they leave out.
+    /// Array and primitive classes have none.
+    pub fn class_annotations(&mut self, class: JClass) -> Vec<ResolvedAnnotation> {
+        let annotations = self.context.class(class.0).class.as_ref()
+            .map(|class| class.annotations().to_vec())
+            .unwrap_or_default();
+
+        annotations.iter().map(|annotation| self.context.apply_annotation_defaults(annotation)).collect()
+    }
+
+    /// Methods and constructors declared by a class itself in the order of its class file, e.g. to find the ones with a `@Test` annotation
+    pub fn declared_methods(&mut self, class: JClass) -> Vec<JMethod> {
+        let methods = match self.context.class(class.0).class.clone() {
+            Some(class) => declared_methods(&class),
+            None => return vec![]
+        };
+
+        methods.into_iter()
+            .map(|(_, method)| JMethod {
+                name: method.name.clone(),
+                descriptor: method.descriptor.clone(),
+                access_flags: method.access_flags,
+                annotations: method.annotations().iter().map(|annotation| self.context.apply_annotation_defaults(annotation)).collect()
+            })
+            .collect()
+    }
+
+    /// Runtime visible annotations of a field declared by a class itself, `None` without such a field
+    pub fn field_annotations(&mut self, class: JClass, name: &str) -> Option<Vec<ResolvedAnnotation>> {
+        let annotations = self.context.class(class.0).class.as_ref()?.fields.get(name)?.annotations().to_vec();
+
+        Some(annotations.iter().map(|annotation| self.context.apply_annotation_defaults(annotation)).collect())
+    }
+
+    /// Frames of the Java code running right now, innermost first, e.g. for a native method registered by the embedder
+    /// or to tell where the VM was when it panicked
+    pub fn current_stack_trace(&self) -> Vec<StackTraceElement> {
+        self.context.current_stack_trace().iter().map(|frame| self.stack_trace_element(frame)).collect()
+    }
+
+    fn stack_trace_element(&self, frame: &TraceFrame) -> StackTraceElement {
+        StackTraceElement {
+            class_name: self.context.class(frame.class).java_name(),
+            method_name: frame.method.name.clone(),
+            program_counter: frame.program_counter,
+            file_name: self.context.frame_source_file(frame),
+            line_number: frame.line_number().map(u32::from)
+        }
+    }
+
+    /// Class of an object, `None` for null
+    pub fn object_class(&self, object: JObject) -> Option<JClass> {
+        self.context.heap.get(object.0).map(|object| JClass(object.class))
+    }
+
+    /// Lets the garbage collector free an object once Rust code doesn't use it anymore
+    pub fn release(&mut self, object: JObject) {
+        if let Some(count) = self.context.embedder_roots.get_mut(&object.0) {
+            *count -= 1;
+
+            if *count == 0 {
+                self.context.embedder_roots.remove(&object.0);
+            }
+        }
+    }
+
+    /// Hands an object out to Rust, keeping it alive until it's released
+    fn pin(&mut self, object: Value) -> JObject {
+        if !object.is_null() {
+            *self.context.embedder_roots.entry(object.as_reference()).or_insert(0) += 1;
+        }
+
+        JObject(object.as_reference())
+    }
+
+    fn method_descriptor(&mut self, class: JClass, name: &str, descriptor: &str) -> Result<MethodDescriptor, JavaError> {
+        match MethodDescriptor::parse(descriptor) {
+            Some(method_descriptor) => Ok(method_descriptor),
+            None => {
+                let signature = format!("{}.{}{}", self.context.class(class.0).name, name, descriptor);
+                Err(self.java_error(Throwable::new("java/lang/NoSuchMethodError", &signature)))
+            }
+        }
+    }
+
+    fn field_type(&mut self, descriptor: &str) -> Result<FieldType, JavaError> {
+        match FieldType::parse(descriptor) {
+            Some(field_type) => Ok(field_type),
+            None => Err(self.java_error(Throwable::new("java/lang/NoSuchFieldError", descriptor)))
+        }
+    }
+
+    /// Index into the field values of an object for a field its class or a super class declares
+    fn field_slot(&mut self, object: JObject, name: &str, descriptor: &str) -> Result<usize, Throwable> {
+        let class_id = self.context.object(Value::Reference(object.0))?.class;
+        let declaring_class = self.context.resolve_field(class_id, name, descriptor)
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchFieldError", name))?;
+
+        self.context.class(class_id).field_slot(declaring_class, name)
+            .ok_or_else(|| Throwable::new("java/lang/IncompatibleClassChangeError", &format!("Expected non-static field {}", name)))
+    }
+
+    fn arguments(&mut self, parameters: &[FieldType], args: &[JValue]) -> Result<Vec<Value>, JavaError> {
+        if parameters.len() != args.len() {
+            return Err(JavaError::ArgumentCount { expected: parameters.len(), found: args.len() });
+        }
+
+        parameters.iter().zip(args).map(|(parameter, arg)| self.argument(parameter, *arg)).collect()
+    }
+
+    /// Converts a Rust value for a parameter or field of the given type, objects have to be instances of it
+    fn argument(&mut self, field_type: &FieldType, value: JValue) -> Result<Value, JavaError> {
+        let converted = match (field_type, value) {
+            (FieldType::Boolean, JValue::Boolean(value))    => Value::from_bool(value),
+            (FieldType::Byte, JValue::Byte(value))          => Value::from_int(value as i32),
+            (FieldType::Char, JValue::Char(value))          => Value::from_int(value as i32),
+            (FieldType::Short, JValue::Short(value))        => Value::from_int(value as i32),
+            (FieldType::Int, JValue::Int(value))            => Value::from_int(value),
+            (FieldType::Long, JValue::Long(value))          => Value::from_long(value),
+            (FieldType::Float, JValue::Float(value))        => Value::from_float(value),
+            (FieldType::Double, JValue::Double(value))      => Value::from_double(value),
+            (_, JValue::Object(object)) if field_type.is_reference() => {
+                let object = Value::Reference(object.0);
+                if object.is_null() {
+                    return Ok(object);
+                }
+
+                let instance = self.context.load_type_class(field_type)
+                    .and_then(|class_id| self.context.is_instance_of(object, class_id));
+
+                match instance {
+                    Ok(true) => object,
+                    Ok(false) => {
+                        let class_name = self.context.object(object).map(|object| self.context.class(object.class).java_name()).unwrap_or_default();
+                        return Err(JavaError::TypeMismatch { expected: field_type.to_string(), found: class_name });
+                    },
+                    Err(throwable) => return Err(self.java_error(throwable))
+                }
+            },
+            _ => return Err(type_mismatch(field_type, value))
+        };
+
+        Ok(converted)
+    }
+
+    /// Converts what a method returned or a field held to a Rust value
+    fn returned(&mut self, result: Result<Option<Value>, Throwable>, return_type: Option<&FieldType>) -> Result<JValue, JavaError> {
+        let value = match result {
+            Ok(value) => value,
+            Err(throwable) => return Err(self.java_error(throwable))
+        };
+
+        let value = match (return_type, value) {
+            (None, _) => JValue::Void,
+            (Some(return_type), None) => return Err(JavaError::TypeMismatch { expected: return_type.to_string(), found: "void".to_string() }),
+            (Some(return_type), Some(value)) => match return_type {
+                FieldType::Boolean  => JValue::Boolean(value.as_int() != 0),
+                FieldType::Byte     => JValue::Byte(value.as_int() as i8),
+                FieldType::Char     => JValue::Char(value.as_int() as u16),
+                FieldType::Short    => JValue::Short(value.as_int() as i16),
+                FieldType::Int      => JValue::Int(value.as_int()),
+                FieldType::Long     => JValue::Long(value.as_long()),
+                FieldType::Float    => JValue::Float(value.as_float()),
+                FieldType::Double   => JValue::Double(value.as_double()),
+                FieldType::Object(_) | FieldType::Array(_) => JValue::Object(self.pin(value))
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Turns an exception that got back to Rust into an error, pinning the throwable object
+    fn java_error(&mut self, throwable: Throwable) -> JavaError {
+        let throwable = match self.context.throwable_object(throwable) {
+            Throwable::Halt(halt) => return JavaError::Halted(halt),
+            throwable => throwable
+        };
+
+        match throwable {
+            Throwable::Object(reference) => {
+                let object = self.pin(Value::Reference(reference));
+                let class_name = self.context.heap.get(reference).map(|object| self.context.class(object.class).java_name()).unwrap_or_default();
+
+                let stack_trace = self.context.backtraces.get(&reference).map(|frames| frames.iter()
+                    .map(|frame| self.stack_trace_element(frame))
+                    .collect());
+
+                JavaError::Exception(JavaException {
+                    throwable: object,
+                    class_name,
+                    message: self.context.throwable_message(Value::Reference(reference)),
+                    stack_trace: stack_trace.unwrap_or_default()
+                })
+            },
+            throwable => {
+                let (class_name, message) = match throwable {
+                    Throwable::New { class_name, message } => (class_name.replace('/', "."), message),
+                    throwable => (throwable.to_string(), None)
+                };
+
+                JavaError::Exception(JavaException { throwable: JObject::null(), class_name, message, stack_trace: vec![] })
+            }
+        }
+    }
+
+}
\ No newline at end of file
@@ -1,14 +1,20 @@
 #![allow(dead_code)]
 
 use crate::java;
+use crate::java::access_flags;
+use crate::java::annotation::{self, ResolvedAnnotation};
 use crate::java::Attribute;
 
 #[derive(Debug)]
 pub struct Field {
+    pub access_flags: u16,
+
     pub name: String,
     pub descriptor: String,
 
-    pub attributes: Vec<java::Attribute>
+    pub attributes: Vec<java::Attribute>,
+
+    annotations: Vec<ResolvedAnnotation>
 }
 
 impl Field {
@@ -19,15 +25,21 @@ impl Field {
                 let mut attributes = vec![];
 
                 for attribute in &field_info.attributes {
-                    if let Some(attribute) = Attribute::new(&class_file, &attribute) {
+                    if let Some(attribute) = Attribute::new(class_file, attribute) {
                         attributes.push(attribute);
                     }
                 }
 
+                let annotations = annotation::runtime_visible_annotations(class_file, &attributes);
+
                 return Some(Field {
+                    access_flags: field_info.access_flags,
+
                     name,
                     descriptor,
-                    attributes
+                    attributes,
+
+                    annotations
                 })
             }
         }
@@ -35,4 +47,21 @@ impl Field {
         None
     }
 
+    /// Runtime visible annotations, without the default values of elements they leave out
+    pub fn annotations(&self) -> &[ResolvedAnnotation] {
+        &self.annotations
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.access_flags & access_flags::ACC_STATIC != 0
+    }
+
+    /// Constant pool index of the ConstantValue attribute, if present
+    pub fn constant_value_index(&self) -> Option<u16> {
+        self.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::ConstantValue(constant_value) => Some(constant_value.constantvalue_index),
+            _ => None
+        })
+    }
+
 }
\ No newline at end of file
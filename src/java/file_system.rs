@@ -0,0 +1,145 @@
+#![allow(dead_code)]
+
+use std::fs::Metadata;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use crate::java::native::NativeRegistry;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+// Bits of `UnixFileSystem.getBooleanAttributes0`, see `java.io.FileSystem`
+const BA_EXISTS: i32     = 0x01;
+const BA_REGULAR: i32    = 0x02;
+const BA_DIRECTORY: i32  = 0x04;
+
+// Access modes of `UnixFileSystem.checkAccess`
+const ACCESS_EXECUTE: i32 = 0x01;
+const ACCESS_WRITE: i32   = 0x02;
+const ACCESS_READ: i32    = 0x04;
+
+const UNIX_FILE_SYSTEM: &str = "java/io/UnixFileSystem";
+
+impl VmContext {
+
+    /// The path of a `java.io.File`
+    fn file_path(&mut self, file: Value) -> Result<PathBuf, Throwable> {
+        if file.is_null() {
+            return Err(Throwable::null_pointer());
+        }
+
+        let path = self.get_field(file, "java/io/File", "path")?;
+
+        Ok(PathBuf::from(self.rust_string(path)?))
+    }
+
+}
+
+/// The metadata of the file a `java.io.File` names, following symbolic links
+fn file_metadata(context: &mut VmContext, file: Value) -> Result<Option<Metadata>, Throwable> {
+    Ok(std::fs::metadata(context.file_path(file)?).ok())
+}
+
+fn canonicalize(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let path = PathBuf::from(context.rust_string(args[1])?);
+
+    // Like realpath, the part of the path that doesn't exist is kept as it is
+    let mut existing = path.as_path();
+    let mut rest = Vec::new();
+    let canonical = loop {
+        match existing.canonicalize() {
+            Ok(canonical) => break rest.iter().rev().fold(canonical, |canonical, name| canonical.join(name)),
+            Err(_) => match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    rest.push(name.to_os_string());
+                    existing = parent;
+                },
+                _ => break path.clone()
+            }
+        }
+    };
+
+    Ok(Some(context.new_string(&canonical.to_string_lossy())?))
+}
+
+fn get_boolean_attributes(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let attributes = match file_metadata(context, args[1])? {
+        Some(metadata) if metadata.is_dir() => BA_EXISTS | BA_DIRECTORY,
+        Some(metadata) if metadata.is_file() => BA_EXISTS | BA_REGULAR,
+        Some(_) => BA_EXISTS,
+        None => 0
+    };
+
+    Ok(Some(Value::from_int(attributes)))
+}
+
+fn check_access(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let metadata = match file_metadata(context, args[1])? {
+        Some(metadata) => metadata,
+        None => return Ok(Some(Value::from_bool(false)))
+    };
+
+    // Approximated by the permission bits of the owner
+    let mode = permission_bits(&metadata);
+    let accessible = match args[2].as_int() {
+        ACCESS_READ => mode & 0o400 != 0,
+        ACCESS_WRITE => mode & 0o200 != 0,
+        ACCESS_EXECUTE => mode & 0o100 != 0,
+        _ => false
+    };
+
+    Ok(Some(Value::from_bool(accessible)))
+}
+
+#[cfg(unix)]
+fn permission_bits(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode()
+}
+
+#[cfg(not(unix))]
+fn permission_bits(metadata: &Metadata) -> u32 {
+    if metadata.permissions().readonly() { 0o555 } else { 0o777 }
+}
+
+fn get_last_modified_time(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let millis = file_metadata(context, args[1])?
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0);
+
+    Ok(Some(Value::from_long(millis)))
+}
+
+fn get_length(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let length = file_metadata(context, args[1])?.map(|metadata| metadata.len() as i64).unwrap_or(0);
+
+    Ok(Some(Value::from_long(length)))
+}
+
+fn list(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let entries = match std::fs::read_dir(context.file_path(args[1])?) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Some(Value::null()))
+    };
+
+    let names: Vec<Option<String>> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| Some(entry.file_name().to_string_lossy().into_owned()))
+        .collect();
+
+    Ok(Some(context.new_string_array(&names)?))
+}
+
+/// Queries of the file system for `java.io.File`, files can't be created, changed or deleted yet
+pub fn register_file_system_natives(registry: &mut NativeRegistry) {
+    registry.register_host(UNIX_FILE_SYSTEM, "initIDs", "()V", |_, _| Ok(None));
+    registry.register_host(UNIX_FILE_SYSTEM, "canonicalize0", "(Ljava/lang/String;)Ljava/lang/String;", canonicalize);
+    registry.register_host(UNIX_FILE_SYSTEM, "getBooleanAttributes0", "(Ljava/io/File;)I", get_boolean_attributes);
+    registry.register_host(UNIX_FILE_SYSTEM, "checkAccess", "(Ljava/io/File;I)Z", check_access);
+    registry.register_host(UNIX_FILE_SYSTEM, "getLastModifiedTime", "(Ljava/io/File;)J", get_last_modified_time);
+    registry.register_host(UNIX_FILE_SYSTEM, "getLength", "(Ljava/io/File;)J", get_length);
+    registry.register_host(UNIX_FILE_SYSTEM, "list", "(Ljava/io/File;)[Ljava/lang/String;", list);
+    registry.register_host(UNIX_FILE_SYSTEM, "getNameMax0", "(Ljava/lang/String;)J", |_, _| Ok(Some(Value::from_long(255))));
+}
\ No newline at end of file
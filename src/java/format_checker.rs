@@ -0,0 +1,502 @@
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Formatter;
+
+use crate::java::access_flags::*;
+use crate::java::class::ConstantPoolEntry;
+use crate::java::descriptor::{FieldType, MethodDescriptor};
+use crate::java::{Attribute, AttributeInfo, ClassFile};
+
+pub const MIN_MAJOR_VERSION: u16 = 45;
+pub const MAX_MAJOR_VERSION: u16 = 65;
+
+#[derive(Debug, Clone)]
+pub struct ClassFormatError {
+    pub class_name: String,
+    pub message: String
+}
+
+impl ClassFormatError {
+
+    /// The error of a class file that can't be parsed, `class_name` is the name it was looked up by
+    pub fn unparsable(class_name: &str, error: &binrw::Error) -> Self {
+        let message = match error.root_cause() {
+            binrw::Error::BadMagic { pos: 0, .. } => "Incompatible magic value".to_string(),
+            binrw::Error::AssertFail { message, .. } => message.clone(),
+            error if is_truncated(error) => "Truncated class file".to_string(),
+            error => error.to_string()
+        };
+
+        ClassFormatError {
+            class_name: class_name.to_string(),
+            message
+        }
+    }
+
+}
+
+/// Whether parsing failed because the class file ended early, also inside one of the variants of an enum
+fn is_truncated(error: &binrw::Error) -> bool {
+    match error.root_cause() {
+        binrw::Error::Io(error) => error.kind() == std::io::ErrorKind::UnexpectedEof,
+        binrw::Error::EnumErrors { variant_errors, .. } => variant_errors.iter().any(|(_, error)| is_truncated(error)),
+        _ => false
+    }
+}
+
+/// Structural checks of a parsed class file (JVMS 4.8), run before a class gets linked
+pub struct FormatChecker<'a> {
+    class_file: &'a ClassFile,
+    class_name: String
+}
+
+pub fn check_class_format(class_file: &ClassFile) -> Result<(), ClassFormatError> {
+    let class_name = class_file.get_class_name(class_file.this_class as usize).unwrap_or_else(|| "<unknown>".to_string());
+    let checker = FormatChecker { class_file, class_name };
+
+    checker.check_version()?;
+    checker.check_constant_pool()?;
+    checker.check_class()?;
+    checker.check_fields()?;
+    checker.check_methods()?;
+
+    Ok(())
+}
+
+/// Unqualified names of fields and methods (JVMS 4.2.2)
+fn is_unqualified_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', ';', '[', '/'])
+}
+
+fn is_method_name(name: &str) -> bool {
+    name == "<init>" || name == "<clinit>" || (is_unqualified_name(name) && !name.contains(['<', '>']))
+}
+
+/// Binary class names in internal form, e.g. `java/lang/Object`, or array descriptors
+fn is_class_name(name: &str) -> bool {
+    if name.starts_with('[') {
+        return FieldType::parse(name).is_some() && name.chars().take_while(|character| *character == '[').count() <= 255;
+    }
+
+    !name.is_empty() && name.split('/').all(is_unqualified_name)
+}
+
+fn field_type_is_valid(field_type: &FieldType) -> bool {
+    match field_type {
+        FieldType::Object(name) => is_class_name(name) && !name.starts_with('['),
+        FieldType::Array(component) => field_type_is_valid(component),
+        _ => true
+    }
+}
+
+impl<'a> FormatChecker<'a> {
+
+    fn error(&self, message: String) -> ClassFormatError {
+        ClassFormatError {
+            class_name: self.class_name.clone(),
+            message
+        }
+    }
+
+    fn entry(&self, index: u16) -> Option<&'a ConstantPoolEntry> {
+        self.class_file.constant_pool.get((index as usize).checked_sub(1)?)
+    }
+
+    fn utf8(&self, index: u16, context: &str) -> Result<String, ClassFormatError> {
+        match self.entry(index) {
+            Some(ConstantPoolEntry::String { .. }) => self.class_file.get_constant_pool_string(index as usize)
+                .ok_or_else(|| self.error(format!("Illegal UTF8 string in constant pool at index {} ({})", index, context))),
+            _ => Err(self.error(format!("Invalid constant pool index {} for {}, expected a Utf8 entry", index, context)))
+        }
+    }
+
+    fn check_utf8_index(&self, index: u16, context: &str) -> Result<(), ClassFormatError> {
+        match self.entry(index) {
+            Some(ConstantPoolEntry::String { .. }) => Ok(()),
+            _ => Err(self.error(format!("Invalid constant pool index {} for {}, expected a Utf8 entry", index, context)))
+        }
+    }
+
+    fn class_reference(&self, index: u16, context: &str) -> Result<String, ClassFormatError> {
+        match self.entry(index) {
+            Some(ConstantPoolEntry::ClassReference(name_index)) => self.utf8(*name_index, context),
+            _ => Err(self.error(format!("Invalid constant pool index {} for {}, expected a Class entry", index, context)))
+        }
+    }
+
+    fn name_and_type(&self, index: u16, context: &str) -> Result<(String, String), ClassFormatError> {
+        match self.entry(index) {
+            Some(ConstantPoolEntry::NameAndTypeDescriptor(name_index, descriptor_index)) =>
+                Ok((self.utf8(*name_index, context)?, self.utf8(*descriptor_index, context)?)),
+            _ => Err(self.error(format!("Invalid constant pool index {} for {}, expected a NameAndType entry", index, context)))
+        }
+    }
+
+    fn check_version(&self) -> Result<(), ClassFormatError> {
+        let major_version = self.class_file.major_version;
+
+        if !(MIN_MAJOR_VERSION..=MAX_MAJOR_VERSION).contains(&major_version) {
+            return Err(self.error(format!("Unsupported class file version {}.{}", major_version, self.class_file.minor_version)));
+        }
+
+        if major_version >= 56 && self.class_file.minor_version != 0 && self.class_file.minor_version != 0xFFFF {
+            return Err(self.error(format!("Illegal minor version {} for class file version {}", self.class_file.minor_version, major_version)));
+        }
+
+        Ok(())
+    }
+
+    fn check_utf8(&self, index: usize, bytes: &[u8]) -> Result<(), ClassFormatError> {
+        // Modified UTF-8 never contains a zero byte or bytes in the range 0xF0 to 0xFF
+        if bytes.iter().any(|byte| *byte == 0 || *byte >= 0xF0) {
+            return Err(self.error(format!("Illegal UTF8 string in constant pool at index {}", index)));
+        }
+
+        Ok(())
+    }
+
+    fn check_member_reference(&self, index: usize, class_index: u16, name_and_type_index: u16, is_method: bool) -> Result<(), ClassFormatError> {
+        let context = format!("member reference #{}", index);
+
+        let class_name = self.class_reference(class_index, &context)?;
+        if !is_class_name(&class_name) {
+            return Err(self.error(format!("Illegal class name \"{}\" in member reference #{}", class_name, index)));
+        }
+
+        let (name, descriptor) = self.name_and_type(name_and_type_index, &context)?;
+        if is_method {
+            if !is_method_name(&name) || name == "<clinit>" {
+                return Err(self.error(format!("Illegal method name \"{}\" in member reference #{}", name, index)));
+            }
+
+            let method_descriptor = MethodDescriptor::parse(&descriptor).filter(|method_descriptor| method_descriptor.parameters.iter().all(field_type_is_valid));
+            match method_descriptor {
+                Some(method_descriptor) => {
+                    if name == "<init>" && method_descriptor.return_type.is_some() {
+                        return Err(self.error(format!("Method \"<init>\" in member reference #{} has non-void return type", index)));
+                    }
+                },
+                None => return Err(self.error(format!("Illegal method signature \"{}\" in member reference #{}", descriptor, index)))
+            }
+        } else {
+            if !is_unqualified_name(&name) {
+                return Err(self.error(format!("Illegal field name \"{}\" in member reference #{}", name, index)));
+            }
+
+            if !FieldType::parse(&descriptor).is_some_and(|field_type| field_type_is_valid(&field_type)) {
+                return Err(self.error(format!("Illegal field signature \"{}\" in member reference #{}", descriptor, index)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_constant_pool(&self) -> Result<(), ClassFormatError> {
+        let constant_pool = &self.class_file.constant_pool;
+
+        for (position, entry) in constant_pool.iter().enumerate() {
+            let index = position + 1;
+            let context = format!("constant pool entry #{}", index);
+
+            match entry {
+                ConstantPoolEntry::None() => {
+                    let previous = position.checked_sub(1).and_then(|previous| constant_pool.get(previous));
+                    if !matches!(previous, Some(ConstantPoolEntry::Long(_, _)) | Some(ConstantPoolEntry::Double(_, _))) {
+                        return Err(self.error(format!("Unknown constant tag 0 at index {}", index)));
+                    }
+                },
+                ConstantPoolEntry::String { string, .. } => self.check_utf8(index, string)?,
+                ConstantPoolEntry::ClassReference(name_index) => {
+                    let name = self.utf8(*name_index, &context)?;
+                    if !is_class_name(&name) {
+                        return Err(self.error(format!("Illegal class name \"{}\" in constant pool entry #{}", name, index)));
+                    }
+                },
+                ConstantPoolEntry::StringReference(string_index) => self.check_utf8_index(*string_index, &context)?,
+                ConstantPoolEntry::FieldReference(class_index, name_and_type_index) =>
+                    self.check_member_reference(index, *class_index, *name_and_type_index, false)?,
+                ConstantPoolEntry::MethodReference(class_index, name_and_type_index) |
+                ConstantPoolEntry::InterfaceMethodReference(class_index, name_and_type_index) =>
+                    self.check_member_reference(index, *class_index, *name_and_type_index, true)?,
+                ConstantPoolEntry::NameAndTypeDescriptor(name_index, descriptor_index) => {
+                    self.utf8(*name_index, &context)?;
+                    self.utf8(*descriptor_index, &context)?;
+                },
+                ConstantPoolEntry::MethodHandle(kind, reference_index) => {
+                    let reference = self.entry(*reference_index);
+                    let valid = match kind {
+                        1..=4 => matches!(reference, Some(ConstantPoolEntry::FieldReference(_, _))),
+                        5 | 8 => matches!(reference, Some(ConstantPoolEntry::MethodReference(_, _))),
+                        6 | 7 => matches!(reference, Some(ConstantPoolEntry::MethodReference(_, _)) | Some(ConstantPoolEntry::InterfaceMethodReference(_, _))),
+                        9 => matches!(reference, Some(ConstantPoolEntry::InterfaceMethodReference(_, _))),
+                        _ => return Err(self.error(format!("Bad method handle kind {} at constant pool index {}", kind, index)))
+                    };
+
+                    if !valid {
+                        return Err(self.error(format!("Bad method handle reference #{} at constant pool index {}", reference_index, index)));
+                    }
+                },
+                ConstantPoolEntry::MethodType(descriptor_index) => {
+                    let descriptor = self.utf8(*descriptor_index, &context)?;
+                    if MethodDescriptor::parse(&descriptor).is_none() {
+                        return Err(self.error(format!("Illegal method type descriptor \"{}\" at constant pool index {}", descriptor, index)));
+                    }
+                },
+                ConstantPoolEntry::Dynamic(_, name_and_type_index) => {
+                    let (_, descriptor) = self.name_and_type(*name_and_type_index, &context)?;
+                    if FieldType::parse(&descriptor).is_none() {
+                        return Err(self.error(format!("Illegal field signature \"{}\" in dynamic constant #{}", descriptor, index)));
+                    }
+                },
+                ConstantPoolEntry::InvokeDynamic(_, name_and_type_index) => {
+                    let (_, descriptor) = self.name_and_type(*name_and_type_index, &context)?;
+                    if MethodDescriptor::parse(&descriptor).is_none() {
+                        return Err(self.error(format!("Illegal method signature \"{}\" in invokedynamic constant #{}", descriptor, index)));
+                    }
+                },
+                ConstantPoolEntry::Module(name_index) | ConstantPoolEntry::Package(name_index) => { self.utf8(*name_index, &context)?; },
+                ConstantPoolEntry::Integer(_) | ConstantPoolEntry::Float(_) |
+                ConstantPoolEntry::Long(_, _) | ConstantPoolEntry::Double(_, _) => { }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_class(&self) -> Result<(), ClassFormatError> {
+        let class_file = self.class_file;
+        let flags = class_file.access_flags;
+
+        let this_class = self.class_reference(class_file.this_class, "this_class")?;
+        if this_class.starts_with('[') {
+            return Err(self.error(format!("Illegal this_class \"{}\"", this_class)));
+        }
+
+        if class_file.super_class == 0 {
+            if this_class != "java/lang/Object" {
+                return Err(self.error("Invalid superclass index 0".to_string()));
+            }
+        } else {
+            let super_class = self.class_reference(class_file.super_class, "super_class")?;
+            if super_class.starts_with('[') {
+                return Err(self.error(format!("Illegal superclass \"{}\"", super_class)));
+            }
+            if flags & ACC_INTERFACE != 0 && super_class != "java/lang/Object" {
+                return Err(self.error("Interfaces must have java.lang.Object as superclass".to_string()));
+            }
+        }
+
+        let mut interfaces = HashSet::new();
+        for interface in &class_file.interface_table {
+            let name = self.class_reference(*interface, "interface")?;
+            if !interfaces.insert(name.clone()) {
+                return Err(self.error(format!("Duplicate interface name \"{}\"", name)));
+            }
+        }
+
+        if flags & ACC_MODULE != 0 {
+            return Err(self.error("module-info classes can't be loaded".to_string()));
+        }
+
+        if flags & ACC_INTERFACE != 0 {
+            if flags & ACC_ABSTRACT == 0 || flags & (ACC_FINAL | ACC_ENUM) != 0 || (flags & ACC_SUPER != 0 && class_file.major_version >= 53) {
+                return Err(self.error(format!("Illegal class modifiers 0x{:04X}", flags)));
+            }
+        } else if flags & ACC_ANNOTATION != 0 || flags & ACC_FINAL != 0 && flags & ACC_ABSTRACT != 0 {
+            return Err(self.error(format!("Illegal class modifiers 0x{:04X}", flags)));
+        }
+
+        self.check_attributes(&class_file.attribute_table, "class")?;
+
+        Ok(())
+    }
+
+    fn check_visibility(&self, flags: u16, kind: &str, name: &str) -> Result<(), ClassFormatError> {
+        if (flags & (ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED)).count_ones() > 1 {
+            return Err(self.error(format!("Illegal {} modifiers 0x{:04X} on {}", kind, flags, name)));
+        }
+
+        Ok(())
+    }
+
+    fn check_fields(&self) -> Result<(), ClassFormatError> {
+        let is_interface = self.class_file.access_flags & ACC_INTERFACE != 0;
+        let mut signatures = HashSet::new();
+
+        for field_info in &self.class_file.field_table {
+            let name = self.utf8(field_info.name_index, "field name")?;
+            let descriptor = self.utf8(field_info.descriptor_index, "field descriptor")?;
+            let flags = field_info.access_flags;
+
+            if !is_unqualified_name(&name) {
+                return Err(self.error(format!("Illegal field name \"{}\"", name)));
+            }
+
+            let field_type = FieldType::parse(&descriptor).filter(field_type_is_valid)
+                .ok_or_else(|| self.error(format!("Field \"{}\" has illegal signature \"{}\"", name, descriptor)))?;
+
+            if !signatures.insert((name.clone(), descriptor.clone())) {
+                return Err(self.error(format!("Duplicate field name \"{}\" with signature \"{}\"", name, descriptor)));
+            }
+
+            self.check_visibility(flags, "field", &name)?;
+            if flags & ACC_FINAL != 0 && flags & ACC_VOLATILE != 0 {
+                return Err(self.error(format!("Illegal field modifiers 0x{:04X} on {}", flags, name)));
+            }
+            if is_interface && flags & (ACC_PUBLIC | ACC_STATIC | ACC_FINAL) != (ACC_PUBLIC | ACC_STATIC | ACC_FINAL) {
+                return Err(self.error(format!("Illegal field modifiers 0x{:04X} on interface field {}", flags, name)));
+            }
+
+            for attribute_info in &field_info.attributes {
+                if self.attribute_name(attribute_info)? != "ConstantValue" || flags & ACC_STATIC == 0 { continue; }
+
+                let constant = match Attribute::new(self.class_file, attribute_info) {
+                    Some(Attribute::ConstantValue(attribute)) => self.entry(attribute.constantvalue_index),
+                    _ => return Err(self.error(format!("Malformed ConstantValue attribute on field {}", name)))
+                };
+
+                let valid = match field_type {
+                    FieldType::Long => matches!(constant, Some(ConstantPoolEntry::Long(_, _))),
+                    FieldType::Float => matches!(constant, Some(ConstantPoolEntry::Float(_))),
+                    FieldType::Double => matches!(constant, Some(ConstantPoolEntry::Double(_, _))),
+                    FieldType::Int | FieldType::Short | FieldType::Char | FieldType::Byte | FieldType::Boolean => matches!(constant, Some(ConstantPoolEntry::Integer(_))),
+                    FieldType::Object(ref class_name) => class_name == "java/lang/String" && matches!(constant, Some(ConstantPoolEntry::StringReference(_))),
+                    FieldType::Array(_) => false
+                };
+
+                if !valid {
+                    return Err(self.error(format!("Inconsistent constant value type in field {}", name)));
+                }
+            }
+
+            self.check_attributes(&field_info.attributes, &format!("field {}", name))?;
+        }
+
+        Ok(())
+    }
+
+    fn check_methods(&self) -> Result<(), ClassFormatError> {
+        let is_interface = self.class_file.access_flags & ACC_INTERFACE != 0;
+        let major_version = self.class_file.major_version;
+        let mut signatures = HashSet::new();
+
+        for method_info in &self.class_file.method_table {
+            let name = self.utf8(method_info.name_index, "method name")?;
+            let descriptor = self.utf8(method_info.descriptor_index, "method descriptor")?;
+            let flags = method_info.access_flags;
+
+            if !is_method_name(&name) {
+                return Err(self.error(format!("Illegal method name \"{}\"", name)));
+            }
+
+            let method_descriptor = MethodDescriptor::parse(&descriptor).filter(|method_descriptor| method_descriptor.parameters.iter().all(field_type_is_valid))
+                .ok_or_else(|| self.error(format!("Method \"{}\" has illegal signature \"{}\"", name, descriptor)))?;
+
+            let this_slot = if flags & ACC_STATIC == 0 { 1 } else { 0 };
+            if method_descriptor.parameter_slots() + this_slot > 255 {
+                return Err(self.error(format!("Too many arguments in method signature \"{}\" of {}", descriptor, name)));
+            }
+
+            if !signatures.insert((name.clone(), descriptor.clone())) {
+                return Err(self.error(format!("Duplicate method name \"{}\" with signature \"{}\"", name, descriptor)));
+            }
+
+            self.check_visibility(flags, "method", &name)?;
+
+            match name.as_str() {
+                "<init>" => {
+                    if method_descriptor.return_type.is_some() {
+                        return Err(self.error(format!("Method \"<init>\" has illegal signature \"{}\"", descriptor)));
+                    }
+                    if is_interface || flags & (ACC_STATIC | ACC_FINAL | ACC_SYNCHRONIZED | ACC_BRIDGE | ACC_NATIVE | ACC_ABSTRACT) != 0 {
+                        return Err(self.error(format!("Method <init> has illegal modifiers 0x{:04X}", flags)));
+                    }
+                },
+                "<clinit>" => {
+                    if method_descriptor.return_type.is_some() || (major_version >= 51 && !method_descriptor.parameters.is_empty()) {
+                        return Err(self.error(format!("Method \"<clinit>\" has illegal signature \"{}\"", descriptor)));
+                    }
+                    if major_version >= 51 && flags & ACC_STATIC == 0 {
+                        return Err(self.error("Method <clinit> is not static".to_string()));
+                    }
+                },
+                _ => {
+                    if flags & ACC_ABSTRACT != 0 && flags & (ACC_PRIVATE | ACC_STATIC | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE) != 0 {
+                        return Err(self.error(format!("Illegal method modifiers 0x{:04X} on abstract method {}", flags, name)));
+                    }
+                    if flags & ACC_ABSTRACT != 0 && flags & ACC_STRICT != 0 && (46..61).contains(&major_version) {
+                        return Err(self.error(format!("Illegal method modifiers 0x{:04X} on abstract method {}", flags, name)));
+                    }
+
+                    if is_interface {
+                        let illegal = if major_version >= 52 {
+                            flags & (ACC_PROTECTED | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE) != 0 || flags & (ACC_PUBLIC | ACC_PRIVATE) == 0
+                        } else {
+                            flags & (ACC_PUBLIC | ACC_ABSTRACT) != (ACC_PUBLIC | ACC_ABSTRACT) || flags & !(ACC_PUBLIC | ACC_ABSTRACT | ACC_BRIDGE | ACC_VARARGS | ACC_SYNTHETIC) != 0
+                        };
+
+                        if illegal {
+                            return Err(self.error(format!("Illegal method modifiers 0x{:04X} on interface method {}", flags, name)));
+                        }
+                    }
+                }
+            }
+
+            let mut code_count = 0;
+            for attribute_info in &method_info.attributes {
+                if self.attribute_name(attribute_info)? == "Code" {
+                    code_count += 1;
+                }
+            }
+
+            let needs_code = flags & (ACC_ABSTRACT | ACC_NATIVE) == 0;
+            match (needs_code, code_count) {
+                (true, 0) => return Err(self.error(format!("Absent Code attribute in method {}{} that is not native or abstract", name, descriptor))),
+                (false, 0) | (true, 1) => { },
+                (false, _) => return Err(self.error(format!("Code attribute in native or abstract method {}{}", name, descriptor))),
+                (true, _) => return Err(self.error(format!("Multiple Code attributes in method {}{}", name, descriptor)))
+            }
+
+            self.check_attributes(&method_info.attributes, &format!("method {}{}", name, descriptor))?;
+        }
+
+        Ok(())
+    }
+
+    fn attribute_name(&self, attribute_info: &AttributeInfo) -> Result<String, ClassFormatError> {
+        self.utf8(attribute_info.attribute_name_index, "attribute name")
+    }
+
+    fn check_attributes(&self, attributes: &[AttributeInfo], owner: &str) -> Result<(), ClassFormatError> {
+        for attribute_info in attributes {
+            let name = self.attribute_name(attribute_info)?;
+
+            if attribute_info.attribute_length as usize != attribute_info.info.len() {
+                return Err(self.error(format!("Wrong size for attribute {} of {}", name, owner)));
+            }
+
+            if let Some(Attribute::Code(code)) = Attribute::is_supported(&name).then(|| Attribute::new(self.class_file, attribute_info)).flatten() {
+                for entry in &code.exception_table {
+                    if entry.catch_type != 0 {
+                        self.class_reference(entry.catch_type, &format!("exception table of {}", owner))?;
+                    }
+                }
+
+                self.check_attributes(&code.attributes, owner)?;
+            }
+        }
+
+        Ok(())
+    }
+
+}
+
+impl fmt::Display for ClassFormatError {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "ClassFormatError: {} in class file {}", self.message, self.class_name)
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,166 @@
+#![allow(dead_code)]
+
+use std::time::Instant;
+
+use crate::java::heap::{ReferenceKind, ReferenceType};
+use crate::java::runtime_class::ClassState;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+fn reference(value: &Value) -> Option<u32> {
+    match value {
+        Value::Reference(reference) if *reference != 0 => Some(*reference),
+        _ => None
+    }
+}
+
+impl VmContext {
+
+    /// References the garbage collector starts marking from
+    fn gc_roots(&self) -> Vec<u32> {
+        let mut roots = vec![];
+
+        for executor in self.executors() {
+            for frame in &executor.frames {
+                roots.extend(frame.locals.iter().filter_map(reference));
+                roots.extend(frame.stack.iter().filter_map(reference));
+            }
+
+            roots.push(executor.thread_object);
+        }
+
+        for class in &self.classes {
+            roots.extend(class.static_values.values().filter_map(reference));
+            roots.push(class.mirror);
+        }
+
+        roots.extend(self.interned_strings.values().copied());
+        roots.extend(self.dynamic_constants.values().filter_map(reference));
+        roots.extend(&self.linked_appendices);
+        roots.extend(self.console.references());
+        roots.extend(self.native_roots.iter().filter_map(reference));
+        roots.extend(self.embedder_roots.keys().copied());
+        roots.extend(reference(&self.reference_pending_list));
+        roots.extend(self.pending_finalization.iter().copied());
+        roots.extend(self.scheduler.references());
+
+        roots
+    }
+
+    /// How the collector treats each loaded class, `None` for classes that aren't `java.lang.ref.Reference`s
+    fn reference_types(&self) -> Vec<Option<ReferenceType>> {
+        let reference_class = match self.class_id("java/lang/ref/Reference") {
+            Some(reference_class) => reference_class,
+            None => return vec![]
+        };
+
+        let referent_slot = match self.class(reference_class).field_slot(reference_class, "referent") {
+            Some(referent_slot) => referent_slot,
+            None => return vec![]
+        };
+
+        let kinds: Vec<_> = [
+            ("java/lang/ref/SoftReference", ReferenceKind::Soft),
+            ("java/lang/ref/WeakReference", ReferenceKind::Weak),
+            ("java/lang/ref/PhantomReference", ReferenceKind::Phantom)
+        ].iter().filter_map(|(class_name, kind)| self.class_id(class_name).map(|class_id| (class_id, *kind))).collect();
+
+        self.classes.iter()
+            .map(|class| kinds.iter()
+                .find(|(class_id, _)| self.is_subclass_of(class.id, *class_id))
+                .map(|(_, kind)| ReferenceType { kind: *kind, referent_slot }))
+            .collect()
+    }
+
+    fn collect(&mut self, clear_soft_references: bool) {
+        let start = Instant::now();
+
+        let roots = self.gc_roots();
+        let reference_types = self.reference_types();
+        let collection = self.heap.collect(roots, &reference_types, clear_soft_references);
+        self.backtraces.retain(|throwable, _| self.heap.get(*throwable).is_some());
+
+        // Cleared references are handed to java.lang.ref.Reference through the pending list, like HotSpot does
+        for cleared in collection.cleared_references {
+            let reference = Value::Reference(cleared);
+            if self.set_field(reference, "java/lang/ref/Reference", "discovered", self.reference_pending_list).is_ok() {
+                self.reference_pending_list = reference;
+            }
+        }
+
+        self.pending_finalization.extend(collection.finalize);
+
+        let pause = start.elapsed();
+        self.heap.stats.last_pause = pause;
+        self.heap.stats.total_pause += pause;
+    }
+
+    /// Frees all objects that can't be reached anymore, soft references are only cleared when memory runs out.
+    /// Values held only by Rust code have to be in `native_roots` while this runs, otherwise they may be freed.
+    pub fn collect_garbage(&mut self) {
+        self.collect(false);
+        self.process_pending_references();
+    }
+
+    /// Enqueues cleared references and runs finalizers, which runs Java code and so happens after a collection.
+    /// Exceptions thrown while doing so are ignored.
+    pub fn process_pending_references(&mut self) {
+        if self.processing_references {
+            return;
+        }
+
+        self.processing_references = true;
+
+        let reference_class = self.class_id("java/lang/ref/Reference").filter(|class_id| self.class(*class_id).state == ClassState::Initialized);
+        if let Some(reference_class) = reference_class.filter(|_| !self.reference_pending_list.is_null()) {
+            if let Some(method) = self.class(reference_class).find_method("processPendingReferences", "()V").cloned() {
+                let _ = self.invoke(reference_class, method, &[]);
+            }
+        }
+
+        while let Some(object) = self.pending_finalization.last().copied() {
+            let class_id = self.heap.get(object).map(|object| object.class);
+            if let Some((class_id, method)) = class_id.and_then(|class_id| self.select_method(class_id, "finalize", "()V")) {
+                let _ = self.invoke(class_id, method, &[Value::Reference(object)]);
+            }
+
+            self.pending_finalization.retain(|pending| *pending != object);
+        }
+
+        self.processing_references = false;
+    }
+
+    /// Runs `operation` with `values` treated as roots, for Rust code holding values across calls that may collect garbage
+    pub fn with_roots<T>(&mut self, values: &[Value], operation: impl FnOnce(&mut Self) -> T) -> T {
+        let mark = self.native_roots.len();
+        self.native_roots.extend_from_slice(values);
+
+        let result = operation(self);
+        self.native_roots.truncate(mark);
+
+        result
+    }
+
+    /// Collects garbage if allocating `bytes` would exceed the heap size limit, raises `OutOfMemoryError` if that didn't help,
+    /// see `VmContext::heap_exhausted`
+    pub fn ensure_heap_space(&mut self, bytes: usize) -> Result<(), Throwable> {
+        if self.heap.has_space(bytes) {
+            return Ok(());
+        }
+
+        self.collect_garbage();
+        if self.heap.has_space(bytes) {
+            return Ok(());
+        }
+
+        // Soft references are cleared before giving up
+        self.collect(true);
+        self.process_pending_references();
+
+        if self.heap.has_space(bytes) {
+            Ok(())
+        } else {
+            Err(self.heap_exhausted())
+        }
+    }
+
+}
\ No newline at end of file
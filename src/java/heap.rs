@@ -0,0 +1,642 @@
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicI32, AtomicI64, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::java::descriptor::FieldType;
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::Value;
+
+#[derive(Debug, Clone)]
+pub enum ArrayData {
+    /// Used for both `byte[]` and `boolean[]`
+    Byte(Vec<i8>),
+    Char(Vec<u16>),
+    Short(Vec<i16>),
+    Int(Vec<i32>),
+    Long(Vec<i64>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    Reference(Vec<u32>)
+}
+
+/// An instance field, an atomic of the width of its type so that `Unsafe.compareAndSet*` and `compareAndExchange*`
+/// replace it with a single `compare_exchange`. All accesses are sequentially consistent, which covers what the
+/// Java Memory Model guarantees for volatile fields.
+#[derive(Debug)]
+pub enum AtomicField {
+    Int(AtomicI32),
+    /// Bits of the `float`
+    Float(AtomicU32),
+    Long(AtomicI64),
+    /// Bits of the `double`
+    Double(AtomicU64),
+    Reference(AtomicU32)
+}
+
+#[derive(Debug, Clone)]
+pub enum ObjectData {
+    /// Instance fields, laid out as described by the class' `instance_fields`
+    Instance(Vec<AtomicField>),
+    Array(ArrayData)
+}
+
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub class: ClassId,
+    pub data: ObjectData
+}
+
+/// Heap size limit used unless configured otherwise, like `-Xmx512m`
+pub const DEFAULT_MAX_HEAP_SIZE: usize = 512 * 1024 * 1024;
+/// Heap usage at which the first collection runs
+const INITIAL_COLLECTION_THRESHOLD: usize = 8 * 1024 * 1024;
+
+/// Size of an object's heap slot, which holds its class and the pointer to its fields or elements
+const OBJECT_HEADER_SIZE: usize = std::mem::size_of::<Option<Object>>();
+
+#[derive(Debug, Clone, Default)]
+pub struct GcStats {
+    pub collections: u64,
+    pub freed_objects: u64,
+    pub freed_bytes: u64,
+    /// Objects and bytes that survived the last collection
+    pub live_objects: usize,
+    pub live_bytes: usize,
+    pub total_pause: Duration,
+    pub last_pause: Duration
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    Soft,
+    Weak,
+    Phantom
+}
+
+/// How the collector treats instances of a `java.lang.ref.Reference` class
+#[derive(Debug, Clone, Copy)]
+pub struct ReferenceType {
+    pub kind: ReferenceKind,
+    /// Instance field index of `Reference.referent`
+    pub referent_slot: usize
+}
+
+/// Result of a collection
+#[derive(Debug, Default)]
+pub struct Collection {
+    /// References whose referents were freed, or are only reachable through finalizable objects
+    pub cleared_references: Vec<u32>,
+    /// Unreachable objects whose `finalize()` has to run
+    pub finalize: Vec<u32>
+}
+
+/// Object storage, references are indices into `objects` and `0` is `null`
+#[derive(Debug)]
+pub struct Heap {
+    objects: Vec<Option<Object>>,
+    free_list: Vec<u32>,
+
+    /// Estimated size of all allocated objects, including unreachable ones
+    used_bytes: usize,
+    max_bytes: usize,
+    /// `used_bytes` at which the next collection is due
+    next_collection: usize,
+
+    /// Objects with a `finalize()` method that haven't been finalized yet
+    finalizable: HashSet<u32>,
+
+    pub stats: GcStats
+}
+
+/// Parses a heap size the way `-Xmx` does, e.g. `64m`, `2g` or `1048576`
+pub fn parse_heap_size(size: &str) -> Option<usize> {
+    let (digits, unit) = match size.char_indices().last()? {
+        (index, unit) if unit.is_ascii_alphabetic() => (&size[..index], unit.to_ascii_lowercase()),
+        _ => (size, 'b')
+    };
+
+    let multiplier: usize = match unit {
+        'b' => 1,
+        'k' => 1024,
+        'm' => 1024 * 1024,
+        'g' => 1024 * 1024 * 1024,
+        _ => return None
+    };
+
+    digits.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+impl ArrayData {
+
+    pub fn new(component_type: &FieldType, length: usize) -> Self {
+        match component_type {
+            FieldType::Boolean | FieldType::Byte    => ArrayData::Byte(vec![0; length]),
+            FieldType::Char                         => ArrayData::Char(vec![0; length]),
+            FieldType::Short                        => ArrayData::Short(vec![0; length]),
+            FieldType::Int                          => ArrayData::Int(vec![0; length]),
+            FieldType::Long                         => ArrayData::Long(vec![0; length]),
+            FieldType::Float                        => ArrayData::Float(vec![0.0; length]),
+            FieldType::Double                       => ArrayData::Double(vec![0.0; length]),
+            FieldType::Object(_) | FieldType::Array(_) => ArrayData::Reference(vec![0; length])
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ArrayData::Byte(elements)       => elements.len(),
+            ArrayData::Char(elements)       => elements.len(),
+            ArrayData::Short(elements)      => elements.len(),
+            ArrayData::Int(elements)        => elements.len(),
+            ArrayData::Long(elements)       => elements.len(),
+            ArrayData::Float(elements)      => elements.len(),
+            ArrayData::Double(elements)     => elements.len(),
+            ArrayData::Reference(elements)  => elements.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Element at `index`, which has to be in bounds
+    pub fn get(&self, index: usize) -> Value {
+        match self {
+            ArrayData::Byte(elements)       => Value::from_int(elements[index] as i32),
+            ArrayData::Char(elements)       => Value::from_int(elements[index] as i32),
+            ArrayData::Short(elements)      => Value::from_int(elements[index] as i32),
+            ArrayData::Int(elements)        => Value::from_int(elements[index]),
+            ArrayData::Long(elements)       => Value::from_long(elements[index]),
+            ArrayData::Float(elements)      => Value::from_float(elements[index]),
+            ArrayData::Double(elements)     => Value::from_double(elements[index]),
+            ArrayData::Reference(elements)  => Value::Reference(elements[index])
+        }
+    }
+
+    /// Stores `value` at `index`, narrowing integers to the element type
+    pub fn set(&mut self, index: usize, value: Value) {
+        match self {
+            ArrayData::Byte(elements)       => elements[index] = value.as_int() as i8,
+            ArrayData::Char(elements)       => elements[index] = value.as_int() as u16,
+            ArrayData::Short(elements)      => elements[index] = value.as_int() as i16,
+            ArrayData::Int(elements)        => elements[index] = value.as_int(),
+            ArrayData::Long(elements)       => elements[index] = value.as_long(),
+            ArrayData::Float(elements)      => elements[index] = value.as_float(),
+            ArrayData::Double(elements)     => elements[index] = value.as_double(),
+            ArrayData::Reference(elements)  => elements[index] = value.as_reference()
+        }
+    }
+
+    fn element_bits(&self, index: usize) -> u64 {
+        match self {
+            ArrayData::Byte(elements)       => elements[index] as u8 as u64,
+            ArrayData::Char(elements)       => elements[index] as u64,
+            ArrayData::Short(elements)      => elements[index] as u16 as u64,
+            ArrayData::Int(elements)        => elements[index] as u32 as u64,
+            ArrayData::Long(elements)       => elements[index] as u64,
+            ArrayData::Float(elements)      => elements[index].to_bits() as u64,
+            ArrayData::Double(elements)     => elements[index].to_bits(),
+            ArrayData::Reference(elements)  => elements[index] as u64
+        }
+    }
+
+    fn set_element_bits(&mut self, index: usize, bits: u64) {
+        match self {
+            ArrayData::Byte(elements)       => elements[index] = bits as u8 as i8,
+            ArrayData::Char(elements)       => elements[index] = bits as u16,
+            ArrayData::Short(elements)      => elements[index] = bits as u16 as i16,
+            ArrayData::Int(elements)        => elements[index] = bits as u32 as i32,
+            ArrayData::Long(elements)       => elements[index] = bits as i64,
+            ArrayData::Float(elements)      => elements[index] = f32::from_bits(bits as u32),
+            ArrayData::Double(elements)     => elements[index] = f64::from_bits(bits),
+            ArrayData::Reference(elements)  => elements[index] = bits as u32
+        }
+    }
+
+    /// Whether `width` bytes from byte `offset` are within the elements of a primitive array
+    fn covers_bits(&self, offset: usize, width: usize) -> bool {
+        !matches!(self, ArrayData::Reference(_)) && offset.checked_add(width).is_some_and(|end| end <= self.byte_size())
+    }
+
+    /// `width` bytes from byte `offset` of a primitive array, which `Unsafe` reads regardless of the element size,
+    /// e.g. an `int` out of a `byte[]`. The elements are laid out in little endian order.
+    pub fn get_bits(&self, offset: usize, width: usize) -> Option<u64> {
+        if !self.covers_bits(offset, width) {
+            return None;
+        }
+
+        let element_size = self.element_size();
+        let bits = (0..width).fold(0, |bits, byte| {
+            let position = offset + byte;
+            let element_byte = (self.element_bits(position / element_size) >> (8 * (position % element_size))) & 0xFF;
+
+            bits | element_byte << (8 * byte)
+        });
+
+        Some(bits)
+    }
+
+    /// Writes the low `width` bytes of `bits` from byte `offset` of a primitive array, see `get_bits`
+    pub fn set_bits(&mut self, offset: usize, width: usize, bits: u64) -> bool {
+        if !self.covers_bits(offset, width) {
+            return false;
+        }
+
+        let element_size = self.element_size();
+        for byte in 0..width {
+            let position = offset + byte;
+            let shift = 8 * (position % element_size);
+            let element = self.element_bits(position / element_size) & !(0xFF << shift);
+
+            self.set_element_bits(position / element_size, element | ((bits >> (8 * byte)) & 0xFF) << shift);
+        }
+
+        true
+    }
+
+    /// Bytes taken up by each element, also the index scale reported by `Unsafe`
+    pub fn element_size(&self) -> usize {
+        match self {
+            ArrayData::Byte(_)                              => 1,
+            ArrayData::Char(_) | ArrayData::Short(_)        => 2,
+            ArrayData::Int(_) | ArrayData::Float(_) | ArrayData::Reference(_) => 4,
+            ArrayData::Long(_) | ArrayData::Double(_)       => 8
+        }
+    }
+
+    /// Bytes taken up by the elements
+    pub fn byte_size(&self) -> usize {
+        self.len() * self.element_size()
+    }
+
+    /// Estimated size of an array object with the given element type and length
+    pub fn object_size(component_type: &FieldType, length: usize) -> usize {
+        let element_size = match component_type {
+            FieldType::Boolean | FieldType::Byte    => 1,
+            FieldType::Char | FieldType::Short      => 2,
+            FieldType::Long | FieldType::Double     => 8,
+            _                                       => 4
+        };
+
+        OBJECT_HEADER_SIZE.saturating_add(length.saturating_mul(element_size))
+    }
+
+    /// Copies `length` elements from `source` into this array, both arrays have to hold the same element type
+    pub fn copy_from(&mut self, destination_index: usize, source: &ArrayData, source_index: usize, length: usize) -> bool {
+        let source_range = source_index..source_index + length;
+        let destination_range = destination_index..destination_index + length;
+
+        match (self, source) {
+            (ArrayData::Byte(destination), ArrayData::Byte(source))             => destination[destination_range].copy_from_slice(&source[source_range]),
+            (ArrayData::Char(destination), ArrayData::Char(source))             => destination[destination_range].copy_from_slice(&source[source_range]),
+            (ArrayData::Short(destination), ArrayData::Short(source))           => destination[destination_range].copy_from_slice(&source[source_range]),
+            (ArrayData::Int(destination), ArrayData::Int(source))               => destination[destination_range].copy_from_slice(&source[source_range]),
+            (ArrayData::Long(destination), ArrayData::Long(source))             => destination[destination_range].copy_from_slice(&source[source_range]),
+            (ArrayData::Float(destination), ArrayData::Float(source))           => destination[destination_range].copy_from_slice(&source[source_range]),
+            (ArrayData::Double(destination), ArrayData::Double(source))         => destination[destination_range].copy_from_slice(&source[source_range]),
+            (ArrayData::Reference(destination), ArrayData::Reference(source))   => destination[destination_range].copy_from_slice(&source[source_range]),
+            _ => return false
+        }
+
+        true
+    }
+
+}
+
+impl AtomicField {
+
+    /// A field holding `value`, whose kind is the kind of the field from now on
+    pub fn new(value: Value) -> Self {
+        match value {
+            Value::Reference(reference) => AtomicField::Reference(AtomicU32::new(reference)),
+            Value::Float(bits) => AtomicField::Float(AtomicU32::new(bits)),
+            Value::Long(bits) => AtomicField::Long(AtomicI64::new(bits as i64)),
+            Value::Double(bits) => AtomicField::Double(AtomicU64::new(bits)),
+            Value::Integer(bits) => AtomicField::Int(AtomicI32::new(bits as i32)),
+            Value::None => AtomicField::Int(AtomicI32::new(0))
+        }
+    }
+
+    pub fn get(&self) -> Value {
+        match self {
+            AtomicField::Int(value) => Value::from_int(value.load(Ordering::SeqCst)),
+            AtomicField::Float(bits) => Value::Float(bits.load(Ordering::SeqCst)),
+            AtomicField::Long(value) => Value::from_long(value.load(Ordering::SeqCst)),
+            AtomicField::Double(bits) => Value::Double(bits.load(Ordering::SeqCst)),
+            AtomicField::Reference(reference) => Value::Reference(reference.load(Ordering::SeqCst))
+        }
+    }
+
+    /// Stores the bits of `value` of the width of the field
+    pub fn set(&self, value: Value) {
+        let bits = value.bits();
+
+        match self {
+            AtomicField::Int(field) => field.store(bits as i32, Ordering::SeqCst),
+            AtomicField::Float(field) => field.store(bits as u32, Ordering::SeqCst),
+            // Only references, the collector follows whatever a reference field holds
+            AtomicField::Reference(field) => field.store(value.as_reference(), Ordering::SeqCst),
+            AtomicField::Long(field) => field.store(bits as i64, Ordering::SeqCst),
+            AtomicField::Double(field) => field.store(bits, Ordering::SeqCst)
+        }
+    }
+
+    /// Replaces the field with `value` if its bits are those of `expected`, returns the previous value
+    pub fn compare_exchange(&self, expected: Value, value: Value) -> Value {
+        let (expected, bits) = (expected.bits(), value.bits());
+
+        match self {
+            AtomicField::Int(field) => Value::from_int(field.compare_exchange(expected as i32, bits as i32, Ordering::SeqCst, Ordering::SeqCst).unwrap_or_else(|current| current)),
+            AtomicField::Float(field) => Value::Float(field.compare_exchange(expected as u32, bits as u32, Ordering::SeqCst, Ordering::SeqCst).unwrap_or_else(|current| current)),
+            AtomicField::Long(field) => Value::from_long(field.compare_exchange(expected as i64, bits as i64, Ordering::SeqCst, Ordering::SeqCst).unwrap_or_else(|current| current)),
+            AtomicField::Double(field) => Value::Double(field.compare_exchange(expected, bits, Ordering::SeqCst, Ordering::SeqCst).unwrap_or_else(|current| current)),
+            AtomicField::Reference(field) => Value::Reference(field.compare_exchange(expected as u32, value.as_reference(), Ordering::SeqCst, Ordering::SeqCst).unwrap_or_else(|current| current))
+        }
+    }
+
+}
+
+impl Clone for AtomicField {
+
+    fn clone(&self) -> Self {
+        AtomicField::new(self.get())
+    }
+
+}
+
+impl Object {
+
+    /// Estimated number of bytes the object takes up
+    pub fn size(&self) -> usize {
+        match &self.data {
+            ObjectData::Instance(fields) => Object::instance_size(fields.len()),
+            ObjectData::Array(array) => OBJECT_HEADER_SIZE + array.byte_size()
+        }
+    }
+
+    /// Estimated size of an instance with the given number of fields
+    pub fn instance_size(field_count: usize) -> usize {
+        OBJECT_HEADER_SIZE + field_count * std::mem::size_of::<Value>()
+    }
+
+    /// References held by the object's fields or elements
+    pub fn references(&self) -> Vec<u32> {
+        match &self.data {
+            ObjectData::Instance(fields) => fields.iter()
+                .filter_map(|field| match field.get() {
+                    Value::Reference(reference) if reference != 0 => Some(reference),
+                    _ => None
+                })
+                .collect(),
+            ObjectData::Array(ArrayData::Reference(elements)) => elements.iter().copied().filter(|reference| *reference != 0).collect(),
+            ObjectData::Array(_) => vec![]
+        }
+    }
+
+    pub fn fields(&self) -> Option<&[AtomicField]> {
+        match &self.data {
+            ObjectData::Instance(fields) => Some(fields),
+            _ => None
+        }
+    }
+
+    /// Value of the instance field in a slot, `None` for arrays and slots the object doesn't have
+    pub fn field(&self, slot: usize) -> Option<Value> {
+        self.fields()?.get(slot).map(AtomicField::get)
+    }
+
+    /// Stores a value in the instance field in a slot, returns whether the object has that field
+    pub fn set_field(&self, slot: usize, value: Value) -> bool {
+        match self.fields().and_then(|fields| fields.get(slot)) {
+            Some(field) => {
+                field.set(value);
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn array(&self) -> Option<&ArrayData> {
+        match &self.data {
+            ObjectData::Array(array) => Some(array),
+            _ => None
+        }
+    }
+
+    pub fn array_mut(&mut self) -> Option<&mut ArrayData> {
+        match &mut self.data {
+            ObjectData::Array(array) => Some(array),
+            _ => None
+        }
+    }
+
+}
+
+impl Heap {
+
+    pub fn new() -> Self {
+        Heap {
+            objects: vec![ None ],
+            free_list: vec![],
+
+            used_bytes: 0,
+            max_bytes: DEFAULT_MAX_HEAP_SIZE,
+            next_collection: INITIAL_COLLECTION_THRESHOLD,
+
+            finalizable: HashSet::new(),
+
+            stats: GcStats::default()
+        }
+    }
+
+    pub fn allocate(&mut self, object: Object) -> u32 {
+        self.used_bytes += object.size();
+
+        if let Some(reference) = self.free_list.pop() {
+            self.objects[reference as usize] = Some(object);
+            return reference;
+        }
+
+        self.objects.push(Some(object));
+
+        (self.objects.len() - 1) as u32
+    }
+
+    pub fn get(&self, reference: u32) -> Option<&Object> {
+        self.objects.get(reference as usize)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, reference: u32) -> Option<&mut Object> {
+        self.objects.get_mut(reference as usize)?.as_mut()
+    }
+
+    /// Number of allocated objects, including unreachable ones
+    pub fn object_count(&self) -> usize {
+        self.objects.len() - 1 - self.free_list.len()
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Changes the heap size limit, the next collection is due before allocations reach it
+    pub fn set_max_bytes(&mut self, bytes: usize) {
+        self.max_bytes = bytes;
+        self.schedule_collection();
+    }
+
+    /// Whether allocating `bytes` more stays within the heap size limit
+    pub fn has_space(&self, bytes: usize) -> bool {
+        self.used_bytes.saturating_add(bytes) <= self.max_bytes
+    }
+
+    /// Whether enough was allocated since the last collection to run the next one
+    pub fn collection_due(&self) -> bool {
+        self.used_bytes >= self.next_collection
+    }
+
+    /// Collect again once the heap has doubled, collecting more often as it fills up. The collection is due before
+    /// the limit is reached, allocations by natives can't collect garbage and fail at it.
+    fn schedule_collection(&mut self) {
+        let live_bytes = self.used_bytes;
+        self.next_collection = (live_bytes * 2).max(INITIAL_COLLECTION_THRESHOLD);
+        if self.next_collection >= self.max_bytes {
+            self.next_collection = live_bytes + (self.max_bytes.saturating_sub(live_bytes) / 2).max(OBJECT_HEADER_SIZE);
+        }
+    }
+
+    /// Marks everything reachable from `pending`, not following the referents of reference objects.
+    /// Reference objects with a referent are added to `discovered`.
+    fn mark(&self, marked: &mut [bool], mut pending: Vec<u32>, reference_types: &[Option<ReferenceType>], clear_soft_references: bool, discovered: &mut Vec<u32>) {
+        while let Some(reference) = pending.pop() {
+            let index = reference as usize;
+            if index >= marked.len() || marked[index] {
+                continue;
+            }
+
+            let object = match &self.objects[index] {
+                Some(object) => object,
+                None => continue
+            };
+
+            marked[index] = true;
+
+            let reference_type = reference_types.get(object.class as usize).copied().flatten();
+            match (reference_type, object.fields()) {
+                (Some(reference_type), Some(fields)) => {
+                    for (slot, field) in fields.iter().enumerate() {
+                        if let Value::Reference(field) = field.get() {
+                            if field == 0 {
+                                continue;
+                            }
+
+                            let strong = reference_type.kind == ReferenceKind::Soft && !clear_soft_references;
+                            if slot == reference_type.referent_slot && !strong {
+                                discovered.push(reference);
+                            } else {
+                                pending.push(field);
+                            }
+                        }
+                    }
+                },
+                _ => pending.extend(object.references())
+            }
+        }
+    }
+
+    /// Clears the discovered references of the given kinds whose referents weren't marked and returns them,
+    /// the other discovered references stay in `discovered`
+    fn clear_references(&mut self, marked: &[bool], discovered: &mut Vec<u32>, reference_types: &[Option<ReferenceType>], kinds: &[ReferenceKind]) -> Vec<u32> {
+        let mut cleared = vec![];
+
+        for reference in std::mem::take(discovered) {
+            let object = match self.objects[reference as usize].as_ref() {
+                Some(object) => object,
+                None => continue
+            };
+
+            let reference_type = match reference_types.get(object.class as usize).copied().flatten() {
+                Some(reference_type) if kinds.contains(&reference_type.kind) => reference_type,
+                _ => {
+                    discovered.push(reference);
+                    continue;
+                }
+            };
+
+            let target = object.field(reference_type.referent_slot).map_or(0, |referent| referent.as_reference());
+            if target != 0 && !marked[target as usize] {
+                object.set_field(reference_type.referent_slot, Value::null());
+                cleared.push(reference);
+            }
+        }
+
+        cleared
+    }
+
+    /// Marks everything reachable from `roots` and frees all other objects.
+    /// `reference_types` describes the `java.lang.ref.Reference` classes by class id, soft references are only
+    /// cleared with `clear_soft_references`. Returns the cleared references and the objects that need to be finalized,
+    /// which survive this collection.
+    pub fn collect(&mut self, roots: impl IntoIterator<Item = u32>, reference_types: &[Option<ReferenceType>], clear_soft_references: bool) -> Collection {
+        let mut marked = vec![false; self.objects.len()];
+        let mut discovered = vec![];
+
+        self.mark(&mut marked, roots.into_iter().collect(), reference_types, clear_soft_references, &mut discovered);
+
+        // Soft and weak references are cleared before finalization, finalizable objects and everything they reach survive
+        let mut cleared = self.clear_references(&marked, &mut discovered, reference_types, &[ReferenceKind::Soft, ReferenceKind::Weak]);
+
+        let finalize: Vec<u32> = self.finalizable.iter().copied().filter(|reference| !marked[*reference as usize]).collect();
+        for reference in &finalize {
+            self.finalizable.remove(reference);
+        }
+        self.mark(&mut marked, finalize.clone(), reference_types, clear_soft_references, &mut discovered);
+
+        cleared.extend(self.clear_references(&marked, &mut discovered, reference_types, &[ReferenceKind::Soft, ReferenceKind::Weak, ReferenceKind::Phantom]));
+
+        let (mut freed_objects, mut freed_bytes) = (0, 0);
+        for (index, slot) in self.objects.iter_mut().enumerate().skip(1) {
+            if marked[index] {
+                continue;
+            }
+
+            if let Some(object) = slot.take() {
+                freed_objects += 1;
+                freed_bytes += object.size();
+                self.free_list.push(index as u32);
+            }
+        }
+
+        self.used_bytes -= freed_bytes;
+
+        let live_bytes = self.used_bytes;
+        self.schedule_collection();
+
+        self.stats.collections += 1;
+        self.stats.freed_objects += freed_objects;
+        self.stats.freed_bytes += freed_bytes as u64;
+        self.stats.live_objects = self.object_count();
+        self.stats.live_bytes = live_bytes;
+
+        Collection { cleared_references: cleared, finalize }
+    }
+
+    /// Runs `finalize()` of an object before it's freed
+    pub fn register_finalizer(&mut self, reference: u32) {
+        self.finalizable.insert(reference);
+    }
+
+}
+
+impl Default for Heap {
+
+    fn default() -> Self {
+        Self::new()
+    }
+
+}
\ No newline at end of file
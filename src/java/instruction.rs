@@ -0,0 +1,470 @@
+#![allow(dead_code)]
+
+use std::fmt;
+use std::fmt::Formatter;
+
+use crate::java::opcodes::Opcode;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    InvalidOpcode { pc: usize, value: u8 },
+    UnexpectedEnd { pc: usize },
+    InvalidWideOpcode { pc: usize, opcode: Opcode },
+    InvalidBranchTarget { pc: usize, target: i64 },
+    InvalidSwitchRange { pc: usize, low: i32, high: i32 },
+    NegativeLookupSwitchCount { pc: usize, count: i32 }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    InvalidOperand { pc: usize, opcode: Opcode },
+    BranchOutOfRange { pc: usize, target: u32 }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayType {
+    Boolean = 4,
+    Char    = 5,
+    Float   = 6,
+    Double  = 7,
+    Byte    = 8,
+    Short   = 9,
+    Int     = 10,
+    Long    = 11
+}
+
+/// A single decoded instruction. Branch targets are absolute offsets into the code array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    /// Instructions without any operands
+    Simple(Opcode),
+    /// bipush and sipush
+    Push(Opcode, i16),
+    /// Loads, stores and ret, `wide` is set if the instruction was prefixed by `wide`
+    Local { opcode: Opcode, index: u16, wide: bool },
+    Increment { index: u16, value: i16, wide: bool },
+    /// ldc, ldc_w, ldc2_w, field and method access, new, anewarray, checkcast and instanceof
+    ConstantPool(Opcode, u16),
+    InvokeInterface { index: u16, count: u8 },
+    InvokeDynamic { index: u16 },
+    Branch(Opcode, u32),
+    TableSwitch { default: u32, low: i32, high: i32, targets: Vec<u32> },
+    LookupSwitch { default: u32, pairs: Vec<(i32, u32)> },
+    NewArray(ArrayType),
+    MultiANewArray { index: u16, dimensions: u8 }
+}
+
+struct Reader<'a> {
+    code: &'a [u8],
+    pc: usize,
+    position: usize
+}
+
+impl<'a> Reader<'a> {
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        let value = *self.code.get(self.position).ok_or(DecodeError::UnexpectedEnd { pc: self.pc })?;
+        self.position += 1;
+
+        Ok(value)
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok((self.u8()? as u16) << 8 | self.u8()? as u16)
+    }
+
+    fn i16(&mut self) -> Result<i16, DecodeError> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn i32(&mut self) -> Result<i32, DecodeError> {
+        Ok(((self.u16()? as u32) << 16 | self.u16()? as u32) as i32)
+    }
+
+    fn target(&self, offset: i32) -> Result<u32, DecodeError> {
+        let target = self.pc as i64 + offset as i64;
+        if target < 0 || target >= self.code.len() as i64 {
+            return Err(DecodeError::InvalidBranchTarget { pc: self.pc, target });
+        }
+
+        Ok(target as u32)
+    }
+
+    fn align(&mut self) -> Result<(), DecodeError> {
+        while !self.position.is_multiple_of(4) {
+            self.u8()?;
+        }
+
+        Ok(())
+    }
+
+}
+
+impl ArrayType {
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            4  => Some(ArrayType::Boolean),
+            5  => Some(ArrayType::Char),
+            6  => Some(ArrayType::Float),
+            7  => Some(ArrayType::Double),
+            8  => Some(ArrayType::Byte),
+            9  => Some(ArrayType::Short),
+            10 => Some(ArrayType::Int),
+            11 => Some(ArrayType::Long),
+            _  => None
+        }
+    }
+
+    pub fn descriptor(&self) -> char {
+        match self {
+            ArrayType::Boolean  => 'Z',
+            ArrayType::Char     => 'C',
+            ArrayType::Float    => 'F',
+            ArrayType::Double   => 'D',
+            ArrayType::Byte     => 'B',
+            ArrayType::Short    => 'S',
+            ArrayType::Int      => 'I',
+            ArrayType::Long     => 'J'
+        }
+    }
+
+}
+
+impl Instruction {
+
+    pub fn opcode(&self) -> Opcode {
+        match self {
+            Instruction::Simple(opcode)                 => *opcode,
+            Instruction::Push(opcode, _)                => *opcode,
+            Instruction::Local { opcode, .. }           => *opcode,
+            Instruction::Increment { .. }               => Opcode::iinc,
+            Instruction::ConstantPool(opcode, _)        => *opcode,
+            Instruction::InvokeInterface { .. }         => Opcode::invokeinterface,
+            Instruction::InvokeDynamic { .. }           => Opcode::invokedynamic,
+            Instruction::Branch(opcode, _)              => *opcode,
+            Instruction::TableSwitch { .. }             => Opcode::tableswitch,
+            Instruction::LookupSwitch { .. }            => Opcode::lookupswitch,
+            Instruction::NewArray(_)                    => Opcode::newarray,
+            Instruction::MultiANewArray { .. }          => Opcode::multianewarray
+        }
+    }
+
+    /// Encoded length of this instruction in bytes when placed at `pc`
+    pub fn length(&self, pc: usize) -> usize {
+        let padding = (4 - (pc + 1) % 4) % 4;
+
+        match self {
+            Instruction::Simple(_)                      => 1,
+            Instruction::Push(opcode, _)                => if *opcode == Opcode::bipush { 2 } else { 3 },
+            Instruction::Local { wide, .. }             => if *wide { 4 } else { 2 },
+            Instruction::Increment { wide, .. }         => if *wide { 6 } else { 3 },
+            Instruction::ConstantPool(opcode, _)        => if *opcode == Opcode::ldc { 2 } else { 3 },
+            Instruction::InvokeInterface { .. }         => 5,
+            Instruction::InvokeDynamic { .. }           => 5,
+            Instruction::Branch(opcode, _)              => match opcode { Opcode::goto_w | Opcode::jsr_w => 5, _ => 3 },
+            Instruction::TableSwitch { targets, .. }    => 1 + padding + 12 + targets.len() * 4,
+            Instruction::LookupSwitch { pairs, .. }     => 1 + padding + 8 + pairs.len() * 8,
+            Instruction::NewArray(_)                    => 2,
+            Instruction::MultiANewArray { .. }          => 4
+        }
+    }
+
+    /// All branch targets of this instruction, including switch defaults
+    pub fn branch_targets(&self) -> Vec<u32> {
+        match self {
+            Instruction::Branch(_, target)                      => vec![ *target ],
+            Instruction::TableSwitch { default, targets, .. }   => {
+                let mut result = vec![ *default ];
+                result.extend(targets);
+                result
+            },
+            Instruction::LookupSwitch { default, pairs }        => {
+                let mut result = vec![ *default ];
+                result.extend(pairs.iter().map(|(_, target)| *target));
+                result
+            },
+            _ => vec![]
+        }
+    }
+
+    pub fn decode_at(code: &[u8], pc: usize) -> Result<Self, DecodeError> {
+        let mut reader = Reader { code, pc, position: pc };
+
+        let value = reader.u8()?;
+        let opcode = Opcode::try_from(value).map_err(|value| DecodeError::InvalidOpcode { pc, value })?;
+
+        let instruction = match opcode {
+            Opcode::bipush                              => Instruction::Push(opcode, reader.u8()? as i8 as i16),
+            Opcode::sipush                              => Instruction::Push(opcode, reader.i16()?),
+
+            Opcode::ldc                                 => Instruction::ConstantPool(opcode, reader.u8()? as u16),
+
+            Opcode::ldc_w | Opcode::ldc2_w |
+            Opcode::getstatic | Opcode::putstatic |
+            Opcode::getfield | Opcode::putfield |
+            Opcode::invokevirtual | Opcode::invokespecial | Opcode::invokestatic |
+            Opcode::new | Opcode::anewarray |
+            Opcode::checkcast | Opcode::instanceof      => Instruction::ConstantPool(opcode, reader.u16()?),
+
+            Opcode::iload | Opcode::lload | Opcode::fload | Opcode::dload | Opcode::aload |
+            Opcode::istore | Opcode::lstore | Opcode::fstore | Opcode::dstore | Opcode::astore |
+            Opcode::ret                                 => Instruction::Local { opcode, index: reader.u8()? as u16, wide: false },
+
+            Opcode::iinc                                => Instruction::Increment { index: reader.u8()? as u16, value: reader.u8()? as i8 as i16, wide: false },
+
+            Opcode::ifeq | Opcode::ifne | Opcode::iflt | Opcode::ifge | Opcode::ifgt | Opcode::ifle |
+            Opcode::if_icmpeq | Opcode::if_icmpne | Opcode::if_icmplt | Opcode::if_icmpge | Opcode::if_icmpgt | Opcode::if_icmple |
+            Opcode::if_acmpeq | Opcode::if_acmpne |
+            Opcode::goto | Opcode::jsr |
+            Opcode::ifnull | Opcode::ifnonnull          => {
+                let offset = reader.i16()? as i32;
+                Instruction::Branch(opcode, reader.target(offset)?)
+            },
+
+            Opcode::goto_w | Opcode::jsr_w              => {
+                let offset = reader.i32()?;
+                Instruction::Branch(opcode, reader.target(offset)?)
+            },
+
+            Opcode::tableswitch                         => {
+                reader.align()?;
+                let default = reader.i32()?;
+                let low = reader.i32()?;
+                let high = reader.i32()?;
+                if low > high {
+                    return Err(DecodeError::InvalidSwitchRange { pc, low, high });
+                }
+
+                let default = reader.target(default)?;
+                let mut targets = vec![];
+                for _ in low..=high {
+                    let offset = reader.i32()?;
+                    targets.push(reader.target(offset)?);
+                }
+
+                Instruction::TableSwitch { default, low, high, targets }
+            },
+
+            Opcode::lookupswitch                        => {
+                reader.align()?;
+                let default = reader.i32()?;
+                let default = reader.target(default)?;
+                let count = reader.i32()?;
+                if count < 0 {
+                    return Err(DecodeError::NegativeLookupSwitchCount { pc, count });
+                }
+
+                let mut pairs = vec![];
+                for _ in 0..count {
+                    let key = reader.i32()?;
+                    let offset = reader.i32()?;
+                    pairs.push((key, reader.target(offset)?));
+                }
+
+                Instruction::LookupSwitch { default, pairs }
+            },
+
+            Opcode::invokeinterface                     => {
+                let index = reader.u16()?;
+                let count = reader.u8()?;
+                reader.u8()?;
+
+                Instruction::InvokeInterface { index, count }
+            },
+
+            Opcode::invokedynamic                       => {
+                let index = reader.u16()?;
+                reader.u16()?;
+
+                Instruction::InvokeDynamic { index }
+            },
+
+            Opcode::newarray                            => {
+                let value = reader.u8()?;
+                let array_type = ArrayType::from_u8(value).ok_or(DecodeError::InvalidOpcode { pc, value })?;
+
+                Instruction::NewArray(array_type)
+            },
+
+            Opcode::multianewarray                      => Instruction::MultiANewArray { index: reader.u16()?, dimensions: reader.u8()? },
+
+            Opcode::wide                                => {
+                let value = reader.u8()?;
+                let opcode = Opcode::try_from(value).map_err(|value| DecodeError::InvalidOpcode { pc: pc + 1, value })?;
+
+                match opcode {
+                    Opcode::iload | Opcode::lload | Opcode::fload | Opcode::dload | Opcode::aload |
+                    Opcode::istore | Opcode::lstore | Opcode::fstore | Opcode::dstore | Opcode::astore |
+                    Opcode::ret                         => Instruction::Local { opcode, index: reader.u16()?, wide: true },
+                    Opcode::iinc                        => Instruction::Increment { index: reader.u16()?, value: reader.i16()?, wide: true },
+                    _                                   => return Err(DecodeError::InvalidWideOpcode { pc, opcode })
+                }
+            },
+
+            _                                           => Instruction::Simple(opcode)
+        };
+
+        Ok(instruction)
+    }
+
+    pub fn encode(&self, pc: usize, output: &mut Vec<u8>) -> Result<(), EncodeError> {
+        let offset = |target: u32| -> i64 { target as i64 - pc as i64 };
+        let short_offset = |target: u32| -> Result<i16, EncodeError> {
+            i16::try_from(offset(target)).map_err(|_| EncodeError::BranchOutOfRange { pc, target })
+        };
+        let long_offset = |target: u32| -> Result<i32, EncodeError> {
+            i32::try_from(offset(target)).map_err(|_| EncodeError::BranchOutOfRange { pc, target })
+        };
+        let invalid = || EncodeError::InvalidOperand { pc, opcode: self.opcode() };
+
+        match self {
+            Instruction::Simple(opcode)                 => output.push(*opcode as u8),
+            Instruction::Push(opcode, value)            => {
+                output.push(*opcode as u8);
+                if *opcode == Opcode::bipush {
+                    output.push(i8::try_from(*value).map_err(|_| invalid())? as u8);
+                } else {
+                    output.extend(value.to_be_bytes());
+                }
+            },
+            Instruction::Local { opcode, index, wide }  => {
+                if *wide {
+                    output.push(Opcode::wide as u8);
+                    output.push(*opcode as u8);
+                    output.extend(index.to_be_bytes());
+                } else {
+                    output.push(*opcode as u8);
+                    output.push(u8::try_from(*index).map_err(|_| invalid())?);
+                }
+            },
+            Instruction::Increment { index, value, wide } => {
+                if *wide {
+                    output.push(Opcode::wide as u8);
+                    output.push(Opcode::iinc as u8);
+                    output.extend(index.to_be_bytes());
+                    output.extend(value.to_be_bytes());
+                } else {
+                    output.push(Opcode::iinc as u8);
+                    output.push(u8::try_from(*index).map_err(|_| invalid())?);
+                    output.push(i8::try_from(*value).map_err(|_| invalid())? as u8);
+                }
+            },
+            Instruction::ConstantPool(opcode, index)    => {
+                output.push(*opcode as u8);
+                if *opcode == Opcode::ldc {
+                    output.push(u8::try_from(*index).map_err(|_| invalid())?);
+                } else {
+                    output.extend(index.to_be_bytes());
+                }
+            },
+            Instruction::InvokeInterface { index, count } => {
+                output.push(Opcode::invokeinterface as u8);
+                output.extend(index.to_be_bytes());
+                output.push(*count);
+                output.push(0);
+            },
+            Instruction::InvokeDynamic { index }        => {
+                output.push(Opcode::invokedynamic as u8);
+                output.extend(index.to_be_bytes());
+                output.extend([ 0, 0 ]);
+            },
+            Instruction::Branch(opcode, target)         => {
+                output.push(*opcode as u8);
+                match opcode {
+                    Opcode::goto_w | Opcode::jsr_w      => output.extend(long_offset(*target)?.to_be_bytes()),
+                    _                                   => output.extend(short_offset(*target)?.to_be_bytes())
+                }
+            },
+            Instruction::TableSwitch { default, low, high, targets } => {
+                output.push(Opcode::tableswitch as u8);
+                output.resize(output.len() + (4 - (pc + 1) % 4) % 4, 0);
+                output.extend(long_offset(*default)?.to_be_bytes());
+                output.extend(low.to_be_bytes());
+                output.extend(high.to_be_bytes());
+                for target in targets {
+                    output.extend(long_offset(*target)?.to_be_bytes());
+                }
+            },
+            Instruction::LookupSwitch { default, pairs } => {
+                output.push(Opcode::lookupswitch as u8);
+                output.resize(output.len() + (4 - (pc + 1) % 4) % 4, 0);
+                output.extend(long_offset(*default)?.to_be_bytes());
+                output.extend((pairs.len() as i32).to_be_bytes());
+                for (key, target) in pairs {
+                    output.extend(key.to_be_bytes());
+                    output.extend(long_offset(*target)?.to_be_bytes());
+                }
+            },
+            Instruction::NewArray(array_type)           => {
+                output.push(Opcode::newarray as u8);
+                output.push(*array_type as u8);
+            },
+            Instruction::MultiANewArray { index, dimensions } => {
+                output.push(Opcode::multianewarray as u8);
+                output.extend(index.to_be_bytes());
+                output.push(*dimensions);
+            }
+        };
+
+        Ok(())
+    }
+
+}
+
+/// Decodes a method's byte code into instructions paired with their offset in the code array
+pub fn decode_with_offsets(code: &[u8]) -> Result<Vec<(usize, Instruction)>, DecodeError> {
+    let mut result = vec![];
+
+    let mut pc = 0;
+    while pc < code.len() {
+        let instruction = Instruction::decode_at(code, pc)?;
+        let length = instruction.length(pc);
+
+        result.push((pc, instruction));
+        pc += length;
+    }
+
+    Ok(result)
+}
+
+pub fn decode(code: &[u8]) -> Result<Vec<Instruction>, DecodeError> {
+    Ok(decode_with_offsets(code)?.into_iter().map(|(_, instruction)| instruction).collect())
+}
+
+pub fn encode(instructions: &[Instruction]) -> Result<Vec<u8>, EncodeError> {
+    let mut result = vec![];
+
+    for instruction in instructions {
+        let pc = result.len();
+        instruction.encode(pc, &mut result)?;
+    }
+
+    Ok(result)
+}
+
+impl fmt::Display for DecodeError {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidOpcode { pc, value }            => write!(f, "Invalid opcode 0x{:02X} at pc {}", value, pc),
+            DecodeError::UnexpectedEnd { pc }                   => write!(f, "Unexpected end of code in instruction at pc {}", pc),
+            DecodeError::InvalidWideOpcode { pc, opcode }       => write!(f, "Opcode '{}' cannot be widened at pc {}", opcode, pc),
+            DecodeError::InvalidBranchTarget { pc, target }     => write!(f, "Branch target {} out of range at pc {}", target, pc),
+            DecodeError::InvalidSwitchRange { pc, low, high }   => write!(f, "Invalid switch range {}..{} at pc {}", low, high, pc),
+            DecodeError::NegativeLookupSwitchCount { pc, count } => write!(f, "Negative lookupswitch pair count {} at pc {}", count, pc)
+        }
+    }
+
+}
+
+impl fmt::Display for EncodeError {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::InvalidOperand { pc, opcode }          => write!(f, "Operand of '{}' doesn't fit its encoding at pc {}", opcode, pc),
+            EncodeError::BranchOutOfRange { pc, target }        => write!(f, "Branch target {} unreachable from pc {}", target, pc)
+        }
+    }
+
+}
\ No newline at end of file
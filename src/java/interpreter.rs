@@ -0,0 +1,1128 @@
+#![allow(dead_code)]
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crate::java;
+use crate::java::class::ConstantPoolEntry;
+use crate::java::decoded_code::{quickened, DecodedCode, DecodedInstruction, Dispatch, Op, ResolvedField, ResolvedMethod};
+use crate::java::dispatch::{CacheLookup, InlineCache, NO_SLOT};
+use crate::java::descriptor::{FieldType, MethodDescriptor};
+use crate::java::heap::{ArrayData, Object};
+use crate::java::invokedynamic::{CallSiteTarget, LambdaCall, LambdaProxy};
+use crate::java::opcodes::Opcode;
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::{Scope, Throwable, Value, VmContext};
+
+/// What the interpreter loop does after an instruction has been executed
+pub enum Flow {
+    Next,
+    Jump(usize),
+    /// A frame was pushed for a call, the caller continues after the invoke instruction once it returns
+    Invoke,
+    /// The thread has to wait, the instruction runs again once it continues
+    Retry,
+    Return(Option<Value>),
+    /// A safepoint is due, the frame continues at its program counter afterwards
+    Safepoint
+}
+
+fn compare<T: PartialOrd>(left: T, right: T, nan_result: i32) -> Value {
+    match left.partial_cmp(&right) {
+        Some(Ordering::Less)    => Value::from_int(-1),
+        Some(Ordering::Equal)   => Value::from_int(0),
+        Some(Ordering::Greater) => Value::from_int(1),
+        None                    => Value::from_int(nan_result)
+    }
+}
+
+fn array_index_out_of_bounds(index: i32, length: usize) -> Throwable {
+    Throwable::new("java/lang/ArrayIndexOutOfBoundsException", &format!("Index {} out of bounds for length {}", index, length))
+}
+
+fn invalid_constant(index: u16) -> Throwable {
+    Throwable::new("java/lang/ClassFormatError", &format!("Invalid constant pool index {}", index))
+}
+
+/// Java signature of a method for error messages, e.g. `'int java.lang.Object.hashCode()'`
+fn method_signature(class_name: &str, name: &str, descriptor: &str) -> String {
+    match MethodDescriptor::parse(descriptor) {
+        Some(method_descriptor) => {
+            let return_type = method_descriptor.return_type.map(|return_type| return_type.to_string()).unwrap_or_else(|| "void".to_string());
+            let parameters: Vec<String> = method_descriptor.parameters.iter().map(|parameter| parameter.to_string()).collect();
+
+            format!("'{} {}.{}({})'", return_type, class_name.replace('/', "."), name, parameters.join(", "))
+        },
+        None => format!("'{}.{}{}'", class_name.replace('/', "."), name, descriptor)
+    }
+}
+
+impl VmContext {
+
+    pub fn frame(&mut self) -> &mut Scope {
+        self.executor.frames.last_mut().expect("No active frame")
+    }
+
+    fn class_file_of(&self, class_id: ClassId) -> Arc<java::Class> {
+        self.class(class_id).class.clone().expect("Executing code of a class without a class file")
+    }
+
+    /// Invokes a method with its arguments, including `this` for instance methods, and runs it to completion
+    pub fn invoke(&mut self, class_id: ClassId, method: Arc<java::Method>, args: &[Value]) -> Result<Option<Value>, Throwable> {
+        if method.is_native() {
+            return self.invoke_native(class_id, &method, args);
+        }
+
+        if method.is_abstract() {
+            if let Some(proxy) = args.first().and_then(|receiver| self.lambda_proxy(*receiver)).filter(|proxy| proxy.implements(&method.name, &method.descriptor)) {
+                return self.invoke_lambda(&proxy, &method.descriptor, args);
+            }
+        }
+
+        let depth = self.executor.frames.len();
+        self.push_frame(class_id, method, args)?;
+
+        self.execute_byte_code(depth)
+    }
+
+    /// Invokes an instance method on `args[0]`, selecting the implementation by the class of the receiver like `invokevirtual`
+    pub fn invoke_virtual(&mut self, name: &str, descriptor: &str, args: &[Value]) -> Result<Option<Value>, Throwable> {
+        let receiver = args[0];
+        if receiver.is_null() {
+            return Err(Throwable::null_pointer());
+        }
+
+        if let Some(stream) = self.console.stream(receiver.as_reference()) {
+            if self.with_roots(args, |context| context.invoke_console_intrinsic(stream, name, descriptor, args))? {
+                return Ok(None);
+            }
+        }
+
+        let class_id = self.object(receiver)?.class;
+        let (selected_class, method) = self.select_method(class_id, name, descriptor)
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchMethodError", &method_signature(&self.class(class_id).name, name, descriptor)))?;
+
+        // Abstract methods are only invoked on lambdas, which implement them without a class file
+        if method.is_abstract() && self.lambda_proxy(receiver).is_none() {
+            return Err(Throwable::new("java/lang/AbstractMethodError", &method_signature(&self.class(class_id).name, name, descriptor)));
+        }
+
+        self.invoke(selected_class, method, args)
+    }
+
+    /// Invokes a static method of a class or its superclasses like `invokestatic`, initializing the declaring class first
+    pub fn invoke_static(&mut self, class_id: ClassId, name: &str, descriptor: &str, args: &[Value]) -> Result<Option<Value>, Throwable> {
+        let (method_class, method) = self.resolve_method(class_id, name, descriptor)
+            .filter(|(_, method)| method.is_static())
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchMethodError", &method_signature(&self.class(class_id).name, name, descriptor)))?;
+
+        self.initialize_class(method_class)?;
+        self.with_roots(args, |context| context.invoke(method_class, method, args))
+    }
+
+    fn invoke_native(&mut self, class_id: ClassId, method: &java::Method, args: &[Value]) -> Result<Option<Value>, Throwable> {
+        let class_name = &self.class(class_id).name;
+
+        let native = match self.natives.find(class_name, &method.name, &method.descriptor) {
+            Some(native) => native,
+            None => {
+                let signature = method_signature(class_name, &method.name, &method.descriptor);
+                if !self.missing_natives.contains(&signature) {
+                    self.missing_natives.push(signature.clone());
+                }
+
+                return Err(Throwable::new("java/lang/UnsatisfiedLinkError", &signature));
+            }
+        };
+
+        if native.reaches_host && self.limiter.limits().is_some() {
+            self.check_native_access(class_id, method)?;
+        }
+
+        match self.tracer.is_some() {
+            true => {
+                self.trace_entry(class_id, method, args);
+                let result = self.with_roots(args, |context| (native.method)(context, args));
+
+                match &result {
+                    Ok(value) => self.trace_return(class_id, method, *value),
+                    Err(throwable) => self.trace_throw(class_id, method, throwable)
+                }
+
+                result
+            },
+            false => self.with_roots(args, |context| (native.method)(context, args))
+        }
+    }
+
+    fn push_frame(&mut self, class_id: ClassId, method: Arc<java::Method>, args: &[Value]) -> Result<(), Throwable> {
+        if self.executor.frames.len() >= self.executor.max_stack_depth {
+            return Err(self.stack_exhausted());
+        }
+
+        if self.tracer.is_some() {
+            self.trace_entry(class_id, &method, args);
+        }
+
+        let scope = self.new_frame(class_id, method, args)?;
+        self.executor.frames.push(scope);
+
+        Ok(())
+    }
+
+    /// Frame for a call of a method with byte code
+    pub fn new_frame(&mut self, class_id: ClassId, method: Arc<java::Method>, args: &[Value]) -> Result<Scope, Throwable> {
+        let (code, decoded_code) = match (method.code(), self.decoded_code(class_id, &method)) {
+            (Some(code), Some(decoded_code)) => (code, decoded_code),
+            _ => return Err(Throwable::new("java/lang/AbstractMethodError", &method_signature(&self.class(class_id).name, &method.name, &method.descriptor)))
+        };
+
+        #[cfg(feature = "jit")]
+        decoded_code.jit.count_invocation();
+
+        let mut scope = Scope::new(class_id, method.clone(), decoded_code, code.max_locals as usize, code.max_stack as usize);
+
+        let mut index = 0;
+        for arg in args {
+            scope.store(index, *arg)?;
+            index += if arg.is_category2() { 2 } else { 1 };
+        }
+
+        if method.is_synchronized() {
+            scope.synchronized_on = Some(match method.is_static() {
+                true => self.class_mirror(class_id)?.as_reference(),
+                false => args.first().map_or(0, Value::as_reference)
+            });
+        }
+
+        Ok(scope)
+    }
+
+    /// Pops the innermost frame, exiting the monitor of a synchronized method
+    fn pop_frame(&mut self) {
+        if let Some(frame) = self.executor.frames.pop() {
+            if let Some(object) = frame.synchronized_on.filter(|_| frame.holds_monitor) {
+                let _ = self.exit_monitor(object);
+            }
+        }
+    }
+
+    /// Runs the frames above `depth` until the frame at `depth` returns or throws.
+    /// On a thread started by the scheduler this also returns when the thread has to wait, leaving its frames in place.
+    pub fn execute_byte_code(&mut self, depth: usize) -> Result<Option<Value>, Throwable> {
+        self.executor.activations += 1;
+        let result = self.run_frames(depth);
+        self.executor.activations -= 1;
+
+        result
+    }
+
+    fn run_frames(&mut self, depth: usize) -> Result<Option<Value>, Throwable> {
+        loop {
+            // Instruction boundaries are safepoints, all live values are reachable from the frames
+            if self.heap.collection_due() {
+                self.collect_garbage();
+            }
+
+            // They are also where threads take turns
+            if self.scheduler.remaining == 0 {
+                match self.switch_threads() {
+                    Ok(true) => { },
+                    Ok(false) => return Ok(None),
+                    Err(throwable) => {
+                        self.handle_exception(throwable, depth)?;
+                        continue;
+                    }
+                }
+            }
+
+            if self.limiter.countdown == 0 {
+                if let Err(throwable) = self.check_limits() {
+                    self.handle_exception(throwable, depth)?;
+                    continue;
+                }
+            }
+
+            let (class_id, method, code, monitor) = {
+                let frame = self.frame();
+                (frame.class, frame.method.clone(), frame.code.clone(), frame.synchronized_on.filter(|_| !frame.holds_monitor))
+            };
+
+            if let Some(object) = monitor {
+                if !self.try_enter_monitor(object) {
+                    self.block_on_monitor(object);
+                    continue;
+                }
+
+                self.frame().holds_monitor = true;
+            }
+
+            #[cfg(feature = "jit")]
+            let flow = match self.compiled_entry(class_id, &method, &code) {
+                Some(compiled) => self.run_compiled(class_id, &method, &code, compiled),
+                None => self.run_instructions(class_id, &method, &code)
+            };
+            #[cfg(not(feature = "jit"))]
+            let flow = self.run_instructions(class_id, &method, &code);
+
+            match flow {
+                Ok(Flow::Return(value)) => {
+                    if self.tracer.is_some() {
+                        self.trace_return(class_id, &method, value);
+                    }
+
+                    self.pop_frame();
+                    if self.executor.frames.len() == depth {
+                        return Ok(value);
+                    }
+
+                    self.return_to_caller(value);
+                },
+                Ok(_) => { },
+                Err(throwable) => self.handle_exception(throwable, depth)?
+            }
+        }
+    }
+
+    /// Runs instructions of the innermost frame until it calls or leaves a method, has to wait, or a safepoint is due
+    pub fn run_instructions(&mut self, class_id: ClassId, method: &java::Method, code: &DecodedCode) -> Result<Flow, Throwable> {
+        loop {
+            self.scheduler.remaining -= 1;
+            self.limiter.countdown -= 1;
+
+            let program_counter = self.frame().program_counter;
+            let instruction = code.at(program_counter)
+                .ok_or_else(|| Throwable::new("java/lang/VerifyError", &format!("No instruction starts at pc {}", program_counter)))?;
+
+            let flow = match self.tracer.is_some() {
+                true => self.trace_instruction(class_id, method, program_counter, |context| context.execute_instruction(class_id, instruction))?,
+                false => self.execute_instruction(class_id, instruction)?
+            };
+
+            match flow {
+                Flow::Next => self.frame().program_counter = instruction.next,
+                Flow::Jump(target) => {
+                    self.frame().program_counter = target;
+
+                    // Loops make methods hot, and continue in compiled code once the method is compiled
+                    #[cfg(feature = "jit")]
+                    if target <= program_counter && self.count_backedge(code) {
+                        return Ok(Flow::Safepoint);
+                    }
+                },
+                flow => return Ok(flow)
+            }
+
+            if self.scheduler.remaining == 0 || self.limiter.countdown == 0 || self.heap.collection_due() {
+                return Ok(Flow::Safepoint);
+            }
+        }
+    }
+
+    /// Pushes the return value onto the caller's stack and continues after its invoke instruction
+    fn return_to_caller(&mut self, value: Option<Value>) {
+        let frame = self.frame();
+        if let Some(value) = value {
+            frame.push(value);
+        }
+
+        frame.program_counter = frame.code.at(frame.program_counter).map(|instruction| instruction.next).unwrap_or(frame.program_counter + 3);
+    }
+
+    /// Unwinds frames until a matching exception handler is found, or the frame at `depth` was left
+    fn handle_exception(&mut self, throwable: Throwable, depth: usize) -> Result<(), Throwable> {
+        if let Throwable::Halt(_) = throwable {
+            while self.executor.frames.len() > depth {
+                self.pop_frame();
+            }
+
+            return Err(throwable);
+        }
+
+        let throwable = self.throwable_object(throwable);
+
+        loop {
+            if let Throwable::Object(reference) = &throwable {
+                let reference = *reference;
+                if let Some(handler) = self.find_exception_handler(Value::Reference(reference)) {
+                    let frame = self.frame();
+                    frame.stack.clear();
+                    frame.push(Value::Reference(reference));
+                    frame.program_counter = handler;
+
+                    return Ok(());
+                }
+            }
+
+            if self.tracer.is_some() {
+                let (class_id, method) = (self.frame().class, self.frame().method.clone());
+                self.trace_throw(class_id, &method, &throwable);
+            }
+
+            self.pop_frame();
+            if self.executor.frames.len() == depth {
+                return Err(throwable);
+            }
+        }
+    }
+
+    fn find_exception_handler(&mut self, exception: Value) -> Option<usize> {
+        let (class_id, method, program_counter) = {
+            let frame = self.frame();
+            (frame.class, frame.method.clone(), frame.program_counter)
+        };
+
+        let class = self.class_file_of(class_id);
+        for entry in &method.code()?.exception_table {
+            if program_counter < entry.start_pc as usize || program_counter >= entry.end_pc as usize {
+                continue;
+            }
+
+            if entry.catch_type == 0 {
+                return Some(entry.handler_pc as usize);
+            }
+
+            let catch_class = class.class_file.get_class_name(entry.catch_type as usize).and_then(|class_name| self.load_class(&class_name).ok());
+            if let Some(catch_class) = catch_class {
+                if self.is_instance_of(exception, catch_class).unwrap_or(false) {
+                    return Some(entry.handler_pc as usize);
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn execute_instruction(&mut self, class_id: ClassId, instruction: &DecodedInstruction) -> Result<Flow, Throwable> {
+        match &instruction.op {
+            Op::Simple(opcode) => self.execute_simple(*opcode),
+            Op::Const(value) => {
+                self.frame().push(*value);
+                Ok(Flow::Next)
+            },
+            Op::Load(index) => {
+                let frame = self.frame();
+                let value = frame.load(*index as usize)?;
+                frame.push(value);
+
+                Ok(Flow::Next)
+            },
+            Op::Store(index) => {
+                let frame = self.frame();
+                let value = frame.pop()?;
+                frame.store(*index as usize, value)?;
+
+                Ok(Flow::Next)
+            },
+            Op::Increment(index, value) => {
+                let frame = self.frame();
+                let local = frame.load(*index as usize)?.as_int();
+                frame.store(*index as usize, Value::from_int(local.wrapping_add(*value)))?;
+
+                Ok(Flow::Next)
+            },
+            Op::Ret(index) => Ok(Flow::Jump(self.frame().load(*index as usize)?.as_int() as usize)),
+            Op::Branch(opcode, target) => self.execute_branch(*opcode, *target, instruction.next),
+            Op::TableSwitch { default, low, targets } => {
+                let offset = self.frame().pop()?.as_int() as i64 - *low as i64;
+                let target = usize::try_from(offset).ok().and_then(|offset| targets.get(offset)).unwrap_or(default);
+
+                Ok(Flow::Jump(*target))
+            },
+            Op::LookupSwitch { default, pairs } => {
+                let key = self.frame().pop()?.as_int();
+                let target = pairs.iter().find(|(value, _)| *value == key).map(|(_, target)| *target).unwrap_or(*default);
+
+                Ok(Flow::Jump(target))
+            },
+            Op::NewArray(component_type) => {
+                let length = self.frame().peek(0)?.as_int();
+                self.ensure_heap_space(ArrayData::object_size(component_type, length.max(0) as usize))?;
+
+                let length = self.frame().pop()?.as_int();
+                let array = self.new_array(component_type, length)?;
+                self.frame().push(array);
+
+                Ok(Flow::Next)
+            },
+            Op::MultiANewArray(index, dimensions) => {
+                let class = self.class_file_of(class_id);
+                let class_name = class.class_file.get_class_name(*index as usize).ok_or_else(|| invalid_constant(*index))?;
+                let array_type = FieldType::parse(&class_name).ok_or_else(|| invalid_constant(*index))?;
+
+                let lengths = (0..*dimensions as usize).rev().map(|depth| Ok(self.frame().peek(depth)?.as_int())).collect::<Result<Vec<i32>, Throwable>>()?;
+                if let Some(length) = lengths.iter().find(|length| **length < 0) {
+                    return Err(Throwable::new("java/lang/NegativeArraySizeException", &length.to_string()));
+                }
+
+                // Every level of arrays is allocated in full, so they count as the size of reference arrays
+                let mut count = 1usize;
+                let mut size = 0usize;
+                for length in &lengths {
+                    size = size.saturating_add(count.saturating_mul(ArrayData::object_size(&FieldType::Int, *length as usize)));
+                    count = count.saturating_mul(*length as usize);
+                }
+                self.ensure_heap_space(size)?;
+
+                self.frame().pop_values(*dimensions as usize)?;
+
+                let array = self.new_multi_array(&array_type, &lengths)?;
+                self.frame().push(array);
+
+                Ok(Flow::Next)
+            },
+            Op::Ldc(index, value) => {
+                let value = *quickened(value, || self.resolve_constant(class_id, *index))?;
+                self.frame().push(value);
+
+                Ok(Flow::Next)
+            },
+            Op::Field(opcode, index, field) => {
+                let field = quickened(field, || self.resolve_field_reference(class_id, *opcode, *index))?;
+                self.execute_field(*opcode, field)
+            },
+            Op::Invoke(opcode, index, method) => {
+                let method = quickened(method, || self.resolve_method_reference(class_id, *opcode, *index))?;
+                self.execute_invoke(method)
+            },
+            Op::InvokeDynamic(index) => {
+                let call_site = self.link_call_site(class_id, *index)?;
+                let mut args = self.frame().pop_values(call_site.argument_count())?;
+
+                if let CallSiteTarget::Linked(invoker) = &call_site.target {
+                    args.extend(invoker.appendix.map(Value::Reference));
+                    self.push_frame(invoker.class, invoker.method.clone(), &args)?;
+
+                    return Ok(Flow::Invoke);
+                }
+
+                let value = self.with_roots(&args, |context| context.invoke_call_site(&call_site, &args))?;
+                self.frame().push(value);
+
+                Ok(Flow::Next)
+            },
+            Op::New(index, new_class) => {
+                let new_class = *quickened(new_class, || self.resolve_new(class_id, *index))?;
+
+                self.initialize_class(new_class)?;
+                self.ensure_heap_space(Object::instance_size(self.class(new_class).instance_fields.len()))?;
+
+                let object = self.new_object(new_class);
+                self.frame().push(object);
+
+                Ok(Flow::Next)
+            },
+            Op::ANewArray(index, component_type) => {
+                let component_type = quickened(component_type, || {
+                    let class_name = self.class_file_of(class_id).class_file.get_class_name(*index as usize).ok_or_else(|| invalid_constant(*index))?;
+                    match class_name.starts_with('[') {
+                        true => FieldType::parse(&class_name).ok_or_else(|| invalid_constant(*index)),
+                        false => Ok(FieldType::Object(class_name))
+                    }
+                })?;
+
+                let length = self.frame().peek(0)?.as_int();
+                self.ensure_heap_space(ArrayData::object_size(component_type, length.max(0) as usize))?;
+
+                let length = self.frame().pop()?.as_int();
+                let array = self.new_array(component_type, length)?;
+                self.frame().push(array);
+
+                Ok(Flow::Next)
+            },
+            Op::TypeCheck(opcode, index, target) => {
+                let target = *quickened(target, || {
+                    let class_name = self.class_file_of(class_id).class_file.get_class_name(*index as usize).ok_or_else(|| invalid_constant(*index))?;
+                    self.load_class(&class_name)
+                })?;
+
+                if *opcode == Opcode::instanceof {
+                    let object = self.frame().pop()?;
+                    let result = self.is_instance_of(object, target)?;
+                    self.frame().push(Value::from_bool(result));
+                } else {
+                    let object = self.frame().peek(0)?;
+                    if !object.is_null() && !self.is_instance_of(object, target)? {
+                        let object_class = self.object(object)?.class;
+
+                        return Err(Throwable::new("java/lang/ClassCastException", &format!("class {} cannot be cast to class {}",
+                            self.class(object_class).java_name(), self.class(target).java_name())));
+                    }
+                }
+
+                Ok(Flow::Next)
+            },
+            Op::Invalid(message) => Err(Throwable::new("java/lang/VerifyError", message))
+        }
+    }
+
+    fn new_multi_array(&mut self, array_type: &FieldType, lengths: &[i32]) -> Result<Value, Throwable> {
+        let component_type = match array_type {
+            FieldType::Array(component_type) => component_type.as_ref(),
+            _ => return Err(Throwable::new("java/lang/IncompatibleClassChangeError", "multianewarray of a non-array type"))
+        };
+
+        let array = self.new_array(component_type, lengths[0])?;
+        if lengths.len() > 1 {
+            for index in 0..lengths[0] as usize {
+                let element = self.new_multi_array(component_type, &lengths[1..])?;
+                if let Some(elements) = self.object_mut(array)?.array_mut() {
+                    elements.set(index, element);
+                }
+            }
+        }
+
+        Ok(array)
+    }
+
+    fn execute_simple(&mut self, opcode: Opcode) -> Result<Flow, Throwable> {
+        let code = opcode as u8;
+
+        if (Opcode::iaload as u8..=Opcode::saload as u8).contains(&code) {
+            let index = self.frame().pop()?.as_int();
+            let array = self.frame().pop()?;
+            let value = self.array_element(array, index)?;
+            self.frame().push(value);
+
+            return Ok(Flow::Next);
+        }
+
+        if (Opcode::iastore as u8..=Opcode::sastore as u8).contains(&code) {
+            let value = self.frame().pop()?;
+            let index = self.frame().pop()?.as_int();
+            let array = self.frame().pop()?;
+
+            if opcode == Opcode::aastore && !value.is_null() {
+                let array_class = self.object(array)?.class;
+                let component_class = match &self.class(array_class).component_type {
+                    Some(component_type) => self.class_id(&component_type.class_name()),
+                    None => None
+                };
+
+                if let Some(component_class) = component_class {
+                    if !self.is_instance_of(value, component_class)? {
+                        let value_class = self.object(value)?.class;
+                        return Err(Throwable::new("java/lang/ArrayStoreException", &self.class(value_class).java_name()));
+                    }
+                }
+            }
+
+            self.set_array_element(array, index, value)?;
+
+            return Ok(Flow::Next);
+        }
+
+        let frame = self.frame();
+        match opcode {
+            Opcode::nop => { },
+
+            Opcode::pop => { frame.pop()?; },
+            Opcode::pop2 => {
+                if !frame.pop()?.is_category2() {
+                    frame.pop()?;
+                }
+            },
+            Opcode::dup => {
+                let value = frame.peek(0)?;
+                frame.push(value);
+            },
+            Opcode::dup_x1 => {
+                let (value1, value2) = (frame.pop()?, frame.pop()?);
+                for value in [value1, value2, value1] { frame.push(value); }
+            },
+            Opcode::dup_x2 => {
+                let (value1, value2) = (frame.pop()?, frame.pop()?);
+                if value2.is_category2() {
+                    for value in [value1, value2, value1] { frame.push(value); }
+                } else {
+                    let value3 = frame.pop()?;
+                    for value in [value1, value3, value2, value1] { frame.push(value); }
+                }
+            },
+            Opcode::dup2 => {
+                if frame.peek(0)?.is_category2() {
+                    let value = frame.peek(0)?;
+                    frame.push(value);
+                } else {
+                    let (value1, value2) = (frame.peek(0)?, frame.peek(1)?);
+                    frame.push(value2);
+                    frame.push(value1);
+                }
+            },
+            Opcode::dup2_x1 => {
+                let (value1, value2) = (frame.pop()?, frame.pop()?);
+                if value1.is_category2() {
+                    for value in [value1, value2, value1] { frame.push(value); }
+                } else {
+                    let value3 = frame.pop()?;
+                    for value in [value2, value1, value3, value2, value1] { frame.push(value); }
+                }
+            },
+            Opcode::dup2_x2 => {
+                let (value1, value2) = (frame.pop()?, frame.pop()?);
+                if value1.is_category2() {
+                    if value2.is_category2() {
+                        for value in [value1, value2, value1] { frame.push(value); }
+                    } else {
+                        let value3 = frame.pop()?;
+                        for value in [value1, value3, value2, value1] { frame.push(value); }
+                    }
+                } else {
+                    let value3 = frame.pop()?;
+                    if value3.is_category2() {
+                        for value in [value2, value1, value3, value2, value1] { frame.push(value); }
+                    } else {
+                        let value4 = frame.pop()?;
+                        for value in [value2, value1, value4, value3, value2, value1] { frame.push(value); }
+                    }
+                }
+            },
+            Opcode::swap => {
+                let (value1, value2) = (frame.pop()?, frame.pop()?);
+                frame.push(value1);
+                frame.push(value2);
+            },
+
+            Opcode::iadd => frame.binary_int(i32::wrapping_add)?,
+            Opcode::ladd => frame.binary_long(i64::wrapping_add)?,
+            Opcode::fadd => frame.binary_float(|left, right| left + right)?,
+            Opcode::dadd => frame.binary_double(|left, right| left + right)?,
+            Opcode::isub => frame.binary_int(i32::wrapping_sub)?,
+            Opcode::lsub => frame.binary_long(i64::wrapping_sub)?,
+            Opcode::fsub => frame.binary_float(|left, right| left - right)?,
+            Opcode::dsub => frame.binary_double(|left, right| left - right)?,
+            Opcode::imul => frame.binary_int(i32::wrapping_mul)?,
+            Opcode::lmul => frame.binary_long(i64::wrapping_mul)?,
+            Opcode::fmul => frame.binary_float(|left, right| left * right)?,
+            Opcode::dmul => frame.binary_double(|left, right| left * right)?,
+            Opcode::idiv | Opcode::irem => {
+                if frame.peek(0)?.as_int() == 0 {
+                    return Err(Throwable::new("java/lang/ArithmeticException", "/ by zero"));
+                }
+
+                frame.binary_int(if opcode == Opcode::idiv { i32::wrapping_div } else { i32::wrapping_rem })?;
+            },
+            Opcode::ldiv | Opcode::lrem => {
+                if frame.peek(0)?.as_long() == 0 {
+                    return Err(Throwable::new("java/lang/ArithmeticException", "/ by zero"));
+                }
+
+                frame.binary_long(if opcode == Opcode::ldiv { i64::wrapping_div } else { i64::wrapping_rem })?;
+            },
+            Opcode::fdiv => frame.binary_float(|left, right| left / right)?,
+            Opcode::ddiv => frame.binary_double(|left, right| left / right)?,
+            Opcode::frem => frame.binary_float(|left, right| left % right)?,
+            Opcode::drem => frame.binary_double(|left, right| left % right)?,
+            Opcode::ineg => {
+                let value = frame.pop()?.as_int();
+                frame.push(Value::from_int(value.wrapping_neg()));
+            },
+            Opcode::lneg => {
+                let value = frame.pop()?.as_long();
+                frame.push(Value::from_long(value.wrapping_neg()));
+            },
+            Opcode::fneg => {
+                let value = frame.pop()?.as_float();
+                frame.push(Value::from_float(-value));
+            },
+            Opcode::dneg => {
+                let value = frame.pop()?.as_double();
+                frame.push(Value::from_double(-value));
+            },
+
+            Opcode::ishl => frame.binary_int(|left, right| left.wrapping_shl(right as u32))?,
+            Opcode::ishr => frame.binary_int(|left, right| left.wrapping_shr(right as u32))?,
+            Opcode::iushr => frame.binary_int(|left, right| (left as u32).wrapping_shr(right as u32) as i32)?,
+            Opcode::lshl | Opcode::lshr | Opcode::lushr => {
+                let shift = frame.pop()?.as_int() as u32;
+                let value = frame.pop()?.as_long();
+
+                frame.push(Value::from_long(match opcode {
+                    Opcode::lshl => value.wrapping_shl(shift),
+                    Opcode::lshr => value.wrapping_shr(shift),
+                    _ => (value as u64).wrapping_shr(shift) as i64
+                }));
+            },
+            Opcode::iand => frame.binary_int(|left, right| left & right)?,
+            Opcode::land => frame.binary_long(|left, right| left & right)?,
+            Opcode::ior => frame.binary_int(|left, right| left | right)?,
+            Opcode::lor => frame.binary_long(|left, right| left | right)?,
+            Opcode::ixor => frame.binary_int(|left, right| left ^ right)?,
+            Opcode::lxor => frame.binary_long(|left, right| left ^ right)?,
+
+            Opcode::i2l => { let value = frame.pop()?.as_int(); frame.push(Value::from_long(value as i64)); },
+            Opcode::i2f => { let value = frame.pop()?.as_int(); frame.push(Value::from_float(value as f32)); },
+            Opcode::i2d => { let value = frame.pop()?.as_int(); frame.push(Value::from_double(value as f64)); },
+            Opcode::l2i => { let value = frame.pop()?.as_long(); frame.push(Value::from_int(value as i32)); },
+            Opcode::l2f => { let value = frame.pop()?.as_long(); frame.push(Value::from_float(value as f32)); },
+            Opcode::l2d => { let value = frame.pop()?.as_long(); frame.push(Value::from_double(value as f64)); },
+            Opcode::f2i => { let value = frame.pop()?.as_float(); frame.push(Value::from_int(value as i32)); },
+            Opcode::f2l => { let value = frame.pop()?.as_float(); frame.push(Value::from_long(value as i64)); },
+            Opcode::f2d => { let value = frame.pop()?.as_float(); frame.push(Value::from_double(value as f64)); },
+            Opcode::d2i => { let value = frame.pop()?.as_double(); frame.push(Value::from_int(value as i32)); },
+            Opcode::d2l => { let value = frame.pop()?.as_double(); frame.push(Value::from_long(value as i64)); },
+            Opcode::d2f => { let value = frame.pop()?.as_double(); frame.push(Value::from_float(value as f32)); },
+            Opcode::i2b => { let value = frame.pop()?.as_int(); frame.push(Value::from_int(value as i8 as i32)); },
+            Opcode::i2c => { let value = frame.pop()?.as_int(); frame.push(Value::from_int(value as u16 as i32)); },
+            Opcode::i2s => { let value = frame.pop()?.as_int(); frame.push(Value::from_int(value as i16 as i32)); },
+
+            Opcode::lcmp => {
+                let (right, left) = (frame.pop()?.as_long(), frame.pop()?.as_long());
+                frame.push(compare(left, right, 0));
+            },
+            Opcode::fcmpl | Opcode::fcmpg => {
+                let (right, left) = (frame.pop()?.as_float(), frame.pop()?.as_float());
+                frame.push(compare(left, right, if opcode == Opcode::fcmpl { -1 } else { 1 }));
+            },
+            Opcode::dcmpl | Opcode::dcmpg => {
+                let (right, left) = (frame.pop()?.as_double(), frame.pop()?.as_double());
+                frame.push(compare(left, right, if opcode == Opcode::dcmpl { -1 } else { 1 }));
+            },
+
+            Opcode::ireturn | Opcode::lreturn | Opcode::freturn | Opcode::dreturn | Opcode::areturn => return Ok(Flow::Return(Some(frame.pop()?))),
+            Opcode::r#return => return Ok(Flow::Return(None)),
+
+            Opcode::arraylength => {
+                let array = frame.pop()?;
+                let length = self.array_length(array)?;
+                self.frame().push(Value::from_int(length as i32));
+            },
+            Opcode::athrow => {
+                let exception = frame.pop()?;
+                if exception.is_null() {
+                    return Err(Throwable::null_pointer());
+                }
+
+                return Err(Throwable::Object(exception.as_reference()));
+            },
+            Opcode::monitorenter => {
+                let object = frame.pop()?;
+                if object.is_null() {
+                    return Err(Throwable::null_pointer());
+                }
+
+                if !self.try_enter_monitor(object.as_reference()) {
+                    self.frame().push(object);
+                    self.block_on_monitor(object.as_reference());
+
+                    return Ok(Flow::Retry);
+                }
+            },
+            Opcode::monitorexit => {
+                let object = frame.pop()?;
+                if object.is_null() {
+                    return Err(Throwable::null_pointer());
+                }
+
+                self.exit_monitor(object.as_reference())?;
+            },
+
+            _ => return Err(Throwable::new("java/lang/VerifyError", &format!("Unexpected opcode {}", opcode)))
+        }
+
+        Ok(Flow::Next)
+    }
+
+    pub fn array_element(&self, array: Value, index: i32) -> Result<Value, Throwable> {
+        let elements = self.object(array)?.array().ok_or_else(|| Throwable::new("java/lang/IncompatibleClassChangeError", "Not an array"))?;
+        if index < 0 || index as usize >= elements.len() {
+            return Err(array_index_out_of_bounds(index, elements.len()));
+        }
+
+        Ok(elements.get(index as usize))
+    }
+
+    pub fn set_array_element(&mut self, array: Value, index: i32, value: Value) -> Result<(), Throwable> {
+        let elements = self.object_mut(array)?.array_mut().ok_or_else(|| Throwable::new("java/lang/IncompatibleClassChangeError", "Not an array"))?;
+        if index < 0 || index as usize >= elements.len() {
+            return Err(array_index_out_of_bounds(index, elements.len()));
+        }
+
+        elements.set(index as usize, value);
+
+        Ok(())
+    }
+
+    fn execute_branch(&mut self, opcode: Opcode, target: usize, next: usize) -> Result<Flow, Throwable> {
+        let frame = self.frame();
+
+        let taken = match opcode {
+            Opcode::goto | Opcode::goto_w => true,
+            Opcode::jsr | Opcode::jsr_w => {
+                frame.push(Value::from_int(next as i32));
+                true
+            },
+
+            Opcode::ifeq => frame.pop()?.as_int() == 0,
+            Opcode::ifne => frame.pop()?.as_int() != 0,
+            Opcode::iflt => frame.pop()?.as_int() < 0,
+            Opcode::ifge => frame.pop()?.as_int() >= 0,
+            Opcode::ifgt => frame.pop()?.as_int() > 0,
+            Opcode::ifle => frame.pop()?.as_int() <= 0,
+
+            Opcode::ifnull => frame.pop()?.is_null(),
+            Opcode::ifnonnull => !frame.pop()?.is_null(),
+
+            Opcode::if_acmpeq | Opcode::if_acmpne => {
+                let (right, left) = (frame.pop()?.as_reference(), frame.pop()?.as_reference());
+                (left == right) == (opcode == Opcode::if_acmpeq)
+            },
+
+            _ => {
+                let (right, left) = (frame.pop()?.as_int(), frame.pop()?.as_int());
+                match opcode {
+                    Opcode::if_icmpeq => left == right,
+                    Opcode::if_icmpne => left != right,
+                    Opcode::if_icmplt => left < right,
+                    Opcode::if_icmpge => left >= right,
+                    Opcode::if_icmpgt => left > right,
+                    _ => left <= right
+                }
+            }
+        };
+
+        Ok(if taken { Flow::Jump(target) } else { Flow::Next })
+    }
+
+    /// Value of an `ldc` of a string, a class, a method type, a method handle or a dynamic constant, numbers are decoded into `Op::Const`
+    fn resolve_constant(&mut self, class_id: ClassId, index: u16) -> Result<Value, Throwable> {
+        let class = self.class_file_of(class_id);
+
+        match class.class_file.constant_pool.get(index as usize - 1).ok_or_else(|| invalid_constant(index))? {
+            ConstantPoolEntry::Dynamic(_, _) => self.dynamic_constant(class_id, index),
+            ConstantPoolEntry::MethodType(_) | ConstantPoolEntry::MethodHandle(_, _) => self.method_handle_constant(class_id, index),
+            ConstantPoolEntry::StringReference(_) => self.constant_pool_string(&class, index)?.ok_or_else(|| invalid_constant(index)),
+            ConstantPoolEntry::ClassReference(_) => {
+                let class_name = class.class_file.get_class_name(index as usize).ok_or_else(|| invalid_constant(index))?;
+                let class_id = self.load_class(&class_name)?;
+
+                self.class_mirror(class_id)
+            },
+            entry => Err(Throwable::new("java/lang/InternalError", &format!("Unsupported constant {:?}", entry)))
+        }
+    }
+
+    fn resolve_field_reference(&mut self, class_id: ClassId, opcode: Opcode, index: u16) -> Result<ResolvedField, Throwable> {
+        let class = self.class_file_of(class_id);
+        let (class_name, name, descriptor) = class.class_file.get_member_reference(index as usize).ok_or_else(|| invalid_constant(index))?;
+
+        let target = self.load_class(&class_name)?;
+        let declaring_class = self.resolve_field(target, &name, &descriptor)
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchFieldError", &name))?;
+
+        match opcode {
+            Opcode::getstatic | Opcode::putstatic => Ok(ResolvedField::Static { class: declaring_class, default: Value::default_for(&descriptor), name }),
+            _ => {
+                let slot = self.class(declaring_class).field_slot(declaring_class, &name)
+                    .ok_or_else(|| Throwable::new("java/lang/IncompatibleClassChangeError", &format!("Expected non-static field {}.{}", class_name, name)))?;
+
+                Ok(ResolvedField::Instance { slot, name })
+            }
+        }
+    }
+
+    fn execute_field(&mut self, opcode: Opcode, field: &ResolvedField) -> Result<Flow, Throwable> {
+        match field {
+            ResolvedField::Static { class, name, default } => {
+                self.initialize_class(*class)?;
+
+                if opcode == Opcode::getstatic {
+                    let value = self.class(*class).static_values.get(name).copied().unwrap_or(*default);
+                    self.frame().push(value);
+                } else {
+                    let value = self.frame().pop()?;
+                    self.classes[*class as usize].static_values.insert(name.clone(), value);
+                }
+            },
+            ResolvedField::Instance { slot, name } => {
+                if opcode == Opcode::getfield {
+                    let object = self.frame().pop()?;
+                    let value = self.object(object)?.field(*slot)
+                        .ok_or_else(|| Throwable::new("java/lang/IncompatibleClassChangeError", name))?;
+                    self.frame().push(value);
+                } else {
+                    let value = self.frame().pop()?;
+                    let object = self.frame().pop()?;
+                    if !self.object(object)?.set_field(*slot, value) {
+                        return Err(Throwable::new("java/lang/IncompatibleClassChangeError", name));
+                    }
+                }
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn resolve_new(&mut self, class_id: ClassId, index: u16) -> Result<ClassId, Throwable> {
+        let class_name = self.class_file_of(class_id).class_file.get_class_name(index as usize).ok_or_else(|| invalid_constant(index))?;
+        let new_class = self.load_class(&class_name)?;
+
+        let flags = self.class(new_class).access_flags;
+        if flags & (java::access_flags::ACC_INTERFACE | java::access_flags::ACC_ABSTRACT) != 0 {
+            return Err(Throwable::new("java/lang/InstantiationError", &class_name.replace('/', ".")));
+        }
+
+        Ok(new_class)
+    }
+
+    fn resolve_method_reference(&mut self, class_id: ClassId, opcode: Opcode, index: u16) -> Result<ResolvedMethod, Throwable> {
+        let class = self.class_file_of(class_id);
+        let (class_name, name, descriptor) = class.class_file.get_member_reference(index as usize).ok_or_else(|| invalid_constant(index))?;
+        let method_descriptor = MethodDescriptor::parse(&descriptor).ok_or_else(|| invalid_constant(index))?;
+
+        let target = self.load_class(&class_name)?;
+        let argument_count = method_descriptor.parameters.len() + if opcode == Opcode::invokestatic { 0 } else { 1 };
+
+        // Signature polymorphic methods take any descriptor, most of them are linked to an invoker by Java code (JVMS 5.4.3.3)
+        if let Some(method) = self.signature_polymorphic_method(target, &name) {
+            let (class, method, dispatch) = match self.intrinsic(target, &method) {
+                Some(intrinsic) => (target, method, Dispatch::Intrinsic(intrinsic)),
+                None => {
+                    let invoker = self.link_method(class_id, target, &name, &descriptor)?;
+                    (invoker.class, invoker.method, Dispatch::Linked(invoker.appendix))
+                }
+            };
+
+            return Ok(ResolvedMethod { class, method, argument_count, dispatch, slot: NO_SLOT, cache: InlineCache::new() });
+        }
+
+        let (resolved_class, resolved_method) = self.resolve_method(target, &name, &descriptor)
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchMethodError", &method_signature(&class_name, &name, &descriptor)))?;
+
+        let (class, method, dispatch) = match opcode {
+            Opcode::invokestatic => {
+                if !resolved_method.is_static() {
+                    return Err(Throwable::new("java/lang/IncompatibleClassChangeError", &format!("Expected static method {}", method_signature(&class_name, &name, &descriptor))));
+                }
+
+                (resolved_class, resolved_method, Dispatch::Static)
+            },
+            // invokespecial only dispatches for super calls, constructors and private methods are invoked directly
+            Opcode::invokespecial => {
+                let is_super_call = name != "<init>" && !resolved_method.is_private() && resolved_class != class_id &&
+                    !self.class(resolved_class).is_interface() && self.is_subclass_of(class_id, resolved_class);
+
+                match self.class(class_id).super_class.filter(|_| is_super_call) {
+                    Some(super_class) => {
+                        let (selected_class, method) = self.select_method(super_class, &name, &descriptor)
+                            .filter(|(_, method)| !method.is_abstract())
+                            .ok_or_else(|| Throwable::new("java/lang/AbstractMethodError", &method_signature(&self.class(super_class).name, &name, &descriptor)))?;
+
+                        (selected_class, method, Dispatch::Direct)
+                    },
+                    None => (resolved_class, resolved_method, Dispatch::Direct)
+                }
+            },
+            _ if resolved_method.is_private() => (resolved_class, resolved_method, Dispatch::Direct),
+            _ => (resolved_class, resolved_method, Dispatch::Virtual)
+        };
+
+        let slot = match dispatch {
+            Dispatch::Virtual => self.class(class).dispatch.slot(&name, &descriptor).unwrap_or(NO_SLOT),
+            _ => NO_SLOT
+        };
+
+        Ok(ResolvedMethod { class, method, argument_count, dispatch, slot, cache: InlineCache::new() })
+    }
+
+    fn execute_invoke(&mut self, resolved: &ResolvedMethod) -> Result<Flow, Throwable> {
+        let (name, descriptor) = (&resolved.method.name, &resolved.method.descriptor);
+        let mut args = self.frame().pop_values(resolved.argument_count)?;
+
+        let (selected_class, method) = match resolved.dispatch {
+            Dispatch::Static => {
+                self.initialize_class(resolved.class)?;
+                (resolved.class, resolved.method.clone())
+            },
+            Dispatch::Intrinsic(intrinsic) => {
+                let (selected_class, method, argument_count) = self.with_roots(&args, |context| context.intrinsic_target(intrinsic, &args))?;
+                args.truncate(argument_count);
+
+                if let Some(proxy) = args.first().and_then(|receiver| self.lambda_proxy(*receiver)).filter(|proxy| method.is_abstract() && proxy.implements(&method.name, &method.descriptor)) {
+                    return self.call_lambda(&proxy, &method.descriptor, &args);
+                }
+
+                (selected_class, method)
+            },
+            Dispatch::Linked(appendix) => {
+                args.extend(appendix.map(Value::Reference));
+                (resolved.class, resolved.method.clone())
+            },
+            dispatch => {
+                let receiver = args[0];
+                if receiver.is_null() {
+                    return Err(Throwable::null_pointer());
+                }
+
+                if let Some(stream) = self.console.stream(receiver.as_reference()) {
+                    if self.with_roots(&args, |context| context.invoke_console_intrinsic(stream, name, descriptor, &args))? {
+                        return Ok(Flow::Next);
+                    }
+                }
+
+                // Whether a lambda proxy implements the method depends on its class only, so cached receivers aren't
+                let receiver_class = self.object(receiver)?.class;
+                let lookup = match dispatch {
+                    Dispatch::Virtual => Some(self.lookup_inline_cache(&resolved.cache, receiver_class)),
+                    _ => None
+                };
+                let cached = match lookup {
+                    Some(CacheLookup::Monomorphic(slot) | CacheLookup::Polymorphic(slot)) => Some(slot),
+                    _ => None
+                };
+
+                if let Some(proxy) = self.lambda_proxy(receiver).filter(|proxy| cached.is_none() && proxy.implements(name, descriptor)) {
+                    return self.call_lambda(&proxy, descriptor, &args);
+                }
+
+                match dispatch {
+                    Dispatch::Virtual => {
+                        let slot = cached.or_else(|| {
+                            let slot = self.select_slot(receiver_class, resolved)?;
+                            if let Some(CacheLookup::Miss) = lookup {
+                                resolved.cache.insert(receiver_class, slot);
+                            }
+
+                            Some(slot)
+                        });
+
+                        slot.map(|slot| self.class(receiver_class).dispatch.vtable[slot as usize].clone())
+                            .filter(|(_, method)| !method.is_abstract())
+                            .ok_or_else(|| Throwable::new("java/lang/AbstractMethodError", &method_signature(&self.class(receiver_class).name, name, descriptor)))?
+                    },
+                    _ => (resolved.class, resolved.method.clone())
+                }
+            }
+        };
+
+        if method.is_native() {
+            if let Some(value) = self.invoke_native(selected_class, &method, &args)? {
+                self.frame().push(value);
+            }
+
+            return Ok(Flow::Next);
+        }
+
+        self.push_frame(selected_class, method, &args)?;
+
+        Ok(Flow::Invoke)
+    }
+
+    /// Calls the implementation of a lambda in a new frame, or pushes what it returned if it ran to completion
+    fn call_lambda(&mut self, proxy: &LambdaProxy, descriptor: &str, args: &[Value]) -> Result<Flow, Throwable> {
+        match self.with_roots(args, |context| context.lambda_call(proxy, descriptor, args))? {
+            LambdaCall::Frame(class_id, method, args) => {
+                self.push_frame(class_id, method, &args)?;
+                Ok(Flow::Invoke)
+            },
+            LambdaCall::Returned(value) => {
+                if let Some(value) = value {
+                    self.frame().push(value);
+                }
+
+                Ok(Flow::Next)
+            }
+        }
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,684 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::java;
+use crate::java::access_flags;
+use crate::java::class::ConstantPoolEntry;
+use crate::java::descriptor::{FieldType, MethodDescriptor};
+use crate::java::dispatch::DispatchTable;
+use crate::java::heap::AtomicField;
+use crate::java::method_handle::Invoker;
+use crate::java::runtime_class::{ClassId, ClassState, FieldSlot, RuntimeClass};
+use crate::java::string::decode_modified_utf8;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+pub const REF_GET_FIELD: u8             = 1;
+pub const REF_GET_STATIC: u8            = 2;
+pub const REF_PUT_FIELD: u8             = 3;
+pub const REF_PUT_STATIC: u8            = 4;
+pub const REF_INVOKE_VIRTUAL: u8        = 5;
+pub const REF_INVOKE_STATIC: u8         = 6;
+pub const REF_INVOKE_SPECIAL: u8        = 7;
+pub const REF_NEW_INVOKE_SPECIAL: u8    = 8;
+pub const REF_INVOKE_INTERFACE: u8      = 9;
+
+/// `LambdaMetafactory.altMetafactory` flags
+const FLAG_SERIALIZABLE: i32    = 1;
+const FLAG_MARKERS: i32         = 2;
+const FLAG_BRIDGES: i32         = 4;
+
+/// A CONSTANT_MethodHandle entry
+#[derive(Debug, Clone)]
+pub struct MethodHandle {
+    pub kind: u8,
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String
+}
+
+/// A resolved static argument of a bootstrap method
+#[derive(Debug, Clone)]
+pub enum BootstrapArgument {
+    Constant(FieldType, Value),
+    /// UTF-16 code units of a string constant
+    String(Vec<u16>),
+    Class(String),
+    MethodType(String),
+    MethodHandle(MethodHandle)
+}
+
+#[derive(Debug, Clone)]
+pub enum RecipeElement {
+    Argument,
+    /// UTF-16 code units, so that unpaired surrogates survive concatenation
+    Constant(Vec<u16>)
+}
+
+#[derive(Debug)]
+pub enum CallSiteTarget {
+    /// `StringConcatFactory`, concatenates the recipe with the arguments filled in
+    Concat(Vec<RecipeElement>),
+    /// `LambdaMetafactory`, creates an instance of the proxy class capturing the arguments
+    Lambda(ClassId),
+    /// Any other bootstrap method, run by `MethodHandleNatives.linkCallSite`
+    Linked(Invoker)
+}
+
+/// A linked `invokedynamic` instruction
+#[derive(Debug)]
+pub struct CallSite {
+    pub parameters: Vec<FieldType>,
+    pub target: CallSiteTarget
+}
+
+/// Synthetic class implementing a functional interface by calling a method handle
+#[derive(Debug)]
+pub struct LambdaProxy {
+    pub method_name: String,
+    /// The erased interface method descriptor followed by the bridges
+    pub descriptors: Vec<String>,
+    pub captured: Vec<FieldType>,
+    pub implementation: MethodHandle
+}
+
+/// How the implementation of a lambda is run
+pub enum LambdaCall {
+    /// The implementation can be run in a new frame, its result is what the lambda returns
+    Frame(ClassId, Arc<java::Method>, Vec<Value>),
+    Returned(Option<Value>)
+}
+
+fn bootstrap_method_error(message: &str) -> Throwable {
+    Throwable::new("java/lang/BootstrapMethodError", message)
+}
+
+pub fn wrapper_class(field_type: &FieldType) -> Option<&'static str> {
+    Some(match field_type {
+        FieldType::Boolean  => "java/lang/Boolean",
+        FieldType::Byte     => "java/lang/Byte",
+        FieldType::Char     => "java/lang/Character",
+        FieldType::Short    => "java/lang/Short",
+        FieldType::Int      => "java/lang/Integer",
+        FieldType::Long     => "java/lang/Long",
+        FieldType::Float    => "java/lang/Float",
+        FieldType::Double   => "java/lang/Double",
+        _ => return None
+    })
+}
+
+/// Primitive widening conversion, other conversions leave the value unchanged
+pub fn widen(value: Value, from: &FieldType, to: &FieldType) -> Value {
+    let is_int = matches!(from, FieldType::Byte | FieldType::Short | FieldType::Char | FieldType::Int);
+
+    match to {
+        FieldType::Long if is_int                                       => Value::from_long(value.as_int() as i64),
+        FieldType::Float if is_int                                      => Value::from_float(value.as_int() as f32),
+        FieldType::Float if *from == FieldType::Long                    => Value::from_float(value.as_long() as f32),
+        FieldType::Double if is_int                                     => Value::from_double(value.as_int() as f64),
+        FieldType::Double if *from == FieldType::Long                   => Value::from_double(value.as_long() as f64),
+        FieldType::Double if *from == FieldType::Float                  => Value::from_double(value.as_float() as f64),
+        _ => value
+    }
+}
+
+impl CallSite {
+
+    pub fn argument_count(&self) -> usize {
+        self.parameters.len()
+    }
+
+}
+
+impl LambdaProxy {
+
+    pub fn implements(&self, name: &str, descriptor: &str) -> bool {
+        self.method_name == name && self.descriptors.iter().any(|candidate| candidate == descriptor)
+    }
+
+}
+
+impl VmContext {
+
+    fn method_handle(&self, class: &java::Class, index: u16) -> Result<MethodHandle, Throwable> {
+        let (kind, class_name, name, descriptor) = class.class_file.get_method_handle(index as usize)
+            .ok_or_else(|| Throwable::new("java/lang/ClassFormatError", &format!("Invalid method handle #{}", index)))?;
+
+        Ok(MethodHandle { kind, class_name, name, descriptor })
+    }
+
+    fn bootstrap_argument(&mut self, class_id: ClassId, class: &java::Class, index: u16) -> Result<BootstrapArgument, Throwable> {
+        let invalid = || Throwable::new("java/lang/ClassFormatError", &format!("Invalid bootstrap argument #{}", index));
+
+        let argument = match class.class_file.constant_pool.get((index as usize).wrapping_sub(1)).ok_or_else(invalid)? {
+            ConstantPoolEntry::Integer(_)   => BootstrapArgument::Constant(FieldType::Int, self.constant_value(class, index).ok_or_else(invalid)?),
+            ConstantPoolEntry::Float(_)     => BootstrapArgument::Constant(FieldType::Float, self.constant_value(class, index).ok_or_else(invalid)?),
+            ConstantPoolEntry::Long(_, _)   => BootstrapArgument::Constant(FieldType::Long, self.constant_value(class, index).ok_or_else(invalid)?),
+            ConstantPoolEntry::Double(_, _) => BootstrapArgument::Constant(FieldType::Double, self.constant_value(class, index).ok_or_else(invalid)?),
+            ConstantPoolEntry::StringReference(string_index) => match class.class_file.constant_pool.get((*string_index as usize).wrapping_sub(1)) {
+                Some(ConstantPoolEntry::String { length: _, string }) => BootstrapArgument::String(decode_modified_utf8(string)),
+                _ => return Err(invalid())
+            },
+            ConstantPoolEntry::ClassReference(_) => BootstrapArgument::Class(class.class_file.get_class_name(index as usize).ok_or_else(invalid)?),
+            ConstantPoolEntry::MethodType(descriptor_index) => BootstrapArgument::MethodType(class.class_file.get_constant_pool_string(*descriptor_index as usize).ok_or_else(invalid)?),
+            ConstantPoolEntry::MethodHandle(_, _) => BootstrapArgument::MethodHandle(self.method_handle(class, index)?),
+            ConstantPoolEntry::Dynamic(_, name_and_type) => {
+                let (_, descriptor) = class.class_file.get_name_and_type(*name_and_type as usize).ok_or_else(invalid)?;
+                let field_type = FieldType::parse(&descriptor).ok_or_else(invalid)?;
+
+                BootstrapArgument::Constant(field_type, self.dynamic_constant(class_id, index)?)
+            },
+            _ => return Err(invalid())
+        };
+
+        Ok(argument)
+    }
+
+    /// Bootstrap method handle and resolved static arguments of a bootstrap method table entry
+    fn bootstrap_method(&mut self, class_id: ClassId, class: &java::Class, index: u16) -> Result<(MethodHandle, Vec<BootstrapArgument>), Throwable> {
+        let bootstrap_method = class.bootstrap_methods.get(index as usize).cloned()
+            .ok_or_else(|| Throwable::new("java/lang/ClassFormatError", &format!("Invalid bootstrap method index {}", index)))?;
+
+        let handle = self.method_handle(class, bootstrap_method.bootstrap_method_ref)?;
+        let arguments = bootstrap_method.bootstrap_arguments.iter()
+            .map(|argument| self.bootstrap_argument(class_id, class, *argument))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((handle, arguments))
+    }
+
+    /// Links the call site of an `invokedynamic` instruction, call sites are linked once per instruction
+    pub fn link_call_site(&mut self, class_id: ClassId, index: u16) -> Result<Arc<CallSite>, Throwable> {
+        if let Some(call_site) = self.call_sites.get(&(class_id, index)) {
+            return Ok(call_site.clone());
+        }
+
+        let class = self.class(class_id).class.clone().ok_or_else(|| bootstrap_method_error("Class without class file"))?;
+        let invalid = || Throwable::new("java/lang/ClassFormatError", &format!("Invalid invokedynamic constant #{}", index));
+
+        let (bootstrap_index, name_and_type) = match class.class_file.constant_pool.get((index as usize).wrapping_sub(1)) {
+            Some(ConstantPoolEntry::InvokeDynamic(bootstrap_index, name_and_type)) => (*bootstrap_index, *name_and_type),
+            _ => return Err(invalid())
+        };
+
+        let (name, descriptor) = class.class_file.get_name_and_type(name_and_type as usize).ok_or_else(invalid)?;
+        let method_descriptor = MethodDescriptor::parse(&descriptor).ok_or_else(invalid)?;
+        let (handle, arguments) = self.bootstrap_method(class_id, &class, bootstrap_index)?;
+
+        let target = match (handle.class_name.as_str(), handle.name.as_str()) {
+            ("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants") => CallSiteTarget::Concat(self.concat_recipe(&arguments)?),
+            ("java/lang/invoke/StringConcatFactory", "makeConcat") =>
+                CallSiteTarget::Concat(vec![RecipeElement::Argument; method_descriptor.parameters.len()]),
+            ("java/lang/invoke/LambdaMetafactory", "metafactory") | ("java/lang/invoke/LambdaMetafactory", "altMetafactory") =>
+                CallSiteTarget::Lambda(self.lambda_proxy_class(class_id, &name, &method_descriptor, &arguments, handle.name == "altMetafactory")?),
+            _ => CallSiteTarget::Linked(self.bootstrap_call_site(class_id, index, &handle, &arguments, &name, &descriptor)?)
+        };
+
+        let call_site = Arc::new(CallSite { parameters: method_descriptor.parameters, target });
+        self.call_sites.insert((class_id, index), call_site.clone());
+
+        Ok(call_site)
+    }
+
+    /// Runs a linked call site with the arguments popped off the operand stack
+    pub fn invoke_call_site(&mut self, call_site: &CallSite, args: &[Value]) -> Result<Value, Throwable> {
+        match &call_site.target {
+            CallSiteTarget::Concat(recipe) => {
+                let mut result = vec![];
+                let mut arguments = call_site.parameters.iter().zip(args);
+
+                for element in recipe {
+                    match element {
+                        RecipeElement::Constant(constant) => result.extend_from_slice(constant),
+                        RecipeElement::Argument => {
+                            let (field_type, value) = arguments.next().ok_or_else(|| bootstrap_method_error("Mismatched concat recipe"))?;
+                            result.extend(self.java_string_of(field_type, *value)?);
+                        }
+                    }
+                }
+
+                self.new_string_from_utf16(&result)
+            },
+            CallSiteTarget::Lambda(proxy_class) => {
+                let lambda = self.new_object(*proxy_class);
+                if let Some(fields) = self.object(lambda)?.fields() {
+                    for (field, value) in fields.iter().zip(args) {
+                        field.set(*value);
+                    }
+                }
+
+                Ok(lambda)
+            },
+            CallSiteTarget::Linked(invoker) => {
+                let args: Vec<Value> = args.iter().copied().chain(invoker.appendix.map(Value::Reference)).collect();
+
+                Ok(self.invoke(invoker.class, invoker.method.clone(), &args)?.unwrap_or(Value::null()))
+            }
+        }
+    }
+
+    /// The object a static argument is passed to a bootstrap method as
+    fn bootstrap_argument_object(&mut self, caller: ClassId, argument: &BootstrapArgument) -> Result<Value, Throwable> {
+        match argument {
+            BootstrapArgument::Constant(field_type, value) => self.box_value(*value, field_type),
+            BootstrapArgument::String(string) => {
+                let string = self.new_string_from_utf16(string)?;
+                self.intern_string(string)
+            },
+            BootstrapArgument::Class(class_name) => {
+                let class_id = self.load_class(class_name)?;
+                self.class_mirror(class_id)
+            },
+            BootstrapArgument::MethodType(descriptor) => self.method_type(descriptor),
+            BootstrapArgument::MethodHandle(handle) => self.method_handle_object(caller, handle)
+        }
+    }
+
+    /// The bootstrap method handle, name, type and static arguments passed to the link methods of `MethodHandleNatives`, rooted
+    /// until the enclosing `with_roots` returns
+    fn bootstrap_objects(&mut self, caller: ClassId, handle: &MethodHandle, arguments: &[BootstrapArgument], name: &str, member_type: Value) -> Result<[Value; 5], Throwable> {
+        let caller_mirror = self.class_mirror(caller)?;
+        let bootstrap_method = self.method_handle_object(caller, handle)?;
+        self.rooted(bootstrap_method);
+        let name = self.interned_string(name)?;
+        self.rooted(name);
+        let static_arguments = self.new_reference_array("java/lang/Object", arguments, |context, argument| context.bootstrap_argument_object(caller, argument))?;
+        self.rooted(static_arguments);
+
+        Ok([caller_mirror, bootstrap_method, name, member_type, static_arguments])
+    }
+
+    /// Links a call site by running its bootstrap method through `MethodHandleNatives.linkCallSite`
+    fn bootstrap_call_site(&mut self, caller: ClassId, index: u16, handle: &MethodHandle, arguments: &[BootstrapArgument], name: &str, descriptor: &str) -> Result<Invoker, Throwable> {
+        self.with_roots(&[], |context| {
+            let method_type = context.method_type(descriptor)?;
+            context.rooted(method_type);
+            let [caller_mirror, bootstrap_method, name, method_type, static_arguments] = context.bootstrap_objects(caller, handle, arguments, name, method_type)?;
+            let appendix = context.new_array(&FieldType::Object("java/lang/Object".to_string()), 1)?;
+            context.rooted(appendix);
+
+            let member_name = context.link_upcall("linkCallSite", "(Ljava/lang/Object;ILjava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/invoke/MemberName;",
+                &[caller_mirror, Value::from_int(index as i32), bootstrap_method, name, method_type, static_arguments, appendix])?;
+
+            context.invoker(member_name, appendix)
+        })
+    }
+
+    /// Resolves a dynamic constant by running its bootstrap method through `MethodHandleNatives.linkDynamicConstant`
+    fn bootstrap_constant(&mut self, caller: ClassId, index: u16, handle: &MethodHandle, arguments: &[BootstrapArgument], name: &str, field_type: &FieldType) -> Result<Value, Throwable> {
+        let constant = self.with_roots(&[], |context| {
+            let constant_type = context.type_mirror(Some(field_type))?;
+            let [caller_mirror, bootstrap_method, name, constant_type, static_arguments] = context.bootstrap_objects(caller, handle, arguments, name, constant_type)?;
+
+            context.link_upcall("linkDynamicConstant", "(Ljava/lang/Object;ILjava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+                &[caller_mirror, Value::from_int(index as i32), bootstrap_method, name, constant_type, static_arguments])
+        })?;
+
+        // Primitive constants come back boxed
+        self.with_roots(&[constant], |context| context.unbox_value(constant, field_type))
+    }
+
+    /// Resolves a CONSTANT_MethodType or CONSTANT_MethodHandle entry for `ldc`, constants are resolved once per entry
+    pub fn method_handle_constant(&mut self, class_id: ClassId, index: u16) -> Result<Value, Throwable> {
+        if let Some(value) = self.dynamic_constants.get(&(class_id, index)) {
+            return Ok(*value);
+        }
+
+        let class = self.class(class_id).class.clone().ok_or_else(|| bootstrap_method_error("Class without class file"))?;
+        let argument = self.bootstrap_argument(class_id, &class, index)?;
+        let value = self.bootstrap_argument_object(class_id, &argument)?;
+        self.dynamic_constants.insert((class_id, index), value);
+
+        Ok(value)
+    }
+
+    fn concat_recipe(&mut self, arguments: &[BootstrapArgument]) -> Result<Vec<RecipeElement>, Throwable> {
+        let recipe = match arguments.first() {
+            Some(BootstrapArgument::String(recipe)) => recipe.clone(),
+            _ => return Err(bootstrap_method_error("makeConcatWithConstants without a recipe"))
+        };
+
+        let mut constants = arguments[1..].iter();
+        let mut elements = vec![];
+        let mut text = vec![];
+
+        for unit in recipe {
+            let element = match unit {
+                1 => RecipeElement::Argument,
+                2 => RecipeElement::Constant(match constants.next() {
+                    Some(BootstrapArgument::String(constant)) => constant.clone(),
+                    Some(BootstrapArgument::Constant(field_type, value)) => self.java_string_of(field_type, *value)?,
+                    _ => return Err(bootstrap_method_error("Missing concat constant"))
+                }),
+                unit => {
+                    text.push(unit);
+                    continue;
+                }
+            };
+
+            if !text.is_empty() {
+                elements.push(RecipeElement::Constant(std::mem::take(&mut text)));
+            }
+            elements.push(element);
+        }
+
+        if !text.is_empty() {
+            elements.push(RecipeElement::Constant(text));
+        }
+
+        Ok(elements)
+    }
+
+    /// Creates the class of the lambdas of a `LambdaMetafactory` call site
+    fn lambda_proxy_class(&mut self, caller: ClassId, method_name: &str, descriptor: &MethodDescriptor, arguments: &[BootstrapArgument], alternate: bool) -> Result<ClassId, Throwable> {
+        let (method_type, implementation) = match arguments {
+            [BootstrapArgument::MethodType(method_type), BootstrapArgument::MethodHandle(implementation), BootstrapArgument::MethodType(_), ..] =>
+                (method_type.clone(), implementation.clone()),
+            _ => return Err(bootstrap_method_error("Invalid LambdaMetafactory arguments"))
+        };
+
+        let interface_name = match &descriptor.return_type {
+            Some(FieldType::Object(interface_name)) => interface_name.clone(),
+            _ => return Err(bootstrap_method_error("LambdaMetafactory call site doesn't return an interface"))
+        };
+
+        let mut interface_names = vec![interface_name];
+        let mut descriptors = vec![method_type];
+
+        if alternate {
+            let flags = match arguments.get(3) {
+                Some(BootstrapArgument::Constant(FieldType::Int, flags)) => flags.as_int(),
+                _ => return Err(bootstrap_method_error("Invalid altMetafactory flags"))
+            };
+
+            let mut rest = arguments[4..].iter();
+            let mut counted = |flag: i32| -> Result<Vec<BootstrapArgument>, Throwable> {
+                if flags & flag == 0 {
+                    return Ok(vec![]);
+                }
+
+                match rest.next() {
+                    Some(BootstrapArgument::Constant(FieldType::Int, count)) => Ok(rest.by_ref().take(count.as_int().max(0) as usize).cloned().collect()),
+                    _ => Err(bootstrap_method_error("Invalid altMetafactory arguments"))
+                }
+            };
+
+            let markers = counted(FLAG_MARKERS)?;
+            let bridges = counted(FLAG_BRIDGES)?;
+
+            interface_names.extend(markers.into_iter().filter_map(|marker| match marker {
+                BootstrapArgument::Class(marker) => Some(marker),
+                _ => None
+            }));
+            descriptors.extend(bridges.into_iter().filter_map(|bridge| match bridge {
+                BootstrapArgument::MethodType(bridge) => Some(bridge),
+                _ => None
+            }));
+
+            if flags & FLAG_SERIALIZABLE != 0 {
+                interface_names.push("java/io/Serializable".to_string());
+            }
+        }
+
+        let super_class = self.load_class("java/lang/Object")?;
+        let interfaces = interface_names.iter().map(|interface_name| self.load_class(interface_name)).collect::<Result<Vec<_>, _>>()?;
+
+        let id = self.classes.len() as ClassId;
+        let name = format!("{}$$Lambda${}", self.class(caller).name, self.lambda_proxies.len() + 1);
+        let instance_fields = descriptor.parameters.iter().enumerate()
+            .map(|(index, parameter)| FieldSlot { declaring_class: id, name: format!("arg${}", index + 1), descriptor: parameter.descriptor() })
+            .collect();
+
+        self.classes.push(RuntimeClass {
+            id,
+            name: name.clone(),
+
+            class: None,
+            access_flags: access_flags::ACC_FINAL | access_flags::ACC_SYNTHETIC,
+
+            decoded_code: HashMap::new(),
+            dispatch: DispatchTable::default(),
+
+            super_class: Some(super_class),
+            interfaces,
+
+            component_type: None,
+
+            instance_fields,
+            static_values: HashMap::new(),
+
+            mirror: 0,
+
+            has_finalizer: false,
+
+            state: ClassState::Initialized
+        });
+        self.class_ids.insert(name, id);
+        self.link_dispatch_table(id);
+
+        self.lambda_proxies.insert(id, Arc::new(LambdaProxy {
+            method_name: method_name.to_string(),
+            descriptors,
+            captured: descriptor.parameters.clone(),
+            implementation
+        }));
+
+        Ok(id)
+    }
+
+    /// The proxy a lambda object is an instance of
+    pub fn lambda_proxy(&self, receiver: Value) -> Option<Arc<LambdaProxy>> {
+        let class_id = self.heap.get(receiver.as_reference())?.class;
+
+        self.lambda_proxies.get(&class_id).cloned()
+    }
+
+    /// Boxes a primitive value with `valueOf` of its wrapper class, references are returned as they are
+    pub fn box_value(&mut self, value: Value, field_type: &FieldType) -> Result<Value, Throwable> {
+        let wrapper = match wrapper_class(field_type) {
+            Some(wrapper) => wrapper,
+            None => return Ok(value)
+        };
+
+        let class_id = self.load_class(wrapper)?;
+        self.initialize_class(class_id)?;
+
+        let descriptor = format!("({})L{};", field_type.descriptor(), wrapper);
+        let (class_id, method) = self.resolve_method(class_id, "valueOf", &descriptor)
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchMethodError", &format!("{}.valueOf{}", wrapper, descriptor)))?;
+
+        Ok(self.invoke(class_id, method, &[value])?.unwrap_or(Value::null()))
+    }
+
+    /// Unboxes a wrapper object with its `<type>Value()` method, references are returned as they are
+    pub fn unbox_value(&mut self, value: Value, field_type: &FieldType) -> Result<Value, Throwable> {
+        let name = match field_type {
+            FieldType::Boolean => "booleanValue".to_string(),
+            FieldType::Char => "charValue".to_string(),
+            field_type if !field_type.is_reference() => format!("{}Value", field_type),
+            _ => return Ok(value)
+        };
+
+        let object_class = self.object(value)?.class;
+        let descriptor = format!("(){}", field_type.descriptor());
+        let (class_id, method) = self.select_method(object_class, &name, &descriptor)
+            .ok_or_else(|| Throwable::new("java/lang/ClassCastException", &format!("{} cannot be unboxed to {}", self.class(object_class).java_name(), field_type)))?;
+
+        Ok(self.invoke(class_id, method, &[value])?.unwrap_or(Value::from_int(0)))
+    }
+
+    /// Converts a value passed to or returned from a lambda to the type expected on the other side
+    fn adapt_value(&mut self, value: Value, from: &FieldType, to: &FieldType) -> Result<Value, Throwable> {
+        match (from.is_reference(), to.is_reference()) {
+            (true, true)    => Ok(value),
+            (false, true)   => self.box_value(value, from),
+            (true, false)   => {
+                let unboxed_type = match from {
+                    FieldType::Object(class_name) => ["Z", "B", "C", "S", "I", "J", "F", "D"].iter()
+                        .filter_map(|descriptor| FieldType::parse(descriptor))
+                        .find(|primitive| wrapper_class(primitive) == Some(class_name.as_str()))
+                        .unwrap_or_else(|| to.clone()),
+                    _ => to.clone()
+                };
+
+                let unboxed = self.unbox_value(value, &unboxed_type)?;
+                Ok(widen(unboxed, &unboxed_type, to))
+            },
+            (false, false)  => Ok(widen(value, from, to))
+        }
+    }
+
+    /// Prepares a call of the implementation of a lambda, `args` start with the lambda object
+    pub fn lambda_call(&mut self, proxy: &LambdaProxy, descriptor: &str, args: &[Value]) -> Result<LambdaCall, Throwable> {
+        let invoked = MethodDescriptor::parse(descriptor).ok_or_else(|| bootstrap_method_error(descriptor))?;
+        let implementation = &proxy.implementation;
+        let implementation_descriptor = MethodDescriptor::parse(&implementation.descriptor).ok_or_else(|| bootstrap_method_error(&implementation.descriptor))?;
+
+        let mut values: Vec<Value> = self.object(args[0])?.fields().map_or(vec![], |fields| fields.iter().map(AtomicField::get).collect());
+        values.extend_from_slice(&args[1..]);
+
+        let source_types: Vec<FieldType> = proxy.captured.iter().chain(invoked.parameters.iter()).cloned().collect();
+        let target_types: Vec<FieldType> = match implementation.kind {
+            REF_INVOKE_STATIC | REF_NEW_INVOKE_SPECIAL => implementation_descriptor.parameters.clone(),
+            _ => std::iter::once(FieldType::Object(implementation.class_name.clone())).chain(implementation_descriptor.parameters.iter().cloned()).collect()
+        };
+
+        if source_types.len() != target_types.len() {
+            return Err(Throwable::new("java/lang/invoke/LambdaConversionException", &format!("Incorrect number of parameters for {}", implementation.name)));
+        }
+
+        // Boxing runs Java code, so adapted values are rooted until they are passed on
+        let mark = self.native_roots.len();
+        self.native_roots.extend_from_slice(&values);
+
+        let mut adapted = Vec::with_capacity(values.len());
+        for ((value, from), to) in values.into_iter().zip(&source_types).zip(&target_types) {
+            match self.adapt_value(value, from, to) {
+                Ok(value) => {
+                    self.native_roots.push(value);
+                    adapted.push(value);
+                },
+                Err(throwable) => {
+                    self.native_roots.truncate(mark);
+                    return Err(throwable);
+                }
+            }
+        }
+
+        let result = self.call_implementation(implementation, &implementation_descriptor, &invoked, adapted);
+        self.native_roots.truncate(mark);
+
+        result
+    }
+
+    fn call_implementation(&mut self, implementation: &MethodHandle, implementation_descriptor: &MethodDescriptor, invoked: &MethodDescriptor, mut adapted: Vec<Value>) -> Result<LambdaCall, Throwable> {
+
+        let target = self.load_class(&implementation.class_name)?;
+        let (class_id, method) = match implementation.kind {
+            REF_INVOKE_VIRTUAL | REF_INVOKE_INTERFACE => {
+                let receiver_class = self.object(adapted[0])?.class;
+
+                self.select_method(receiver_class, &implementation.name, &implementation.descriptor)
+                    .filter(|(_, method)| !method.is_abstract())
+                    .ok_or_else(|| Throwable::new("java/lang/AbstractMethodError", &implementation.name))?
+            },
+            _ => {
+                if implementation.kind != REF_INVOKE_SPECIAL {
+                    self.initialize_class(target)?;
+                }
+
+                self.resolve_method(target, &implementation.name, &implementation.descriptor)
+                    .ok_or_else(|| Throwable::new("java/lang/NoSuchMethodError", &implementation.name))?
+            }
+        };
+
+        if implementation.kind == REF_NEW_INVOKE_SPECIAL {
+            let object = self.new_object(target);
+            adapted.insert(0, object);
+            self.invoke(class_id, method, &adapted)?;
+
+            return Ok(LambdaCall::Returned(self.adapt_return(Some(object), &Some(FieldType::Object(implementation.class_name.clone())), &invoked.return_type)?));
+        }
+
+        let compatible_return = match (&implementation_descriptor.return_type, &invoked.return_type) {
+            (None, None) => true,
+            (Some(from), Some(to)) => from == to || (from.is_reference() && to.is_reference()),
+            _ => false
+        };
+
+        if compatible_return && !method.is_native() {
+            return Ok(LambdaCall::Frame(class_id, method, adapted));
+        }
+
+        let result = self.invoke(class_id, method, &adapted)?;
+
+        Ok(LambdaCall::Returned(self.adapt_return(result, &implementation_descriptor.return_type, &invoked.return_type)?))
+    }
+
+    fn adapt_return(&mut self, value: Option<Value>, from: &Option<FieldType>, to: &Option<FieldType>) -> Result<Option<Value>, Throwable> {
+        match (value, from, to) {
+            (_, _, None) => Ok(None),
+            (Some(value), Some(from), Some(to)) => Ok(Some(self.adapt_value(value, from, to)?)),
+            (_, _, Some(to)) => Ok(Some(Value::default_for(&to.descriptor())))
+        }
+    }
+
+    /// Runs the implementation of a lambda to completion
+    pub fn invoke_lambda(&mut self, proxy: &LambdaProxy, descriptor: &str, args: &[Value]) -> Result<Option<Value>, Throwable> {
+        match self.lambda_call(proxy, descriptor, args)? {
+            LambdaCall::Frame(class_id, method, args) => self.invoke(class_id, method, &args),
+            LambdaCall::Returned(value) => Ok(value)
+        }
+    }
+
+    /// Resolves a CONSTANT_Dynamic entry, constants are resolved once per entry
+    pub fn dynamic_constant(&mut self, class_id: ClassId, index: u16) -> Result<Value, Throwable> {
+        if let Some(value) = self.dynamic_constants.get(&(class_id, index)) {
+            return Ok(*value);
+        }
+
+        let class = self.class(class_id).class.clone().ok_or_else(|| bootstrap_method_error("Class without class file"))?;
+        let invalid = || Throwable::new("java/lang/ClassFormatError", &format!("Invalid dynamic constant #{}", index));
+
+        let (bootstrap_index, name_and_type) = match class.class_file.constant_pool.get((index as usize).wrapping_sub(1)) {
+            Some(ConstantPoolEntry::Dynamic(bootstrap_index, name_and_type)) => (*bootstrap_index, *name_and_type),
+            _ => return Err(invalid())
+        };
+
+        let (name, descriptor) = class.class_file.get_name_and_type(name_and_type as usize).ok_or_else(invalid)?;
+        let field_type = FieldType::parse(&descriptor).ok_or_else(invalid)?;
+        let (handle, arguments) = self.bootstrap_method(class_id, &class, bootstrap_index)?;
+
+        let value = match (handle.class_name.as_str(), handle.name.as_str()) {
+            ("java/lang/invoke/ConstantBootstraps", "nullConstant") => Value::default_for(&descriptor),
+            ("java/lang/invoke/ConstantBootstraps", "primitiveClass") => {
+                let primitive = FieldType::parse(&name).filter(|primitive| !primitive.is_reference())
+                    .map(|primitive| primitive.to_string())
+                    .unwrap_or_else(|| if name == "V" { "void".to_string() } else { name.clone() });
+
+                let primitive_class = self.load_primitive_class(&primitive)?;
+                self.class_mirror(primitive_class)?
+            },
+            ("java/lang/invoke/ConstantBootstraps", "getStaticFinal") | ("java/lang/invoke/ConstantBootstraps", "enumConstant") => {
+                let declaring_class_name = match arguments.first() {
+                    Some(BootstrapArgument::Class(declaring_class_name)) => declaring_class_name.clone(),
+                    _ => wrapper_class(&field_type).map(str::to_string).unwrap_or_else(|| field_type.class_name())
+                };
+
+                let declaring_class = self.load_class(&declaring_class_name)?;
+                let field_class = self.resolve_field(declaring_class, &name, &descriptor)
+                    .ok_or_else(|| Throwable::new("java/lang/NoSuchFieldError", &name))?;
+
+                self.initialize_class(field_class)?;
+                self.class(field_class).static_values.get(&name).copied().unwrap_or_else(|| Value::default_for(&descriptor))
+            },
+            _ => self.bootstrap_constant(class_id, index, &handle, &arguments, &name, &field_type)?
+        };
+
+        self.dynamic_constants.insert((class_id, index), value);
+
+        Ok(value)
+    }
+
+}
\ No newline at end of file
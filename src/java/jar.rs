@@ -3,28 +3,34 @@
 use std::collections::HashMap;
 use std::fs;
 use std::io::{ BufReader, Read };
+use std::path::Path;
+use std::sync::Arc;
 use zip::ZipArchive;
 
 use crate::java::class;
+use crate::java::format_checker::ClassFormatError;
 
 #[derive(Debug)]
 pub struct Jar {
     pub name: String,
 
     pub manifest: HashMap<String, String>,
-    pub classes: HashMap<String, class::Class>
+    pub classes: HashMap<String, Arc<class::Class>>,
+    /// Class files that can't be parsed by their file name, loading them raises the `ClassFormatError`
+    pub unparsable_classes: HashMap<String, ClassFormatError>
 }
 
 impl Jar {
 
     pub fn new(jar_path: &str) -> Result<Self, &'static str> {
-        let file = fs::File::open(&jar_path);
-        return if let Ok(file) = file {
+        let file = fs::File::open(jar_path);
+        if let Ok(file) = file {
             let reader = BufReader::new(file);
             let archive = zip::ZipArchive::new(reader);
 
             if let Ok(mut archive) = archive {
                 let mut classes = HashMap::new();
+                let mut unparsable_classes = HashMap::new();
                 for i in 0..archive.len() {
                     let file = archive.by_index(i);
                     if file.is_err() { continue; }
@@ -36,19 +42,15 @@ impl Jar {
                     let mut file_content: Vec<u8> = vec![];
                     if file.read_to_end(&mut file_content).is_err() { continue; }
 
-                    if let Some(class) = class::Class::new(&file_content) {
-                        classes.insert(file_name, class);
-                    } else {
-                        println!("Failed to read class file '{}'", file_name);
-                    }
-
+                    Self::add_class(file_name, &file_content, &mut classes, &mut unparsable_classes);
                 }
 
                 Ok(Jar {
                     name: jar_path.to_string(),
 
                     manifest: Self::parse_manifest(&mut archive)?,
-                    classes
+                    classes,
+                    unparsable_classes
                 })
             } else {
                 Err("Failed to parse JAR file")
@@ -58,6 +60,58 @@ impl Jar {
         }
     }
 
+    /// Reads the class files below a directory on the class path like the entries of an unpacked jar
+    pub fn from_directory(directory_path: &str) -> Result<Self, &'static str> {
+        let mut classes = HashMap::new();
+        let mut unparsable_classes = HashMap::new();
+        Self::read_directory(Path::new(directory_path), "", &mut classes, &mut unparsable_classes)?;
+
+        Ok(Jar {
+            name: directory_path.to_string(),
+
+            manifest: HashMap::new(),
+            classes,
+            unparsable_classes
+        })
+    }
+
+    fn read_directory(directory: &Path, prefix: &str, classes: &mut HashMap<String, Arc<class::Class>>,
+                      unparsable_classes: &mut HashMap<String, ClassFormatError>) -> Result<(), &'static str> {
+        let entries = fs::read_dir(directory).map_err(|_| "Failed to read directory")?;
+
+        for entry in entries.flatten() {
+            let file_name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            let path = entry.path();
+
+            if path.is_dir() {
+                Self::read_directory(&path, &format!("{}/", file_name), classes, unparsable_classes)?;
+                continue;
+            }
+
+            if !file_name.ends_with(".class") { continue; }
+
+            let file_content = match fs::read(&path) {
+                Ok(file_content) => file_content,
+                Err(_) => continue
+            };
+
+            Self::add_class(file_name, &file_content, classes, unparsable_classes);
+        }
+
+        Ok(())
+    }
+
+    fn add_class(file_name: String, file_content: &Vec<u8>, classes: &mut HashMap<String, Arc<class::Class>>,
+                 unparsable_classes: &mut HashMap<String, ClassFormatError>) {
+        match class::Class::new(file_content) {
+            Ok(class) => { classes.insert(file_name, Arc::new(class)); },
+            Err(error) => {
+                let class_name = file_name.trim_end_matches(".class");
+                unparsable_classes.insert(file_name.clone(), ClassFormatError::unparsable(class_name, &error));
+            }
+        }
+    }
+
     fn parse_manifest(jar_archive: &mut ZipArchive<BufReader<fs::File>>) -> Result<HashMap<String, String>, &'static str>{
         let mut manifest_content = String::new();
 
@@ -85,6 +139,21 @@ impl Jar {
         Ok(result)
     }
 
+    /// `Main-Class` of the manifest in internal form, e.g. `com/acme/Main`
+    pub fn main_class_name(&self) -> Option<String> {
+        self.manifest.get("Main-Class").map(|name| name.replace('.', "/"))
+    }
+
+    /// Jars listed in the `Class-Path` of the manifest, relative to the directory of this jar
+    pub fn manifest_class_path(&self) -> Vec<String> {
+        let directory = Path::new(&self.name).parent().unwrap_or(Path::new(""));
+
+        self.manifest.get("Class-Path").map(|class_path| class_path.split_whitespace()
+            .map(|entry| directory.join(entry).to_string_lossy().into_owned())
+            .collect())
+            .unwrap_or_default()
+    }
+
     pub fn get_main_class(&self) -> Option<(String, &class::Class)> {
         let mut main_class_name = self.manifest.get("Main-Class")?.clone();
         main_class_name.push_str(".class");
@@ -0,0 +1,329 @@
+#![allow(dead_code)]
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::java;
+use crate::java::decoded_code::{DecodedCode, DecodedInstruction};
+use crate::java::interpreter::Flow;
+use crate::java::jit_compiler::{self, CodeModule};
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+/// Values compiled code returns to `VmContext::run_compiled`, and helpers return to compiled code
+pub const EXIT_CONTINUE: u32 = 0;
+/// The method returned `JitFrame::result`, `Value::None` for `void`
+pub const EXIT_RETURN: u32 = 1;
+/// The invoke instruction at the program counter is due, the frame continues after it once the call returns
+pub const EXIT_CALL: u32 = 2;
+/// The interpreter continues at the program counter, because a safepoint is due or there's no code for it
+pub const EXIT_INTERPRET: u32 = 3;
+/// The instruction at the program counter has to wait and runs again once the thread continues
+pub const EXIT_RETRY: u32 = 4;
+/// The instruction at the program counter threw `JitFrame::throwable`
+pub const EXIT_THROW: u32 = 5;
+
+pub type CompiledFunction = unsafe extern "C" fn(*mut JitFrame) -> u32;
+
+/// When the JIT compiles methods, see `VirtualMachine::set_jit`
+#[derive(Debug, Clone)]
+pub struct JitOptions {
+    /// Calls of a method before it's compiled
+    pub invocation_threshold: u32,
+    /// Backward branches taken in a method before it's compiled, so long running loops are compiled on their first call
+    pub backedge_threshold: u32,
+    /// Logs compiled and rejected methods to `System.out` like `-XX:+PrintCompilation`
+    pub print_compilation: bool
+}
+
+/// What the JIT compiled and how often compiled code ran, see `VirtualMachine::jit_stats`
+#[derive(Debug, Clone, Default)]
+pub struct JitStats {
+    pub compiled_methods: u64,
+    /// Hot methods using byte code the compiler doesn't translate, e.g. `jsr`, which stay interpreted
+    pub rejected_methods: u64,
+    /// Times frames ran compiled code
+    pub entries: u64,
+    /// Times a frame left compiled code to continue in the interpreter, at exceptions, blocking monitors and safepoints
+    pub deoptimizations: u64,
+    pub compile_time: Duration
+}
+
+/// The JIT of a VM, holding the machine code of all compiled methods
+#[derive(Default)]
+pub struct Jit {
+    /// `None` if methods are only interpreted
+    pub options: Option<JitOptions>,
+    pub stats: JitStats,
+    /// Created with the first compiled method
+    pub module: Option<CodeModule>
+}
+
+/// How hot a method is, kept with its decoded code
+#[derive(Debug, Default)]
+pub struct JitState {
+    invocations: AtomicU32,
+    backedges: AtomicU32,
+    /// Set once the method got hot, `None` if it couldn't be compiled
+    pub compiled: OnceLock<Option<CompiledCode>>
+}
+
+/// Machine code of a method, it runs the frame from any program counter in `entries` until it has to leave
+#[derive(Debug)]
+pub struct CompiledCode {
+    pub function: CompiledFunction,
+    /// By program counter, whether compiled code can start there: the method start, exception handlers,
+    /// loop headers and the instructions after calls
+    pub entries: Box<[bool]>
+}
+
+/// The state of an interpreter frame while compiled code runs it. Locals stay in the frame, the operand stack
+/// is kept in registers and written to `stack` only before compiled code leaves or calls a helper.
+#[repr(C)]
+pub struct JitFrame {
+    pub locals: *mut Value,
+    pub stack: *mut Value,
+    pub context: *mut VmContext,
+    /// `Scheduler::remaining` and `Limiter::countdown`, compiled code takes its instructions off them block by block
+    pub remaining: *mut u32,
+    pub countdown: *mut u64,
+    pub program_counter: u32,
+    /// Values written to `stack`
+    pub stack_depth: u32,
+    pub result: Value,
+    pub throwable: Option<Throwable>
+}
+
+impl Default for JitOptions {
+
+    fn default() -> Self {
+        JitOptions { invocation_threshold: 1_000, backedge_threshold: 10_000, print_compilation: false }
+    }
+
+}
+
+impl Jit {
+
+    pub fn new() -> Self {
+        Jit { options: Some(JitOptions::default()), stats: JitStats::default(), module: None }
+    }
+
+}
+
+impl JitState {
+
+    pub fn count_invocation(&self) {
+        let invocations = self.invocations.load(Ordering::Relaxed);
+        self.invocations.store(invocations.saturating_add(1), Ordering::Relaxed);
+    }
+
+    /// Counts a backward branch, true if the frame should continue in compiled code
+    pub fn count_backedge(&self, options: &JitOptions) -> bool {
+        match self.compiled.get() {
+            Some(compiled) => compiled.is_some(),
+            None => {
+                let backedges = self.backedges.load(Ordering::Relaxed).saturating_add(1);
+                self.backedges.store(backedges, Ordering::Relaxed);
+
+                backedges >= options.backedge_threshold
+            }
+        }
+    }
+
+    fn is_hot(&self, options: &JitOptions) -> bool {
+        self.invocations.load(Ordering::Relaxed) >= options.invocation_threshold ||
+            self.backedges.load(Ordering::Relaxed) >= options.backedge_threshold
+    }
+
+}
+
+/// Runs an instruction compiled code doesn't translate on the frame, with the operand stack compiled code wrote to it.
+/// Returns `EXIT_CONTINUE` if the frame continues with the next instruction, compiled code reads the stack back then.
+///
+/// # Safety
+/// Only compiled code calls this, with the frame `run_compiled` passed to it and an instruction of the method it runs.
+pub unsafe extern "C" fn execute_instruction(jit_frame: *mut JitFrame, instruction: *const DecodedInstruction) -> u32 {
+    // SAFETY: compiled code passes the frame `run_compiled` gave it and an instruction of the method's decoded code
+    let (jit_frame, instruction) = unsafe { (&mut *jit_frame, &*instruction) };
+    // SAFETY: `run_compiled` doesn't use the context until compiled code returned
+    let context = unsafe { &mut *jit_frame.context };
+
+    let frame = context.frame();
+    // SAFETY: compiled code wrote this many values into the stack, which has room for the maximum depth
+    unsafe { frame.stack.set_len(jit_frame.stack_depth as usize) };
+    frame.program_counter = jit_frame.program_counter as usize;
+    let class_id = frame.class;
+
+    let result = context.execute_instruction(class_id, instruction);
+
+    // Calls of class initializers may have moved the frame
+    let collection_due = context.heap.collection_due();
+    let frame = context.frame();
+    jit_frame.stack = frame.stack.as_mut_ptr();
+
+    match result {
+        // Allocations may have made a collection due, the interpreter continues after the instruction to reach a safepoint
+        Ok(Flow::Next) if collection_due => {
+            jit_frame.program_counter = instruction.next as u32;
+            jit_frame.stack_depth = frame.stack.len() as u32;
+
+            EXIT_INTERPRET
+        },
+        Ok(Flow::Next) => {
+            jit_frame.stack_depth = frame.stack.len() as u32;
+            // The values stay in memory for compiled code to read
+            frame.stack.clear();
+
+            EXIT_CONTINUE
+        },
+        Ok(Flow::Retry) => {
+            jit_frame.stack_depth = frame.stack.len() as u32;
+            EXIT_RETRY
+        },
+        Ok(_) => unreachable!("Compiled code calls helpers only for instructions that continue with the next one"),
+        Err(throwable) => {
+            jit_frame.stack_depth = frame.stack.len() as u32;
+            jit_frame.throwable = Some(throwable);
+
+            EXIT_THROW
+        }
+    }
+}
+
+pub extern "C" fn float_remainder(left: f32, right: f32) -> f32 {
+    left % right
+}
+
+pub extern "C" fn double_remainder(left: f64, right: f64) -> f64 {
+    left % right
+}
+
+impl VmContext {
+
+    /// The compiled code of a frame's method if it can continue the frame at its program counter.
+    /// Methods are compiled here once they got hot.
+    pub fn compiled_entry<'a>(&mut self, class_id: ClassId, method: &java::Method, code: &'a DecodedCode) -> Option<&'a CompiledCode> {
+        let options = self.jit.options.as_ref()?;
+
+        // Traced methods run in the interpreter, which traces every instruction
+        if self.tracer.is_some() {
+            return None;
+        }
+
+        let compiled = match code.jit.compiled.get() {
+            Some(compiled) => compiled.as_ref()?,
+            None if code.jit.is_hot(options) => {
+                let compiled = self.compile_method(class_id, method, code);
+                code.jit.compiled.get_or_init(|| compiled).as_ref()?
+            },
+            None => return None
+        };
+
+        let program_counter = self.frame().program_counter;
+        compiled.entries.get(program_counter).copied().unwrap_or(false).then_some(compiled)
+    }
+
+    fn compile_method(&mut self, class_id: ClassId, method: &java::Method, code: &DecodedCode) -> Option<CompiledCode> {
+        let class = self.class(class_id).class.clone()?;
+        let start = Instant::now();
+
+        let result = match &mut self.jit.module {
+            Some(module) => jit_compiler::compile(module, &class, method, code),
+            None => CodeModule::new().and_then(|module| jit_compiler::compile(self.jit.module.insert(module), &class, method, code))
+        };
+
+        self.jit.stats.compile_time += start.elapsed();
+
+        let name = format!("{}.{}{}", self.class(class_id).name.replace('/', "."), method.name, method.descriptor);
+        let message = match &result {
+            Ok(_) => {
+                self.jit.stats.compiled_methods += 1;
+                format!("[jit,compile] {} ({} bytes)\n", name, method.code().map(|code| code.code.len()).unwrap_or(0))
+            },
+            Err(reason) => {
+                self.jit.stats.rejected_methods += 1;
+                format!("[jit,reject] {}: {}\n", name, reason)
+            }
+        };
+
+        if self.jit.options.as_ref().is_some_and(|options| options.print_compilation) {
+            self.console.out.write(message.as_bytes());
+        }
+
+        result.ok()
+    }
+
+    /// Runs the innermost frame in compiled code from its program counter, which has to be an entry of the code,
+    /// until the frame calls or leaves the method, has to wait, or a safepoint is due
+    pub fn run_compiled(&mut self, class_id: ClassId, method: &java::Method, code: &DecodedCode, compiled: &CompiledCode) -> Result<Flow, Throwable> {
+        self.jit.stats.entries += 1;
+
+        let context: *mut VmContext = self;
+        let frame = self.frame();
+        let (locals, stack, stack_depth, program_counter) = (frame.locals.as_mut_ptr(), frame.stack.as_mut_ptr(), frame.stack.len(), frame.program_counter);
+        // The values stay in memory for compiled code to read
+        frame.stack.clear();
+
+        let mut jit_frame = JitFrame {
+            locals,
+            stack,
+            context,
+            remaining: &mut self.scheduler.remaining,
+            countdown: &mut self.limiter.countdown,
+            program_counter: program_counter as u32,
+            stack_depth: stack_depth as u32,
+            result: Value::None,
+            throwable: None
+        };
+
+        // SAFETY: the code was compiled for this method, and the frame points into the innermost frame of the method
+        let exit = unsafe { (compiled.function)(&mut jit_frame) };
+
+        let frame = self.frame();
+        frame.program_counter = jit_frame.program_counter as usize;
+        // SAFETY: compiled code and helpers wrote this many values into the stack, which has room for the maximum depth
+        unsafe { frame.stack.set_len(jit_frame.stack_depth as usize) };
+
+        match exit {
+            EXIT_RETURN => Ok(Flow::Return(Some(jit_frame.result).filter(|value| *value != Value::None))),
+            EXIT_CALL => {
+                let instruction = code.at(jit_frame.program_counter as usize).expect("Compiled code called at an unknown instruction");
+
+                match self.execute_instruction(class_id, instruction)? {
+                    Flow::Next => {
+                        self.frame().program_counter = instruction.next;
+                        Ok(Flow::Next)
+                    },
+                    flow => Ok(flow)
+                }
+            },
+            EXIT_RETRY => {
+                self.jit.stats.deoptimizations += 1;
+                Ok(Flow::Retry)
+            },
+            EXIT_THROW => {
+                self.jit.stats.deoptimizations += 1;
+                Err(jit_frame.throwable.take().expect("Compiled code threw without a throwable"))
+            },
+            _ => {
+                self.jit.stats.deoptimizations += 1;
+
+                if self.scheduler.remaining == 0 || self.limiter.countdown == 0 || self.heap.collection_due() {
+                    return Ok(Flow::Safepoint);
+                }
+
+                self.run_instructions(class_id, method, code)
+            }
+        }
+    }
+
+    /// Counts a backward branch of the innermost frame, true if it should continue in compiled code
+    pub fn count_backedge(&self, code: &DecodedCode) -> bool {
+        match &self.jit.options {
+            Some(options) => self.tracer.is_none() && code.jit.count_backedge(options),
+            None => false
+        }
+    }
+
+}
\ No newline at end of file
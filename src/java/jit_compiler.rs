@@ -0,0 +1,1318 @@
+#![allow(dead_code)]
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem::{offset_of, ManuallyDrop};
+
+use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
+use cranelift_codegen::ir::immediates::{Ieee32, Ieee64};
+use cranelift_codegen::ir::{self, types, AbiParam, Block, InstBuilder, MemFlags, Signature, Type};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Switch};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Module};
+
+use crate::java;
+use crate::java::attribute::ExceptionTable;
+use crate::java::class::ConstantPoolEntry;
+use crate::java::decoded_code::{DecodedCode, Op};
+use crate::java::descriptor::{FieldType, MethodDescriptor};
+use crate::java::instruction::Instruction;
+use crate::java::jit::{self, CompiledCode, CompiledFunction, JitFrame, EXIT_CALL, EXIT_INTERPRET, EXIT_RETURN};
+use crate::java::opcodes::Opcode;
+use crate::java::vm::Value;
+
+/// Size of a `Value` in frames, its payload follows the tag byte at `PAYLOAD_OFFSET`
+const VALUE_SIZE: i32 = 16;
+const PAYLOAD_OFFSET: i32 = 8;
+
+const _: () = assert!(std::mem::size_of::<Value>() == VALUE_SIZE as usize && std::mem::align_of::<Value>() == PAYLOAD_OFFSET as usize);
+
+/// The tag byte `Value` is laid out with, which compiled code writes when it stores values
+const fn tag_of(value: &Value) -> u8 {
+    // SAFETY: `Value` is `repr(C, u8)`, so it starts with its tag
+    unsafe { *(value as *const Value as *const u8) }
+}
+
+/// The machine code of compiled methods. It's freed with the VM, which drops the decoded code referring to it.
+pub struct CodeModule {
+    module: ManuallyDrop<JITModule>
+}
+
+// SAFETY: the module is only used by the thread holding the VM, like the rest of `VmContext`
+unsafe impl Send for CodeModule {}
+
+/// Types of values on the operand stack, as far as compiled code needs to know them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Int,
+    Long,
+    Float,
+    Double,
+    Reference
+}
+
+/// How an instruction continues
+enum Control {
+    Next,
+    Goto(usize),
+    /// Continues with the next instruction or the target
+    Branch(usize),
+    Switch(Vec<usize>),
+    /// Returns or throws
+    End
+}
+
+/// The reachable instructions of a method with the operand stack before each of them
+struct Analysis {
+    /// Program counter, instruction and program counter of the next instruction
+    instructions: Vec<(usize, Instruction, usize)>,
+    stacks: BTreeMap<usize, Vec<Kind>>,
+    /// Instructions starting blocks of compiled code, with the number of instructions in the block
+    leaders: BTreeMap<usize, u32>,
+    /// Where compiled code can be entered, see `CompiledCode::entries`
+    entries: BTreeSet<usize>
+}
+
+impl CodeModule {
+
+    pub fn new() -> Result<Self, String> {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").map_err(|error| error.to_string())?;
+
+        let isa = cranelift_native::builder()
+            .map_err(|error| error.to_string())?
+            .finish(settings::Flags::new(flags))
+            .map_err(|error| error.to_string())?;
+
+        let module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+        Ok(CodeModule { module: ManuallyDrop::new(module) })
+    }
+
+}
+
+impl Drop for CodeModule {
+
+    fn drop(&mut self) {
+        // SAFETY: the module isn't used afterwards, and compiled code only runs while the VM is alive
+        unsafe { ManuallyDrop::take(&mut self.module).free_memory() };
+    }
+
+}
+
+impl Kind {
+
+    fn of(field_type: &FieldType) -> Self {
+        match field_type {
+            FieldType::Long => Kind::Long,
+            FieldType::Float => Kind::Float,
+            FieldType::Double => Kind::Double,
+            FieldType::Object(_) | FieldType::Array(_) => Kind::Reference,
+            _ => Kind::Int
+        }
+    }
+
+    fn of_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Integer(_) => Ok(Kind::Int),
+            Value::Long(_) => Ok(Kind::Long),
+            Value::Float(_) => Ok(Kind::Float),
+            Value::Double(_) => Ok(Kind::Double),
+            Value::Reference(_) => Ok(Kind::Reference),
+            Value::None => Err("constant without a value".to_string())
+        }
+    }
+
+    /// Types of the values loaded and stored by the typed instructions, in the order of `iload`, `lload`, `fload`, `dload` and `aload`
+    fn of_typed(index: u8) -> Self {
+        [Kind::Int, Kind::Long, Kind::Float, Kind::Double, Kind::Reference][index as usize]
+    }
+
+    fn is_category2(self) -> bool {
+        matches!(self, Kind::Long | Kind::Double)
+    }
+
+    fn ir_type(self) -> Type {
+        match self {
+            Kind::Int | Kind::Reference => types::I32,
+            Kind::Long => types::I64,
+            Kind::Float => types::F32,
+            Kind::Double => types::F64
+        }
+    }
+
+    fn tag(self) -> u8 {
+        match self {
+            Kind::Int => tag_of(&Value::Integer(0)),
+            Kind::Long => tag_of(&Value::Long(0)),
+            Kind::Float => tag_of(&Value::Float(0)),
+            Kind::Double => tag_of(&Value::Double(0)),
+            Kind::Reference => tag_of(&Value::Reference(0))
+        }
+    }
+
+}
+
+fn pop(stack: &mut Vec<Kind>, expected: Kind) -> Result<(), String> {
+    match stack.pop() {
+        Some(kind) if kind == expected => Ok(()),
+        Some(kind) => Err(format!("expected {:?} on the stack, found {:?}", expected, kind)),
+        None => Err("stack underflow".to_string())
+    }
+}
+
+/// Applies `pop`, `pop2`, `dup*` and `swap` to a stack, the same way the interpreter does with its values
+fn rearrange<T: Copy>(opcode: Opcode, stack: &mut Vec<T>, is_category2: impl Fn(&T) -> bool) -> Result<(), String> {
+    fn take<T>(stack: &mut Vec<T>) -> Result<T, String> {
+        stack.pop().ok_or_else(|| "stack underflow".to_string())
+    }
+
+    match opcode {
+        Opcode::pop => { take(stack)?; },
+        Opcode::pop2 => {
+            if !is_category2(&take(stack)?) {
+                take(stack)?;
+            }
+        },
+        Opcode::dup => {
+            let value = take(stack)?;
+            stack.extend([value, value]);
+        },
+        Opcode::dup_x1 => {
+            let (value1, value2) = (take(stack)?, take(stack)?);
+            stack.extend([value1, value2, value1]);
+        },
+        Opcode::dup_x2 => {
+            let (value1, value2) = (take(stack)?, take(stack)?);
+            if is_category2(&value2) {
+                stack.extend([value1, value2, value1]);
+            } else {
+                let value3 = take(stack)?;
+                stack.extend([value1, value3, value2, value1]);
+            }
+        },
+        Opcode::dup2 => {
+            let value1 = take(stack)?;
+            if is_category2(&value1) {
+                stack.extend([value1, value1]);
+            } else {
+                let value2 = take(stack)?;
+                stack.extend([value2, value1, value2, value1]);
+            }
+        },
+        Opcode::dup2_x1 => {
+            let (value1, value2) = (take(stack)?, take(stack)?);
+            if is_category2(&value1) {
+                stack.extend([value1, value2, value1]);
+            } else {
+                let value3 = take(stack)?;
+                stack.extend([value2, value1, value3, value2, value1]);
+            }
+        },
+        Opcode::dup2_x2 => {
+            let (value1, value2) = (take(stack)?, take(stack)?);
+            if is_category2(&value1) {
+                if is_category2(&value2) {
+                    stack.extend([value1, value2, value1]);
+                } else {
+                    let value3 = take(stack)?;
+                    stack.extend([value1, value3, value2, value1]);
+                }
+            } else {
+                let value3 = take(stack)?;
+                if is_category2(&value3) {
+                    stack.extend([value2, value1, value3, value2, value1]);
+                } else {
+                    let value4 = take(stack)?;
+                    stack.extend([value2, value1, value4, value3, value2, value1]);
+                }
+            }
+        },
+        Opcode::swap => {
+            let (value1, value2) = (take(stack)?, take(stack)?);
+            stack.extend([value1, value2]);
+        },
+        _ => unreachable!("{} doesn't rearrange the stack", opcode)
+    }
+
+    Ok(())
+}
+
+fn is_call(instruction: &Instruction) -> bool {
+    matches!(instruction, Instruction::InvokeInterface { .. } | Instruction::InvokeDynamic { .. } |
+        Instruction::ConstantPool(Opcode::invokevirtual | Opcode::invokespecial | Opcode::invokestatic, _))
+}
+
+/// The type of the values of the typed instructions in a range of opcodes like `iaload` to `saload`, where
+/// the types after `aaload` stand for `byte`, `char` and `short`
+fn typed_kind(opcode: Opcode, first: Opcode) -> Kind {
+    match opcode as u8 - first as u8 {
+        index @ 0..=4 => Kind::of_typed(index),
+        _ => Kind::Int
+    }
+}
+
+struct Analyzer<'a> {
+    class: &'a java::Class,
+    code: &'a DecodedCode,
+    max_locals: usize,
+    max_stack: usize
+}
+
+impl Analyzer<'_> {
+
+    fn check_local(&self, index: usize, kind: Kind) -> Result<(), String> {
+        let slots = if kind.is_category2() { 2 } else { 1 };
+        match index + slots <= self.max_locals {
+            true => Ok(()),
+            false => Err(format!("local {} out of range", index))
+        }
+    }
+
+    fn member_kind(&self, index: u16) -> Result<(FieldType, Option<Kind>, Vec<Kind>), String> {
+        let (_, _, descriptor) = self.class.class_file.get_member_reference(index as usize)
+            .ok_or_else(|| format!("invalid member reference {}", index))?;
+
+        self.descriptor_kinds(&descriptor)
+    }
+
+    /// The type of a field descriptor, or the return and parameter types of a method descriptor
+    fn descriptor_kinds(&self, descriptor: &str) -> Result<(FieldType, Option<Kind>, Vec<Kind>), String> {
+        if let Some(field_type) = FieldType::parse(descriptor) {
+            let kind = Kind::of(&field_type);
+            return Ok((field_type, Some(kind), vec![]));
+        }
+
+        let method_descriptor = MethodDescriptor::parse(descriptor).ok_or_else(|| format!("invalid descriptor {}", descriptor))?;
+        Ok((
+            FieldType::Int,
+            method_descriptor.return_type.as_ref().map(Kind::of),
+            method_descriptor.parameters.iter().map(Kind::of).collect()
+        ))
+    }
+
+    fn name_and_type_kinds(&self, index: u16) -> Result<(Option<Kind>, Vec<Kind>), String> {
+        let name_and_type = match self.class.class_file.constant_pool.get((index as usize).wrapping_sub(1)) {
+            Some(ConstantPoolEntry::InvokeDynamic(_, name_and_type) | ConstantPoolEntry::Dynamic(_, name_and_type)) => *name_and_type,
+            _ => return Err(format!("invalid dynamic constant {}", index))
+        };
+
+        let (_, descriptor) = self.class.class_file.get_name_and_type(name_and_type as usize)
+            .ok_or_else(|| format!("invalid name and type {}", name_and_type))?;
+        let (_, kind, parameters) = self.descriptor_kinds(&descriptor)?;
+
+        Ok((kind, parameters))
+    }
+
+    /// Applies an instruction to the types on the operand stack
+    fn stack_effect(&self, pc: usize, instruction: &Instruction, stack: &mut Vec<Kind>) -> Result<Control, String> {
+        match instruction {
+            Instruction::Simple(opcode) => self.simple_effect(pc, *opcode, stack),
+            Instruction::Push(..) => {
+                stack.push(Kind::Int);
+                Ok(Control::Next)
+            },
+            Instruction::Local { opcode, index, .. } => {
+                let code = *opcode as u8;
+                let index = *index as usize;
+
+                if (Opcode::iload as u8..=Opcode::aload as u8).contains(&code) {
+                    let kind = Kind::of_typed(code - Opcode::iload as u8);
+                    self.check_local(index, kind)?;
+                    stack.push(kind);
+                } else if (Opcode::istore as u8..=Opcode::astore as u8).contains(&code) {
+                    let kind = Kind::of_typed(code - Opcode::istore as u8);
+                    self.check_local(index, kind)?;
+                    pop(stack, kind)?;
+                } else {
+                    return Err(format!("unsupported instruction {}", opcode));
+                }
+
+                Ok(Control::Next)
+            },
+            Instruction::Increment { index, .. } => {
+                self.check_local(*index as usize, Kind::Int)?;
+                Ok(Control::Next)
+            },
+            Instruction::ConstantPool(opcode, index) => {
+                match opcode {
+                    Opcode::ldc | Opcode::ldc_w | Opcode::ldc2_w => match self.code.at(pc).map(|instruction| &instruction.op) {
+                        Some(Op::Const(value)) => stack.push(Kind::of_value(value)?),
+                        _ => match self.class.class_file.constant_pool.get((*index as usize).wrapping_sub(1)) {
+                            Some(ConstantPoolEntry::Dynamic(..)) => {
+                                let (kind, _) = self.name_and_type_kinds(*index)?;
+                                stack.push(kind.ok_or("dynamic constant without a type")?);
+                            },
+                            _ => stack.push(Kind::Reference)
+                        }
+                    },
+                    Opcode::getstatic | Opcode::putstatic | Opcode::getfield | Opcode::putfield => {
+                        let (_, kind, _) = self.member_kind(*index)?;
+                        let kind = kind.ok_or("field without a type")?;
+
+                        match opcode {
+                            Opcode::getstatic => stack.push(kind),
+                            Opcode::putstatic => pop(stack, kind)?,
+                            Opcode::getfield => {
+                                pop(stack, Kind::Reference)?;
+                                stack.push(kind);
+                            },
+                            _ => {
+                                pop(stack, kind)?;
+                                pop(stack, Kind::Reference)?;
+                            }
+                        }
+                    },
+                    Opcode::invokevirtual | Opcode::invokespecial | Opcode::invokestatic => {
+                        let (_, return_kind, parameters) = self.member_kind(*index)?;
+                        self.call_effect(stack, return_kind, &parameters, *opcode != Opcode::invokestatic)?;
+                    },
+                    Opcode::new => stack.push(Kind::Reference),
+                    Opcode::anewarray => {
+                        pop(stack, Kind::Int)?;
+                        stack.push(Kind::Reference);
+                    },
+                    Opcode::checkcast => {
+                        pop(stack, Kind::Reference)?;
+                        stack.push(Kind::Reference);
+                    },
+                    Opcode::instanceof => {
+                        pop(stack, Kind::Reference)?;
+                        stack.push(Kind::Int);
+                    },
+                    _ => return Err(format!("unsupported instruction {}", opcode))
+                }
+
+                Ok(Control::Next)
+            },
+            Instruction::InvokeInterface { index, .. } => {
+                let (_, return_kind, parameters) = self.member_kind(*index)?;
+                self.call_effect(stack, return_kind, &parameters, true)?;
+                Ok(Control::Next)
+            },
+            Instruction::InvokeDynamic { index } => {
+                let (return_kind, parameters) = self.name_and_type_kinds(*index)?;
+                self.call_effect(stack, return_kind, &parameters, false)?;
+                Ok(Control::Next)
+            },
+            Instruction::Branch(opcode, target) => {
+                let target = *target as usize;
+
+                match opcode {
+                    Opcode::goto | Opcode::goto_w => return Ok(Control::Goto(target)),
+                    Opcode::ifeq | Opcode::ifne | Opcode::iflt | Opcode::ifge | Opcode::ifgt | Opcode::ifle => pop(stack, Kind::Int)?,
+                    Opcode::if_icmpeq | Opcode::if_icmpne | Opcode::if_icmplt | Opcode::if_icmpge | Opcode::if_icmpgt | Opcode::if_icmple => {
+                        pop(stack, Kind::Int)?;
+                        pop(stack, Kind::Int)?;
+                    },
+                    Opcode::if_acmpeq | Opcode::if_acmpne => {
+                        pop(stack, Kind::Reference)?;
+                        pop(stack, Kind::Reference)?;
+                    },
+                    Opcode::ifnull | Opcode::ifnonnull => pop(stack, Kind::Reference)?,
+                    _ => return Err(format!("unsupported instruction {}", opcode))
+                }
+
+                Ok(Control::Branch(target))
+            },
+            Instruction::TableSwitch { default, targets, .. } => {
+                pop(stack, Kind::Int)?;
+                Ok(Control::Switch(targets.iter().chain([default]).map(|target| *target as usize).collect()))
+            },
+            Instruction::LookupSwitch { default, pairs } => {
+                pop(stack, Kind::Int)?;
+                Ok(Control::Switch(pairs.iter().map(|(_, target)| target).chain([default]).map(|target| *target as usize).collect()))
+            },
+            Instruction::NewArray(_) => {
+                pop(stack, Kind::Int)?;
+                stack.push(Kind::Reference);
+                Ok(Control::Next)
+            },
+            Instruction::MultiANewArray { dimensions, .. } => {
+                for _ in 0..*dimensions {
+                    pop(stack, Kind::Int)?;
+                }
+
+                stack.push(Kind::Reference);
+                Ok(Control::Next)
+            }
+        }
+    }
+
+    fn call_effect(&self, stack: &mut Vec<Kind>, return_kind: Option<Kind>, parameters: &[Kind], has_receiver: bool) -> Result<(), String> {
+        for parameter in parameters.iter().rev() {
+            pop(stack, *parameter)?;
+        }
+
+        if has_receiver {
+            pop(stack, Kind::Reference)?;
+        }
+
+        stack.extend(return_kind);
+        Ok(())
+    }
+
+    fn simple_effect(&self, pc: usize, opcode: Opcode, stack: &mut Vec<Kind>) -> Result<Control, String> {
+        use Kind::*;
+
+        let code = opcode as u8;
+        let binary = |stack: &mut Vec<Kind>, kind: Kind| -> Result<(), String> {
+            pop(stack, kind)?;
+            pop(stack, kind)?;
+            stack.push(kind);
+            Ok(())
+        };
+        let convert = |stack: &mut Vec<Kind>, from: Kind, to: Kind| -> Result<(), String> {
+            pop(stack, from)?;
+            stack.push(to);
+            Ok(())
+        };
+
+        match opcode {
+            Opcode::nop => { },
+            Opcode::aconst_null | Opcode::iconst_m1 | Opcode::iconst_0 | Opcode::iconst_1 | Opcode::iconst_2 | Opcode::iconst_3 |
+            Opcode::iconst_4 | Opcode::iconst_5 | Opcode::lconst_0 | Opcode::lconst_1 | Opcode::fconst_0 | Opcode::fconst_1 |
+            Opcode::fconst_2 | Opcode::dconst_0 | Opcode::dconst_1 => match self.code.at(pc).map(|instruction| &instruction.op) {
+                Some(Op::Const(value)) => stack.push(Kind::of_value(value)?),
+                _ => return Err(format!("undecoded constant {}", opcode))
+            },
+
+            _ if (Opcode::iload_0 as u8..=Opcode::aload_3 as u8).contains(&code) => {
+                let kind = Kind::of_typed((code - Opcode::iload_0 as u8) / 4);
+                self.check_local(((code - Opcode::iload_0 as u8) % 4) as usize, kind)?;
+                stack.push(kind);
+            },
+            _ if (Opcode::istore_0 as u8..=Opcode::astore_3 as u8).contains(&code) => {
+                let kind = Kind::of_typed((code - Opcode::istore_0 as u8) / 4);
+                self.check_local(((code - Opcode::istore_0 as u8) % 4) as usize, kind)?;
+                pop(stack, kind)?;
+            },
+
+            Opcode::iaload | Opcode::laload | Opcode::faload | Opcode::daload | Opcode::aaload |
+            Opcode::baload | Opcode::caload | Opcode::saload => {
+                pop(stack, Int)?;
+                pop(stack, Reference)?;
+                stack.push(typed_kind(opcode, Opcode::iaload));
+            },
+            Opcode::iastore | Opcode::lastore | Opcode::fastore | Opcode::dastore | Opcode::aastore |
+            Opcode::bastore | Opcode::castore | Opcode::sastore => {
+                pop(stack, typed_kind(opcode, Opcode::iastore))?;
+                pop(stack, Int)?;
+                pop(stack, Reference)?;
+            },
+
+            Opcode::pop | Opcode::pop2 | Opcode::dup | Opcode::dup_x1 | Opcode::dup_x2 |
+            Opcode::dup2 | Opcode::dup2_x1 | Opcode::dup2_x2 | Opcode::swap => rearrange(opcode, stack, |kind| kind.is_category2())?,
+
+            Opcode::iadd | Opcode::isub | Opcode::imul | Opcode::idiv | Opcode::irem | Opcode::iand | Opcode::ior | Opcode::ixor |
+            Opcode::ishl | Opcode::ishr | Opcode::iushr => binary(stack, Int)?,
+            Opcode::ladd | Opcode::lsub | Opcode::lmul | Opcode::ldiv | Opcode::lrem | Opcode::land | Opcode::lor | Opcode::lxor => binary(stack, Long)?,
+            Opcode::fadd | Opcode::fsub | Opcode::fmul | Opcode::fdiv | Opcode::frem => binary(stack, Float)?,
+            Opcode::dadd | Opcode::dsub | Opcode::dmul | Opcode::ddiv | Opcode::drem => binary(stack, Double)?,
+            Opcode::lshl | Opcode::lshr | Opcode::lushr => {
+                pop(stack, Int)?;
+                pop(stack, Long)?;
+                stack.push(Long);
+            },
+            Opcode::ineg => convert(stack, Int, Int)?,
+            Opcode::lneg => convert(stack, Long, Long)?,
+            Opcode::fneg => convert(stack, Float, Float)?,
+            Opcode::dneg => convert(stack, Double, Double)?,
+
+            Opcode::i2l => convert(stack, Int, Long)?,
+            Opcode::i2f => convert(stack, Int, Float)?,
+            Opcode::i2d => convert(stack, Int, Double)?,
+            Opcode::l2i => convert(stack, Long, Int)?,
+            Opcode::l2f => convert(stack, Long, Float)?,
+            Opcode::l2d => convert(stack, Long, Double)?,
+            Opcode::f2i => convert(stack, Float, Int)?,
+            Opcode::f2l => convert(stack, Float, Long)?,
+            Opcode::f2d => convert(stack, Float, Double)?,
+            Opcode::d2i => convert(stack, Double, Int)?,
+            Opcode::d2l => convert(stack, Double, Long)?,
+            Opcode::d2f => convert(stack, Double, Float)?,
+            Opcode::i2b | Opcode::i2c | Opcode::i2s => convert(stack, Int, Int)?,
+
+            Opcode::lcmp => {
+                pop(stack, Long)?;
+                convert(stack, Long, Int)?;
+            },
+            Opcode::fcmpl | Opcode::fcmpg => {
+                pop(stack, Float)?;
+                convert(stack, Float, Int)?;
+            },
+            Opcode::dcmpl | Opcode::dcmpg => {
+                pop(stack, Double)?;
+                convert(stack, Double, Int)?;
+            },
+
+            Opcode::ireturn | Opcode::lreturn | Opcode::freturn | Opcode::dreturn | Opcode::areturn => {
+                pop(stack, typed_kind(opcode, Opcode::ireturn))?;
+                return Ok(Control::End);
+            },
+            Opcode::r#return => return Ok(Control::End),
+
+            Opcode::arraylength => convert(stack, Reference, Int)?,
+            Opcode::athrow => {
+                pop(stack, Reference)?;
+                return Ok(Control::End);
+            },
+            Opcode::monitorenter | Opcode::monitorexit => pop(stack, Reference)?,
+
+            _ => return Err(format!("unsupported instruction {}", opcode))
+        }
+
+        Ok(Control::Next)
+    }
+
+    /// Follows the control flow of the method from its start and its exception handlers, rejecting
+    /// code the compiler can't translate, e.g. operand stacks of different shapes where control flow merges
+    fn analyze(&self, code: &[u8], exception_table: &[ExceptionTable]) -> Result<Analysis, String> {
+        let mut instructions = vec![];
+        let mut index = BTreeMap::new();
+
+        let mut pc = 0;
+        while pc < code.len() {
+            let instruction = Instruction::decode_at(code, pc).map_err(|error| error.to_string())?;
+            let next = pc + instruction.length(pc);
+
+            index.insert(pc, instructions.len());
+            instructions.push((pc, instruction, next));
+            pc = next;
+        }
+
+        let mut stacks: BTreeMap<usize, Vec<Kind>> = BTreeMap::new();
+        let mut leaders = BTreeSet::from([0]);
+        let mut entries = BTreeSet::from([0]);
+        let mut pending = vec![];
+
+        let mut merge = |pc: usize, stack: Vec<Kind>, pending: &mut Vec<(usize, Vec<Kind>)>| -> Result<(), String> {
+            if !index.contains_key(&pc) {
+                return Err(format!("no instruction at pc {}", pc));
+            }
+
+            if stack.len() > self.max_stack {
+                return Err(format!("stack overflow at pc {}", pc));
+            }
+
+            match stacks.get(&pc) {
+                Some(existing) if *existing != stack => Err(format!("stacks of different shapes at pc {}", pc)),
+                Some(_) => Ok(()),
+                None => {
+                    stacks.insert(pc, stack.clone());
+                    pending.push((pc, stack));
+                    Ok(())
+                }
+            }
+        };
+
+        merge(0, vec![], &mut pending)?;
+        for entry in exception_table {
+            let handler = entry.handler_pc as usize;
+            merge(handler, vec![Kind::Reference], &mut pending)?;
+            leaders.insert(handler);
+            entries.insert(handler);
+        }
+
+        while let Some((pc, mut stack)) = pending.pop() {
+            let (_, instruction, next) = &instructions[index[&pc]];
+
+            match self.stack_effect(pc, instruction, &mut stack)? {
+                Control::Next => {
+                    merge(*next, stack, &mut pending)?;
+                    if is_call(instruction) {
+                        leaders.insert(*next);
+                        entries.insert(*next);
+                    }
+                },
+                Control::Goto(target) => {
+                    merge(target, stack, &mut pending)?;
+                    leaders.extend([target, *next]);
+                    if target <= pc {
+                        entries.insert(target);
+                    }
+                },
+                Control::Branch(target) => {
+                    merge(target, stack.clone(), &mut pending)?;
+                    merge(*next, stack, &mut pending)?;
+                    leaders.extend([target, *next]);
+                    if target <= pc {
+                        entries.insert(target);
+                    }
+                },
+                Control::Switch(targets) => {
+                    for target in &targets {
+                        merge(*target, stack.clone(), &mut pending)?;
+                        if *target <= pc {
+                            entries.insert(*target);
+                        }
+                    }
+
+                    leaders.extend(targets);
+                    leaders.insert(*next);
+                },
+                Control::End => { leaders.insert(*next); }
+            }
+        }
+
+        // Blocks run up to the next leader, they're counted as a whole against the instructions a thread may run
+        let mut counted_leaders = BTreeMap::new();
+        let mut current = None;
+        for (pc, _, _) in &instructions {
+            if !stacks.contains_key(pc) {
+                current = None;
+                continue;
+            }
+
+            if leaders.contains(pc) || current.is_none() {
+                current = Some(*pc);
+                counted_leaders.insert(*pc, 0);
+            }
+
+            if let Some(leader) = current {
+                *counted_leaders.get_mut(&leader).expect("Leader without a count") += 1;
+            }
+        }
+
+        entries.retain(|pc| stacks.contains_key(pc));
+
+        Ok(Analysis { instructions, stacks, leaders: counted_leaders, entries })
+    }
+
+}
+
+struct Translator<'a, 'b> {
+    builder: FunctionBuilder<'b>,
+    analysis: &'a Analysis,
+    code: &'a DecodedCode,
+    pointer: Type,
+
+    frame: ir::Value,
+    locals: ir::Value,
+    remaining: ir::Value,
+    countdown: ir::Value,
+
+    blocks: BTreeMap<usize, Block>,
+    /// Signatures of `jit::execute_instruction` and the remainder helpers
+    execute: ir::SigRef,
+    float_remainder: ir::SigRef,
+    double_remainder: ir::SigRef,
+
+    /// Values on the operand stack at the instruction being translated
+    stack: Vec<(ir::Value, Kind)>
+}
+
+fn flags() -> MemFlags {
+    MemFlags::trusted()
+}
+
+fn offset(offset: usize) -> i32 {
+    offset as i32
+}
+
+impl Translator<'_, '_> {
+
+    fn push(&mut self, value: ir::Value, kind: Kind) {
+        self.stack.push((value, kind));
+    }
+
+    fn pop(&mut self) -> ir::Value {
+        self.stack.pop().expect("Operand stack underflow in compiled code").0
+    }
+
+    fn stack_values(&self) -> Vec<ir::Value> {
+        self.stack.iter().map(|(value, _)| *value).collect()
+    }
+
+    fn int_constant(&mut self, value: i32) -> ir::Value {
+        self.builder.ins().iconst(types::I32, value as u32 as i64)
+    }
+
+    fn exit_code(&mut self, code: u32) -> ir::Value {
+        self.builder.ins().iconst(types::I32, code as i64)
+    }
+
+    fn store_value(&mut self, base: ir::Value, position: i32, value: ir::Value, kind: Kind) {
+        let tag = self.builder.ins().iconst(types::I8, kind.tag() as i64);
+        self.builder.ins().store(flags(), tag, base, position);
+        self.builder.ins().store(flags(), value, base, position + PAYLOAD_OFFSET);
+    }
+
+    fn load_local(&mut self, index: usize, kind: Kind) {
+        let value = self.builder.ins().load(kind.ir_type(), flags(), self.locals, index as i32 * VALUE_SIZE + PAYLOAD_OFFSET);
+        self.push(value, kind);
+    }
+
+    fn store_local(&mut self, index: usize, kind: Kind) {
+        let value = self.pop();
+        self.store_value(self.locals, index as i32 * VALUE_SIZE, value, kind);
+
+        // Like `Scope::store`, the second slot of a long or double doesn't hold a value
+        if kind.is_category2() {
+            let none = self.builder.ins().iconst(types::I8, tag_of(&Value::None) as i64);
+            self.builder.ins().store(flags(), none, self.locals, (index as i32 + 1) * VALUE_SIZE);
+        }
+    }
+
+    /// Writes the operand stack and the program counter to the frame, for helpers and the interpreter
+    fn spill(&mut self, pc: usize) {
+        let stack = self.builder.ins().load(self.pointer, flags(), self.frame, offset(offset_of!(JitFrame, stack)));
+        for (index, (value, kind)) in self.stack.clone().into_iter().enumerate() {
+            self.store_value(stack, index as i32 * VALUE_SIZE, value, kind);
+        }
+
+        let depth = self.int_constant(self.stack.len() as i32);
+        self.builder.ins().store(flags(), depth, self.frame, offset(offset_of!(JitFrame, stack_depth)));
+        let pc = self.int_constant(pc as i32);
+        self.builder.ins().store(flags(), pc, self.frame, offset(offset_of!(JitFrame, program_counter)));
+    }
+
+    /// Reads the operand stack back from the frame, with the types it has at an instruction
+    fn reload(&mut self, pc: usize) {
+        let kinds = self.analysis.stacks[&pc].clone();
+        let stack = self.builder.ins().load(self.pointer, flags(), self.frame, offset(offset_of!(JitFrame, stack)));
+
+        self.stack = kinds.into_iter().enumerate()
+            .map(|(index, kind)| (self.builder.ins().load(kind.ir_type(), flags(), stack, index as i32 * VALUE_SIZE + PAYLOAD_OFFSET), kind))
+            .collect();
+    }
+
+    /// Leaves compiled code, handing the frame to `run_compiled`
+    fn exit(&mut self, pc: usize, code: u32) {
+        self.spill(pc);
+        let code = self.exit_code(code);
+        self.builder.ins().return_(&[code]);
+    }
+
+    /// Executes the instruction at a program counter in the interpreter, continuing with `next` unless it left the frame
+    fn call_helper(&mut self, pc: usize, next: Option<usize>) {
+        self.spill(pc);
+
+        let instruction = self.code.at(pc).expect("Compiled an instruction that wasn't decoded");
+        let instruction = self.builder.ins().iconst(self.pointer, instruction as *const _ as i64);
+        let helper = self.builder.ins().iconst(self.pointer, jit::execute_instruction as *const () as i64);
+        let call = self.builder.ins().call_indirect(self.execute, helper, &[self.frame, instruction]);
+        let status = self.builder.inst_results(call)[0];
+
+        match next {
+            Some(next) => {
+                let (leave, proceed) = (self.builder.create_block(), self.builder.create_block());
+                self.builder.ins().brif(status, leave, &[], proceed, &[]);
+
+                self.builder.switch_to_block(leave);
+                self.builder.ins().return_(&[status]);
+
+                self.builder.switch_to_block(proceed);
+                self.reload(next);
+            },
+            None => { self.builder.ins().return_(&[status]); }
+        }
+    }
+
+    /// Takes the instructions of a block off the instructions the thread may run before the next safepoint,
+    /// leaving to the interpreter if there aren't enough
+    fn count_instructions(&mut self, pc: usize, count: u32) {
+        let remaining = self.builder.ins().load(types::I32, flags(), self.remaining, 0);
+        let countdown = self.builder.ins().load(types::I64, flags(), self.countdown, 0);
+        let enough_remaining = self.builder.ins().icmp_imm(IntCC::UnsignedGreaterThan, remaining, count as i64);
+        let enough_countdown = self.builder.ins().icmp_imm(IntCC::UnsignedGreaterThan, countdown, count as i64);
+        let enough = self.builder.ins().band(enough_remaining, enough_countdown);
+
+        let (run, stop) = (self.builder.create_block(), self.builder.create_block());
+        self.builder.ins().brif(enough, run, &[], stop, &[]);
+
+        self.builder.switch_to_block(stop);
+        self.exit(pc, EXIT_INTERPRET);
+
+        self.builder.switch_to_block(run);
+        let remaining = self.builder.ins().iadd_imm(remaining, -(count as i64));
+        self.builder.ins().store(flags(), remaining, self.remaining, 0);
+        let countdown = self.builder.ins().iadd_imm(countdown, -(count as i64));
+        self.builder.ins().store(flags(), countdown, self.countdown, 0);
+    }
+
+    fn translate(&mut self, entry_pc: ir::Value) {
+        for pc in self.analysis.leaders.keys() {
+            let block = self.builder.create_block();
+            for kind in &self.analysis.stacks[pc] {
+                self.builder.append_block_param(block, kind.ir_type());
+            }
+
+            self.blocks.insert(*pc, block);
+        }
+
+        // The frame enters at its program counter with the stack `run_compiled` left in memory
+        let mut switch = Switch::new();
+        let fallback = self.builder.create_block();
+        let entries: Vec<(usize, Block)> = self.analysis.entries.iter().map(|pc| (*pc, self.builder.create_block())).collect();
+        for (pc, block) in &entries {
+            switch.set_entry(*pc as u128, *block);
+        }
+        switch.emit(&mut self.builder, entry_pc, fallback);
+
+        self.builder.switch_to_block(fallback);
+        let code = self.exit_code(EXIT_INTERPRET);
+        self.builder.ins().return_(&[code]);
+
+        for (pc, block) in entries {
+            self.builder.switch_to_block(block);
+            self.reload(pc);
+            let values = self.stack_values();
+            self.builder.ins().jump(self.blocks[&pc], &values);
+        }
+
+        let analysis = self.analysis;
+        let mut open = false;
+        for (pc, instruction, next) in &analysis.instructions {
+            if !analysis.stacks.contains_key(pc) {
+                open = false;
+                continue;
+            }
+
+            if let Some(count) = analysis.leaders.get(pc) {
+                let block = self.blocks[pc];
+                if open {
+                    let values = self.stack_values();
+                    self.builder.ins().jump(block, &values);
+                }
+
+                self.builder.switch_to_block(block);
+                self.stack = self.builder.block_params(block).iter().copied().zip(analysis.stacks[pc].iter().copied()).collect();
+                self.count_instructions(*pc, *count);
+            }
+
+            open = self.translate_instruction(*pc, instruction, *next);
+        }
+
+        self.builder.seal_all_blocks();
+    }
+
+    /// Translates an instruction, true if the block continues with the next instruction
+    fn translate_instruction(&mut self, pc: usize, instruction: &Instruction, next: usize) -> bool {
+        match instruction {
+            Instruction::Simple(opcode) => return self.translate_simple(pc, *opcode, next),
+            Instruction::Push(_, value) => {
+                let value = self.int_constant(*value as i32);
+                self.push(value, Kind::Int);
+            },
+            Instruction::Local { opcode, index, .. } => {
+                let code = *opcode as u8;
+                match code <= Opcode::aload as u8 {
+                    true => self.load_local(*index as usize, Kind::of_typed(code - Opcode::iload as u8)),
+                    false => self.store_local(*index as usize, Kind::of_typed(code - Opcode::istore as u8))
+                }
+            },
+            Instruction::Increment { index, value, .. } => {
+                let position = *index as i32 * VALUE_SIZE;
+                let local = self.builder.ins().load(types::I32, flags(), self.locals, position + PAYLOAD_OFFSET);
+                let incremented = self.builder.ins().iadd_imm(local, *value as i64);
+                self.store_value(self.locals, position, incremented, Kind::Int);
+            },
+            Instruction::ConstantPool(Opcode::ldc | Opcode::ldc_w | Opcode::ldc2_w, _) => match &self.code.at(pc).expect("Compiled an instruction that wasn't decoded").op {
+                Op::Const(value) => self.push_constant(*value),
+                _ => self.call_helper(pc, Some(next))
+            },
+            Instruction::ConstantPool(..) | Instruction::InvokeInterface { .. } | Instruction::InvokeDynamic { .. } if is_call(instruction) => {
+                self.exit(pc, EXIT_CALL);
+                return false;
+            },
+            Instruction::Branch(Opcode::goto | Opcode::goto_w, target) => {
+                let values = self.stack_values();
+                self.builder.ins().jump(self.blocks[&(*target as usize)], &values);
+                return false;
+            },
+            Instruction::Branch(opcode, target) => {
+                self.translate_branch(*opcode, *target as usize, next);
+                return false;
+            },
+            Instruction::TableSwitch { default, low, targets, .. } => {
+                let cases: Vec<(i32, usize)> = targets.iter().enumerate().map(|(index, target)| (low.wrapping_add(index as i32), *target as usize)).collect();
+                self.translate_switch(&cases, *default as usize);
+                return false;
+            },
+            Instruction::LookupSwitch { default, pairs } => {
+                let cases: Vec<(i32, usize)> = pairs.iter().map(|(key, target)| (*key, *target as usize)).collect();
+                self.translate_switch(&cases, *default as usize);
+                return false;
+            },
+            // Field accesses, allocations, type checks and invokedynamic may load and initialize classes, the interpreter runs them
+            _ => self.call_helper(pc, Some(next))
+        }
+
+        true
+    }
+
+    fn push_constant(&mut self, value: Value) {
+        let (constant, kind) = match value {
+            Value::Integer(bits) => (self.builder.ins().iconst(types::I32, bits as i64), Kind::Int),
+            Value::Reference(reference) => (self.builder.ins().iconst(types::I32, reference as i64), Kind::Reference),
+            Value::Long(bits) => (self.builder.ins().iconst(types::I64, bits as i64), Kind::Long),
+            Value::Float(bits) => (self.builder.ins().f32const(Ieee32::with_bits(bits)), Kind::Float),
+            Value::Double(bits) => (self.builder.ins().f64const(Ieee64::with_bits(bits)), Kind::Double),
+            Value::None => unreachable!("Constants have a value")
+        };
+
+        self.push(constant, kind);
+    }
+
+    fn translate_branch(&mut self, opcode: Opcode, target: usize, next: usize) {
+        let condition = match opcode {
+            Opcode::ifeq | Opcode::ifne | Opcode::iflt | Opcode::ifge | Opcode::ifgt | Opcode::ifle | Opcode::ifnull | Opcode::ifnonnull => {
+                let value = self.pop();
+                let condition = match opcode {
+                    Opcode::ifeq | Opcode::ifnull => IntCC::Equal,
+                    Opcode::ifne | Opcode::ifnonnull => IntCC::NotEqual,
+                    Opcode::iflt => IntCC::SignedLessThan,
+                    Opcode::ifge => IntCC::SignedGreaterThanOrEqual,
+                    Opcode::ifgt => IntCC::SignedGreaterThan,
+                    _ => IntCC::SignedLessThanOrEqual
+                };
+
+                self.builder.ins().icmp_imm(condition, value, 0)
+            },
+            _ => {
+                let (right, left) = (self.pop(), self.pop());
+                let condition = match opcode {
+                    Opcode::if_icmpeq | Opcode::if_acmpeq => IntCC::Equal,
+                    Opcode::if_icmpne | Opcode::if_acmpne => IntCC::NotEqual,
+                    Opcode::if_icmplt => IntCC::SignedLessThan,
+                    Opcode::if_icmpge => IntCC::SignedGreaterThanOrEqual,
+                    Opcode::if_icmpgt => IntCC::SignedGreaterThan,
+                    _ => IntCC::SignedLessThanOrEqual
+                };
+
+                self.builder.ins().icmp(condition, left, right)
+            }
+        };
+
+        let values = self.stack_values();
+        self.builder.ins().brif(condition, self.blocks[&target], &values, self.blocks[&next], &values);
+    }
+
+    fn translate_switch(&mut self, cases: &[(i32, usize)], default: usize) {
+        let key = self.pop();
+        let values = self.stack_values();
+
+        // Switch targets can't take arguments, blocks of targets with values on the stack are reached through a jump
+        let mut target_blocks = BTreeMap::new();
+        let mut target_block = |translator: &mut Self, target: usize| -> Block {
+            *target_blocks.entry(target).or_insert_with(|| match values.is_empty() {
+                true => translator.blocks[&target],
+                false => {
+                    let current = translator.builder.current_block().expect("Switch outside of a block");
+                    let trampoline = translator.builder.create_block();
+                    translator.builder.switch_to_block(trampoline);
+                    translator.builder.ins().jump(translator.blocks[&target], &values);
+                    translator.builder.switch_to_block(current);
+
+                    trampoline
+                }
+            })
+        };
+
+        let mut switch = Switch::new();
+        for (case, target) in cases {
+            let block = target_block(self, *target);
+            switch.set_entry(*case as u32 as u128, block);
+        }
+
+        let default = target_block(self, default);
+        switch.emit(&mut self.builder, key, default);
+    }
+
+    fn binary(&mut self, operation: fn(&mut FunctionBuilder, ir::Value, ir::Value) -> ir::Value, kind: Kind) {
+        let (right, left) = (self.pop(), self.pop());
+        let result = operation(&mut self.builder, left, right);
+        self.push(result, kind);
+    }
+
+    fn unary(&mut self, operation: fn(&mut FunctionBuilder, ir::Value) -> ir::Value, kind: Kind) {
+        let value = self.pop();
+        let result = operation(&mut self.builder, value);
+        self.push(result, kind);
+    }
+
+    /// `idiv`, `irem`, `ldiv` and `lrem`, which throw in the interpreter on a zero divisor
+    fn translate_division(&mut self, pc: usize, opcode: Opcode) {
+        let stack = self.stack.clone();
+        let (right, left) = (self.pop(), self.pop());
+        let (kind, minus_one) = match opcode {
+            Opcode::idiv | Opcode::irem => (Kind::Int, u32::MAX as i64),
+            _ => (Kind::Long, -1)
+        };
+
+        let is_zero = self.builder.ins().icmp_imm(IntCC::Equal, right, 0);
+        let (throw, divide) = (self.builder.create_block(), self.builder.create_block());
+        self.builder.ins().brif(is_zero, throw, &[], divide, &[]);
+
+        self.builder.switch_to_block(throw);
+        let values = std::mem::replace(&mut self.stack, stack);
+        self.call_helper(pc, None);
+        self.stack = values;
+
+        // Division of the minimum value by -1 overflows, Java wraps it around
+        self.builder.switch_to_block(divide);
+        let is_minus_one = self.builder.ins().icmp_imm(IntCC::Equal, right, minus_one);
+        let one = self.builder.ins().iconst(kind.ir_type(), 1);
+        let divisor = self.builder.ins().select(is_minus_one, one, right);
+
+        let result = match opcode {
+            Opcode::idiv | Opcode::ldiv => {
+                let quotient = self.builder.ins().sdiv(left, divisor);
+                let negated = self.builder.ins().ineg(left);
+                self.builder.ins().select(is_minus_one, negated, quotient)
+            },
+            _ => {
+                let remainder = self.builder.ins().srem(left, divisor);
+                let zero = self.builder.ins().iconst(kind.ir_type(), 0);
+                self.builder.ins().select(is_minus_one, zero, remainder)
+            }
+        };
+
+        self.push(result, kind);
+    }
+
+    fn translate_shift(&mut self, opcode: Opcode, kind: Kind) {
+        let (amount, value) = (self.pop(), self.pop());
+
+        let amount = match kind {
+            Kind::Long => {
+                let amount = self.builder.ins().band_imm(amount, 63);
+                self.builder.ins().uextend(types::I64, amount)
+            },
+            _ => self.builder.ins().band_imm(amount, 31)
+        };
+
+        let result = match opcode {
+            Opcode::ishl | Opcode::lshl => self.builder.ins().ishl(value, amount),
+            Opcode::ishr | Opcode::lshr => self.builder.ins().sshr(value, amount),
+            _ => self.builder.ins().ushr(value, amount)
+        };
+
+        self.push(result, kind);
+    }
+
+    /// `lcmp`, `fcmp<op>` and `dcmp<op>`, `nan_result` is pushed if either value is NaN
+    fn translate_compare(&mut self, kind: Kind, nan_result: i32) {
+        let (right, left) = (self.pop(), self.pop());
+
+        let (greater, less) = match kind {
+            Kind::Long => (self.builder.ins().icmp(IntCC::SignedGreaterThan, left, right), self.builder.ins().icmp(IntCC::SignedLessThan, left, right)),
+            _ => (self.builder.ins().fcmp(FloatCC::GreaterThan, left, right), self.builder.ins().fcmp(FloatCC::LessThan, left, right))
+        };
+
+        let greater = self.builder.ins().uextend(types::I32, greater);
+        let less = self.builder.ins().uextend(types::I32, less);
+        let mut result = self.builder.ins().isub(greater, less);
+
+        if kind != Kind::Long {
+            let unordered = self.builder.ins().fcmp(FloatCC::Unordered, left, right);
+            let nan_result = self.int_constant(nan_result);
+            result = self.builder.ins().select(unordered, nan_result, result);
+        }
+
+        self.push(result, Kind::Int);
+    }
+
+    fn translate_remainder(&mut self, kind: Kind) {
+        let (right, left) = (self.pop(), self.pop());
+        let (signature, helper) = match kind {
+            Kind::Float => (self.float_remainder, jit::float_remainder as *const () as usize),
+            _ => (self.double_remainder, jit::double_remainder as *const () as usize)
+        };
+
+        let helper = self.builder.ins().iconst(self.pointer, helper as i64);
+        let call = self.builder.ins().call_indirect(signature, helper, &[left, right]);
+        let result = self.builder.inst_results(call)[0];
+        self.push(result, kind);
+    }
+
+    fn translate_return(&mut self, kind: Option<Kind>) {
+        let result = offset(offset_of!(JitFrame, result));
+
+        match kind {
+            Some(kind) => {
+                let value = self.pop();
+                self.store_value(self.frame, result, value, kind);
+            },
+            None => {
+                let none = self.builder.ins().iconst(types::I8, tag_of(&Value::None) as i64);
+                self.builder.ins().store(flags(), none, self.frame, result);
+            }
+        }
+
+        let code = self.exit_code(EXIT_RETURN);
+        self.builder.ins().return_(&[code]);
+    }
+
+    fn translate_simple(&mut self, pc: usize, opcode: Opcode, next: usize) -> bool {
+        use Kind::*;
+
+        let code = opcode as u8;
+
+        match opcode {
+            Opcode::nop => { },
+            Opcode::aconst_null | Opcode::iconst_m1 | Opcode::iconst_0 | Opcode::iconst_1 | Opcode::iconst_2 | Opcode::iconst_3 |
+            Opcode::iconst_4 | Opcode::iconst_5 | Opcode::lconst_0 | Opcode::lconst_1 | Opcode::fconst_0 | Opcode::fconst_1 |
+            Opcode::fconst_2 | Opcode::dconst_0 | Opcode::dconst_1 => match &self.code.at(pc).expect("Compiled an instruction that wasn't decoded").op {
+                Op::Const(value) => self.push_constant(*value),
+                _ => unreachable!("Constant instructions are decoded to constants")
+            },
+
+            _ if (Opcode::iload_0 as u8..=Opcode::aload_3 as u8).contains(&code) => {
+                let offset = code - Opcode::iload_0 as u8;
+                self.load_local((offset % 4) as usize, Kind::of_typed(offset / 4));
+            },
+            _ if (Opcode::istore_0 as u8..=Opcode::astore_3 as u8).contains(&code) => {
+                let offset = code - Opcode::istore_0 as u8;
+                self.store_local((offset % 4) as usize, Kind::of_typed(offset / 4));
+            },
+
+            Opcode::pop | Opcode::pop2 | Opcode::dup | Opcode::dup_x1 | Opcode::dup_x2 |
+            Opcode::dup2 | Opcode::dup2_x1 | Opcode::dup2_x2 | Opcode::swap => {
+                rearrange(opcode, &mut self.stack, |(_, kind)| kind.is_category2()).expect("Stack shapes were checked");
+            },
+
+            Opcode::iadd | Opcode::ladd => self.binary(|builder, left, right| builder.ins().iadd(left, right), typed_kind(opcode, Opcode::iadd)),
+            Opcode::isub | Opcode::lsub => self.binary(|builder, left, right| builder.ins().isub(left, right), typed_kind(opcode, Opcode::isub)),
+            Opcode::imul | Opcode::lmul => self.binary(|builder, left, right| builder.ins().imul(left, right), typed_kind(opcode, Opcode::imul)),
+            Opcode::fadd | Opcode::dadd => self.binary(|builder, left, right| builder.ins().fadd(left, right), typed_kind(opcode, Opcode::iadd)),
+            Opcode::fsub | Opcode::dsub => self.binary(|builder, left, right| builder.ins().fsub(left, right), typed_kind(opcode, Opcode::isub)),
+            Opcode::fmul | Opcode::dmul => self.binary(|builder, left, right| builder.ins().fmul(left, right), typed_kind(opcode, Opcode::imul)),
+            Opcode::fdiv | Opcode::ddiv => self.binary(|builder, left, right| builder.ins().fdiv(left, right), typed_kind(opcode, Opcode::idiv)),
+            Opcode::idiv | Opcode::irem | Opcode::ldiv | Opcode::lrem => self.translate_division(pc, opcode),
+            Opcode::frem => self.translate_remainder(Float),
+            Opcode::drem => self.translate_remainder(Double),
+            Opcode::ineg | Opcode::lneg => self.unary(|builder, value| builder.ins().ineg(value), typed_kind(opcode, Opcode::ineg)),
+            Opcode::fneg | Opcode::dneg => self.unary(|builder, value| builder.ins().fneg(value), typed_kind(opcode, Opcode::ineg)),
+
+            Opcode::ishl | Opcode::ishr | Opcode::iushr => self.translate_shift(opcode, Int),
+            Opcode::lshl | Opcode::lshr | Opcode::lushr => self.translate_shift(opcode, Long),
+            Opcode::iand | Opcode::land => self.binary(|builder, left, right| builder.ins().band(left, right), typed_kind(opcode, Opcode::iand)),
+            Opcode::ior | Opcode::lor => self.binary(|builder, left, right| builder.ins().bor(left, right), typed_kind(opcode, Opcode::ior)),
+            Opcode::ixor | Opcode::lxor => self.binary(|builder, left, right| builder.ins().bxor(left, right), typed_kind(opcode, Opcode::ixor)),
+
+            // Float to integer conversions saturate and turn NaN into 0, like Rust's `as` in the interpreter
+            Opcode::i2l => self.unary(|builder, value| builder.ins().sextend(types::I64, value), Long),
+            Opcode::i2f => self.unary(|builder, value| builder.ins().fcvt_from_sint(types::F32, value), Float),
+            Opcode::i2d => self.unary(|builder, value| builder.ins().fcvt_from_sint(types::F64, value), Double),
+            Opcode::l2i => self.unary(|builder, value| builder.ins().ireduce(types::I32, value), Int),
+            Opcode::l2f => self.unary(|builder, value| builder.ins().fcvt_from_sint(types::F32, value), Float),
+            Opcode::l2d => self.unary(|builder, value| builder.ins().fcvt_from_sint(types::F64, value), Double),
+            Opcode::f2i | Opcode::d2i => self.unary(|builder, value| builder.ins().fcvt_to_sint_sat(types::I32, value), Int),
+            Opcode::f2l | Opcode::d2l => self.unary(|builder, value| builder.ins().fcvt_to_sint_sat(types::I64, value), Long),
+            Opcode::f2d => self.unary(|builder, value| builder.ins().fpromote(types::F64, value), Double),
+            Opcode::d2f => self.unary(|builder, value| builder.ins().fdemote(types::F32, value), Float),
+            Opcode::i2b => self.unary(|builder, value| {
+                let byte = builder.ins().ireduce(types::I8, value);
+                builder.ins().sextend(types::I32, byte)
+            }, Int),
+            Opcode::i2c => self.unary(|builder, value| {
+                let char = builder.ins().ireduce(types::I16, value);
+                builder.ins().uextend(types::I32, char)
+            }, Int),
+            Opcode::i2s => self.unary(|builder, value| {
+                let short = builder.ins().ireduce(types::I16, value);
+                builder.ins().sextend(types::I32, short)
+            }, Int),
+
+            Opcode::lcmp => self.translate_compare(Long, 0),
+            Opcode::fcmpl => self.translate_compare(Float, -1),
+            Opcode::fcmpg => self.translate_compare(Float, 1),
+            Opcode::dcmpl => self.translate_compare(Double, -1),
+            Opcode::dcmpg => self.translate_compare(Double, 1),
+
+            Opcode::ireturn | Opcode::lreturn | Opcode::freturn | Opcode::dreturn | Opcode::areturn => {
+                self.translate_return(Some(typed_kind(opcode, Opcode::ireturn)));
+                return false;
+            },
+            Opcode::r#return => {
+                self.translate_return(None);
+                return false;
+            },
+            Opcode::athrow => {
+                self.call_helper(pc, None);
+                return false;
+            },
+
+            // Array accesses and monitors run in the interpreter
+            _ => self.call_helper(pc, Some(next))
+        }
+
+        true
+    }
+
+}
+
+/// Compiles a method to machine code, or returns why it can't be compiled
+pub fn compile(module: &mut CodeModule, class: &java::Class, method: &java::Method, code: &DecodedCode) -> Result<CompiledCode, String> {
+    let attribute = method.code().ok_or("method without code")?;
+    let analyzer = Analyzer { class, code, max_locals: attribute.max_locals as usize, max_stack: attribute.max_stack as usize };
+    let analysis = analyzer.analyze(&attribute.code, &attribute.exception_table)?;
+
+    let module = &mut *module.module;
+    let pointer = module.target_config().pointer_type();
+    let call_conv = module.target_config().default_call_conv;
+
+    let mut context = module.make_context();
+    context.func.signature.params.push(AbiParam::new(pointer));
+    context.func.signature.returns.push(AbiParam::new(types::I32));
+
+    let signature = |params: &[Type], returns: &[Type]| {
+        let mut signature = Signature::new(call_conv);
+        signature.params.extend(params.iter().map(|param| AbiParam::new(*param)));
+        signature.returns.extend(returns.iter().map(|value| AbiParam::new(*value)));
+        signature
+    };
+
+    let mut function_context = FunctionBuilderContext::new();
+    {
+        let mut builder = FunctionBuilder::new(&mut context.func, &mut function_context);
+        let execute = builder.import_signature(signature(&[pointer, pointer], &[types::I32]));
+        let float_remainder = builder.import_signature(signature(&[types::F32, types::F32], &[types::F32]));
+        let double_remainder = builder.import_signature(signature(&[types::F64, types::F64], &[types::F64]));
+
+        let start = builder.create_block();
+        builder.append_block_params_for_function_params(start);
+        builder.switch_to_block(start);
+
+        let frame = builder.block_params(start)[0];
+        let locals = builder.ins().load(pointer, flags(), frame, offset(offset_of!(JitFrame, locals)));
+        let remaining = builder.ins().load(pointer, flags(), frame, offset(offset_of!(JitFrame, remaining)));
+        let countdown = builder.ins().load(pointer, flags(), frame, offset(offset_of!(JitFrame, countdown)));
+        let entry_pc = builder.ins().load(types::I32, flags(), frame, offset(offset_of!(JitFrame, program_counter)));
+
+        let mut translator = Translator {
+            builder,
+            analysis: &analysis,
+            code,
+            pointer,
+            frame,
+            locals,
+            remaining,
+            countdown,
+            blocks: BTreeMap::new(),
+            execute,
+            float_remainder,
+            double_remainder,
+            stack: vec![]
+        };
+
+        translator.translate(entry_pc);
+        translator.builder.finalize();
+    }
+
+    let id = module.declare_anonymous_function(&context.func.signature).map_err(|error| error.to_string())?;
+    module.define_function(id, &mut context).map_err(|error| format!("{:?}", error))?;
+    module.clear_context(&mut context);
+    module.finalize_definitions().map_err(|error| error.to_string())?;
+
+    // SAFETY: the function was compiled with the signature of `CompiledFunction`
+    let function = unsafe { std::mem::transmute::<*const u8, CompiledFunction>(module.get_finalized_function(id)) };
+
+    let mut entries = vec![false; attribute.code.len()].into_boxed_slice();
+    for pc in &analysis.entries {
+        entries[*pc] = true;
+    }
+
+    Ok(CompiledCode { function, entries })
+}
\ No newline at end of file
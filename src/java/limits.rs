@@ -0,0 +1,250 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+use std::time::{Duration, Instant};
+
+use crate::java;
+use crate::java::boot::BootState;
+use crate::java::runtime_class::ClassId;
+use crate::java::thread::ThreadState;
+use crate::java::trace::glob_matches;
+use crate::java::vm::{Halt, Throwable, VmContext};
+
+/// Instructions between two checks of the timeout
+const TIMEOUT_CHECK_INTERVAL: u64 = 10_000;
+
+/// Bounds on what a program may use, for running untrusted code, see `VirtualMachine::set_limits`.
+/// Exceeding one stops the VM with `Halt::LimitExceeded`, which Java code can't catch, unlike the
+/// `OutOfMemoryError` and `StackOverflowError` of `-Xmx` and `-Xss`.
+/// Limits are counted from when they're set, or from when `java.base` finished booting, which isn't limited.
+#[derive(Debug, Clone, Default)]
+pub struct VmLimits {
+    /// Byte code instructions all threads together may execute
+    pub max_instructions: Option<u64>,
+    /// Bytes of live objects, exceeded when a garbage collection couldn't make room for an allocation
+    pub max_heap_bytes: Option<usize>,
+    /// Frames the stack of a thread may hold
+    pub max_stack_depth: Option<usize>,
+    /// Threads alive at the same time, including the main thread
+    pub max_threads: Option<usize>,
+    /// Wall-clock time the program may run, including the time its threads sleep or wait
+    pub timeout: Option<Duration>,
+    /// Natives that reach the host, like the file system, processes or sockets, raise a `SecurityException`
+    /// unless a glob matches the Java name of the class and the method, e.g. `java.io.UnixFileSystem.getLength`.
+    /// Natives registered with `VirtualMachine::register_native` count as reaching the host.
+    pub allowed_natives: Vec<String>
+}
+
+/// A limit of `VmLimits` that was exceeded, with the value it was set to.
+/// Displayed as what the program did, e.g. `executed more than 1000 instructions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Instructions(u64),
+    HeapBytes(usize),
+    StackDepth(usize),
+    Threads(usize),
+    Timeout(Duration)
+}
+
+/// Enforces the `VmLimits` of a VM, without limits it never stops the interpreter
+pub struct Limiter {
+    limits: Option<VmLimits>,
+    /// Instructions counted up to the last check
+    executed: u64,
+    /// Instructions until the interpreter calls `VmContext::check_limits` again, counted down with every instruction
+    pub countdown: u64,
+    /// What `countdown` started at
+    armed: u64,
+    deadline: Option<Instant>,
+    /// Whether a native method that reaches the host may run, by its class and the address of the method
+    allowed_natives: HashMap<(ClassId, usize), bool>
+}
+
+impl Limiter {
+
+    pub fn new() -> Self {
+        Limiter {
+            limits: None,
+            executed: 0,
+            countdown: u64::MAX,
+            armed: u64::MAX,
+            deadline: None,
+            allowed_natives: HashMap::new()
+        }
+    }
+
+    pub fn limits(&self) -> Option<&VmLimits> {
+        self.limits.as_ref()
+    }
+
+    /// Starts counting the limits from now
+    fn restart(&mut self) {
+        self.executed = 0;
+        self.deadline = self.limits.as_ref().and_then(|limits| limits.timeout).map(|timeout| Instant::now() + timeout);
+        self.arm();
+    }
+
+    /// Sets the countdown to the instructions left, or to the next check of the timeout if that comes first
+    fn arm(&mut self) {
+        let remaining = match self.limits.as_ref().and_then(|limits| limits.max_instructions) {
+            // The check runs once the instructions left were executed, before any further one
+            Some(max_instructions) => max_instructions.saturating_sub(self.executed),
+            None => u64::MAX
+        };
+
+        let interval = if self.deadline.is_some() { TIMEOUT_CHECK_INTERVAL } else { u64::MAX };
+
+        self.armed = remaining.min(interval).max(1);
+        self.countdown = self.armed;
+    }
+
+    /// The sooner of `deadline` and the timeout, for threads that wait
+    pub fn wait_deadline(&self, deadline: Option<Instant>) -> Option<Instant> {
+        match (deadline, self.deadline) {
+            (Some(deadline), Some(timeout)) => Some(deadline.min(timeout)),
+            (deadline, timeout) => deadline.or(timeout)
+        }
+    }
+
+}
+
+impl Default for Limiter {
+
+    fn default() -> Self {
+        Self::new()
+    }
+
+}
+
+impl VmContext {
+
+    pub fn set_limits(&mut self, limits: Option<VmLimits>) {
+        if let Some(limits) = &limits {
+            if let Some(max_heap_bytes) = limits.max_heap_bytes {
+                let max_bytes = self.heap.max_bytes().min(max_heap_bytes);
+                self.heap.set_max_bytes(max_bytes);
+            }
+
+            if let Some(max_stack_depth) = limits.max_stack_depth {
+                self.scheduler.max_stack_depth = self.scheduler.max_stack_depth.min(max_stack_depth);
+                self.executor.max_stack_depth = self.executor.max_stack_depth.min(max_stack_depth);
+            }
+        }
+
+        self.limiter.limits = limits;
+        self.limiter.allowed_natives.clear();
+        self.limiter.restart();
+    }
+
+    /// Starts counting the limits once `java.base` is booted
+    pub fn restart_limits(&mut self) {
+        self.limiter.restart();
+    }
+
+    /// Called by the interpreter when the countdown of the limiter ran out
+    pub fn check_limits(&mut self) -> Result<(), Throwable> {
+        // Booting java.base isn't limited, counting starts once it's done
+        if self.boot_state == BootState::Booting {
+            self.limiter.arm();
+            return Ok(());
+        }
+
+        self.limiter.executed = self.limiter.executed.saturating_add(self.limiter.armed);
+        self.limiter.arm();
+
+        if let Some(max_instructions) = self.limiter.limits.as_ref().and_then(|limits| limits.max_instructions) {
+            if self.limiter.executed >= max_instructions {
+                return Err(Throwable::Halt(Halt::LimitExceeded(Limit::Instructions(max_instructions))));
+            }
+        }
+
+        self.check_timeout()
+    }
+
+    /// Raises `Halt::LimitExceeded` if the timeout passed
+    pub fn check_timeout(&mut self) -> Result<(), Throwable> {
+        match self.limiter.deadline {
+            Some(deadline) if Instant::now() >= deadline && self.boot_state != BootState::Booting => {
+                let timeout = self.limiter.limits.as_ref().and_then(|limits| limits.timeout).unwrap_or_default();
+                Err(Throwable::Halt(Halt::LimitExceeded(Limit::Timeout(timeout))))
+            },
+            _ => Ok(())
+        }
+    }
+
+    /// What exceeding the heap size raises: `OutOfMemoryError`, or `Halt::LimitExceeded` under `VmLimits::max_heap_bytes`
+    pub fn heap_exhausted(&mut self) -> Throwable {
+        match self.limiter.limits.as_ref().and_then(|limits| limits.max_heap_bytes) {
+            Some(max_heap_bytes) => Throwable::Halt(Halt::LimitExceeded(Limit::HeapBytes(max_heap_bytes))),
+            None => Throwable::new("java/lang/OutOfMemoryError", "Java heap space")
+        }
+    }
+
+    /// What exceeding the stack depth raises: `StackOverflowError`, or `Halt::LimitExceeded` under `VmLimits::max_stack_depth`
+    pub fn stack_exhausted(&mut self) -> Throwable {
+        match self.limiter.limits.as_ref().and_then(|limits| limits.max_stack_depth) {
+            Some(max_stack_depth) => Throwable::Halt(Halt::LimitExceeded(Limit::StackDepth(max_stack_depth))),
+            None => Throwable::without_message("java/lang/StackOverflowError")
+        }
+    }
+
+    /// Raises `Halt::LimitExceeded` if starting another thread would exceed `VmLimits::max_threads`
+    pub fn check_thread_limit(&mut self) -> Result<(), Throwable> {
+        let max_threads = match self.limiter.limits.as_ref().and_then(|limits| limits.max_threads) {
+            Some(max_threads) => max_threads,
+            None => return Ok(())
+        };
+
+        let alive = self.scheduler.threads.iter().filter(|thread| thread.state != ThreadState::Terminated).count();
+        if alive >= max_threads {
+            return Err(Throwable::Halt(Halt::LimitExceeded(Limit::Threads(max_threads))));
+        }
+
+        Ok(())
+    }
+
+    /// Raises a `SecurityException` if `VmLimits::allowed_natives` doesn't allow a native method that reaches the host,
+    /// see `NativeRegistry::register_host`. `java.base` may use all natives while it boots.
+    pub fn check_native_access(&mut self, class_id: ClassId, method: &java::Method) -> Result<(), Throwable> {
+        let limits = match &self.limiter.limits {
+            Some(limits) if self.boot_state != BootState::Booting => limits,
+            _ => return Ok(())
+        };
+
+        let key = (class_id, method as *const java::Method as usize);
+        let native_name = |context: &VmContext| format!("{}.{}", context.class(class_id).java_name(), method.name);
+
+        let allowed = match self.limiter.allowed_natives.get(&key) {
+            Some(allowed) => *allowed,
+            None => {
+                let name = native_name(self);
+                let allowed = limits.allowed_natives.iter().any(|pattern| glob_matches(pattern, &name));
+
+                self.limiter.allowed_natives.insert(key, allowed);
+                allowed
+            }
+        };
+
+        match allowed {
+            true => Ok(()),
+            false => Err(Throwable::new("java/lang/SecurityException", &format!("Native method {} reaches the host and isn't allowed", native_name(self))))
+        }
+    }
+
+}
+
+impl fmt::Display for Limit {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Instructions(max) => write!(f, "executed more than {} instructions", max),
+            Limit::HeapBytes(max) => write!(f, "used more than {} bytes of heap", max),
+            Limit::StackDepth(max) => write!(f, "needed more than {} frames on a thread's stack", max),
+            Limit::Threads(max) => write!(f, "tried to run more than {} threads at once", max),
+            Limit::Timeout(timeout) => write!(f, "ran longer than {:?}", timeout)
+        }
+    }
+
+}
@@ -1,14 +1,24 @@
 #![allow(dead_code)]
 
 use crate::java;
+use crate::java::access_flags;
+use crate::java::annotation::{self, AnnotationValue, ResolvedAnnotation};
+use crate::java::attribute::AttributeCode;
 use crate::java::Attribute;
 
 #[derive(Debug)]
 pub struct Method {
+    pub access_flags: u16,
+
     pub name: String,
     pub descriptor: String,
 
-    pub attributes: Vec<java::Attribute>
+    pub attributes: Vec<java::Attribute>,
+
+    annotations: Vec<ResolvedAnnotation>,
+    annotation_default: Option<AnnotationValue>,
+    /// Start of the code of each source line as `(start_pc, line_number)`, ordered by `start_pc`
+    line_numbers: Vec<(u16, u16)>
 }
 
 impl Method {
@@ -19,15 +29,25 @@ impl Method {
                 let mut attributes = vec![];
 
                 for attribute in &method_info.attributes {
-                    if let Some(attribute) = Attribute::new(&class_file, &attribute) {
+                    if let Some(attribute) = Attribute::new(class_file, attribute) {
                         attributes.push(attribute);
                     }
                 }
 
+                let annotations = annotation::runtime_visible_annotations(class_file, &attributes);
+                let annotation_default = annotation::annotation_default(class_file, &attributes);
+                let line_numbers = Self::parse_line_numbers(class_file, &attributes);
+
                 return Some(Method {
+                    access_flags: method_info.access_flags,
+
                     name,
                     descriptor,
-                    attributes
+                    attributes,
+
+                    annotations,
+                    annotation_default,
+                    line_numbers
                 })
             }
         }
@@ -35,4 +55,71 @@ impl Method {
         None
     }
 
+    /// The LineNumberTable attributes of the code, a method can have several of them
+    fn parse_line_numbers(class_file: &java::ClassFile, attributes: &[Attribute]) -> Vec<(u16, u16)> {
+        let code = attributes.iter().find_map(|attribute| match attribute {
+            Attribute::Code(code) => Some(code),
+            _ => None
+        });
+
+        let mut line_numbers: Vec<(u16, u16)> = code.iter()
+            .flat_map(|code| &code.attributes)
+            .filter(|attribute_info| class_file.get_constant_pool_string(attribute_info.attribute_name_index as usize).as_deref() == Some("LineNumberTable"))
+            .filter_map(|attribute_info| match Attribute::new(class_file, attribute_info) {
+                Some(Attribute::LineNumberTable(attribute)) => Some(attribute.line_number_table),
+                _ => None
+            })
+            .flatten()
+            .map(|line_number| (line_number.start_pc, line_number.line_number))
+            .collect();
+
+        line_numbers.sort_by_key(|(start_pc, _)| *start_pc);
+        line_numbers
+    }
+
+    /// The source line of the instruction at `program_counter`, `None` without line number information
+    pub fn line_number(&self, program_counter: usize) -> Option<u16> {
+        self.line_numbers.iter()
+            .take_while(|(start_pc, _)| *start_pc as usize <= program_counter)
+            .last()
+            .map(|(_, line_number)| *line_number)
+    }
+
+    pub fn code(&self) -> Option<&AttributeCode> {
+        self.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::Code(code) => Some(code),
+            _ => None
+        })
+    }
+
+    /// Runtime visible annotations, without the default values of elements they leave out
+    pub fn annotations(&self) -> &[ResolvedAnnotation] {
+        &self.annotations
+    }
+
+    /// Default value of an element of an annotation interface
+    pub fn annotation_default(&self) -> Option<&AnnotationValue> {
+        self.annotation_default.as_ref()
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.access_flags & access_flags::ACC_STATIC != 0
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.access_flags & access_flags::ACC_NATIVE != 0
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags & access_flags::ACC_ABSTRACT != 0
+    }
+
+    pub fn is_synchronized(&self) -> bool {
+        self.access_flags & access_flags::ACC_SYNCHRONIZED != 0
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.access_flags & access_flags::ACC_PRIVATE != 0
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,610 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use crate::java;
+use crate::java::access_flags;
+use crate::java::class::ConstantPoolEntry;
+use crate::java::descriptor::{FieldType, MethodDescriptor};
+use crate::java::format_checker::ClassFormatError;
+use crate::java::invokedynamic::{MethodHandle, REF_GET_FIELD, REF_GET_STATIC, REF_INVOKE_INTERFACE, REF_INVOKE_SPECIAL, REF_INVOKE_STATIC,
+    REF_INVOKE_VIRTUAL, REF_NEW_INVOKE_SPECIAL, REF_PUT_FIELD, REF_PUT_STATIC};
+use crate::java::native::NativeRegistry;
+use crate::java::runtime_class::ClassId;
+use crate::java::unsafe_access::STATIC_FIELD_OFFSET;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+const METHOD_HANDLE_NATIVES: &str = "java/lang/invoke/MethodHandleNatives";
+const MEMBER_NAME: &str = "java/lang/invoke/MemberName";
+const RESOLVED_METHOD_NAME: &str = "java/lang/invoke/ResolvedMethodName";
+const METHOD_HANDLE: &str = "java/lang/invoke/MethodHandle";
+const VAR_HANDLE: &str = "java/lang/invoke/VarHandle";
+const METHOD_TYPE: &str = "java/lang/invoke/MethodType";
+
+/// `MemberName.flags`, see `MethodHandleNatives.Constants`
+const MN_IS_METHOD: i32             = 0x0001_0000;
+const MN_IS_CONSTRUCTOR: i32        = 0x0002_0000;
+const MN_IS_FIELD: i32              = 0x0004_0000;
+const MN_TRUSTED_FINAL: i32         = 0x0020_0000;
+const MN_REFERENCE_KIND_SHIFT: i32  = 24;
+const MN_REFERENCE_KIND_MASK: i32   = 0xF;
+
+/// `ClassLoader.defineClass0` flag of `Lookup.defineHiddenClass`
+const HIDDEN_CLASS: i32 = 2;
+
+/// Signature polymorphic methods of `MethodHandle` the interpreter runs itself, calls of the others are linked by Java code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intrinsic {
+    /// Calls the method of the lambda form of the method handle passed first
+    InvokeBasic,
+    /// Call the method of the `MemberName` passed last, the virtual ones select it by the receiver
+    LinkToStatic,
+    LinkToSpecial,
+    LinkToVirtual,
+    LinkToInterface
+}
+
+impl Intrinsic {
+
+    fn of(name: &str) -> Option<Self> {
+        Some(match name {
+            "invokeBasic"       => Intrinsic::InvokeBasic,
+            "linkToStatic"      => Intrinsic::LinkToStatic,
+            "linkToSpecial"     => Intrinsic::LinkToSpecial,
+            "linkToVirtual"     => Intrinsic::LinkToVirtual,
+            "linkToInterface"   => Intrinsic::LinkToInterface,
+            _ => return None
+        })
+    }
+
+}
+
+/// A method `MethodHandleNatives` linked a call site or a signature polymorphic call to, it's called with the
+/// arguments of the call followed by the appendix
+#[derive(Debug, Clone)]
+pub struct Invoker {
+    pub class: ClassId,
+    pub method: Arc<java::Method>,
+    /// Rooted in `VmContext::linked_appendices`
+    pub appendix: Option<u32>
+}
+
+/// Fields HotSpot injects into classes of `java.lang.invoke`, the VM keeps what a `MemberName` resolved to in them
+pub fn injected_fields(class_name: &str) -> &'static [(&'static str, &'static str)] {
+    match class_name {
+        RESOLVED_METHOD_NAME => &[("vmtarget", "J"), ("vmholder", "Ljava/lang/Class;")],
+        MEMBER_NAME => &[("vmindex", "J")],
+        _ => &[]
+    }
+}
+
+/// Native varargs methods of `MethodHandle` and `VarHandle`, which take any arguments (JVMS 2.9.3)
+fn is_signature_polymorphic(method: &java::Method) -> bool {
+    method.is_native() && method.access_flags & access_flags::ACC_VARARGS != 0 && method.descriptor.starts_with("([Ljava/lang/Object;)")
+}
+
+fn internal_error(message: &str) -> Throwable {
+    Throwable::new("java/lang/InternalError", message)
+}
+
+impl VmContext {
+
+    /// Calls a static method of `MethodHandleNatives`, which is how Java code links call sites and constants for the VM
+    pub fn link_upcall(&mut self, name: &str, descriptor: &str, args: &[Value]) -> Result<Value, Throwable> {
+        let natives = self.load_class(METHOD_HANDLE_NATIVES)?;
+
+        Ok(self.invoke_static(natives, name, descriptor, args)?.unwrap_or(Value::null()))
+    }
+
+    /// The `MethodType` of a method descriptor
+    pub fn method_type(&mut self, descriptor: &str) -> Result<Value, Throwable> {
+        let method_descriptor = MethodDescriptor::parse(descriptor)
+            .ok_or_else(|| Throwable::new("java/lang/ClassFormatError", &format!("Invalid method descriptor {}", descriptor)))?;
+
+        self.with_roots(&[], |context| {
+            let return_type = context.type_mirror(method_descriptor.return_type.as_ref())?;
+            let parameter_types = context.type_mirrors(&method_descriptor.parameters)?;
+            context.rooted(parameter_types);
+
+            context.link_upcall("findMethodHandleType", "(Ljava/lang/Class;[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;", &[return_type, parameter_types])
+        })
+    }
+
+    /// The `java.lang.invoke.MethodHandle` of a CONSTANT_MethodHandle entry of `caller`
+    pub fn method_handle_object(&mut self, caller: ClassId, handle: &MethodHandle) -> Result<Value, Throwable> {
+        let defining_class = self.load_class(&handle.class_name)?;
+
+        self.with_roots(&[], |context| {
+            let caller = context.class_mirror(caller)?;
+            let defining_class = context.class_mirror(defining_class)?;
+            let name = context.interned_string(&handle.name)?;
+            context.rooted(name);
+
+            let member_type = match FieldType::parse(&handle.descriptor) {
+                Some(field_type) => context.type_mirror(Some(&field_type))?,
+                None => context.method_type(&handle.descriptor)?
+            };
+            context.rooted(member_type);
+
+            context.link_upcall("linkMethodHandleConstant", "(Ljava/lang/Class;ILjava/lang/Class;Ljava/lang/String;Ljava/lang/Object;)Ljava/lang/invoke/MethodHandle;",
+                &[caller, Value::from_int(handle.kind as i32), defining_class, name, member_type])
+        })
+    }
+
+    /// Links a call of a signature polymorphic method through `MethodHandleNatives.linkMethod`, like `invokeExact`
+    /// or the access modes of `VarHandle`
+    pub fn link_method(&mut self, caller: ClassId, class_id: ClassId, name: &str, descriptor: &str) -> Result<Invoker, Throwable> {
+        self.with_roots(&[], |context| {
+            let caller = context.class_mirror(caller)?;
+            let defining_class = context.class_mirror(class_id)?;
+            let name = context.interned_string(name)?;
+            context.rooted(name);
+            let descriptor = context.new_string(descriptor)?;
+            context.rooted(descriptor);
+            let appendix = context.new_array(&FieldType::Object("java/lang/Object".to_string()), 1)?;
+            context.rooted(appendix);
+
+            let member_name = context.link_upcall("linkMethod", "(Ljava/lang/Class;ILjava/lang/Class;Ljava/lang/String;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/invoke/MemberName;",
+                &[caller, Value::from_int(REF_INVOKE_VIRTUAL as i32), defining_class, name, descriptor, appendix])?;
+
+            context.invoker(member_name, appendix)
+        })
+    }
+
+    /// The invoker a link method of `MethodHandleNatives` returned, with the appendix it stored into `appendix`.
+    /// Appendices stay alive as long as the VM, like the call sites and instructions they were linked for.
+    pub fn invoker(&mut self, member_name: Value, appendix: Value) -> Result<Invoker, Throwable> {
+        let (class, method) = self.member_name_target(member_name)?;
+        self.initialize_class(class)?;
+
+        let appendix = self.object(appendix)?.array().map(|array| array.get(0)).filter(|appendix| !appendix.is_null());
+        if let Some(appendix) = appendix {
+            self.linked_appendices.push(appendix.as_reference());
+        }
+
+        Ok(Invoker { class, method, appendix: appendix.map(|appendix| appendix.as_reference()) })
+    }
+
+    /// The signature polymorphic method of `MethodHandle` or `VarHandle` with a name, which calls with any descriptor resolve to
+    pub fn signature_polymorphic_method(&self, class_id: ClassId, name: &str) -> Option<Arc<java::Method>> {
+        let class = self.class(class_id);
+        if class.name != METHOD_HANDLE && class.name != VAR_HANDLE {
+            return None;
+        }
+
+        class.class.as_ref()?.methods.values().find(|method| method.name == name && is_signature_polymorphic(method)).cloned()
+    }
+
+    /// Whether calls of a signature polymorphic method are run by the interpreter rather than linked by Java code
+    pub fn intrinsic(&self, class_id: ClassId, method: &java::Method) -> Option<Intrinsic> {
+        Intrinsic::of(&method.name).filter(|_| self.class(class_id).name == METHOD_HANDLE && is_signature_polymorphic(method))
+    }
+
+    /// The method an intrinsic call runs and how many of the arguments it takes, which leaves out the trailing
+    /// `MemberName` of `linkTo*` calls
+    pub fn intrinsic_target(&mut self, intrinsic: Intrinsic, args: &[Value]) -> Result<(ClassId, Arc<java::Method>, usize), Throwable> {
+        let receiver = args.first().copied().filter(|receiver| !receiver.is_null());
+
+        let (class_id, method, args) = match intrinsic {
+            Intrinsic::InvokeBasic => {
+                let method_handle = receiver.ok_or_else(Throwable::null_pointer)?;
+                let form = self.get_field(method_handle, METHOD_HANDLE, "form")?;
+                let entry = self.get_field(form, "java/lang/invoke/LambdaForm", "vmentry")?;
+                let (class_id, method) = self.member_name_target(entry)?;
+
+                (class_id, method, args)
+            },
+            _ => {
+                let (member_name, args) = args.split_last().ok_or_else(|| internal_error("Missing member name"))?;
+                let (class_id, method) = self.member_name_target(*member_name)?;
+
+                let (class_id, method) = match intrinsic {
+                    Intrinsic::LinkToStatic => {
+                        self.initialize_class(class_id)?;
+                        (class_id, method)
+                    },
+                    Intrinsic::LinkToVirtual | Intrinsic::LinkToInterface if !method.is_private() => {
+                        let receiver = args.first().copied().filter(|receiver| !receiver.is_null()).ok_or_else(Throwable::null_pointer)?;
+                        let receiver_class = self.object(receiver)?.class;
+
+                        self.select_method(receiver_class, &method.name, &method.descriptor).unwrap_or((class_id, method))
+                    },
+                    _ => {
+                        if args.first().is_none_or(|receiver| receiver.is_null()) {
+                            return Err(Throwable::null_pointer());
+                        }
+
+                        (class_id, method)
+                    }
+                };
+
+                (class_id, method, args)
+            }
+        };
+
+        // Member names can stand for the intrinsics themselves, e.g. in interpreted lambda forms
+        match self.intrinsic(class_id, &method) {
+            Some(intrinsic) => self.intrinsic_target(intrinsic, args),
+            None => Ok((class_id, method, args.len()))
+        }
+    }
+
+    /// Index of a method in `method_targets`, which `ResolvedMethodName.vmtarget` holds
+    fn method_target_index(&mut self, class_id: ClassId, method: &Arc<java::Method>) -> usize {
+        let key = (class_id, Arc::as_ptr(method) as usize);
+
+        *self.method_target_indices.entry(key).or_insert_with(|| {
+            self.method_targets.push((class_id, method.clone()));
+            self.method_targets.len() - 1
+        })
+    }
+
+    /// The method a resolved `MemberName` stands for
+    pub fn member_name_target(&mut self, member_name: Value) -> Result<(ClassId, Arc<java::Method>), Throwable> {
+        let resolved = self.get_field(member_name, MEMBER_NAME, "method")?;
+        if resolved.is_null() {
+            return Err(internal_error("Unresolved member name"));
+        }
+
+        let index = self.get_field(resolved, RESOLVED_METHOD_NAME, "vmtarget")?.as_long();
+
+        self.method_targets.get(index as usize).cloned().ok_or_else(|| internal_error("Invalid member name target"))
+    }
+
+    /// Descriptor of the type of a member name, which is a `MethodType`, a `Class`, a descriptor or the return
+    /// and parameter types in an `Object[]`
+    fn member_descriptor(&mut self, member_type: Value) -> Result<String, Throwable> {
+        let class_name = self.class(self.object(member_type)?.class).name.clone();
+
+        match class_name.as_str() {
+            "java/lang/String" => self.rust_string(member_type),
+            "java/lang/Class" => self.mirror_descriptor(member_type),
+            METHOD_TYPE => {
+                let return_type = self.get_field(member_type, METHOD_TYPE, "rtype")?;
+                let parameter_types = self.get_field(member_type, METHOD_TYPE, "ptypes")?;
+
+                self.signature_descriptor(return_type, parameter_types)
+            },
+            "[Ljava/lang/Object;" => {
+                let types = self.object(member_type)?.array().filter(|array| array.len() == 2).map(|array| (array.get(0), array.get(1)));
+                let (return_type, parameter_types) = types.ok_or_else(|| internal_error("Invalid member type"))?;
+
+                self.signature_descriptor(return_type, parameter_types)
+            },
+            _ => Err(internal_error(&format!("Unsupported member type {}", class_name)))
+        }
+    }
+
+    fn signature_descriptor(&self, return_type: Value, parameter_types: Value) -> Result<String, Throwable> {
+        let parameter_types: Vec<Value> = self.object(parameter_types)?.array()
+            .map(|array| (0..array.len()).map(|index| array.get(index)).collect())
+            .unwrap_or_default();
+
+        let parameters = parameter_types.into_iter().map(|mirror| self.mirror_descriptor(mirror)).collect::<Result<String, _>>()?;
+
+        Ok(format!("({}){}", parameters, self.mirror_descriptor(return_type)?))
+    }
+
+    fn mirror_descriptor(&self, mirror: Value) -> Result<String, Throwable> {
+        let class_id = self.mirror_class(mirror).ok_or_else(Throwable::null_pointer)?;
+
+        Ok(self.class_field_type(class_id).map_or_else(|| "V".to_string(), |field_type| field_type.descriptor()))
+    }
+
+    /// The reference kind a member name of a method ends up with, calls that don't select the method by the
+    /// receiver become `REF_invokeSpecial` like in HotSpot
+    fn method_reference_kind(&self, class_id: ClassId, method: &java::Method, requested: u8) -> u8 {
+        let class = self.class(class_id);
+        let is_final = method.access_flags & access_flags::ACC_FINAL != 0 || class.access_flags & access_flags::ACC_FINAL != 0;
+
+        match requested {
+            _ if method.is_static() => REF_INVOKE_STATIC,
+            REF_NEW_INVOKE_SPECIAL | REF_INVOKE_SPECIAL => requested,
+            _ if method.name == "<init>" || method.is_private() || (is_final && !class.is_interface()) => REF_INVOKE_SPECIAL,
+            _ if class.is_interface() => REF_INVOKE_INTERFACE,
+            _ => REF_INVOKE_VIRTUAL
+        }
+    }
+
+    /// Makes a member name stand for a method
+    fn set_member_method(&mut self, member_name: Value, class_id: ClassId, method: &Arc<java::Method>, reference_kind: u8) -> Result<(), Throwable> {
+        let member_kind = if method.name == "<init>" { MN_IS_CONSTRUCTOR } else { MN_IS_METHOD };
+        let flags = method.access_flags as i32 | member_kind | (reference_kind as i32) << MN_REFERENCE_KIND_SHIFT;
+
+        let index = self.method_target_index(class_id, method);
+        let mirror = self.class_mirror(class_id)?;
+        let resolved_method_name = self.load_class(RESOLVED_METHOD_NAME)?;
+        let resolved = self.new_object(resolved_method_name);
+        self.set_field(resolved, RESOLVED_METHOD_NAME, "vmtarget", Value::from_long(index as i64))?;
+        self.set_field(resolved, RESOLVED_METHOD_NAME, "vmholder", mirror)?;
+
+        self.set_field(member_name, MEMBER_NAME, "clazz", mirror)?;
+        self.set_field(member_name, MEMBER_NAME, "flags", Value::from_int(flags))?;
+        self.set_field(member_name, MEMBER_NAME, "method", resolved)
+    }
+
+    /// Makes a member name stand for the field at `index` in the class file of `class_id`, its `vmindex` is the
+    /// `Unsafe` offset of the field
+    fn set_member_field(&mut self, member_name: Value, class_id: ClassId, index: usize, reference_kind: u8) -> Result<(), Throwable> {
+        let class = self.class(class_id).class.clone().ok_or_else(|| internal_error("Field of a class without class file"))?;
+        let field_info = &class.class_file.field_table[index];
+        let name = class.class_file.get_constant_pool_string(field_info.name_index as usize).unwrap_or_default();
+
+        let is_static = field_info.access_flags & access_flags::ACC_STATIC != 0;
+        let offset = match is_static {
+            true => STATIC_FIELD_OFFSET + index as i64,
+            false => self.class(class_id).field_slot(class_id, &name).ok_or_else(|| internal_error(&name))? as i64
+        };
+
+        let trusted_final = if is_static && field_info.access_flags & access_flags::ACC_FINAL != 0 { MN_TRUSTED_FINAL } else { 0 };
+        let flags = field_info.access_flags as i32 | MN_IS_FIELD | trusted_final | (reference_kind as i32) << MN_REFERENCE_KIND_SHIFT;
+
+        let mirror = self.class_mirror(class_id)?;
+        self.set_field(member_name, MEMBER_NAME, "clazz", mirror)?;
+        self.set_field(member_name, MEMBER_NAME, "flags", Value::from_int(flags))?;
+        self.set_field(member_name, MEMBER_NAME, "vmindex", Value::from_long(offset))
+    }
+
+    /// Resolves the class, name and type of a member name to a method or field, `false` if there is none
+    fn resolve_member_name(&mut self, member_name: Value) -> Result<bool, Throwable> {
+        let mirror = self.get_field(member_name, MEMBER_NAME, "clazz")?;
+        let name = self.get_field(member_name, MEMBER_NAME, "name")?;
+        let member_type = self.get_field(member_name, MEMBER_NAME, "type")?;
+        let flags = self.get_field(member_name, MEMBER_NAME, "flags")?.as_int();
+
+        let class_id = self.mirror_class(mirror).ok_or_else(|| internal_error("Member name without a class"))?;
+        let name = self.rust_string(name)?;
+        let descriptor = self.member_descriptor(member_type)?;
+        let reference_kind = ((flags >> MN_REFERENCE_KIND_SHIFT) & MN_REFERENCE_KIND_MASK) as u8;
+
+        let static_mismatch = |is_static: bool| Throwable::new("java/lang/IncompatibleClassChangeError",
+            &format!("Expected {} member {}.{}", if is_static { "non-static" } else { "static" }, self.class(class_id).java_name(), name));
+
+        if flags & MN_IS_FIELD != 0 {
+            let Some(declaring_class) = self.resolve_field(class_id, &name, &descriptor) else {
+                return Ok(false);
+            };
+
+            let class = self.class(declaring_class).class.clone().ok_or_else(|| internal_error("Field of a class without class file"))?;
+            let index = class.class_file.field_table.iter().position(|field_info| {
+                class.class_file.get_constant_pool_string(field_info.name_index as usize).as_deref() == Some(name.as_str()) &&
+                    class.class_file.get_constant_pool_string(field_info.descriptor_index as usize).as_deref() == Some(descriptor.as_str())
+            }).ok_or_else(|| internal_error(&name))?;
+
+            // Like in HotSpot the field decides whether the member name is static, only whether it's a setter is kept
+            let is_static = class.class_file.field_table[index].access_flags & access_flags::ACC_STATIC != 0;
+            let is_setter = matches!(reference_kind, REF_PUT_FIELD | REF_PUT_STATIC);
+            let reference_kind = match (is_static, is_setter) {
+                (false, false)  => REF_GET_FIELD,
+                (true, false)   => REF_GET_STATIC,
+                (false, true)   => REF_PUT_FIELD,
+                (true, true)    => REF_PUT_STATIC
+            };
+
+            self.set_member_field(member_name, declaring_class, index, reference_kind)?;
+
+            return Ok(true);
+        }
+
+        let found = match name.as_str() {
+            "<init>" => self.class(class_id).find_method(&name, &descriptor).map(|method| (class_id, method.clone())),
+            _ => self.resolve_method(class_id, &name, &descriptor)
+                .or_else(|| self.signature_polymorphic_method(class_id, &name).map(|method| (class_id, method)))
+        };
+
+        let Some((method_class, method)) = found else {
+            return Ok(false);
+        };
+
+        if method.is_static() != (reference_kind == REF_INVOKE_STATIC) {
+            return Err(static_mismatch(method.is_static()));
+        }
+
+        let reference_kind = self.method_reference_kind(method_class, &method, reference_kind);
+        self.set_member_method(member_name, method_class, &method, reference_kind)?;
+
+        Ok(true)
+    }
+
+    /// Initializes a member name from a `java.lang.reflect.Method`, `Constructor` or `Field`
+    fn init_member_name(&mut self, member_name: Value, member: Value) -> Result<(), Throwable> {
+        let member_class = self.class(self.object(member)?.class).name.clone();
+
+        match member_class.as_str() {
+            "java/lang/reflect/Field" => {
+                let mirror = self.get_field(member, &member_class, "clazz")?;
+                let slot = self.get_field(member, &member_class, "slot")?.as_int();
+                let class_id = self.mirror_class(mirror).ok_or_else(Throwable::null_pointer)?;
+
+                let is_static = self.class(class_id).class.as_ref()
+                    .and_then(|class| class.class_file.field_table.get(slot as usize))
+                    .map(|field_info| field_info.access_flags & access_flags::ACC_STATIC != 0)
+                    .ok_or_else(|| internal_error("Invalid field slot"))?;
+
+                let reference_kind = if is_static { REF_GET_STATIC } else { REF_GET_FIELD };
+                self.set_member_field(member_name, class_id, slot as usize, reference_kind)
+            },
+            "java/lang/reflect/Method" | "java/lang/reflect/Constructor" => {
+                let (class_id, method) = self.reflected_method(member, &member_class)?;
+                let reference_kind = self.method_reference_kind(class_id, &method, REF_INVOKE_VIRTUAL);
+
+                self.set_member_method(member_name, class_id, &method, reference_kind)
+            },
+            _ => Err(internal_error(&format!("Unsupported member {}", member_class)))
+        }
+    }
+
+    /// Fills in the name and type of a resolved member name
+    fn expand_member_name(&mut self, member_name: Value) -> Result<(), Throwable> {
+        let flags = self.get_field(member_name, MEMBER_NAME, "flags")?.as_int();
+
+        let (name, descriptor) = match flags & MN_IS_FIELD != 0 {
+            true => {
+                let mirror = self.get_field(member_name, MEMBER_NAME, "clazz")?;
+                let offset = self.get_field(member_name, MEMBER_NAME, "vmindex")?.as_long();
+                let class_id = self.mirror_class(mirror).ok_or_else(|| internal_error("Member name without a class"))?;
+                let class = self.class(class_id);
+
+                let field = match offset >= STATIC_FIELD_OFFSET {
+                    true => class.class.as_ref().and_then(|class_file| {
+                        let field_info = class_file.class_file.field_table.get((offset - STATIC_FIELD_OFFSET) as usize)?;
+                        Some((class_file.class_file.get_constant_pool_string(field_info.name_index as usize)?,
+                            class_file.class_file.get_constant_pool_string(field_info.descriptor_index as usize)?))
+                    }),
+                    false => class.instance_fields.get(offset as usize).map(|slot| (slot.name.clone(), slot.descriptor.clone()))
+                };
+
+                field.ok_or_else(|| internal_error("Invalid field offset"))?
+            },
+            false => {
+                let (_, method) = self.member_name_target(member_name)?;
+                (method.name.clone(), method.descriptor.clone())
+            }
+        };
+
+        if self.get_field(member_name, MEMBER_NAME, "name")?.is_null() {
+            let name = self.interned_string(&name)?;
+            self.set_field(member_name, MEMBER_NAME, "name", name)?;
+        }
+
+        if self.get_field(member_name, MEMBER_NAME, "type")?.is_null() {
+            let descriptor = self.new_string(&descriptor)?;
+            self.set_field(member_name, MEMBER_NAME, "type", descriptor)?;
+        }
+
+        Ok(())
+    }
+
+    /// Defines a class like `Lookup.defineHiddenClass`, it's named after its class file with a suffix that makes the
+    /// name unique, so that the same class file can be defined again
+    pub fn define_hidden_class(&mut self, bytes: &[u8]) -> Result<ClassId, Throwable> {
+        let mut class = java::Class::new(&bytes.to_vec()).map_err(|error| ClassFormatError::unparsable("<unknown>", &error))?;
+        let name = format!("{}/0x{:016x}", class.name(), self.classes.len());
+
+        let name_index = match class.class_file.constant_pool.get((class.class_file.this_class as usize).wrapping_sub(1)) {
+            Some(ConstantPoolEntry::ClassReference(name_index)) => *name_index as usize,
+            _ => return Err(Throwable::new("java/lang/ClassFormatError", "Invalid this_class"))
+        };
+        class.class_file.constant_pool[name_index - 1] = ConstantPoolEntry::String { length: name.len() as u16, string: name.as_bytes().to_vec() };
+
+        self.defined_classes.insert(name.clone(), (Arc::new(class), "__JVM_LookupDefineClass__".to_string()));
+
+        let result = self.load_class(&name);
+        if result.is_err() {
+            self.defined_classes.remove(&name);
+        }
+
+        result
+    }
+
+}
+
+fn method_handle_natives_resolve(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (member_name, speculative) = (args[0], args[3].as_int() != 0);
+
+    if context.resolve_member_name(member_name)? {
+        return Ok(Some(member_name));
+    }
+
+    if speculative {
+        return Ok(Some(Value::null()));
+    }
+
+    let flags = context.get_field(member_name, MEMBER_NAME, "flags")?.as_int();
+    let name = context.get_field(member_name, MEMBER_NAME, "name")?;
+    let name = context.rust_string(name)?;
+
+    match flags & MN_IS_FIELD != 0 {
+        true => Err(Throwable::new("java/lang/NoSuchFieldError", &name)),
+        false => Err(Throwable::new("java/lang/NoSuchMethodError", &name))
+    }
+}
+
+fn method_handle_natives_init(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    context.init_member_name(args[0], args[1])?;
+
+    Ok(None)
+}
+
+fn method_handle_natives_expand(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    context.expand_member_name(args[0])?;
+
+    Ok(None)
+}
+
+fn method_handle_natives_field_offset(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(context.get_field(args[0], MEMBER_NAME, "vmindex")?))
+}
+
+fn method_handle_natives_static_field_base(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(context.get_field(args[0], MEMBER_NAME, "clazz")?))
+}
+
+fn method_handle_natives_get_member_vm_info(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let flags = context.get_field(args[0], MEMBER_NAME, "flags")?.as_int();
+    let offset = context.get_field(args[0], MEMBER_NAME, "vmindex")?;
+    let target = match flags & MN_IS_FIELD != 0 {
+        true => context.get_field(args[0], MEMBER_NAME, "clazz")?,
+        false => args[0]
+    };
+
+    let offset = context.box_value(offset, &FieldType::Long)?;
+    let info = context.with_roots(&[offset], |context| context.new_reference_array("java/lang/Object", &[offset, target], |_, value| Ok(*value)))?;
+
+    Ok(Some(info))
+}
+
+fn method_handle_natives_set_call_site_target(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    context.set_field(args[0], "java/lang/invoke/CallSite", "target", args[1])?;
+
+    Ok(None)
+}
+
+/// Classes defined through a `Lookup`, e.g. the lambda forms method handles run
+fn class_loader_define_class0(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let bytes = context.byte_array_range(args[3], args[4].as_int(), args[5].as_int())?;
+
+    let class_id = match args[8].as_int() & HIDDEN_CLASS != 0 {
+        true => context.define_hidden_class(&bytes)?,
+        false => {
+            let class_name = match args[2].is_null() {
+                true => None,
+                false => Some(context.rust_string(args[2])?.replace('.', "/"))
+            };
+
+            context.define_class(class_name.as_deref(), &bytes, "__JVM_LookupDefineClass__")?
+        }
+    };
+
+    let mirror = context.class_mirror(class_id)?;
+    context.set_field(mirror, "java/lang/Class", "classData", args[9])?;
+
+    if args[7].as_int() != 0 {
+        context.initialize_class(class_id)?;
+    }
+
+    Ok(Some(mirror))
+}
+
+pub fn register_method_handle_natives(registry: &mut NativeRegistry) {
+    const MEMBER_NAME_DESCRIPTOR: &str = "Ljava/lang/invoke/MemberName;";
+
+    registry.register(METHOD_HANDLE_NATIVES, "registerNatives", "()V", |_, _| Ok(None));
+    registry.register(METHOD_HANDLE_NATIVES, "init", &format!("({}Ljava/lang/Object;)V", MEMBER_NAME_DESCRIPTOR), method_handle_natives_init);
+    registry.register(METHOD_HANDLE_NATIVES, "expand", &format!("({})V", MEMBER_NAME_DESCRIPTOR), method_handle_natives_expand);
+    registry.register(METHOD_HANDLE_NATIVES, "resolve", &format!("({0}Ljava/lang/Class;IZ){0}", MEMBER_NAME_DESCRIPTOR), method_handle_natives_resolve);
+    registry.register(METHOD_HANDLE_NATIVES, "objectFieldOffset", &format!("({})J", MEMBER_NAME_DESCRIPTOR), method_handle_natives_field_offset);
+    registry.register(METHOD_HANDLE_NATIVES, "staticFieldOffset", &format!("({})J", MEMBER_NAME_DESCRIPTOR), method_handle_natives_field_offset);
+    registry.register(METHOD_HANDLE_NATIVES, "staticFieldBase", &format!("({})Ljava/lang/Object;", MEMBER_NAME_DESCRIPTOR), method_handle_natives_static_field_base);
+    registry.register(METHOD_HANDLE_NATIVES, "getMemberVMInfo", &format!("({})Ljava/lang/Object;", MEMBER_NAME_DESCRIPTOR), method_handle_natives_get_member_vm_info);
+
+    for suffix in ["Normal", "Volatile"] {
+        registry.register(METHOD_HANDLE_NATIVES, &format!("setCallSiteTarget{}", suffix), "(Ljava/lang/invoke/CallSite;Ljava/lang/invoke/MethodHandle;)V",
+            method_handle_natives_set_call_site_target);
+    }
+    registry.register(METHOD_HANDLE_NATIVES, "clearCallSiteContext", "(Ljava/lang/invoke/MethodHandleNatives$CallSiteContext;)V", |_, _| Ok(None));
+
+    registry.register("java/lang/ClassLoader", "defineClass0",
+        "(Ljava/lang/ClassLoader;Ljava/lang/Class;Ljava/lang/String;[BIILjava/security/ProtectionDomain;ZILjava/lang/Object;)Ljava/lang/Class;",
+        class_loader_define_class0);
+}
@@ -5,6 +5,38 @@ pub mod method;
 pub mod attribute;
 pub mod vm;
 pub mod opcodes;
+pub mod instruction;
+pub mod decoded_code;
+pub mod dispatch;
+#[cfg(feature = "jit")]
+pub mod jit;
+#[cfg(feature = "jit")]
+pub mod jit_compiler;
+pub mod access_flags;
+pub mod descriptor;
+pub mod disassembler;
+pub mod verifier;
+pub mod format_checker;
+pub mod heap;
+pub mod gc;
+pub mod runtime_class;
+pub mod native;
+pub mod interpreter;
+pub mod string;
+pub mod console;
+pub mod system;
+pub mod file_system;
+pub mod boot;
+pub mod reflection;
+pub mod annotation;
+pub mod stack_trace;
+pub mod trace;
+pub mod limits;
+pub mod invokedynamic;
+pub mod method_handle;
+pub mod unsafe_access;
+pub mod thread;
+pub mod embed;
 
 pub use jar::Jar;
 
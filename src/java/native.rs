@@ -0,0 +1,541 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::java::access_flags;
+use crate::java::descriptor::FieldType;
+use crate::java::heap::{ArrayData, ObjectData};
+use crate::java::runtime_class::RuntimeClass;
+use crate::java::file_system::register_file_system_natives;
+use crate::java::annotation::register_annotation_natives;
+use crate::java::method_handle::register_method_handle_natives;
+use crate::java::reflection::register_reflection_natives;
+use crate::java::stack_trace::register_stack_trace_natives;
+use crate::java::system::register_system_natives;
+use crate::java::thread::register_thread_natives;
+use crate::java::unsafe_access::register_unsafe_natives;
+use crate::java::vm::{Halt, Throwable, Value, VmContext};
+
+/// Rust implementation of a Java method. Instance methods receive `this` as their first argument,
+/// `long` and `double` arguments occupy a single entry.
+pub type NativeMethod = Arc<dyn Fn(&mut VmContext, &[Value]) -> Result<Option<Value>, Throwable> + Send + Sync>;
+
+/// A native method and whether it reaches the host, like the file system, processes or sockets.
+/// Under `VmLimits` natives that do only run if `VmLimits::allowed_natives` allows them.
+#[derive(Clone)]
+pub struct RegisteredNative {
+    pub method: NativeMethod,
+    pub reaches_host: bool
+}
+
+/// Native method implementations keyed by class name, method name and descriptor
+#[derive(Clone, Default)]
+pub struct NativeRegistry {
+    methods: HashMap<(String, String, String), RegisteredNative>
+}
+
+impl NativeRegistry {
+
+    pub fn new() -> Self {
+        let mut registry = NativeRegistry::default();
+        register_builtin_natives(&mut registry);
+
+        registry
+    }
+
+    /// Registers a native that only works on the VM, e.g. on objects of the heap
+    pub fn register<F>(&mut self, class_name: &str, name: &str, descriptor: &str, native: F)
+        where F: Fn(&mut VmContext, &[Value]) -> Result<Option<Value>, Throwable> + Send + Sync + 'static {
+        self.insert(class_name, name, descriptor, Arc::new(native), false);
+    }
+
+    /// Registers a native that reaches the host
+    pub fn register_host<F>(&mut self, class_name: &str, name: &str, descriptor: &str, native: F)
+        where F: Fn(&mut VmContext, &[Value]) -> Result<Option<Value>, Throwable> + Send + Sync + 'static {
+        self.insert(class_name, name, descriptor, Arc::new(native), true);
+    }
+
+    fn insert(&mut self, class_name: &str, name: &str, descriptor: &str, method: NativeMethod, reaches_host: bool) {
+        self.methods.insert((class_name.to_string(), name.to_string(), descriptor.to_string()), RegisteredNative { method, reaches_host });
+    }
+
+    pub fn find(&self, class_name: &str, name: &str, descriptor: &str) -> Option<RegisteredNative> {
+        self.methods.get(&(class_name.to_string(), name.to_string(), descriptor.to_string())).cloned()
+    }
+
+    pub fn contains(&self, class_name: &str, name: &str, descriptor: &str) -> bool {
+        self.methods.contains_key(&(class_name.to_string(), name.to_string(), descriptor.to_string()))
+    }
+
+}
+
+fn no_op(_: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(None)
+}
+
+fn identity_hash_code(reference: u32) -> i32 {
+    // Spread consecutive handles so hash based collections don't degenerate
+    (reference.wrapping_mul(0x9E37_79B9) >> 1) as i32
+}
+
+fn object_hash_code(_: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(Value::from_int(identity_hash_code(args[0].as_reference()))))
+}
+
+fn system_identity_hash_code(_: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let reference = args[0].as_reference();
+    let hash_code = if reference == 0 { 0 } else { identity_hash_code(reference) };
+
+    Ok(Some(Value::from_int(hash_code)))
+}
+
+fn object_get_class(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.object(args[0])?.class;
+
+    Ok(Some(context.class_mirror(class_id)?))
+}
+
+fn object_clone(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let object = context.object(args[0])?.clone();
+
+    if let ObjectData::Instance(_) = object.data {
+        let cloneable = context.load_class("java/lang/Cloneable")?;
+        if !context.is_subclass_of(object.class, cloneable) {
+            return Err(Throwable::new("java/lang/CloneNotSupportedException", &context.classes[object.class as usize].java_name()));
+        }
+    }
+
+    Ok(Some(Value::Reference(context.heap.allocate(object))))
+}
+
+fn string_intern(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(context.intern_string(args[0])?))
+}
+
+fn reference_get_and_clear_pending_list(context: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(std::mem::replace(&mut context.reference_pending_list, Value::null())))
+}
+
+fn reference_refers_to(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let referent = context.get_field(args[0], "java/lang/ref/Reference", "referent")?;
+
+    Ok(Some(Value::from_bool(referent.as_reference() == args[1].as_reference())))
+}
+
+fn reference_clear(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    context.set_field(args[0], "java/lang/ref/Reference", "referent", Value::null())?;
+
+    Ok(None)
+}
+
+fn runtime_gc(context: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    context.collect_garbage();
+
+    Ok(None)
+}
+
+fn class_get_primitive_class(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let name = context.rust_string(args[0])?;
+    let class_id = context.load_primitive_class(&name)?;
+
+    Ok(Some(context.class_mirror(class_id)?))
+}
+
+/// Arrays aren't laid out in memory, offsets are element indices scaled by the element size
+fn unsafe_array_index_scale(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(args[1]).ok_or_else(Throwable::null_pointer)?;
+
+    let scale = match &context.class(class_id).component_type {
+        Some(FieldType::Boolean) | Some(FieldType::Byte)    => 1,
+        Some(FieldType::Char) | Some(FieldType::Short)      => 2,
+        Some(FieldType::Int) | Some(FieldType::Float)       => 4,
+        Some(FieldType::Long) | Some(FieldType::Double)     => 8,
+        Some(_)                                             => 4,
+        None => return Err(Throwable::without_message("java/lang/IllegalArgumentException"))
+    };
+
+    Ok(Some(Value::from_int(scale)))
+}
+
+fn system_arraycopy(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (source, source_index, destination, destination_index, length) =
+        (args[0], args[1].as_int(), args[2], args[3].as_int(), args[4].as_int());
+
+    let source_class = context.object(source)?.class;
+    let destination_class = context.object(destination)?.class;
+
+    let source_component = context.classes[source_class as usize].component_type.clone();
+    let destination_component = context.classes[destination_class as usize].component_type.clone();
+
+    let (source_component, destination_component) = match (source_component, destination_component) {
+        (Some(source_component), Some(destination_component)) => (source_component, destination_component),
+        _ => return Err(Throwable::new("java/lang/ArrayStoreException", "arraycopy: argument type mismatch"))
+    };
+
+    let reference_copy = source_component.is_reference() && destination_component.is_reference();
+    if !reference_copy && source_component != destination_component {
+        return Err(Throwable::new("java/lang/ArrayStoreException", &format!("arraycopy: type mismatch: can not copy {}[] into {}[]", source_component, destination_component)));
+    }
+
+    let source_length = context.array_length(source)? as i64;
+    let destination_length = context.array_length(destination)? as i64;
+    if source_index < 0 || destination_index < 0 || length < 0 ||
+        source_index as i64 + length as i64 > source_length || destination_index as i64 + length as i64 > destination_length {
+        return Err(Throwable::new("java/lang/ArrayIndexOutOfBoundsException", &format!("arraycopy: last source index {} out of bounds for length {}", source_index as i64 + length as i64, source_length)));
+    }
+
+    let (source_index, destination_index, length) = (source_index as usize, destination_index as usize, length as usize);
+
+    // Reference arrays of different types need a store check for every element
+    let destination_component_class = match &destination_component {
+        _ if !reference_copy || context.is_subclass_of(source_class, destination_class) => None,
+        component_type => Some(context.load_class(&component_type.class_name())?)
+    };
+
+    let elements = context.object(source)?.array().cloned().unwrap_or(ArrayData::Byte(vec![]));
+    if let Some(component_class) = destination_component_class {
+        for offset in 0..length {
+            let element = elements.get(source_index + offset);
+            if !element.is_null() && !context.is_instance_of(element, component_class)? {
+                return Err(Throwable::new("java/lang/ArrayStoreException", "arraycopy: element type mismatch"));
+            }
+
+            if let Some(array) = context.object_mut(destination)?.array_mut() {
+                array.set(destination_index + offset, element);
+            }
+        }
+
+        return Ok(None);
+    }
+
+    if let Some(array) = context.object_mut(destination)?.array_mut() {
+        array.copy_from(destination_index, &elements, source_index, length);
+    }
+
+    Ok(None)
+}
+
+fn reflection_get_caller_class(context: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    // Natives don't get a frame, the top one is the method asking for its caller.
+    // Like HotSpot, frames of reflective calls are skipped.
+    let caller = context.executor.frames.iter().rev().skip(1)
+        .map(|frame| frame.class)
+        .find(|class_id| {
+            let name = &context.class(*class_id).name;
+            name != "java/lang/reflect/Method" && !name.starts_with("jdk/internal/reflect/")
+        });
+
+    match caller {
+        Some(class_id) => Ok(Some(context.class_mirror(class_id)?)),
+        None => Ok(Some(Value::null()))
+    }
+}
+
+/// Whether two classes may access each other's private members, which `Reflection.verifyMemberAccess` asks for
+fn reflection_are_nest_mates(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let first = context.mirror_class(args[0]).ok_or_else(Throwable::null_pointer)?;
+    let second = context.mirror_class(args[1]).ok_or_else(Throwable::null_pointer)?;
+
+    let are_nest_mates = first == second || context.nest_host(first) == context.nest_host(second);
+    Ok(Some(Value::from_bool(are_nest_mates)))
+}
+
+fn system_current_time_millis(_: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_millis() as i64).unwrap_or(0);
+
+    Ok(Some(Value::from_long(millis)))
+}
+
+fn system_nano_time(context: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(Value::from_long(context.start_time.elapsed().as_nanos() as i64)))
+}
+
+fn thread_current_thread(context: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(context.main_thread_object()?))
+}
+
+/// The name of a library without the platform's prefix and suffix, e.g. `zip` for `libzip.so`
+fn native_libraries_find_builtin_lib(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let file_name = context.rust_string(args[0])?;
+    let name = file_name.strip_prefix("lib").unwrap_or(&file_name);
+    let name = name.strip_suffix(".so").unwrap_or(name).to_string();
+
+    Ok(Some(context.new_string(&name)?))
+}
+
+/// Classes defined by any loader end up with the boot loader, there's a single namespace
+fn class_loader_define_class(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_name = match args[1].is_null() {
+        true => None,
+        false => Some(context.rust_string(args[1])?.replace('.', "/"))
+    };
+    let bytes = context.byte_array_range(args[2], args[3].as_int(), args[4].as_int())?;
+    let source = match args[6].is_null() {
+        true => "__JVM_DefineClass__".to_string(),
+        false => context.rust_string(args[6])?
+    };
+
+    let class_id = context.define_class(class_name.as_deref(), &bytes, &source)?;
+
+    Ok(Some(context.class_mirror(class_id)?))
+}
+
+/// `BootLoader.loadClassOrNull`, `null` for classes that are neither loaded nor on the class path
+fn class_loader_find_bootstrap_class(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_name = context.rust_string(args[0])?.replace('.', "/");
+    if context.class_id(&class_name).is_none() && context.find_class(&class_name).is_none() {
+        return Ok(Some(Value::null()));
+    }
+
+    let class_id = context.load_class(&class_name)?;
+
+    Ok(Some(context.class_mirror(class_id)?))
+}
+
+/// Every class belongs to the unnamed module of the boot loader, including the ones whose mirror already exists
+fn boot_loader_set_unnamed_module(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let mirrors: Vec<u32> = context.classes.iter().map(|class| class.mirror).filter(|mirror| *mirror != 0).collect();
+
+    for mirror in mirrors {
+        context.set_field(Value::Reference(mirror), "java/lang/Class", "module", args[0])?;
+    }
+
+    Ok(None)
+}
+
+fn class_for_name(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let name = context.rust_string(args[0])?;
+
+    // Only binary names are accepted, the internal form is a different class name
+    if name.contains('/') {
+        return Err(Throwable::new("java/lang/ClassNotFoundException", &name));
+    }
+
+    let class_id = context.load_class(&name.replace('.', "/"))
+        .map_err(|_| Throwable::new("java/lang/ClassNotFoundException", &name))?;
+
+    if args[1].as_int() != 0 {
+        context.initialize_class(class_id)?;
+    }
+
+    Ok(Some(context.class_mirror(class_id)?))
+}
+
+fn class_init_class_name(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(args[0]).ok_or_else(Throwable::null_pointer)?;
+    let name = context.new_string(&context.class(class_id).java_name())?;
+    let name = context.intern_string(name)?;
+
+    // Class.getName caches the name in the mirror
+    context.set_field(args[0], "java/lang/Class", "name", name)?;
+
+    Ok(Some(name))
+}
+
+fn class_predicate(context: &mut VmContext, mirror: Value, predicate: fn(&RuntimeClass) -> bool) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(mirror).ok_or_else(Throwable::null_pointer)?;
+
+    Ok(Some(Value::from_bool(predicate(context.class(class_id)))))
+}
+
+/// Interfaces and primitive types don't have a superclass
+fn class_get_superclass(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(args[0]).ok_or_else(Throwable::null_pointer)?;
+
+    match context.class(class_id).super_class {
+        Some(super_class) if !context.class(class_id).is_interface() => Ok(Some(context.class_mirror(super_class)?)),
+        _ => Ok(Some(Value::null()))
+    }
+}
+
+fn class_get_interfaces(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(args[0]).ok_or_else(Throwable::null_pointer)?;
+    let interfaces = context.class(class_id).interfaces.clone();
+
+    Ok(Some(context.new_class_array(&interfaces)?))
+}
+
+fn class_is_assignable_from(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(args[0]).ok_or_else(Throwable::null_pointer)?;
+    let other = context.mirror_class(args[1]).ok_or_else(Throwable::null_pointer)?;
+
+    Ok(Some(Value::from_bool(context.is_subclass_of(other, class_id))))
+}
+
+fn array_new_array(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(args[0]).ok_or_else(Throwable::null_pointer)?;
+    let component_type = context.class_field_type(class_id).ok_or_else(|| Throwable::without_message("java/lang/IllegalArgumentException"))?;
+
+    Ok(Some(context.new_array(&component_type, args[1].as_int())?))
+}
+
+fn class_desired_assertion_status(_: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(Value::from_bool(false)))
+}
+
+fn throwable_fill_in_stack_trace(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    context.fill_in_stack_trace(args[0].as_reference());
+
+    Ok(Some(args[0]))
+}
+
+fn float_to_raw_int_bits(_: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(Value::Integer(args[0].as_float().to_bits())))
+}
+
+fn int_bits_to_float(_: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(Value::Float(args[0].as_int() as u32)))
+}
+
+fn double_to_raw_long_bits(_: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(Value::Long(args[0].as_double().to_bits())))
+}
+
+fn long_bits_to_double(_: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(Value::Double(args[0].as_long() as u64)))
+}
+
+type MathFunction = fn(f64) -> f64;
+
+fn register_math_natives(registry: &mut NativeRegistry) {
+    let unary: [(&str, MathFunction); 13] = [
+        ("sin", f64::sin), ("cos", f64::cos), ("tan", f64::tan),
+        ("asin", f64::asin), ("acos", f64::acos), ("atan", f64::atan),
+        ("exp", f64::exp), ("log", f64::ln), ("log10", f64::log10),
+        ("sqrt", f64::sqrt), ("cbrt", f64::cbrt), ("sinh", f64::sinh), ("cosh", f64::cosh)
+    ];
+
+    for (name, function) in unary {
+        registry.register("java/lang/StrictMath", name, "(D)D", move |_, args| Ok(Some(Value::from_double(function(args[0].as_double())))));
+    }
+
+    registry.register("java/lang/StrictMath", "tanh", "(D)D", |_, args| Ok(Some(Value::from_double(args[0].as_double().tanh()))));
+    registry.register("java/lang/StrictMath", "expm1", "(D)D", |_, args| Ok(Some(Value::from_double(args[0].as_double().exp_m1()))));
+    registry.register("java/lang/StrictMath", "log1p", "(D)D", |_, args| Ok(Some(Value::from_double(args[0].as_double().ln_1p()))));
+    registry.register("java/lang/StrictMath", "atan2", "(DD)D", |_, args| Ok(Some(Value::from_double(args[0].as_double().atan2(args[1].as_double())))));
+    registry.register("java/lang/StrictMath", "pow", "(DD)D", |_, args| Ok(Some(Value::from_double(args[0].as_double().powf(args[1].as_double())))));
+    registry.register("java/lang/StrictMath", "hypot", "(DD)D", |_, args| Ok(Some(Value::from_double(args[0].as_double().hypot(args[1].as_double())))));
+    registry.register("java/lang/StrictMath", "IEEEremainder", "(DD)D", |_, args| {
+        let (dividend, divisor) = (args[0].as_double(), args[1].as_double());
+
+        Ok(Some(Value::from_double(dividend - (dividend / divisor).round_ties_even() * divisor)))
+    });
+}
+
+pub fn register_builtin_natives(registry: &mut NativeRegistry) {
+    for class_name in ["java/lang/Object", "java/lang/System", "java/lang/Class", "java/lang/Thread", "jdk/internal/misc/Unsafe", "jdk/internal/misc/CDS", "jdk/internal/misc/ScopedMemoryAccess", "java/lang/ClassLoader"] {
+        registry.register(class_name, "registerNatives", "()V", no_op);
+    }
+
+    registry.register("java/lang/Object", "getClass", "()Ljava/lang/Class;", object_get_class);
+    registry.register("java/lang/Object", "hashCode", "()I", object_hash_code);
+    registry.register("java/lang/Object", "clone", "()Ljava/lang/Object;", object_clone);
+
+    registry.register("java/lang/System", "arraycopy", "(Ljava/lang/Object;ILjava/lang/Object;II)V", system_arraycopy);
+    registry.register("java/lang/System", "identityHashCode", "(Ljava/lang/Object;)I", system_identity_hash_code);
+    registry.register("java/lang/System", "currentTimeMillis", "()J", system_current_time_millis);
+    registry.register("java/lang/System", "nanoTime", "()J", system_nano_time);
+
+    registry.register("java/lang/ref/Reference", "getAndClearReferencePendingList", "()Ljava/lang/ref/Reference;", reference_get_and_clear_pending_list);
+    registry.register("java/lang/ref/Reference", "hasReferencePendingList", "()Z", |context, _| Ok(Some(Value::from_bool(!context.reference_pending_list.is_null()))));
+    // The pending list is processed right after each collection instead of by the reference handler thread
+    registry.register("java/lang/ref/Reference", "waitForReferencePendingList", "()V", no_op);
+    registry.register("java/lang/ref/Reference", "refersTo0", "(Ljava/lang/Object;)Z", reference_refers_to);
+    registry.register("java/lang/ref/PhantomReference", "refersTo0", "(Ljava/lang/Object;)Z", reference_refers_to);
+    registry.register("java/lang/ref/Reference", "clear0", "()V", reference_clear);
+
+    registry.register("java/lang/Runtime", "gc", "()V", runtime_gc);
+    registry.register("java/lang/Runtime", "maxMemory", "()J", |context, _| Ok(Some(Value::from_long(context.heap.max_bytes() as i64))));
+    registry.register("java/lang/Runtime", "totalMemory", "()J", |context, _| Ok(Some(Value::from_long(context.heap.max_bytes() as i64))));
+    registry.register("java/lang/Runtime", "freeMemory", "()J", |context, _| Ok(Some(Value::from_long(context.heap.max_bytes().saturating_sub(context.heap.used_bytes()) as i64))));
+    registry.register("java/lang/Runtime", "availableProcessors", "()I", |_, _| Ok(Some(Value::from_int(1))));
+
+    // System.exit and Runtime.halt end up here after running the shutdown hooks, it stops all threads
+    registry.register("java/lang/Shutdown", "beforeHalt", "()V", no_op);
+    registry.register("java/lang/Shutdown", "halt0", "(I)V", |_, args| Err(Throwable::Halt(Halt::Exit(args[0].as_int()))));
+
+    registry.register("java/lang/Class", "getPrimitiveClass", "(Ljava/lang/String;)Ljava/lang/Class;", class_get_primitive_class);
+    registry.register("java/lang/Class", "forName0", "(Ljava/lang/String;ZLjava/lang/ClassLoader;Ljava/lang/Class;)Ljava/lang/Class;", class_for_name);
+    registry.register("java/lang/Class", "initClassName", "()Ljava/lang/String;", class_init_class_name);
+    registry.register("java/lang/Class", "isArray", "()Z", |context, args| class_predicate(context, args[0], |class| class.is_array()));
+    registry.register("java/lang/Class", "isInterface", "()Z", |context, args| class_predicate(context, args[0], |class| class.is_interface()));
+    registry.register("java/lang/Class", "isPrimitive", "()Z", |context, args| class_predicate(context, args[0], |class| class.class.is_none() && !class.is_array()));
+    registry.register("java/lang/Class", "getSuperclass", "()Ljava/lang/Class;", class_get_superclass);
+    registry.register("java/lang/Class", "getInterfaces0", "()[Ljava/lang/Class;", class_get_interfaces);
+    // ACC_SUPER isn't a modifier of the language
+    registry.register("java/lang/Class", "getModifiers", "()I", |context, args| {
+        let class_id = context.mirror_class(args[0]).ok_or_else(Throwable::null_pointer)?;
+        Ok(Some(Value::from_int((context.class(class_id).access_flags & !access_flags::ACC_SUPER) as i32)))
+    });
+    registry.register("java/lang/Class", "isAssignableFrom", "(Ljava/lang/Class;)Z", class_is_assignable_from);
+    registry.register("java/lang/Class", "isInstance", "(Ljava/lang/Object;)Z", |context, args| {
+        let class_id = context.mirror_class(args[0]).ok_or_else(Throwable::null_pointer)?;
+        Ok(Some(Value::from_bool(!args[1].is_null() && context.is_instance_of(args[1], class_id)?)))
+    });
+    registry.register("java/lang/reflect/Array", "newArray", "(Ljava/lang/Class;I)Ljava/lang/Object;", array_new_array);
+    registry.register("java/lang/Class", "desiredAssertionStatus0", "(Ljava/lang/Class;)Z", class_desired_assertion_status);
+    registry.register("java/lang/Throwable", "fillInStackTrace", "(I)Ljava/lang/Throwable;", throwable_fill_in_stack_trace);
+
+    registry.register("java/lang/String", "intern", "()Ljava/lang/String;", string_intern);
+    registry.register("java/lang/StringUTF16", "isBigEndian", "()Z", |_, _| Ok(Some(Value::from_bool(false))));
+
+    registry.register("jdk/internal/reflect/Reflection", "getCallerClass", "()Ljava/lang/Class;", reflection_get_caller_class);
+    registry.register("jdk/internal/reflect/Reflection", "areNestMates", "(Ljava/lang/Class;Ljava/lang/Class;)Z", reflection_are_nest_mates);
+    registry.register("jdk/internal/reflect/Reflection", "getClassAccessFlags", "(Ljava/lang/Class;)I", |context, args| {
+        let class_id = context.mirror_class(args[0]).ok_or_else(Throwable::null_pointer)?;
+        Ok(Some(Value::from_int(context.class(class_id).access_flags as i32)))
+    });
+
+    registry.register("java/lang/Thread", "currentThread", "()Ljava/lang/Thread;", thread_current_thread);
+
+    // Without a stack inspection there are no privileged frames, which means full access
+    registry.register("java/security/AccessController", "getStackAccessControlContext", "()Ljava/security/AccessControlContext;", |_, _| Ok(Some(Value::null())));
+    registry.register("java/security/AccessController", "getInheritedAccessControlContext", "()Ljava/security/AccessControlContext;", |_, _| Ok(Some(Value::null())));
+
+    registry.register("jdk/internal/misc/Unsafe", "arrayBaseOffset0", "(Ljava/lang/Class;)I", |_, _| Ok(Some(Value::from_int(0))));
+    registry.register("jdk/internal/misc/Unsafe", "arrayIndexScale0", "(Ljava/lang/Class;)I", unsafe_array_index_scale);
+
+    // Module boundaries aren't enforced, the module graph is only kept by the Java side
+    registry.register("java/lang/Module", "defineModule0", "(Ljava/lang/Module;ZLjava/lang/String;Ljava/lang/String;[Ljava/lang/Object;)V", no_op);
+    registry.register("java/lang/Module", "addReads0", "(Ljava/lang/Module;Ljava/lang/Module;)V", no_op);
+    registry.register("java/lang/Module", "addExports0", "(Ljava/lang/Module;Ljava/lang/String;Ljava/lang/Module;)V", no_op);
+    registry.register("java/lang/Module", "addExportsToAll0", "(Ljava/lang/Module;Ljava/lang/String;)V", no_op);
+    registry.register("java/lang/Module", "addExportsToAllUnnamed0", "(Ljava/lang/Module;Ljava/lang/String;)V", no_op);
+    registry.register("jdk/internal/loader/BootLoader", "setBootLoaderUnnamedModule0", "(Ljava/lang/Module;)V", boot_loader_set_unnamed_module);
+
+    registry.register("java/lang/ClassLoader", "findBootstrapClass", "(Ljava/lang/String;)Ljava/lang/Class;", class_loader_find_bootstrap_class);
+    registry.register("java/lang/ClassLoader", "defineClass1", "(Ljava/lang/ClassLoader;Ljava/lang/String;[BIILjava/security/ProtectionDomain;Ljava/lang/String;)Ljava/lang/Class;", class_loader_define_class);
+
+    // Native libraries are linked into the VM, loading one of them just makes its natives available
+    registry.register_host("jdk/internal/loader/NativeLibraries", "findBuiltinLib", "(Ljava/lang/String;)Ljava/lang/String;", native_libraries_find_builtin_lib);
+    registry.register_host("jdk/internal/loader/NativeLibraries", "load", "(Ljdk/internal/loader/NativeLibraries$NativeLibraryImpl;Ljava/lang/String;ZZZ)Z", |_, _| Ok(Some(Value::from_bool(true))));
+    registry.register_host("jdk/internal/loader/NativeLibraries", "unload", "(Ljava/lang/String;ZZJ)V", no_op);
+    registry.register_host("jdk/internal/loader/NativeLibraries", "findEntry0", "(Ljdk/internal/loader/NativeLibraries$NativeLibraryImpl;Ljava/lang/String;)J", |_, _| Ok(Some(Value::from_long(0))));
+
+    // Archived module graphs and system properties of class data sharing aren't supported, so there's nothing to initialize
+    registry.register("jdk/internal/misc/VM", "initialize", "()V", no_op);
+
+    // Class data sharing isn't supported, there's never an archive
+    registry.register("jdk/internal/misc/CDS", "isDumpingClassList0", "()Z", |_, _| Ok(Some(Value::from_bool(false))));
+    registry.register("jdk/internal/misc/CDS", "isDumpingArchive0", "()Z", |_, _| Ok(Some(Value::from_bool(false))));
+    registry.register("jdk/internal/misc/CDS", "isSharingEnabled0", "()Z", |_, _| Ok(Some(Value::from_bool(false))));
+    registry.register("jdk/internal/misc/CDS", "getRandomSeedForDumping", "()J", |_, _| Ok(Some(Value::from_long(0))));
+    registry.register("jdk/internal/misc/CDS", "initializeFromArchive", "(Ljava/lang/Class;)V", no_op);
+
+    registry.register("java/lang/Float", "floatToRawIntBits", "(F)I", float_to_raw_int_bits);
+    registry.register("java/lang/Float", "intBitsToFloat", "(I)F", int_bits_to_float);
+    registry.register("java/lang/Double", "doubleToRawLongBits", "(D)J", double_to_raw_long_bits);
+    registry.register("java/lang/Double", "longBitsToDouble", "(J)D", long_bits_to_double);
+
+    register_math_natives(registry);
+    register_unsafe_natives(registry);
+    register_method_handle_natives(registry);
+    register_thread_natives(registry);
+    register_system_natives(registry);
+    register_file_system_natives(registry);
+    register_reflection_natives(registry);
+    register_annotation_natives(registry);
+    register_stack_trace_natives(registry);
+}
\ No newline at end of file
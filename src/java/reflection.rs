@@ -0,0 +1,618 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use crate::java;
+use crate::java::access_flags;
+use crate::java::annotation::raw_attribute;
+use crate::java::attribute::InnerClass;
+use crate::java::descriptor::{FieldType, MethodDescriptor};
+use crate::java::heap::ArrayData;
+use crate::java::invokedynamic::{widen, wrapper_class};
+use crate::java::native::NativeRegistry;
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::{Throwable, Value, VmContext};
+use crate::java::Attribute;
+
+const CLASS: &str = "java/lang/Class";
+const FIELD: &str = "java/lang/reflect/Field";
+const METHOD: &str = "java/lang/reflect/Method";
+const CONSTRUCTOR: &str = "java/lang/reflect/Constructor";
+
+const FIELD_CONSTRUCTOR: &str = "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/Class;IZILjava/lang/String;[B)V";
+const METHOD_CONSTRUCTOR: &str = "(Ljava/lang/Class;Ljava/lang/String;[Ljava/lang/Class;Ljava/lang/Class;[Ljava/lang/Class;IILjava/lang/String;[B[B[B)V";
+const CONSTRUCTOR_CONSTRUCTOR: &str = "(Ljava/lang/Class;[Ljava/lang/Class;[Ljava/lang/Class;IILjava/lang/String;[B[B)V";
+
+/// Whether a primitive of type `from` converts to `to` by identity or widening (JLS 5.1.2)
+fn is_widening(from: &FieldType, to: &FieldType) -> bool {
+    use FieldType::*;
+
+    from == to || matches!((from, to),
+        (Byte, Short | Int | Long | Float | Double) |
+        (Short | Char, Int | Long | Float | Double) |
+        (Int, Long | Float | Double) |
+        (Long, Float | Double) |
+        (Float, Double))
+}
+
+/// The primitive type a wrapper class boxes, e.g. `int` for `java/lang/Integer`
+fn unboxed_type(class_name: &str) -> Option<FieldType> {
+    ["Z", "B", "C", "S", "I", "J", "F", "D"].iter()
+        .filter_map(|descriptor| FieldType::parse(descriptor))
+        .find(|primitive| wrapper_class(primitive) == Some(class_name))
+}
+
+/// Generic signature of a class member from its Signature attribute
+fn member_signature(class: &java::Class, attributes: &[Attribute]) -> Option<String> {
+    attributes.iter().find_map(|attribute| match attribute {
+        Attribute::Signature(signature) => class.class_file.get_constant_pool_string(signature.signature_index as usize),
+        _ => None
+    })
+}
+
+/// Names of the checked exceptions a method declares in its Exceptions attribute
+fn declared_exceptions(class: &java::Class, method: &java::Method) -> Vec<String> {
+    method.attributes.iter()
+        .find_map(|attribute| match attribute {
+            Attribute::Exceptions(exceptions) => Some(exceptions.exception_index_table.iter()
+                .filter_map(|index| class.class_file.get_class_name(*index as usize))
+                .collect()),
+            _ => None
+        })
+        .unwrap_or_default()
+}
+
+/// Methods in the order of the class file together with their index in it, which is the slot of their reflection object
+pub fn declared_methods(class: &java::Class) -> Vec<(usize, Arc<java::Method>)> {
+    class.class_file.method_table.iter().enumerate()
+        .filter_map(|(slot, method_info)| {
+            let name = class.class_file.get_constant_pool_string(method_info.name_index as usize)?;
+            let descriptor = class.class_file.get_constant_pool_string(method_info.descriptor_index as usize)?;
+
+            Some((slot, class.find_method(&name, &descriptor)?.clone()))
+        })
+        .collect()
+}
+
+/// The InnerClasses entry describing the class itself, if it's a nested, local or anonymous class
+fn inner_class_entry(class: &java::Class) -> Option<InnerClass> {
+    let name = class.name();
+
+    match class.find_attribute("InnerClasses")? {
+        Attribute::InnerClasses(attribute) => attribute.classes.into_iter()
+            .find(|entry| class.class_file.get_class_name(entry.inner_class_info_index as usize).as_ref() == Some(&name)),
+        _ => None
+    }
+}
+
+/// The package of a class name in internal form, empty for the unnamed package
+fn package_name(class_name: &str) -> &str {
+    class_name.rsplit_once('/').map(|(package, _)| package).unwrap_or("")
+}
+
+impl VmContext {
+
+    /// The host of the nest a class belongs to (JVMS 5.4.4): the class its NestHost attribute names if that class
+    /// can be loaded, is in the same package and lists it in its NestMembers, otherwise the class itself
+    pub fn nest_host(&mut self, class_id: ClassId) -> ClassId {
+        let class = match &self.class(class_id).class {
+            Some(class) => class.clone(),
+            None => return class_id
+        };
+
+        let host_name = match class.find_attribute("NestHost") {
+            Some(Attribute::NestHost(attribute)) => class.class_file.get_class_name(attribute.host_class_index as usize),
+            _ => None
+        };
+
+        let host_name = match host_name {
+            Some(host_name) if package_name(&host_name) == package_name(&class.name()) => host_name,
+            _ => return class_id
+        };
+
+        let host_id = match self.load_class(&host_name) {
+            Ok(host_id) => host_id,
+            Err(_) => return class_id
+        };
+
+        let host = match self.class(host_id).class.clone() {
+            Some(host) => host,
+            None => return class_id
+        };
+
+        let name = class.name();
+        let is_member = match host.find_attribute("NestMembers") {
+            Some(Attribute::NestMembers(attribute)) => attribute.classes.iter()
+                .any(|member| host.class_file.get_class_name(*member as usize).as_ref() == Some(&name)),
+            _ => false
+        };
+
+        if is_member { host_id } else { class_id }
+    }
+
+    /// Keeps a value alive until the enclosing `with_roots` returns
+    pub fn rooted(&mut self, value: Value) -> Value {
+        self.native_roots.push(value);
+        value
+    }
+
+    pub fn interned_string(&mut self, string: &str) -> Result<Value, Throwable> {
+        let string = self.new_string(string)?;
+
+        self.intern_string(string)
+    }
+
+    pub fn optional_string(&mut self, string: Option<&str>) -> Result<Value, Throwable> {
+        match string {
+            Some(string) => self.new_string(string),
+            None => Ok(Value::null())
+        }
+    }
+
+    /// Creates an array of `component_class` objects, `element` creates each of them
+    pub fn new_reference_array<T>(&mut self, component_class: &str, items: &[T], element: impl Fn(&mut Self, &T) -> Result<Value, Throwable>) -> Result<Value, Throwable> {
+        let array = self.new_array(&FieldType::Object(component_class.to_string()), items.len() as i32)?;
+
+        self.with_roots(&[array], |context| -> Result<(), Throwable> {
+            for (index, item) in items.iter().enumerate() {
+                let value = element(context, item)?;
+
+                if let Some(array) = context.object_mut(array)?.array_mut() {
+                    array.set(index, value);
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(array)
+    }
+
+    /// Creates a `Class[]` with the mirrors of classes
+    pub fn new_class_array(&mut self, class_ids: &[ClassId]) -> Result<Value, Throwable> {
+        self.new_reference_array(CLASS, class_ids, |context, class_id| context.class_mirror(*class_id))
+    }
+
+    fn class_array_of_names(&mut self, class_names: &[String]) -> Result<Value, Throwable> {
+        self.new_reference_array(CLASS, class_names, |context, class_name| {
+            let class_id = context.load_class(class_name)?;
+            context.class_mirror(class_id)
+        })
+    }
+
+    /// Mirror of the class of values of a type, `None` is `void`
+    pub fn type_mirror(&mut self, field_type: Option<&FieldType>) -> Result<Value, Throwable> {
+        let class_id = match field_type {
+            Some(field_type) => self.load_type_class(field_type)?,
+            None => self.load_primitive_class("void")?
+        };
+
+        self.class_mirror(class_id)
+    }
+
+    pub fn type_mirrors(&mut self, types: &[FieldType]) -> Result<Value, Throwable> {
+        self.new_reference_array(CLASS, types, |context, field_type| context.type_mirror(Some(field_type)))
+    }
+
+    /// The `java.lang.reflect.Field` of the field at `slot` in the class file
+    fn new_reflected_field(&mut self, class_id: ClassId, class: &java::Class, slot: usize) -> Result<Value, Throwable> {
+        let field_info = &class.class_file.field_table[slot];
+        let field = class.class_file.get_constant_pool_string(field_info.name_index as usize)
+            .and_then(|name| class.fields.get(&name))
+            .ok_or_else(|| Throwable::new("java/lang/InternalError", "Malformed field"))?;
+        let field_type = FieldType::parse(&field.descriptor).ok_or_else(|| Throwable::new("java/lang/InternalError", &field.descriptor))?;
+
+        // Final static fields can't be changed through reflection, not even after setAccessible
+        let trusted_final = field.access_flags & access_flags::ACC_FINAL != 0 && field.is_static();
+
+        self.with_roots(&[], |context| {
+            let args = [
+                context.class_mirror(class_id)?,
+                context.interned_string(&field.name)?,
+                context.type_mirror(Some(&field_type))?,
+                Value::from_int(field.access_flags as i32),
+                Value::from_bool(trusted_final),
+                Value::from_int(slot as i32),
+                {
+                    let signature = context.optional_string(member_signature(class, &field.attributes).as_deref())?;
+                    context.rooted(signature)
+                },
+                {
+                    let annotations = context.raw_attribute_array(raw_attribute(&class.class_file, &field_info.attributes, "RuntimeVisibleAnnotations"))?;
+                    context.rooted(annotations)
+                }
+            ];
+
+            context.construct(FIELD, FIELD_CONSTRUCTOR, &args)
+        })
+    }
+
+    /// The `java.lang.reflect.Method` or `Constructor` of the method at `slot` in the class file
+    fn new_reflected_method(&mut self, class_id: ClassId, class: &java::Class, slot: usize, method: &java::Method) -> Result<Value, Throwable> {
+        let descriptor = MethodDescriptor::parse(&method.descriptor).ok_or_else(|| Throwable::new("java/lang/InternalError", &method.descriptor))?;
+        let exceptions = declared_exceptions(class, method);
+
+        self.with_roots(&[], |context| {
+            let declaring_class = context.class_mirror(class_id)?;
+            let parameter_types = context.type_mirrors(&descriptor.parameters)?;
+            context.rooted(parameter_types);
+            let exception_types = context.class_array_of_names(&exceptions)?;
+            context.rooted(exception_types);
+            let signature = context.optional_string(member_signature(class, &method.attributes).as_deref())?;
+            context.rooted(signature);
+
+            let attributes = &class.class_file.method_table[slot].attributes;
+            let annotations = context.raw_attribute_array(raw_attribute(&class.class_file, attributes, "RuntimeVisibleAnnotations"))?;
+            context.rooted(annotations);
+            let parameter_annotations = context.raw_attribute_array(raw_attribute(&class.class_file, attributes, "RuntimeVisibleParameterAnnotations"))?;
+            context.rooted(parameter_annotations);
+
+            let modifiers = Value::from_int(method.access_flags as i32);
+            let slot = Value::from_int(slot as i32);
+
+            if method.name == "<init>" {
+                let args = [declaring_class, parameter_types, exception_types, modifiers, slot, signature, annotations, parameter_annotations];
+
+                return context.construct(CONSTRUCTOR, CONSTRUCTOR_CONSTRUCTOR, &args);
+            }
+
+            let name = context.interned_string(&method.name)?;
+            let return_type = context.type_mirror(descriptor.return_type.as_ref())?;
+            let annotation_default = context.raw_attribute_array(raw_attribute(&class.class_file, attributes, "AnnotationDefault"))?;
+            let args = [declaring_class, name, parameter_types, return_type, exception_types, modifiers, slot, signature, annotations, parameter_annotations, annotation_default];
+
+            context.construct(METHOD, METHOD_CONSTRUCTOR, &args)
+        })
+    }
+
+    /// The class and method a `java.lang.reflect.Method` or `Constructor` stands for
+    pub fn reflected_method(&mut self, member: Value, member_class: &str) -> Result<(ClassId, Arc<java::Method>), Throwable> {
+        let mirror = self.get_field(member, member_class, "clazz")?;
+        let slot = self.get_field(member, member_class, "slot")?.as_int();
+        let class_id = self.mirror_class(mirror).ok_or_else(Throwable::null_pointer)?;
+
+        self.class(class_id).class.as_ref()
+            .and_then(|class| declared_methods(class).into_iter().find(|(index, _)| *index as i32 == slot))
+            .map(|(_, method)| (class_id, method))
+            .ok_or_else(|| Throwable::new("java/lang/InternalError", "Invalid method slot"))
+    }
+
+    /// Converts the `Object[]` arguments of a reflective call to the parameter types, unboxing and widening primitives
+    fn reflective_arguments(&mut self, parameters: &[FieldType], args: Value) -> Result<Vec<Value>, Throwable> {
+        let values: Vec<Value> = match args.is_null() {
+            true => vec![],
+            false => self.object(args)?.array().map(|array| (0..array.len()).map(|index| array.get(index)).collect()).unwrap_or_default()
+        };
+
+        if values.len() != parameters.len() {
+            return Err(Throwable::new("java/lang/IllegalArgumentException", "wrong number of arguments"));
+        }
+
+        let argument_type_mismatch = || Throwable::new("java/lang/IllegalArgumentException", "argument type mismatch");
+
+        let mut converted = Vec::with_capacity(values.len());
+        for (value, parameter) in values.into_iter().zip(parameters) {
+            if parameter.is_reference() {
+                let parameter_class = self.load_type_class(parameter)?;
+                if !value.is_null() && !self.is_instance_of(value, parameter_class)? {
+                    return Err(argument_type_mismatch());
+                }
+
+                converted.push(value);
+                continue;
+            }
+
+            let boxed_type = match value.is_null() {
+                true => None,
+                false => unboxed_type(&self.class(self.object(value)?.class).name)
+            };
+
+            match boxed_type.filter(|boxed_type| is_widening(boxed_type, parameter)) {
+                Some(boxed_type) => {
+                    let unboxed = self.unbox_value(value, &boxed_type)?;
+                    converted.push(widen(unboxed, &boxed_type, parameter));
+                },
+                None => return Err(argument_type_mismatch())
+            }
+        }
+
+        Ok(converted)
+    }
+
+    /// Wraps an exception thrown by a reflectively invoked method in an `InvocationTargetException`
+    fn invocation_target_exception(&mut self, throwable: Throwable) -> Throwable {
+        let target = match self.throwable_object(throwable) {
+            Throwable::Object(reference) => Value::Reference(reference),
+            throwable => return throwable
+        };
+
+        match self.with_roots(&[target], |context| context.construct("java/lang/reflect/InvocationTargetException", "(Ljava/lang/Throwable;)V", &[target])) {
+            Ok(exception) => Throwable::Object(exception.as_reference()),
+            Err(throwable) => throwable
+        }
+    }
+
+    /// Raises `ReflectionFactory.inflationThreshold` so reflective calls keep using the native accessors,
+    /// which would otherwise be replaced by generated byte code after a few calls
+    pub fn disable_accessor_generation(&mut self, class_id: ClassId) {
+        self.classes[class_id as usize].static_values.insert("inflationThreshold".to_string(), Value::from_int(i32::MAX));
+    }
+
+}
+
+/// The class file of the class a mirror represents, `None` for primitive and array classes
+fn mirror_class_file(context: &VmContext, mirror: Value) -> Result<(ClassId, Option<Arc<java::Class>>), Throwable> {
+    let class_id = context.mirror_class(mirror).ok_or_else(Throwable::null_pointer)?;
+
+    Ok((class_id, context.class(class_id).class.clone()))
+}
+
+fn class_get_declared_fields(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (class_id, class) = mirror_class_file(context, args[0])?;
+    let public_only = args[1].as_int() != 0;
+
+    let class = match class {
+        Some(class) => class,
+        None => return Ok(Some(context.new_reference_array::<usize>(FIELD, &[], |_, _| Ok(Value::null()))?))
+    };
+
+    let slots: Vec<usize> = class.class_file.field_table.iter().enumerate()
+        .filter(|(_, field_info)| !public_only || field_info.access_flags & access_flags::ACC_PUBLIC != 0)
+        .map(|(slot, _)| slot)
+        .collect();
+
+    Ok(Some(context.new_reference_array(FIELD, &slots, |context, slot| context.new_reflected_field(class_id, &class, *slot))?))
+}
+
+/// Declared methods or constructors, `<clinit>` is neither
+fn declared_members(context: &mut VmContext, mirror: Value, public_only: bool, constructors: bool) -> Result<Value, Throwable> {
+    let (class_id, class) = mirror_class_file(context, mirror)?;
+    let member_class = if constructors { CONSTRUCTOR } else { METHOD };
+
+    let class = match class {
+        Some(class) => class,
+        None => return context.new_reference_array::<usize>(member_class, &[], |_, _| Ok(Value::null()))
+    };
+
+    let methods: Vec<(usize, Arc<java::Method>)> = declared_methods(&class).into_iter()
+        .filter(|(_, method)| (method.name == "<init>") == constructors && method.name != "<clinit>")
+        .filter(|(_, method)| !public_only || method.access_flags & access_flags::ACC_PUBLIC != 0)
+        .collect();
+
+    context.new_reference_array(member_class, &methods, |context, (slot, method)| context.new_reflected_method(class_id, &class, *slot, method))
+}
+
+fn class_get_declared_methods(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(declared_members(context, args[0], args[1].as_int() != 0, false)?))
+}
+
+fn class_get_declared_constructors(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(declared_members(context, args[0], args[1].as_int() != 0, true)?))
+}
+
+/// The class a member class is declared in, local and anonymous classes have none
+fn class_get_declaring_class(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let outer_class = match mirror_class_file(context, args[0])?.1 {
+        Some(class) => inner_class_entry(&class)
+            .filter(|entry| entry.outer_class_info_index != 0)
+            .and_then(|entry| class.class_file.get_class_name(entry.outer_class_info_index as usize)),
+        None => None
+    };
+
+    match outer_class {
+        Some(outer_class) => {
+            let class_id = context.load_class(&outer_class)?;
+            Ok(Some(context.class_mirror(class_id)?))
+        },
+        None => Ok(Some(Value::null()))
+    }
+}
+
+/// The simple name of a nested class from its InnerClasses entry, `null` for top-level and anonymous classes
+fn class_get_simple_binary_name(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let simple_name = match mirror_class_file(context, args[0])?.1 {
+        Some(class) => inner_class_entry(&class)
+            .filter(|entry| entry.inner_name_index != 0)
+            .and_then(|entry| class.class_file.get_constant_pool_string(entry.inner_name_index as usize)),
+        None => None
+    };
+
+    Ok(Some(context.optional_string(simple_name.as_deref())?))
+}
+
+/// The member classes declared in a class
+fn class_get_declared_classes(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let member_classes: Vec<String> = match mirror_class_file(context, args[0])?.1 {
+        Some(class) => match class.find_attribute("InnerClasses") {
+            Some(Attribute::InnerClasses(attribute)) => {
+                let name = class.name();
+
+                attribute.classes.iter()
+                    .filter(|entry| entry.outer_class_info_index != 0 && entry.inner_name_index != 0)
+                    .filter(|entry| class.class_file.get_class_name(entry.outer_class_info_index as usize).as_ref() == Some(&name))
+                    .filter_map(|entry| class.class_file.get_class_name(entry.inner_class_info_index as usize))
+                    .collect()
+            },
+            _ => vec![]
+        },
+        None => vec![]
+    };
+
+    Ok(Some(context.class_array_of_names(&member_classes)?))
+}
+
+/// The class, method name and descriptor enclosing a local or anonymous class, `null` for other classes
+fn class_get_enclosing_method(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class = match mirror_class_file(context, args[0])?.1 {
+        Some(class) => class,
+        None => return Ok(Some(Value::null()))
+    };
+
+    let enclosing_method = match class.find_attribute("EnclosingMethod") {
+        Some(Attribute::EnclosingMethod(attribute)) => attribute,
+        _ => return Ok(Some(Value::null()))
+    };
+
+    let enclosing_class = class.class_file.get_class_name(enclosing_method.class_index as usize)
+        .ok_or_else(|| Throwable::new("java/lang/InternalError", "Malformed EnclosingMethod attribute"))?;
+    let (name, descriptor) = match enclosing_method.method_index {
+        0 => (None, None),
+        index => class.class_file.get_name_and_type(index as usize).unzip()
+    };
+
+    let enclosing_class = context.load_class(&enclosing_class)?;
+    let values = [
+        context.class_mirror(enclosing_class)?,
+        match name { Some(name) => context.interned_string(&name)?, None => Value::null() },
+        match descriptor { Some(descriptor) => context.interned_string(&descriptor)?, None => Value::null() }
+    ];
+
+    Ok(Some(context.new_reference_array("java/lang/Object", &values, |_, value| Ok(*value))?))
+}
+
+fn class_get_generic_signature(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let signature = match mirror_class_file(context, args[0])?.1 {
+        Some(class) => match class.find_attribute("Signature") {
+            Some(Attribute::Signature(attribute)) => class.class_file.get_constant_pool_string(attribute.signature_index as usize),
+            _ => None
+        },
+        None => None
+    };
+
+    Ok(Some(context.optional_string(signature.as_deref())?))
+}
+
+/// `NativeMethodAccessorImpl.invoke0(Method, Object, Object[])`
+fn method_invoke(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (class_id, method) = context.reflected_method(args[0], METHOD)?;
+    let descriptor = MethodDescriptor::parse(&method.descriptor).ok_or_else(|| Throwable::new("java/lang/InternalError", &method.descriptor))?;
+
+    let mut values = vec![];
+    if method.is_static() {
+        context.initialize_class(class_id)?;
+    } else {
+        let receiver = args[1];
+        if receiver.is_null() {
+            return Err(Throwable::null_pointer());
+        }
+
+        if !context.is_instance_of(receiver, class_id)? {
+            return Err(Throwable::new("java/lang/IllegalArgumentException", "object is not an instance of declaring class"));
+        }
+
+        values.push(receiver);
+    }
+
+    values.extend(context.reflective_arguments(&descriptor.parameters, args[2])?);
+
+    // Like invokevirtual, overriding methods are selected by the class of the receiver
+    let result = match method.is_static() || method.is_private() {
+        true => context.invoke(class_id, method.clone(), &values),
+        false => context.invoke_virtual(&method.name, &method.descriptor, &values)
+    };
+
+    match (result, &descriptor.return_type) {
+        (Ok(Some(value)), Some(return_type)) => Ok(Some(context.box_value(value, return_type)?)),
+        (Ok(_), _) => Ok(Some(Value::null())),
+        (Err(throwable), _) => Err(context.invocation_target_exception(throwable))
+    }
+}
+
+/// `NativeConstructorAccessorImpl.newInstance0(Constructor, Object[])`
+fn constructor_new_instance(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (class_id, constructor) = context.reflected_method(args[0], CONSTRUCTOR)?;
+    let descriptor = MethodDescriptor::parse(&constructor.descriptor).ok_or_else(|| Throwable::new("java/lang/InternalError", &constructor.descriptor))?;
+
+    let class = context.class(class_id);
+    if class.is_interface() || class.is_abstract() {
+        return Err(Throwable::new("java/lang/InstantiationException", &class.java_name()));
+    }
+
+    context.initialize_class(class_id)?;
+
+    let mut values = context.reflective_arguments(&descriptor.parameters, args[1])?;
+    let object = context.new_object(class_id);
+    values.insert(0, object);
+
+    match context.with_roots(&[object], |context| context.invoke(class_id, constructor, &values)) {
+        Ok(_) => Ok(Some(object)),
+        Err(throwable) => Err(context.invocation_target_exception(throwable))
+    }
+}
+
+/// Natives of `java.lang.Class` that look into class files and the accessors of `java.lang.reflect`.
+/// Fields are read and written by `Unsafe` field accessors, see `STATIC_FIELD_OFFSET`.
+/// The elements and component type of an array passed to `java.lang.reflect.Array`
+fn reflected_array(context: &VmContext, array: Value) -> Result<(&ArrayData, Option<FieldType>), Throwable> {
+    if array.is_null() {
+        return Err(Throwable::null_pointer());
+    }
+
+    let object = context.object(array)?;
+    let elements = object.array().ok_or_else(|| Throwable::new("java/lang/IllegalArgumentException", "Argument is not an array"))?;
+
+    Ok((elements, context.class(object.class).component_type.clone()))
+}
+
+fn array_get_length(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (elements, _) = reflected_array(context, args[0])?;
+
+    Ok(Some(Value::from_int(elements.len() as i32)))
+}
+
+/// The element at an index, boxed if the array is primitive
+fn array_get(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (elements, component_type) = reflected_array(context, args[0])?;
+    let index = args[1].as_int();
+
+    if index < 0 || index as usize >= elements.len() {
+        return Err(Throwable::new("java/lang/ArrayIndexOutOfBoundsException", &format!("Index {} out of bounds for length {}", index, elements.len())));
+    }
+
+    let element = elements.get(index as usize);
+    match component_type {
+        Some(component_type) => Ok(Some(context.box_value(element, &component_type)?)),
+        None => Ok(Some(element))
+    }
+}
+
+pub fn register_reflection_natives(registry: &mut NativeRegistry) {
+    registry.register(CLASS, "getDeclaredFields0", "(Z)[Ljava/lang/reflect/Field;", class_get_declared_fields);
+    registry.register(CLASS, "getDeclaredMethods0", "(Z)[Ljava/lang/reflect/Method;", class_get_declared_methods);
+    registry.register(CLASS, "getDeclaredConstructors0", "(Z)[Ljava/lang/reflect/Constructor;", class_get_declared_constructors);
+    registry.register(CLASS, "getDeclaredClasses0", "()[Ljava/lang/Class;", class_get_declared_classes);
+    registry.register(CLASS, "getDeclaringClass0", "()Ljava/lang/Class;", class_get_declaring_class);
+    registry.register(CLASS, "getSimpleBinaryName0", "()Ljava/lang/String;", class_get_simple_binary_name);
+    registry.register(CLASS, "getEnclosingMethod0", "()[Ljava/lang/Object;", class_get_enclosing_method);
+    registry.register(CLASS, "getGenericSignature0", "()Ljava/lang/String;", class_get_generic_signature);
+
+    // Classes defined at runtime aren't hidden either, and records and sealed classes aren't parsed yet
+    registry.register(CLASS, "isHidden", "()Z", |_, _| Ok(Some(Value::from_bool(false))));
+    registry.register(CLASS, "isRecord0", "()Z", |_, _| Ok(Some(Value::from_bool(false))));
+    registry.register(CLASS, "getRecordComponents0", "()[Ljava/lang/reflect/RecordComponent;", |_, _| Ok(Some(Value::null())));
+    registry.register(CLASS, "getPermittedSubclasses0", "()[Ljava/lang/Class;", |_, _| Ok(Some(Value::null())));
+    registry.register(CLASS, "getNestHost0", "()Ljava/lang/Class;", |context, args| {
+        let class_id = context.mirror_class(args[0]).ok_or_else(Throwable::null_pointer)?;
+        let host_id = context.nest_host(class_id);
+        Ok(Some(context.class_mirror(host_id)?))
+    });
+
+    // Type annotations aren't exposed, see `register_annotation_natives` for the declaration ones
+    registry.register(CLASS, "getRawTypeAnnotations", "()[B", |_, _| Ok(Some(Value::null())));
+    registry.register("java/lang/reflect/Executable", "getTypeAnnotationBytes0", "()[B", |_, _| Ok(Some(Value::null())));
+    registry.register(FIELD, "getTypeAnnotationBytes0", "()[B", |_, _| Ok(Some(Value::null())));
+
+    // Without MethodParameters the parameters are synthesized as arg0, arg1, ...
+    registry.register("java/lang/reflect/Executable", "getParameters0", "()[Ljava/lang/reflect/Parameter;", |_, _| Ok(Some(Value::null())));
+
+    // Classes aren't signed and there are no security policies
+    registry.register(CLASS, "getSigners", "()[Ljava/lang/Object;", |_, _| Ok(Some(Value::null())));
+    registry.register(CLASS, "setSigners", "([Ljava/lang/Object;)V", |_, _| Ok(None));
+    registry.register(CLASS, "getProtectionDomain0", "()Ljava/security/ProtectionDomain;", |_, _| Ok(Some(Value::null())));
+
+    registry.register("java/lang/reflect/Array", "getLength", "(Ljava/lang/Object;)I", array_get_length);
+    registry.register("java/lang/reflect/Array", "get", "(Ljava/lang/Object;I)Ljava/lang/Object;", array_get);
+
+    registry.register("jdk/internal/reflect/NativeMethodAccessorImpl", "invoke0", "(Ljava/lang/reflect/Method;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;", method_invoke);
+    registry.register("jdk/internal/reflect/NativeConstructorAccessorImpl", "newInstance0", "(Ljava/lang/reflect/Constructor;[Ljava/lang/Object;)Ljava/lang/Object;", constructor_new_instance);
+}
\ No newline at end of file
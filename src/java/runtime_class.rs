@@ -0,0 +1,97 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::java;
+use crate::java::access_flags;
+use crate::java::decoded_code::DecodedCode;
+use crate::java::dispatch::DispatchTable;
+use crate::java::descriptor::FieldType;
+use crate::java::vm::Value;
+
+/// Index of a loaded class in `VmContext::classes`
+pub type ClassId = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClassState {
+    Linked,
+    Initializing,
+    Initialized,
+    Erroneous
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldSlot {
+    pub declaring_class: ClassId,
+    pub name: String,
+    pub descriptor: String
+}
+
+/// A loaded and linked class together with its runtime state
+#[derive(Debug)]
+pub struct RuntimeClass {
+    pub id: ClassId,
+    pub name: String,
+
+    /// `None` for array classes, which have no class file
+    pub class: Option<Arc<java::Class>>,
+    pub access_flags: u16,
+
+    /// Code of the methods decoded when the class was linked, by the address of the method
+    pub decoded_code: HashMap<usize, Arc<DecodedCode>>,
+    /// Methods selected by virtual and interface calls, empty until the class is linked and for primitive classes
+    pub dispatch: DispatchTable,
+
+    pub super_class: Option<ClassId>,
+    pub interfaces: Vec<ClassId>,
+
+    /// Element type of array classes
+    pub component_type: Option<FieldType>,
+
+    /// Instance field layout, starting with the fields of the super class
+    pub instance_fields: Vec<FieldSlot>,
+    pub static_values: HashMap<String, Value>,
+
+    /// The `java.lang.Class` object of this class, `0` until it's first needed
+    pub mirror: u32,
+
+    /// Whether instances have a non-empty `finalize()` method that runs before they are freed
+    pub has_finalizer: bool,
+
+    pub state: ClassState
+}
+
+impl RuntimeClass {
+
+    pub fn is_interface(&self) -> bool {
+        self.access_flags & access_flags::ACC_INTERFACE != 0
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags & access_flags::ACC_ABSTRACT != 0
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.component_type.is_some()
+    }
+
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&Arc<java::Method>> {
+        self.class.as_ref()?.find_method(name, descriptor)
+    }
+
+    pub fn find_field(&self, name: &str, descriptor: &str) -> Option<&java::Field> {
+        self.class.as_ref()?.fields.get(name).filter(|field| field.descriptor == descriptor)
+    }
+
+    /// Index into the instance field values of a field declared by `declaring_class`
+    pub fn field_slot(&self, declaring_class: ClassId, name: &str) -> Option<usize> {
+        self.instance_fields.iter().position(|slot| slot.declaring_class == declaring_class && slot.name == name)
+    }
+
+    /// Class name as written in Java source, e.g. `java.lang.Object`
+    pub fn java_name(&self) -> String {
+        self.name.replace('/', ".")
+    }
+
+}
\ No newline at end of file
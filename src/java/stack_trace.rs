@@ -0,0 +1,269 @@
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+
+use crate::java::native::NativeRegistry;
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::{Throwable, TraceFrame, Value, VmContext};
+
+const STACK_TRACE_ELEMENT: &str = "java/lang/StackTraceElement";
+const STACK_FRAME_INFO: &str = "java/lang/StackFrameInfo";
+const MEMBER_NAME: &str = "java/lang/invoke/MemberName";
+const STACK_STREAM_FACTORY: &str = "java/lang/StackStreamFactory";
+const ABSTRACT_STACK_WALKER: &str = "java/lang/StackStreamFactory$AbstractStackWalker";
+
+/// `StackWalker` mode filling the frame buffer with classes instead of `StackFrameInfo` objects
+const FILL_CLASS_REFS_ONLY: i64 = 0x2;
+
+/// `MemberName` flags of methods and constructors
+const IS_METHOD: i32 = 0x10000;
+const IS_CONSTRUCTOR: i32 = 0x20000;
+
+impl TraceFrame {
+
+    /// Source line of the instruction the frame was at, `None` if the class file has no line numbers
+    pub fn line_number(&self) -> Option<u16> {
+        self.method.line_number(self.program_counter)
+    }
+
+}
+
+impl VmContext {
+
+    /// Frames of the running thread, innermost first
+    pub fn current_stack_trace(&self) -> Vec<TraceFrame> {
+        self.executor.frames.iter().rev().map(TraceFrame::from).collect()
+    }
+
+    /// Name of the source file of the class a frame is in, e.g. `Foo.java`
+    pub fn frame_source_file(&self, frame: &TraceFrame) -> Option<String> {
+        self.class(frame.class).class.as_ref()?.source_file().map(str::to_string)
+    }
+
+    /// A frame like `StackTraceElement.toString`, e.g. `com.acme.Foo.bar(Foo.java:42)`
+    pub fn describe_frame(&self, frame: &TraceFrame) -> String {
+        let location = match (self.frame_source_file(frame), frame.line_number()) {
+            (Some(file_name), Some(line_number)) => format!("{}:{}", file_name, line_number),
+            (Some(file_name), None) => file_name,
+            (None, _) => "Unknown Source".to_string()
+        };
+
+        format!("{}.{}({})", self.class(frame.class).java_name(), frame.method.name, location)
+    }
+
+    /// `cause` of a throwable object, `None` if it has none or wasn't initialized, which makes it the throwable itself
+    fn throwable_cause(&self, throwable: u32) -> Option<u32> {
+        let object = self.heap.get(throwable)?;
+        let throwable_class = *self.class_ids.get("java/lang/Throwable")?;
+        let slot = self.class(object.class).field_slot(throwable_class, "cause")?;
+
+        match object.field(slot)? {
+            Value::Reference(cause) if cause != 0 && cause != throwable => Some(cause),
+            _ => None
+        }
+    }
+
+    /// A throwable with its stack trace and causes, formatted like `Throwable.printStackTrace` does
+    pub fn format_stack_trace(&self, throwable: &Throwable) -> String {
+        let mut output = format!("{}\n", self.describe_throwable(throwable));
+
+        let mut reference = match throwable {
+            Throwable::Object(reference) => *reference,
+            _ => return output
+        };
+
+        let mut enclosing_frames: &[TraceFrame] = &[];
+        let mut seen = HashSet::new();
+
+        loop {
+            let frames = self.backtraces.get(&reference).map(Vec::as_slice).unwrap_or_default();
+
+            // Frames a cause has in common with the trace it caused are left out, from the outermost one on
+            let in_common = frames.iter().rev().zip(enclosing_frames.iter().rev())
+                .take_while(|(frame, enclosing)| frame.class == enclosing.class && frame.method.name == enclosing.method.name && frame.program_counter == enclosing.program_counter)
+                .count();
+
+            for frame in &frames[..frames.len() - in_common] {
+                output.push_str(&format!("\tat {}\n", self.describe_frame(frame)));
+            }
+            if in_common > 0 {
+                output.push_str(&format!("\t... {} more\n", in_common));
+            }
+
+            seen.insert(reference);
+            reference = match self.throwable_cause(reference).filter(|cause| !seen.contains(cause)) {
+                Some(cause) => cause,
+                None => return output
+            };
+
+            output.push_str(&format!("Caused by: {}\n", self.describe_throwable(&Throwable::Object(reference))));
+            enclosing_frames = frames;
+        }
+    }
+
+    /// Fills in a `java.lang.StackTraceElement` for a frame, its format is computed by the Java side
+    fn init_stack_trace_element(&mut self, element: Value, frame: &TraceFrame) -> Result<(), Throwable> {
+        let declaring_class = self.class_mirror(frame.class)?;
+        self.set_field(element, STACK_TRACE_ELEMENT, "declaringClassObject", declaring_class)?;
+
+        let class_name = self.interned_string(&self.class(frame.class).java_name())?;
+        self.set_field(element, STACK_TRACE_ELEMENT, "declaringClass", class_name)?;
+
+        let method_name = self.interned_string(&frame.method.name)?;
+        self.set_field(element, STACK_TRACE_ELEMENT, "methodName", method_name)?;
+
+        let file_name = self.frame_source_file(frame);
+        let file_name = self.optional_string(file_name.as_deref())?;
+        self.set_field(element, STACK_TRACE_ELEMENT, "fileName", file_name)?;
+
+        // -2 marks native methods, which never have frames of their own here
+        let line_number = frame.line_number().map(i32::from).unwrap_or(-1);
+        self.set_field(element, STACK_TRACE_ELEMENT, "lineNumber", Value::from_int(line_number))
+    }
+
+    /// The frame a `java.lang.StackFrameInfo` filled in by a stack walk stands for
+    fn stack_frame_info_frame(&mut self, stack_frame_info: Value) -> Result<TraceFrame, Throwable> {
+        let member_name = self.get_field(stack_frame_info, STACK_FRAME_INFO, "memberName")?;
+        let program_counter = self.get_field(stack_frame_info, STACK_FRAME_INFO, "bci")?.as_int() as usize;
+
+        let mirror = self.get_field(member_name, MEMBER_NAME, "clazz")?;
+        let name = self.get_field(member_name, MEMBER_NAME, "name")?;
+        let descriptor = self.get_field(member_name, MEMBER_NAME, "type")?;
+        let (name, descriptor) = (self.rust_string(name)?, self.rust_string(descriptor)?);
+
+        let class = self.mirror_class(mirror).ok_or_else(|| Throwable::new("java/lang/InternalError", "Stack frame without a class"))?;
+        let method = self.class(class).class.as_ref()
+            .and_then(|class_file| class_file.find_method(&name, &descriptor).cloned())
+            .ok_or_else(|| Throwable::new("java/lang/InternalError", &format!("Stack frame of an unknown method {}{}", name, descriptor)))?;
+
+        Ok(TraceFrame { class, method, program_counter })
+    }
+
+    /// Stores the next frames of a stack walk into its frame buffer from `start_index` on, returns the index after the last one
+    fn fill_stack_frames(&mut self, anchor: u32, mode: i64, batch_size: i32, start_index: i32, frames: Value) -> Result<i32, Throwable> {
+        let capacity = self.object(frames)?.array().map(|array| array.len()).unwrap_or(0);
+        let end = (start_index.max(0) as usize + batch_size.max(0) as usize).min(capacity);
+
+        let mut index = start_index.max(0) as usize;
+        while index < end {
+            let frame = match self.stack_walks.get_mut(&anchor).filter(|remaining| !remaining.is_empty()) {
+                Some(remaining) => remaining.remove(0),
+                None => break
+            };
+
+            let mirror = self.class_mirror(frame.class)?;
+            if mode & FILL_CLASS_REFS_ONLY != 0 {
+                self.set_array_element(frames, index as i32, mirror)?;
+            } else {
+                let stack_frame_info = self.array_element(frames, index as i32)?;
+                let member_name = self.get_field(stack_frame_info, STACK_FRAME_INFO, "memberName")?;
+
+                let kind = if frame.method.name == "<init>" { IS_CONSTRUCTOR } else { IS_METHOD };
+                self.set_field(member_name, MEMBER_NAME, "clazz", mirror)?;
+                self.set_field(member_name, MEMBER_NAME, "flags", Value::from_int(kind | frame.method.access_flags as i32))?;
+                let name = self.interned_string(&frame.method.name)?;
+                self.set_field(member_name, MEMBER_NAME, "name", name)?;
+                let descriptor = self.interned_string(&frame.method.descriptor)?;
+                self.set_field(member_name, MEMBER_NAME, "type", descriptor)?;
+
+                self.set_field(stack_frame_info, STACK_FRAME_INFO, "bci", Value::from_int(frame.program_counter as i32))?;
+            }
+
+            index += 1;
+        }
+
+        Ok(index as i32)
+    }
+
+    /// Whether a frame belongs to the implementation of `StackWalker`, these are left out of every walk
+    fn is_stack_walker_frame(&self, class_id: ClassId, abstract_stack_walker: ClassId) -> bool {
+        let class = self.class(class_id);
+
+        class.name == "java/lang/StackWalker" || class_id == abstract_stack_walker || class.super_class == Some(abstract_stack_walker)
+    }
+
+}
+
+/// Fills in the elements `StackTraceElement.of` allocated for the frames captured by `fillInStackTrace`
+fn stack_trace_element_init_stack_trace_elements(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    if args[0].is_null() || args[1].is_null() {
+        return Err(Throwable::null_pointer());
+    }
+
+    let frames = context.backtraces.get(&args[1].as_reference()).cloned().unwrap_or_default();
+
+    for (index, frame) in frames.iter().enumerate() {
+        let element = context.array_element(args[0], index as i32)?;
+        context.init_stack_trace_element(element, frame)?;
+    }
+
+    Ok(None)
+}
+
+fn stack_trace_element_init_stack_trace_element(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    if args[0].is_null() || args[1].is_null() {
+        return Err(Throwable::null_pointer());
+    }
+
+    let frame = context.stack_frame_info_frame(args[1])?;
+    context.init_stack_trace_element(args[0], &frame)?;
+
+    Ok(None)
+}
+
+/// Starts a stack walk: fills the first batch of frames and hands them to `doStackWalk`, which consumes all of them,
+/// fetching further batches through `fetchStackFrames`. The walker object itself is the anchor of the walk.
+fn stack_walker_call_stack_walk(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (walker, mode, skip_frames, batch_size, start_index, frames) = (args[0], args[1].as_long(), args[2].as_int(), args[3].as_int(), args[4].as_int(), args[5]);
+    if frames.is_null() {
+        return Err(Throwable::null_pointer());
+    }
+
+    let abstract_stack_walker = context.load_class(ABSTRACT_STACK_WALKER)?;
+    let remaining: Vec<TraceFrame> = context.current_stack_trace().into_iter()
+        .skip_while(|frame| context.is_stack_walker_frame(frame.class, abstract_stack_walker))
+        .skip(skip_frames.max(0) as usize)
+        .collect();
+
+    let anchor = walker.as_reference();
+    context.stack_walks.insert(anchor, remaining);
+
+    let result = context.fill_stack_frames(anchor, mode, batch_size, start_index, frames).and_then(|end_index| {
+        let (method_class, method) = context.resolve_method(abstract_stack_walker, "doStackWalk", "(JIIII)Ljava/lang/Object;")
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchMethodError", "doStackWalk"))?;
+
+        let args = [walker, Value::from_long(anchor as i64), Value::from_int(skip_frames), Value::from_int(batch_size), Value::from_int(start_index), Value::from_int(end_index)];
+        context.invoke(method_class, method, &args)
+    });
+
+    context.stack_walks.remove(&anchor);
+
+    result
+}
+
+fn stack_walker_fetch_stack_frames(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (mode, anchor, batch_size, start_index, frames) = (args[1].as_long(), args[2].as_long(), args[3].as_int(), args[4].as_int(), args[5]);
+    if frames.is_null() {
+        return Err(Throwable::null_pointer());
+    }
+
+    let anchor = u32::try_from(anchor).ok().filter(|anchor| context.stack_walks.contains_key(anchor))
+        .ok_or_else(|| Throwable::new("java/lang/InternalError", "doStackWalk: corrupted buffers on stack"))?;
+
+    Ok(Some(Value::from_int(context.fill_stack_frames(anchor, mode, batch_size, start_index, frames)?)))
+}
+
+/// The modes `StackStreamFactory` passes to `callStackWalk` are the ones `fill_stack_frames` understands
+fn stack_stream_factory_check_stack_walk_modes(_: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    Ok(Some(Value::from_bool(true)))
+}
+
+/// Stack traces of throwables and `StackWalker`. Throwables keep their frames on the Rust side, see `VmContext::fill_in_stack_trace`.
+pub fn register_stack_trace_natives(registry: &mut NativeRegistry) {
+    registry.register(STACK_TRACE_ELEMENT, "initStackTraceElements", "([Ljava/lang/StackTraceElement;Ljava/lang/Throwable;)V", stack_trace_element_init_stack_trace_elements);
+    registry.register(STACK_TRACE_ELEMENT, "initStackTraceElement", "(Ljava/lang/StackTraceElement;Ljava/lang/StackFrameInfo;)V", stack_trace_element_init_stack_trace_element);
+
+    registry.register(STACK_STREAM_FACTORY, "checkStackWalkModes", "()Z", stack_stream_factory_check_stack_walk_modes);
+    registry.register(ABSTRACT_STACK_WALKER, "callStackWalk", "(JIII[Ljava/lang/Object;)Ljava/lang/Object;", stack_walker_call_stack_walk);
+    registry.register(ABSTRACT_STACK_WALKER, "fetchStackFrames", "(JJII[Ljava/lang/Object;)I", stack_walker_fetch_stack_frames);
+}
\ No newline at end of file
@@ -0,0 +1,271 @@
+#![allow(dead_code)]
+
+use crate::java;
+use crate::java::class::ConstantPoolEntry;
+use crate::java::descriptor::FieldType;
+use crate::java::heap::ArrayData;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+/// `java.lang.String.coder` of strings stored as one byte per character
+pub const LATIN1: i32 = 0;
+/// `java.lang.String.coder` of strings stored as UTF-16 code units
+pub const UTF16: i32 = 1;
+
+/// UTF-16 code units of a string in the class file's modified UTF-8 encoding,
+/// invalid sequences decode to U+FFFD
+pub fn decode_modified_utf8(bytes: &[u8]) -> Vec<u16> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    let continuation = |index: usize| bytes.get(index).filter(|byte| *byte & 0xC0 == 0x80).map(|byte| (*byte & 0x3F) as u16);
+
+    while index < bytes.len() {
+        let byte = bytes[index] as u16;
+
+        let (unit, length) = match byte {
+            0x00..=0x7F => (Some(byte), 1),
+            0xC0..=0xDF => (continuation(index + 1).map(|second| ((byte & 0x1F) << 6) | second), 2),
+            0xE0..=0xEF => (continuation(index + 1).zip(continuation(index + 2))
+                .map(|(second, third)| ((byte & 0x0F) << 12) | (second << 6) | third), 3),
+            _ => (None, 1)
+        };
+
+        match unit {
+            Some(unit) => {
+                units.push(unit);
+                index += length;
+            },
+            None => {
+                units.push(0xFFFD);
+                index += 1;
+            }
+        }
+    }
+
+    units
+}
+
+/// Shortest decimal representation of a floating point number the way `Double.toString` formats it,
+/// `scientific` is the shortest round-trip representation in Rust's `{:e}` format
+fn java_floating_point_string(scientific: &str, plain: bool) -> String {
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa)
+    };
+    let digits: String = mantissa.chars().filter(|character| *character != '.').collect();
+
+    if !plain {
+        let fraction = if digits.len() > 1 { &digits[1..] } else { "0" };
+        return format!("{}{}.{}E{}", sign, &digits[..1], fraction, exponent);
+    }
+
+    if exponent < 0 {
+        return format!("{}0.{}{}", sign, "0".repeat((-exponent - 1) as usize), digits);
+    }
+
+    let point = exponent as usize + 1;
+    if digits.len() <= point {
+        return format!("{}{}{}.0", sign, digits, "0".repeat(point - digits.len()));
+    }
+
+    format!("{}{}.{}", sign, &digits[..point], &digits[point..])
+}
+
+pub fn java_double_string(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+
+    if value.is_infinite() {
+        return if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+
+    if value == 0.0 {
+        return if value.is_sign_negative() { "-0.0" } else { "0.0" }.to_string();
+    }
+
+    java_floating_point_string(&format!("{:e}", value), (1e-3..1e7).contains(&value.abs()))
+}
+
+pub fn java_float_string(value: f32) -> String {
+    if value.is_nan() || value.is_infinite() || value == 0.0 {
+        return java_double_string(value as f64);
+    }
+
+    java_floating_point_string(&format!("{:e}", value), (1e-3..1e7).contains(&value.abs()))
+}
+
+impl VmContext {
+
+    /// Creates a `java.lang.String` object, stored as Latin-1 when possible
+    pub fn new_string(&mut self, string: &str) -> Result<Value, Throwable> {
+        let units: Vec<u16> = string.encode_utf16().collect();
+
+        self.new_string_from_utf16(&units)
+    }
+
+    pub fn new_string_from_utf16(&mut self, units: &[u16]) -> Result<Value, Throwable> {
+        // String's methods depend on COMPACT_STRINGS, which is set by its initializer
+        let string_class = self.load_class("java/lang/String")?;
+        self.initialize_class(string_class)?;
+
+        let (bytes, coder): (Vec<i8>, i32) = if units.iter().all(|unit| *unit <= 0xFF) {
+            (units.iter().map(|unit| *unit as u8 as i8).collect(), LATIN1)
+        } else {
+            // StringUTF16.isBigEndian() is false
+            (units.iter().flat_map(|unit| unit.to_le_bytes()).map(|byte| byte as i8).collect(), UTF16)
+        };
+
+        let value = self.new_array(&FieldType::Byte, bytes.len() as i32)?;
+        if let Some(array) = self.object_mut(value)?.array_mut() {
+            *array = ArrayData::Byte(bytes);
+        }
+
+        let string = self.new_object(string_class);
+        self.set_field(string, "java/lang/String", "value", value)?;
+        self.set_field(string, "java/lang/String", "coder", Value::from_int(coder))?;
+
+        Ok(string)
+    }
+
+    /// Creates a `String[]`, `None` elements are null
+    pub fn new_string_array(&mut self, strings: &[Option<String>]) -> Result<Value, Throwable> {
+        let array = self.new_array(&FieldType::Object("java/lang/String".to_string()), strings.len() as i32)?;
+
+        self.with_roots(&[array], |context| -> Result<(), Throwable> {
+            for (index, string) in strings.iter().enumerate() {
+                let element = match string {
+                    Some(string) => context.new_string(string)?,
+                    None => continue
+                };
+
+                if let Some(array) = context.object_mut(array)?.array_mut() {
+                    array.set(index, element);
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(array)
+    }
+
+    /// The canonical `java.lang.String` object with the same contents, as returned by `String.intern()`
+    pub fn intern_string(&mut self, string: Value) -> Result<Value, Throwable> {
+        let units = self.string_units(string)?;
+
+        match self.interned_strings.get(&units) {
+            Some(reference) => Ok(Value::Reference(*reference)),
+            None => {
+                self.interned_strings.insert(units, string.as_reference());
+                Ok(string)
+            }
+        }
+    }
+
+    /// Interned string of a CONSTANT_String entry, `None` for other entries
+    pub fn constant_pool_string(&mut self, class: &java::Class, index: u16) -> Result<Option<Value>, Throwable> {
+        let constant_pool = &class.class_file.constant_pool;
+
+        let string_index = match constant_pool.get((index as usize).wrapping_sub(1)) {
+            Some(ConstantPoolEntry::StringReference(string_index)) => *string_index,
+            _ => return Ok(None)
+        };
+
+        match constant_pool.get((string_index as usize).wrapping_sub(1)) {
+            Some(ConstantPoolEntry::String { length: _, string }) => self.constant_string(string).map(Some),
+            _ => Ok(None)
+        }
+    }
+
+    /// Interned string for a CONSTANT_Utf8 entry's bytes, as loaded by `ldc` and ConstantValue attributes
+    pub fn constant_string(&mut self, bytes: &[u8]) -> Result<Value, Throwable> {
+        let units = decode_modified_utf8(bytes);
+
+        if let Some(reference) = self.interned_strings.get(&units) {
+            return Ok(Value::Reference(*reference));
+        }
+
+        let string = self.new_string_from_utf16(&units)?;
+        self.interned_strings.insert(units, string.as_reference());
+
+        Ok(string)
+    }
+
+    /// UTF-16 code units of a `java.lang.String` object
+    pub fn string_units(&self, string: Value) -> Result<Vec<u16>, Throwable> {
+        let object = self.object(string)?;
+        let layout = &self.class(object.class).instance_fields;
+        let fields = object.fields().ok_or_else(|| Throwable::new("java/lang/ClassCastException", "Not a java.lang.String"))?;
+
+        let field = |name: &str| layout.iter().rposition(|slot| slot.name == name).map(|slot| fields[slot].get());
+        let (value, coder) = match (field("value"), field("coder")) {
+            (Some(value), Some(coder)) => (value, coder.as_int()),
+            _ => return Err(Throwable::new("java/lang/ClassCastException", "Not a java.lang.String"))
+        };
+
+        let bytes = match self.object(value)?.array() {
+            Some(ArrayData::Byte(bytes)) => bytes,
+            _ => return Err(Throwable::new("java/lang/InternalError", "Malformed java.lang.String"))
+        };
+
+        if coder == LATIN1 {
+            return Ok(bytes.iter().map(|byte| *byte as u8 as u16).collect());
+        }
+
+        Ok(bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0] as u8, pair[1] as u8])).collect())
+    }
+
+    /// Contents of a `java.lang.String` object, unpaired surrogates are replaced by U+FFFD
+    pub fn rust_string(&self, string: Value) -> Result<String, Throwable> {
+        Ok(String::from_utf16_lossy(&self.string_units(string)?))
+    }
+
+    /// Contents of a `char[]`
+    pub fn rust_string_from_chars(&self, chars: Value) -> Result<String, Throwable> {
+        match self.object(chars)?.array() {
+            Some(ArrayData::Char(units)) => Ok(String::from_utf16_lossy(units)),
+            _ => Err(Throwable::new("java/lang/ClassCastException", "Not a char[]"))
+        }
+    }
+
+    /// UTF-16 code units of `String.valueOf` of a value of the given type, e.g. to append it to a string
+    pub fn java_string_of(&mut self, field_type: &FieldType, value: Value) -> Result<Vec<u16>, Throwable> {
+        let text = match field_type {
+            FieldType::Boolean  => (value.as_int() != 0).to_string(),
+            FieldType::Char     => return Ok(vec![value.as_int() as u16]),
+            FieldType::Byte | FieldType::Short | FieldType::Int => value.as_int().to_string(),
+            FieldType::Long     => value.as_long().to_string(),
+            FieldType::Float    => java_float_string(value.as_float()),
+            FieldType::Double   => java_double_string(value.as_double()),
+            _                   => return self.string_value_of(value)
+        };
+
+        Ok(text.encode_utf16().collect())
+    }
+
+    /// UTF-16 code units of `String.valueOf(Object)`
+    pub fn string_value_of(&mut self, object: Value) -> Result<Vec<u16>, Throwable> {
+        if object.is_null() {
+            return Ok("null".encode_utf16().collect());
+        }
+
+        let string_class = self.load_class("java/lang/String")?;
+        if self.is_instance_of(object, string_class)? {
+            return self.string_units(object);
+        }
+
+        let object_class = self.object(object)?.class;
+        let (class_id, method) = self.select_method(object_class, "toString", "()Ljava/lang/String;")
+            .ok_or_else(|| Throwable::new("java/lang/AbstractMethodError", "toString"))?;
+
+        match self.invoke(class_id, method, &[object])? {
+            Some(string) if !string.is_null() => self.string_units(string),
+            _ => Ok("null".encode_utf16().collect())
+        }
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,368 @@
+#![allow(dead_code)]
+
+use std::io::Read;
+
+use crate::java::descriptor::FieldType;
+use crate::java::heap::ArrayData;
+use crate::java::native::NativeRegistry;
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+/// File descriptors of the standard streams, see `java.io.FileDescriptor`
+const STDIN: i32 = 0;
+const STDOUT: i32 = 1;
+const STDERR: i32 = 2;
+
+/// Properties of the platform, the ones `jdk.internal.util.SystemProps.Raw` has an index for.
+/// Unlike the ones of the VM, they can't be overridden with `-D` before `SystemProps` decides which ones it keeps.
+pub fn platform_properties() -> Vec<(&'static str, String)> {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "?".to_string());
+    let user = std::env::var("USER").unwrap_or_else(|_| "?".to_string());
+    let directory = std::env::current_dir().map(|directory| directory.to_string_lossy().into_owned()).unwrap_or_else(|_| "?".to_string());
+
+    // The kernel release, like `uname -r`
+    let os_version = std::fs::read_to_string("/proc/sys/kernel/osrelease").map(|release| release.trim().to_string()).unwrap_or_else(|_| "unknown".to_string());
+
+    // Java names x86-64 like Linux distributions used to
+    let os_arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "x86" => "i386",
+        arch => arch
+    };
+
+    let little_endian = cfg!(target_endian = "little");
+
+    vec![
+        ("display.language", "en".to_string()),
+        ("display.country", "US".to_string()),
+        ("format.language", "en".to_string()),
+        ("format.country", "US".to_string()),
+        ("file.encoding", "UTF-8".to_string()),
+        ("file.separator", "/".to_string()),
+        ("java.io.tmpdir", "/tmp".to_string()),
+        ("line.separator", "\n".to_string()),
+        ("os.arch", os_arch.to_string()),
+        ("os.name", "Linux".to_string()),
+        ("os.version", os_version),
+        ("path.separator", ":".to_string()),
+        ("sun.arch.data.model", (std::mem::size_of::<usize>() * 8).to_string()),
+        ("sun.cpu.endian", if little_endian { "little" } else { "big" }.to_string()),
+        ("sun.io.unicode.encoding", if little_endian { "UnicodeLittle" } else { "UnicodeBig" }.to_string()),
+        ("sun.jnu.encoding", "UTF-8".to_string()),
+        ("user.dir", directory),
+        ("user.home", home),
+        ("user.name", user)
+    ]
+}
+
+impl VmContext {
+
+    /// Properties of the VM, followed by the ones set with `-D` or by embedders, which take precedence
+    pub fn vm_properties(&self) -> Vec<(String, String)> {
+        let class_path: Vec<&str> = self.class_path.iter().map(|jar| jar.name.as_str()).collect();
+
+        // The directory of java.base.jar stands in for the JDK installation
+        let java_home = self.library_jars.first()
+            .and_then(|jar| std::path::Path::new(&jar.name).canonicalize().ok())
+            .and_then(|path| path.parent().map(|directory| directory.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| ".".to_string());
+
+        let mut properties: Vec<(String, String)> = [
+            ("java.home", java_home),
+            ("java.class.path", class_path.join(":")),
+            ("java.library.path", String::new()),
+            ("sun.boot.library.path", String::new()),
+            ("java.vm.specification.name", "Java Virtual Machine Specification".to_string()),
+            ("java.vm.specification.vendor", "Oracle Corporation".to_string()),
+            ("java.vm.specification.version", "17".to_string()),
+            ("java.vm.name", env!("CARGO_PKG_NAME").to_string()),
+            ("java.vm.vendor", env!("CARGO_PKG_NAME").to_string()),
+            ("java.vm.version", env!("CARGO_PKG_VERSION").to_string()),
+            ("java.vm.info", "interpreted mode".to_string()),
+            ("jdk.debug", "release".to_string()),
+            ("sun.java.launcher", "SUN_STANDARD".to_string())
+        ].into_iter()
+            .filter(|(name, _)| !self.system_properties.contains_key(*name))
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+
+        let mut overridden: Vec<(String, String)> = self.system_properties.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+        overridden.sort();
+        properties.extend(overridden);
+
+        properties
+    }
+
+    /// Sets up what `System.initPhase1` would for the properties and `System.in` when the VM wasn't booted, right after `System` was initialized.
+    /// `System.out` and `System.err` stay console streams.
+    pub fn initialize_system(&mut self, system_class: ClassId) -> Result<(), Throwable> {
+        let props = self.class(system_class).static_values.get("props").copied().unwrap_or(Value::null());
+        if props.is_null() {
+            // Charset encoders depend on the shared secrets of java.lang
+            self.invoke_static(system_class, "setJavaLangAccess", "()V", &[])?;
+
+            let system_props = self.load_class("jdk/internal/util/SystemProps")?;
+            let properties = self.invoke_static(system_props, "initProperties", "()Ljava/util/Map;", &[])?.unwrap_or(Value::null());
+
+            self.with_roots(&[properties], |context| -> Result<(), Throwable> {
+                let version_props = context.load_class("java/lang/VersionProps")?;
+                context.invoke_static(version_props, "init", "(Ljava/util/Map;)V", &[properties])?;
+
+                let vm = context.load_class("jdk/internal/misc/VM")?;
+                context.invoke_static(vm, "saveProperties", "(Ljava/util/Map;)V", &[properties])?;
+
+                let props = context.invoke_static(system_class, "createProperties", "(Ljava/util/Map;)Ljava/util/Properties;", &[properties])?.unwrap_or(Value::null());
+                context.classes[system_class as usize].static_values.insert("props".to_string(), props);
+
+                let line_separator = context.new_string("\n")?;
+                context.classes[system_class as usize].static_values.insert("lineSeparator".to_string(), line_separator);
+
+                Ok(())
+            })?;
+        }
+
+        let stdin = self.class(system_class).static_values.get("in").copied().unwrap_or(Value::null());
+        if stdin.is_null() {
+            let file_descriptor = self.load_class("java/io/FileDescriptor")?;
+            self.initialize_class(file_descriptor)?;
+
+            let descriptor = self.class(file_descriptor).static_values.get("in").copied().unwrap_or(Value::null());
+            let input = self.construct("java/io/FileInputStream", "(Ljava/io/FileDescriptor;)V", &[descriptor])?;
+            let buffered = self.with_roots(&[input], |context| context.construct("java/io/BufferedInputStream", "(Ljava/io/InputStream;)V", &[input]))?;
+
+            self.classes[system_class as usize].static_values.insert("in".to_string(), buffered);
+        }
+
+        self.install_console_streams(system_class)
+    }
+
+    /// The `fd` of the `FileDescriptor` of a `FileInputStream` or `FileOutputStream`
+    fn stream_file_descriptor(&mut self, stream: Value, class_name: &str) -> Result<i32, Throwable> {
+        let descriptor = self.get_field(stream, class_name, "fd")?;
+        if descriptor.is_null() {
+            return Err(Throwable::new("java/io/IOException", "Stream Closed"));
+        }
+
+        Ok(self.get_field(descriptor, "java/io/FileDescriptor", "fd")?.as_int())
+    }
+
+    /// Bytes of a `byte[]` in the range `offset..offset + length`
+    pub fn byte_array_range(&self, array: Value, offset: i32, length: i32) -> Result<Vec<u8>, Throwable> {
+        let bytes = match self.object(array)?.array() {
+            Some(ArrayData::Byte(bytes)) => bytes,
+            _ => return Err(Throwable::null_pointer())
+        };
+
+        if offset < 0 || length < 0 || offset as usize + length as usize > bytes.len() {
+            return Err(Throwable::without_message("java/lang/IndexOutOfBoundsException"));
+        }
+
+        Ok(bytes[offset as usize..(offset + length) as usize].iter().map(|byte| *byte as u8).collect())
+    }
+
+    pub fn new_byte_array(&mut self, bytes: &[u8]) -> Result<Value, Throwable> {
+        let array = self.new_array(&FieldType::Byte, bytes.len() as i32)?;
+        if let Some(array) = self.object_mut(array)?.array_mut() {
+            *array = ArrayData::Byte(bytes.iter().map(|byte| *byte as i8).collect());
+        }
+
+        Ok(array)
+    }
+
+    /// Writes to the file descriptor of a `FileOutputStream`, the standard streams go to the console
+    fn write_file_descriptor(&mut self, descriptor: i32, bytes: &[u8]) -> Result<(), Throwable> {
+        match descriptor {
+            STDOUT => self.console.out.write(bytes),
+            STDERR => self.console.err.write(bytes),
+            // Only the standard streams are open, files can't be opened yet
+            _ => return Err(Throwable::new("java/io/IOException", "Stream Closed"))
+        }
+
+        Ok(())
+    }
+
+    /// Reads from the file descriptor of a `FileInputStream` into `buffer`, returns the number of bytes read, `0` at the end
+    fn read_file_descriptor(&mut self, descriptor: i32, buffer: &mut [u8]) -> Result<usize, Throwable> {
+        if descriptor != STDIN {
+            return Err(Throwable::new("java/io/IOException", "Stream Closed"));
+        }
+
+        std::io::stdin().read(buffer).map_err(|error| Throwable::new("java/io/IOException", &error.to_string()))
+    }
+
+}
+
+fn system_set_stream(context: &mut VmContext, field: &str, stream: Value) -> Result<Option<Value>, Throwable> {
+    let system_class = context.load_class("java/lang/System")?;
+    context.classes[system_class as usize].static_values.insert(field.to_string(), stream);
+
+    Ok(None)
+}
+
+fn system_map_library_name(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    if args[0].is_null() {
+        return Err(Throwable::null_pointer());
+    }
+
+    let name = context.rust_string(args[0])?;
+
+    Ok(Some(context.new_string(&format!("lib{}.so", name))?))
+}
+
+fn raw_vm_properties(context: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    let entries: Vec<Option<String>> = context.vm_properties().into_iter()
+        .flat_map(|(name, value)| [Some(name), Some(value)])
+        .collect();
+
+    Ok(Some(context.new_string_array(&entries)?))
+}
+
+/// The platform properties at the indices given by the `_<name>_NDX` constants of `SystemProps.Raw`, e.g. `_os_name_NDX`
+fn raw_platform_properties(context: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    let raw_class = context.load_class("jdk/internal/util/SystemProps$Raw")?;
+    let static_values = &context.class(raw_class).static_values;
+
+    let length = static_values.get("FIXED_LENGTH").map(Value::as_int).unwrap_or(0).max(0) as usize;
+    let mut entries = vec![None; length];
+
+    for (name, value) in platform_properties() {
+        let index = static_values.get(&format!("_{}_NDX", name.replace('.', "_"))).map(Value::as_int);
+
+        if let Some(entry) = index.and_then(|index| usize::try_from(index).ok()).and_then(|index| entries.get_mut(index)) {
+            *entry = Some(value);
+        }
+    }
+
+    Ok(Some(context.new_string_array(&entries)?))
+}
+
+/// Names and values of the environment variables, alternating, for `System.getenv`
+fn process_environment_environ(context: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    let variables: Vec<(String, String)> = std::env::vars_os()
+        .map(|(name, value)| (name.to_string_lossy().into_owned(), value.to_string_lossy().into_owned()))
+        .collect();
+
+    let array = context.new_array(&FieldType::Array(Box::new(FieldType::Byte)), (variables.len() * 2) as i32)?;
+
+    context.with_roots(&[array], |context| -> Result<(), Throwable> {
+        for (index, bytes) in variables.iter().flat_map(|(name, value)| [name.as_bytes(), value.as_bytes()]).enumerate() {
+            let element = context.new_byte_array(bytes)?;
+            if let Some(array) = context.object_mut(array)?.array_mut() {
+                array.set(index, element);
+            }
+        }
+
+        Ok(())
+    })?;
+
+    Ok(Some(array))
+}
+
+fn file_output_stream_write_bytes(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let descriptor = context.stream_file_descriptor(args[0], "java/io/FileOutputStream")?;
+    let bytes = context.byte_array_range(args[1], args[2].as_int(), args[3].as_int())?;
+    context.write_file_descriptor(descriptor, &bytes)?;
+
+    Ok(None)
+}
+
+fn file_output_stream_write(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let descriptor = context.stream_file_descriptor(args[0], "java/io/FileOutputStream")?;
+    context.write_file_descriptor(descriptor, &[args[1].as_int() as u8])?;
+
+    Ok(None)
+}
+
+fn file_input_stream_read_bytes(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let descriptor = context.stream_file_descriptor(args[0], "java/io/FileInputStream")?;
+    let (offset, length) = (args[2].as_int(), args[3].as_int());
+
+    // Checks the range
+    context.byte_array_range(args[1], offset, length)?;
+    if length == 0 {
+        return Ok(Some(Value::from_int(0)));
+    }
+
+    let mut buffer = vec![0; length as usize];
+    let read = context.read_file_descriptor(descriptor, &mut buffer)?;
+    if read == 0 {
+        return Ok(Some(Value::from_int(-1)));
+    }
+
+    if let Some(ArrayData::Byte(bytes)) = context.object_mut(args[1])?.array_mut() {
+        for (index, byte) in buffer[..read].iter().enumerate() {
+            bytes[offset as usize + index] = *byte as i8;
+        }
+    }
+
+    Ok(Some(Value::from_int(read as i32)))
+}
+
+fn file_input_stream_read(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let descriptor = context.stream_file_descriptor(args[0], "java/io/FileInputStream")?;
+
+    let mut buffer = [0];
+    let byte = match context.read_file_descriptor(descriptor, &mut buffer)? {
+        0 => -1,
+        _ => buffer[0] as i32
+    };
+
+    Ok(Some(Value::from_int(byte)))
+}
+
+/// Number of a POSIX signal by its name without the `SIG` prefix, `-1` if there's no such signal
+fn signal_find_signal(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let name = context.rust_string(args[0])?;
+
+    let number = match name.as_str() {
+        "HUP" => 1,
+        "INT" => 2,
+        "QUIT" => 3,
+        "ABRT" => 6,
+        "KILL" => 9,
+        "USR1" => 10,
+        "USR2" => 12,
+        "PIPE" => 13,
+        "ALRM" => 14,
+        "TERM" => 15,
+        _ => -1
+    };
+
+    Ok(Some(Value::from_int(number)))
+}
+
+pub fn register_system_natives(registry: &mut NativeRegistry) {
+    registry.register("java/lang/System", "setIn0", "(Ljava/io/InputStream;)V", |context, args| system_set_stream(context, "in", args[0]));
+    registry.register("java/lang/System", "setOut0", "(Ljava/io/PrintStream;)V", |context, args| system_set_stream(context, "out", args[0]));
+    registry.register("java/lang/System", "setErr0", "(Ljava/io/PrintStream;)V", |context, args| system_set_stream(context, "err", args[0]));
+    registry.register("java/lang/System", "mapLibraryName", "(Ljava/lang/String;)Ljava/lang/String;", system_map_library_name);
+
+    registry.register("jdk/internal/util/SystemProps$Raw", "vmProperties", "()[Ljava/lang/String;", raw_vm_properties);
+    registry.register("jdk/internal/util/SystemProps$Raw", "platformProperties", "()[Ljava/lang/String;", raw_platform_properties);
+    registry.register("jdk/internal/misc/VM", "getRuntimeArguments", "()[Ljava/lang/String;", |context, _| Ok(Some(context.new_string_array(&[])?)));
+    registry.register_host("java/lang/ProcessEnvironment", "environ", "()[[B", process_environment_environ);
+
+    // Signals of the OS aren't delivered to Java code, handlers are accepted but never run
+    registry.register("jdk/internal/misc/Signal", "findSignal0", "(Ljava/lang/String;)I", signal_find_signal);
+    registry.register("jdk/internal/misc/Signal", "handle0", "(IJ)J", |_, _| Ok(Some(Value::from_long(0))));
+    registry.register("jdk/internal/misc/Signal", "raise0", "(I)V", |_, _| Ok(None));
+
+    // Descriptors aren't backed by handles or flags of the OS, only the standard streams are open
+    registry.register("java/io/FileDescriptor", "initIDs", "()V", |_, _| Ok(None));
+    registry.register("java/io/FileDescriptor", "getHandle", "(I)J", |_, _| Ok(Some(Value::from_long(-1))));
+    registry.register("java/io/FileDescriptor", "getAppend", "(I)Z", |_, _| Ok(Some(Value::from_bool(false))));
+    registry.register("java/io/FileDescriptor", "sync0", "()V", |_, _| Ok(None));
+    registry.register("java/io/FileDescriptor", "close0", "()V", |context, args| {
+        context.set_field(args[0], "java/io/FileDescriptor", "fd", Value::from_int(-1))?;
+        Ok(None)
+    });
+
+    registry.register("java/io/FileOutputStream", "initIDs", "()V", |_, _| Ok(None));
+    registry.register("java/io/FileOutputStream", "writeBytes", "([BIIZ)V", file_output_stream_write_bytes);
+    registry.register("java/io/FileOutputStream", "write", "(IZ)V", file_output_stream_write);
+
+    registry.register("java/io/FileInputStream", "initIDs", "()V", |_, _| Ok(None));
+    registry.register("java/io/FileInputStream", "readBytes", "([BII)I", file_input_stream_read_bytes);
+    registry.register("java/io/FileInputStream", "read0", "()I", file_input_stream_read);
+    registry.register("java/io/FileInputStream", "available0", "()I", |_, _| Ok(Some(Value::from_int(0))));
+}
\ No newline at end of file
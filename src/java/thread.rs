@@ -0,0 +1,764 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::java::native::NativeRegistry;
+use crate::java::vm::{Executor, Halt, Throwable, TraceFrame, Value, VmContext, DEFAULT_STACK_DEPTH};
+
+/// Index of a thread in `Scheduler::threads`, the main thread is `0`
+pub type ThreadId = usize;
+
+/// Instructions a thread runs before the next one gets its turn
+pub const DEFAULT_QUANTUM: u32 = 10_000;
+
+// Bits of `java.lang.Thread.threadStatus`, see `jdk.internal.misc.VM.toThreadState`
+const STATUS_ALIVE: i32                     = 0x0001;
+const STATUS_TERMINATED: i32                = 0x0002;
+const STATUS_RUNNABLE: i32                  = 0x0004;
+const STATUS_WAITING_INDEFINITELY: i32      = 0x0010;
+const STATUS_WAITING_WITH_TIMEOUT: i32      = 0x0020;
+const STATUS_SLEEPING: i32                  = 0x0040;
+const STATUS_IN_OBJECT_WAIT: i32            = 0x0100;
+const STATUS_PARKED: i32                    = 0x0200;
+const STATUS_BLOCKED_ON_MONITOR_ENTER: i32  = 0x0400;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThreadState {
+    Runnable,
+    /// Waiting to enter the monitor of an object
+    Blocked { monitor: u32 },
+    /// In `Object.wait`, the monitor is entered `count` times again once the thread was notified or the wait timed out
+    Waiting { monitor: u32, count: u32, deadline: Option<Instant>, notified: bool },
+    Sleeping { deadline: Instant },
+    /// In `Unsafe.park` until the thread gets a permit
+    Parked { deadline: Option<Instant> },
+    Terminated
+}
+
+/// The thread owning a monitor and how often it entered it
+#[derive(Debug, Clone, Copy)]
+pub struct Monitor {
+    pub owner: ThreadId,
+    pub count: u32
+}
+
+pub struct JavaThread {
+    /// Frames of the thread while another one runs, the running thread's frames are in `VmContext::executor`
+    pub executor: Executor,
+    pub state: ThreadState,
+
+    /// Whether the thread had to wait inside Rust code, e.g. a class initializer, it continues once everything
+    /// that ran on top of it on the Rust stack is done
+    pub suspended: bool,
+
+    /// Set by `Unsafe.unpark`, consumed by `Unsafe.park`
+    pub permit: bool,
+    /// Raised in the thread once it runs again, e.g. the `InterruptedException` of an interrupted `sleep`
+    pub pending_exception: Option<Throwable>
+}
+
+/// Green threads: all Java threads run on the current OS thread, taking turns after a number of instructions
+/// or when the running thread has to wait. Apart from sleeps and timeouts the order is deterministic.
+pub struct Scheduler {
+    pub threads: Vec<JavaThread>,
+    pub current: ThreadId,
+
+    /// Entered monitors by object
+    pub monitors: HashMap<u32, Monitor>,
+
+    pub quantum: u32,
+    /// Frames the stack of threads started from now on holds
+    pub max_stack_depth: usize,
+    /// Instructions left until the running thread has to let others run, `0` makes it switch right away
+    pub remaining: u32
+}
+
+impl JavaThread {
+
+    pub fn new(executor: Executor) -> Self {
+        JavaThread {
+            executor,
+            state: ThreadState::Runnable,
+
+            suspended: false,
+
+            permit: false,
+            pending_exception: None
+        }
+    }
+
+}
+
+impl Scheduler {
+
+    pub fn new() -> Self {
+        Scheduler {
+            threads: vec![JavaThread::new(Executor::new())],
+            current: 0,
+
+            monitors: HashMap::new(),
+
+            quantum: DEFAULT_QUANTUM,
+            max_stack_depth: DEFAULT_STACK_DEPTH,
+            remaining: DEFAULT_QUANTUM
+        }
+    }
+
+    /// Whether `thread` could enter the monitor of `object` right now
+    fn monitor_available(&self, object: u32, thread: ThreadId) -> bool {
+        self.monitors.get(&object).is_none_or(|monitor| monitor.owner == thread)
+    }
+
+    /// References of the entered monitors and the exceptions waiting to be raised
+    pub fn references(&self) -> impl Iterator<Item = u32> + '_ {
+        let exceptions = self.threads.iter().filter_map(|thread| match thread.pending_exception {
+            Some(Throwable::Object(reference)) => Some(reference),
+            _ => None
+        });
+
+        self.monitors.keys().copied().chain(exceptions)
+    }
+
+}
+
+impl Default for Scheduler {
+
+    fn default() -> Self {
+        Self::new()
+    }
+
+}
+
+/// `Thread.threadStatus` of a thread in the given state
+fn thread_status(state: ThreadState) -> i32 {
+    match state {
+        ThreadState::Runnable                               => STATUS_ALIVE | STATUS_RUNNABLE,
+        ThreadState::Blocked { .. }                         => STATUS_ALIVE | STATUS_BLOCKED_ON_MONITOR_ENTER,
+        ThreadState::Waiting { deadline: None, .. }         => STATUS_ALIVE | STATUS_IN_OBJECT_WAIT | STATUS_WAITING_INDEFINITELY,
+        ThreadState::Waiting { .. }                         => STATUS_ALIVE | STATUS_IN_OBJECT_WAIT | STATUS_WAITING_WITH_TIMEOUT,
+        ThreadState::Sleeping { .. }                        => STATUS_ALIVE | STATUS_SLEEPING | STATUS_WAITING_WITH_TIMEOUT,
+        ThreadState::Parked { deadline: None }              => STATUS_ALIVE | STATUS_PARKED | STATUS_WAITING_INDEFINITELY,
+        ThreadState::Parked { .. }                          => STATUS_ALIVE | STATUS_PARKED | STATUS_WAITING_WITH_TIMEOUT,
+        ThreadState::Terminated                             => STATUS_TERMINATED
+    }
+}
+
+fn deadline_after(duration: Duration) -> Instant {
+    let now = Instant::now();
+
+    // Timeouts like Long.MAX_VALUE milliseconds don't fit into an Instant
+    now.checked_add(duration).unwrap_or(now + Duration::from_secs(u32::MAX as u64))
+}
+
+impl VmContext {
+
+    /// Frames of a thread, which are in `executor` while it runs
+    fn thread_executor(&self, thread: ThreadId) -> &Executor {
+        if thread == self.scheduler.current {
+            &self.executor
+        } else {
+            &self.scheduler.threads[thread].executor
+        }
+    }
+
+    /// All thread's frames, also used as garbage collection roots
+    pub fn executors(&self) -> impl Iterator<Item = &Executor> {
+        (0..self.scheduler.threads.len()).map(|thread| self.thread_executor(thread))
+    }
+
+    /// The thread a `java.lang.Thread` object stands for, if it was started and hasn't terminated
+    fn thread_of(&mut self, thread_object: Value) -> Result<Option<ThreadId>, Throwable> {
+        let eetop = self.get_field(thread_object, "java/lang/Thread", "eetop")?.as_long();
+
+        Ok(if eetop > 0 { Some(eetop as ThreadId - 1) } else { None })
+    }
+
+    fn thread_name(&mut self, thread: ThreadId) -> String {
+        let thread_object = Value::Reference(self.thread_executor(thread).thread_object);
+        if thread_object.is_null() {
+            return "main".to_string();
+        }
+
+        self.get_field(thread_object, "java/lang/Thread", "name")
+            .and_then(|name| self.rust_string(name))
+            .unwrap_or_else(|_| format!("Thread-{}", thread))
+    }
+
+    fn set_thread_state(&mut self, thread: ThreadId, state: ThreadState) {
+        self.scheduler.threads[thread].state = state;
+
+        let thread_object = Value::Reference(self.thread_executor(thread).thread_object);
+        if !thread_object.is_null() {
+            let _ = self.set_field(thread_object, "java/lang/Thread", "threadStatus", Value::from_int(thread_status(state)));
+        }
+
+        if thread == self.scheduler.current && state != ThreadState::Runnable {
+            self.scheduler.remaining = 0;
+        }
+    }
+
+    /// The `java.lang.Thread` object of the running thread, created for the main thread on first use
+    pub fn main_thread_object(&mut self) -> Result<Value, Throwable> {
+        if self.executor.thread_object == 0 {
+            // Like HotSpot, the main thread is in the "main" group below the "system" group
+            let system_group = self.construct("java/lang/ThreadGroup", "()V", &[])?;
+            let main_name = self.new_string("main")?;
+            let main_group = self.with_roots(&[system_group, main_name], |context| context.construct("java/lang/ThreadGroup", "(Ljava/lang/ThreadGroup;Ljava/lang/String;)V", &[system_group, main_name]))?;
+
+            let thread_class = self.load_class("java/lang/Thread")?;
+            let thread = self.new_object(thread_class);
+
+            self.set_field(thread, "java/lang/Thread", "priority", Value::from_int(5))?;
+            self.set_field(thread, "java/lang/Thread", "group", main_group)?;
+            self.set_field(thread, "java/lang/Thread", "name", main_name)?;
+            self.executor.thread_object = thread.as_reference();
+            self.attach_thread_object(self.scheduler.current, thread)?;
+        }
+
+        Ok(Value::Reference(self.executor.thread_object))
+    }
+
+    /// Connects a `java.lang.Thread` object to a thread, like HotSpot does with `eetop`
+    pub fn attach_thread_object(&mut self, thread: ThreadId, thread_object: Value) -> Result<(), Throwable> {
+        self.set_field(thread_object, "java/lang/Thread", "eetop", Value::from_long(thread as i64 + 1))?;
+        self.set_field(thread_object, "java/lang/Thread", "threadStatus", Value::from_int(thread_status(self.scheduler.threads[thread].state)))
+    }
+
+    /// Starts a thread running `Thread.run()` of a thread object
+    pub fn start_thread(&mut self, thread_object: Value) -> Result<(), Throwable> {
+        self.check_thread_limit()?;
+
+        let class_id = self.object(thread_object)?.class;
+        let (run_class, run_method) = self.select_method(class_id, "run", "()V")
+            .ok_or_else(|| Throwable::new("java/lang/AbstractMethodError", "run"))?;
+
+        let mut executor = Executor::new();
+        executor.max_stack_depth = self.scheduler.max_stack_depth;
+        executor.thread_object = thread_object.as_reference();
+        executor.scheduled = true;
+        executor.frames.push(self.new_frame(run_class, run_method, &[thread_object])?);
+
+        self.scheduler.threads.push(JavaThread::new(executor));
+        self.attach_thread_object(self.scheduler.threads.len() - 1, thread_object)
+    }
+
+    /// Ends a thread whose frames all returned, waking up threads joining it
+    fn finish_thread(&mut self, thread: ThreadId) {
+        self.set_thread_state(thread, ThreadState::Terminated);
+        self.scheduler.monitors.retain(|_, monitor| monitor.owner != thread);
+
+        let thread_object = Value::Reference(self.thread_executor(thread).thread_object);
+        if !thread_object.is_null() {
+            let _ = self.set_field(thread_object, "java/lang/Thread", "eetop", Value::from_long(0));
+            self.notify_waiters(thread_object.as_reference(), true);
+        }
+    }
+
+    pub fn report_uncaught_exception(&mut self, throwable: &Throwable) {
+        let name = self.thread_name(self.scheduler.current);
+        let message = format!("Exception in thread \"{}\" {}", name, self.format_stack_trace(throwable));
+
+        self.console.err.write(message.as_bytes());
+    }
+
+    /// Whether a thread could continue running
+    fn thread_ready(&self, thread: ThreadId) -> bool {
+        let now = Instant::now();
+        let passed = |deadline: Option<Instant>| deadline.is_some_and(|deadline| now >= deadline);
+
+        match self.scheduler.threads[thread].state {
+            ThreadState::Runnable => true,
+            ThreadState::Blocked { monitor } => self.scheduler.monitor_available(monitor, thread),
+            ThreadState::Waiting { monitor, deadline, notified, .. } => (notified || passed(deadline)) && self.scheduler.monitor_available(monitor, thread),
+            ThreadState::Sleeping { deadline } => now >= deadline,
+            ThreadState::Parked { deadline } => self.scheduler.threads[thread].permit || passed(deadline),
+            ThreadState::Terminated => false
+        }
+    }
+
+    /// Makes the running thread runnable again after it was ready, entering the monitor it waited for
+    fn resume_current_thread(&mut self) {
+        let current = self.scheduler.current;
+
+        match self.scheduler.threads[current].state {
+            ThreadState::Waiting { monitor, count, .. } => {
+                self.scheduler.monitors.insert(monitor, Monitor { owner: current, count });
+            },
+            ThreadState::Parked { .. } => self.scheduler.threads[current].permit = false,
+            // Blocked threads run monitorenter or enter the monitor of their synchronized method again
+            _ => { }
+        }
+
+        if self.scheduler.threads[current].state != ThreadState::Runnable {
+            self.set_thread_state(current, ThreadState::Runnable);
+        }
+    }
+
+    fn switch_to(&mut self, thread: ThreadId) {
+        let current = self.scheduler.current;
+
+        std::mem::swap(&mut self.executor, &mut self.scheduler.threads[current].executor);
+        std::mem::swap(&mut self.executor, &mut self.scheduler.threads[thread].executor);
+        self.scheduler.current = thread;
+    }
+
+    /// Runs a thread until it has to wait, its time slice ran out or it's done
+    fn run_thread(&mut self, thread: ThreadId) -> Result<(), Throwable> {
+        let previous = self.scheduler.current;
+        self.switch_to(thread);
+        self.resume_current_thread();
+
+        // A pending exception is raised by the interpreter at the first switch point
+        self.scheduler.remaining = if self.scheduler.threads[thread].pending_exception.is_some() { 0 } else { self.scheduler.quantum };
+
+        let result = self.execute_byte_code(0);
+        let result = match result {
+            Err(Throwable::Halt(halt)) => {
+                self.finish_thread(thread);
+                Err(Throwable::Halt(halt))
+            },
+            result if self.executor.frames.is_empty() => {
+                if let Err(throwable) = result {
+                    self.report_uncaught_exception(&throwable);
+                }
+
+                self.finish_thread(thread);
+                Ok(())
+            },
+            _ => Ok(())
+        };
+
+        self.switch_to(previous);
+        result
+    }
+
+    /// Called by the interpreter when the time slice of the running thread ran out or it has to wait.
+    /// Returns whether it continues, otherwise it has to return to the scheduler that resumed it.
+    pub fn switch_threads(&mut self) -> Result<bool, Throwable> {
+        let current = self.scheduler.current;
+
+        if let Some(throwable) = self.scheduler.threads[current].pending_exception.take() {
+            self.scheduler.remaining = self.scheduler.quantum;
+            return Err(throwable);
+        }
+
+        // Class initializers aren't preempted, other threads would see partially initialized classes
+        let preemptible = self.scheduler.threads.len() > 1 && self.executor.initializing == 0;
+        if self.thread_ready(current) && !preemptible {
+            self.resume_current_thread();
+            self.scheduler.remaining = self.scheduler.quantum;
+            return Ok(true);
+        }
+
+        if self.executor.scheduled && self.executor.activations == 1 {
+            return Ok(false);
+        }
+
+        self.yield_thread()?;
+
+        match self.scheduler.threads[current].pending_exception.take() {
+            Some(throwable) => Err(throwable),
+            None => Ok(true)
+        }
+    }
+
+    /// Lets the other threads run from inside Rust code, returns once the running thread can continue
+    pub fn yield_thread(&mut self) -> Result<(), Throwable> {
+        let current = self.scheduler.current;
+
+        self.scheduler.threads[current].suspended = true;
+        let result = self.run_other_threads(|context| context.thread_ready(current));
+        self.scheduler.threads[current].suspended = false;
+        result?;
+
+        self.resume_current_thread();
+        self.scheduler.remaining = self.scheduler.quantum;
+
+        Ok(())
+    }
+
+    /// Runs the threads other than the current one in turns until `done` holds
+    fn run_other_threads(&mut self, done: impl Fn(&VmContext) -> bool) -> Result<(), Throwable> {
+        let current = self.scheduler.current;
+
+        loop {
+            let mut progress = false;
+
+            let count = self.scheduler.threads.len();
+            for candidate in (1..count).map(|offset| (current + offset) % count) {
+                if self.scheduler.threads[candidate].suspended || !self.thread_ready(candidate) {
+                    continue;
+                }
+
+                self.run_thread(candidate)?;
+                progress = true;
+            }
+
+            if done(self) {
+                return Ok(());
+            }
+
+            if !progress {
+                match self.next_deadline() {
+                    Some(deadline) => {
+                        std::thread::sleep(self.limiter.wait_deadline(Some(deadline)).unwrap_or(deadline).saturating_duration_since(Instant::now()));
+                        self.check_timeout()?;
+                    },
+                    None => return Err(self.deadlock())
+                }
+            }
+        }
+    }
+
+    /// When the next sleep or timed wait of a thread that could run ends
+    fn next_deadline(&self) -> Option<Instant> {
+        let now = Instant::now();
+
+        self.scheduler.threads.iter().enumerate()
+            .filter(|(id, thread)| !thread.suspended || *id == self.scheduler.current)
+            .filter_map(|(_, thread)| match thread.state {
+                ThreadState::Waiting { deadline, .. } | ThreadState::Parked { deadline } => deadline,
+                ThreadState::Sleeping { deadline } => Some(deadline),
+                _ => None
+            })
+            .filter(|deadline| *deadline > now)
+            .min()
+    }
+
+    /// Waits until all non-daemon threads other than the current one terminated, the current thread is done
+    pub fn join_non_daemon_threads(&mut self) -> Result<(), Throwable> {
+        let current = self.scheduler.current;
+        self.finish_thread(current);
+
+        // Like the launcher's DestroyJavaVM thread, the current thread goes on without its Thread object, e.g. to run
+        // the shutdown hooks
+        self.executor.thread_object = 0;
+        self.set_thread_state(current, ThreadState::Runnable);
+
+        self.run_other_threads(|context| {
+            !context.scheduler.threads.iter().enumerate().any(|(id, thread)| {
+                id != current && thread.state != ThreadState::Terminated && !context.is_daemon(id)
+            })
+        })
+    }
+
+    fn is_daemon(&self, thread: ThreadId) -> bool {
+        self.daemon_flag(thread).is_some_and(|daemon| daemon.as_int() != 0)
+    }
+
+    /// `Thread.daemon` of a thread, without loading classes so it can be used on a shared reference
+    fn daemon_flag(&self, thread: ThreadId) -> Option<Value> {
+        let object = self.heap.get(self.thread_executor(thread).thread_object)?;
+        let thread_class = self.class_id("java/lang/Thread")?;
+        let slot = self.class(object.class).field_slot(thread_class, "daemon")?;
+
+        object.field(slot)
+    }
+
+    /// Writes a dump of all threads to `System.err` and stops the VM
+    fn deadlock(&mut self) -> Throwable {
+        let mut dump = String::from("Found a Java-level deadlock, no thread can make progress:\n");
+
+        for thread in 0..self.scheduler.threads.len() {
+            let state = self.scheduler.threads[thread].state;
+            if state == ThreadState::Terminated {
+                continue;
+            }
+
+            let description = match state {
+                ThreadState::Blocked { monitor } => {
+                    let owner = self.scheduler.monitors.get(&monitor).map(|monitor| monitor.owner);
+                    let owner = owner.map(|owner| format!(", held by \"{}\"", self.thread_name(owner))).unwrap_or_default();
+
+                    format!("waiting to lock {}{}", self.object_description(monitor), owner)
+                },
+                ThreadState::Waiting { monitor, .. } => format!("waiting on {}", self.object_description(monitor)),
+                ThreadState::Sleeping { .. } => "sleeping".to_string(),
+                ThreadState::Parked { .. } => "parked".to_string(),
+                _ => "runnable".to_string()
+            };
+
+            dump += &format!("\n\"{}\" #{} {}\n", self.thread_name(thread), thread, description);
+            dump += &self.stack_description(thread);
+        }
+
+        self.console.err.write(dump.as_bytes());
+
+        Throwable::Halt(Halt::Deadlock)
+    }
+
+    fn object_description(&self, object: u32) -> String {
+        match self.heap.get(object) {
+            Some(instance) => format!("<0x{:x}> (a {})", object, self.class(instance.class).java_name()),
+            None => format!("<0x{:x}>", object)
+        }
+    }
+
+    /// The frames of a thread, innermost first
+    fn stack_description(&self, thread: ThreadId) -> String {
+        self.thread_executor(thread).frames.iter().rev()
+            .map(|frame| format!("\tat {}\n", self.describe_frame(&TraceFrame::from(frame))))
+            .collect()
+    }
+
+    /// Enters the monitor of `object` on the running thread, returns false if another thread owns it
+    pub fn try_enter_monitor(&mut self, object: u32) -> bool {
+        let current = self.scheduler.current;
+
+        match self.scheduler.monitors.get_mut(&object) {
+            Some(monitor) if monitor.owner == current => {
+                monitor.count += 1;
+                true
+            },
+            Some(_) => false,
+            None => {
+                self.scheduler.monitors.insert(object, Monitor { owner: current, count: 1 });
+                true
+            }
+        }
+    }
+
+    /// Makes the running thread wait until it can enter the monitor of `object`
+    pub fn block_on_monitor(&mut self, object: u32) {
+        self.set_thread_state(self.scheduler.current, ThreadState::Blocked { monitor: object });
+    }
+
+    pub fn exit_monitor(&mut self, object: u32) -> Result<(), Throwable> {
+        let count = self.owned_monitor_count(object)?;
+
+        if count == 1 {
+            self.scheduler.monitors.remove(&object);
+        } else if let Some(monitor) = self.scheduler.monitors.get_mut(&object) {
+            monitor.count -= 1;
+        }
+
+        Ok(())
+    }
+
+    /// How often the running thread entered the monitor of `object`, it's an error if it doesn't own it
+    fn owned_monitor_count(&self, object: u32) -> Result<u32, Throwable> {
+        match self.scheduler.monitors.get(&object) {
+            Some(monitor) if monitor.owner == self.scheduler.current => Ok(monitor.count),
+            _ => Err(Throwable::new("java/lang/IllegalMonitorStateException", "current thread is not owner"))
+        }
+    }
+
+    /// Clears the interrupt status of the running thread and returns whether it was set
+    fn take_interrupt(&mut self) -> Result<bool, Throwable> {
+        let thread_object = Value::Reference(self.executor.thread_object);
+        if thread_object.is_null() {
+            return Ok(false);
+        }
+
+        let interrupted = self.get_field(thread_object, "java/lang/Thread", "interrupted")?.as_int() != 0;
+        if interrupted {
+            self.set_field(thread_object, "java/lang/Thread", "interrupted", Value::from_bool(false))?;
+        }
+
+        Ok(interrupted)
+    }
+
+    /// `Object.wait`, the running thread leaves the monitor and waits until it's notified or the timeout passed
+    pub fn wait_on_monitor(&mut self, object: u32, timeout: Option<Duration>) -> Result<(), Throwable> {
+        let count = self.owned_monitor_count(object)?;
+
+        if self.take_interrupt()? {
+            return Err(Throwable::without_message("java/lang/InterruptedException"));
+        }
+
+        self.scheduler.monitors.remove(&object);
+        self.set_thread_state(self.scheduler.current, ThreadState::Waiting {
+            monitor: object,
+            count,
+            deadline: timeout.map(deadline_after),
+            notified: false
+        });
+
+        Ok(())
+    }
+
+    /// Wakes up the first thread waiting on the monitor of `object`, or all of them
+    fn notify_waiters(&mut self, object: u32, all: bool) {
+        for thread in &mut self.scheduler.threads {
+            if let ThreadState::Waiting { monitor, notified: notified @ false, .. } = &mut thread.state {
+                if *monitor == object {
+                    *notified = true;
+
+                    if !all {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn notify_monitor(&mut self, object: u32, all: bool) -> Result<(), Throwable> {
+        self.owned_monitor_count(object)?;
+        self.notify_waiters(object, all);
+
+        Ok(())
+    }
+
+    /// `Thread.interrupt`, wakes up the thread if it's waiting, sleeping or parked
+    fn interrupt_thread(&mut self, thread: ThreadId, thread_object: Value) -> Result<(), Throwable> {
+        let wakes_up = match &mut self.scheduler.threads[thread].state {
+            ThreadState::Waiting { notified, .. } => {
+                *notified = true;
+                true
+            },
+            ThreadState::Sleeping { deadline } => {
+                *deadline = Instant::now();
+                true
+            },
+            ThreadState::Parked { .. } => {
+                self.scheduler.threads[thread].permit = true;
+                return Ok(());
+            },
+            _ => false
+        };
+
+        // The interrupt status is cleared when InterruptedException is thrown
+        if wakes_up {
+            self.set_field(thread_object, "java/lang/Thread", "interrupted", Value::from_bool(false))?;
+            self.scheduler.threads[thread].pending_exception = Some(Throwable::without_message("java/lang/InterruptedException"));
+        }
+
+        Ok(())
+    }
+
+    fn park(&mut self, absolute: bool, time: i64) -> Result<(), Throwable> {
+        let current = self.scheduler.current;
+        if std::mem::take(&mut self.scheduler.threads[current].permit) {
+            return Ok(());
+        }
+
+        let thread_object = Value::Reference(self.executor.thread_object);
+        if !thread_object.is_null() && self.get_field(thread_object, "java/lang/Thread", "interrupted")?.as_int() != 0 {
+            return Ok(());
+        }
+
+        let deadline = if absolute {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64;
+            if time <= now {
+                return Ok(());
+            }
+
+            Some(deadline_after(Duration::from_millis((time - now) as u64)))
+        } else if time > 0 {
+            Some(deadline_after(Duration::from_nanos(time as u64)))
+        } else if time == 0 {
+            None
+        } else {
+            return Ok(());
+        };
+
+        self.set_thread_state(current, ThreadState::Parked { deadline });
+
+        Ok(())
+    }
+
+}
+
+fn thread_start(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.object(args[0])?.class;
+
+    // The reference handler's work is done right after each collection instead
+    if context.class(class_id).name == "java/lang/ref/Reference$ReferenceHandler" {
+        return Ok(None);
+    }
+
+    if context.thread_of(args[0])?.is_some() {
+        return Err(Throwable::without_message("java/lang/IllegalThreadStateException"));
+    }
+
+    context.start_thread(args[0])?;
+
+    Ok(None)
+}
+
+fn thread_sleep(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let millis = args[0].as_long();
+    if millis < 0 {
+        return Err(Throwable::new("java/lang/IllegalArgumentException", "timeout value is negative"));
+    }
+
+    if context.take_interrupt()? {
+        return Err(Throwable::new("java/lang/InterruptedException", "sleep interrupted"));
+    }
+
+    let state = ThreadState::Sleeping { deadline: deadline_after(Duration::from_millis(millis as u64)) };
+    context.set_thread_state(context.scheduler.current, state);
+
+    Ok(None)
+}
+
+fn thread_yield(context: &mut VmContext, _: &[Value]) -> Result<Option<Value>, Throwable> {
+    context.scheduler.remaining = 0;
+
+    Ok(None)
+}
+
+fn thread_holds_lock(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    if args[0].is_null() {
+        return Err(Throwable::null_pointer());
+    }
+
+    Ok(Some(Value::from_bool(context.owned_monitor_count(args[0].as_reference()).is_ok())))
+}
+
+fn thread_interrupt(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    if let Some(thread) = context.thread_of(args[0])? {
+        context.interrupt_thread(thread, args[0])?;
+    }
+
+    Ok(None)
+}
+
+fn object_wait(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let timeout = args[1].as_long();
+    if timeout < 0 {
+        return Err(Throwable::new("java/lang/IllegalArgumentException", "timeout value is negative"));
+    }
+
+    let timeout = if timeout > 0 { Some(Duration::from_millis(timeout as u64)) } else { None };
+    context.wait_on_monitor(args[0].as_reference(), timeout)?;
+
+    Ok(None)
+}
+
+fn unsafe_park(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    context.park(args[1].as_int() != 0, args[2].as_long())?;
+
+    Ok(None)
+}
+
+fn unsafe_unpark(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    if args[1].is_null() {
+        return Ok(None);
+    }
+
+    if let Some(thread) = context.thread_of(args[1])? {
+        context.scheduler.threads[thread].permit = true;
+    }
+
+    Ok(None)
+}
+
+pub fn register_thread_natives(registry: &mut NativeRegistry) {
+    registry.register("java/lang/Thread", "start0", "()V", thread_start);
+    registry.register("java/lang/Thread", "sleep", "(J)V", thread_sleep);
+    registry.register("java/lang/Thread", "yield", "()V", thread_yield);
+    registry.register("java/lang/Thread", "holdsLock", "(Ljava/lang/Object;)Z", thread_holds_lock);
+    registry.register("java/lang/Thread", "interrupt0", "()V", thread_interrupt);
+    registry.register("java/lang/Thread", "clearInterruptEvent", "()V", |_, _| Ok(None));
+    registry.register("java/lang/Thread", "setPriority0", "(I)V", |_, _| Ok(None));
+    registry.register("java/lang/Thread", "setNativeName", "(Ljava/lang/String;)V", |_, _| Ok(None));
+
+    registry.register("java/lang/Object", "wait", "(J)V", object_wait);
+    registry.register("java/lang/Object", "notify", "()V", |context, args| context.notify_monitor(args[0].as_reference(), false).map(|_| None));
+    registry.register("java/lang/Object", "notifyAll", "()V", |context, args| context.notify_monitor(args[0].as_reference(), true).map(|_| None));
+
+    registry.register("jdk/internal/misc/Unsafe", "park", "(ZJ)V", unsafe_park);
+    registry.register("jdk/internal/misc/Unsafe", "unpark", "(Ljava/lang/Object;)V", unsafe_unpark);
+}
\ No newline at end of file
@@ -0,0 +1,353 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::java;
+use crate::java::disassembler::describe_instruction;
+use crate::java::instruction::Instruction;
+use crate::java::runtime_class::ClassId;
+use crate::java::vm::{Throwable, Value, VmContext};
+
+/// How trace events are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraceFormat {
+    /// A line per event meant to be read by people
+    #[default]
+    Text,
+    /// A JSON object per line, values are strings formatted like in the text format
+    JsonLines
+}
+
+/// Where trace events are written to
+#[derive(Debug, Clone, Default)]
+pub enum TraceOutput {
+    #[default]
+    Stderr,
+    /// Created or truncated when tracing is enabled
+    File(PathBuf),
+    /// In-memory buffer, e.g. to inspect the trace in tests
+    Buffer(Arc<Mutex<Vec<u8>>>)
+}
+
+/// What `VirtualMachine::set_trace` traces
+#[derive(Debug, Clone)]
+pub struct TraceOptions {
+    /// Globs matched against the Java name of the class and the method name, e.g. `Main.*` or `java.util.*List.add`.
+    /// `*` matches any number of characters and `?` a single one. Without any, every method is traced.
+    pub filters: Vec<String>,
+    /// Whether the instructions of traced methods are traced, and not just calls and returns
+    pub instructions: bool,
+    pub format: TraceFormat,
+    pub output: TraceOutput
+}
+
+impl Default for TraceOptions {
+
+    fn default() -> Self {
+        TraceOptions {
+            filters: vec![],
+            instructions: true,
+            format: TraceFormat::default(),
+            output: TraceOutput::default()
+        }
+    }
+
+}
+
+/// Writes trace events of the methods the filters select, see `TraceOptions`
+pub struct Tracer {
+    filters: Vec<String>,
+    instructions: bool,
+    format: TraceFormat,
+    writer: Box<dyn Write + Send>,
+    /// Whether a method is traced, by its class and the address of the method
+    selected: HashMap<(ClassId, usize), bool>
+}
+
+/// Writes into the buffer of `TraceOutput::Buffer`
+struct BufferWriter(Arc<Mutex<Vec<u8>>>);
+
+impl Write for BufferWriter {
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        if let Ok(mut buffer) = self.0.lock() {
+            buffer.extend_from_slice(bytes);
+        }
+
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+}
+
+/// How a traced method was left
+enum Exit<'a> {
+    Return(Option<Value>),
+    Throw(&'a Throwable)
+}
+
+/// Whether `text` matches a glob where `*` stands for any number of characters and `?` for a single one
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c)
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+fn json_array(values: &[String]) -> String {
+    let values: Vec<String> = values.iter().map(|value| json_string(value)).collect();
+    format!("[{}]", values.join(","))
+}
+
+impl Tracer {
+
+    pub fn new(options: TraceOptions) -> io::Result<Self> {
+        let writer: Box<dyn Write + Send> = match options.output {
+            TraceOutput::Stderr => Box::new(BufWriter::new(io::stderr())),
+            TraceOutput::File(path) => Box::new(BufWriter::new(File::create(path)?)),
+            TraceOutput::Buffer(buffer) => Box::new(BufferWriter(buffer))
+        };
+
+        Ok(Tracer {
+            filters: options.filters,
+            instructions: options.instructions,
+            format: options.format,
+            writer,
+            selected: HashMap::new()
+        })
+    }
+
+    fn write_line(&mut self, line: &str) {
+        // A trace that can't be written doesn't change how the program runs
+        let _ = writeln!(self.writer, "{}", line);
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+
+}
+
+impl Drop for Tracer {
+
+    fn drop(&mut self) {
+        self.flush();
+    }
+
+}
+
+impl VmContext {
+
+    /// Whether events of a method are traced, the answer is cached per method
+    fn trace_selects(&mut self, class_id: ClassId, method: &java::Method) -> bool {
+        let key = (class_id, method as *const java::Method as usize);
+        let filters = match &self.tracer {
+            Some(tracer) => match tracer.selected.get(&key) {
+                Some(selected) => return *selected,
+                None => &tracer.filters
+            },
+            None => return false
+        };
+
+        let name = format!("{}.{}", self.class(class_id).java_name(), method.name);
+        let selected = filters.is_empty() || filters.iter().any(|filter| glob_matches(filter, &name));
+
+        if let Some(tracer) = &mut self.tracer {
+            tracer.selected.insert(key, selected);
+        }
+
+        selected
+    }
+
+    /// A value as it appears in traces, e.g. `42`, `7L`, `null` or `java.lang.String@1f`
+    fn trace_value(&self, value: Value) -> String {
+        match value {
+            Value::None => "_".to_string(),
+            Value::Reference(0) => "null".to_string(),
+            Value::Reference(reference) => match self.heap.get(reference) {
+                Some(object) => format!("{}@{:x}", self.class(object.class).java_name(), reference),
+                None => format!("<invalid @{:x}>", reference)
+            },
+            Value::Integer(bits) => (bits as i32).to_string(),
+            Value::Float(bits) => format!("{:?}f", f32::from_bits(bits)),
+            Value::Long(bits) => format!("{}L", bits as i64),
+            Value::Double(bits) => format!("{:?}", f64::from_bits(bits))
+        }
+    }
+
+    fn trace_values(&self, values: &[Value]) -> Vec<String> {
+        values.iter().map(|value| self.trace_value(*value)).collect()
+    }
+
+    /// The operand stack and locals of a frame
+    fn trace_frame_state(&self, index: usize) -> (Vec<String>, Vec<String>) {
+        match self.executor.frames.get(index) {
+            Some(frame) => (self.trace_values(&frame.stack), self.trace_values(&frame.locals)),
+            None => (vec![], vec![])
+        }
+    }
+
+    fn trace_method_name(&self, class_id: ClassId, method: &java::Method) -> String {
+        format!("{}.{}{}", self.class(class_id).java_name(), method.name, method.descriptor)
+    }
+
+    /// Writes an event of the method at a frame depth, `text` follows the thread and the depth in the text format
+    /// and `json` are the fields after them
+    fn write_trace_event(&mut self, event: &str, depth: usize, text: String, json: Vec<(&str, String)>) {
+        let thread = self.scheduler.current;
+
+        if let Some(tracer) = &mut self.tracer {
+            let line = match tracer.format {
+                TraceFormat::Text => format!("T{} {:>4} {}", thread, depth, text),
+                TraceFormat::JsonLines => {
+                    let fields: Vec<String> = json.into_iter().map(|(name, value)| format!("{}:{}", json_string(name), value)).collect();
+                    format!("{{\"event\":{},\"thread\":{},\"depth\":{},{}}}", json_string(event), thread, depth, fields.join(","))
+                }
+            };
+
+            tracer.write_line(&line);
+        }
+    }
+
+    /// Traces a call of a method with its arguments, including `this`
+    pub fn trace_entry(&mut self, class_id: ClassId, method: &java::Method, args: &[Value]) {
+        if !self.trace_selects(class_id, method) {
+            return;
+        }
+
+        let name = self.trace_method_name(class_id, method);
+        let args = self.trace_values(args);
+
+        // The frame of the method isn't pushed yet, native methods don't get one
+        let depth = self.executor.frames.len() + 1;
+
+        self.write_trace_event(
+            "enter",
+            depth,
+            format!("> {}{} ({})", name, if method.is_native() { " native" } else { "" }, args.join(", ")),
+            vec![("method", json_string(&name)), ("native", method.is_native().to_string()), ("args", json_array(&args))]
+        );
+    }
+
+    fn trace_exit(&mut self, class_id: ClassId, method: &java::Method, exit: Exit) {
+        if !self.trace_selects(class_id, method) {
+            return;
+        }
+
+        let name = self.trace_method_name(class_id, method);
+        let (text, field) = match exit {
+            Exit::Return(Some(value)) => {
+                let value = self.trace_value(value);
+                (format!("< {} = {}", name, value), ("return", json_string(&value)))
+            },
+            Exit::Return(None) => (format!("< {}", name), ("return", "null".to_string())),
+            Exit::Throw(throwable) => {
+                let exception = self.describe_throwable(throwable);
+                (format!("< {} threw {}", name, exception), ("exception", json_string(&exception)))
+            }
+        };
+
+        // The frame of the method is only popped afterwards
+        let depth = self.executor.frames.len() + method.is_native() as usize;
+
+        self.write_trace_event("exit", depth, text, vec![("method", json_string(&name)), field]);
+    }
+
+    /// Traces the return of a native method or the innermost frame
+    pub fn trace_return(&mut self, class_id: ClassId, method: &java::Method, value: Option<Value>) {
+        self.trace_exit(class_id, method, Exit::Return(value));
+    }
+
+    /// Traces a method left because of an exception
+    pub fn trace_throw(&mut self, class_id: ClassId, method: &java::Method, throwable: &Throwable) {
+        self.trace_exit(class_id, method, Exit::Throw(throwable));
+    }
+
+    /// Runs an instruction of the innermost frame through `execute`, tracing it with the state of the frame before and after
+    pub fn trace_instruction<T>(&mut self, class_id: ClassId, method: &java::Method, program_counter: usize, execute: impl FnOnce(&mut Self) -> T) -> T {
+        let traced = self.tracer.as_ref().is_some_and(|tracer| tracer.instructions) && self.trace_selects(class_id, method);
+        if !traced {
+            return execute(self);
+        }
+
+        let index = self.executor.frames.len() - 1;
+        let (stack_before, locals_before) = self.trace_frame_state(index);
+
+        let result = execute(self);
+
+        // The frame is still there, even if it returned or threw, it's only popped once the interpreter handles the result
+        let (stack_after, locals_after) = self.trace_frame_state(index);
+        let name = self.trace_method_name(class_id, method);
+        let class_file = &self.class(class_id).class.as_ref().expect("Executing code of a class without a class file").class_file;
+        let text = match method.code().map(|code| Instruction::decode_at(&code.code, program_counter)) {
+            Some(Ok(instruction)) => describe_instruction(class_file, &instruction),
+            Some(Err(error)) => error.to_string(),
+            None => String::new()
+        };
+
+        self.write_trace_event(
+            "instruction",
+            index + 1,
+            format!("  {} @{} {}  stack [{}] -> [{}]  locals [{}] -> [{}]", name, program_counter, text,
+                    stack_before.join(", "), stack_after.join(", "), locals_before.join(", "), locals_after.join(", ")),
+            vec![
+                ("method", json_string(&name)),
+                ("pc", program_counter.to_string()),
+                ("instruction", json_string(&text)),
+                ("stack_before", json_array(&stack_before)),
+                ("stack_after", json_array(&stack_after)),
+                ("locals_before", json_array(&locals_before)),
+                ("locals_after", json_array(&locals_after))
+            ]
+        );
+
+        result
+    }
+
+}
\ No newline at end of file
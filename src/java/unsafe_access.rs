@@ -0,0 +1,314 @@
+#![allow(dead_code)]
+
+use crate::java::heap::ArrayData;
+use crate::java::native::NativeRegistry;
+use crate::java::runtime_class::{ClassId, ClassState};
+use crate::java::vm::{Throwable, Value, VmContext};
+
+/// `Unsafe` offsets of static fields start here, their base is the mirror of the declaring class
+/// and the rest of the offset is the index of the field in the class file
+pub const STATIC_FIELD_OFFSET: i64 = 1 << 32;
+
+/// Value kinds `Unsafe` reads and writes, named like the `get*`/`put*` natives
+const ACCESS_KINDS: [(&str, &str); 9] = [
+    ("Int", "I"),
+    ("Reference", "Ljava/lang/Object;"),
+    ("Boolean", "Z"),
+    ("Byte", "B"),
+    ("Short", "S"),
+    ("Char", "C"),
+    ("Long", "J"),
+    ("Float", "F"),
+    ("Double", "D")
+];
+
+/// Bytes a value of an access kind takes up
+fn access_width(descriptor: &str) -> usize {
+    match descriptor {
+        "Z" | "B" => 1,
+        "S" | "C" => 2,
+        "J" | "D" => 8,
+        _ => 4
+    }
+}
+
+fn bits_value(bits: u64, descriptor: &str) -> Value {
+    match descriptor {
+        "Z" | "B" => Value::from_int(bits as u8 as i8 as i32),
+        "S" => Value::from_int(bits as u16 as i16 as i32),
+        "C" => Value::from_int(bits as u16 as i32),
+        "J" => Value::Long(bits),
+        "F" => Value::Float(bits as u32),
+        "D" => Value::Double(bits),
+        _ => Value::Integer(bits as u32)
+    }
+}
+
+/// A value as an access kind reads it, e.g. the bits of a `double` field read by `getLong`
+fn with_kind(value: Value, descriptor: &str) -> Value {
+    match descriptor {
+        "Ljava/lang/Object;" => value,
+        _ => bits_value(value.bits(), descriptor)
+    }
+}
+
+impl VmContext {
+
+    /// Whether an access of `width` bytes doesn't line up with the elements of a primitive array, like `ByteBuffer.getInt` on a `byte[]`
+    fn is_mismatched_array_access(&self, object: Value, offset: i64, width: usize) -> bool {
+        if object.is_null() || !(0..STATIC_FIELD_OFFSET).contains(&offset) {
+            return false;
+        }
+
+        match self.object(object).ok().and_then(|object| object.array()) {
+            Some(ArrayData::Reference(_)) | None => false,
+            Some(array) => array.element_size() != width || !(offset as usize).is_multiple_of(width)
+        }
+    }
+
+    /// Reads like `unsafe_get`, a value of the access kind `descriptor` out of the bytes of a primitive array if it doesn't line up with its elements
+    pub fn unsafe_get_kind(&self, object: Value, offset: i64, descriptor: &str) -> Result<Value, Throwable> {
+        let width = access_width(descriptor);
+        if !self.is_mismatched_array_access(object, offset, width) {
+            return Ok(with_kind(self.unsafe_get(object, offset)?, descriptor));
+        }
+
+        self.object(object)?.array()
+            .and_then(|array| array.get_bits(offset as usize, width))
+            .map(|bits| bits_value(bits, descriptor))
+            .ok_or_else(|| Throwable::new("java/lang/InternalError", &format!("Invalid Unsafe offset {}", offset)))
+    }
+
+    /// Writes like `unsafe_put`, into the bytes of a primitive array if the access kind `descriptor` doesn't line up with its elements
+    pub fn unsafe_put_kind(&mut self, object: Value, offset: i64, value: Value, descriptor: &str) -> Result<(), Throwable> {
+        let width = access_width(descriptor);
+        if !self.is_mismatched_array_access(object, offset, width) {
+            return self.unsafe_put(object, offset, value);
+        }
+
+        let stored = self.object_mut(object)?.array_mut()
+            .is_some_and(|array| array.set_bits(offset as usize, width, value.bits()));
+
+        if stored {
+            Ok(())
+        } else {
+            Err(Throwable::new("java/lang/InternalError", &format!("Invalid Unsafe offset {}", offset)))
+        }
+    }
+
+    /// Reads the field or array element of `object` at an `Unsafe` offset.
+    /// Objects aren't laid out in memory: field offsets are slot indices and element offsets are indices scaled by the element size.
+    pub fn unsafe_get(&self, object: Value, offset: i64) -> Result<Value, Throwable> {
+        if offset >= STATIC_FIELD_OFFSET {
+            let (class_id, name) = self.unsafe_static_field(object, offset)?;
+            return Ok(self.class(class_id).static_values.get(&name).copied().unwrap_or(Value::null()));
+        }
+
+        let object = self.object(object)?;
+
+        let value = match (object.fields(), object.array()) {
+            (Some(_), _) => usize::try_from(offset).ok().and_then(|slot| object.field(slot)),
+            (_, Some(array)) => usize::try_from(offset).ok()
+                .map(|offset| offset / array.element_size())
+                .filter(|index| *index < array.len())
+                .map(|index| array.get(index)),
+            _ => None
+        };
+
+        value.ok_or_else(|| Throwable::new("java/lang/InternalError", &format!("Invalid Unsafe offset {}", offset)))
+    }
+
+    pub fn unsafe_put(&mut self, object: Value, offset: i64, value: Value) -> Result<(), Throwable> {
+        if offset >= STATIC_FIELD_OFFSET {
+            let (class_id, name) = self.unsafe_static_field(object, offset)?;
+            self.classes[class_id as usize].static_values.insert(name, value);
+            return Ok(());
+        }
+
+        let object = self.object_mut(object)?;
+
+        let stored = match object.array_mut() {
+            Some(array) => match usize::try_from(offset).ok().map(|offset| offset / array.element_size()).filter(|index| *index < array.len()) {
+                Some(index) => {
+                    array.set(index, value);
+                    true
+                },
+                None => false
+            },
+            None => usize::try_from(offset).is_ok_and(|slot| object.set_field(slot, value))
+        };
+
+        if stored {
+            Ok(())
+        } else {
+            Err(Throwable::new("java/lang/InternalError", &format!("Invalid Unsafe offset {}", offset)))
+        }
+    }
+
+    /// Class and name of the static field at an `Unsafe` offset of a class mirror
+    fn unsafe_static_field(&self, mirror: Value, offset: i64) -> Result<(ClassId, String), Throwable> {
+        let invalid_offset = || Throwable::new("java/lang/InternalError", &format!("Invalid Unsafe offset {}", offset));
+
+        let class_id = self.mirror_class(mirror).ok_or_else(invalid_offset)?;
+        let class = self.class(class_id).class.as_ref().ok_or_else(invalid_offset)?;
+
+        let name = class.class_file.field_table.get((offset - STATIC_FIELD_OFFSET) as usize)
+            .and_then(|field_info| class.class_file.get_constant_pool_string(field_info.name_index as usize))
+            .ok_or_else(invalid_offset)?;
+
+        Ok((class_id, name))
+    }
+
+    /// Replaces the value at an `Unsafe` offset if it's currently `expected`, returns the previous value.
+    /// Instance fields are replaced with the `compare_exchange` of their atomic. Array elements and static fields
+    /// aren't atomics, they're compared and replaced while the context is borrowed exclusively.
+    pub fn unsafe_compare_and_exchange(&mut self, object: Value, offset: i64, expected: Value, value: Value) -> Result<Value, Throwable> {
+        if (0..STATIC_FIELD_OFFSET).contains(&offset) {
+            if let Some(field) = self.object(object)?.fields().and_then(|fields| fields.get(offset as usize)) {
+                return Ok(field.compare_exchange(expected, value));
+            }
+        }
+
+        let current = self.unsafe_get(object, offset)?;
+
+        if same_value(current, expected) {
+            self.unsafe_put(object, offset, value)?;
+        }
+
+        Ok(current)
+    }
+
+}
+
+/// Compares like Java does for the operand of a CAS, by bits, so that e.g. `compareAndSetLong` works on a `double` field
+fn same_value(current: Value, expected: Value) -> bool {
+    match (current, expected) {
+        (Value::None, _) | (_, Value::None) => current == expected,
+        _ => current.bits() == expected.bits()
+    }
+}
+
+fn unsafe_object_field_offset(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(args[1]).ok_or_else(Throwable::null_pointer)?;
+    let name = context.rust_string(args[2])?;
+
+    let slot = context.class(class_id).field_slot(class_id, &name)
+        .ok_or_else(|| Throwable::new("java/lang/InternalError", &name))?;
+
+    Ok(Some(Value::from_long(slot as i64)))
+}
+
+/// The class and index in the class file of a `java.lang.reflect.Field`
+fn reflected_field(context: &mut VmContext, field: Value) -> Result<(ClassId, usize), Throwable> {
+    if field.is_null() {
+        return Err(Throwable::null_pointer());
+    }
+
+    let mirror = context.get_field(field, "java/lang/reflect/Field", "clazz")?;
+    let slot = context.get_field(field, "java/lang/reflect/Field", "slot")?.as_int();
+    let class_id = context.mirror_class(mirror).ok_or_else(Throwable::null_pointer)?;
+
+    Ok((class_id, slot as usize))
+}
+
+fn unsafe_object_field_offset_of_field(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (class_id, _) = reflected_field(context, args[1])?;
+    let name = context.get_field(args[1], "java/lang/reflect/Field", "name")?;
+    let name = context.rust_string(name)?;
+
+    let slot = context.class(class_id).field_slot(class_id, &name)
+        .ok_or_else(|| Throwable::new("java/lang/InternalError", &name))?;
+
+    Ok(Some(Value::from_long(slot as i64)))
+}
+
+fn unsafe_static_field_offset(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (_, slot) = reflected_field(context, args[1])?;
+
+    Ok(Some(Value::from_long(STATIC_FIELD_OFFSET + slot as i64)))
+}
+
+fn unsafe_static_field_base(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let (class_id, _) = reflected_field(context, args[1])?;
+
+    Ok(Some(context.class_mirror(class_id)?))
+}
+
+fn unsafe_allocate_instance(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(args[1]).ok_or_else(Throwable::null_pointer)?;
+
+    if context.class(class_id).is_interface() || context.class(class_id).is_abstract() || context.class(class_id).is_array() {
+        return Err(Throwable::new("java/lang/InstantiationException", &context.class(class_id).java_name()));
+    }
+
+    context.initialize_class(class_id)?;
+
+    Ok(Some(context.new_object(class_id)))
+}
+
+fn unsafe_ensure_class_initialized(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(args[1]).ok_or_else(Throwable::null_pointer)?;
+    context.initialize_class(class_id)?;
+
+    Ok(None)
+}
+
+fn unsafe_should_be_initialized(context: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    let class_id = context.mirror_class(args[1]).ok_or_else(Throwable::null_pointer)?;
+
+    Ok(Some(Value::from_bool(context.class(class_id).state != ClassState::Initialized)))
+}
+
+fn unsafe_throw_exception(_: &mut VmContext, args: &[Value]) -> Result<Option<Value>, Throwable> {
+    if args[1].is_null() {
+        return Err(Throwable::null_pointer());
+    }
+
+    Err(Throwable::Object(args[1].as_reference()))
+}
+
+pub fn register_unsafe_natives(registry: &mut NativeRegistry) {
+    const UNSAFE: &str = "jdk/internal/misc/Unsafe";
+
+    for (kind, descriptor) in ACCESS_KINDS {
+        for suffix in ["", "Volatile"] {
+            registry.register(UNSAFE, &format!("get{}{}", kind, suffix), &format!("(Ljava/lang/Object;J){}", descriptor), move |context, args| {
+                Ok(Some(context.unsafe_get_kind(args[1], args[2].as_long(), descriptor)?))
+            });
+            registry.register(UNSAFE, &format!("put{}{}", kind, suffix), &format!("(Ljava/lang/Object;J{})V", descriptor), move |context, args| {
+                context.unsafe_put_kind(args[1], args[2].as_long(), args[3], descriptor)?;
+
+                Ok(None)
+            });
+        }
+    }
+
+    for (kind, descriptor) in [("Int", "I"), ("Long", "J"), ("Reference", "Ljava/lang/Object;")] {
+        registry.register(UNSAFE, &format!("compareAndSet{}", kind), &format!("(Ljava/lang/Object;J{0}{0})Z", descriptor), |context, args| {
+            let previous = context.unsafe_compare_and_exchange(args[1], args[2].as_long(), args[3], args[4])?;
+
+            Ok(Some(Value::from_bool(same_value(previous, args[3]))))
+        });
+        registry.register(UNSAFE, &format!("compareAndExchange{}", kind), &format!("(Ljava/lang/Object;J{0}{0}){0}", descriptor), move |context, args| {
+            let previous = context.unsafe_compare_and_exchange(args[1], args[2].as_long(), args[3], args[4])?;
+
+            Ok(Some(with_kind(previous, descriptor)))
+        });
+    }
+
+    for fence in ["loadFence", "storeFence", "fullFence"] {
+        registry.register(UNSAFE, fence, "()V", |_, _| Ok(None));
+    }
+
+    registry.register(UNSAFE, "objectFieldOffset0", "(Ljava/lang/reflect/Field;)J", unsafe_object_field_offset_of_field);
+    registry.register(UNSAFE, "objectFieldOffset1", "(Ljava/lang/Class;Ljava/lang/String;)J", unsafe_object_field_offset);
+    registry.register(UNSAFE, "staticFieldOffset0", "(Ljava/lang/reflect/Field;)J", unsafe_static_field_offset);
+    registry.register(UNSAFE, "staticFieldBase0", "(Ljava/lang/reflect/Field;)Ljava/lang/Object;", unsafe_static_field_base);
+    registry.register(UNSAFE, "allocateInstance", "(Ljava/lang/Class;)Ljava/lang/Object;", unsafe_allocate_instance);
+    registry.register(UNSAFE, "ensureClassInitialized0", "(Ljava/lang/Class;)V", unsafe_ensure_class_initialized);
+    registry.register(UNSAFE, "shouldBeInitialized0", "(Ljava/lang/Class;)Z", unsafe_should_be_initialized);
+    registry.register(UNSAFE, "throwException", "(Ljava/lang/Throwable;)V", unsafe_throw_exception);
+
+    // Long CAS is always supported, AtomicLong doesn't need to fall back to locking
+    registry.register("java/util/concurrent/atomic/AtomicLong", "VMSupportsCS8", "()Z", |_, _| Ok(Some(Value::from_bool(true))));
+}
\ No newline at end of file
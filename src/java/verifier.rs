@@ -0,0 +1,1528 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Formatter;
+
+use crate::java::access_flags;
+use crate::java::attribute::{AttributeCode, StackMapFrameBody, VerificationTypeInfo};
+use crate::java::class::{ConstantPoolEntry, MethodInfo};
+use crate::java::descriptor::{FieldType, MethodDescriptor};
+use crate::java::instruction;
+use crate::java::instruction::Instruction;
+use crate::java::opcodes::Opcode;
+use crate::java::{Attribute, ClassFile};
+
+/// Which classes get verified before they're executed, mirrors `-Xverify`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyMode {
+    /// Trust everything, equivalent to `-Xverify:none`
+    None,
+    /// Verify classes of the main jar but trust library jars
+    Remote,
+    /// Verify every class
+    All
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifyError {
+    pub class_name: String,
+    pub method_name: String,
+    pub method_descriptor: String,
+    pub pc: Option<usize>,
+    pub message: String
+}
+
+/// Access to the class hierarchy needed to check assignability of reference types
+pub trait ClassHierarchy {
+    /// Super class name and interface flag of `class_name`, `None` if the class can't be found
+    fn lookup(&self, class_name: &str) -> Option<(Option<String>, bool)>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationType {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    /// Result of a `new` instruction at the given offset that hasn't been passed to `<init>` yet
+    Uninitialized(u16),
+    /// Class, interface or array type, arrays are named by their descriptor
+    Reference(String),
+    /// Return address pushed by `jsr`, named by the offset of the subroutine it calls. Only class files older than
+    /// version 50 can use subroutines.
+    ReturnAddress(u16)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    locals: Vec<VerificationType>,
+    stack: Vec<VerificationType>
+}
+
+struct MethodVerifier<'a> {
+    class_file: &'a ClassFile,
+    hierarchy: &'a dyn ClassHierarchy,
+
+    class_name: String,
+    method_name: String,
+    method_descriptor: String,
+    descriptor: MethodDescriptor,
+
+    code: &'a AttributeCode,
+    instructions: Vec<(usize, Instruction)>,
+    stack_map: HashMap<usize, Frame>,
+
+    pc: Option<usize>
+}
+
+const JAVA_LANG_OBJECT: &str = "java/lang/Object";
+const JAVA_LANG_THROWABLE: &str = "java/lang/Throwable";
+
+impl VerificationType {
+
+    fn from_field_type(field_type: &FieldType) -> Self {
+        match field_type {
+            FieldType::Boolean | FieldType::Byte | FieldType::Char |
+            FieldType::Short | FieldType::Int   => VerificationType::Integer,
+            FieldType::Float                    => VerificationType::Float,
+            FieldType::Long                     => VerificationType::Long,
+            FieldType::Double                   => VerificationType::Double,
+            FieldType::Object(name)             => VerificationType::Reference(name.clone()),
+            FieldType::Array(_)                 => VerificationType::Reference(field_type.descriptor())
+        }
+    }
+
+    fn is_category2(&self) -> bool {
+        matches!(self, VerificationType::Long | VerificationType::Double)
+    }
+
+    fn is_reference(&self) -> bool {
+        matches!(self, VerificationType::Null | VerificationType::UninitializedThis | VerificationType::Uninitialized(_) | VerificationType::Reference(_))
+    }
+
+    fn reference(name: &str) -> Self {
+        VerificationType::Reference(name.to_string())
+    }
+
+}
+
+/// Verifies all methods of a class, using the type checking verifier (JVMS 4.10.1) or the type inference verifier
+/// (JVMS 4.10.2) for class files older than version 50, which don't have a StackMapTable
+pub fn verify_class(class_file: &ClassFile, hierarchy: &dyn ClassHierarchy) -> Result<(), VerifyError> {
+    for method_info in &class_file.method_table {
+        verify_method(class_file, method_info, hierarchy)?;
+    }
+
+    Ok(())
+}
+
+fn verify_method(class_file: &ClassFile, method_info: &MethodInfo, hierarchy: &dyn ClassHierarchy) -> Result<(), VerifyError> {
+    let class_name = class_file.get_class_name(class_file.this_class as usize).unwrap_or_default();
+    let method_name = class_file.get_constant_pool_string(method_info.name_index as usize).unwrap_or_default();
+    let method_descriptor = class_file.get_constant_pool_string(method_info.descriptor_index as usize).unwrap_or_default();
+
+    let error = |message: &str| VerifyError {
+        class_name: class_name.clone(),
+        method_name: method_name.clone(),
+        method_descriptor: method_descriptor.clone(),
+        pc: None,
+        message: message.to_string()
+    };
+
+    let descriptor = MethodDescriptor::parse(&method_descriptor).ok_or_else(|| error("Invalid method descriptor"))?;
+
+    let mut code = None;
+    let mut stack_map_table = None;
+    for attribute_info in &method_info.attributes {
+        if class_file.get_constant_pool_string(attribute_info.attribute_name_index as usize).as_deref() != Some("Code") { continue; }
+
+        match Attribute::new(class_file, attribute_info) {
+            Some(Attribute::Code(attribute)) => code = Some(attribute),
+            _ => return Err(error("Malformed Code attribute"))
+        }
+    }
+
+    let code = match code {
+        Some(code) => code,
+        None => return Ok(())
+    };
+
+    for attribute_info in &code.attributes {
+        if class_file.get_constant_pool_string(attribute_info.attribute_name_index as usize).as_deref() != Some("StackMapTable") { continue; }
+
+        match Attribute::new(class_file, attribute_info) {
+            Some(Attribute::StackMapTable(attribute)) => stack_map_table = Some(attribute),
+            _ => return Err(error("Malformed StackMapTable attribute"))
+        }
+    }
+
+    if code.code.is_empty() || code.code.len() >= 65536 {
+        return Err(error("Invalid code length"));
+    }
+
+    let instructions = instruction::decode_with_offsets(&code.code).map_err(|decode_error| {
+        let mut verify_error = error(&decode_error.to_string());
+        verify_error.pc = match decode_error {
+            instruction::DecodeError::InvalidOpcode { pc, .. } |
+            instruction::DecodeError::UnexpectedEnd { pc } |
+            instruction::DecodeError::InvalidWideOpcode { pc, .. } |
+            instruction::DecodeError::InvalidBranchTarget { pc, .. } |
+            instruction::DecodeError::InvalidSwitchRange { pc, .. } |
+            instruction::DecodeError::NegativeLookupSwitchCount { pc, .. } => Some(pc)
+        };
+        verify_error
+    })?;
+
+    let mut verifier = MethodVerifier {
+        class_file,
+        hierarchy,
+        class_name: class_name.clone(),
+        method_name: method_name.clone(),
+        method_descriptor: method_descriptor.clone(),
+        descriptor,
+        code: &code,
+        instructions,
+        stack_map: HashMap::new(),
+        pc: None
+    };
+
+    let is_static = method_info.access_flags & access_flags::ACC_STATIC != 0;
+    let initial_locals = verifier.initial_locals(is_static)?;
+
+    if class_file.major_version < 50 {
+        return verifier.infer(initial_locals);
+    }
+
+    if let Some(stack_map_table) = &stack_map_table {
+        verifier.decode_stack_map(&initial_locals, &stack_map_table.entries)?;
+    }
+
+    verifier.verify(initial_locals)
+}
+
+impl<'a> MethodVerifier<'a> {
+
+    fn error(&self, message: String) -> VerifyError {
+        VerifyError {
+            class_name: self.class_name.clone(),
+            method_name: self.method_name.clone(),
+            method_descriptor: self.method_descriptor.clone(),
+            pc: self.pc,
+            message
+        }
+    }
+
+    fn max_stack(&self) -> usize {
+        self.code.max_stack as usize
+    }
+
+    fn max_locals(&self) -> usize {
+        self.code.max_locals as usize
+    }
+
+    fn is_instruction_start(&self, pc: usize) -> bool {
+        self.instructions.binary_search_by_key(&pc, |(offset, _)| *offset).is_ok()
+    }
+
+    fn instruction_at(&self, pc: usize) -> Option<&Instruction> {
+        let index = self.instructions.binary_search_by_key(&pc, |(offset, _)| *offset).ok()?;
+
+        Some(&self.instructions[index].1)
+    }
+
+    /// Locals in their compressed form, as the implicit first frame of the StackMapTable sees them
+    fn initial_locals(&self, is_static: bool) -> Result<Vec<VerificationType>, VerifyError> {
+        let mut locals = vec![];
+
+        if !is_static {
+            if self.method_name == "<init>" && self.class_name != JAVA_LANG_OBJECT {
+                locals.push(VerificationType::UninitializedThis);
+            } else {
+                locals.push(VerificationType::Reference(self.class_name.clone()));
+            }
+        }
+
+        for parameter in &self.descriptor.parameters {
+            locals.push(VerificationType::from_field_type(parameter));
+        }
+
+        Ok(locals)
+    }
+
+    /// Expands compressed locals to `max_locals` slots, category 2 types are followed by Top
+    fn expand_locals(&self, locals: &[VerificationType]) -> Result<Vec<VerificationType>, VerifyError> {
+        let mut result = vec![];
+
+        for local in locals {
+            result.push(local.clone());
+            if local.is_category2() {
+                result.push(VerificationType::Top);
+            }
+        }
+
+        if result.len() > self.max_locals() {
+            return Err(self.error(format!("Local variable table of size {} exceeds max_locals {}", result.len(), self.max_locals())));
+        }
+
+        result.resize(self.max_locals(), VerificationType::Top);
+
+        Ok(result)
+    }
+
+    fn expand_stack(&self, stack: &[VerificationType]) -> Result<Vec<VerificationType>, VerifyError> {
+        let mut result = vec![];
+
+        for item in stack {
+            result.push(item.clone());
+            if item.is_category2() {
+                result.push(VerificationType::Top);
+            }
+        }
+
+        if result.len() > self.max_stack() {
+            return Err(self.error(format!("Operand stack of size {} exceeds max_stack {}", result.len(), self.max_stack())));
+        }
+
+        Ok(result)
+    }
+
+    fn verification_type(&self, info: &VerificationTypeInfo) -> Result<VerificationType, VerifyError> {
+        let result = match info {
+            VerificationTypeInfo::Top()                 => VerificationType::Top,
+            VerificationTypeInfo::Integer()             => VerificationType::Integer,
+            VerificationTypeInfo::Float()               => VerificationType::Float,
+            VerificationTypeInfo::Double()              => VerificationType::Double,
+            VerificationTypeInfo::Long()                => VerificationType::Long,
+            VerificationTypeInfo::Null()                => VerificationType::Null,
+            VerificationTypeInfo::UninitializedThis()   => VerificationType::UninitializedThis,
+            VerificationTypeInfo::Object(index)         => VerificationType::Reference(self.class_reference(*index)?),
+            VerificationTypeInfo::Uninitialized(offset) => {
+                if !matches!(self.instruction_at(*offset as usize), Some(Instruction::ConstantPool(Opcode::new, _))) {
+                    return Err(self.error(format!("Uninitialized type refers to offset {} which isn't a new instruction", offset)));
+                }
+
+                VerificationType::Uninitialized(*offset)
+            }
+        };
+
+        Ok(result)
+    }
+
+    fn decode_stack_map(&mut self, initial_locals: &[VerificationType], entries: &[crate::java::attribute::StackMapFrame]) -> Result<(), VerifyError> {
+        let mut locals = initial_locals.to_vec();
+        let mut offset: Option<usize> = None;
+
+        for entry in entries {
+            let (offset_delta, stack) = match &entry.body {
+                StackMapFrameBody::Same() => (entry.frame_type as usize, vec![]),
+                StackMapFrameBody::SameLocals1StackItem { stack } => (entry.frame_type as usize - 64, vec![ self.verification_type(stack)? ]),
+                StackMapFrameBody::SameLocals1StackItemExtended { offset_delta, stack } => (*offset_delta as usize, vec![ self.verification_type(stack)? ]),
+                StackMapFrameBody::Chop { offset_delta } => {
+                    let count = 251 - entry.frame_type as usize;
+                    if count > locals.len() {
+                        return Err(self.error("Chop frame removes more locals than exist".to_string()));
+                    }
+                    locals.truncate(locals.len() - count);
+
+                    (*offset_delta as usize, vec![])
+                },
+                StackMapFrameBody::SameExtended { offset_delta } => (*offset_delta as usize, vec![]),
+                StackMapFrameBody::Append { offset_delta, locals: appended } => {
+                    for local in appended {
+                        locals.push(self.verification_type(local)?);
+                    }
+
+                    (*offset_delta as usize, vec![])
+                },
+                StackMapFrameBody::Full { offset_delta, locals: full_locals, stack, .. } => {
+                    locals = full_locals.iter().map(|local| self.verification_type(local)).collect::<Result<_, _>>()?;
+
+                    (*offset_delta as usize, stack.iter().map(|item| self.verification_type(item)).collect::<Result<_, _>>()?)
+                }
+            };
+
+            let pc = match offset {
+                None => offset_delta,
+                Some(previous) => previous + offset_delta + 1
+            };
+            offset = Some(pc);
+
+            if !self.is_instruction_start(pc) {
+                return Err(self.error(format!("StackMapTable frame at offset {} isn't at an instruction boundary", pc)));
+            }
+
+            let frame = Frame {
+                locals: self.expand_locals(&locals)?,
+                stack: self.expand_stack(&stack)?
+            };
+
+            self.stack_map.insert(pc, frame);
+        }
+
+        Ok(())
+    }
+
+    fn is_assignable(&self, from: &VerificationType, to: &VerificationType) -> bool {
+        if from == to {
+            return true;
+        }
+
+        match (from, to) {
+            (_, VerificationType::Top)                                      => true,
+            (VerificationType::Null, VerificationType::Reference(_))        => true,
+            (VerificationType::Reference(from), VerificationType::Reference(to)) => self.is_reference_assignable(from, to),
+            _ => false
+        }
+    }
+
+    fn is_reference_assignable(&self, from: &str, to: &str) -> bool {
+        if from == to || to == JAVA_LANG_OBJECT {
+            return true;
+        }
+
+        match (from.strip_prefix('['), to.strip_prefix('[')) {
+            (Some(from_component), Some(to_component)) => {
+                let is_reference = |component: &str| component.starts_with('L') || component.starts_with('[');
+
+                if is_reference(from_component) && is_reference(to_component) {
+                    let strip = |component: &str| -> String {
+                        component.strip_prefix('L').and_then(|name| name.strip_suffix(';')).unwrap_or(component).to_string()
+                    };
+
+                    self.is_reference_assignable(&strip(from_component), &strip(to_component))
+                } else {
+                    from_component == to_component
+                }
+            },
+            (Some(_), None) => to == "java/lang/Cloneable" || to == "java/io/Serializable",
+            (None, Some(_)) => false,
+            (None, None) => {
+                // Interfaces are treated like java/lang/Object, the check is deferred to runtime
+                if let Some((_, true)) = self.hierarchy.lookup(to) {
+                    return true;
+                }
+
+                let mut current = from.to_string();
+                for _ in 0..256 {
+                    match self.hierarchy.lookup(&current) {
+                        Some((Some(super_class), _)) => {
+                            if super_class == to {
+                                return true;
+                            }
+                            current = super_class;
+                        },
+                        Some((None, _)) => return false,
+                        // Unknown classes can't be checked here, loading them will fail later
+                        None => return true
+                    }
+                }
+
+                false
+            }
+        }
+    }
+
+    fn is_frame_assignable(&self, from: &Frame, to: &Frame) -> bool {
+        from.stack.len() == to.stack.len() &&
+            from.stack.iter().zip(&to.stack).all(|(from, to)| self.is_assignable(from, to)) &&
+            from.locals.iter().zip(&to.locals).all(|(from, to)| self.is_assignable(from, to))
+    }
+
+    fn check_target(&self, frame: &Frame, target: usize) -> Result<(), VerifyError> {
+        match self.stack_map.get(&target) {
+            Some(target_frame) => {
+                if !self.is_frame_assignable(frame, target_frame) {
+                    return Err(self.error(format!("Inconsistent stackmap frames at branch target {}", target)));
+                }
+
+                Ok(())
+            },
+            None => Err(self.error(format!("Expecting a stackmap frame at branch target {}", target)))
+        }
+    }
+
+    fn push(&self, frame: &mut Frame, verification_type: VerificationType) -> Result<(), VerifyError> {
+        let category2 = verification_type.is_category2();
+
+        frame.stack.push(verification_type);
+        if category2 {
+            frame.stack.push(VerificationType::Top);
+        }
+
+        if frame.stack.len() > self.max_stack() {
+            return Err(self.error("Operand stack overflow".to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn pop_slot(&self, frame: &mut Frame) -> Result<VerificationType, VerifyError> {
+        frame.stack.pop().ok_or_else(|| self.error("Operand stack underflow".to_string()))
+    }
+
+    fn pop(&self, frame: &mut Frame, expected: &VerificationType) -> Result<VerificationType, VerifyError> {
+        if expected.is_category2() {
+            let top = self.pop_slot(frame)?;
+            let actual = self.pop_slot(frame)?;
+            if top != VerificationType::Top || &actual != expected {
+                return Err(self.error(format!("Bad type on operand stack, expected {:?} but found {:?}", expected, actual)));
+            }
+
+            return Ok(actual);
+        }
+
+        let actual = self.pop_slot(frame)?;
+        if actual == VerificationType::Top || actual.is_category2() || !self.is_assignable(&actual, expected) {
+            return Err(self.error(format!("Bad type on operand stack, expected {:?} but found {:?}", expected, actual)));
+        }
+
+        Ok(actual)
+    }
+
+    fn pop_reference(&self, frame: &mut Frame) -> Result<VerificationType, VerifyError> {
+        let actual = self.pop_slot(frame)?;
+        if !actual.is_reference() {
+            return Err(self.error(format!("Bad type on operand stack, expected a reference but found {:?}", actual)));
+        }
+
+        Ok(actual)
+    }
+
+    fn pop_initialized_reference(&self, frame: &mut Frame, expected: &str) -> Result<VerificationType, VerifyError> {
+        self.pop(frame, &VerificationType::reference(expected))
+    }
+
+    fn pop_array(&self, frame: &mut Frame) -> Result<Option<String>, VerifyError> {
+        match self.pop_slot(frame)? {
+            VerificationType::Null => Ok(None),
+            VerificationType::Reference(name) if name.starts_with('[') => Ok(Some(name)),
+            actual => Err(self.error(format!("Bad type on operand stack, expected an array but found {:?}", actual)))
+        }
+    }
+
+    /// Pops `count` raw slots for the stack manipulation instructions without splitting a long or double
+    fn take_slots(&self, frame: &mut Frame, count: usize) -> Result<Vec<VerificationType>, VerifyError> {
+        if frame.stack.len() < count {
+            return Err(self.error("Operand stack underflow".to_string()));
+        }
+
+        let slots = frame.stack.split_off(frame.stack.len() - count);
+        if slots[0] == VerificationType::Top || slots[count - 1].is_category2() {
+            return Err(self.error("Stack manipulation splits a category 2 value".to_string()));
+        }
+
+        Ok(slots)
+    }
+
+    fn push_slots(&self, frame: &mut Frame, groups: &[&Vec<VerificationType>]) -> Result<(), VerifyError> {
+        for group in groups {
+            frame.stack.extend(group.iter().cloned());
+        }
+
+        if frame.stack.len() > self.max_stack() {
+            return Err(self.error("Operand stack overflow".to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn load(&self, frame: &Frame, index: usize, expected: Option<&VerificationType>) -> Result<VerificationType, VerifyError> {
+        let actual = frame.locals.get(index).ok_or_else(|| self.error(format!("Local variable index {} out of bounds", index)))?;
+
+        match expected {
+            Some(expected) => {
+                if !self.is_assignable(actual, expected) {
+                    return Err(self.error(format!("Bad local variable type, expected {:?} but found {:?}", expected, actual)));
+                }
+
+                if expected.is_category2() && frame.locals.get(index + 1) != Some(&VerificationType::Top) {
+                    return Err(self.error(format!("Local variable index {} out of bounds", index + 1)));
+                }
+            },
+            None => {
+                if !actual.is_reference() {
+                    return Err(self.error(format!("Bad local variable type, expected a reference but found {:?}", actual)));
+                }
+            }
+        }
+
+        Ok(actual.clone())
+    }
+
+    fn store(&self, frame: &mut Frame, index: usize, verification_type: VerificationType) -> Result<(), VerifyError> {
+        let slots = if verification_type.is_category2() { 2 } else { 1 };
+        if index + slots > frame.locals.len() {
+            return Err(self.error(format!("Local variable index {} out of bounds", index + slots - 1)));
+        }
+
+        // Overwriting the second half of a long or double invalidates the first half
+        if index > 0 && frame.locals[index - 1].is_category2() {
+            frame.locals[index - 1] = VerificationType::Top;
+        }
+
+        frame.locals[index] = verification_type;
+        if slots == 2 {
+            frame.locals[index + 1] = VerificationType::Top;
+        }
+
+        Ok(())
+    }
+
+    fn entry(&self, index: u16) -> Result<&'a ConstantPoolEntry, VerifyError> {
+        self.class_file.constant_pool.get((index as usize).wrapping_sub(1)).ok_or_else(|| self.error(format!("Invalid constant pool index {}", index)))
+    }
+
+    fn utf8(&self, index: u16) -> Result<String, VerifyError> {
+        self.class_file.get_constant_pool_string(index as usize).ok_or_else(|| self.error(format!("Constant pool index {} isn't a Utf8 entry", index)))
+    }
+
+    fn class_reference(&self, index: u16) -> Result<String, VerifyError> {
+        self.class_file.get_class_name(index as usize).ok_or_else(|| self.error(format!("Constant pool index {} isn't a Class entry", index)))
+    }
+
+    fn name_and_type(&self, index: u16) -> Result<(String, String), VerifyError> {
+        match self.entry(index)? {
+            ConstantPoolEntry::NameAndTypeDescriptor(name_index, type_index) => Ok((self.utf8(*name_index)?, self.utf8(*type_index)?)),
+            _ => Err(self.error(format!("Constant pool index {} isn't a NameAndType entry", index)))
+        }
+    }
+
+    /// Owner, name and descriptor of a field or method reference
+    fn member_reference(&self, index: u16, allowed: &[&str]) -> Result<(String, String, String), VerifyError> {
+        let (kind, class_index, name_and_type_index) = match self.entry(index)? {
+            ConstantPoolEntry::FieldReference(class, name_and_type)             => ("Fieldref", class, name_and_type),
+            ConstantPoolEntry::MethodReference(class, name_and_type)            => ("Methodref", class, name_and_type),
+            ConstantPoolEntry::InterfaceMethodReference(class, name_and_type)   => ("InterfaceMethodref", class, name_and_type),
+            _ => ("", &0, &0)
+        };
+
+        if !allowed.contains(&kind) {
+            return Err(self.error(format!("Constant pool index {} must be one of {:?}", index, allowed)));
+        }
+
+        let class_name = self.class_reference(*class_index)?;
+        let (name, descriptor) = self.name_and_type(*name_and_type_index)?;
+
+        Ok((class_name, name, descriptor))
+    }
+
+    fn field_type(&self, descriptor: &str) -> Result<VerificationType, VerifyError> {
+        let field_type = FieldType::parse(descriptor).ok_or_else(|| self.error(format!("Invalid field descriptor '{}'", descriptor)))?;
+
+        Ok(VerificationType::from_field_type(&field_type))
+    }
+
+    fn method_descriptor(&self, descriptor: &str) -> Result<MethodDescriptor, VerifyError> {
+        MethodDescriptor::parse(descriptor).ok_or_else(|| self.error(format!("Invalid method descriptor '{}'", descriptor)))
+    }
+
+    fn pop_arguments(&self, frame: &mut Frame, descriptor: &MethodDescriptor) -> Result<(), VerifyError> {
+        for parameter in descriptor.parameters.iter().rev() {
+            self.pop(frame, &VerificationType::from_field_type(parameter))?;
+        }
+
+        Ok(())
+    }
+
+    fn push_return(&self, frame: &mut Frame, descriptor: &MethodDescriptor) -> Result<(), VerifyError> {
+        if let Some(return_type) = &descriptor.return_type {
+            self.push(frame, VerificationType::from_field_type(return_type))?;
+        }
+
+        Ok(())
+    }
+
+    fn replace_uninitialized(frame: &mut Frame, uninitialized: &VerificationType, initialized: &VerificationType) {
+        for slot in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+            if slot == uninitialized {
+                *slot = initialized.clone();
+            }
+        }
+    }
+
+    fn check_return(&self, frame: &mut Frame, expected: Option<VerificationType>) -> Result<(), VerifyError> {
+        let return_type = self.descriptor.return_type.as_ref().map(VerificationType::from_field_type);
+
+        match (&return_type, &expected) {
+            (None, None) => {
+                if self.method_name == "<init>" && frame.locals.contains(&VerificationType::UninitializedThis) {
+                    return Err(self.error("Constructor must call super() or this() before return".to_string()));
+                }
+            },
+            (Some(return_type), Some(expected)) => {
+                let compatible = match expected {
+                    VerificationType::Reference(_) => return_type.is_reference(),
+                    _ => return_type == expected
+                };
+
+                if !compatible {
+                    return Err(self.error("Method return type doesn't match the return instruction".to_string()));
+                }
+
+                self.pop(frame, return_type)?;
+            },
+            _ => return Err(self.error("Method return type doesn't match the return instruction".to_string()))
+        }
+
+        Ok(())
+    }
+
+    fn verify(&mut self, initial_locals: Vec<VerificationType>) -> Result<(), VerifyError> {
+        let initial = Frame {
+            locals: self.expand_locals(&initial_locals)?,
+            stack: vec![]
+        };
+
+        self.check_exception_table()?;
+
+        let instructions = self.instructions.clone();
+        self.verify_instructions(&instructions, initial)
+    }
+
+    fn check_exception_table(&self) -> Result<(), VerifyError> {
+        for entry in &self.code.exception_table {
+            let (start, end, handler) = (entry.start_pc as usize, entry.end_pc as usize, entry.handler_pc as usize);
+
+            if start >= end || !self.is_instruction_start(start) || (end != self.code.code.len() && !self.is_instruction_start(end)) {
+                return Err(self.error(format!("Illegal exception table range {}..{}", start, end)));
+            }
+
+            if !self.is_instruction_start(handler) {
+                return Err(self.error(format!("Illegal exception table handler {}", handler)));
+            }
+
+            if entry.catch_type != 0 {
+                let catch_type = self.class_reference(entry.catch_type)?;
+                if !self.is_reference_assignable(&catch_type, JAVA_LANG_THROWABLE) {
+                    return Err(self.error(format!("Catch type {} is not a subclass of Throwable", catch_type)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_handlers(&self, pc: usize, frame: &Frame) -> Result<(), VerifyError> {
+        for entry in &self.code.exception_table {
+            if pc < entry.start_pc as usize || pc >= entry.end_pc as usize { continue; }
+
+            let catch_type = if entry.catch_type == 0 { JAVA_LANG_THROWABLE.to_string() } else { self.class_reference(entry.catch_type)? };
+            let handler_frame = Frame {
+                locals: frame.locals.clone(),
+                stack: vec![ VerificationType::Reference(catch_type) ]
+            };
+
+            self.check_target(&handler_frame, entry.handler_pc as usize)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the instructions to a fixed point, merging the frames of all paths into an instruction (JVMS 4.10.2.2)
+    fn infer(&mut self, initial_locals: Vec<VerificationType>) -> Result<(), VerifyError> {
+        let initial = Frame {
+            locals: self.expand_locals(&initial_locals)?,
+            stack: vec![]
+        };
+
+        self.check_exception_table()?;
+
+        let mut frames = HashMap::from([(0, initial)]);
+        let mut changed = vec![0];
+
+        // Calls of each subroutine as the offset of the jsr and the instruction after it, and the merged frames
+        // of the ret instructions returning from it
+        let mut callers: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+        let mut returns: HashMap<usize, Frame> = HashMap::new();
+        let mut subroutine_locals: HashMap<usize, HashSet<usize>> = HashMap::new();
+
+        while let Some(pc) = changed.pop() {
+            self.pc = Some(pc);
+
+            let instruction = self.instruction_at(pc).cloned().ok_or_else(|| self.error(format!("No instruction at offset {}", pc)))?;
+            let frame = frames[&pc].clone();
+            let next = pc + instruction.length(pc);
+
+            self.merge_handlers(&mut frames, &mut changed, pc, &frame)?;
+
+            match instruction {
+                Instruction::Branch(Opcode::jsr | Opcode::jsr_w, target) => {
+                    let subroutine = target as usize;
+
+                    let mut entered = frame.clone();
+                    self.push(&mut entered, VerificationType::ReturnAddress(target as u16))?;
+                    self.merge_into(&mut frames, &mut changed, subroutine, entered)?;
+
+                    let subroutine_callers = callers.entry(subroutine).or_default();
+                    if !subroutine_callers.contains(&(pc, next)) {
+                        subroutine_callers.push((pc, next));
+                    }
+
+                    if let Some(returned) = returns.get(&subroutine) {
+                        let written = subroutine_locals.entry(subroutine).or_insert_with(|| self.subroutine_locals(subroutine));
+                        let after = Self::after_subroutine(&frame, returned, written);
+                        self.merge_into(&mut frames, &mut changed, next, after)?;
+                    }
+                },
+                Instruction::Local { opcode: Opcode::ret, index, .. } => {
+                    let subroutine = match frame.locals.get(index as usize) {
+                        Some(VerificationType::ReturnAddress(subroutine)) => *subroutine as usize,
+                        actual => return Err(self.error(format!("Bad local variable type, ret expects a return address but found {:?}", actual)))
+                    };
+
+                    let returned = match returns.get(&subroutine) {
+                        Some(previous) => self.merge_frames(previous, &frame)?,
+                        None => frame
+                    };
+                    if returns.get(&subroutine) == Some(&returned) {
+                        continue;
+                    }
+
+                    let written = subroutine_locals.entry(subroutine).or_insert_with(|| self.subroutine_locals(subroutine));
+                    for (caller, caller_next) in callers.get(&subroutine).into_iter().flatten() {
+                        let after = Self::after_subroutine(&frames[caller], &returned, written);
+                        self.merge_into(&mut frames, &mut changed, *caller_next, after)?;
+                    }
+
+                    returns.insert(subroutine, returned);
+                },
+                _ => {
+                    let (after, falls_through) = self.execute(pc, &instruction, frame)?;
+
+                    if matches!(instruction, Instruction::Local { .. }) || matches!(instruction, Instruction::Simple(opcode) if Self::implicit_local(opcode).is_some()) {
+                        self.merge_handlers(&mut frames, &mut changed, pc, &after)?;
+                    }
+
+                    for target in instruction.branch_targets() {
+                        self.merge_into(&mut frames, &mut changed, target as usize, after.clone())?;
+                    }
+
+                    if falls_through {
+                        if next >= self.code.code.len() {
+                            return Err(self.error("Falling off the end of the code".to_string()));
+                        }
+
+                        self.merge_into(&mut frames, &mut changed, next, after)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn merge_handlers(&self, frames: &mut HashMap<usize, Frame>, changed: &mut Vec<usize>, pc: usize, frame: &Frame) -> Result<(), VerifyError> {
+        for entry in &self.code.exception_table {
+            if pc < entry.start_pc as usize || pc >= entry.end_pc as usize { continue; }
+
+            let catch_type = if entry.catch_type == 0 { JAVA_LANG_THROWABLE.to_string() } else { self.class_reference(entry.catch_type)? };
+            let handler_frame = Frame {
+                locals: frame.locals.clone(),
+                stack: vec![ VerificationType::Reference(catch_type) ]
+            };
+
+            self.merge_into(frames, changed, entry.handler_pc as usize, handler_frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// Merges a frame into the one of the instruction at `pc`, which is verified (again) if its frame changed
+    fn merge_into(&self, frames: &mut HashMap<usize, Frame>, changed: &mut Vec<usize>, pc: usize, frame: Frame) -> Result<(), VerifyError> {
+        match frames.get_mut(&pc) {
+            Some(existing) => {
+                let merged = self.merge_frames(existing, &frame)?;
+                if merged != *existing {
+                    *existing = merged;
+                    changed.push(pc);
+                }
+            },
+            None => {
+                frames.insert(pc, frame);
+                changed.push(pc);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn merge_frames(&self, existing: &Frame, incoming: &Frame) -> Result<Frame, VerifyError> {
+        if existing.stack.len() != incoming.stack.len() {
+            return Err(self.error(format!("Inconsistent stack height {} != {}", existing.stack.len(), incoming.stack.len())));
+        }
+
+        let mut stack = vec![];
+        for (existing, incoming) in existing.stack.iter().zip(&incoming.stack) {
+            let merged = self.merge_types(existing, incoming);
+            if merged == VerificationType::Top && *existing != VerificationType::Top {
+                return Err(self.error(format!("Mismatched stack types {:?} and {:?}", existing, incoming)));
+            }
+
+            stack.push(merged);
+        }
+
+        let mut locals: Vec<_> = existing.locals.iter().zip(&incoming.locals).map(|(existing, incoming)| self.merge_types(existing, incoming)).collect();
+
+        // The first half of a long or double whose second half was lost is unusable too
+        for index in 0..locals.len() {
+            if locals[index].is_category2() && locals.get(index + 1) != Some(&VerificationType::Top) {
+                locals[index] = VerificationType::Top;
+            }
+        }
+
+        Ok(Frame { locals, stack })
+    }
+
+    fn merge_types(&self, existing: &VerificationType, incoming: &VerificationType) -> VerificationType {
+        match (existing, incoming) {
+            _ if existing == incoming => existing.clone(),
+            (VerificationType::Null, VerificationType::Reference(_)) => incoming.clone(),
+            (VerificationType::Reference(_), VerificationType::Null) => existing.clone(),
+            (VerificationType::Reference(existing), VerificationType::Reference(incoming)) => VerificationType::Reference(self.common_super_type(existing, incoming)),
+            _ => VerificationType::Top
+        }
+    }
+
+    /// The most specific class both reference types are assignable to, interfaces merge to `java/lang/Object`
+    fn common_super_type(&self, first: &str, second: &str) -> String {
+        if self.is_reference_assignable(first, second) {
+            return second.to_string();
+        }
+        if self.is_reference_assignable(second, first) {
+            return first.to_string();
+        }
+
+        if let (Some(first_component), Some(second_component)) = (first.strip_prefix('['), second.strip_prefix('[')) {
+            let class_name = |component: &str| -> Option<String> {
+                match component.strip_prefix('L').and_then(|name| name.strip_suffix(';')) {
+                    Some(name) => Some(name.to_string()),
+                    None if component.starts_with('[') => Some(component.to_string()),
+                    None => None
+                }
+            };
+
+            if let (Some(first_element), Some(second_element)) = (class_name(first_component), class_name(second_component)) {
+                let element = self.common_super_type(&first_element, &second_element);
+                return if element.starts_with('[') { format!("[{}", element) } else { format!("[L{};", element) };
+            }
+
+            return JAVA_LANG_OBJECT.to_string();
+        }
+
+        if first.starts_with('[') || second.starts_with('[') {
+            return JAVA_LANG_OBJECT.to_string();
+        }
+
+        let mut current = first.to_string();
+        for _ in 0..256 {
+            match self.hierarchy.lookup(&current) {
+                Some((Some(super_class), _)) => {
+                    if self.is_reference_assignable(second, &super_class) {
+                        return super_class;
+                    }
+                    current = super_class;
+                },
+                _ => break
+            }
+        }
+
+        JAVA_LANG_OBJECT.to_string()
+    }
+
+    /// Frame after returning from a subroutine: locals the subroutine may write come from its ret, all others
+    /// still have the types they had at the jsr
+    fn after_subroutine(caller: &Frame, returned: &Frame, written: &HashSet<usize>) -> Frame {
+        let locals = caller.locals.iter().zip(&returned.locals).enumerate()
+            .map(|(index, (caller, returned))| if written.contains(&index) { returned.clone() } else { caller.clone() })
+            .collect();
+
+        Frame { locals, stack: returned.stack.clone() }
+    }
+
+    /// Locals written by the instructions reachable from a subroutine, nested subroutines included
+    fn subroutine_locals(&self, subroutine: usize) -> HashSet<usize> {
+        let mut written = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![subroutine];
+
+        while let Some(pc) = pending.pop() {
+            if !visited.insert(pc) { continue; }
+            let Some(instruction) = self.instruction_at(pc) else { continue };
+
+            let store = match instruction {
+                Instruction::Local { opcode, index, .. } => Some((*opcode, *index as usize)),
+                Instruction::Simple(opcode) => Self::implicit_local(*opcode).map(|(opcode, index)| (opcode, index as usize)),
+                _ => None
+            };
+
+            if let Some((opcode, index)) = store {
+                if matches!(opcode, Opcode::istore | Opcode::lstore | Opcode::fstore | Opcode::dstore | Opcode::astore) {
+                    // A store also clobbers the pair of a long or double it overlaps
+                    written.extend([index, index + 1]);
+                    if index > 0 {
+                        written.insert(index - 1);
+                    }
+                }
+            }
+
+            pending.extend(instruction.branch_targets().into_iter().map(|target| target as usize));
+
+            let falls_through = match instruction {
+                Instruction::Simple(opcode) => !matches!(opcode, Opcode::ireturn | Opcode::lreturn | Opcode::freturn | Opcode::dreturn |
+                    Opcode::areturn | Opcode::r#return | Opcode::athrow),
+                Instruction::Branch(opcode, _) => !matches!(opcode, Opcode::goto | Opcode::goto_w),
+                Instruction::Local { opcode: Opcode::ret, .. } => false,
+                Instruction::TableSwitch { .. } | Instruction::LookupSwitch { .. } => false,
+                _ => true
+            };
+
+            if falls_through {
+                pending.push(pc + instruction.length(pc));
+            }
+
+            // Handlers of the subroutine's instructions are part of it as well
+            for entry in &self.code.exception_table {
+                if pc >= entry.start_pc as usize && pc < entry.end_pc as usize {
+                    pending.push(entry.handler_pc as usize);
+                }
+            }
+        }
+
+        written
+    }
+
+    fn verify_instructions(&mut self, instructions: &[(usize, Instruction)], initial: Frame) -> Result<(), VerifyError> {
+        let mut current = Some(initial);
+
+        for (pc, instruction) in instructions {
+            self.pc = Some(*pc);
+
+            if let Some(mapped) = self.stack_map.get(pc) {
+                if let Some(current) = &current {
+                    if !self.is_frame_assignable(current, mapped) {
+                        return Err(self.error("Instruction type does not match stack map".to_string()));
+                    }
+                }
+
+                current = Some(mapped.clone());
+            }
+
+            let frame = current.take().ok_or_else(|| self.error("Expecting a stackmap frame at branch target".to_string()))?;
+
+            self.check_handlers(*pc, &frame)?;
+
+            let (after, falls_through) = self.execute(*pc, instruction, frame)?;
+
+            if matches!(instruction, Instruction::Local { .. } | Instruction::Increment { .. }) || matches!(instruction, Instruction::Simple(opcode) if Self::implicit_local(*opcode).is_some()) {
+                self.check_handlers(*pc, &after)?;
+            }
+
+            for target in instruction.branch_targets() {
+                self.check_target(&after, target as usize)?;
+            }
+
+            if falls_through {
+                current = Some(after);
+            }
+        }
+
+        if current.is_some() {
+            return Err(self.error("Falling off the end of the code".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Base instruction and index of the `xload_n` and `xstore_n` shorthands
+    fn implicit_local(opcode: Opcode) -> Option<(Opcode, u16)> {
+        let value = opcode as u8;
+
+        match value {
+            0x1A..=0x2D => Some((Opcode::try_from(Opcode::iload as u8 + (value - 0x1A) / 4).ok()?, ((value - 0x1A) % 4) as u16)),
+            0x3B..=0x4E => Some((Opcode::try_from(Opcode::istore as u8 + (value - 0x3B) / 4).ok()?, ((value - 0x3B) % 4) as u16)),
+            _ => None
+        }
+    }
+
+    fn local_type(opcode: Opcode) -> Option<VerificationType> {
+        match opcode {
+            Opcode::iload | Opcode::istore  => Some(VerificationType::Integer),
+            Opcode::lload | Opcode::lstore  => Some(VerificationType::Long),
+            Opcode::fload | Opcode::fstore  => Some(VerificationType::Float),
+            Opcode::dload | Opcode::dstore  => Some(VerificationType::Double),
+            _ => None
+        }
+    }
+
+    fn execute_local(&self, frame: &mut Frame, opcode: Opcode, index: usize) -> Result<(), VerifyError> {
+        match opcode {
+            Opcode::iload | Opcode::lload | Opcode::fload | Opcode::dload => {
+                let verification_type = Self::local_type(opcode).unwrap();
+                self.load(frame, index, Some(&verification_type))?;
+                self.push(frame, verification_type)
+            },
+            Opcode::aload => {
+                let verification_type = self.load(frame, index, None)?;
+                self.push(frame, verification_type)
+            },
+            Opcode::istore | Opcode::lstore | Opcode::fstore | Opcode::dstore => {
+                let verification_type = Self::local_type(opcode).unwrap();
+                self.pop(frame, &verification_type)?;
+                self.store(frame, index, verification_type)
+            },
+            Opcode::astore => {
+                // Subroutines store their return address before they can return with ret
+                let verification_type = match frame.stack.last() {
+                    Some(VerificationType::ReturnAddress(_)) => self.pop_slot(frame)?,
+                    _ => self.pop_reference(frame)?
+                };
+                self.store(frame, index, verification_type)
+            },
+            _ => Err(self.error(format!("Instruction '{}' isn't supported by the type checking verifier", opcode)))
+        }
+    }
+
+    /// Operand types popped and the type pushed by instructions that only do arithmetic
+    fn arithmetic_signature(opcode: Opcode) -> Option<(Vec<VerificationType>, Option<VerificationType>)> {
+        use VerificationType::{Integer as I, Long as L, Float as F, Double as D};
+
+        let signature = match opcode {
+            Opcode::nop                                                     => (vec![], None),
+            Opcode::aconst_null                                             => (vec![], Some(VerificationType::Null)),
+            Opcode::iconst_m1 | Opcode::iconst_0 | Opcode::iconst_1 | Opcode::iconst_2 |
+            Opcode::iconst_3 | Opcode::iconst_4 | Opcode::iconst_5          => (vec![], Some(I)),
+            Opcode::lconst_0 | Opcode::lconst_1                             => (vec![], Some(L)),
+            Opcode::fconst_0 | Opcode::fconst_1 | Opcode::fconst_2          => (vec![], Some(F)),
+            Opcode::dconst_0 | Opcode::dconst_1                             => (vec![], Some(D)),
+
+            Opcode::iadd | Opcode::isub | Opcode::imul | Opcode::idiv | Opcode::irem |
+            Opcode::ishl | Opcode::ishr | Opcode::iushr |
+            Opcode::iand | Opcode::ior | Opcode::ixor                       => (vec![I, I], Some(I)),
+            Opcode::ladd | Opcode::lsub | Opcode::lmul | Opcode::ldiv | Opcode::lrem |
+            Opcode::land | Opcode::lor | Opcode::lxor                       => (vec![L, L], Some(L)),
+            Opcode::lshl | Opcode::lshr | Opcode::lushr                     => (vec![L, I], Some(L)),
+            Opcode::fadd | Opcode::fsub | Opcode::fmul | Opcode::fdiv | Opcode::frem => (vec![F, F], Some(F)),
+            Opcode::dadd | Opcode::dsub | Opcode::dmul | Opcode::ddiv | Opcode::drem => (vec![D, D], Some(D)),
+
+            Opcode::ineg                                                    => (vec![I], Some(I)),
+            Opcode::lneg                                                    => (vec![L], Some(L)),
+            Opcode::fneg                                                    => (vec![F], Some(F)),
+            Opcode::dneg                                                    => (vec![D], Some(D)),
+
+            Opcode::i2l                                                     => (vec![I], Some(L)),
+            Opcode::i2f                                                     => (vec![I], Some(F)),
+            Opcode::i2d                                                     => (vec![I], Some(D)),
+            Opcode::l2i                                                     => (vec![L], Some(I)),
+            Opcode::l2f                                                     => (vec![L], Some(F)),
+            Opcode::l2d                                                     => (vec![L], Some(D)),
+            Opcode::f2i                                                     => (vec![F], Some(I)),
+            Opcode::f2l                                                     => (vec![F], Some(L)),
+            Opcode::f2d                                                     => (vec![F], Some(D)),
+            Opcode::d2i                                                     => (vec![D], Some(I)),
+            Opcode::d2l                                                     => (vec![D], Some(L)),
+            Opcode::d2f                                                     => (vec![D], Some(F)),
+            Opcode::i2b | Opcode::i2c | Opcode::i2s                         => (vec![I], Some(I)),
+
+            Opcode::lcmp                                                    => (vec![L, L], Some(I)),
+            Opcode::fcmpl | Opcode::fcmpg                                   => (vec![F, F], Some(I)),
+            Opcode::dcmpl | Opcode::dcmpg                                   => (vec![D, D], Some(I)),
+
+            _ => return None
+        };
+
+        Some(signature)
+    }
+
+    /// Element type of a primitive array load or store, `None` for the reference variants
+    fn array_element(opcode: Opcode) -> Option<(&'static [&'static str], VerificationType)> {
+        let element = match opcode {
+            Opcode::iaload | Opcode::iastore => (&["[I"][..], VerificationType::Integer),
+            Opcode::laload | Opcode::lastore => (&["[J"][..], VerificationType::Long),
+            Opcode::faload | Opcode::fastore => (&["[F"][..], VerificationType::Float),
+            Opcode::daload | Opcode::dastore => (&["[D"][..], VerificationType::Double),
+            Opcode::baload | Opcode::bastore => (&["[B", "[Z"][..], VerificationType::Integer),
+            Opcode::caload | Opcode::castore => (&["[C"][..], VerificationType::Integer),
+            Opcode::saload | Opcode::sastore => (&["[S"][..], VerificationType::Integer),
+            _ => return None
+        };
+
+        Some(element)
+    }
+
+    fn execute_simple(&self, pc: usize, frame: &mut Frame, opcode: Opcode) -> Result<bool, VerifyError> {
+        if let Some((pops, push)) = Self::arithmetic_signature(opcode) {
+            for expected in pops.iter().rev() {
+                self.pop(frame, expected)?;
+            }
+            if let Some(push) = push {
+                self.push(frame, push)?;
+            }
+
+            return Ok(true);
+        }
+
+        if let Some((base, index)) = Self::implicit_local(opcode) {
+            self.execute_local(frame, base, index as usize)?;
+
+            return Ok(true);
+        }
+
+        match opcode {
+            Opcode::iaload | Opcode::laload | Opcode::faload | Opcode::daload |
+            Opcode::baload | Opcode::caload | Opcode::saload => {
+                let (arrays, element) = Self::array_element(opcode).unwrap();
+                self.pop(frame, &VerificationType::Integer)?;
+                if let Some(array) = self.pop_array(frame)? {
+                    if !arrays.contains(&array.as_str()) {
+                        return Err(self.error(format!("Bad type on operand stack, expected {:?} but found {}", arrays, array)));
+                    }
+                }
+                self.push(frame, element)?;
+            },
+            Opcode::aaload => {
+                self.pop(frame, &VerificationType::Integer)?;
+                match self.pop_array(frame)? {
+                    Some(array) => {
+                        let component = &array[1..];
+                        let element = match component.strip_prefix('L').and_then(|name| name.strip_suffix(';')) {
+                            Some(name) => name,
+                            None if component.starts_with('[') => component,
+                            None => return Err(self.error(format!("aaload on a primitive array {}", array)))
+                        };
+
+                        self.push(frame, VerificationType::reference(element))?;
+                    },
+                    None => self.push(frame, VerificationType::Null)?
+                }
+            },
+            Opcode::iastore | Opcode::lastore | Opcode::fastore | Opcode::dastore |
+            Opcode::bastore | Opcode::castore | Opcode::sastore => {
+                let (arrays, element) = Self::array_element(opcode).unwrap();
+                self.pop(frame, &element)?;
+                self.pop(frame, &VerificationType::Integer)?;
+                if let Some(array) = self.pop_array(frame)? {
+                    if !arrays.contains(&array.as_str()) {
+                        return Err(self.error(format!("Bad type on operand stack, expected {:?} but found {}", arrays, array)));
+                    }
+                }
+            },
+            Opcode::aastore => {
+                self.pop_reference(frame)?;
+                self.pop(frame, &VerificationType::Integer)?;
+                if let Some(array) = self.pop_array(frame)? {
+                    if !array[1..].starts_with('L') && !array[1..].starts_with('[') {
+                        return Err(self.error(format!("aastore on a primitive array {}", array)));
+                    }
+                }
+            },
+
+            Opcode::pop => { self.take_slots(frame, 1)?; },
+            Opcode::pop2 => { self.take_slots(frame, 2)?; },
+            Opcode::dup => {
+                let a = self.take_slots(frame, 1)?;
+                self.push_slots(frame, &[&a, &a])?;
+            },
+            Opcode::dup_x1 => {
+                let a = self.take_slots(frame, 1)?;
+                let b = self.take_slots(frame, 1)?;
+                self.push_slots(frame, &[&a, &b, &a])?;
+            },
+            Opcode::dup_x2 => {
+                let a = self.take_slots(frame, 1)?;
+                let b = self.take_slots(frame, 2)?;
+                self.push_slots(frame, &[&a, &b, &a])?;
+            },
+            Opcode::dup2 => {
+                let a = self.take_slots(frame, 2)?;
+                self.push_slots(frame, &[&a, &a])?;
+            },
+            Opcode::dup2_x1 => {
+                let a = self.take_slots(frame, 2)?;
+                let b = self.take_slots(frame, 1)?;
+                self.push_slots(frame, &[&a, &b, &a])?;
+            },
+            Opcode::dup2_x2 => {
+                let a = self.take_slots(frame, 2)?;
+                let b = self.take_slots(frame, 2)?;
+                self.push_slots(frame, &[&a, &b, &a])?;
+            },
+            Opcode::swap => {
+                let a = self.take_slots(frame, 1)?;
+                let b = self.take_slots(frame, 1)?;
+                self.push_slots(frame, &[&a, &b])?;
+            },
+
+            Opcode::ireturn => { self.check_return(frame, Some(VerificationType::Integer))?; return Ok(false); },
+            Opcode::lreturn => { self.check_return(frame, Some(VerificationType::Long))?; return Ok(false); },
+            Opcode::freturn => { self.check_return(frame, Some(VerificationType::Float))?; return Ok(false); },
+            Opcode::dreturn => { self.check_return(frame, Some(VerificationType::Double))?; return Ok(false); },
+            Opcode::areturn => { self.check_return(frame, Some(VerificationType::reference(JAVA_LANG_OBJECT)))?; return Ok(false); },
+            Opcode::r#return => { self.check_return(frame, None)?; return Ok(false); },
+
+            Opcode::arraylength => {
+                self.pop_array(frame)?;
+                self.push(frame, VerificationType::Integer)?;
+            },
+            Opcode::athrow => {
+                self.pop_initialized_reference(frame, JAVA_LANG_THROWABLE)?;
+                return Ok(false);
+            },
+            Opcode::monitorenter | Opcode::monitorexit => {
+                self.pop_reference(frame)?;
+            },
+
+            _ => return Err(self.error(format!("Illegal instruction '{}' at {}", opcode, pc)))
+        }
+
+        Ok(true)
+    }
+
+    fn execute_constant_pool(&self, pc: usize, frame: &mut Frame, opcode: Opcode, index: u16) -> Result<(), VerifyError> {
+        match opcode {
+            Opcode::ldc | Opcode::ldc_w => {
+                let verification_type = match self.entry(index)? {
+                    ConstantPoolEntry::Integer(_)           => VerificationType::Integer,
+                    ConstantPoolEntry::Float(_)             => VerificationType::Float,
+                    ConstantPoolEntry::StringReference(_)   => VerificationType::reference("java/lang/String"),
+                    ConstantPoolEntry::ClassReference(_)    => VerificationType::reference("java/lang/Class"),
+                    ConstantPoolEntry::MethodType(_)        => VerificationType::reference("java/lang/invoke/MethodType"),
+                    ConstantPoolEntry::MethodHandle(_, _)   => VerificationType::reference("java/lang/invoke/MethodHandle"),
+                    ConstantPoolEntry::Dynamic(_, name_and_type) => self.field_type(&self.name_and_type(*name_and_type)?.1)?,
+                    _ => return Err(self.error(format!("Invalid constant pool entry {} for ldc", index)))
+                };
+
+                if verification_type.is_category2() {
+                    return Err(self.error("ldc can't load a long or double".to_string()));
+                }
+
+                self.push(frame, verification_type)
+            },
+            Opcode::ldc2_w => {
+                let verification_type = match self.entry(index)? {
+                    ConstantPoolEntry::Long(_, _)           => VerificationType::Long,
+                    ConstantPoolEntry::Double(_, _)         => VerificationType::Double,
+                    ConstantPoolEntry::Dynamic(_, name_and_type) => self.field_type(&self.name_and_type(*name_and_type)?.1)?,
+                    _ => return Err(self.error(format!("Invalid constant pool entry {} for ldc2_w", index)))
+                };
+
+                if !verification_type.is_category2() {
+                    return Err(self.error("ldc2_w can only load a long or double".to_string()));
+                }
+
+                self.push(frame, verification_type)
+            },
+            Opcode::getstatic => {
+                let (_, _, descriptor) = self.member_reference(index, &["Fieldref"])?;
+                let field_type = self.field_type(&descriptor)?;
+                self.push(frame, field_type)
+            },
+            Opcode::putstatic => {
+                let (_, _, descriptor) = self.member_reference(index, &["Fieldref"])?;
+                let field_type = self.field_type(&descriptor)?;
+                self.pop(frame, &field_type)?;
+                Ok(())
+            },
+            Opcode::getfield => {
+                let (class_name, _, descriptor) = self.member_reference(index, &["Fieldref"])?;
+                let field_type = self.field_type(&descriptor)?;
+                self.pop_initialized_reference(frame, &class_name)?;
+                self.push(frame, field_type)
+            },
+            Opcode::putfield => {
+                let (class_name, _, descriptor) = self.member_reference(index, &["Fieldref"])?;
+                let field_type = self.field_type(&descriptor)?;
+                self.pop(frame, &field_type)?;
+
+                // Constructors may assign their own fields before calling super()
+                if frame.stack.last() == Some(&VerificationType::UninitializedThis) && class_name == self.class_name {
+                    self.pop_slot(frame)?;
+                } else {
+                    self.pop_initialized_reference(frame, &class_name)?;
+                }
+                Ok(())
+            },
+            Opcode::invokevirtual | Opcode::invokespecial | Opcode::invokestatic => {
+                let allowed: &[&str] = if opcode == Opcode::invokevirtual { &["Methodref"] } else { &["Methodref", "InterfaceMethodref"] };
+                let (class_name, name, descriptor) = self.member_reference(index, allowed)?;
+                let method_descriptor = self.method_descriptor(&descriptor)?;
+
+                if name == "<clinit>" || (name == "<init>" && opcode != Opcode::invokespecial) {
+                    return Err(self.error(format!("Illegal call to {}", name)));
+                }
+
+                self.pop_arguments(frame, &method_descriptor)?;
+
+                if name == "<init>" {
+                    if method_descriptor.return_type.is_some() {
+                        return Err(self.error("<init> must return void".to_string()));
+                    }
+
+                    let receiver = self.pop_slot(frame)?;
+                    let initialized = match &receiver {
+                        VerificationType::UninitializedThis => {
+                            let super_class = self.class_file.get_class_name(self.class_file.super_class as usize);
+                            if class_name != self.class_name && Some(&class_name) != super_class.as_ref() {
+                                return Err(self.error(format!("Bad <init> method call on {}", class_name)));
+                            }
+
+                            VerificationType::Reference(self.class_name.clone())
+                        },
+                        VerificationType::Uninitialized(new_pc) => {
+                            let new_class = match self.instruction_at(*new_pc as usize) {
+                                Some(Instruction::ConstantPool(Opcode::new, new_index)) => self.class_reference(*new_index)?,
+                                _ => return Err(self.error(format!("Uninitialized type refers to offset {} which isn't a new instruction", new_pc)))
+                            };
+
+                            if new_class != class_name {
+                                return Err(self.error(format!("Call to wrong <init> method, expected {} but found {}", new_class, class_name)));
+                            }
+
+                            VerificationType::Reference(new_class)
+                        },
+                        _ => return Err(self.error(format!("Bad type on operand stack, <init> called on {:?}", receiver)))
+                    };
+
+                    Self::replace_uninitialized(frame, &receiver, &initialized);
+                } else if opcode != Opcode::invokestatic {
+                    self.pop_initialized_reference(frame, &class_name)?;
+                }
+
+                self.push_return(frame, &method_descriptor)
+            },
+            Opcode::new => {
+                let class_name = self.class_reference(index)?;
+                if class_name.starts_with('[') {
+                    return Err(self.error(format!("Illegal use of new with array type {}", class_name)));
+                }
+
+                self.push(frame, VerificationType::Uninitialized(pc as u16))
+            },
+            Opcode::anewarray => {
+                let class_name = self.class_reference(index)?;
+                self.pop(frame, &VerificationType::Integer)?;
+
+                let array = if class_name.starts_with('[') { format!("[{}", class_name) } else { format!("[L{};", class_name) };
+                self.push(frame, VerificationType::Reference(array))
+            },
+            Opcode::checkcast => {
+                let class_name = self.class_reference(index)?;
+                self.pop_reference(frame)?;
+                self.push(frame, VerificationType::Reference(class_name))
+            },
+            Opcode::instanceof => {
+                self.class_reference(index)?;
+                self.pop_reference(frame)?;
+                self.push(frame, VerificationType::Integer)
+            },
+            _ => Err(self.error(format!("Illegal instruction '{}'", opcode)))
+        }
+    }
+
+    fn execute(&self, pc: usize, instruction: &Instruction, mut frame: Frame) -> Result<(Frame, bool), VerifyError> {
+        let mut falls_through = true;
+
+        match instruction {
+            Instruction::Simple(opcode) => falls_through = self.execute_simple(pc, &mut frame, *opcode)?,
+            Instruction::Push(_, _) => self.push(&mut frame, VerificationType::Integer)?,
+            Instruction::Local { opcode, index, .. } => self.execute_local(&mut frame, *opcode, *index as usize)?,
+            Instruction::Increment { index, .. } => {
+                self.load(&frame, *index as usize, Some(&VerificationType::Integer))?;
+            },
+            Instruction::ConstantPool(opcode, index) => self.execute_constant_pool(pc, &mut frame, *opcode, *index)?,
+            Instruction::InvokeInterface { index, count } => {
+                let (class_name, name, descriptor) = self.member_reference(*index, &["InterfaceMethodref"])?;
+                let method_descriptor = self.method_descriptor(&descriptor)?;
+
+                if name.starts_with('<') {
+                    return Err(self.error(format!("Illegal call to {}", name)));
+                }
+                if *count as usize != method_descriptor.parameter_slots() + 1 {
+                    return Err(self.error("Inconsistent args count operand in invokeinterface".to_string()));
+                }
+
+                self.pop_arguments(&mut frame, &method_descriptor)?;
+                self.pop_initialized_reference(&mut frame, &class_name)?;
+                self.push_return(&mut frame, &method_descriptor)?;
+            },
+            Instruction::InvokeDynamic { index } => {
+                let name_and_type = match self.entry(*index)? {
+                    ConstantPoolEntry::InvokeDynamic(_, name_and_type) => *name_and_type,
+                    _ => return Err(self.error(format!("Constant pool index {} isn't an InvokeDynamic entry", index)))
+                };
+
+                let (_, descriptor) = self.name_and_type(name_and_type)?;
+                let method_descriptor = self.method_descriptor(&descriptor)?;
+
+                self.pop_arguments(&mut frame, &method_descriptor)?;
+                self.push_return(&mut frame, &method_descriptor)?;
+            },
+            Instruction::Branch(opcode, _) => {
+                match opcode {
+                    Opcode::ifeq | Opcode::ifne | Opcode::iflt | Opcode::ifge | Opcode::ifgt | Opcode::ifle => {
+                        self.pop(&mut frame, &VerificationType::Integer)?;
+                    },
+                    Opcode::if_icmpeq | Opcode::if_icmpne | Opcode::if_icmplt |
+                    Opcode::if_icmpge | Opcode::if_icmpgt | Opcode::if_icmple => {
+                        self.pop(&mut frame, &VerificationType::Integer)?;
+                        self.pop(&mut frame, &VerificationType::Integer)?;
+                    },
+                    Opcode::if_acmpeq | Opcode::if_acmpne => {
+                        self.pop_reference(&mut frame)?;
+                        self.pop_reference(&mut frame)?;
+                    },
+                    Opcode::ifnull | Opcode::ifnonnull => {
+                        self.pop_reference(&mut frame)?;
+                    },
+                    Opcode::goto | Opcode::goto_w => falls_through = false,
+                    _ => return Err(self.error(format!("Instruction '{}' isn't supported by the type checking verifier", opcode)))
+                }
+            },
+            Instruction::TableSwitch { .. } | Instruction::LookupSwitch { .. } => {
+                if let Instruction::LookupSwitch { pairs, .. } = instruction {
+                    if pairs.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+                        return Err(self.error("Bad lookupswitch instruction, keys aren't sorted".to_string()));
+                    }
+                }
+
+                self.pop(&mut frame, &VerificationType::Integer)?;
+                falls_through = false;
+            },
+            Instruction::NewArray(array_type) => {
+                self.pop(&mut frame, &VerificationType::Integer)?;
+                self.push(&mut frame, VerificationType::Reference(format!("[{}", array_type.descriptor())))?;
+            },
+            Instruction::MultiANewArray { index, dimensions } => {
+                let class_name = self.class_reference(*index)?;
+                if *dimensions == 0 || class_name.chars().take_while(|character| *character == '[').count() < *dimensions as usize {
+                    return Err(self.error(format!("Illegal dimension {} for multianewarray of {}", dimensions, class_name)));
+                }
+
+                for _ in 0..*dimensions {
+                    self.pop(&mut frame, &VerificationType::Integer)?;
+                }
+                self.push(&mut frame, VerificationType::Reference(class_name))?;
+            }
+        }
+
+        Ok((frame, falls_through))
+    }
+
+}
+
+impl fmt::Display for VerifyError {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "VerifyError: {}.{}{}", self.class_name, self.method_name, self.method_descriptor)?;
+
+        if let Some(pc) = self.pc {
+            write!(f, " at pc {}", pc)?;
+        }
+
+        write!(f, ": {}", self.message)
+    }
+
+}
\ No newline at end of file
@@ -1,143 +1,1511 @@
 #![allow(dead_code)]
 
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::time::Instant;
+
 use crate::java;
 use crate::java::class::ConstantPoolEntry;
+use crate::java::console::{Console, ConsoleOutput};
+use crate::java::decoded_code::DecodedCode;
+use crate::java::dispatch::{DispatchStats, DispatchTable};
+use crate::java::access_flags;
+use crate::java::boot::{BootError, BootState};
+use crate::java::descriptor::FieldType;
+use crate::java::format_checker;
+use crate::java::format_checker::ClassFormatError;
+use crate::java::invokedynamic::{CallSite, LambdaProxy};
+#[cfg(feature = "jit")]
+use crate::java::jit::{Jit, JitOptions, JitStats};
+use crate::java::limits::{Limit, Limiter, VmLimits};
+use crate::java::heap::{ArrayData, AtomicField, GcStats, Heap, Object, ObjectData};
+use crate::java::method_handle::injected_fields;
+use crate::java::native::NativeRegistry;
 use crate::java::opcodes::Opcode;
+use crate::java::runtime_class::{ClassId, ClassState, FieldSlot, RuntimeClass};
+use crate::java::thread::Scheduler;
+use crate::java::trace::{TraceOptions, Tracer};
+use crate::java::verifier;
+use crate::java::verifier::{ClassHierarchy, VerifyError, VerifyMode};
 
-#[derive(Clone)]
+/// A value in a local variable, on the operand stack or in a field.
+/// Numbers are stored as their raw bits, `long` and `double` take up a single `Value`.
+/// The layout is fixed to a tag byte followed by the payload, so that compiled code can access frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C, u8)]
 pub enum Value {
     None,
+    /// Heap reference, `0` is `null`
     Reference(u32),
     Integer(u32),
-    Float(u32)
+    Float(u32),
+    Long(u64),
+    Double(u64)
+}
+
+/// An exception propagating through Java and Rust frames
+#[derive(Debug, Clone)]
+pub enum Throwable {
+    /// A `java.lang.Throwable` instance on the heap
+    Object(u32),
+    /// An exception raised by Rust code, it's instantiated once it reaches Java code
+    New { class_name: String, message: Option<String> },
+    /// Stops all threads, it unwinds every frame and can't be caught by Java code
+    Halt(Halt)
+}
+
+/// Why `VirtualMachine::execute` couldn't run a program to its end
+#[derive(Debug, Clone)]
+pub enum ExecutionError {
+    /// The main class wasn't found or has no `main(String[])`, with the message `java` prints
+    MainClass(String),
+    Boot(BootError),
+    /// No thread could make progress, a thread dump was written to `System.err`
+    Deadlock,
+    LimitExceeded(Limit)
+}
+
+/// Why the VM stopped running Java code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    /// No thread could make progress, a thread dump was written to `System.err`
+    Deadlock,
+    /// `System.exit` or `Runtime.halt` was called with a status
+    Exit(i32),
+    /// The program exceeded a limit of `VmLimits`
+    LimitExceeded(Limit)
 }
 
+/// Frames a thread's stack holds by default
+pub const DEFAULT_STACK_DEPTH: usize = 4096;
+
+/// Stack bytes a frame counts as for `-Xss`, so the usual 1M stack holds the default number of frames
+pub const STACK_FRAME_SIZE: usize = 256;
+
 pub struct Scope {
+    pub class: ClassId,
+    pub method: Arc<java::Method>,
+    pub code: Arc<DecodedCode>,
+
+    /// Byte offset of the instruction in the code of the method
     pub program_counter: usize,
 
     pub locals: Vec<Value>,
 
+    /// The operand stack, with room for the maximum depth of the method
     pub stack: Vec<Value>,
-    pub stack_pointer: usize
+
+    /// Object whose monitor a synchronized method enters before its first instruction
+    pub synchronized_on: Option<u32>,
+    /// Whether the monitor of `synchronized_on` was entered, it's exited when the frame is popped
+    pub holds_monitor: bool
+}
+
+/// A method and the instruction it was at when a stack trace was captured
+#[derive(Debug, Clone)]
+pub struct TraceFrame {
+    pub class: ClassId,
+    pub method: Arc<java::Method>,
+    pub program_counter: usize
+}
+
+impl From<&Scope> for TraceFrame {
+
+    fn from(frame: &Scope) -> Self {
+        TraceFrame { class: frame.class, method: frame.method.clone(), program_counter: frame.program_counter }
+    }
+
 }
 
+/// The frames of a thread
 pub struct Executor {
+    pub frames: Vec<Scope>,
+    pub max_stack_depth: usize,
 
+    /// The `java.lang.Thread` object of this thread, created on first use
+    pub thread_object: u32,
+
+    /// Interpreter loops running this thread's frames on the Rust stack
+    pub activations: usize,
+    /// Whether the outermost interpreter loop was started by the scheduler, which it returns to when the thread has to wait
+    pub scheduled: bool,
+    /// Class initializers running on this thread, which isn't preempted while there are any
+    pub initializing: usize
 }
 
-pub struct VirtualMachine {
-    pub main_jar: java::Jar,
+/// Everything the interpreter and native methods operate on
+pub struct VmContext {
+    /// Jars and directories application classes are loaded from, searched in order before the library jars
+    pub class_path: Vec<java::Jar>,
     pub library_jars: Vec<java::Jar>,
 
-    pub curr_class_name: String,
+    pub verify_mode: VerifyMode,
+    /// Whether loaded classes are logged to `System.out`, like `-verbose:class`
+    pub verbose_class: bool,
+
+    /// Set with `-D` or by embedders
+    pub system_properties: HashMap<String, String>,
+
+    pub classes: Vec<RuntimeClass>,
+    pub class_ids: HashMap<String, ClassId>,
+    /// Classes whose super types are being loaded, reaching one of them again means the hierarchy is circular
+    pub loading_classes: HashSet<String>,
+    /// Class files defined at runtime, e.g. proxy classes, with the source they were reported to come from
+    pub defined_classes: HashMap<String, (Arc<java::Class>, String)>,
+    /// Classes by the reference of their `java.lang.Class` object
+    pub class_mirrors: HashMap<u32, ClassId>,
+    /// Interned `java.lang.String` objects by their UTF-16 contents
+    pub interned_strings: HashMap<Vec<u16>, u32>,
+
+    /// Linked `invokedynamic` call sites and resolved dynamic, method type and method handle constants by class and constant pool index
+    pub call_sites: HashMap<(ClassId, u16), Arc<CallSite>>,
+    pub dynamic_constants: HashMap<(ClassId, u16), Value>,
+    pub lambda_proxies: HashMap<ClassId, Arc<LambdaProxy>>,
+    /// Methods resolved `MemberName`s stand for, indexed by `ResolvedMethodName.vmtarget`
+    pub method_targets: Vec<(ClassId, Arc<java::Method>)>,
+    /// Indices into `method_targets` by class and the address of the method
+    pub method_target_indices: HashMap<(ClassId, usize), usize>,
+    /// Appendices of call sites and signature polymorphic calls linked by Java code
+    pub linked_appendices: Vec<u32>,
+
+    pub heap: Heap,
+    pub natives: NativeRegistry,
+
+    /// The frames of the running thread
+    pub executor: Executor,
+    pub scheduler: Scheduler,
+    pub console: Console,
+    /// Values held by Rust code, e.g. the arguments of running native methods, which the garbage collector treats as roots
+    pub native_roots: Vec<Value>,
+    /// Objects handed out through the embedding API by how often, they're roots until released as often
+    pub embedder_roots: HashMap<u32, usize>,
+
+    pub boot_state: BootState,
+    /// Native methods that were called but aren't implemented, reported when booting fails
+    pub missing_natives: Vec<String>,
+
+    /// Stack traces of throwables, captured by `fillInStackTrace` or when the VM raised them
+    pub backtraces: HashMap<u32, Vec<TraceFrame>>,
+    /// Frames running `StackWalker`s haven't fetched yet, by the walker object as the anchor of the walk
+    pub stack_walks: HashMap<u32, Vec<TraceFrame>>,
+
+    /// Cleared references linked through `Reference.discovered`, waiting to be enqueued
+    pub reference_pending_list: Value,
+    /// Unreachable objects whose `finalize()` hasn't run yet
+    pub pending_finalization: Vec<u32>,
+    /// Whether pending references and finalizers are being processed
+    pub processing_references: bool,
+
+    /// Enforces the limits set with `VirtualMachine::set_limits`
+    pub limiter: Limiter,
+    /// How virtual and interface calls were dispatched, see `VirtualMachine::dispatch_stats`
+    pub dispatch_stats: DispatchStats,
+    /// Compiles hot methods, see `VirtualMachine::set_jit`
+    #[cfg(feature = "jit")]
+    pub jit: Jit,
+
+    /// Writes trace events of executed methods, `None` unless tracing was enabled with `VirtualMachine::set_trace`
+    pub tracer: Option<Tracer>,
+
+    pub start_time: Instant
+}
+
+pub struct VirtualMachine {
+    /// Boxed, the context is large and embedders move the VirtualMachine around
+    pub context: Box<VmContext>
+}
+
+impl Value {
+
+    pub fn null() -> Self {
+        Value::Reference(0)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Reference(0))
+    }
+
+    pub fn from_int(value: i32) -> Self {
+        Value::Integer(value as u32)
+    }
+
+    pub fn from_bool(value: bool) -> Self {
+        Value::Integer(value as u32)
+    }
+
+    pub fn from_long(value: i64) -> Self {
+        Value::Long(value as u64)
+    }
+
+    pub fn from_float(value: f32) -> Self {
+        Value::Float(value.to_bits())
+    }
+
+    pub fn from_double(value: f64) -> Self {
+        Value::Double(value.to_bits())
+    }
+
+    pub fn as_int(&self) -> i32 {
+        match self {
+            Value::Integer(value) => *value as i32,
+            _ => 0
+        }
+    }
+
+    pub fn as_long(&self) -> i64 {
+        match self {
+            Value::Long(value) => *value as i64,
+            _ => 0
+        }
+    }
+
+    pub fn as_float(&self) -> f32 {
+        match self {
+            Value::Float(value) => f32::from_bits(*value),
+            _ => 0.0
+        }
+    }
+
+    pub fn as_double(&self) -> f64 {
+        match self {
+            Value::Double(value) => f64::from_bits(*value),
+            _ => 0.0
+        }
+    }
+
+    /// The payload whatever the kind of the value, zero extended
+    pub fn bits(&self) -> u64 {
+        match self {
+            Value::Integer(bits) | Value::Float(bits) | Value::Reference(bits) => *bits as u64,
+            Value::Long(bits) | Value::Double(bits) => *bits,
+            Value::None => 0
+        }
+    }
+
+    pub fn as_reference(&self) -> u32 {
+        match self {
+            Value::Reference(value) => *value,
+            _ => 0
+        }
+    }
+
+    /// Whether this value takes up two local variable slots
+    pub fn is_category2(&self) -> bool {
+        matches!(self, Value::Long(_) | Value::Double(_))
+    }
+
+    /// Initial value of a field or array element of the given type
+    pub fn default_for(descriptor: &str) -> Self {
+        match descriptor.as_bytes().first() {
+            Some(b'J')          => Value::Long(0),
+            Some(b'F')          => Value::Float(0),
+            Some(b'D')          => Value::Double(0),
+            Some(b'L' | b'[')   => Value::null(),
+            _                   => Value::Integer(0)
+        }
+    }
+
+}
+
+impl Halt {
+
+    /// Exit status of the process when the VM stopped for this reason
+    pub fn exit_status(&self) -> i32 {
+        match self {
+            Halt::Deadlock | Halt::LimitExceeded(_) => 1,
+            Halt::Exit(status) => *status
+        }
+    }
+
+}
+
+impl ExecutionError {
+
+    /// The result of a program stopped for a reason, `System.exit` ends it normally
+    fn from_halt(halt: Halt) -> Result<i32, ExecutionError> {
+        match halt {
+            Halt::Exit(status) => Ok(status),
+            Halt::Deadlock => Err(ExecutionError::Deadlock),
+            Halt::LimitExceeded(limit) => Err(ExecutionError::LimitExceeded(limit))
+        }
+    }
+
+    /// Exit status of the process when the program couldn't run or was stopped for this reason
+    pub fn exit_status(&self) -> i32 {
+        1
+    }
+
+}
+
+impl Throwable {
+
+    pub fn new(class_name: &str, message: &str) -> Self {
+        Throwable::New {
+            class_name: class_name.to_string(),
+            message: Some(message.to_string())
+        }
+    }
+
+    pub fn without_message(class_name: &str) -> Self {
+        Throwable::New {
+            class_name: class_name.to_string(),
+            message: None
+        }
+    }
+
+    pub fn null_pointer() -> Self {
+        Self::without_message("java/lang/NullPointerException")
+    }
+
+}
+
+impl From<ClassFormatError> for Throwable {
+
+    fn from(error: ClassFormatError) -> Self {
+        Throwable::new("java/lang/ClassFormatError", &format!("{} in class file {}", error.message, error.class_name))
+    }
+
+}
+
+impl From<VerifyError> for Throwable {
+
+    fn from(error: VerifyError) -> Self {
+        let location = match error.pc {
+            Some(pc) => format!(" at pc {}", pc),
+            None => String::new()
+        };
+
+        Throwable::new("java/lang/VerifyError", &format!("{}.{}{}{}: {}", error.class_name, error.method_name, error.method_descriptor, location, error.message))
+    }
+
+}
+
+impl Scope {
+
+    pub fn new(class: ClassId, method: Arc<java::Method>, code: Arc<DecodedCode>, max_locals: usize, max_stack: usize) -> Self {
+        Scope {
+            class,
+            method,
+            code,
+
+            program_counter: 0,
+
+            locals: vec![Value::None; max_locals],
+
+            stack: Vec::with_capacity(max_stack),
+
+            synchronized_on: None,
+            holds_monitor: false
+        }
+    }
+
+    pub fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    pub fn pop(&mut self) -> Result<Value, Throwable> {
+        self.stack.pop().ok_or_else(|| self.invalid("Operand stack underflow"))
+    }
+
+    /// Value `depth` entries below the top of the operand stack
+    pub fn peek(&self, depth: usize) -> Result<Value, Throwable> {
+        self.stack.len().checked_sub(depth + 1).map(|index| self.stack[index]).ok_or_else(|| self.invalid("Operand stack underflow"))
+    }
+
+    pub fn pop_values(&mut self, count: usize) -> Result<Vec<Value>, Throwable> {
+        let start = self.stack.len().checked_sub(count).ok_or_else(|| self.invalid("Operand stack underflow"))?;
+
+        Ok(self.stack.split_off(start))
+    }
+
+    pub fn load(&self, index: usize) -> Result<Value, Throwable> {
+        self.locals.get(index).copied().ok_or_else(|| self.invalid(&format!("Local variable index {} out of bounds", index)))
+    }
+
+    pub fn store(&mut self, index: usize, value: Value) -> Result<(), Throwable> {
+        let slots = if value.is_category2() { 2 } else { 1 };
+        if index + slots > self.locals.len() {
+            return Err(self.invalid(&format!("Local variable index {} out of bounds", index + slots - 1)));
+        }
+
+        self.locals[index] = value;
+        if slots == 2 {
+            self.locals[index + 1] = Value::None;
+        }
+
+        Ok(())
+    }
+
+    /// Code that wasn't verified went wrong in a way the verifier would have rejected
+    fn invalid(&self, message: &str) -> Throwable {
+        Throwable::new("java/lang/VerifyError", &format!("{} in {}{} at pc {}", message, self.method.name, self.method.descriptor, self.program_counter))
+    }
+
+    pub fn binary_int(&mut self, operation: fn(i32, i32) -> i32) -> Result<(), Throwable> {
+        let right = self.pop()?.as_int();
+        let left = self.pop()?.as_int();
+        self.push(Value::from_int(operation(left, right)));
+
+        Ok(())
+    }
+
+    pub fn binary_long(&mut self, operation: fn(i64, i64) -> i64) -> Result<(), Throwable> {
+        let right = self.pop()?.as_long();
+        let left = self.pop()?.as_long();
+        self.push(Value::from_long(operation(left, right)));
+
+        Ok(())
+    }
+
+    pub fn binary_float(&mut self, operation: fn(f32, f32) -> f32) -> Result<(), Throwable> {
+        let right = self.pop()?.as_float();
+        let left = self.pop()?.as_float();
+        self.push(Value::from_float(operation(left, right)));
+
+        Ok(())
+    }
+
+    pub fn binary_double(&mut self, operation: fn(f64, f64) -> f64) -> Result<(), Throwable> {
+        let right = self.pop()?.as_double();
+        let left = self.pop()?.as_double();
+        self.push(Value::from_double(operation(left, right)));
+
+        Ok(())
+    }
 
-    executor: Executor
 }
 
 impl Executor {
 
-    fn get_constant_pool_entry<'a>(&self, class: &'a java::Class, index: usize) -> Option<&'a ConstantPoolEntry> {
-        Some(&class.class_file.constant_pool[(index - 1) as usize])
+    pub fn new() -> Self {
+        Executor {
+            frames: vec![],
+            max_stack_depth: DEFAULT_STACK_DEPTH,
+
+            thread_object: 0,
+
+            activations: 0,
+            scheduled: false,
+            initializing: 0
+        }
+    }
+
+}
+
+impl Default for Executor {
+
+    fn default() -> Self {
+        Self::new()
+    }
+
+}
+
+impl Default for VmContext {
+
+    fn default() -> Self {
+        Self::new()
+    }
+
+}
+
+impl VmContext {
+
+    pub fn new() -> Self {
+        VmContext {
+            class_path: vec![],
+            library_jars: vec![],
+
+            verify_mode: VerifyMode::Remote,
+            verbose_class: false,
+
+            system_properties: HashMap::new(),
+
+            classes: vec![],
+            class_ids: HashMap::new(),
+            loading_classes: HashSet::new(),
+            defined_classes: HashMap::new(),
+            class_mirrors: HashMap::new(),
+            interned_strings: HashMap::new(),
+
+            call_sites: HashMap::new(),
+            dynamic_constants: HashMap::new(),
+            lambda_proxies: HashMap::new(),
+            method_targets: vec![],
+            method_target_indices: HashMap::new(),
+            linked_appendices: vec![],
+
+            heap: Heap::new(),
+            natives: NativeRegistry::new(),
+
+            executor: Executor::new(),
+            scheduler: Scheduler::new(),
+            console: Console::new(),
+            native_roots: vec![],
+            embedder_roots: HashMap::new(),
+
+            boot_state: BootState::NotBooted,
+            missing_natives: Vec::new(),
+
+            backtraces: HashMap::new(),
+            stack_walks: HashMap::new(),
+
+            reference_pending_list: Value::null(),
+            pending_finalization: vec![],
+            processing_references: false,
+
+            limiter: Limiter::new(),
+            dispatch_stats: DispatchStats::default(),
+            #[cfg(feature = "jit")]
+            jit: Jit::new(),
+
+            tracer: None,
+
+            start_time: Instant::now()
+        }
+    }
+
+    pub fn find_class(&self, class_name: &str) -> Option<&java::Class> {
+        self.find_class_file(class_name).map(|(class, _, _)| class.as_ref())
+    }
+
+    /// Looks up a class among the classes defined at runtime, on the class path and then in the library jars,
+    /// also returns where it was found and whether it came from a library
+    fn find_class_file(&self, class_name: &str) -> Option<(&Arc<java::Class>, &str, bool)> {
+        if let Some((class, source)) = self.defined_classes.get(class_name) {
+            return Some((class, source, false));
+        }
+
+        let file_name = format!("{}.class", class_name);
+
+        if let Some((class, jar)) = self.class_path.iter().find_map(|jar| jar.classes.get(&file_name).map(|class| (class, jar))) {
+            return Some((class, &jar.name, false));
+        }
+
+        self.library_jars.iter().find_map(|jar| jar.classes.get(&file_name).map(|class| (class, jar.name.as_str(), true)))
+    }
+
+    /// Why the class file of a class couldn't be parsed, if one was found on the class path but not parsed
+    fn find_unparsable_class_file(&self, class_name: &str) -> Option<&ClassFormatError> {
+        let file_name = format!("{}.class", class_name);
+        self.class_path.iter().chain(&self.library_jars).find_map(|jar| jar.unparsable_classes.get(&file_name))
+    }
+
+    pub fn check_class_format(&self, class: &java::Class) -> Result<(), ClassFormatError> {
+        format_checker::check_class_format(&class.class_file)
+    }
+
+    pub fn verify_class(&self, class: &java::Class, from_library: bool) -> Result<(), VerifyError> {
+        match self.verify_mode {
+            VerifyMode::None => Ok(()),
+            VerifyMode::Remote if from_library => Ok(()),
+            _ => verifier::verify_class(&class.class_file, self)
+        }
+    }
+
+    pub fn class_id(&self, class_name: &str) -> Option<ClassId> {
+        self.class_ids.get(class_name).copied()
+    }
+
+    pub fn class(&self, class_id: ClassId) -> &RuntimeClass {
+        &self.classes[class_id as usize]
+    }
+
+    /// Loads and links a class and its super types, array classes are created on demand
+    pub fn load_class(&mut self, class_name: &str) -> Result<ClassId, Throwable> {
+        if let Some(class_id) = self.class_id(class_name) {
+            return Ok(class_id);
+        }
+
+        if class_name.starts_with('[') {
+            return self.load_array_class(class_name);
+        }
+
+        let (class, source, from_library) = match self.find_class_file(class_name) {
+            Some((class, source, from_library)) => (class.clone(), source.to_string(), from_library),
+            None => return Err(match self.find_unparsable_class_file(class_name) {
+                Some(error) => error.clone().into(),
+                None => Throwable::new("java/lang/NoClassDefFoundError", class_name)
+            })
+        };
+
+        self.check_class_format(&class)?;
+        self.verify_class(&class, from_library)?;
+
+        if !self.loading_classes.insert(class_name.to_string()) {
+            return Err(Throwable::new("java/lang/ClassCircularityError", class_name));
+        }
+
+        let super_types = self.load_super_types(&class);
+        self.loading_classes.remove(class_name);
+        let (super_class, interfaces) = super_types?;
+
+        let id = self.classes.len() as ClassId;
+
+        let mut instance_fields = match super_class {
+            Some(super_class) => self.class(super_class).instance_fields.clone(),
+            None => vec![]
+        };
+
+        // java.lang.Object's finalize() is empty, so only overriding it with actual code makes a class finalizable
+        let has_finalizer = match class.find_method("finalize", "()V") {
+            Some(finalize) => class_name != "java/lang/Object" && finalize.code().is_some_and(|code| code.code != [Opcode::r#return as u8]),
+            None => super_class.is_some_and(|super_class| self.class(super_class).has_finalizer)
+        };
+
+        let mut static_values = HashMap::new();
+        for field_info in &class.class_file.field_table {
+            let name = class.class_file.get_constant_pool_string(field_info.name_index as usize).unwrap_or_default();
+            let descriptor = class.class_file.get_constant_pool_string(field_info.descriptor_index as usize).unwrap_or_default();
+
+            if field_info.access_flags & access_flags::ACC_STATIC != 0 {
+                static_values.insert(name, Value::default_for(&descriptor));
+            } else {
+                instance_fields.push(FieldSlot { declaring_class: id, name, descriptor });
+            }
+        }
+
+        for (name, descriptor) in injected_fields(class_name) {
+            instance_fields.push(FieldSlot { declaring_class: id, name: name.to_string(), descriptor: descriptor.to_string() });
+        }
+
+        let decoded_code = self.decode_methods(&class);
+
+        self.classes.push(RuntimeClass {
+            id,
+            name: class_name.to_string(),
+
+            access_flags: class.class_file.access_flags,
+            class: Some(class),
+            decoded_code,
+            dispatch: DispatchTable::default(),
+
+            super_class,
+            interfaces,
+
+            component_type: None,
+
+            instance_fields,
+            static_values,
+
+            mirror: 0,
+
+            has_finalizer,
+
+            state: ClassState::Linked
+        });
+        self.class_ids.insert(class_name.to_string(), id);
+        self.link_dispatch_table(id);
+
+        if self.verbose_class {
+            let message = format!("[class,load] {} source: {}\n", class_name.replace('/', "."), source);
+            self.console.out.write(message.as_bytes());
+        }
+
+        Ok(id)
+    }
+
+    fn load_super_types(&mut self, class: &java::Class) -> Result<(Option<ClassId>, Vec<ClassId>), Throwable> {
+        let super_class = match class.super_class_name() {
+            Some(super_class_name) => Some(self.load_class(&super_class_name)?),
+            None => None
+        };
+
+        let mut interfaces = vec![];
+        for interface_name in class.interface_names() {
+            interfaces.push(self.load_class(&interface_name)?);
+        }
+
+        Ok((super_class, interfaces))
+    }
+
+    /// Defines a class from the bytes of its class file like `ClassLoader.defineClass`, `class_name` is the name it's expected to have
+    pub fn define_class(&mut self, class_name: Option<&str>, bytes: &[u8], source: &str) -> Result<ClassId, Throwable> {
+        let class = java::Class::new(&bytes.to_vec()).map_err(|error| ClassFormatError::unparsable(class_name.unwrap_or("<unknown>"), &error))?;
+        let name = class.name();
+
+        if let Some(class_name) = class_name.filter(|class_name| *class_name != name) {
+            return Err(Throwable::new("java/lang/NoClassDefFoundError", &format!("{} (wrong name: {})", class_name, name)));
+        }
+
+        if self.class_id(&name).is_some() || self.find_class_file(&name).is_some() {
+            return Err(Throwable::new("java/lang/LinkageError", &format!("duplicate class definition for name: \"{}\"", name)));
+        }
+
+        self.defined_classes.insert(name.clone(), (Arc::new(class), source.to_string()));
+
+        let result = self.load_class(&name);
+        if result.is_err() {
+            self.defined_classes.remove(&name);
+        }
+
+        result
+    }
+
+    fn load_array_class(&mut self, class_name: &str) -> Result<ClassId, Throwable> {
+        let component_type = match FieldType::parse(class_name) {
+            Some(FieldType::Array(component_type)) => *component_type,
+            _ => return Err(Throwable::new("java/lang/NoClassDefFoundError", class_name))
+        };
+
+        if component_type.is_reference() {
+            self.load_class(&component_type.class_name())?;
+        }
+
+        let super_class = self.load_class("java/lang/Object").ok();
+        let interfaces = ["java/lang/Cloneable", "java/io/Serializable"].iter()
+            .filter_map(|interface_name| self.load_class(interface_name).ok())
+            .collect();
+
+        let id = self.classes.len() as ClassId;
+        self.classes.push(RuntimeClass {
+            id,
+            name: class_name.to_string(),
+
+            class: None,
+            access_flags: access_flags::ACC_PUBLIC | access_flags::ACC_FINAL | access_flags::ACC_ABSTRACT,
+
+            decoded_code: HashMap::new(),
+            dispatch: DispatchTable::default(),
+
+            super_class,
+            interfaces,
+
+            component_type: Some(component_type),
+
+            instance_fields: vec![],
+            static_values: HashMap::new(),
+
+            mirror: 0,
+
+            has_finalizer: false,
+
+            state: ClassState::Initialized
+        });
+        self.class_ids.insert(class_name.to_string(), id);
+        self.link_dispatch_table(id);
+
+        Ok(id)
+    }
+
+    /// Class of values of a field type, which is a primitive class for primitive types
+    pub fn load_type_class(&mut self, field_type: &FieldType) -> Result<ClassId, Throwable> {
+        match field_type {
+            FieldType::Object(class_name) => self.load_class(class_name),
+            FieldType::Array(_) => self.load_class(&field_type.descriptor()),
+            primitive => self.load_primitive_class(&primitive.to_string())
+        }
+    }
+
+    /// Field type of values of a class, `None` for `void`
+    pub fn class_field_type(&self, class_id: ClassId) -> Option<FieldType> {
+        let class = self.class(class_id);
+
+        match class.name.as_str() {
+            "boolean"   => Some(FieldType::Boolean),
+            "byte"      => Some(FieldType::Byte),
+            "char"      => Some(FieldType::Char),
+            "short"     => Some(FieldType::Short),
+            "int"       => Some(FieldType::Int),
+            "long"      => Some(FieldType::Long),
+            "float"     => Some(FieldType::Float),
+            "double"    => Some(FieldType::Double),
+            "void"      => None,
+            name if class.is_array() => FieldType::parse(name),
+            name => Some(FieldType::Object(name.to_string()))
+        }
+    }
+
+    /// Class of a primitive type or `void` by its Java name, e.g. `int`, as returned by `Class.getPrimitiveClass`
+    pub fn load_primitive_class(&mut self, name: &str) -> Result<ClassId, Throwable> {
+        if !["boolean", "byte", "char", "short", "int", "long", "float", "double", "void"].contains(&name) {
+            return Err(Throwable::new("java/lang/ClassNotFoundException", name));
+        }
+
+        if let Some(class_id) = self.class_id(name) {
+            return Ok(class_id);
+        }
+
+        let id = self.classes.len() as ClassId;
+        self.classes.push(RuntimeClass {
+            id,
+            name: name.to_string(),
+
+            class: None,
+            access_flags: access_flags::ACC_PUBLIC | access_flags::ACC_FINAL | access_flags::ACC_ABSTRACT,
+
+            decoded_code: HashMap::new(),
+            dispatch: DispatchTable::default(),
+
+            super_class: None,
+            interfaces: vec![],
+
+            component_type: None,
+
+            instance_fields: vec![],
+            static_values: HashMap::new(),
+
+            mirror: 0,
+
+            has_finalizer: false,
+
+            state: ClassState::Initialized
+        });
+        self.class_ids.insert(name.to_string(), id);
+
+        Ok(id)
+    }
+
+    /// Runs the static initializer of a class and its super classes, if that hasn't happened yet
+    pub fn initialize_class(&mut self, class_id: ClassId) -> Result<(), Throwable> {
+        match self.class(class_id).state {
+            ClassState::Initialized | ClassState::Initializing => return Ok(()),
+            ClassState::Erroneous => return Err(Throwable::new("java/lang/NoClassDefFoundError", &format!("Could not initialize class {}", self.class(class_id).java_name()))),
+            ClassState::Linked => { }
+        }
+
+        self.classes[class_id as usize].state = ClassState::Initializing;
+
+        if let Some(super_class) = self.class(class_id).super_class {
+            if let Err(throwable) = self.initialize_class(super_class) {
+                self.classes[class_id as usize].state = ClassState::Erroneous;
+                return Err(throwable);
+            }
+        }
+
+        if let Err(throwable) = self.apply_constant_values(class_id) {
+            self.classes[class_id as usize].state = ClassState::Erroneous;
+            return Err(throwable);
+        }
+
+        if let Some(initializer) = self.class(class_id).find_method("<clinit>", "()V").cloned() {
+            self.executor.initializing += 1;
+            let result = self.invoke(class_id, initializer, &[]);
+            self.executor.initializing -= 1;
+
+            if let Err(throwable) = result {
+                self.classes[class_id as usize].state = ClassState::Erroneous;
+                return Err(throwable);
+            }
+        }
+
+        self.classes[class_id as usize].state = ClassState::Initialized;
+
+        match self.class(class_id).name.as_str() {
+            // Once booted, System.initPhase1 sets up the system class
+            "java/lang/System" if self.boot_state == BootState::NotBooted => self.initialize_system(class_id)?,
+            "jdk/internal/misc/UnsafeConstants" => self.inject_unsafe_constants(class_id),
+            "jdk/internal/reflect/ReflectionFactory" => self.disable_accessor_generation(class_id),
+            _ => { }
+        }
+
+        Ok(())
+    }
+
+    /// Sets static fields with a ConstantValue attribute
+    fn apply_constant_values(&mut self, class_id: ClassId) -> Result<(), Throwable> {
+        let class = match &self.class(class_id).class {
+            Some(class) => class.clone(),
+            None => return Ok(())
+        };
+
+        for field in class.fields.values().filter(|field| field.is_static()) {
+            let index = match field.constant_value_index() {
+                Some(index) => index,
+                None => continue
+            };
+
+            let value = match self.constant_value(&class, index) {
+                Some(value) => value,
+                None => match self.constant_pool_string(&class, index)? {
+                    Some(value) => value,
+                    None => continue
+                }
+            };
+
+            self.classes[class_id as usize].static_values.insert(field.name.clone(), value);
+        }
+
+        Ok(())
+    }
+
+    /// Numeric constant pool entry as a value
+    pub fn constant_value(&self, class: &java::Class, index: u16) -> Option<Value> {
+        match class.class_file.constant_pool.get((index as usize).checked_sub(1)?)? {
+            ConstantPoolEntry::Integer(value)       => Some(Value::Integer(*value)),
+            ConstantPoolEntry::Float(value)         => Some(Value::Float(*value)),
+            ConstantPoolEntry::Long(high, low)      => Some(Value::Long(((*high as u64) << 32) | *low as u64)),
+            ConstantPoolEntry::Double(high, low)    => Some(Value::Double(((*high as u64) << 32) | *low as u64)),
+            _ => None
+        }
+    }
+
+    /// Whether `class_id` is `target` or one of its subclasses or implementations
+    pub fn is_subclass_of(&self, class_id: ClassId, target: ClassId) -> bool {
+        if class_id == target {
+            return true;
+        }
+
+        let class = self.class(class_id);
+        let target_class = self.class(target);
+
+        if let (Some(component_type), Some(target_component_type)) = (&class.component_type, &target_class.component_type) {
+            if !component_type.is_reference() || !target_component_type.is_reference() {
+                return component_type == target_component_type;
+            }
+
+            return match (self.class_id(&component_type.class_name()), self.class_id(&target_component_type.class_name())) {
+                (Some(component), Some(target_component)) => self.is_subclass_of(component, target_component),
+                _ => false
+            };
+        }
+
+        if let Some(super_class) = class.super_class {
+            if self.is_subclass_of(super_class, target) {
+                return true;
+            }
+        }
+
+        target_class.is_interface() && class.interfaces.iter().any(|interface| self.is_subclass_of(*interface, target))
+    }
+
+    pub fn is_instance_of(&self, value: Value, target: ClassId) -> Result<bool, Throwable> {
+        if value.is_null() {
+            return Ok(false);
+        }
+
+        Ok(self.is_subclass_of(self.object(value)?.class, target))
+    }
+
+    /// Finds the class declaring a field, searching super interfaces and super classes (JVMS 5.4.3.2)
+    pub fn resolve_field(&self, class_id: ClassId, name: &str, descriptor: &str) -> Option<ClassId> {
+        let class = self.class(class_id);
+        if class.find_field(name, descriptor).is_some() {
+            return Some(class_id);
+        }
+
+        for interface in &class.interfaces {
+            if let Some(declaring_class) = self.resolve_field(*interface, name, descriptor) {
+                return Some(declaring_class);
+            }
+        }
+
+        self.resolve_field(class.super_class?, name, descriptor)
     }
 
-    fn execute_byte_code(&self, class: &java::Class, byte_code: &Vec<u8>, scope: &mut Scope) {
-        let option = |index: usize| -> u32 { *byte_code.get(index).unwrap() as u32 };
+    /// Finds a method in a class, its super classes and then its super interfaces (JVMS 5.4.3.3)
+    pub fn resolve_method(&self, class_id: ClassId, name: &str, descriptor: &str) -> Option<(ClassId, Arc<java::Method>)> {
+        let mut current = Some(class_id);
+        while let Some(class_id) = current {
+            let class = self.class(class_id);
+            if let Some(method) = class.find_method(name, descriptor) {
+                return Some((class_id, method.clone()));
+            }
+
+            current = class.super_class;
+        }
 
-        while scope.program_counter < byte_code.len() {
-            let value = option(scope.program_counter) as u8;
-            let opcode : Opcode = unsafe { std::mem::transmute(value) };
+        self.resolve_interface_method(class_id, name, descriptor)
+    }
 
-            println!("    0x{:02X} {}", value, opcode);
+    /// Searches the super interfaces of a class, preferring default methods over abstract ones
+    fn resolve_interface_method(&self, class_id: ClassId, name: &str, descriptor: &str) -> Option<(ClassId, Arc<java::Method>)> {
+        let mut abstract_method = None;
 
-            match opcode {
-                Opcode::getstatic => {
-                    let index = option(scope.program_counter + 1) << 8 | option(scope.program_counter + 2);
-                    if let Some(ConstantPoolEntry::FieldReference(class_index, name_and_type_index)) = self.get_constant_pool_entry(class, (index) as usize) {
-                        if let Some(ConstantPoolEntry::ClassReference(name_index)) = self.get_constant_pool_entry(class, *class_index as usize) {
-                            println!("{}", class.class_file.get_constant_pool_string(*name_index as usize).unwrap());
-                        }
-                        if let Some(ConstantPoolEntry::NameAndTypeDescriptor(name_index, type_index)) = self.get_constant_pool_entry(class, *name_and_type_index as usize) {
-                            println!("{}", class.class_file.get_constant_pool_string(*name_index as usize).unwrap());
-                            println!("{}", class.class_file.get_constant_pool_string(*type_index as usize).unwrap());
-                        }
-                    } else {
-                        println!("Invalid constant pool entry!");
+        let mut current = Some(class_id);
+        while let Some(class_id) = current {
+            for interface in &self.class(class_id).interfaces {
+                if let Some(method) = self.class(*interface).find_method(name, descriptor) {
+                    if !method.is_abstract() && !method.is_static() && !method.is_private() {
+                        return Some((*interface, method.clone()));
                     }
-                },
-                Opcode::nop => { },
-                _ => {}//panic!("Invalid opcode!")
+
+                    abstract_method.get_or_insert((*interface, method.clone()));
+                }
+
+                match self.resolve_interface_method(*interface, name, descriptor) {
+                    Some((declaring_class, method)) if !method.is_abstract() => return Some((declaring_class, method)),
+                    Some(found) => { abstract_method.get_or_insert(found); },
+                    None => { }
+                }
             }
 
-            scope.program_counter += opcode.instruction_length() + 1;
+            current = self.class(class_id).super_class;
         }
+
+        abstract_method
     }
 
-    pub fn execute_method(&self, class : &java::Class, method: &java::Method) {
-        println!("Executing method '{}' [ {} ]", method.name, method.descriptor);
-        for attribute in &method.attributes {
-            if let java::Attribute::Code(code_attribute) = attribute {
-                println!("  Stack Size:  {}", code_attribute.max_stack);
-                println!("  Locals Size: {}", code_attribute.max_locals);
+    /// Selects the method invoked by invokevirtual or invokeinterface on an object of class `class_id` (JVMS 5.4.6)
+    pub fn select_method(&self, class_id: ClassId, name: &str, descriptor: &str) -> Option<(ClassId, Arc<java::Method>)> {
+        let mut current = Some(class_id);
+        while let Some(class_id) = current {
+            let class = self.class(class_id);
+            if let Some(method) = class.find_method(name, descriptor) {
+                if !method.is_static() {
+                    return Some((class_id, method.clone()));
+                }
+            }
+
+            current = class.super_class;
+        }
+
+        self.resolve_interface_method(class_id, name, descriptor)
+    }
+
+    /// The `java.lang.Class` object of a class, created on first use
+    pub fn class_mirror(&mut self, class_id: ClassId) -> Result<Value, Throwable> {
+        let mirror = self.class(class_id).mirror;
+        if mirror != 0 {
+            return Ok(Value::Reference(mirror));
+        }
+
+        let class_class = self.load_class("java/lang/Class")?;
+        let mirror = self.new_object(class_class).as_reference();
 
-                let mut scope = Scope {
-                    program_counter: 0,
+        self.classes[class_id as usize].mirror = mirror;
+        self.class_mirrors.insert(mirror, class_id);
 
-                    locals: vec![Value::None; code_attribute.max_locals as usize],
+        if let Some(component_type) = self.class(class_id).component_type.clone() {
+            let component_class = self.load_type_class(&component_type)?;
+            let component_mirror = self.class_mirror(component_class)?;
+
+            self.set_field(Value::Reference(mirror), "java/lang/Class", "componentType", component_mirror)?;
+        }
+
+        // All classes are in the unnamed module of the boot loader once it exists, see `BootLoader.setBootLoaderUnnamedModule0`
+        let unnamed_module = self.class_id("jdk/internal/loader/BootLoader")
+            .and_then(|boot_loader| self.class(boot_loader).static_values.get("UNNAMED_MODULE").copied())
+            .filter(|module| !module.is_null());
+        if let Some(unnamed_module) = unnamed_module {
+            self.set_field(Value::Reference(mirror), "java/lang/Class", "module", unnamed_module)?;
+        }
+
+        Ok(Value::Reference(mirror))
+    }
+
+    /// The class represented by a `java.lang.Class` object
+    pub fn mirror_class(&self, mirror: Value) -> Option<ClassId> {
+        self.class_mirrors.get(&mirror.as_reference()).copied()
+    }
+
+    pub fn new_object(&mut self, class_id: ClassId) -> Value {
+        let fields = self.class(class_id).instance_fields.iter()
+            .map(|slot| AtomicField::new(Value::default_for(&slot.descriptor)))
+            .collect();
+
+        let reference = self.heap.allocate(Object { class: class_id, data: ObjectData::Instance(fields) });
+        if self.class(class_id).has_finalizer {
+            self.heap.register_finalizer(reference);
+        }
+
+        Value::Reference(reference)
+    }
+
+    /// Allocates an object of a class and runs the constructor with the given descriptor on it
+    pub fn construct(&mut self, class_name: &str, descriptor: &str, args: &[Value]) -> Result<Value, Throwable> {
+        let class_id = self.load_class(class_name)?;
+
+        self.instantiate(class_id, descriptor, args)
+    }
+
+    pub fn instantiate(&mut self, class_id: ClassId, descriptor: &str, args: &[Value]) -> Result<Value, Throwable> {
+        let class = self.class(class_id);
+        if class.is_interface() || class.is_abstract() || class.is_array() {
+            return Err(Throwable::new("java/lang/InstantiationException", &class.java_name()));
+        }
+
+        self.initialize_class(class_id)?;
+
+        let constructor = self.class(class_id).find_method("<init>", descriptor).cloned()
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchMethodError", &format!("{}.<init>{}", self.class(class_id).name, descriptor)))?;
+
+        let object = self.new_object(class_id);
+        let args: Vec<Value> = std::iter::once(object).chain(args.iter().copied()).collect();
+        self.with_roots(&args, |context| context.invoke(class_id, constructor, &args))?;
+
+        Ok(object)
+    }
 
-                    stack: vec![Value::None; code_attribute.max_stack as usize],
-                    stack_pointer: 0
+    pub fn new_array(&mut self, component_type: &FieldType, length: i32) -> Result<Value, Throwable> {
+        if length < 0 {
+            return Err(Throwable::new("java/lang/NegativeArraySizeException", &length.to_string()));
+        }
+
+        let class_id = self.load_class(&format!("[{}", component_type.descriptor()))?;
+        if !self.heap.has_space(ArrayData::object_size(component_type, length as usize)) {
+            return Err(self.heap_exhausted());
+        }
+
+        let data = ObjectData::Array(ArrayData::new(component_type, length as usize));
+
+        Ok(Value::Reference(self.heap.allocate(Object { class: class_id, data })))
+    }
+
+    pub fn object(&self, value: Value) -> Result<&Object, Throwable> {
+        self.heap.get(value.as_reference()).ok_or_else(Throwable::null_pointer)
+    }
+
+    pub fn object_mut(&mut self, value: Value) -> Result<&mut Object, Throwable> {
+        self.heap.get_mut(value.as_reference()).ok_or_else(Throwable::null_pointer)
+    }
+
+    pub fn array_length(&self, value: Value) -> Result<usize, Throwable> {
+        self.object(value)?.array().map(|array| array.len())
+            .ok_or_else(|| Throwable::new("java/lang/IncompatibleClassChangeError", "Not an array"))
+    }
+
+    /// Index into the field values of instances of `class_name` for a field it or a super class declares
+    pub fn field_slot(&mut self, class_name: &str, name: &str) -> Result<usize, Throwable> {
+        let class_id = self.load_class(class_name)?;
+
+        self.class(class_id).instance_fields.iter().rposition(|slot| slot.name == name)
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchFieldError", name))
+    }
+
+    pub fn get_field(&mut self, object: Value, class_name: &str, name: &str) -> Result<Value, Throwable> {
+        let slot = self.field_slot(class_name, name)?;
+
+        self.object(object)?.field(slot).ok_or_else(|| Throwable::new("java/lang/NoSuchFieldError", name))
+    }
+
+    pub fn set_field(&mut self, object: Value, class_name: &str, name: &str, value: Value) -> Result<(), Throwable> {
+        let slot = self.field_slot(class_name, name)?;
+
+        match self.object(object)?.set_field(slot, value) {
+            true => Ok(()),
+            false => Err(Throwable::new("java/lang/NoSuchFieldError", name))
+        }
+    }
+
+    /// Turns an exception raised from Rust into a Java object, if its class can be loaded
+    pub fn throwable_object(&mut self, throwable: Throwable) -> Throwable {
+        let class_name = match &throwable {
+            Throwable::New { class_name, .. } => class_name.clone(),
+            Throwable::Object(_) | Throwable::Halt(_) => return throwable
+        };
+
+        let message = match &throwable {
+            Throwable::New { message: Some(message), .. } => Some(message.clone()),
+            _ => None
+        };
+
+        let object = match self.load_class(&class_name) {
+            Ok(class_id) => self.new_object(class_id),
+            Err(_) => return throwable
+        };
+
+        self.fill_in_stack_trace(object.as_reference());
+
+        if let Some(message) = message {
+            self.with_roots(&[object], |context| {
+                if let Ok(message) = context.new_string(&message) {
+                    let _ = context.set_field(object, "java/lang/Throwable", "detailMessage", message);
+                }
+            });
+        }
+
+        Throwable::Object(object.as_reference())
+    }
+
+    /// Captures the frames of the running thread as the stack trace of a throwable, leaving out the ones creating it
+    pub fn fill_in_stack_trace(&mut self, throwable: u32) {
+        let throwable_class = match self.heap.get(throwable) {
+            Some(object) => object.class,
+            None => return
+        };
+
+        let frames = self.executor.frames.iter().rev()
+            .skip_while(|frame| frame.method.name == "fillInStackTrace" || (frame.method.name == "<init>" && self.is_subclass_of(throwable_class, frame.class)))
+            .map(TraceFrame::from)
+            .collect::<Vec<_>>();
+
+        let depth = frames.len();
+        self.backtraces.insert(throwable, frames);
+
+        // `Throwable.getOurStackTrace` only asks for the frames once there's a backtrace, it just marks that they're kept here
+        let _ = self.set_field(Value::Reference(throwable), "java/lang/Throwable", "backtrace", Value::Reference(throwable));
+        let _ = self.set_field(Value::Reference(throwable), "java/lang/Throwable", "depth", Value::from_int(depth as i32));
+    }
+
+    /// `detailMessage` of a throwable object
+    pub fn throwable_message(&self, throwable: Value) -> Option<String> {
+        let object = self.object(throwable).ok()?;
+        let throwable_class = *self.class_ids.get("java/lang/Throwable")?;
+        let slot = self.class(object.class).field_slot(throwable_class, "detailMessage")?;
+
+        let message = object.field(slot)?;
+        if message.is_null() {
+            return None;
+        }
+
+        self.rust_string(message).ok()
+    }
+
+    pub fn describe_throwable(&self, throwable: &Throwable) -> String {
+        match throwable {
+            Throwable::Object(reference) => {
+                let class_name = match self.heap.get(*reference) {
+                    Some(object) => self.class(object.class).java_name(),
+                    None => return "null".to_string()
                 };
 
-                self.execute_byte_code(class, &code_attribute.code, &mut scope);
+                match self.throwable_message(Value::Reference(*reference)) {
+                    Some(message) => format!("{}: {}", class_name, message),
+                    None => class_name
+                }
+            },
+            _ => throwable.to_string()
+        }
+    }
+
+    /// Initializes a class and invokes its `main(String[])` method with the given arguments
+    pub fn run_main(&mut self, class_name: &str, args: &[String]) -> Result<(), Throwable> {
+        let class_id = self.load_class(class_name)?;
+        self.initialize_class(class_id)?;
 
-                return;
-            }
+        let method = self.class(class_id).find_method("main", "([Ljava/lang/String;)V").cloned()
+            .filter(|method| method.is_static())
+            .ok_or_else(|| Throwable::new("java/lang/NoSuchMethodError", "main"))?;
+
+        let args: Vec<Option<String>> = args.iter().cloned().map(Some).collect();
+        let array = self.new_string_array(&args)?;
+
+        self.invoke(class_id, method, &[array])?;
+
+        Ok(())
+    }
+
+    /// Runs the shutdown hooks like the launcher does once the program is done, if it registered any
+    pub fn shutdown(&mut self) -> Result<(), Throwable> {
+        let shutdown_class = match self.class_id("java/lang/Shutdown") {
+            Some(class_id) if self.class(class_id).state == ClassState::Initialized => class_id,
+            _ => return Ok(())
+        };
+
+        match self.class(shutdown_class).find_method("shutdown", "()V").cloned() {
+            Some(method) => self.invoke(shutdown_class, method, &[]).map(|_| ()),
+            None => Ok(())
         }
+    }
 
-        println!("Method '{}' does not have a Code attribute!", method.name);
+}
+
+impl ClassHierarchy for VmContext {
+
+    fn lookup(&self, class_name: &str) -> Option<(Option<String>, bool)> {
+        let class = self.find_class(class_name)?;
+
+        Some((class.super_class_name(), class.class_file.access_flags & access_flags::ACC_INTERFACE != 0))
     }
 
 }
 
 impl VirtualMachine {
 
-    pub fn new(jar: java::Jar) -> Option<Self> {
-        if let Some((name, _)) = jar.get_main_class() {
-            return Some(VirtualMachine {
-                main_jar: jar,
-                library_jars: vec![],
-                curr_class_name: name,
-                executor: Executor { }
-            });
-        } else {
-            println!("Cannot find main class!");
+    pub fn new() -> Self {
+        VirtualMachine {
+            context: Box::new(VmContext::new())
         }
+    }
 
-        None
+    /// Adds a jar or directory to the end of the class path
+    pub fn add_class_path(&mut self, jar: java::Jar) {
+        self.context.class_path.push(jar);
     }
 
+    /// Adds a jar classes are loaded from if they aren't on the class path, like `java.base`. Library classes
+    /// are trusted and not verified by default.
     pub fn add_library_jar(&mut self, jar: java::Jar) {
-        self.library_jars.push(jar);
+        self.context.library_jars.push(jar);
+    }
+
+    pub fn set_verify_mode(&mut self, verify_mode: VerifyMode) {
+        self.context.verify_mode = verify_mode;
     }
 
-    pub fn run(&mut self) {
-        if let Some(class) = self.main_jar.classes.get_mut(&self.curr_class_name) {
-            if let Some(init_method) = class.methods.get("<init>") {
-                if !class.initialized {
-                    self.executor.execute_method(class, init_method);
-                    class.initialized = true;
+    /// Redirects `System.out`
+    pub fn set_stdout(&mut self, output: ConsoleOutput) {
+        self.context.console.out = output;
+    }
+
+    /// Redirects `System.err`
+    pub fn set_stderr(&mut self, output: ConsoleOutput) {
+        self.context.console.err = output;
+    }
+
+    /// Limits the heap size like `-Xmx`, allocations beyond it raise `OutOfMemoryError`
+    pub fn set_max_heap_size(&mut self, bytes: usize) {
+        self.context.heap.set_max_bytes(bytes);
+    }
+
+    /// Limits the stack size of threads like `-Xss`, deeper calls raise `StackOverflowError`
+    pub fn set_stack_size(&mut self, bytes: usize) {
+        let depth = (bytes / STACK_FRAME_SIZE).max(1);
+
+        self.context.scheduler.max_stack_depth = depth;
+        self.context.executor.max_stack_depth = depth;
+    }
+
+    /// Logs loaded classes to `System.out` like `-verbose:class`
+    pub fn set_verbose_class(&mut self, verbose: bool) {
+        self.context.verbose_class = verbose;
+    }
+
+    /// Limits what the program may use, e.g. to run untrusted code, or lifts the limits with `None`, see `VmLimits`.
+    /// Lifting them leaves the heap size and stack depth as low as the limits set them.
+    pub fn set_limits(&mut self, limits: Option<VmLimits>) {
+        self.context.set_limits(limits);
+    }
+
+    /// Traces calls, returns and instructions of the methods the options select, or stops tracing with `None`
+    pub fn set_trace(&mut self, options: Option<TraceOptions>) -> std::io::Result<()> {
+        self.context.tracer = options.map(Tracer::new).transpose()?;
+        Ok(())
+    }
+
+    /// Sets a system property like `-D`
+    pub fn set_system_property(&mut self, name: &str, value: &str) {
+        self.context.system_properties.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn gc_stats(&self) -> &GcStats {
+        &self.context.heap.stats
+    }
+
+    /// How often the inline caches of virtual and interface call sites found the receiver class
+    pub fn dispatch_stats(&self) -> &DispatchStats {
+        &self.context.dispatch_stats
+    }
+
+    /// Sets when hot methods are compiled to machine code, or only interprets methods from now on with `None`.
+    /// Methods are compiled with the default `JitOptions` unless this is called.
+    #[cfg(feature = "jit")]
+    pub fn set_jit(&mut self, options: Option<JitOptions>) {
+        self.context.jit.options = options;
+    }
+
+    /// How many methods the JIT compiled and how often compiled code ran
+    #[cfg(feature = "jit")]
+    pub fn jit_stats(&self) -> &JitStats {
+        &self.context.jit.stats
+    }
+
+    pub fn collect_garbage(&mut self) {
+        self.context.collect_garbage();
+    }
+
+    /// Registers a Rust implementation for a native method, replacing any built-in one.
+    /// Under `VmLimits` it's treated as reaching the host and only runs if `VmLimits::allowed_natives` allows it.
+    pub fn register_native<F>(&mut self, class_name: &str, name: &str, descriptor: &str, native: F)
+        where F: Fn(&mut VmContext, &[Value]) -> Result<Option<Value>, Throwable> + Send + Sync + 'static {
+        self.context.natives.register_host(class_name, name, descriptor, native);
+    }
+
+    /// Registers a Rust implementation for a native method that doesn't reach the host, which runs under `VmLimits`
+    /// without being allowed, see `register_native`
+    pub fn register_sandboxed_native<F>(&mut self, class_name: &str, name: &str, descriptor: &str, native: F)
+        where F: Fn(&mut VmContext, &[Value]) -> Result<Option<Value>, Throwable> + Send + Sync + 'static {
+        self.context.natives.register(class_name, name, descriptor, native);
+    }
+
+    /// Instructions a thread runs before the next one gets its turn
+    pub fn set_thread_quantum(&mut self, instructions: u32) {
+        self.context.scheduler.quantum = instructions.max(1);
+    }
+
+    /// Initializes `java.base` like the `java` launcher does before it runs a program, see `VmContext::boot`.
+    /// Without it, classes of `java.base` are initialized on first use.
+    pub fn boot(&mut self) -> Result<(), BootError> {
+        self.context.boot()
+    }
+
+    /// Runs `main(String[])` of a class like the `java` launcher does and returns the exit status of the program:
+    /// the one passed to `System.exit`, `1` if `main` threw an exception or the VM stopped, `0` otherwise.
+    /// Why the program couldn't run or was stopped is written to `System.err`.
+    pub fn run(&mut self, class_name: &str, args: &[String]) -> i32 {
+        match self.execute(class_name, args) {
+            Ok(status) => status,
+            Err(error) => {
+                // A deadlock was reported with a thread dump already
+                if !matches!(error, ExecutionError::Deadlock) {
+                    self.context.console.err.write(format!("{}\n", error).as_bytes());
                 }
-            } else {
-                panic!("Cannot find <init> method!");
+
+                error.exit_status()
             }
+        }
+    }
 
-            if let Some(method) = class.methods.get("main") {
-                self.executor.execute_method(class, method);
-            } else {
-                panic!("Cannot find main method!");
+    /// Runs `main(String[])` of a class like `run`, but returns why the program couldn't run or was stopped instead of
+    /// reporting it. Uncaught exceptions are still reported like `java` does and end the program with status `1`.
+    pub fn execute(&mut self, class_name: &str, args: &[String]) -> Result<i32, ExecutionError> {
+        let class_name = class_name.replace('.', "/");
+
+        match (self.context.find_class(&class_name), self.context.find_unparsable_class_file(&class_name)) {
+            (None, Some(error)) => {
+                return Err(ExecutionError::MainClass(format!("Error: LinkageError occurred while loading main class {}\n\tjava.lang.ClassFormatError: {} in class file {}",
+                                                             class_name.replace('/', "."), error.message, error.class_name)));
+            },
+            (None, None) => return Err(ExecutionError::MainClass(format!("Error: Could not find or load main class {0}\nCaused by: java.lang.ClassNotFoundException: {0}", class_name.replace('/', ".")))),
+            (Some(class), _) if !class.find_method("main", "([Ljava/lang/String;)V").is_some_and(|method| method.is_static()) => {
+                return Err(ExecutionError::MainClass(format!("Error: Main method not found in class {}, please define the main method as:\n   public static void main(String[] args)", class_name.replace('/', "."))));
+            },
+            (Some(_), _) => { }
+        }
+
+        self.boot().map_err(ExecutionError::Boot)?;
+
+        let status = match self.context.run_main(&class_name, args) {
+            Ok(()) => 0,
+            Err(Throwable::Halt(halt)) => return ExecutionError::from_halt(halt),
+            Err(throwable) => {
+                self.context.report_uncaught_exception(&throwable);
+                1
             }
-        } else {
-            panic!("Cannot find main class!");
+        };
+
+        // Like the launcher, wait for the other non-daemon threads and run the shutdown hooks before the program ends
+        match self.context.join_non_daemon_threads().and_then(|_| self.context.shutdown()) {
+            Err(Throwable::Halt(halt)) => ExecutionError::from_halt(halt),
+            _ => Ok(status)
+        }
+    }
+
+}
+
+impl Default for VirtualMachine {
+
+    fn default() -> Self {
+        Self::new()
+    }
+
+}
+
+impl fmt::Display for ExecutionError {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionError::MainClass(message) => write!(f, "{}", message),
+            ExecutionError::Boot(error) => write!(f, "{}", error.to_string().trim_end()),
+            ExecutionError::Deadlock => write!(f, "Deadlock"),
+            ExecutionError::LimitExceeded(limit) => write!(f, "Error: Limit exceeded, the program {}", limit)
+        }
+    }
+
+}
+
+impl fmt::Display for Throwable {
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Throwable::Object(reference) => write!(f, "Throwable@{}", reference),
+            Throwable::New { class_name, message: Some(message) } => write!(f, "{}: {}", class_name.replace('/', "."), message),
+            Throwable::New { class_name, message: None } => write!(f, "{}", class_name.replace('/', ".")),
+            Throwable::Halt(Halt::Deadlock) => write!(f, "Deadlock"),
+            Throwable::Halt(Halt::Exit(status)) => write!(f, "Exit with status {}", status),
+            Throwable::Halt(Halt::LimitExceeded(limit)) => write!(f, "Limit exceeded, the program {}", limit)
         }
     }
 
@@ -0,0 +1,12 @@
+pub mod java;
+
+pub use java::{Jar, VirtualMachine};
+pub use java::vm::ExecutionError;
+pub use java::boot::BootError;
+pub use java::dispatch::DispatchStats;
+#[cfg(feature = "jit")]
+pub use java::jit::{JitOptions, JitStats};
+pub use java::annotation::{AnnotationValue, ResolvedAnnotation};
+pub use java::limits::{Limit, VmLimits};
+pub use java::trace::{TraceFormat, TraceOptions, TraceOutput};
+pub use java::embed::{JClass, JMethod, JObject, JValue, JavaError, JavaException, StackTraceElement};
\ No newline at end of file
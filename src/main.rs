@@ -1,11 +1,347 @@
-mod java;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 
-fn main() {
-    let main_jar = crate::java::Jar::new("./Test.jar").unwrap();
-    let java_base_jar  = crate::java::Jar::new("./java.base.jar").unwrap();
+use java_vm::java::heap::parse_heap_size;
+use java_vm::java::verifier::VerifyMode;
+use java_vm::{Jar, TraceFormat, TraceOptions, TraceOutput, VirtualMachine};
+#[cfg(feature = "jit")]
+use java_vm::JitOptions;
+
+const USAGE: &str = "Usage: java_vm [options] <mainclass> [args...]
+           (to execute a class)
+   or  java_vm [options] -jar <jarfile> [args...]
+           (to execute a jar file)
+   or  java_vm disasm <file.class | file.jar> [class name]
+           (to disassemble class files)
+
+where options include:
+    -cp <class search path of directories and jar files>
+    -classpath <class search path of directories and jar files>
+    --class-path <class search path of directories and jar files>
+                  A : separated list of directories and JAR archives to search for class files.
+    -D<name>=<value>
+                  set a system property
+    -verbose:class
+                  log every loaded class
+    -Xbootclasspath:<path>
+                  the java.base jar, by default java.base.jar in the current directory or next to the executable
+    -Xmx<size>    set maximum Java heap size
+    -Xss<size>    set Java thread stack size
+    -Xverify:<none|remote|all>
+                  verify no classes, those of the class path, or also those of the boot class path
+                  before they run, remote by default
+    -Xtrace[:<glob>[,<glob>...]]
+                  trace calls, returns and instructions of the methods whose class and method name
+                  match a glob, e.g. -Xtrace:Main.*,java.util.*List.add, or of all methods
+    -Xtracecalls  only trace calls and returns, not instructions
+    -Xtracefile:<path>
+                  write the trace to a file instead of standard error
+    -Xtraceformat:<text|json>
+                  write the trace as text lines or JSON lines, text by default
+    -Xint         only interpret methods, don't compile hot methods to machine code
+    -XX:CompileThreshold=<calls>
+                  calls of a method before it's compiled, 1000 by default
+    -XX:BackEdgeThreshold=<iterations>
+                  loop iterations in a method before it's compiled, 10000 by default
+    -XX:+PrintCompilation
+                  log compiled methods
+    -help, -h, -?, --help
+                  print this help message
+";
+
+/// What the command line asks the VM to do, parsed like the `java` launcher does
+#[derive(Default)]
+struct Options {
+    class_path: Option<String>,
+    boot_class_path: Option<String>,
 
-    let mut vm = crate::java::VirtualMachine::new(main_jar).unwrap();
-    vm.add_library_jar(java_base_jar);
+    /// The jar given with `-jar`, its manifest names the main class
+    jar: Option<String>,
+    main_class: Option<String>,
+    program_args: Vec<String>,
 
-    vm.run();
+    properties: Vec<(String, String)>,
+    max_heap_size: Option<usize>,
+    stack_size: Option<usize>,
+    verbose_class: bool,
+    /// Set by `-Xverify`, the VM's default otherwise
+    verify_mode: Option<VerifyMode>,
+    /// Set by any of the `-Xtrace` options
+    trace: Option<TraceOptions>,
+    /// Set by `-Xint`
+    #[cfg(feature = "jit")]
+    interpret_only: bool,
+    #[cfg(feature = "jit")]
+    jit: JitOptions,
+    help: bool
 }
+
+impl Options {
+
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-cp" | "-classpath" | "--class-path" => {
+                    let class_path = args.next().ok_or_else(|| format!("{} requires class path specification", arg))?;
+                    options.class_path = Some(class_path.clone());
+                },
+                "-jar" => {
+                    let jar = args.next().ok_or_else(|| "-jar requires jar file specification".to_string())?;
+                    options.jar = Some(jar.clone());
+                    options.program_args = args.cloned().collect();
+                    break;
+                },
+                "-verbose:class" => options.verbose_class = true,
+                "-Xtrace" => { options.trace.get_or_insert_with(TraceOptions::default); },
+                "-Xtracecalls" => options.trace.get_or_insert_with(TraceOptions::default).instructions = false,
+                #[cfg(feature = "jit")]
+                "-Xint" => options.interpret_only = true,
+                #[cfg(feature = "jit")]
+                "-XX:+PrintCompilation" => options.jit.print_compilation = true,
+                // Without the JIT every method is interpreted anyway
+                #[cfg(not(feature = "jit"))]
+                "-Xint" => { },
+                "-help" | "-h" | "-?" | "--help" => options.help = true,
+                _ if arg.starts_with("-D") => {
+                    let (name, value) = arg[2..].split_once('=').unwrap_or((&arg[2..], ""));
+                    options.properties.push((name.to_string(), value.to_string()));
+                },
+                _ if arg.starts_with("-Xbootclasspath:") => options.boot_class_path = Some(arg["-Xbootclasspath:".len()..].to_string()),
+                _ if arg.starts_with("-Xtrace:") => {
+                    let filters = arg["-Xtrace:".len()..].split(',').filter(|filter| !filter.is_empty()).map(str::to_string);
+                    options.trace.get_or_insert_with(TraceOptions::default).filters.extend(filters);
+                },
+                _ if arg.starts_with("-Xtracefile:") => {
+                    options.trace.get_or_insert_with(TraceOptions::default).output = TraceOutput::File(PathBuf::from(&arg["-Xtracefile:".len()..]));
+                },
+                _ if arg.starts_with("-Xtraceformat:") => {
+                    options.trace.get_or_insert_with(TraceOptions::default).format = match &arg["-Xtraceformat:".len()..] {
+                        "text" => TraceFormat::Text,
+                        "json" => TraceFormat::JsonLines,
+                        format => return Err(format!("Invalid trace format: {}", format))
+                    };
+                },
+                #[cfg(feature = "jit")]
+                _ if arg.starts_with("-XX:CompileThreshold=") => {
+                    options.jit.invocation_threshold = arg["-XX:CompileThreshold=".len()..].parse().map_err(|_| format!("Invalid compile threshold: {}", arg))?;
+                },
+                #[cfg(feature = "jit")]
+                _ if arg.starts_with("-XX:BackEdgeThreshold=") => {
+                    options.jit.backedge_threshold = arg["-XX:BackEdgeThreshold=".len()..].parse().map_err(|_| format!("Invalid back edge threshold: {}", arg))?;
+                },
+                _ if arg.starts_with("-Xverify:") => {
+                    options.verify_mode = Some(match &arg["-Xverify:".len()..] {
+                        "none" => VerifyMode::None,
+                        "remote" => VerifyMode::Remote,
+                        "all" => VerifyMode::All,
+                        mode => return Err(format!("Invalid verification mode: {}", mode))
+                    });
+                },
+                _ if arg.starts_with("-Xmx") => {
+                    options.max_heap_size = Some(parse_heap_size(&arg[4..]).ok_or_else(|| format!("Invalid maximum heap size: {}", arg))?);
+                },
+                _ if arg.starts_with("-Xss") => {
+                    options.stack_size = Some(parse_heap_size(&arg[4..]).ok_or_else(|| format!("Invalid thread stack size: {}", arg))?);
+                },
+                _ if arg.starts_with('-') => return Err(format!("Unrecognized option: {}", arg)),
+                _ => {
+                    options.main_class = Some(arg.clone());
+                    options.program_args = args.cloned().collect();
+                    break;
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+}
+
+fn disassemble(path: &str, class_name: Option<&String>) {
+    if path.ends_with(".class") {
+        let data = std::fs::read(path).expect("Failed to read class file");
+        match java_vm::java::Class::new(&data) {
+            Ok(class) => {
+                println!("Classfile {}", path);
+                print!("{}", java_vm::java::disassembler::disassemble(&class.class_file));
+            },
+            Err(error) => eprintln!("{}", java_vm::java::format_checker::ClassFormatError::unparsable(path.trim_end_matches(".class"), &error))
+        }
+
+        return;
+    }
+
+    let jar = Jar::new(path).unwrap();
+
+    let mut names: Vec<&String> = jar.classes.keys().collect();
+    names.sort();
+
+    for name in names {
+        if let Some(class_name) = class_name {
+            if name.trim_end_matches(".class") != class_name.replace('.', "/") { continue; }
+        }
+
+        println!("Classfile jar:{}!/{}", path, name);
+        print!("{}", java_vm::java::disassembler::disassemble(&jar.classes[name].class_file));
+    }
+}
+
+/// A class path entry, a directory or a jar file
+fn open_class_path_entry(path: &str) -> Result<Jar, &'static str> {
+    if Path::new(path).is_dir() {
+        Jar::from_directory(path)
+    } else {
+        Jar::new(path)
+    }
+}
+
+/// `java.base.jar` in the current directory, or else next to the executable
+fn default_boot_class_path() -> String {
+    let local = Path::new("java.base.jar");
+    if local.exists() {
+        return local.to_string_lossy().into_owned();
+    }
+
+    std::env::current_exe().ok()
+        .and_then(|executable| executable.parent().map(|directory| directory.join("java.base.jar")))
+        .filter(|path| path.exists())
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|| local.to_string_lossy().into_owned())
+}
+
+/// Sets up the VM as the options say and runs the program, returns the exit status of the process
+fn launch(options: Options) -> i32 {
+    let mut vm = VirtualMachine::new();
+
+    let (main_class, class_path) = match &options.jar {
+        Some(jar_path) => {
+            let jar = match Jar::new(jar_path) {
+                Ok(jar) => jar,
+                Err(_) => {
+                    eprintln!("Error: Unable to access jarfile {}", jar_path);
+                    return 1;
+                }
+            };
+
+            let main_class = match jar.main_class_name() {
+                Some(main_class) => main_class,
+                None => {
+                    eprintln!("no main manifest attribute, in {}", jar_path);
+                    return 1;
+                }
+            };
+
+            let class_path = jar.manifest_class_path();
+            vm.add_class_path(jar);
+
+            (main_class, class_path)
+        },
+        None => {
+            let main_class = match &options.main_class {
+                Some(main_class) => main_class.clone(),
+                None => {
+                    eprint!("{}", USAGE);
+                    return 1;
+                }
+            };
+
+            let class_path = options.class_path.clone()
+                .or_else(|| std::env::var("CLASSPATH").ok())
+                .unwrap_or_else(|| ".".to_string());
+
+            (main_class, class_path.split(':').filter(|entry| !entry.is_empty()).map(str::to_string).collect())
+        }
+    };
+
+    // Like java, entries that can't be opened are skipped
+    for entry in class_path {
+        if let Ok(jar) = open_class_path_entry(&entry) {
+            vm.add_class_path(jar);
+        }
+    }
+
+    let boot_class_path = options.boot_class_path.clone().unwrap_or_else(default_boot_class_path);
+    match Jar::new(&boot_class_path) {
+        Ok(jar) => vm.add_library_jar(jar),
+        Err(_) => {
+            eprintln!("Error: Could not open the boot class path {}", boot_class_path);
+            return 1;
+        }
+    }
+
+    // Like java, the command names the jar or main class and its arguments
+    let command = options.jar.clone().unwrap_or_else(|| main_class.clone());
+    vm.set_system_property("sun.java.command", &std::iter::once(command).chain(options.program_args.iter().cloned()).collect::<Vec<_>>().join(" "));
+
+    for (name, value) in &options.properties {
+        vm.set_system_property(name, value);
+    }
+
+    if let Some(bytes) = options.max_heap_size {
+        vm.set_max_heap_size(bytes);
+    }
+
+    if let Some(bytes) = options.stack_size {
+        vm.set_stack_size(bytes);
+    }
+
+    vm.set_verbose_class(options.verbose_class);
+
+    if let Some(verify_mode) = options.verify_mode {
+        vm.set_verify_mode(verify_mode);
+    }
+
+    #[cfg(feature = "jit")]
+    vm.set_jit((!options.interpret_only).then(|| options.jit.clone()));
+
+    if let Err(error) = vm.set_trace(options.trace.clone()) {
+        eprintln!("Error: Could not open the trace file: {}", error);
+        return 1;
+    }
+
+    // A panic is a bug in the VM, the Java code it was running helps to reproduce it
+    match panic::catch_unwind(AssertUnwindSafe(|| vm.run(&main_class, &options.program_args))) {
+        Ok(status) => status,
+        Err(payload) => {
+            eprintln!("Java stack at the time of the panic:");
+            for element in vm.current_stack_trace() {
+                eprintln!("\tat {}", element);
+            }
+
+            panic::resume_unwind(payload)
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("disasm") {
+        if let Some(path) = args.get(2) {
+            disassemble(path, args.get(3));
+        } else {
+            println!("Usage: {} disasm <file.class | file.jar> [class name]", args[0]);
+        }
+
+        return;
+    }
+
+    let options = match Options::parse(&args[1..]) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("Error: Could not create the Java Virtual Machine.");
+            std::process::exit(1);
+        }
+    };
+
+    if options.help {
+        print!("{}", USAGE);
+        return;
+    }
+
+    std::process::exit(launch(options));
+}
\ No newline at end of file
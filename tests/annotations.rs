@@ -0,0 +1,89 @@
+//! Runtime visible annotations of `tests/programs/Annotated.java`: resolved element values with their defaults
+//! through the embedding API, and `getAnnotation` in Java backed by proxies.
+
+mod common;
+
+use java_vm::{AnnotationValue, JClass, ResolvedAnnotation, VirtualMachine};
+
+fn annotated() -> (VirtualMachine, JClass) {
+    common::load_program("annotation-classes", &["tests/programs/Annotated.java"], "Annotated")
+}
+
+fn tag(value: &str) -> AnnotationValue {
+    AnnotationValue::Annotation(ResolvedAnnotation {
+        type_name: "Annotated$Tag".to_string(),
+        elements: vec![("value".to_string(), AnnotationValue::String(value.to_string()))]
+    })
+}
+
+#[test]
+fn annotations_are_resolved_with_defaults() {
+    let (mut vm, class) = annotated();
+
+    let annotations = vm.class_annotations(class);
+    assert_eq!(annotations.len(), 1, "{:?}", annotations);
+
+    let config = &annotations[0];
+    assert_eq!(config.type_name, "Annotated$Config");
+    assert_eq!(config.java_name(), "Annotated$Config");
+
+    let expected = [
+        ("name", AnnotationValue::String("annotated".to_string())),
+        ("level", AnnotationValue::Int(3)),
+        ("ratio", AnnotationValue::Double(0.5)),
+        ("letters", AnnotationValue::Array(vec![AnnotationValue::Char('a' as u16), AnnotationValue::Char('b' as u16)])),
+        ("policy", AnnotationValue::Enum { class_name: "java/lang/annotation/RetentionPolicy".to_string(), constant: "SOURCE".to_string() }),
+        ("type", AnnotationValue::Class("Ljava/lang/String;".to_string())),
+        ("tags", AnnotationValue::Array(vec![tag("first"), tag("second")])),
+        // Defaults of the elements that were left out
+        ("big", AnnotationValue::Long(1 << 40)),
+        ("scale", AnnotationValue::Float(1.5)),
+        ("enabled", AnnotationValue::Boolean(true)),
+        ("small", AnnotationValue::Byte(-1)),
+        ("medium", AnnotationValue::Short(300)),
+        ("main", tag("main"))
+    ];
+    for (name, value) in &expected {
+        assert_eq!(config.element(name), Some(value), "{}", name);
+    }
+    assert_eq!(config.elements.len(), expected.len(), "{:?}", config.elements);
+
+    assert_eq!(vm.field_annotations(class, "annotatedField"), Some(vec![match tag("field") {
+        AnnotationValue::Annotation(annotation) => annotation,
+        _ => unreachable!()
+    }]));
+    assert_eq!(vm.field_annotations(class, "plainField"), Some(vec![]));
+    assert_eq!(vm.field_annotations(class, "noSuchField"), None);
+}
+
+#[test]
+fn annotated_methods_can_be_discovered() {
+    let (mut vm, class) = annotated();
+
+    let checks: Vec<(String, Option<AnnotationValue>)> = vm.declared_methods(class).into_iter()
+        .filter_map(|method| {
+            let check = method.annotations.iter().find(|annotation| annotation.type_name == "Annotated$Check")?;
+            Some((method.name.clone(), check.element("expected").cloned()))
+        })
+        .collect();
+
+    assert_eq!(checks, vec![
+        ("first".to_string(), Some(AnnotationValue::String("one".to_string()))),
+        ("second".to_string(), Some(AnnotationValue::String(String::new())))
+    ]);
+
+    for (name, expected) in checks {
+        let AnnotationValue::String(expected) = expected.unwrap() else { unreachable!() };
+        assert_eq!(common::string_result(&mut vm, class, &name, "()Ljava/lang/String;", &[]), expected);
+    }
+}
+
+#[test]
+fn reflection_returns_annotation_proxies() {
+    let (mut vm, class) = annotated();
+
+    assert_eq!(common::string_result(&mut vm, class, "reflected", "()Ljava/lang/String;", &[]),
+               "annotated 3 1099511627776 0.5 1.5 true -1 300 [a, b] SOURCE java.lang.String 2 second main | field one 1 false 0 true");
+    assert_eq!(common::string_result(&mut vm, class, "equality", "()Ljava/lang/String;", &[]),
+               "true true Tag @Annotated$Tag(\"first\")");
+}
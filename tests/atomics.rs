@@ -0,0 +1,27 @@
+//! `tests/programs/Atomics.java`, whose atomics update their fields through a `VarHandle`, on green threads.
+
+mod common;
+
+use java_vm::JValue;
+
+use common::string_result;
+
+#[test]
+fn atomics_update_through_var_handles() {
+    let (mut vm, class) = common::load_program("atomics-classes", &["tests/programs/Atomics.java"], "Atomics");
+
+    assert_eq!(string_result(&mut vm, class, "reference", "()Ljava/lang/String;", &[]), "true false b d");
+    assert_eq!(string_result(&mut vm, class, "bool", "()Ljava/lang/String;", &[]), "false true false");
+    assert_eq!(string_result(&mut vm, class, "fields", "()Ljava/lang/String;", &[]),
+               "0 false -7 -1099511627776 false 9223372036854775807 true -0.25 first false second");
+}
+
+#[test]
+fn tasks_complete_on_other_threads() {
+    let (mut vm, class) = common::load_program("atomics-classes", &["tests/programs/Atomics.java"], "Atomics");
+
+    assert!(matches!(vm.invoke_static(class, "futureTask", "()I", &[]), Ok(JValue::Int(42))));
+
+    let args = [JValue::Int(8), JValue::Int(500)];
+    assert_eq!(string_result(&mut vm, class, "executor", "(II)Ljava/lang/String;", &args), "4000 28 done");
+}
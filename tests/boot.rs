@@ -0,0 +1,57 @@
+//! Booting `java.base`: it completes with the natives the VM has, and a failing boot reports the step that failed
+//! and the natives it called that aren't implemented, here those of `tests/programs/BrokenLoader.java`.
+
+mod common;
+
+use std::process::Command;
+
+use java_vm::VirtualMachine;
+
+fn broken_vm() -> VirtualMachine {
+    let classes = common::compile_programs("boot-classes", &["tests/programs/BrokenLoader.java"]);
+
+    let mut vm = common::vm_with_classes(&classes);
+    vm.set_system_property("java.system.class.loader", "BrokenLoader");
+    vm
+}
+
+#[test]
+fn java_base_boots_without_missing_natives() {
+    let classes = common::compile_programs("boot-classes", &["tests/programs/BrokenLoader.java"]);
+    let mut vm = common::vm_with_classes(&classes);
+
+    vm.boot().unwrap_or_else(|error| panic!("{}", error));
+    // Booting again does nothing
+    vm.boot().unwrap_or_else(|error| panic!("{}", error));
+}
+
+#[test]
+fn failed_boots_list_missing_natives() {
+    let error = broken_vm().boot().expect_err("Boot didn't fail");
+
+    assert_eq!(error.phase, "System.initPhase3");
+    assert_eq!(error.exception, "java.lang.UnsatisfiedLinkError: 'int BrokenLoader.connect(java.lang.String)'");
+    assert_eq!(error.missing_natives, vec!["'int BrokenLoader.connect(java.lang.String)'".to_string()]);
+
+    let report = error.to_string();
+    assert_eq!(report, "Error occurred during initialization of VM\n\
+                        System.initPhase3 failed: java.lang.UnsatisfiedLinkError: 'int BrokenLoader.connect(java.lang.String)'\n\
+                        Native methods that aren't implemented:\n    'int BrokenLoader.connect(java.lang.String)'\n");
+}
+
+#[test]
+fn launcher_reports_failed_boots() {
+    let classes = common::compile_programs("boot-classes", &["tests/programs/BrokenLoader.java"]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_java_vm"))
+        .arg(format!("-Xbootclasspath:{}", common::java_base_jar().display()))
+        .arg("-cp").arg(&classes)
+        .arg("-Djava.system.class.loader=BrokenLoader")
+        .arg("BrokenLoader")
+        .output().unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(output.stdout.is_empty());
+    assert!(stderr.contains("Native methods that aren't implemented:\n    'int BrokenLoader.connect(java.lang.String)'\n"), "{}", stderr);
+}
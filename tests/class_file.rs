@@ -0,0 +1,85 @@
+//! Class files of `java.base` written back unmodified have to come out byte for byte like they were read, and so do
+//! the attributes the VM parses into an `Attribute`.
+
+mod common;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+
+use java_vm::java::{Attribute, AttributeInfo, Class, ClassFile};
+
+/// The class files of `java.base.jar` with their names, as the bytes in the jar
+fn java_base_class_files() -> Vec<(String, Vec<u8>)> {
+    let jar = File::open(common::java_base_jar()).expect("java.base.jar can't be opened");
+    let mut archive = zip::ZipArchive::new(jar).expect("java.base.jar isn't a zip archive");
+
+    (0..archive.len()).filter_map(|index| {
+        let mut file = archive.by_index(index).unwrap();
+        let name = file.name().to_string();
+        if !name.ends_with(".class") {
+            return None;
+        }
+
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes).unwrap();
+        Some((name, bytes))
+    }).collect()
+}
+
+/// Writes an attribute back if the VM parses it and compares it to what was read, the same for the attributes of a
+/// `Code` attribute. Adds the names of the attributes that were written back to `written`.
+fn check_attribute(class_file: &ClassFile, class_name: &str, attribute_info: &AttributeInfo, written: &mut HashSet<&'static str>) {
+    let attribute = match Attribute::new(class_file, attribute_info) {
+        Some(attribute) => attribute,
+        None => return
+    };
+
+    let bytes = attribute.write().unwrap_or_else(|error| panic!("{} of {} can't be written: {}", attribute.name(), class_name, error));
+    assert!(bytes == attribute_info.info, "{} of {} is written differently", attribute.name(), class_name);
+
+    let rebuilt = attribute.to_attribute_info(class_file).unwrap();
+    assert_eq!(rebuilt.attribute_name_index, attribute_info.attribute_name_index, "{} of {}", attribute.name(), class_name);
+    assert_eq!(rebuilt.attribute_length, attribute_info.attribute_length, "{} of {}", attribute.name(), class_name);
+
+    if let Attribute::Code(code) = &attribute {
+        for nested in &code.attributes {
+            check_attribute(class_file, class_name, nested, written);
+        }
+    }
+
+    written.insert(attribute.name());
+}
+
+#[test]
+fn unmodified_class_files_are_written_back_identically() {
+    for (name, bytes) in java_base_class_files() {
+        let class = Class::new(&bytes).unwrap_or_else(|error| panic!("{} can't be read: {}", name, error));
+
+        let written = class.class_file.write().unwrap_or_else(|error| panic!("{} can't be written: {}", name, error));
+        assert!(written == bytes, "{} is written differently", name);
+    }
+}
+
+#[test]
+fn typed_attributes_are_written_back_identically() {
+    let mut written_types = HashSet::new();
+
+    for (name, bytes) in java_base_class_files() {
+        let class_file = Class::new(&bytes).unwrap_or_else(|error| panic!("{} can't be read: {}", name, error)).class_file;
+
+        let attributes = class_file.attribute_table.iter()
+            .chain(class_file.field_table.iter().flat_map(|field| &field.attributes))
+            .chain(class_file.method_table.iter().flat_map(|method| &method.attributes));
+
+        for attribute_info in attributes {
+            check_attribute(&class_file, &name, attribute_info, &mut written_types);
+        }
+    }
+
+    let unwritten: Vec<_> = ["ConstantValue", "Code", "Exceptions", "RuntimeVisibleAnnotations", "RuntimeInvisibleAnnotations", "Signature",
+                             "Deprecated", "AnnotationDefault", "MethodParameters", "LineNumberTable", "StackMapTable", "BootstrapMethods",
+                             "InnerClasses", "EnclosingMethod", "SourceFile", "NestHost", "NestMembers"]
+        .into_iter().filter(|name| !written_types.contains(name)).collect();
+    assert!(unwritten.is_empty(), "java.base has no {:?} attributes", unwritten);
+}
@@ -0,0 +1,507 @@
+//! Class files that can't be parsed or break a rule of the format checker (JVMS 4.8) have to raise a
+//! `ClassFormatError` that says what's wrong, instead of panicking or getting linked.
+//! `tests/programs/Formats.java` is broken in one place at a time, every case checks the message of the error.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use java_vm::java::access_flags::*;
+use java_vm::java::class::{ConstantPoolEntry, FieldInfo, MethodInfo};
+use java_vm::java::format_checker::check_class_format;
+use java_vm::java::{Attribute, AttributeInfo, Class, ClassFile};
+use java_vm::JavaError;
+
+fn compiled() -> PathBuf {
+    common::compile_programs("formats", &["tests/programs/Formats.java"])
+}
+
+fn class_bytes(class_name: &str) -> Vec<u8> {
+    std::fs::read(compiled().join(format!("{}.class", class_name))).unwrap()
+}
+
+fn class_file(class_name: &str) -> ClassFile {
+    Class::new(&class_bytes(class_name)).unwrap_or_else(|error| panic!("{} can't be read: {}", class_name, error)).class_file
+}
+
+/// Appends an entry to the constant pool and returns its index
+fn add(class_file: &mut ClassFile, entry: ConstantPoolEntry) -> u16 {
+    class_file.constant_pool.push(entry);
+    class_file.constant_pool.len() as u16
+}
+
+fn utf8(class_file: &mut ClassFile, string: &str) -> u16 {
+    add(class_file, ConstantPoolEntry::String { length: string.len() as u16, string: string.as_bytes().to_vec() })
+}
+
+fn class_reference(class_file: &mut ClassFile, name: &str) -> u16 {
+    let name_index = utf8(class_file, name);
+    add(class_file, ConstantPoolEntry::ClassReference(name_index))
+}
+
+fn name_and_type(class_file: &mut ClassFile, name: &str, descriptor: &str) -> u16 {
+    let name_index = utf8(class_file, name);
+    let descriptor_index = utf8(class_file, descriptor);
+    add(class_file, ConstantPoolEntry::NameAndTypeDescriptor(name_index, descriptor_index))
+}
+
+fn field<'a>(class_file: &'a mut ClassFile, name: &str) -> &'a mut FieldInfo {
+    let index = class_file.field_table.iter()
+        .position(|field| class_file.get_constant_pool_string(field.name_index as usize).as_deref() == Some(name))
+        .expect("No such field");
+    &mut class_file.field_table[index]
+}
+
+fn method<'a>(class_file: &'a mut ClassFile, name: &str) -> &'a mut MethodInfo {
+    let index = class_file.method_table.iter()
+        .position(|method| class_file.get_constant_pool_string(method.name_index as usize).as_deref() == Some(name))
+        .expect("No such method");
+    &mut class_file.method_table[index]
+}
+
+/// Indices of a method and its `Code` attribute
+fn code_index(class_file: &ClassFile, name: &str) -> (usize, usize) {
+    let method_index = class_file.method_table.iter()
+        .position(|method| class_file.get_constant_pool_string(method.name_index as usize).as_deref() == Some(name))
+        .expect("No such method");
+    let code_index = class_file.method_table[method_index].attributes.iter()
+        .position(|attribute| class_file.get_constant_pool_string(attribute.attribute_name_index as usize).as_deref() == Some("Code"))
+        .expect("Method has no code");
+
+    (method_index, code_index)
+}
+
+fn copy(attribute_info: &AttributeInfo) -> AttributeInfo {
+    AttributeInfo {
+        attribute_name_index: attribute_info.attribute_name_index,
+        attribute_length: attribute_info.attribute_length,
+        info: attribute_info.info.clone()
+    }
+}
+
+/// Breaks a class file of `Formats.java` and returns the message the format checker has to report
+type Case = fn(&mut ClassFile) -> String;
+
+/// Runs cases on fresh copies of a class, which passes the checker unmodified
+fn check_cases(class_name: &str, cases: &[Case]) {
+    check_class_format(&class_file(class_name)).unwrap_or_else(|error| panic!("Unmodified {}: {}", class_name, error));
+
+    for (number, case) in cases.iter().enumerate() {
+        let mut class_file = class_file(class_name);
+        let expected = case(&mut class_file);
+
+        match check_class_format(&class_file) {
+            Err(error) => assert_eq!(error.message, expected, "Case {} of {}", number, class_name),
+            Ok(()) => panic!("Case {} of {} passed the checker instead of raising \"{}\"", number, class_name, expected)
+        }
+    }
+}
+
+#[test]
+fn unsupported_versions_are_rejected() {
+    check_cases("Formats", &[
+        |class_file| {
+            class_file.major_version = 66;
+            "Unsupported class file version 66.0".to_string()
+        },
+        |class_file| {
+            class_file.major_version = 44;
+            "Unsupported class file version 44.0".to_string()
+        },
+        |class_file| {
+            class_file.minor_version = 3;
+            "Illegal minor version 3 for class file version 61".to_string()
+        }
+    ]);
+}
+
+#[test]
+fn malformed_constants_are_rejected() {
+    check_cases("Formats", &[
+        |class_file| {
+            let index = add(class_file, ConstantPoolEntry::String { length: 3, string: vec![b'a', 0, b'b'] });
+            format!("Illegal UTF8 string in constant pool at index {}", index)
+        },
+        |class_file| {
+            let index = add(class_file, ConstantPoolEntry::String { length: 2, string: vec![b'a', 0xF0] });
+            format!("Illegal UTF8 string in constant pool at index {}", index)
+        },
+        |class_file| {
+            add(class_file, ConstantPoolEntry::Integer(1));
+            let index = add(class_file, ConstantPoolEntry::None());
+            format!("Unknown constant tag 0 at index {}", index)
+        },
+        |class_file| {
+            let index = class_reference(class_file, "a//b");
+            format!("Illegal class name \"a//b\" in constant pool entry #{}", index)
+        },
+        |class_file| {
+            let integer = add(class_file, ConstantPoolEntry::Integer(1));
+            let index = add(class_file, ConstantPoolEntry::StringReference(integer));
+            format!("Invalid constant pool index {} for constant pool entry #{}, expected a Utf8 entry", integer, index)
+        }
+    ]);
+}
+
+#[test]
+fn malformed_member_references_are_rejected() {
+    check_cases("Formats", &[
+        |class_file| {
+            let name = utf8(class_file, "Formats");
+            let name_and_type = name_and_type(class_file, "counter", "J");
+            let index = add(class_file, ConstantPoolEntry::FieldReference(name, name_and_type));
+            format!("Invalid constant pool index {} for member reference #{}, expected a Class entry", name, index)
+        },
+        |class_file| {
+            let class = class_reference(class_file, "[[");
+            let name_and_type = name_and_type(class_file, "counter", "J");
+            add(class_file, ConstantPoolEntry::FieldReference(class, name_and_type));
+            // The class entry is checked before the reference to it
+            format!("Illegal class name \"[[\" in constant pool entry #{}", class)
+        },
+        |class_file| {
+            let class = class_file.this_class;
+            let name_and_type = name_and_type(class_file, "a.b", "J");
+            let index = add(class_file, ConstantPoolEntry::FieldReference(class, name_and_type));
+            format!("Illegal field name \"a.b\" in member reference #{}", index)
+        },
+        |class_file| {
+            let class = class_file.this_class;
+            let name_and_type = name_and_type(class_file, "counter", "Q");
+            let index = add(class_file, ConstantPoolEntry::FieldReference(class, name_and_type));
+            format!("Illegal field signature \"Q\" in member reference #{}", index)
+        },
+        |class_file| {
+            let class = class_file.this_class;
+            let name_and_type = name_and_type(class_file, "<clinit>", "()V");
+            let index = add(class_file, ConstantPoolEntry::MethodReference(class, name_and_type));
+            format!("Illegal method name \"<clinit>\" in member reference #{}", index)
+        },
+        |class_file| {
+            let class = class_file.this_class;
+            let name_and_type = name_and_type(class_file, "run", "(I");
+            let index = add(class_file, ConstantPoolEntry::InterfaceMethodReference(class, name_and_type));
+            format!("Illegal method signature \"(I\" in member reference #{}", index)
+        },
+        |class_file| {
+            let class = class_file.this_class;
+            let name_and_type = name_and_type(class_file, "<init>", "()I");
+            let index = add(class_file, ConstantPoolEntry::MethodReference(class, name_and_type));
+            format!("Method \"<init>\" in member reference #{} has non-void return type", index)
+        }
+    ]);
+}
+
+#[test]
+fn malformed_dynamic_constants_are_rejected() {
+    check_cases("Formats", &[
+        |class_file| {
+            let class = class_file.this_class;
+            let name_and_type = name_and_type(class_file, "run", "()V");
+            let method = add(class_file, ConstantPoolEntry::MethodReference(class, name_and_type));
+            let index = add(class_file, ConstantPoolEntry::MethodHandle(10, method));
+            format!("Bad method handle kind 10 at constant pool index {}", index)
+        },
+        |class_file| {
+            let class = class_file.this_class;
+            let name_and_type = name_and_type(class_file, "run", "()V");
+            let method = add(class_file, ConstantPoolEntry::MethodReference(class, name_and_type));
+            let index = add(class_file, ConstantPoolEntry::MethodHandle(1, method));
+            format!("Bad method handle reference #{} at constant pool index {}", method, index)
+        },
+        |class_file| {
+            let descriptor = utf8(class_file, "I");
+            let index = add(class_file, ConstantPoolEntry::MethodType(descriptor));
+            format!("Illegal method type descriptor \"I\" at constant pool index {}", index)
+        },
+        |class_file| {
+            let name_and_type = name_and_type(class_file, "constant", "(I)V");
+            let index = add(class_file, ConstantPoolEntry::Dynamic(0, name_and_type));
+            format!("Illegal field signature \"(I)V\" in dynamic constant #{}", index)
+        },
+        |class_file| {
+            let name_and_type = name_and_type(class_file, "get", "I");
+            let index = add(class_file, ConstantPoolEntry::InvokeDynamic(0, name_and_type));
+            format!("Illegal method signature \"I\" in invokedynamic constant #{}", index)
+        }
+    ]);
+}
+
+#[test]
+fn malformed_classes_are_rejected() {
+    check_cases("Formats", &[
+        |class_file| {
+            class_file.this_class = class_reference(class_file, "[I");
+            "Illegal this_class \"[I\"".to_string()
+        },
+        |class_file| {
+            class_file.super_class = 0;
+            "Invalid superclass index 0".to_string()
+        },
+        |class_file| {
+            class_file.super_class = class_reference(class_file, "[I");
+            "Illegal superclass \"[I\"".to_string()
+        },
+        |class_file| {
+            let interface = class_file.interface_table[0];
+            class_file.interface_table.push(interface);
+            "Duplicate interface name \"java/lang/Runnable\"".to_string()
+        },
+        |class_file| {
+            class_file.access_flags |= ACC_MODULE;
+            "module-info classes can't be loaded".to_string()
+        },
+        |class_file| {
+            class_file.access_flags |= ACC_FINAL | ACC_ABSTRACT;
+            format!("Illegal class modifiers 0x{:04X}", class_file.access_flags)
+        },
+        |class_file| {
+            class_file.access_flags |= ACC_ANNOTATION;
+            format!("Illegal class modifiers 0x{:04X}", class_file.access_flags)
+        }
+    ]);
+
+    check_cases("Shape", &[
+        |class_file| {
+            class_file.super_class = class_reference(class_file, "java/lang/Number");
+            "Interfaces must have java.lang.Object as superclass".to_string()
+        },
+        |class_file| {
+            class_file.access_flags |= ACC_FINAL;
+            format!("Illegal class modifiers 0x{:04X}", class_file.access_flags)
+        },
+        |class_file| {
+            class_file.access_flags &= !ACC_ABSTRACT;
+            format!("Illegal class modifiers 0x{:04X}", class_file.access_flags)
+        }
+    ]);
+}
+
+#[test]
+fn malformed_fields_are_rejected() {
+    check_cases("Formats", &[
+        |class_file| {
+            field(class_file, "counter").name_index = utf8(class_file, "a;b");
+            "Illegal field name \"a;b\"".to_string()
+        },
+        |class_file| {
+            field(class_file, "counter").descriptor_index = utf8(class_file, "V");
+            "Field \"counter\" has illegal signature \"V\"".to_string()
+        },
+        |class_file| {
+            let counter = field(class_file, "counter");
+            let duplicate = FieldInfo { access_flags: 0, name_index: counter.name_index, descriptor_index: counter.descriptor_index, attributes_count: 0, attributes: vec![] };
+            class_file.field_table.push(duplicate);
+            "Duplicate field name \"counter\" with signature \"J\"".to_string()
+        },
+        |class_file| {
+            field(class_file, "counter").access_flags |= ACC_PUBLIC | ACC_PRIVATE;
+            "Illegal field modifiers 0x0043 on counter".to_string()
+        },
+        |class_file| {
+            field(class_file, "counter").access_flags |= ACC_FINAL;
+            "Illegal field modifiers 0x0050 on counter".to_string()
+        },
+        |class_file| {
+            field(class_file, "ANSWER").descriptor_index = utf8(class_file, "J");
+            "Inconsistent constant value type in field ANSWER".to_string()
+        },
+        |class_file| {
+            let constant_value = &mut field(class_file, "ANSWER").attributes[0];
+            constant_value.info.truncate(1);
+            constant_value.attribute_length = 1;
+            "Malformed ConstantValue attribute on field ANSWER".to_string()
+        }
+    ]);
+
+    check_cases("Shape", &[
+        |class_file| {
+            field(class_file, "SIDES").access_flags &= !ACC_FINAL;
+            "Illegal field modifiers 0x0009 on interface field SIDES".to_string()
+        }
+    ]);
+}
+
+#[test]
+fn malformed_methods_are_rejected() {
+    check_cases("Formats", &[
+        |class_file| {
+            method(class_file, "run").name_index = utf8(class_file, "a.b");
+            "Illegal method name \"a.b\"".to_string()
+        },
+        |class_file| {
+            method(class_file, "run").name_index = utf8(class_file, "<run>");
+            "Illegal method name \"<run>\"".to_string()
+        },
+        |class_file| {
+            method(class_file, "run").descriptor_index = utf8(class_file, "(I");
+            "Method \"run\" has illegal signature \"(I\"".to_string()
+        },
+        |class_file| {
+            let descriptor = format!("({})V", "J".repeat(128));
+            method(class_file, "run").descriptor_index = utf8(class_file, &descriptor);
+            format!("Too many arguments in method signature \"{}\" of run", descriptor)
+        },
+        |class_file| {
+            let run = method(class_file, "run");
+            let duplicate = MethodInfo { access_flags: ACC_PUBLIC | ACC_NATIVE, name_index: run.name_index, descriptor_index: run.descriptor_index, attributes_count: 0, attributes: vec![] };
+            class_file.method_table.push(duplicate);
+            "Duplicate method name \"run\" with signature \"()V\"".to_string()
+        },
+        |class_file| {
+            method(class_file, "run").access_flags |= ACC_PROTECTED;
+            "Illegal method modifiers 0x0005 on run".to_string()
+        },
+        |class_file| {
+            method(class_file, "<init>").descriptor_index = utf8(class_file, "()I");
+            "Method \"<init>\" has illegal signature \"()I\"".to_string()
+        },
+        |class_file| {
+            method(class_file, "<init>").access_flags |= ACC_STATIC;
+            "Method <init> has illegal modifiers 0x0009".to_string()
+        },
+        |class_file| {
+            method(class_file, "<clinit>").descriptor_index = utf8(class_file, "(I)V");
+            "Method \"<clinit>\" has illegal signature \"(I)V\"".to_string()
+        },
+        |class_file| {
+            method(class_file, "<clinit>").access_flags &= !ACC_STATIC;
+            "Method <clinit> is not static".to_string()
+        },
+        |class_file| {
+            method(class_file, "run").access_flags |= ACC_ABSTRACT | ACC_FINAL;
+            "Illegal method modifiers 0x0411 on abstract method run".to_string()
+        },
+        |class_file| {
+            // Abstract methods could only be strict before strictfp became the default
+            class_file.major_version = 60;
+            method(class_file, "run").access_flags |= ACC_ABSTRACT | ACC_STRICT;
+            "Illegal method modifiers 0x0C01 on abstract method run".to_string()
+        }
+    ]);
+
+    check_cases("Shape", &[
+        |class_file| {
+            method(class_file, "name").access_flags |= ACC_SYNCHRONIZED;
+            "Illegal method modifiers 0x0021 on interface method name".to_string()
+        },
+        |class_file| {
+            method(class_file, "name").access_flags &= !ACC_PUBLIC;
+            "Illegal method modifiers 0x0000 on interface method name".to_string()
+        },
+        |class_file| {
+            method(class_file, "area").access_flags |= ACC_STATIC;
+            "Illegal method modifiers 0x0409 on abstract method area".to_string()
+        }
+    ]);
+}
+
+#[test]
+fn missing_or_extra_code_is_rejected() {
+    check_cases("Formats", &[
+        |class_file| {
+            method(class_file, "run").attributes.clear();
+            "Absent Code attribute in method run()V that is not native or abstract".to_string()
+        },
+        |class_file| {
+            method(class_file, "run").access_flags |= ACC_NATIVE;
+            "Code attribute in native or abstract method run()V".to_string()
+        },
+        |class_file| {
+            let (run, code) = code_index(class_file, "run");
+            let code = copy(&class_file.method_table[run].attributes[code]);
+            class_file.method_table[run].attributes.push(code);
+            "Multiple Code attributes in method run()V".to_string()
+        }
+    ]);
+
+    check_cases("Shape", &[
+        |class_file| {
+            let (name, code) = code_index(class_file, "name");
+            let code = copy(&class_file.method_table[name].attributes[code]);
+            method(class_file, "area").attributes.push(code);
+            "Code attribute in native or abstract method area()D".to_string()
+        }
+    ]);
+}
+
+#[test]
+fn malformed_attributes_are_rejected() {
+    check_cases("Formats", &[
+        |class_file| {
+            let (run, code) = code_index(class_file, "run");
+            class_file.method_table[run].attributes[code].attribute_length += 1;
+            "Wrong size for attribute Code of method run()V".to_string()
+        },
+        |class_file| {
+            let catch_type = utf8(class_file, "java/lang/ArithmeticException");
+            let (guarded, code_index) = code_index(class_file, "guarded");
+
+            let mut code = match Attribute::new(class_file, &class_file.method_table[guarded].attributes[code_index]) {
+                Some(Attribute::Code(code)) => code,
+                attribute => panic!("guarded has no code but {:?}", attribute)
+            };
+            code.exception_table[0].catch_type = catch_type;
+            let attribute_info = Attribute::Code(code).to_attribute_info(class_file).unwrap();
+
+            class_file.method_table[guarded].attributes[code_index] = attribute_info;
+            format!("Invalid constant pool index {} for exception table of method guarded(I)I, expected a Class entry", catch_type)
+        }
+    ]);
+}
+
+/// The start of a class file up to the constant pool count, for Java 17
+fn header(constant_pool_count: u16) -> Vec<u8> {
+    let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 61];
+    bytes.extend(constant_pool_count.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn unparsable_class_files_raise_class_format_error() {
+    let formats = class_bytes("Formats");
+
+    let mut bad_magic = formats.clone();
+    bad_magic[3] = 0xBF;
+
+    let mut extra_bytes = formats.clone();
+    extra_bytes.push(0);
+
+    let mut unknown_tag = header(2);
+    unknown_tag.push(2);
+
+    let mut last_long = header(2);
+    last_long.extend([5, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+    let cases: [(&str, Vec<u8>, &str); 7] = [
+        ("Empty", vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 0x3D, 0, 0], "Illegal constant pool size 0"),
+        ("Truncated", formats[..formats.len() / 2].to_vec(), "Truncated class file"),
+        ("TruncatedHeader", formats[..6].to_vec(), "Truncated class file"),
+        ("BadMagic", bad_magic, "Incompatible magic value"),
+        ("ExtraBytes", extra_bytes, "Extra bytes at the end of class file"),
+        ("UnknownTag", unknown_tag, "Unknown constant tag 2 at constant pool index 1"),
+        ("LastLong", last_long, "Long or Double at the last constant pool index 1")
+    ];
+
+    let classes = Path::new(env!("CARGO_TARGET_TMPDIR")).join("unparsable-classes");
+    std::fs::create_dir_all(&classes).unwrap();
+    std::fs::write(classes.join("Formats.class"), &formats).unwrap();
+    for (class_name, bytes, _) in &cases {
+        std::fs::write(classes.join(format!("{}.class", class_name)), bytes).unwrap();
+    }
+
+    let mut vm = common::booted_vm(&classes);
+
+    for (class_name, _, message) in &cases {
+        match vm.load_class(class_name) {
+            Err(JavaError::Exception(exception)) => {
+                assert_eq!(exception.class_name, "java.lang.ClassFormatError", "{}", class_name);
+                assert_eq!(exception.message.unwrap_or_default(), format!("{} in class file {}", message, class_name));
+            },
+            result => panic!("Loading {} returned {:?}", class_name, result)
+        }
+    }
+
+    // The classes that parse next to them still load
+    vm.load_class("Formats").expect("Formats can't be loaded");
+}
@@ -0,0 +1,103 @@
+//! Class files that are broken in ways only loading or running them reveals, which have to end up as Java errors
+//! instead of taking the host process down.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use java_vm::java::opcodes::Opcode;
+use java_vm::java::verifier::VerifyMode;
+use java_vm::java::Class;
+use java_vm::{JClass, JValue, JavaError, VirtualMachine};
+
+/// Compiles Java sources written to `CARGO_TARGET_TMPDIR/<name>`
+fn compile_sources(name: &str, sources: &[(&str, &str)]) -> PathBuf {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}-sources", name));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let paths: Vec<_> = sources.iter().map(|(file_name, source)| {
+        let path = directory.join(file_name);
+        std::fs::write(&path, source).unwrap();
+        path.to_string_lossy().into_owned()
+    }).collect();
+
+    common::compile_programs(name, &paths.iter().map(String::as_str).collect::<Vec<_>>())
+}
+
+fn load(classes: &Path, verify_mode: VerifyMode, class_name: &str) -> (VirtualMachine, Result<JClass, JavaError>) {
+    let mut vm = common::vm_with_classes(classes);
+    vm.set_verify_mode(verify_mode);
+    vm.boot().expect("java.base doesn't boot");
+
+    let class = vm.load_class(class_name);
+    (vm, class)
+}
+
+fn exception<T>(result: Result<T, JavaError>) -> (String, String) {
+    match result {
+        Err(JavaError::Exception(exception)) => (exception.class_name, exception.message.unwrap_or_default()),
+        Err(error) => panic!("Expected an exception but got {:?}", error),
+        Ok(_) => panic!("Expected an exception but there was none")
+    }
+}
+
+#[test]
+fn circular_hierarchies_are_rejected() {
+    // Each half of the cycle compiles against a version of the other class that doesn't close it
+    let first = compile_sources("circular-first", &[("A.java", "class A extends B {}"), ("B.java", "class B {}")]);
+    let second = compile_sources("circular-second", &[("A.java", "class A {}"), ("B.java", "class B extends A {}")]);
+
+    let classes = Path::new(env!("CARGO_TARGET_TMPDIR")).join("circular-classes");
+    std::fs::create_dir_all(&classes).unwrap();
+    std::fs::copy(first.join("A.class"), classes.join("A.class")).unwrap();
+    std::fs::copy(second.join("B.class"), classes.join("B.class")).unwrap();
+
+    for verify_mode in [VerifyMode::None, VerifyMode::All] {
+        let (mut vm, class) = load(&classes, verify_mode, "A");
+        assert_eq!(exception(class), ("java.lang.ClassCircularityError".to_string(), "A".to_string()));
+
+        // Neither class is left half loaded
+        assert_eq!(exception(vm.load_class("B")).0, "java.lang.ClassCircularityError");
+    }
+}
+
+/// `Unverified.run(I)I` with the given code, loaded without verification
+fn unverified(name: &str, max_stack: u16, max_locals: u16, code: &[u8]) -> (VirtualMachine, JClass) {
+    let compiled = compile_sources("unverified", &[("Unverified.java", "class Unverified { static int run(int x) { return x; } }")]);
+
+    let class = Class::new(&std::fs::read(compiled.join("Unverified.class")).unwrap()).unwrap();
+    let mut class_file = class.class_file;
+    common::replace_code(&mut class_file, "run", max_stack, max_locals, code);
+
+    let classes = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&classes).unwrap();
+    std::fs::write(classes.join("Unverified.class"), class_file.write().unwrap()).unwrap();
+
+    let (vm, class) = load(&classes, VerifyMode::None, "Unverified");
+    (vm, class.expect("Unverified can't be loaded"))
+}
+
+#[test]
+fn unverified_code_throws_verify_errors() {
+    let broken: [(&str, u16, u16, &[u8], &str); 4] = [
+        ("unverified-underflow", 1, 1, &[Opcode::pop as u8, Opcode::iconst_0 as u8, Opcode::ireturn as u8], "Operand stack underflow in run(I)I at pc 0"),
+        ("unverified-dup", 2, 1, &[Opcode::dup_x1 as u8, Opcode::ireturn as u8], "Operand stack underflow in run(I)I at pc 0"),
+        ("unverified-load", 1, 1, &[Opcode::iload as u8, 5, Opcode::ireturn as u8], "Local variable index 5 out of bounds in run(I)I at pc 0"),
+        ("unverified-store", 1, 1, &[Opcode::iload_0 as u8, Opcode::istore_1 as u8, Opcode::iload_0 as u8, Opcode::ireturn as u8], "Local variable index 1 out of bounds in run(I)I at pc 1")
+    ];
+
+    for (name, max_stack, max_locals, code, message) in broken {
+        let (mut vm, class) = unverified(name, max_stack, max_locals, code);
+
+        let result = vm.invoke_static(class, "run", "(I)I", &[JValue::Int(1)]);
+        assert_eq!(exception(result), ("java.lang.VerifyError".to_string(), message.to_string()), "{}", name);
+
+        // The VM is still usable afterwards
+        assert!(matches!(vm.invoke_static(class, "run", "(I)I", &[JValue::Int(1)]), Err(JavaError::Exception(_))));
+    }
+
+    // The arguments don't fit into the locals
+    let (mut vm, class) = unverified("unverified-arguments", 1, 0, &[Opcode::iconst_0 as u8, Opcode::ireturn as u8]);
+    let (class_name, message) = exception(vm.invoke_static(class, "run", "(I)I", &[JValue::Int(1)]));
+    assert_eq!(class_name, "java.lang.VerifyError", "{}", message);
+}
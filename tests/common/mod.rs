@@ -0,0 +1,168 @@
+//! What the integration tests and benchmarks run on: `java.base.jar` and Java programs compiled with `javac`.
+//!
+//! `java.base.jar` is taken from `JAVA_BASE_JAR`, or else from the crate directory like the launcher does.
+//! Without either it's built once from `jmods/java.base.jmod` of the JDK whose `javac` is on the `PATH`, which has to
+//! be a JDK 17. Tests fail if there's no such JDK, they don't skip.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+use java_vm::java::ClassFile;
+use java_vm::{JClass, JValue, Jar, VirtualMachine};
+
+const SETUP: &str = "set JAVA_BASE_JAR to the java.base.jar of a JDK 17, or put a JDK 17 with its jmods on the PATH";
+
+/// The home directory of the JDK whose `javac` is on the `PATH`
+fn jdk_home() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    let javac = std::env::split_paths(&path).map(|directory| directory.join("javac")).find(|javac| javac.is_file())?;
+
+    Some(javac.canonicalize().ok()?.parent()?.parent()?.to_path_buf())
+}
+
+fn run(command: &mut Command) -> Result<(), String> {
+    let output = command.output().map_err(|error| format!("{:?} couldn't run: {}", command, error))?;
+
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(format!("{:?} failed:\n{}", command, String::from_utf8_lossy(&output.stderr)))
+    }
+}
+
+/// Packs the classes of `java.base.jmod` into a jar, leaving out `module-info.class`
+fn build_java_base_jar(jdk_home: &Path, jar: &Path) -> Result<(), String> {
+    let extracted = Path::new(env!("CARGO_TARGET_TMPDIR")).join("java.base");
+    let _ = std::fs::remove_dir_all(&extracted);
+
+    run(Command::new(jdk_home.join("bin/jmod")).arg("extract").arg("--dir").arg(&extracted).arg(jdk_home.join("jmods/java.base.jmod")))?;
+
+    let classes = extracted.join("classes");
+    std::fs::remove_file(classes.join("module-info.class")).map_err(|error| error.to_string())?;
+
+    // Written next to the jar first, so an interrupted build doesn't leave a truncated jar behind
+    let partial = jar.with_extension("jar.partial");
+    run(Command::new(jdk_home.join("bin/jar")).arg("cf").arg(&partial).arg("-C").arg(&classes).arg("."))?;
+    std::fs::rename(&partial, jar).map_err(|error| error.to_string())?;
+
+    let _ = std::fs::remove_dir_all(&extracted);
+
+    Ok(())
+}
+
+/// Path of `java.base.jar`, panics with how to provide it if there's none
+pub fn java_base_jar() -> PathBuf {
+    static JAVA_BASE_JAR: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+
+    let result = JAVA_BASE_JAR.get_or_init(|| {
+        if let Some(path) = std::env::var_os("JAVA_BASE_JAR") {
+            let path = PathBuf::from(path);
+            return match path.exists() {
+                true => Ok(path),
+                false => Err(format!("JAVA_BASE_JAR is set to {}, which doesn't exist", path.display()))
+            };
+        }
+
+        let local = Path::new(env!("CARGO_MANIFEST_DIR")).join("java.base.jar");
+        if local.exists() {
+            return Ok(local);
+        }
+
+        let built = Path::new(env!("CARGO_TARGET_TMPDIR")).join("java.base.jar");
+        if built.exists() {
+            return Ok(built);
+        }
+
+        let jdk_home = jdk_home().ok_or_else(|| "javac isn't on the PATH".to_string())?;
+        build_java_base_jar(&jdk_home, &built).map(|_| built)
+    });
+
+    match result {
+        Ok(path) => path.clone(),
+        Err(reason) => panic!("java.base.jar isn't available, {}: {}", SETUP, reason)
+    }
+}
+
+/// Compiles Java sources of the crate into `CARGO_TARGET_TMPDIR/<name>` once per test binary, panics if `javac` fails
+pub fn compile_programs(name: &str, sources: &[&str]) -> PathBuf {
+    static COMPILED: Mutex<Option<HashMap<String, PathBuf>>> = Mutex::new(None);
+
+    let mut compiled = COMPILED.lock().unwrap_or_else(|error| error.into_inner());
+    if let Some(output) = compiled.get_or_insert_with(HashMap::new).get(name) {
+        return output.clone();
+    }
+
+    let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let sources = sources.iter().map(|source| Path::new(env!("CARGO_MANIFEST_DIR")).join(source));
+
+    if let Err(reason) = run(Command::new("javac").args(["--release", "17", "-d"]).arg(&output).args(sources)) {
+        panic!("The {} programs couldn't be compiled, put a JDK 17 on the PATH: {}", name, reason);
+    }
+
+    compiled.get_or_insert_with(HashMap::new).insert(name.to_string(), output.clone());
+
+    output
+}
+
+/// A VM with `java.base` and the given directory of classes, which isn't booted yet
+pub fn vm_with_classes(classes: &Path) -> VirtualMachine {
+    let mut vm = VirtualMachine::new();
+    vm.add_class_path(Jar::from_directory(&classes.to_string_lossy()).expect("Test classes can't be read"));
+    vm.add_library_jar(Jar::new(&java_base_jar().to_string_lossy()).expect("java.base.jar can't be read"));
+
+    vm
+}
+
+pub fn booted_vm(classes: &Path) -> VirtualMachine {
+    let mut vm = vm_with_classes(classes);
+    vm.boot().expect("java.base doesn't boot");
+
+    vm
+}
+
+/// Compiles a program like `compile_programs`, boots a VM with it and loads its class `class_name`
+pub fn load_program(name: &str, sources: &[&str], class_name: &str) -> (VirtualMachine, JClass) {
+    let classes = compile_programs(name, sources);
+
+    let mut vm = booted_vm(&classes);
+    let class = vm.load_class(class_name).unwrap_or_else(|error| panic!("{} can't be loaded: {:?}", class_name, error));
+
+    (vm, class)
+}
+
+/// Calls a static method returning a `String`, panics if it returns anything else
+pub fn string_result(vm: &mut VirtualMachine, class: JClass, name: &str, descriptor: &str, args: &[JValue]) -> String {
+    match vm.invoke_static(class, name, descriptor, args) {
+        Ok(JValue::Object(string)) => vm.get_string(string).expect("Not a string"),
+        result => panic!("{}{} returned {:?}", name, descriptor, result)
+    }
+}
+
+/// A Code attribute without exception table and attributes
+fn code_attribute(max_stack: u16, max_locals: u16, code: &[u8]) -> Vec<u8> {
+    let mut attribute = vec![];
+    attribute.extend(max_stack.to_be_bytes());
+    attribute.extend(max_locals.to_be_bytes());
+    attribute.extend((code.len() as u32).to_be_bytes());
+    attribute.extend(code);
+    attribute.extend([0, 0, 0, 0]);
+
+    attribute
+}
+
+/// Replaces the code of a method of a class file, to get byte code `javac` doesn't generate
+pub fn replace_code(class_file: &mut ClassFile, name: &str, max_stack: u16, max_locals: u16, code: &[u8]) {
+    let method_index = class_file.method_table.iter()
+        .position(|method| class_file.get_constant_pool_string(method.name_index as usize).as_deref() == Some(name))
+        .expect("No such method");
+    let code_index = class_file.method_table[method_index].attributes.iter()
+        .position(|attribute| class_file.get_constant_pool_string(attribute.attribute_name_index as usize).as_deref() == Some("Code"))
+        .expect("Method has no code");
+
+    let attribute = &mut class_file.method_table[method_index].attributes[code_index];
+    attribute.info = code_attribute(max_stack, max_locals, code);
+    attribute.attribute_length = attribute.info.len() as u32;
+}
@@ -0,0 +1,42 @@
+//! `System.out` and `System.err` of `tests/programs/Console.java` redirected to buffers, with the intrinsic
+//! `PrintStream`s of an unbooted VM and with the streams of a booted `java.base`.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use java_vm::java::console::ConsoleOutput;
+use java_vm::{JValue, VirtualMachine};
+
+const OUT: &str = "true c -7 1234567890123 1.5 -0.25 xy text null null 5\n\
+                   false\nd\n-2147483648\n9223372036854775807\nNaN\nInfinity\nhi\nline\nbuilt\n";
+const ERR: &str = "error 42\n";
+
+/// Runs `Console.print` and returns what it wrote to `System.out` and `System.err`
+fn print(mut vm: VirtualMachine) -> (String, String) {
+    let out = Arc::new(Mutex::new(Vec::new()));
+    let err = Arc::new(Mutex::new(Vec::new()));
+    vm.set_stdout(ConsoleOutput::Buffer(out.clone()));
+    vm.set_stderr(ConsoleOutput::Buffer(err.clone()));
+
+    let class = vm.load_class("Console").expect("Console can't be loaded");
+    assert!(matches!(vm.invoke_static(class, "print", "()V", &[]), Ok(JValue::Void)));
+
+    let out = String::from_utf8(out.lock().unwrap().clone()).unwrap();
+    let err = String::from_utf8(err.lock().unwrap().clone()).unwrap();
+    (out, err)
+}
+
+#[test]
+fn intrinsic_streams_print_every_overload() {
+    let classes = common::compile_programs("console-classes", &["tests/programs/Console.java"]);
+
+    assert_eq!(print(common::vm_with_classes(&classes)), (OUT.to_string(), ERR.to_string()));
+}
+
+#[test]
+fn booted_streams_print_every_overload() {
+    let classes = common::compile_programs("console-classes", &["tests/programs/Console.java"]);
+
+    assert_eq!(print(common::booted_vm(&classes)), (OUT.to_string(), ERR.to_string()));
+}
@@ -0,0 +1,25 @@
+//! `tests/programs/Deadlock.java`, whose threads wait for each other's locks: the VM halts and dumps the threads to
+//! `System.err` with their frames written like `StackTraceElement.toString`.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use java_vm::java::console::ConsoleOutput;
+use java_vm::java::vm::Halt;
+use java_vm::JavaError;
+
+#[test]
+fn deadlocks_dump_source_locations() {
+    let (mut vm, class) = common::load_program("deadlock-classes", &["tests/programs/Deadlock.java"], "Deadlock");
+    let stderr = Arc::new(Mutex::new(Vec::new()));
+    vm.set_stderr(ConsoleOutput::Buffer(stderr.clone()));
+
+    assert!(matches!(vm.invoke_static(class, "run", "()V", &[]), Err(JavaError::Halted(Halt::Deadlock))));
+
+    let dump = String::from_utf8(stderr.lock().unwrap().clone()).unwrap();
+    assert!(dump.contains("\"other\""), "{}", dump);
+    assert!(dump.contains("\tat Deadlock.lockBoth(Deadlock.java:8)\n"), "{}", dump);
+    assert!(dump.contains("\tat Deadlock.run(Deadlock.java:26)\n"), "{}", dump);
+    assert!(!dump.contains("(pc "), "{}", dump);
+}
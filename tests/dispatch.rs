@@ -0,0 +1,66 @@
+//! Inline caches of the call sites in `tests/programs/Dispatch.java`: which of the counters of
+//! `VirtualMachine::dispatch_stats` the calls of monomorphic, polymorphic and megamorphic call sites increment.
+
+mod common;
+
+use java_vm::{DispatchStats, JClass, JValue, VirtualMachine};
+
+fn dispatch_program() -> (VirtualMachine, JClass) {
+    let (mut vm, class) = common::load_program("dispatch-classes", &["tests/programs/Dispatch.java"], "Dispatch");
+
+    // Compiled code doesn't count its calls
+    #[cfg(feature = "jit")]
+    vm.set_jit(None);
+
+    vm.invoke_static(class, "prepare", "()V", &[]).unwrap();
+    (vm, class)
+}
+
+/// Calls a method of `Dispatch` with a count, returns its result and how the counters changed as
+/// `(monomorphic_hits, polymorphic_hits, misses, megamorphic_calls)`
+fn counted(vm: &mut VirtualMachine, class: JClass, name: &str, count: i32) -> (JValue, [u64; 4]) {
+    fn counters(stats: &DispatchStats) -> [u64; 4] {
+        [stats.monomorphic_hits, stats.polymorphic_hits, stats.misses, stats.megamorphic_calls]
+    }
+
+    let before = counters(vm.dispatch_stats());
+    let result = vm.invoke_static(class, name, "(I)I", &[JValue::Int(count)]).unwrap();
+    let after = counters(vm.dispatch_stats());
+
+    (result, std::array::from_fn(|index| after[index] - before[index]))
+}
+
+#[test]
+fn monomorphic_call_sites_hit_after_the_first_call() {
+    let (mut vm, class) = dispatch_program();
+
+    assert_eq!(counted(&mut vm, class, "monomorphic", 100), (JValue::Int(300), [99, 0, 1, 0]));
+    // The call site keeps its cache across calls of the method
+    assert_eq!(counted(&mut vm, class, "monomorphic", 10), (JValue::Int(30), [10, 0, 0, 0]));
+}
+
+#[test]
+fn polymorphic_call_sites_cache_every_receiver_class() {
+    let (mut vm, class) = dispatch_program();
+
+    assert_eq!(counted(&mut vm, class, "polymorphic", 100), (JValue::Int(350), [0, 98, 2, 0]));
+    // Only triangles reach the call of sides() in Shape.code, Square overrides it
+    assert_eq!(counted(&mut vm, class, "interfaces", 100), (JValue::Int(50 * 30 + 50), [49, 98, 3, 0]));
+}
+
+#[test]
+fn megamorphic_call_sites_look_up_uncached_classes() {
+    let (mut vm, class) = dispatch_program();
+
+    // Four classes are cached, every call with the fifth one looks the method up
+    assert_eq!(counted(&mut vm, class, "megamorphic", 100), (JValue::Int(20 * 18), [0, 76, 4, 20]));
+    assert_eq!(counted(&mut vm, class, "megamorphic", 5), (JValue::Int(18), [0, 4, 0, 1]));
+}
+
+#[test]
+fn call_sites_have_their_own_caches() {
+    let (mut vm, class) = dispatch_program();
+
+    counted(&mut vm, class, "megamorphic", 10);
+    assert_eq!(counted(&mut vm, class, "monomorphic", 10), (JValue::Int(30), [9, 0, 1, 0]));
+}
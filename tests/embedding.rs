@@ -0,0 +1,91 @@
+//! The embedding API driving `tests/programs/embed/Account.java` from Rust: objects, methods and fields with typed
+//! values, Java exceptions as `JavaError`, and objects handed to Rust staying alive until they're released.
+
+mod common;
+
+use java_vm::{JClass, JObject, JValue, JavaError, VirtualMachine};
+
+fn account_class() -> (VirtualMachine, JClass) {
+    common::load_program("embedding-classes", &["tests/programs/embed/Account.java"], "embed/Account")
+}
+
+#[test]
+fn objects_methods_and_fields_are_typed() {
+    let (mut vm, class) = account_class();
+
+    let owner = vm.new_string("Ada").unwrap();
+    let account = vm.new_object(class, "(Ljava/lang/String;J)V", &[owner.into(), 100i64.into()]).unwrap();
+
+    let balance: i64 = vm.invoke_virtual(account, "deposit", "(I)J", &[50.into()]).unwrap().try_into().unwrap();
+    assert_eq!(balance, 150);
+    let rich: bool = vm.invoke_virtual(account, "isRich", "()Z", &[]).unwrap().try_into().unwrap();
+    assert!(!rich);
+
+    vm.set_field(account, "balance", "J", JValue::Long(2_000_000)).unwrap();
+    assert_eq!(vm.get_field(account, "balance", "J").unwrap(), JValue::Long(2_000_000));
+    let owner = vm.get_field(account, "owner", "Ljava/lang/String;").unwrap().try_into().unwrap();
+    assert_eq!(vm.get_string(owner).unwrap(), "Ada");
+
+    let description = vm.invoke_virtual(account, "describe", "()Ljava/lang/String;", &[]).unwrap().try_into().unwrap();
+    assert_eq!(vm.get_string(description).unwrap(), "Ada: 2000000");
+
+    assert_eq!(vm.get_static_field(class, "opened", "I").unwrap(), JValue::Int(1));
+    vm.set_static_field(class, "opened", "I", JValue::Int(41)).unwrap();
+    assert_eq!(vm.get_static_field(class, "opened", "I").unwrap(), JValue::Int(41));
+
+    let interest = vm.invoke_static(class, "interest", "(DFCBS)D",
+                                    &[0.5f64.into(), 2.0f32.into(), JValue::Char('A' as u16), JValue::Byte(2), JValue::Short(3)]);
+    assert_eq!(interest.unwrap(), JValue::Double(71.0));
+
+    assert_eq!(vm.object_class(account), Some(class));
+}
+
+#[test]
+fn exceptions_and_misuse_are_errors() {
+    let (mut vm, class) = account_class();
+    let owner = vm.new_string("Bob").unwrap();
+    let account = vm.new_object(class, "(Ljava/lang/String;J)V", &[owner.into(), 0i64.into()]).unwrap();
+
+    match vm.invoke_virtual(account, "deposit", "(I)J", &[(-5).into()]) {
+        Err(JavaError::Exception(exception)) => {
+            assert_eq!(exception.class_name, "java.lang.IllegalArgumentException");
+            assert_eq!(exception.message.as_deref(), Some("Deposits have to be positive, not -5"));
+
+            let top = &exception.stack_trace[0];
+            assert_eq!((top.class_name.as_str(), top.method_name.as_str(), top.file_name.as_deref()), ("embed.Account", "deposit", Some("Account.java")));
+            assert!(top.line_number.is_some(), "{}", exception);
+        },
+        result => panic!("deposit(-5) returned {:?}", result)
+    }
+
+    assert!(matches!(vm.invoke_virtual(account, "deposit", "(I)J", &[JValue::Long(5)]), Err(JavaError::TypeMismatch { .. })));
+    assert!(matches!(vm.invoke_virtual(account, "deposit", "(I)J", &[]), Err(JavaError::ArgumentCount { expected: 1, found: 0 })));
+    assert!(matches!(vm.load_class("embed/Missing"), Err(JavaError::Exception(exception)) if exception.class_name == "java.lang.NoClassDefFoundError"));
+
+    let result: Result<i32, JavaError> = JValue::Boolean(true).try_into();
+    assert!(matches!(result, Err(JavaError::TypeMismatch { .. })));
+}
+
+#[test]
+fn objects_stay_alive_until_released() {
+    let (mut vm, class) = account_class();
+
+    let owner = vm.new_string("Cleo").unwrap();
+    let account = vm.new_object(class, "(Ljava/lang/String;J)V", &[owner.into(), 1i64.into()]).unwrap();
+    vm.release(owner);
+
+    vm.invoke_static(class, "track", "(Ljava/lang/Object;)V", &[account.into()]).unwrap();
+    let collected = |vm: &mut VirtualMachine| vm.invoke_static(class, "trackedCollected", "()Z", &[]).unwrap();
+
+    assert_eq!(collected(&mut vm), JValue::Boolean(false));
+
+    // Handed out a second time, it has to be released twice
+    let same: JObject = vm.invoke_virtual(account, "self", "()Lembed/Account;", &[]).unwrap().try_into().unwrap();
+    assert_eq!(same, account);
+
+    vm.release(account);
+    assert_eq!(collected(&mut vm), JValue::Boolean(false));
+
+    vm.release(same);
+    assert_eq!(collected(&mut vm), JValue::Boolean(true));
+}
@@ -0,0 +1,49 @@
+//! `tests/programs/Garbage.java` run with a maximum heap size a few megabytes above what `java.base` keeps alive
+//! after booting: garbage has to be collected for it to complete, and holding on to everything has to raise
+//! `OutOfMemoryError` instead of exhausting host memory.
+
+mod common;
+
+use java_vm::{JClass, JValue, JavaError, VirtualMachine};
+
+const HEADROOM: usize = 4 << 20;
+
+fn small_heap_vm() -> (VirtualMachine, JClass) {
+    let (mut vm, class) = common::load_program("gc-classes", &["tests/programs/Garbage.java"], "Garbage");
+
+    vm.collect_garbage();
+    let live_bytes = vm.gc_stats().live_bytes;
+    vm.set_max_heap_size(live_bytes + HEADROOM);
+
+    (vm, class)
+}
+
+#[test]
+fn garbage_is_collected_under_a_small_heap() {
+    let (mut vm, class) = small_heap_vm();
+    let collections = vm.gc_stats().collections;
+
+    // Allocates well over ten times the headroom
+    let result = vm.invoke_static(class, "churn", "(I)J", &[JValue::Int(20_000)]);
+    assert!(matches!(result, Ok(JValue::Long(_))), "{:?}", result);
+
+    let stats = vm.gc_stats();
+    assert!(stats.collections > collections, "{:?}", stats);
+    assert!(stats.freed_bytes as usize > HEADROOM, "{:?}", stats);
+}
+
+#[test]
+fn exhausting_the_heap_raises_out_of_memory_error() {
+    let (mut vm, class) = small_heap_vm();
+
+    match vm.invoke_static(class, "hoard", "()I", &[]) {
+        Err(JavaError::Exception(exception)) => assert_eq!(exception.class_name, "java.lang.OutOfMemoryError"),
+        result => panic!("Garbage.hoard returned {:?}", result)
+    }
+
+    // What was hoarded is garbage once the error is caught
+    match vm.invoke_static(class, "recover", "()Ljava/lang/String;", &[]) {
+        Ok(JValue::Object(string)) => assert_eq!(vm.get_string(string).expect("Not a string"), "Java heap space 507500"),
+        result => panic!("Garbage.recover returned {:?}", result)
+    }
+}
@@ -0,0 +1,183 @@
+//! The byte code decoder and encoder the interpreter, disassembler and verifier share: every method body of
+//! `java.base` has to decode and encode back to the same bytes, and malformed code has to be rejected with an error.
+
+mod common;
+
+use java_vm::java::instruction::{self, ArrayType, DecodeError, EncodeError, Instruction};
+use java_vm::java::opcodes::Opcode;
+use java_vm::Jar;
+
+/// A switch at `pc`, preceded by `nop`s, with its padding and operands
+fn switch_at(pc: usize, opcode: Opcode, operands: &[i32]) -> Vec<u8> {
+    let mut code = vec![Opcode::nop as u8; pc];
+    code.push(opcode as u8);
+    code.resize(code.len() + (4 - (pc + 1) % 4) % 4, 0);
+    code.extend(operands.iter().flat_map(|operand| operand.to_be_bytes()));
+
+    code
+}
+
+fn round_trip(jar: &Jar) -> usize {
+    let mut methods = 0;
+
+    for (file_name, class) in &jar.classes {
+        for method in class.methods.values() {
+            let Some(code) = method.code() else { continue };
+
+            let instructions = instruction::decode(&code.code)
+                .unwrap_or_else(|error| panic!("{} {}{}: {}", file_name, method.name, method.descriptor, error));
+            let encoded = instruction::encode(&instructions)
+                .unwrap_or_else(|error| panic!("{} {}{}: {}", file_name, method.name, method.descriptor, error));
+
+            assert_eq!(encoded, code.code, "{} {}{} doesn't encode to the same bytes", file_name, method.name, method.descriptor);
+            methods += 1;
+        }
+    }
+
+    methods
+}
+
+#[test]
+fn java_base_round_trips() {
+    let jar = Jar::new(&common::java_base_jar().to_string_lossy()).expect("java.base.jar can't be read");
+
+    assert!(round_trip(&jar) > 50_000);
+}
+
+#[test]
+fn compiled_programs_round_trip() {
+    let classes = common::compile_programs("instruction-classes", &["tests/programs/Kernels.java"]);
+    let jar = Jar::from_directory(&classes.to_string_lossy()).expect("Test classes can't be read");
+
+    assert!(round_trip(&jar) > 20);
+}
+
+#[test]
+fn offsets_and_lengths_match() {
+    let code = [
+        Opcode::wide as u8, Opcode::iinc as u8, 1, 0, 0xFF, 0xFF,
+        Opcode::sipush as u8, 0x80, 0x00,
+        Opcode::goto_w as u8, 0xFF, 0xFF, 0xFF, 0xF7,
+        Opcode::r#return as u8
+    ];
+
+    let instructions = instruction::decode_with_offsets(&code).unwrap();
+
+    assert_eq!(instructions, vec![
+        (0, Instruction::Increment { index: 256, value: -1, wide: true }),
+        (6, Instruction::Push(Opcode::sipush, i16::MIN)),
+        (9, Instruction::Branch(Opcode::goto_w, 0)),
+        (14, Instruction::Simple(Opcode::r#return))
+    ]);
+
+    for (pc, instruction) in &instructions {
+        let mut encoded = vec![];
+        instruction.encode(*pc, &mut encoded).unwrap();
+        assert_eq!(encoded.len(), instruction.length(*pc));
+    }
+}
+
+#[test]
+fn switches_are_padded_relative_to_the_code_start() {
+    for pc in 0..4 {
+        let mut code = switch_at(pc, Opcode::tableswitch, &[0, 1, 2, 0, 0]);
+        let end = code.len();
+        code.push(Opcode::r#return as u8);
+
+        // The offsets are relative to the switch, so that they all point at the return after it
+        let relative = (end - pc) as i32;
+        let mut code = switch_at(pc, Opcode::tableswitch, &[relative, 1, 2, relative, relative]);
+        code.push(Opcode::r#return as u8);
+
+        let instructions = instruction::decode_with_offsets(&code).unwrap();
+        assert_eq!(instructions[pc], (pc, Instruction::TableSwitch { default: end as u32, low: 1, high: 2, targets: vec![end as u32; 2] }));
+        assert_eq!(instruction::encode(&instructions.into_iter().map(|(_, instruction)| instruction).collect::<Vec<_>>()).unwrap(), code);
+
+        let mut code = switch_at(pc, Opcode::lookupswitch, &[0, 1, -5, 0]);
+        let end = code.len();
+        code.push(Opcode::r#return as u8);
+
+        let relative = (end - pc) as i32;
+        let mut code = switch_at(pc, Opcode::lookupswitch, &[relative, 1, -5, relative]);
+        code.push(Opcode::r#return as u8);
+
+        let instructions = instruction::decode_with_offsets(&code).unwrap();
+        assert_eq!(instructions[pc], (pc, Instruction::LookupSwitch { default: end as u32, pairs: vec![(-5, end as u32)] }));
+    }
+}
+
+#[test]
+fn truncated_operands_are_rejected() {
+    let truncated: [&[u8]; 10] = [
+        &[Opcode::bipush as u8],
+        &[Opcode::sipush as u8, 0],
+        &[Opcode::ldc_w as u8, 0],
+        &[Opcode::iinc as u8, 1],
+        &[Opcode::wide as u8],
+        &[Opcode::wide as u8, Opcode::iinc as u8, 0, 1, 0],
+        &[Opcode::goto_w as u8, 0, 0, 0],
+        &[Opcode::invokeinterface as u8, 0, 1, 1],
+        &[Opcode::invokedynamic as u8, 0, 1, 0],
+        &[Opcode::multianewarray as u8, 0, 1]
+    ];
+
+    for code in truncated {
+        assert_eq!(Instruction::decode_at(code, 0), Err(DecodeError::UnexpectedEnd { pc: 0 }), "{:?}", code);
+    }
+
+    // The instruction at the end is cut off, not the one before it
+    assert_eq!(instruction::decode(&[Opcode::nop as u8, Opcode::aload as u8]), Err(DecodeError::UnexpectedEnd { pc: 1 }));
+}
+
+#[test]
+fn truncated_switches_are_rejected() {
+    // Ends within the padding, the operands and the jump table
+    assert_eq!(instruction::decode(&[Opcode::tableswitch as u8, 0]), Err(DecodeError::UnexpectedEnd { pc: 0 }));
+    assert_eq!(instruction::decode(&switch_at(1, Opcode::lookupswitch, &[0])), Err(DecodeError::UnexpectedEnd { pc: 1 }));
+    assert_eq!(instruction::decode(&switch_at(0, Opcode::tableswitch, &[0, 0, 3, 0, 0])), Err(DecodeError::UnexpectedEnd { pc: 0 }));
+
+    // More pairs than there are bytes, and a range far larger than the code, which fails without allocating the table
+    assert_eq!(instruction::decode(&switch_at(0, Opcode::lookupswitch, &[0, 2, 1, 0])), Err(DecodeError::UnexpectedEnd { pc: 0 }));
+    assert_eq!(instruction::decode(&switch_at(0, Opcode::tableswitch, &[0, i32::MIN, i32::MAX])), Err(DecodeError::UnexpectedEnd { pc: 0 }));
+}
+
+#[test]
+fn invalid_switch_counts_are_rejected() {
+    assert_eq!(instruction::decode(&switch_at(2, Opcode::tableswitch, &[0, 5, 4])), Err(DecodeError::InvalidSwitchRange { pc: 2, low: 5, high: 4 }));
+    assert_eq!(instruction::decode(&switch_at(2, Opcode::lookupswitch, &[0, -1])), Err(DecodeError::NegativeLookupSwitchCount { pc: 2, count: -1 }));
+    assert_eq!(instruction::decode(&switch_at(0, Opcode::lookupswitch, &[0, i32::MIN])), Err(DecodeError::NegativeLookupSwitchCount { pc: 0, count: i32::MIN }));
+
+    // The default has to be a target in the code as well
+    assert_eq!(instruction::decode(&switch_at(0, Opcode::lookupswitch, &[-1, 0])), Err(DecodeError::InvalidBranchTarget { pc: 0, target: -1 }));
+}
+
+#[test]
+fn invalid_opcodes_and_targets_are_rejected() {
+    assert_eq!(instruction::decode(&[0xCB]), Err(DecodeError::InvalidOpcode { pc: 0, value: 0xCB }));
+    assert_eq!(instruction::decode(&[Opcode::nop as u8, 0xFD]), Err(DecodeError::InvalidOpcode { pc: 1, value: 0xFD }));
+    assert_eq!(instruction::decode(&[Opcode::newarray as u8, 3]), Err(DecodeError::InvalidOpcode { pc: 0, value: 3 }));
+    assert_eq!(instruction::decode(&[Opcode::wide as u8, Opcode::bipush as u8, 0, 0]), Err(DecodeError::InvalidWideOpcode { pc: 0, opcode: Opcode::bipush }));
+
+    assert_eq!(instruction::decode(&[Opcode::goto as u8, 0, 3]), Err(DecodeError::InvalidBranchTarget { pc: 0, target: 3 }));
+    assert_eq!(instruction::decode(&[Opcode::nop as u8, Opcode::ifeq as u8, 0xFF, 0xFE]), Err(DecodeError::InvalidBranchTarget { pc: 1, target: -1 }));
+}
+
+#[test]
+fn operands_that_dont_fit_are_not_encoded() {
+    let invalid = [
+        (Instruction::Push(Opcode::bipush, 128), Opcode::bipush),
+        (Instruction::Local { opcode: Opcode::iload, index: 256, wide: false }, Opcode::iload),
+        (Instruction::Increment { index: 1, value: -129, wide: false }, Opcode::iinc),
+        (Instruction::ConstantPool(Opcode::ldc, 256), Opcode::ldc)
+    ];
+
+    for (instruction, opcode) in invalid {
+        assert_eq!(instruction::encode(&[instruction]), Err(EncodeError::InvalidOperand { pc: 0, opcode }));
+    }
+
+    let far = [Instruction::Simple(Opcode::nop), Instruction::Branch(Opcode::goto, 40_000)];
+    assert_eq!(instruction::encode(&far), Err(EncodeError::BranchOutOfRange { pc: 1, target: 40_000 }));
+
+    let wide = [Instruction::Simple(Opcode::nop), Instruction::Branch(Opcode::goto_w, 40_000), Instruction::NewArray(ArrayType::Long)];
+    assert_eq!(instruction::encode(&wide), Ok(vec![Opcode::nop as u8, Opcode::goto_w as u8, 0, 0, 0x9C, 0x3F, Opcode::newarray as u8, 11]));
+}
\ No newline at end of file
@@ -0,0 +1,77 @@
+//! Call sites of `tests/programs/Bootstraps.java`, linked through the bootstrap methods `javac` uses and a custom one.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use java_vm::java::class::ConstantPoolEntry;
+use java_vm::java::Class;
+use java_vm::JValue;
+
+use common::string_result;
+
+const SOURCES: [&str; 1] = ["tests/programs/Bootstraps.java"];
+
+#[test]
+fn concatenation_keeps_utf16_code_units() {
+    let (mut vm, class) = common::load_program("invokedynamic-classes", &SOURCES, "Bootstraps");
+
+    assert_eq!(string_result(&mut vm, class, "surrogatePair", "()Ljava/lang/String;", &[]), "120 55357 56832");
+    assert_eq!(string_result(&mut vm, class, "unpairedSurrogates", "(I)Ljava/lang/String;", &[JValue::Int(7)]), "55357 55 56832 55296");
+    assert_eq!(string_result(&mut vm, class, "mixedTypes", "(I)Ljava/lang/String;", &[JValue::Int(3)]),
+        "i3 l30000000000 f1.5 d0.75 ztrue cd null sb \u{e9}\u{20ac}");
+}
+
+#[test]
+fn records_are_linked_by_their_bootstrap_method() {
+    let (mut vm, class) = common::load_program("invokedynamic-classes", &SOURCES, "Bootstraps");
+
+    assert_eq!(string_result(&mut vm, class, "record", "(I)Ljava/lang/String;", &[JValue::Int(3)]), "Point[x=3, name=p] true false 205");
+}
+
+#[test]
+fn method_handles_invoke_their_targets() {
+    let (mut vm, class) = common::load_program("invokedynamic-classes", &SOURCES, "Bootstraps");
+
+    assert_eq!(string_result(&mut vm, class, "methodHandles", "()Ljava/lang/String;", &[]), "ab 4 cd");
+}
+
+/// The compiled `Bootstraps` classes with the lambdas linked by `Bootstraps.customSite`, which has the same parameters as
+/// `LambdaMetafactory.metafactory`
+fn custom_bootstraps() -> PathBuf {
+    let classes = common::compile_programs("invokedynamic-classes", &SOURCES);
+    let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("invokedynamic-custom");
+    std::fs::create_dir_all(&output).unwrap();
+
+    for entry in std::fs::read_dir(&classes).unwrap() {
+        let path = entry.unwrap().path();
+        std::fs::copy(&path, output.join(path.file_name().unwrap())).unwrap();
+    }
+
+    let mut class_file = Class::new(&std::fs::read(classes.join("Bootstraps.class")).unwrap()).unwrap().class_file;
+    for entry in &mut class_file.constant_pool {
+        if let ConstantPoolEntry::String { length, string } = entry {
+            let replacement = match string.as_slice() {
+                b"java/lang/invoke/LambdaMetafactory" => "Bootstraps",
+                b"metafactory" => "customSite",
+                _ => continue
+            };
+
+            *string = replacement.as_bytes().to_vec();
+            *length = string.len() as u16;
+        }
+    }
+    std::fs::write(output.join("Bootstraps.class"), class_file.write().unwrap()).unwrap();
+
+    output
+}
+
+#[test]
+fn custom_bootstrap_methods_link_call_sites() {
+    let (mut vm, class) = common::load_program("invokedynamic-classes", &SOURCES, "Bootstraps");
+    assert_eq!(string_result(&mut vm, class, "greeting", "()Ljava/lang/String;", &[]), "lambda");
+
+    let mut vm = common::booted_vm(&custom_bootstraps());
+    let class = vm.load_class("Bootstraps").expect("Bootstraps can't be loaded");
+    assert_eq!(string_result(&mut vm, class, "greeting", "()Ljava/lang/String;", &[]), "custom get lambda ()Supplier");
+}
@@ -0,0 +1,80 @@
+//! Differential test of the JIT: runs the methods of `tests/programs/Kernels.java` on a VM that compiles every method
+//! on its first call and on one that only interprets, and expects the same results and exceptions from both.
+
+#![cfg(feature = "jit")]
+
+mod common;
+
+use java_vm::{JValue, JavaError, JitOptions, VirtualMachine};
+
+/// Static methods of `Kernels`, all taking an `int`
+const METHODS: [(&str, &str); 15] = [
+    ("fibonacci", "(I)I"),
+    ("collatz", "(I)J"),
+    ("divide", "(I)I"),
+    ("divideLong", "(I)J"),
+    ("shifts", "(I)I"),
+    ("conversions", "(I)I"),
+    ("floats", "(I)D"),
+    ("switches", "(I)I"),
+    ("stackShapes", "(I)I"),
+    ("exceptions", "(I)I"),
+    ("uncaught", "(I)I"),
+    ("statics", "(I)I"),
+    ("monitors", "(I)I"),
+    ("calls", "(I)I"),
+    ("matrix", "(I)J")
+];
+
+const ARGUMENTS: [i32; 9] = [0, 1, 2, 7, 10, -3, 1_000, i32::MAX, i32::MIN];
+
+fn booted_vm(jit: Option<JitOptions>) -> VirtualMachine {
+    let classes = common::compile_programs("jit-classes", &["tests/programs/Kernels.java"]);
+
+    let mut vm = common::vm_with_classes(&classes);
+    vm.set_jit(jit);
+    vm.boot().expect("java.base doesn't boot");
+
+    vm
+}
+
+/// The result of a call in a form both VMs can be compared by, NaN included
+fn outcome(result: Result<JValue, JavaError>) -> String {
+    match result {
+        Ok(JValue::Double(value)) => format!("double {:#x}", value.to_bits()),
+        Ok(JValue::Float(value)) => format!("float {:#x}", value.to_bits()),
+        Ok(value) => format!("{:?}", value),
+        Err(JavaError::Exception(exception)) => format!("{}: {:?}", exception.class_name, exception.message),
+        Err(error) => format!("{:?}", error)
+    }
+}
+
+#[test]
+fn compiled_code_matches_interpreter() {
+    let eager = JitOptions { invocation_threshold: 1, backedge_threshold: 1, print_compilation: false };
+    let mut interpreted = booted_vm(None);
+    let mut compiled = booted_vm(Some(eager));
+
+    let interpreted_class = interpreted.load_class("Kernels").expect("Kernels can't be loaded");
+    let compiled_class = compiled.load_class("Kernels").expect("Kernels can't be loaded");
+
+    // Every call runs twice, the second one enters code compiled during the first
+    for (name, descriptor) in METHODS {
+        for argument in ARGUMENTS {
+            let args = [JValue::Int(argument)];
+
+            for _ in 0..2 {
+                let expected = outcome(interpreted.invoke_static(interpreted_class, name, descriptor, &args));
+                let actual = outcome(compiled.invoke_static(compiled_class, name, descriptor, &args));
+                assert_eq!(actual, expected, "Kernels.{}({})", name, argument);
+            }
+        }
+    }
+
+    assert_eq!(interpreted.jit_stats().compiled_methods, 0);
+
+    let stats = compiled.jit_stats();
+    assert_eq!(stats.rejected_methods, 0, "{:?}", stats);
+    assert!(stats.compiled_methods >= METHODS.len() as u64 - 1, "{:?}", stats);
+    assert!(stats.deoptimizations > 0, "{:?}", stats);
+}
\ No newline at end of file
@@ -0,0 +1,55 @@
+//! Natives under `VmLimits`: those that reach the host, including natives embedders register without declaring
+//! they don't, only run if `VmLimits::allowed_natives` allows them.
+
+mod common;
+
+use java_vm::java::vm::Value;
+use java_vm::{JClass, JValue, JavaError, VirtualMachine, VmLimits};
+
+fn limited_vm(allowed_natives: &[&str], sandboxed: bool) -> (VirtualMachine, JClass) {
+    let (mut vm, class) = common::load_program("limits-classes", &["tests/programs/Natives.java"], "Natives");
+    let native = |_: &mut _, args: &[Value]| Ok(Some(Value::from_int(args[0].as_int() * 2)));
+    match sandboxed {
+        true => vm.register_sandboxed_native("Natives", "embedded", "(I)I", native),
+        false => vm.register_native("Natives", "embedded", "(I)I", native)
+    }
+
+    let allowed_natives = allowed_natives.iter().map(|pattern| pattern.to_string()).collect();
+    vm.set_limits(Some(VmLimits { allowed_natives, ..VmLimits::default() }));
+
+    (vm, class)
+}
+
+fn security_exception<T: std::fmt::Debug>(result: Result<T, JavaError>) -> String {
+    match result {
+        Err(JavaError::Exception(exception)) if exception.class_name == "java.lang.SecurityException" => exception.message.unwrap_or_default(),
+        result => panic!("Expected a SecurityException but got {:?}", result)
+    }
+}
+
+#[test]
+fn registered_natives_are_denied_unless_allowed() {
+    let (mut vm, class) = limited_vm(&[], false);
+    let message = security_exception(vm.invoke_static(class, "callEmbedded", "(I)I", &[JValue::Int(21)]));
+    assert!(message.contains("Natives.embedded"), "{}", message);
+
+    let (mut vm, class) = limited_vm(&["Natives.embedded"], false);
+    assert!(matches!(vm.invoke_static(class, "callEmbedded", "(I)I", &[JValue::Int(21)]), Ok(JValue::Int(42))));
+
+    let (mut vm, class) = limited_vm(&[], true);
+    assert!(matches!(vm.invoke_static(class, "callEmbedded", "(I)I", &[JValue::Int(21)]), Ok(JValue::Int(42))));
+}
+
+#[test]
+fn host_natives_are_denied_unless_allowed() {
+    let path = env!("CARGO_MANIFEST_DIR");
+
+    let (mut vm, class) = limited_vm(&[], true);
+    let argument = JValue::Object(vm.new_string(path).unwrap());
+    let message = security_exception(vm.invoke_static(class, "fileExists", "(Ljava/lang/String;)Z", &[argument]));
+    assert!(message.contains("java.io.UnixFileSystem."), "{}", message);
+
+    let (mut vm, class) = limited_vm(&["java.io.UnixFileSystem.*"], true);
+    let argument = JValue::Object(vm.new_string(path).unwrap());
+    assert!(matches!(vm.invoke_static(class, "fileExists", "(Ljava/lang/String;)Z", &[argument]), Ok(JValue::Boolean(true))));
+}
@@ -0,0 +1,109 @@
+import java.lang.reflect.Constructor;
+import java.lang.reflect.Field;
+import java.lang.reflect.Method;
+
+/**
+ * Reflective access to private members the reflection test checks: allowed within a class and its nest, refused
+ * with an IllegalAccessException for other classes until setAccessible(true) suppresses the check.
+ */
+public class Access {
+
+    private int secret = 42;
+
+    private static String hidden() {
+        return "hidden";
+    }
+
+    static class Inner {
+        private int nested = 7;
+
+        private String peek() {
+            return "peeked " + new Access().secret;
+        }
+    }
+
+    public static String ownMembers() throws Exception {
+        Object secret = Access.class.getDeclaredField("secret").get(new Access());
+        Object hidden = Access.class.getDeclaredMethod("hidden").invoke(null);
+        return secret + " " + hidden;
+    }
+
+    public static String nestMateMembers() throws Exception {
+        int nested = Inner.class.getDeclaredField("nested").getInt(new Inner());
+        Object peeked = Inner.class.getDeclaredMethod("peek").invoke(new Inner());
+        return nested + " " + peeked;
+    }
+
+    public static String nestHosts() {
+        return Inner.class.getNestHost().getName() + " " + Inner.class.isNestmateOf(Access.class) + " " + Other.class.isNestmateOf(Access.class);
+    }
+
+    public static String otherField() {
+        try {
+            return "read " + Other.class.getDeclaredField("secret").get(new Other());
+        } catch (ReflectiveOperationException exception) {
+            return exception.getClass().getName() + ": " + exception.getMessage();
+        }
+    }
+
+    public static String otherFieldWrite() {
+        try {
+            Other.class.getDeclaredField("secret").setInt(new Other(), 2);
+            return "written";
+        } catch (ReflectiveOperationException exception) {
+            return exception.getClass().getName();
+        }
+    }
+
+    public static String otherMethod() {
+        try {
+            return "invoked " + Other.class.getDeclaredMethod("hidden").invoke(null);
+        } catch (ReflectiveOperationException exception) {
+            return exception.getClass().getName() + ": " + exception.getMessage();
+        }
+    }
+
+    public static String otherConstructor() {
+        try {
+            Constructor<Other> constructor = Other.class.getDeclaredConstructor(int.class);
+            return "constructed " + constructor.newInstance(3).open;
+        } catch (ReflectiveOperationException exception) {
+            return exception.getClass().getName();
+        }
+    }
+
+    public static String otherPublicMembers() throws Exception {
+        Object open = Other.class.getDeclaredField("open").get(new Other());
+        Object shown = Other.class.getDeclaredMethod("shown").invoke(null);
+        return open + " " + shown;
+    }
+
+    public static String suppressedChecks() throws Exception {
+        Field secret = Other.class.getDeclaredField("secret");
+        secret.setAccessible(true);
+        Method hidden = Other.class.getDeclaredMethod("hidden");
+        hidden.setAccessible(true);
+        return secret.get(new Other()) + " " + hidden.invoke(null);
+    }
+}
+
+class Other {
+
+    private int secret = 1;
+    public int open = 2;
+
+    Other() {
+    }
+
+    private Other(int open) {
+        this.open = open;
+    }
+
+    private static String hidden() {
+        return "other hidden";
+    }
+
+    public static String shown() {
+        return "other shown";
+    }
+}
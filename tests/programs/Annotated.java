@@ -0,0 +1,89 @@
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.annotation.Target;
+import java.util.Arrays;
+
+/**
+ * Annotations with elements of every kind for the annotation test, read through reflection and through the
+ * embedding API, which finds the @Check methods like a test runner would
+ */
+@Annotated.Config(name = "annotated", level = 3, ratio = 0.5, letters = { 'a', 'b' }, policy = RetentionPolicy.SOURCE,
+                  type = String.class, tags = { @Annotated.Tag("first"), @Annotated.Tag("second") })
+@Annotated.Hidden
+public class Annotated {
+
+    @Retention(RetentionPolicy.RUNTIME)
+    public @interface Config {
+        String name();
+        int level() default 1;
+        long big() default 1L << 40;
+        double ratio();
+        float scale() default 1.5f;
+        boolean enabled() default true;
+        byte small() default -1;
+        short medium() default 300;
+        char[] letters();
+        RetentionPolicy policy();
+        Class<?> type() default void.class;
+        Tag[] tags() default {};
+        Tag main() default @Tag("main");
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    public @interface Tag {
+        String value();
+    }
+
+    /** Kept in the class file but not visible at runtime */
+    @Retention(RetentionPolicy.CLASS)
+    public @interface Hidden {
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @Target(ElementType.METHOD)
+    public @interface Check {
+        String expected() default "";
+    }
+
+    @Tag("field")
+    public int annotatedField;
+
+    public int plainField;
+
+    @Check(expected = "one")
+    public static String first() {
+        return "one";
+    }
+
+    public static String helper() {
+        return "helper";
+    }
+
+    @Check
+    @Deprecated
+    public static String second() {
+        return "";
+    }
+
+    public static String reflected() throws Exception {
+        Config config = Annotated.class.getAnnotation(Config.class);
+        Tag field = Annotated.class.getField("annotatedField").getAnnotation(Tag.class);
+        Check check = Annotated.class.getMethod("first").getAnnotation(Check.class);
+
+        return config.name() + " " + config.level() + " " + config.big() + " " + config.ratio() + " " + config.scale() + " "
+            + config.enabled() + " " + config.small() + " " + config.medium() + " " + Arrays.toString(config.letters()) + " "
+            + config.policy() + " " + config.type().getName() + " " + config.tags().length + " " + config.tags()[1].value() + " "
+            + config.main().value() + " | " + field.value() + " " + check.expected() + " "
+            + Annotated.class.getAnnotations().length + " " + Annotated.class.isAnnotationPresent(Hidden.class) + " "
+            + Annotated.class.getMethod("helper").getAnnotations().length + " "
+            + Annotated.class.getMethod("second").isAnnotationPresent(Deprecated.class);
+    }
+
+    public static String equality() {
+        Tag first = Annotated.class.getAnnotation(Config.class).tags()[0];
+        Tag again = Annotated.class.getAnnotation(Config.class).tags()[0];
+
+        return first.equals(again) + " " + (first.hashCode() == again.hashCode()) + " " + first.annotationType().getSimpleName() + " " + first;
+    }
+}
@@ -0,0 +1,93 @@
+import java.lang.invoke.MethodHandles;
+import java.lang.invoke.VarHandle;
+import java.util.ArrayList;
+import java.util.List;
+import java.util.concurrent.ExecutorService;
+import java.util.concurrent.Executors;
+import java.util.concurrent.Future;
+import java.util.concurrent.FutureTask;
+import java.util.concurrent.atomic.AtomicBoolean;
+import java.util.concurrent.atomic.AtomicInteger;
+import java.util.concurrent.atomic.AtomicReference;
+
+/**
+ * Atomics of `java.util.concurrent` that update their fields through a `VarHandle`, run by the atomics test.
+ */
+public class Atomics {
+
+    public static String reference() {
+        AtomicReference<String> reference = new AtomicReference<>("a");
+        boolean swapped = reference.compareAndSet("a", "b");
+        boolean stale = reference.compareAndSet("a", "c");
+
+        return swapped + " " + stale + " " + reference.getAndSet("d") + " " + reference.get();
+    }
+
+    public static String bool() {
+        AtomicBoolean flag = new AtomicBoolean();
+        boolean previous = flag.getAndSet(true);
+
+        return previous + " " + flag.get() + " " + flag.compareAndSet(false, true);
+    }
+
+    int count;
+    long total = -1L << 40;
+    double ratio = 0.5;
+    String name = "first";
+
+    /** Compares and exchanges fields of every width, a stale expected value leaves the field as it is */
+    public static String fields() throws Exception {
+        MethodHandles.Lookup lookup = MethodHandles.lookup();
+        VarHandle count = lookup.findVarHandle(Atomics.class, "count", int.class);
+        VarHandle total = lookup.findVarHandle(Atomics.class, "total", long.class);
+        VarHandle ratio = lookup.findVarHandle(Atomics.class, "ratio", double.class);
+        VarHandle name = lookup.findVarHandle(Atomics.class, "name", String.class);
+        Atomics atomics = new Atomics();
+
+        int previousCount = (int) count.compareAndExchange(atomics, 0, -7);
+        boolean staleCount = count.compareAndSet(atomics, 0, 1);
+        long previousTotal = (long) total.compareAndExchange(atomics, -1L << 40, Long.MAX_VALUE);
+        boolean staleTotal = total.compareAndSet(atomics, -1L << 40, 0L);
+        boolean swappedRatio = ratio.compareAndSet(atomics, 0.5, -0.25);
+        String previousName = (String) name.compareAndExchange(atomics, "first", "second");
+        boolean staleName = name.compareAndSet(atomics, "first", "third");
+
+        return previousCount + " " + staleCount + " " + atomics.count + " " + previousTotal + " " + staleTotal + " " + atomics.total + " "
+            + swappedRatio + " " + atomics.ratio + " " + previousName + " " + staleName + " " + atomics.name;
+    }
+
+    public static int futureTask() throws Exception {
+        FutureTask<Integer> task = new FutureTask<>(() -> 6 * 7);
+        Thread thread = new Thread(task);
+        thread.start();
+
+        return task.get();
+    }
+
+    /** Increments shared atomics from every task, the updates of one task must not be lost to another */
+    public static String executor(int tasks, int increments) throws Exception {
+        AtomicInteger counter = new AtomicInteger();
+        AtomicReference<String> last = new AtomicReference<>("");
+        ExecutorService executor = Executors.newFixedThreadPool(4);
+
+        List<Future<Integer>> futures = new ArrayList<>();
+        for (int i = 0; i < tasks; i++) {
+            int task = i;
+            futures.add(executor.submit(() -> {
+                for (int j = 0; j < increments; j++) {
+                    counter.incrementAndGet();
+                }
+                last.set("done");
+                return task;
+            }));
+        }
+
+        int sum = 0;
+        for (Future<Integer> future : futures) {
+            sum += future.get();
+        }
+        executor.shutdown();
+
+        return counter.get() + " " + sum + " " + last.get();
+    }
+}
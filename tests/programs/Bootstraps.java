@@ -0,0 +1,91 @@
+import java.lang.invoke.CallSite;
+import java.lang.invoke.ConstantCallSite;
+import java.lang.invoke.MethodHandle;
+import java.lang.invoke.MethodHandles;
+import java.lang.invoke.MethodType;
+import java.util.function.Supplier;
+
+/**
+ * Call sites the invokedynamic test links, string concatenation keeps the UTF-16 code units it's given. Records and
+ * custom bootstrap methods are linked by Java code.
+ */
+public class Bootstraps {
+
+    record Point(int x, String name) {}
+
+    /** UTF-16 code units of a string separated by spaces, built without string concatenation */
+    static String units(String string) {
+        StringBuilder builder = new StringBuilder();
+        for (int i = 0; i < string.length(); i++) {
+            if (i > 0) {
+                builder.append(' ');
+            }
+            builder.append((int) string.charAt(i));
+        }
+
+        return builder.toString();
+    }
+
+    public static String surrogatePair() {
+        char high = '\uD83D';
+        char low = '\uDE00';
+
+        return units("x" + high + low);
+    }
+
+    public static String unpairedSurrogates(int value) {
+        String low = "\uDE00";
+
+        return units("\uD83D" + value + low + '\uD800');
+    }
+
+    public static String mixedTypes(int value) {
+        Object nothing = null;
+        StringBuilder builder = new StringBuilder("sb");
+
+        return "i" + value + " l" + (value * 10_000_000_000L) + " f" + (value / 2f) + " d" + (value / 4d) + " z" + (value > 0)
+            + " c" + (char) ('a' + value) + " " + nothing + " " + builder + " \u00e9\u20ac";
+    }
+
+    /** toString, equals and hashCode of a record, whose call sites ObjectMethods.bootstrap links */
+    public static String record(int x) {
+        Point point = new Point(x, "p");
+
+        return point + " " + point.equals(new Point(x, "p")) + " " + point.equals(new Point(x + 1, "p")) + " " + point.hashCode();
+    }
+
+    public static String methodHandles() throws Throwable {
+        MethodHandles.Lookup lookup = MethodHandles.lookup();
+        MethodHandle concat = lookup.findVirtual(String.class, "concat", MethodType.methodType(String.class, String.class));
+        MethodHandle max = lookup.findStatic(Math.class, "max", MethodType.methodType(int.class, int.class, int.class));
+
+        String exact = (String) concat.invokeExact("a", "b");
+        Object boxed = max.invoke((Object) 3, (Object) 4);
+        String bound = (String) concat.bindTo("c").invokeExact("d");
+
+        return exact + " " + boxed + " " + bound;
+    }
+
+    public static String greeting() {
+        Supplier<String> supplier = () -> "lambda";
+
+        return supplier.get();
+    }
+
+    /**
+     * A bootstrap method with the parameters of LambdaMetafactory.metafactory, the test makes the lambdas of this class
+     * call it instead
+     */
+    public static CallSite customSite(MethodHandles.Lookup lookup, String name, MethodType type, MethodType erased, MethodHandle implementation,
+            MethodType instantiated) throws Throwable {
+        String value = (String) implementation.invokeExact();
+        Supplier<String> supplier = new Supplier<>() {
+            public String get() {
+                return "custom " + name + " " + value + " " + type;
+            }
+        };
+
+        return new ConstantCallSite(MethodHandles.constant(Supplier.class, supplier));
+    }
+
+}
@@ -0,0 +1,17 @@
+/**
+ * A system class loader whose constructor calls a native method the VM doesn't implement, which makes
+ * System.initPhase3 fail when the boot test installs it with -Djava.system.class.loader
+ */
+public class BrokenLoader extends ClassLoader {
+
+    public BrokenLoader(ClassLoader parent) {
+        super(parent);
+        connect("loader");
+    }
+
+    private static native int connect(String name);
+
+    public static void main(String[] args) {
+        System.out.println("booted");
+    }
+}
@@ -0,0 +1,45 @@
+/**
+ * Every overload of print and println on System.out, and some on System.err, for the console test
+ */
+public class Console {
+
+    public static void print() {
+        System.out.print(true);
+        System.out.print(' ');
+        System.out.print('c');
+        System.out.print(' ');
+        System.out.print(-7);
+        System.out.print(' ');
+        System.out.print(1234567890123L);
+        System.out.print(' ');
+        System.out.print(1.5f);
+        System.out.print(' ');
+        System.out.print(-0.25);
+        System.out.print(' ');
+        System.out.print(new char[] { 'x', 'y' });
+        System.out.print(' ');
+        System.out.print("text");
+        System.out.print(' ');
+        System.out.print((Object) null);
+        System.out.print(' ');
+        System.out.print((String) null);
+        System.out.print(' ');
+        System.out.print(Integer.valueOf(5));
+        System.out.println();
+
+        System.out.println(false);
+        System.out.println('d');
+        System.out.println(Integer.MIN_VALUE);
+        System.out.println(Long.MAX_VALUE);
+        System.out.println(Float.NaN);
+        System.out.println(Double.POSITIVE_INFINITY);
+        System.out.println(new char[] { 'h', 'i' });
+        System.out.println("line");
+        System.out.println(new StringBuilder("built"));
+        System.out.flush();
+
+        System.err.print("error ");
+        System.err.println(42);
+        System.err.flush();
+    }
+}
@@ -0,0 +1,29 @@
+/**
+ * Two threads taking the same two locks in opposite order, run by the deadlock test
+ */
+public class Deadlock {
+
+    static void lockBoth(Object first, Object second) {
+        synchronized (first) {
+            synchronized (second) {
+                System.out.println("not deadlocked");
+            }
+        }
+    }
+
+    public static void run() {
+        Object left = new Object();
+        Object right = new Object();
+
+        synchronized (left) {
+            Thread thread = new Thread(() -> lockBoth(right, left), "other");
+            thread.start();
+
+            // The other thread holds the right lock once it waits for the left one
+            while (thread.getState() != Thread.State.BLOCKED) {
+                Thread.yield();
+            }
+            lockBoth(right, left);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+/**
+ * Call sites for the dispatch test, each static method has a single virtual or interface call site that sees one,
+ * two or five receiver classes. `prepare` creates the receivers first, so the counted calls don't load classes.
+ */
+public class Dispatch {
+
+    static Shape[] shapes;
+
+    public static void prepare() {
+        shapes = new Shape[] { new Triangle(), new Square(), new Pentagon(), new Hexagon(), new Circle() };
+    }
+
+    public static int monomorphic(int count) {
+        Shape shape = shapes[0];
+        int sum = 0;
+        for (int i = 0; i < count; i++) {
+            sum += shape.sides();
+        }
+        return sum;
+    }
+
+    public static int polymorphic(int count) {
+        int sum = 0;
+        for (int i = 0; i < count; i++) {
+            sum += shapes[i % 2].sides();
+        }
+        return sum;
+    }
+
+    public static int megamorphic(int count) {
+        int sum = 0;
+        for (int i = 0; i < count; i++) {
+            sum += shapes[i % 5].sides();
+        }
+        return sum;
+    }
+
+    public static int interfaces(int count) {
+        int sum = 0;
+        for (int i = 0; i < count; i++) {
+            Coded coded = shapes[i % 2];
+            sum += coded.code();
+        }
+        return sum;
+    }
+}
+
+interface Coded {
+    int code();
+}
+
+abstract class Shape implements Coded {
+    abstract int sides();
+
+    public int code() {
+        return sides() * 10;
+    }
+}
+
+class Triangle extends Shape {
+    int sides() { return 3; }
+}
+
+class Square extends Shape {
+    int sides() { return 4; }
+
+    public int code() {
+        return 1;
+    }
+}
+
+class Pentagon extends Shape {
+    int sides() { return 5; }
+}
+
+class Hexagon extends Shape {
+    int sides() { return 6; }
+}
+
+class Circle extends Shape {
+    int sides() { return 0; }
+}
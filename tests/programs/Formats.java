@@ -0,0 +1,47 @@
+import java.util.function.Supplier;
+
+/**
+ * A class with a bit of everything the format checker looks at, the class format test breaks its class file in
+ * one place at a time.
+ */
+public class Formats implements Runnable {
+
+    static final int ANSWER = 42;
+    static int[] values = { 1, 2, 3 };
+
+    volatile long counter;
+
+    static {
+        values[0] = ANSWER;
+    }
+
+    public Formats() {
+    }
+
+    public void run() {
+        counter++;
+    }
+
+    static Supplier<String> supplier() {
+        return () -> "supplied";
+    }
+
+    static int guarded(int divisor) {
+        try {
+            return ANSWER / divisor;
+        } catch (ArithmeticException exception) {
+            return 0;
+        }
+    }
+}
+
+interface Shape {
+
+    int SIDES = 4;
+
+    double area();
+
+    default String name() {
+        return "shape";
+    }
+}
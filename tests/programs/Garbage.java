@@ -0,0 +1,47 @@
+import java.util.ArrayList;
+import java.util.List;
+
+/**
+ * Allocation-heavy code the garbage collection test runs under a small maximum heap size: garbage that is
+ * dropped right away, and objects that are held on to until the heap runs out.
+ */
+public class Garbage {
+
+    /** Allocates far more than the heap holds in short-lived arrays, strings and lists */
+    public static long churn(int rounds) {
+        long sum = 0;
+        for (int i = 0; i < rounds; i++) {
+            int[] numbers = new int[256];
+            numbers[i % numbers.length] = i;
+
+            List<String> strings = new ArrayList<>();
+            for (int j = 0; j < 8; j++) {
+                strings.add("item " + j);
+            }
+
+            sum += numbers[i % numbers.length] + strings.size();
+        }
+
+        return sum;
+    }
+
+    /** Holds on to every array it allocates, never returns normally */
+    public static int hoard() {
+        List<long[]> arrays = new ArrayList<>();
+        while (true) {
+            arrays.add(new long[1024]);
+        }
+    }
+
+    /** Runs out of heap and recovers once the hoarded arrays are garbage */
+    public static String recover() {
+        int hoarded = 0;
+        try {
+            hoarded = hoard();
+        } catch (OutOfMemoryError error) {
+            return error.getMessage() + " " + churn(1000);
+        }
+
+        return "not exhausted " + hoarded;
+    }
+}
@@ -0,0 +1,275 @@
+/** Methods the JIT test runs compiled and interpreted, each covers byte code whose edge cases compiled code has to get right */
+public class Kernels {
+
+    interface Shape {
+        int area();
+    }
+
+    record Square(int side) implements Shape {
+        public int area() { return side * side; }
+    }
+
+    record Rectangle(int width, int height) implements Shape {
+        public int area() { return width * height; }
+    }
+
+    static class Counter {
+        static int created;
+        static final int[] TABLE = table();
+
+        int count;
+
+        Counter() {
+            created++;
+        }
+
+        static int[] table() {
+            int[] table = new int[16];
+            for (int i = 0; i < table.length; i++) {
+                table[i] = i * i;
+            }
+
+            return table;
+        }
+    }
+
+    static long total;
+
+    public static int fibonacci(int n) {
+        return fib(Math.abs(n % 20));
+    }
+
+    static int fib(int n) {
+        return n < 2 ? n : fib(n - 1) + fib(n - 2);
+    }
+
+    public static long collatz(int n) {
+        long steps = 0;
+        for (int start = 1; start <= Math.abs(n % 1000) + 1; start++) {
+            long value = start;
+            while (value != 1) {
+                value = (value & 1) == 0 ? value >> 1 : value * 3 + 1;
+                steps++;
+            }
+        }
+
+        return steps;
+    }
+
+    /** Throws `ArithmeticException` for 0, wraps around for the minimum value divided by -1 */
+    public static int divide(int n) {
+        int result = 0;
+        for (int divisor = -3; divisor <= 3; divisor++) {
+            if (divisor != 0) {
+                result += n / divisor + n % divisor;
+                result += Integer.MIN_VALUE / divisor + Integer.MIN_VALUE % divisor;
+            }
+        }
+
+        return result + 1000 / n;
+    }
+
+    public static long divideLong(int n) {
+        long value = (long) n * 1_000_003L;
+        long result = Long.MIN_VALUE / -1 + Long.MIN_VALUE % -1;
+
+        for (long divisor = -5; divisor <= 5; divisor++) {
+            if (divisor != 0) {
+                result ^= value / divisor - value % divisor;
+            }
+        }
+
+        return result + 7L % n;
+    }
+
+    public static int shifts(int n) {
+        long wide = n * 0x9E3779B97F4A7C15L;
+        int result = 0;
+
+        for (int shift = -70; shift < 70; shift += 3) {
+            result += (n << shift) ^ (n >> shift) ^ (n >>> shift);
+            result += (int) ((wide << shift) ^ (wide >> shift) ^ (wide >>> shift));
+        }
+
+        return result;
+    }
+
+    public static int conversions(int n) {
+        float[] floats = { Float.NaN, Float.POSITIVE_INFINITY, Float.NEGATIVE_INFINITY, 1e20f, -1e20f, 2.5f, -2.5f, n / 3f };
+        double[] doubles = { Double.NaN, Double.POSITIVE_INFINITY, Double.NEGATIVE_INFINITY, 1e300, -1e300, 2.5, -2.5, n / 3.0 };
+
+        int result = (byte) n + (char) n + (short) n + (int) ((long) n << 33 >> 7);
+        for (int i = 0; i < floats.length; i++) {
+            result = result * 31 + (int) floats[i];
+            result = result * 31 + (int) ((long) floats[i] >>> 11);
+            result = result * 31 + (int) doubles[i];
+            result = result * 31 + (int) ((long) doubles[i] >>> 11);
+            result = result * 31 + Float.floatToRawIntBits((float) doubles[i]);
+            result = result * 31 + (int) Double.doubleToRawLongBits(floats[i]);
+        }
+
+        return result + (int) (float) (long) n + (int) (double) n;
+    }
+
+    public static double floats(int n) {
+        double sum = 0;
+        float single = 0;
+
+        for (int i = 1; i <= 50; i++) {
+            double x = (n + i) / 7.0;
+            sum += x * x - x / (i - 25.5) + x % 1.5 + -x;
+            single += (float) x % 0.75f - (float) i / 3f;
+
+            if (x > sum || x < single) {
+                sum -= 1;
+            }
+        }
+
+        double nan = 0.0 / 0.0;
+        float nanFloat = 0f / 0f;
+        int comparisons = (nan < sum ? 1 : 0) + (nan > sum ? 2 : 0) + (nanFloat <= single ? 4 : 0) + (nanFloat >= single ? 8 : 0) +
+            (nan == nan ? 16 : 0) + (nan != nan ? 32 : 0) + Double.compare(nan, 1.0) + Float.compare(single, nanFloat);
+
+        return sum + single + comparisons;
+    }
+
+    public static int switches(int n) {
+        int result = 0;
+
+        for (int i = n - 20; i < n + 20; i++) {
+            switch (i) {
+                case -3, -2 -> result += 1;
+                case -1 -> result += 2;
+                case 0 -> result += 3;
+                case 1, 2, 3 -> result += i * 5;
+                case 5 -> result -= 7;
+                default -> result += 11;
+            }
+
+            switch (i * 1000) {
+                case -1_000_000 -> result ^= 1;
+                case -5000 -> result ^= 2;
+                case 0 -> result ^= 4;
+                case 7000 -> result ^= 8;
+                case Integer.MIN_VALUE -> result ^= 16;
+                default -> result += 1;
+            }
+        }
+
+        return result;
+    }
+
+    /** Keeps values on the operand stack where branches merge and across calls */
+    public static int stackShapes(int n) {
+        int result = 0;
+        long wide = 0;
+        long[] longs = new long[4];
+
+        for (int i = 0; i < 100; i++) {
+            result += (i % 3 == 0 ? n : -n) * (i > 50 ? fib(i % 5) : 2);
+            wide += longs[i % 4]++ + (longs[(i + 1) % 4] += i);
+            result = result * 3 + Math.max(i, n) + (result > 0 ? 1 : 0);
+        }
+
+        return result + (int) wide;
+    }
+
+    public static int exceptions(int n) {
+        int caught = 0;
+        int[] array = new int[10];
+
+        for (int i = -2; i < 12; i++) {
+            try {
+                caught += array[i] + 100 / (i - n % 10);
+            } catch (ArithmeticException e) {
+                caught += 1000;
+            } catch (ArrayIndexOutOfBoundsException e) {
+                caught += e.getMessage().length();
+            } finally {
+                caught++;
+            }
+        }
+
+        try {
+            Object value = n % 2 == 0 ? "text" : Integer.valueOf(n);
+            caught += ((String) value).length();
+        } catch (ClassCastException e) {
+            caught -= 5;
+        }
+
+        return caught;
+    }
+
+    /** Throws out of compiled code into the caller, which doesn't catch it */
+    public static int uncaught(int n) {
+        if (n > 5) {
+            throw new IllegalStateException("too large: " + n);
+        }
+
+        Object[] objects = new Object[n];
+        return objects.length + ((String) null).length();
+    }
+
+    public static int statics(int n) {
+        for (int i = 0; i < n % 50; i++) {
+            Counter counter = new Counter();
+            counter.count += Counter.TABLE[i % 16];
+            total += counter.count;
+        }
+
+        return Counter.created + (int) total;
+    }
+
+    public static synchronized int monitors(int n) {
+        Object lock = new Object();
+        int result = 0;
+
+        for (int i = 0; i < n % 100; i++) {
+            synchronized (lock) {
+                result += i;
+            }
+        }
+
+        return result;
+    }
+
+    public static int calls(int n) {
+        Shape[] shapes = { new Square(n), new Rectangle(n, 3), new Square(2) };
+        int result = 0;
+
+        for (int i = 0; i < 30; i++) {
+            result += shapes[i % shapes.length].area();
+            result += String.valueOf(i).hashCode();
+        }
+
+        StringBuilder builder = new StringBuilder();
+        for (int i = 0; i < n % 20; i++) {
+            builder.append(i).append(',');
+        }
+
+        return result + builder.toString().hashCode();
+    }
+
+    public static long matrix(int n) {
+        int size = Math.abs(n % 12) + 2;
+        long[][] a = new long[size][size];
+        long sum = 0;
+
+        for (int i = 0; i < size; i++) {
+            for (int j = 0; j < size; j++) {
+                a[i][j] = (long) i * j + n;
+            }
+        }
+
+        for (int i = 0; i < size; i++) {
+            for (int j = 0; j < size; j++) {
+                for (int k = 0; k < size; k++) {
+                    sum += a[i][k] * a[k][j];
+                }
+            }
+        }
+
+        return sum;
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,17 @@
+import java.io.File;
+
+/**
+ * Natives the limits test registers or that reach the host, called from Java code
+ */
+public class Natives {
+
+    static native int embedded(int x);
+
+    public static int callEmbedded(int x) {
+        return embedded(x);
+    }
+
+    public static boolean fileExists(String path) {
+        return new File(path).exists();
+    }
+}
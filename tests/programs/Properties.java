@@ -0,0 +1,24 @@
+/**
+ * Reads system properties and environment variables for the property test, main prints the properties named by
+ * its arguments like the launcher sees them
+ */
+public class Properties {
+
+    public static String property(String name) {
+        return String.valueOf(System.getProperty(name));
+    }
+
+    public static String environment(String name) {
+        return String.valueOf(System.getenv(name));
+    }
+
+    public static String separators() {
+        return System.lineSeparator().equals("\n") + " " + java.io.File.separator + " " + java.io.File.pathSeparator;
+    }
+
+    public static void main(String[] args) {
+        for (String name : args) {
+            System.out.println(name + "=" + System.getProperty(name));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+import java.lang.ref.Cleaner;
+import java.lang.ref.PhantomReference;
+import java.lang.ref.ReferenceQueue;
+import java.lang.ref.SoftReference;
+import java.lang.ref.WeakReference;
+import java.util.ArrayList;
+import java.util.List;
+import java.util.Map;
+import java.util.WeakHashMap;
+
+/**
+ * Weak, soft and phantom references, reference queues and finalizers for the reference test
+ */
+public class References {
+
+    static int finalized;
+
+    static class Finalizable {
+        @Override
+        @SuppressWarnings("removal")
+        protected void finalize() {
+            finalized++;
+        }
+    }
+
+    public static String weak() throws InterruptedException {
+        ReferenceQueue<Object> queue = new ReferenceQueue<>();
+        Object kept = new Object();
+        WeakReference<Object> strong = new WeakReference<>(kept, queue);
+        WeakReference<Object> weak = new WeakReference<>(new Object(), queue);
+
+        System.gc();
+
+        return "cleared " + (weak.get() == null) + ", enqueued " + (queue.remove(1000) == weak) + ", kept " + (strong.get() == kept)
+            + ", queue empty " + (queue.poll() == null);
+    }
+
+    public static String weakHashMap() {
+        Map<Object, String> map = new WeakHashMap<>();
+        Object kept = new Object();
+        map.put(kept, "kept");
+        map.put(new Object(), "dropped");
+
+        System.gc();
+
+        return map.size() + " " + map.get(kept);
+    }
+
+    public static String phantom() throws InterruptedException {
+        ReferenceQueue<Object> queue = new ReferenceQueue<>();
+        PhantomReference<Object> phantom = new PhantomReference<>(new Object(), queue);
+        boolean alwaysNull = phantom.get() == null;
+
+        System.gc();
+
+        return "null " + alwaysNull + ", enqueued " + (queue.remove(1000) == phantom);
+    }
+
+    /** Soft references survive collections until memory runs out, then they're cleared instead of raising OutOfMemoryError */
+    public static String soft() {
+        SoftReference<long[]> soft = new SoftReference<>(new long[1024]);
+        System.gc();
+        boolean kept = soft.get() != null;
+
+        List<long[]> hoarded = new ArrayList<>();
+        try {
+            while (soft.get() != null) {
+                hoarded.add(new long[1024]);
+            }
+        } catch (OutOfMemoryError error) {
+            hoarded = null;
+            return "kept " + kept + ", out of memory before clearing";
+        }
+
+        int count = hoarded.size();
+        hoarded = null;
+        return "kept " + kept + ", cleared " + (count > 0);
+    }
+
+    public static String finalizers() {
+        finalized = 0;
+        for (int i = 0; i < 10; i++) {
+            new Finalizable();
+        }
+
+        System.gc();
+
+        return "finalized " + finalized;
+    }
+
+    public static String cleaner() throws InterruptedException {
+        boolean[] cleaned = new boolean[1];
+        Cleaner.create().register(new Object(), () -> cleaned[0] = true);
+
+        System.gc();
+
+        // The cleaner runs on its own thread
+        for (int i = 0; i < 100 && !cleaned[0]; i++) {
+            Thread.sleep(10);
+        }
+
+        return "cleaned " + cleaned[0];
+    }
+}
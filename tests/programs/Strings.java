@@ -0,0 +1,44 @@
+/**
+ * String constants, interning and concatenation for the string test, with Latin-1 and UTF-16 contents
+ */
+public class Strings {
+
+    public static final String LITERAL = "shared literal";
+
+    public static String interning() {
+        String built = new StringBuilder("shared ").append("literal").toString();
+
+        return (LITERAL == Other.LITERAL) + " " + (LITERAL == "shared literal") + " " + (built == LITERAL) + " "
+            + (built.intern() == LITERAL) + " " + built.equals(LITERAL);
+    }
+
+    public static String concatenation() {
+        int number = 7;
+        char letter = 'z';
+        double fraction = 2.5;
+        Object nothing = null;
+        String text = "text";
+
+        return "n=" + number + " c=" + letter + " d=" + fraction + " o=" + nothing + " s=" + text + " l=" + (number * 1_000_000_000L);
+    }
+
+    public static String unicode() {
+        String snowman = "h\u00e9llo \u2603 \ud83d\ude00";
+
+        return snowman + " " + snowman.length() + " " + snowman.codePointCount(0, snowman.length()) + " " + (int) snowman.charAt(1)
+            + " " + snowman.toUpperCase();
+    }
+
+    public static String reverse(String string) {
+        return new StringBuilder(string).reverse().toString();
+    }
+
+    public static boolean isInterned(String string) {
+        return string == LITERAL;
+    }
+}
+
+class Other {
+
+    static final String LITERAL = new String("shared literal").intern();
+}
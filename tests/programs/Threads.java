@@ -0,0 +1,222 @@
+import java.util.ArrayDeque;
+import java.util.Queue;
+
+/**
+ * Java threads the thread test runs: counters behind monitors and synchronized methods, producers and consumers
+ * handing items over with wait/notify, sleeping, joining and interrupting, and two threads that never yield on
+ * their own, which only interleave when the scheduler preempts them.
+ */
+public class Threads {
+
+    private static final Object lock = new Object();
+    private static int counter;
+    private static int synchronizedCounter;
+
+    private static synchronized void incrementSynchronized() {
+        synchronizedCounter++;
+    }
+
+    private static void join(Thread... threads) throws InterruptedException {
+        for (Thread thread : threads) {
+            thread.join();
+        }
+    }
+
+    /** Four threads incrementing one counter in a synchronized block and another in a synchronized method */
+    public static String counters() throws InterruptedException {
+        counter = 0;
+        synchronizedCounter = 0;
+
+        Thread[] threads = new Thread[4];
+        for (int i = 0; i < threads.length; i++) {
+            threads[i] = new Thread(() -> {
+                for (int j = 0; j < 5_000; j++) {
+                    synchronized (lock) {
+                        counter++;
+                    }
+                    incrementSynchronized();
+                }
+            });
+            threads[i].start();
+        }
+        join(threads);
+
+        return counter + " " + synchronizedCounter;
+    }
+
+    /** A producer handing 100 numbers to a consumer through a queue of at most 3 items */
+    public static String producerConsumer() throws InterruptedException {
+        Queue<Integer> queue = new ArrayDeque<>();
+        long[] sum = new long[1];
+
+        Thread producer = new Thread(() -> {
+            for (int i = 1; i <= 100; i++) {
+                synchronized (queue) {
+                    while (queue.size() == 3) {
+                        try {
+                            queue.wait();
+                        } catch (InterruptedException exception) {
+                            return;
+                        }
+                    }
+                    queue.add(i);
+                    queue.notify();
+                }
+            }
+        });
+        Thread consumer = new Thread(() -> {
+            for (int i = 1; i <= 100; i++) {
+                synchronized (queue) {
+                    while (queue.isEmpty()) {
+                        try {
+                            queue.wait();
+                        } catch (InterruptedException exception) {
+                            return;
+                        }
+                    }
+                    sum[0] += queue.remove();
+                    queue.notify();
+                }
+            }
+        });
+
+        consumer.start();
+        producer.start();
+        join(producer, consumer);
+
+        return "sum " + sum[0];
+    }
+
+    /** Three threads waiting for a flag, all woken by one notifyAll */
+    public static String notifyAllWaiters() throws InterruptedException {
+        Object gate = new Object();
+        boolean[] open = new boolean[1];
+        int[] woken = new int[1];
+
+        Thread[] waiters = new Thread[3];
+        for (int i = 0; i < waiters.length; i++) {
+            waiters[i] = new Thread(() -> {
+                synchronized (gate) {
+                    while (!open[0]) {
+                        try {
+                            gate.wait();
+                        } catch (InterruptedException exception) {
+                            return;
+                        }
+                    }
+                    woken[0]++;
+                }
+            });
+            waiters[i].start();
+        }
+
+        for (Thread waiter : waiters) {
+            while (waiter.getState() != Thread.State.WAITING) {
+                Thread.yield();
+            }
+        }
+
+        synchronized (gate) {
+            open[0] = true;
+            gate.notifyAll();
+        }
+        join(waiters);
+
+        return "woken " + woken[0];
+    }
+
+    /** Sleeping takes at least as long as asked for, join waits for the end of a thread or its timeout */
+    public static String sleepAndJoin() throws InterruptedException {
+        long start = System.nanoTime();
+        Thread.sleep(30);
+        boolean slept = System.nanoTime() - start >= 30_000_000L;
+
+        Thread sleeper = new Thread(() -> {
+            try {
+                Thread.sleep(60_000);
+            } catch (InterruptedException exception) {
+                // Ends the thread
+            }
+        });
+        sleeper.start();
+        sleeper.join(20);
+        boolean aliveAfterTimeout = sleeper.isAlive();
+
+        sleeper.interrupt();
+        sleeper.join();
+
+        return "slept " + slept + ", alive after timeout " + aliveAfterTimeout + ", alive after join " + sleeper.isAlive();
+    }
+
+    /** Interrupting threads that sleep or wait raises InterruptedException in them and clears the flag */
+    public static String interrupts() throws InterruptedException {
+        String[] results = new String[2];
+
+        Thread sleeper = new Thread(() -> {
+            try {
+                Thread.sleep(60_000);
+                results[0] = "slept";
+            } catch (InterruptedException exception) {
+                results[0] = "sleep interrupted " + Thread.currentThread().isInterrupted();
+            }
+        });
+        Object monitor = new Object();
+        Thread waiter = new Thread(() -> {
+            synchronized (monitor) {
+                try {
+                    monitor.wait();
+                    results[1] = "notified";
+                } catch (InterruptedException exception) {
+                    results[1] = "wait interrupted " + Thread.currentThread().isInterrupted();
+                }
+            }
+        });
+
+        sleeper.start();
+        waiter.start();
+        while (sleeper.getState() != Thread.State.TIMED_WAITING || waiter.getState() != Thread.State.WAITING) {
+            Thread.yield();
+        }
+        sleeper.interrupt();
+        waiter.interrupt();
+        join(sleeper, waiter);
+
+        Thread.currentThread().interrupt();
+        boolean first = Thread.interrupted();
+        boolean second = Thread.interrupted();
+
+        return results[0] + ", " + results[1] + ", " + first + " " + second;
+    }
+
+    /** Waiting or notifying without owning the monitor */
+    public static String illegalMonitorState() {
+        Object monitor = new Object();
+        try {
+            monitor.notify();
+            return "notified";
+        } catch (IllegalMonitorStateException exception) {
+            return exception.getClass().getName();
+        }
+    }
+
+    /** Two threads appending letters as fast as they can, the result shows where the scheduler switched */
+    public static String interleaving() throws InterruptedException {
+        StringBuffer letters = new StringBuffer();
+
+        Thread a = new Thread(() -> {
+            for (int i = 0; i < 2_000; i++) {
+                letters.append('a');
+            }
+        });
+        Thread b = new Thread(() -> {
+            for (int i = 0; i < 2_000; i++) {
+                letters.append('b');
+            }
+        });
+        a.start();
+        b.start();
+        join(a, b);
+
+        return letters.toString();
+    }
+}
@@ -0,0 +1,30 @@
+/** Calls for the trace test, which traces `Traced.*` but not `Untraced` or the JDK */
+public class Traced {
+
+    static int square(int value) {
+        return value * value;
+    }
+
+    static int sumOfSquares(int count) {
+        int sum = 0;
+        for (int i = 1; i <= count; i++) {
+            sum += square(i) + Untraced.identity(0);
+        }
+        return sum;
+    }
+
+    static String describe(int count) {
+        return "sum " + sumOfSquares(count);
+    }
+
+    static int fail(String message) {
+        throw new IllegalStateException(message);
+    }
+}
+
+class Untraced {
+
+    static int identity(int value) {
+        return value;
+    }
+}
@@ -0,0 +1,55 @@
+package embed;
+
+import java.lang.ref.WeakReference;
+
+/**
+ * A class in a package the embedding test drives from Rust: constructors, instance and static members, exceptions,
+ * and a weak reference that shows whether an object handed to Rust could be collected
+ */
+public class Account {
+
+    public static int opened;
+    private static WeakReference<Object> tracked;
+
+    public String owner;
+    private long balance;
+
+    public Account(String owner, long balance) {
+        this.owner = owner;
+        this.balance = balance;
+        opened++;
+    }
+
+    public long deposit(int amount) {
+        if (amount <= 0) {
+            throw new IllegalArgumentException("Deposits have to be positive, not " + amount);
+        }
+        balance += amount;
+        return balance;
+    }
+
+    public Account self() {
+        return this;
+    }
+
+    public boolean isRich() {
+        return balance > 1_000_000;
+    }
+
+    public String describe() {
+        return owner + ": " + balance;
+    }
+
+    public static double interest(double rate, float years, char kind, byte months, short days) {
+        return rate * years + kind + months + days;
+    }
+
+    public static void track(Object object) {
+        tracked = new WeakReference<>(object);
+    }
+
+    public static boolean trackedCollected() {
+        System.gc();
+        return tracked.get() == null;
+    }
+}
@@ -0,0 +1,85 @@
+//! System properties and environment variables seen by `tests/programs/Properties.java`: the defaults of the
+//! platform and the VM, overrides by embedders and `-D`, and `System.getenv`.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use java_vm::{JClass, JValue, VirtualMachine};
+
+fn property(vm: &mut VirtualMachine, class: JClass, name: &str) -> String {
+    let name = vm.new_string(name).unwrap();
+    common::string_result(vm, class, "property", "(Ljava/lang/String;)Ljava/lang/String;", &[JValue::Object(name)])
+}
+
+fn classes() -> std::path::PathBuf {
+    common::compile_programs("property-classes", &["tests/programs/Properties.java"])
+}
+
+#[test]
+fn defaults_describe_the_platform_and_the_vm() {
+    let (mut vm, class) = common::load_program("property-classes", &["tests/programs/Properties.java"], "Properties");
+
+    assert_eq!(property(&mut vm, class, "java.specification.version"), "17");
+    assert!(property(&mut vm, class, "java.version").starts_with("17"));
+    assert_eq!(property(&mut vm, class, "java.vm.name"), "java_vm");
+    assert_eq!(property(&mut vm, class, "os.name"), "Linux");
+    assert_eq!(property(&mut vm, class, "file.encoding"), "UTF-8");
+    assert_eq!(property(&mut vm, class, "user.dir"), std::env::current_dir().unwrap().to_string_lossy());
+    assert_eq!(property(&mut vm, class, "user.home"), std::env::var("HOME").unwrap_or_else(|_| "?".to_string()));
+    assert_eq!(property(&mut vm, class, "java.class.path"), classes().to_string_lossy());
+    assert_eq!(property(&mut vm, class, "no.such.property"), "null");
+
+    assert_eq!(common::string_result(&mut vm, class, "separators", "()Ljava/lang/String;", &[]), "true / :");
+}
+
+#[test]
+fn embedders_override_properties() {
+    let mut vm = common::vm_with_classes(&classes());
+    vm.set_system_property("custom.flag", "on");
+    vm.set_system_property("java.vm.vendor", "Embedder");
+    vm.boot().expect("java.base doesn't boot");
+    let class = vm.load_class("Properties").unwrap();
+
+    assert_eq!(property(&mut vm, class, "custom.flag"), "on");
+    assert_eq!(property(&mut vm, class, "java.vm.vendor"), "Embedder");
+}
+
+#[test]
+fn properties_are_available_without_booting() {
+    let mut vm = common::vm_with_classes(&classes());
+    vm.set_system_property("custom.flag", "unbooted");
+    let class = vm.load_class("Properties").unwrap();
+
+    assert_eq!(property(&mut vm, class, "custom.flag"), "unbooted");
+    assert_eq!(property(&mut vm, class, "os.name"), "Linux");
+    assert_eq!(common::string_result(&mut vm, class, "separators", "()Ljava/lang/String;", &[]), "true / :");
+}
+
+#[test]
+fn environment_variables_are_visible() {
+    let (mut vm, class) = common::load_program("property-classes", &["tests/programs/Properties.java"], "Properties");
+
+    let mut environment = |name: &str| {
+        let name = vm.new_string(name).unwrap();
+        common::string_result(&mut vm, class, "environment", "(Ljava/lang/String;)Ljava/lang/String;", &[JValue::Object(name)])
+    };
+
+    assert_eq!(environment("PATH"), std::env::var("PATH").unwrap());
+    assert_eq!(environment("NO_SUCH_VARIABLE_OF_THE_TEST"), "null");
+}
+
+#[test]
+fn launcher_sets_properties_with_d() {
+    let output = Command::new(env!("CARGO_BIN_EXE_java_vm"))
+        .arg(format!("-Xbootclasspath:{}", common::java_base_jar().display()))
+        .arg("-cp").arg(classes())
+        .arg("-Dcustom.flag=from launcher")
+        .args(["Properties", "custom.flag", "os.name"])
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+        .output().unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "custom.flag=from launcher\nos.name=Linux\n");
+}
@@ -0,0 +1,41 @@
+//! `java.lang.ref` of `tests/programs/References.java`: weak and phantom references are cleared and enqueued once
+//! their referents are garbage, soft ones only when the heap runs out, and finalizers and cleaners run.
+
+mod common;
+
+use java_vm::{JClass, VirtualMachine};
+
+fn references() -> (VirtualMachine, JClass) {
+    common::load_program("reference-classes", &["tests/programs/References.java"], "References")
+}
+
+fn call(vm: &mut VirtualMachine, class: JClass, name: &str) -> String {
+    common::string_result(vm, class, name, "()Ljava/lang/String;", &[])
+}
+
+#[test]
+fn weak_and_phantom_references_are_cleared_and_enqueued() {
+    let (mut vm, class) = references();
+
+    assert_eq!(call(&mut vm, class, "weak"), "cleared true, enqueued true, kept true, queue empty true");
+    assert_eq!(call(&mut vm, class, "weakHashMap"), "1 kept");
+    assert_eq!(call(&mut vm, class, "phantom"), "null true, enqueued true");
+}
+
+#[test]
+fn soft_references_are_cleared_when_memory_runs_out() {
+    let (mut vm, class) = references();
+    vm.collect_garbage();
+    let live_bytes = vm.gc_stats().live_bytes;
+    vm.set_max_heap_size(live_bytes + (2 << 20));
+
+    assert_eq!(call(&mut vm, class, "soft"), "kept true, cleared true");
+}
+
+#[test]
+fn finalizers_and_cleaners_run() {
+    let (mut vm, class) = references();
+
+    assert_eq!(call(&mut vm, class, "finalizers"), "finalized 10");
+    assert_eq!(call(&mut vm, class, "cleaner"), "cleaned true");
+}
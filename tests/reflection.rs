@@ -0,0 +1,38 @@
+//! Access checks of reflection, `tests/programs/Access.java`: private members can be used by the class that declares
+//! them and the other members of its nest, anyone else gets an `IllegalAccessException` unless the check is suppressed.
+
+mod common;
+
+use java_vm::{JClass, VirtualMachine};
+
+fn access() -> (VirtualMachine, JClass) {
+    common::load_program("reflection-classes", &["tests/programs/Access.java"], "Access")
+}
+
+fn call(vm: &mut VirtualMachine, class: JClass, name: &str) -> String {
+    common::string_result(vm, class, name, "()Ljava/lang/String;", &[])
+}
+
+#[test]
+fn private_members_are_accessible_within_a_nest() {
+    let (mut vm, class) = access();
+
+    assert_eq!(call(&mut vm, class, "ownMembers"), "42 hidden");
+    assert_eq!(call(&mut vm, class, "nestMateMembers"), "7 peeked 42");
+    assert_eq!(call(&mut vm, class, "nestHosts"), "Access true false");
+}
+
+#[test]
+fn private_members_of_other_classes_are_refused() {
+    let (mut vm, class) = access();
+
+    assert_eq!(call(&mut vm, class, "otherField"),
+               "java.lang.IllegalAccessException: class Access cannot access a member of class Other with modifiers \"private\"");
+    assert_eq!(call(&mut vm, class, "otherFieldWrite"), "java.lang.IllegalAccessException");
+    assert_eq!(call(&mut vm, class, "otherMethod"),
+               "java.lang.IllegalAccessException: class Access cannot access a member of class Other with modifiers \"private static\"");
+    assert_eq!(call(&mut vm, class, "otherConstructor"), "java.lang.IllegalAccessException");
+
+    assert_eq!(call(&mut vm, class, "otherPublicMembers"), "2 other shown");
+    assert_eq!(call(&mut vm, class, "suppressedChecks"), "1 other hidden");
+}
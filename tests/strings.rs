@@ -0,0 +1,69 @@
+//! Strings of `tests/programs/Strings.java`: `ldc` and `ldc_w` constants are interned, concatenation, Latin-1 and
+//! UTF-16 contents, and strings passed between Rust and Java.
+
+mod common;
+
+use std::fmt::Write;
+use std::path::Path;
+
+use java_vm::{JClass, JValue, VirtualMachine};
+
+fn strings() -> (VirtualMachine, JClass) {
+    common::load_program("string-classes", &["tests/programs/Strings.java"], "Strings")
+}
+
+#[test]
+fn constants_are_interned() {
+    let (mut vm, class) = strings();
+
+    assert_eq!(common::string_result(&mut vm, class, "interning", "()Ljava/lang/String;", &[]), "true true false true true");
+
+    // Strings created from Rust are new objects until interned
+    let string = vm.new_string("shared literal").unwrap();
+    assert!(matches!(vm.invoke_static(class, "isInterned", "(Ljava/lang/String;)Z", &[JValue::Object(string)]), Ok(JValue::Boolean(false))));
+}
+
+#[test]
+fn strings_are_concatenated() {
+    let (mut vm, class) = strings();
+
+    assert_eq!(common::string_result(&mut vm, class, "concatenation", "()Ljava/lang/String;", &[]),
+               "n=7 c=z d=2.5 o=null s=text l=7000000000");
+}
+
+#[test]
+fn unicode_survives_the_round_trip() {
+    let (mut vm, class) = strings();
+
+    assert_eq!(common::string_result(&mut vm, class, "unicode", "()Ljava/lang/String;", &[]),
+               "h\u{e9}llo \u{2603} \u{1f600} 10 9 233 H\u{c9}LLO \u{2603} \u{1f600}");
+
+    for text in ["latin-1 \u{e9}", "utf-16 \u{2603}", "surrogates \u{1f600}", ""] {
+        let string = vm.new_string(text).unwrap();
+        assert_eq!(vm.get_string(string).unwrap(), text);
+
+        let reversed: String = text.chars().rev().collect();
+        assert_eq!(common::string_result(&mut vm, class, "reverse", "(Ljava/lang/String;)Ljava/lang/String;", &[JValue::Object(string)]), reversed);
+    }
+}
+
+#[test]
+fn wide_constant_pool_indices_load_strings() {
+    // Enough constants that the later ones need ldc_w
+    let mut source = String::from("public class Wide {\n    public static String last() {\n        String[] strings = {\n");
+    for index in 0..400 {
+        writeln!(source, "            \"constant {}\",", index).unwrap();
+    }
+    source.push_str("        };\n        return strings[0] + \", \" + strings[399] + \" \" + (strings[399] == \"constant 399\");\n    }\n}\n");
+
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("wide-sources");
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("Wide.java");
+    std::fs::write(&path, source).unwrap();
+
+    let classes = common::compile_programs("wide-classes", &[&path.to_string_lossy()]);
+    let mut vm = common::booted_vm(&classes);
+    let class = vm.load_class("Wide").unwrap();
+
+    assert_eq!(common::string_result(&mut vm, class, "last", "()Ljava/lang/String;", &[]), "constant 0, constant 399 true");
+}
@@ -0,0 +1,57 @@
+//! Java threads of `tests/programs/Threads.java` on green threads: monitors, synchronized methods, wait/notify,
+//! sleep, join and interrupts, and a schedule that only depends on the quantum.
+
+mod common;
+
+use java_vm::{JClass, VirtualMachine};
+
+fn threads() -> (VirtualMachine, JClass) {
+    common::load_program("thread-classes", &["tests/programs/Threads.java"], "Threads")
+}
+
+fn call(vm: &mut VirtualMachine, class: JClass, name: &str) -> String {
+    common::string_result(vm, class, name, "()Ljava/lang/String;", &[])
+}
+
+#[test]
+fn monitors_exclude_each_other() {
+    let (mut vm, class) = threads();
+    vm.set_thread_quantum(50);
+
+    assert_eq!(call(&mut vm, class, "counters"), "20000 20000");
+    assert_eq!(call(&mut vm, class, "illegalMonitorState"), "java.lang.IllegalMonitorStateException");
+}
+
+#[test]
+fn wait_and_notify_hand_over_work() {
+    let (mut vm, class) = threads();
+
+    assert_eq!(call(&mut vm, class, "producerConsumer"), "sum 5050");
+    assert_eq!(call(&mut vm, class, "notifyAllWaiters"), "woken 3");
+}
+
+#[test]
+fn sleep_join_and_interrupt() {
+    let (mut vm, class) = threads();
+
+    assert_eq!(call(&mut vm, class, "sleepAndJoin"), "slept true, alive after timeout true, alive after join false");
+    assert_eq!(call(&mut vm, class, "interrupts"), "sleep interrupted false, wait interrupted false, true false");
+}
+
+#[test]
+fn scheduling_is_deterministic_for_a_quantum() {
+    let interleaving = |quantum: u32| {
+        let (mut vm, class) = threads();
+        vm.set_thread_quantum(quantum);
+        call(&mut vm, class, "interleaving")
+    };
+
+    let first = interleaving(500);
+    assert_eq!(first.len(), 4000);
+    assert!(first.contains("ab") && first.contains("ba"), "Threads weren't preempted: {}", first);
+    assert_eq!(interleaving(500), first);
+
+    // A quantum longer than either thread runs them one after the other
+    let sequential = interleaving(1_000_000);
+    assert!(sequential == "a".repeat(2000) + &"b".repeat(2000), "{}", sequential);
+}
@@ -0,0 +1,156 @@
+//! Tracing of calls, returns and instructions of `tests/programs/Traced.java`, selected by globs and written as text
+//! or JSON lines to a buffer, a file or through the `-Xtrace` options of the launcher.
+
+mod common;
+
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use java_vm::java::trace::glob_matches;
+use java_vm::{JValue, TraceFormat, TraceOptions, TraceOutput};
+
+const PROGRAM: &[&str] = &["tests/programs/Traced.java"];
+
+/// Traces `sumOfSquares(3)` with the options and returns the lines of the trace
+fn traced(options: TraceOptions) -> Vec<String> {
+    let (mut vm, class) = common::load_program("trace-classes", PROGRAM, "Traced");
+    let buffer = Arc::new(Mutex::new(vec![]));
+
+    vm.set_trace(Some(TraceOptions { output: TraceOutput::Buffer(buffer.clone()), ..options })).unwrap();
+    let result = vm.invoke_static(class, "sumOfSquares", "(I)I", &[JValue::Int(3)]).unwrap();
+    vm.set_trace(None).unwrap();
+
+    assert_eq!(result, JValue::Int(14));
+
+    let trace = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+    trace.lines().map(str::to_string).collect()
+}
+
+#[test]
+fn globs_match_class_and_method_names() {
+    assert!(glob_matches("Traced.*", "Traced.square"));
+    assert!(glob_matches("java.util.*List.add", "java.util.ArrayList.add"));
+    assert!(glob_matches("*.squ?re", "Traced.square"));
+    assert!(glob_matches("*", ""));
+    assert!(!glob_matches("Traced.*", "Untraced.identity"));
+    assert!(!glob_matches("java.util.*List.add", "java.util.ArrayList.addAll"));
+    assert!(!glob_matches("*.squ?re", "Traced.squaare"));
+}
+
+#[test]
+fn filters_select_the_traced_methods() {
+    let lines = traced(TraceOptions { filters: vec!["Traced.*".to_string()], instructions: false, ..TraceOptions::default() });
+
+    let calls: Vec<&str> = lines.iter().map(|line| line.split_once(' ').unwrap().1.trim_start()).collect();
+    let mut expected = vec!["1 > Traced.sumOfSquares(I)I (3)".to_string()];
+    for i in 1..=3 {
+        expected.push(format!("2 > Traced.square(I)I ({})", i));
+        expected.push(format!("2 < Traced.square(I)I = {}", i * i));
+    }
+    expected.push("1 < Traced.sumOfSquares(I)I = 14".to_string());
+
+    assert_eq!(calls, expected);
+    assert!(lines.iter().all(|line| line.starts_with('T')), "{:?}", lines);
+}
+
+#[test]
+fn instructions_are_traced_with_the_frame_state() {
+    let lines = traced(TraceOptions { filters: vec!["*.square".to_string()], ..TraceOptions::default() });
+
+    let instructions: Vec<&String> = lines.iter().filter(|line| line.contains(" @")).collect();
+    // iload_0, iload_0, imul and ireturn for each of the three calls
+    assert_eq!(instructions.len(), 12, "{:#?}", lines);
+    assert!(instructions[6].contains("Traced.square(I)I @2 imul  stack [2, 2] -> [4]  locals [2] -> [2]"), "{}", instructions[6]);
+    assert!(instructions.iter().all(|line| line.contains("Traced.square")), "{:#?}", instructions);
+    assert!(!lines.iter().any(|line| line.contains("sumOfSquares") || line.contains("Untraced")), "{:#?}", lines);
+}
+
+#[test]
+fn exceptions_are_traced() {
+    let (mut vm, class) = common::load_program("trace-classes", PROGRAM, "Traced");
+    let buffer = Arc::new(Mutex::new(vec![]));
+
+    vm.set_trace(Some(TraceOptions {
+        filters: vec!["Traced.fail".to_string()],
+        instructions: false,
+        output: TraceOutput::Buffer(buffer.clone()),
+        ..TraceOptions::default()
+    })).unwrap();
+    let message = vm.new_string("broken").unwrap();
+    assert!(vm.invoke_static(class, "fail", "(Ljava/lang/String;)I", &[JValue::Object(message)]).is_err());
+    vm.set_trace(None).unwrap();
+
+    let trace = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", trace);
+    assert!(lines[0].contains("> Traced.fail(Ljava/lang/String;)I (java.lang.String@"), "{}", lines[0]);
+    assert!(lines[1].contains("< Traced.fail(Ljava/lang/String;)I threw java.lang.IllegalStateException: broken"), "{}", lines[1]);
+}
+
+#[test]
+fn json_lines_have_one_object_per_event() {
+    let lines = traced(TraceOptions {
+        filters: vec!["Traced.square".to_string()],
+        format: TraceFormat::JsonLines,
+        ..TraceOptions::default()
+    });
+
+    assert_eq!(lines.len(), 3 * (4 + 2), "{:#?}", lines);
+    assert!(lines.iter().all(|line| line.starts_with("{\"event\":\"") && line.ends_with('}')), "{:#?}", lines);
+
+    assert_eq!(lines[0], "{\"event\":\"enter\",\"thread\":0,\"depth\":2,\"method\":\"Traced.square(I)I\",\"native\":false,\"args\":[\"1\"]}");
+    assert_eq!(lines[3], "{\"event\":\"instruction\",\"thread\":0,\"depth\":2,\"method\":\"Traced.square(I)I\",\"pc\":2,\
+                          \"instruction\":\"imul\",\"stack_before\":[\"1\",\"1\"],\"stack_after\":[\"1\"],\
+                          \"locals_before\":[\"1\"],\"locals_after\":[\"1\"]}");
+    assert_eq!(lines[5], "{\"event\":\"exit\",\"thread\":0,\"depth\":2,\"method\":\"Traced.square(I)I\",\"return\":\"1\"}");
+}
+
+#[test]
+fn nothing_is_traced_by_default() {
+    let (mut vm, class) = common::load_program("trace-classes", PROGRAM, "Traced");
+    let buffer = Arc::new(Mutex::new(vec![]));
+
+    vm.set_trace(Some(TraceOptions { output: TraceOutput::Buffer(buffer.clone()), ..TraceOptions::default() })).unwrap();
+    vm.set_trace(None).unwrap();
+    vm.invoke_static(class, "sumOfSquares", "(I)I", &[JValue::Int(3)]).unwrap();
+
+    assert!(buffer.lock().unwrap().is_empty());
+}
+
+#[test]
+fn launcher_writes_the_trace_to_a_file() {
+    let classes = common::compile_programs("trace-classes", PROGRAM);
+    let file = classes.join("trace.jsonl");
+    let _ = std::fs::remove_file(&file);
+
+    let main = classes.join("TraceMain.java");
+    std::fs::write(&main, "public class TraceMain { public static void main(String[] args) { System.out.println(Traced.describe(2)); } }").unwrap();
+    let javac = Command::new("javac").arg("-cp").arg(&classes).arg("-d").arg(&classes).arg(&main).output().unwrap();
+    assert!(javac.status.success(), "{}", String::from_utf8_lossy(&javac.stderr));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_java_vm"))
+        .arg(format!("-Xbootclasspath:{}", common::java_base_jar().display()))
+        .arg("-cp").arg(&classes)
+        .arg("-Xtrace:Traced.describe,Traced.sumOfSquares")
+        .arg("-Xtracecalls")
+        .arg("-Xtraceformat:json")
+        .arg(format!("-Xtracefile:{}", file.display()))
+        .arg("TraceMain")
+        .output().unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "sum 5\n");
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let trace = std::fs::read_to_string(&file).unwrap();
+    let events: Vec<&str> = trace.lines()
+        .map(|line| line.split("\"method\":").nth(1).unwrap())
+        .collect();
+    assert_eq!(events.len(), 4, "{}", trace);
+    assert_eq!(events[..3], [
+        "\"Traced.describe(I)Ljava/lang/String;\",\"native\":false,\"args\":[\"2\"]}",
+        "\"Traced.sumOfSquares(I)I\",\"native\":false,\"args\":[\"2\"]}",
+        "\"Traced.sumOfSquares(I)I\",\"return\":\"5\"}"
+    ]);
+    assert!(events[3].starts_with("\"Traced.describe(I)Ljava/lang/String;\",\"return\":\"java.lang.String@"), "{}", events[3]);
+}
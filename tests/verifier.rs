@@ -0,0 +1,154 @@
+//! Class files older than version 50 have no StackMapTable and are verified by type inference. Real class files
+//! rewritten to version 49 have to pass it and still run the same, broken byte code in them has to be rejected
+//! with a `VerifyError` instead of reaching the interpreter.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use java_vm::java::verifier::{self, ClassHierarchy, VerifyMode};
+use java_vm::java::{access_flags, opcodes::Opcode, Class, ClassFile};
+use java_vm::{JClass, JValue, JavaError, Jar, VirtualMachine};
+
+const OLD_VERSION: u16 = 49;
+
+struct JarHierarchy<'a>(&'a Jar);
+
+impl ClassHierarchy for JarHierarchy<'_> {
+
+    fn lookup(&self, class_name: &str) -> Option<(Option<String>, bool)> {
+        let class = self.0.classes.get(&format!("{}.class", class_name))?;
+
+        Some((class.super_class_name(), class.class_file.access_flags & access_flags::ACC_INTERFACE != 0))
+    }
+
+}
+
+fn with_version(class_file: &ClassFile, major_version: u16) -> Vec<u8> {
+    let mut bytes = class_file.write().expect("Class file can't be written");
+    bytes[6..8].copy_from_slice(&major_version.to_be_bytes());
+
+    bytes
+}
+
+/// The compiled `Kernels` classes rewritten to version 49 into their own directory, `Kernels.fibonacci(I)I` gets
+/// the given code if there is any
+fn old_kernels(name: &str, fibonacci: Option<(u16, u16, &[u8])>) -> PathBuf {
+    let classes = common::compile_programs("verifier-classes", &["tests/programs/Kernels.java"]);
+    let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&output).unwrap();
+
+    let jar = Jar::from_directory(&classes.to_string_lossy()).expect("Test classes can't be read");
+    for (file_name, class) in &jar.classes {
+        let mut class_file = Class::new(&with_version(&class.class_file, OLD_VERSION)).unwrap().class_file;
+
+        if let (Some((max_stack, max_locals, code)), "Kernels.class") = (fibonacci, file_name.as_str()) {
+            common::replace_code(&mut class_file, "fibonacci", max_stack, max_locals, code);
+        }
+
+        std::fs::write(output.join(file_name), with_version(&class_file, OLD_VERSION)).unwrap();
+    }
+
+    output
+}
+
+fn load_verified(classes: &Path) -> (VirtualMachine, Result<JClass, JavaError>) {
+    let mut vm = common::vm_with_classes(classes);
+    vm.set_verify_mode(VerifyMode::All);
+    vm.boot().expect("java.base doesn't boot");
+
+    let class = vm.load_class("Kernels");
+    (vm, class)
+}
+
+fn verify_error(result: Result<JClass, JavaError>) -> String {
+    match result {
+        Err(JavaError::Exception(exception)) if exception.class_name == "java.lang.VerifyError" => exception.message.unwrap_or_default(),
+        Err(error) => panic!("Expected a VerifyError but got {:?}", error),
+        Ok(_) => panic!("Expected a VerifyError but the class was loaded")
+    }
+}
+
+#[test]
+fn java_base_passes_type_inference() {
+    let jar = Jar::new(&common::java_base_jar().to_string_lossy()).expect("java.base.jar can't be read");
+    let hierarchy = JarHierarchy(&jar);
+
+    for (file_name, class) in &jar.classes {
+        let old = Class::new(&with_version(&class.class_file, OLD_VERSION)).unwrap();
+
+        if let Err(error) = verifier::verify_class(&old.class_file, &hierarchy) {
+            panic!("{}: {}", file_name, error);
+        }
+    }
+}
+
+#[test]
+fn old_class_files_run_the_same() {
+    let (mut vm, class) = load_verified(&old_kernels("verifier-old-kernels", None));
+    let class = class.expect("Kernels can't be loaded");
+
+    assert!(matches!(vm.invoke_static(class, "fibonacci", "(I)I", &[JValue::Int(10)]), Ok(JValue::Int(55))));
+    assert!(matches!(vm.invoke_static(class, "switches", "(I)I", &[JValue::Int(7)]), Ok(JValue::Int(_))));
+    assert!(matches!(vm.invoke_static(class, "exceptions", "(I)I", &[JValue::Int(0)]), Ok(JValue::Int(_))));
+}
+
+#[test]
+fn stack_underflow_is_a_verify_error() {
+    let code = [Opcode::pop as u8, Opcode::iconst_0 as u8, Opcode::ireturn as u8];
+    let (_, class) = load_verified(&old_kernels("verifier-underflow", Some((1, 1, &code))));
+
+    let message = verify_error(class);
+    assert!(message.contains("Kernels.fibonacci(I)I at pc 0: Operand stack underflow"), "{}", message);
+}
+
+#[test]
+fn inconsistent_stack_heights_are_a_verify_error() {
+    // Pushes a value on one path only before both join at the return
+    let code = [
+        Opcode::iload_0 as u8,
+        Opcode::ifeq as u8, 0, 4,
+        Opcode::iconst_1 as u8,
+        Opcode::iconst_0 as u8,
+        Opcode::ireturn as u8
+    ];
+    let (_, class) = load_verified(&old_kernels("verifier-heights", Some((2, 1, &code))));
+
+    let message = verify_error(class);
+    assert!(message.contains("Inconsistent stack height"), "{}", message);
+}
+
+#[test]
+fn subroutines_are_verified() {
+    // Adds one to the argument in a subroutine, the local it reads after returning has the type the subroutine left
+    let code = [
+        Opcode::iload_0 as u8,
+        Opcode::istore_1 as u8,
+        Opcode::jsr as u8, 0, 6,
+        Opcode::iload_1 as u8,
+        Opcode::ireturn as u8,
+        Opcode::nop as u8,
+        Opcode::astore_2 as u8,
+        Opcode::iinc as u8, 1, 1,
+        Opcode::ret as u8, 2
+    ];
+    let (mut vm, class) = load_verified(&old_kernels("verifier-subroutine", Some((1, 3, &code))));
+    let class = class.expect("Kernels can't be loaded");
+
+    assert!(matches!(vm.invoke_static(class, "fibonacci", "(I)I", &[JValue::Int(41)]), Ok(JValue::Int(42))));
+
+    // Returns to an int instead of a return address
+    let code = [
+        Opcode::jsr as u8, 0, 5,
+        Opcode::iload_1 as u8,
+        Opcode::ireturn as u8,
+        Opcode::astore_2 as u8,
+        Opcode::iconst_0 as u8,
+        Opcode::istore_1 as u8,
+        Opcode::ret as u8, 1
+    ];
+    let (_, class) = load_verified(&old_kernels("verifier-bad-subroutine", Some((1, 3, &code))));
+
+    let message = verify_error(class);
+    assert!(message.contains("ret expects a return address"), "{}", message);
+}